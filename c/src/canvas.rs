@@ -7,7 +7,7 @@ use std::os::raw::{c_char};
 
 use pathfinder_content::stroke::LineCap;
 use pathfinder_canvas::{Canvas, CanvasFontContext, FillStyle, LineJoin};
-use pathfinder_canvas::{TextAlign};
+use pathfinder_canvas::{FontVariation, SyntheticStyle, TextAlign};
 use pathfinder_content::fill::FillRule;
 use pathfinder_geometry::vector::{Vector2F};
 use pathfinder_canvas::TextMetrics;
@@ -15,7 +15,7 @@ use pathfinder_canvas::TextMetrics;
 use crate::{
     PFVector2F, PFCanvasRef, PFCanvasFontContextRef, FKHandleRef, PFSceneRef, PFRectF, PFPathRef,
     PFFillStyleRef, PFColorU, PFTransform2F, PFTextAlign, PFMatrix2x2F, PFLineJoin, PFTextMetrics,
-    PFLineCap,
+    PFLineCap, PFFontVariation, PFSyntheticStyleFlags,
 };
 
 // `canvas`
@@ -31,6 +31,11 @@ pub const PF_LINE_JOIN_ROUND:   u8 = 2;
 pub const PF_TEXT_ALIGN_LEFT:   u8 = 0;
 pub const PF_TEXT_ALIGN_CENTER: u8 = 1;
 pub const PF_TEXT_ALIGN_RIGHT:  u8 = 2;
+pub const PF_TEXT_ALIGN_START:  u8 = 3;
+pub const PF_TEXT_ALIGN_END:    u8 = 4;
+
+pub const PF_SYNTHETIC_STYLE_BOLD:    u8 = 0x1;
+pub const PF_SYNTHETIC_STYLE_OBLIQUE: u8 = 0x2;
 
 
 // `canvas`
@@ -257,10 +262,58 @@ pub unsafe extern "C" fn PFCanvasSetTextAlign(canvas: PFCanvasRef, new_text_alig
         PF_TEXT_ALIGN_CENTER => TextAlign::Center,
         PF_TEXT_ALIGN_RIGHT  => TextAlign::Right,
         PF_TEXT_ALIGN_LEFT   => TextAlign::Left,
+        PF_TEXT_ALIGN_START  => TextAlign::Start,
+        PF_TEXT_ALIGN_END    => TextAlign::End,
         _                    => TextAlign::Left,
     });
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn PFCanvasSetLetterSpacing(canvas: PFCanvasRef, new_letter_spacing: f32) {
+    (*canvas).set_letter_spacing(new_letter_spacing)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn PFCanvasSetWordSpacing(canvas: PFCanvasRef, new_word_spacing: f32) {
+    (*canvas).set_word_spacing(new_word_spacing)
+}
+
+/// Sets the font, font size, stretch, and caps variant from a CSS `font` shorthand such as
+/// `italic bold 16px Roboto, sans-serif`. Returns 1 on success or 0 if the string couldn't be
+/// parsed, in which case the font is unchanged.
+#[no_mangle]
+pub unsafe extern "C" fn PFCanvasSetFontFromCSS(canvas: PFCanvasRef,
+                                                font: *const c_char,
+                                                font_len: usize)
+                                                -> u8 {
+    (*canvas).set_font_from_css(to_rust_string(&font, font_len)).is_ok() as u8
+}
+
+/// Sets variation axis values for variable fonts. Axes that the font doesn't have are ignored.
+#[no_mangle]
+pub unsafe extern "C" fn PFCanvasSetFontVariationSettings(canvas: PFCanvasRef,
+                                                          variations: *const PFFontVariation,
+                                                          variation_count: usize) {
+    let variations = if variation_count == 0 {
+        vec![]
+    } else {
+        slice::from_raw_parts(variations, variation_count).iter().map(|variation| {
+            FontVariation::new(&variation.axis.to_be_bytes(), variation.value)
+        }).collect()
+    };
+    (*canvas).set_font_variation_settings(variations)
+}
+
+/// Sets the styles to synthesize, as a combination of the `PF_SYNTHETIC_STYLE_*` flags.
+#[no_mangle]
+pub unsafe extern "C" fn PFCanvasSetSyntheticStyle(canvas: PFCanvasRef,
+                                                   flags: PFSyntheticStyleFlags) {
+    (*canvas).set_synthetic_style(SyntheticStyle {
+        bold: flags & PF_SYNTHETIC_STYLE_BOLD != 0,
+        oblique: flags & PF_SYNTHETIC_STYLE_OBLIQUE != 0,
+    })
+}
+
 /// Takes ownership of the PFFillStyle passed in, clone the fill style prior to calling this if you
 /// wish to reuse it.
 #[no_mangle]
@@ -6,11 +6,12 @@ use pathfinder_renderer::gpu::options::{DestFramebuffer};
 use pathfinder_renderer::gpu::renderer::Renderer;
 use pathfinder_geometry::rect::RectI;
 use libc::c_void;
+use std::slice;
 
 use crate::{
     PFGLFunctionLoader, PFGLVersion, PFGLDeviceRef, PFGLDestFramebufferRef, 
     PFVector2I, PFResourceLoaderRef, PFRendererMode, PFRendererOptions, 
-    PFBuildOptionsRef, PFSceneProxyRef, PFGLRendererRef
+    PFBuildOptionsRef, PFSceneProxyRef, PFGLRendererRef, PFCanvasRef, PFRectI, PFColorU
 };
 
 pub const PF_GL_VERSION_GL3:    u8 = 0;
@@ -112,4 +113,25 @@ pub unsafe extern "C" fn PFSceneProxyBuildAndRenderGL(scene_proxy: PFSceneProxyR
                                                       renderer: PFGLRendererRef,
                                                       build_options: PFBuildOptionsRef) {
     (*scene_proxy).build_and_render(&mut *renderer, (*build_options).clone())
-}
\ No newline at end of file
+}
+
+/// Renders everything drawn to `canvas` so far and copies the unpremultiplied RGBA pixels in
+/// `rect` to `pixels`, which must have room for one color per pixel of `rect`, in row-major
+/// order. Returns 1 on success or 0 if `rect` has no pixels or too many of them, in which case
+/// `pixels` is untouched. This function does not take ownership of `canvas` or `renderer`.
+#[no_mangle]
+pub unsafe extern "C" fn PFCanvasGetImageDataGL(canvas: PFCanvasRef,
+                                                renderer: PFGLRendererRef,
+                                                rect: *const PFRectI,
+                                                pixels: *mut PFColorU)
+                                                -> u8 {
+    let image_data = match (*canvas).get_image_data(&mut *renderer, (*rect).to_rust()) {
+        Ok(image_data) => image_data,
+        Err(_) => return 0,
+    };
+    let pixels = slice::from_raw_parts_mut(pixels, image_data.data.len());
+    for (dest, src) in pixels.iter_mut().zip(image_data.data.iter()) {
+        *dest = PFColorU { r: src.r, g: src.g, b: src.b, a: src.a };
+    }
+    1
+}
@@ -71,10 +71,17 @@ pub type PFLineCap = u8;
 pub type PFLineJoin = u8;
 pub type PFArcDirection = u8;
 pub type PFTextAlign = u8;
+pub type PFSyntheticStyleFlags = u8;
 #[repr(C)]
 pub struct PFTextMetrics {
     pub width: f32,
 }
+/// A variation axis value. The axis is an OpenType tag, such as `0x77676874` for `wght`.
+#[repr(C)]
+pub struct PFFontVariation {
+    pub axis: u32,
+    pub value: f32,
+}
 
 // `content`
 #[repr(C)]
@@ -2,8 +2,10 @@
 use crate::{
     PFMetalDestFramebufferRef, PFVector2I, PFMetalDeviceRef, PFResourceLoaderRef,
     PFRendererMode, PFRendererOptions, PFMetalRendererRef, PFSceneProxyRef,
-    IOSurfaceRef, CoreAnimationDrawableRef, NativeMetalDeviceRef
+    IOSurfaceRef, CoreAnimationDrawableRef, NativeMetalDeviceRef, PFCanvasRef, PFRectI, PFColorU
 };
+#[cfg(all(target_os = "macos", not(feature = "pf-gl")))]
+use std::slice;
 
 
 #[cfg(all(target_os = "macos", not(feature = "pf-gl")))]
@@ -64,6 +66,28 @@ pub unsafe extern "C" fn PFSceneProxyBuildAndRenderMetal(scene_proxy: PFScenePro
     (*scene_proxy).build_and_render(&mut *renderer, (*build_options).clone())
 }
 
+/// Renders everything drawn to `canvas` so far and copies the unpremultiplied RGBA pixels in
+/// `rect` to `pixels`, which must have room for one color per pixel of `rect`, in row-major
+/// order. Returns 1 on success or 0 if `rect` has no pixels or too many of them, in which case
+/// `pixels` is untouched. This function does not take ownership of `canvas` or `renderer`.
+#[cfg(all(target_os = "macos", not(feature = "pf-gl")))]
+#[no_mangle]
+pub unsafe extern "C" fn PFCanvasGetImageDataMetal(canvas: PFCanvasRef,
+                                                   renderer: PFMetalRendererRef,
+                                                   rect: *const PFRectI,
+                                                   pixels: *mut PFColorU)
+                                                   -> u8 {
+    let image_data = match (*canvas).get_image_data(&mut *renderer, (*rect).to_rust()) {
+        Ok(image_data) => image_data,
+        Err(_) => return 0,
+    };
+    let pixels = slice::from_raw_parts_mut(pixels, image_data.data.len());
+    for (dest, src) in pixels.iter_mut().zip(image_data.data.iter()) {
+        *dest = PFColorU { r: src.r, g: src.g, b: src.b, a: src.a };
+    }
+    1
+}
+
 #[cfg(all(target_os = "macos", not(feature = "pf-gl")))]
 #[no_mangle]
 pub unsafe extern "C" fn PFMetalDeviceCreateWithIOSurface(metal_device: &NativeMetalDeviceRef,
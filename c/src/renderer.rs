@@ -10,12 +10,13 @@ use crate::{
 use pathfinder_renderer::concurrent::rayon::RayonExecutor;
 use pathfinder_renderer::concurrent::scene_proxy::SceneProxy;
 use pathfinder_renderer::gpu::options::{DestFramebuffer, RendererLevel};
-use pathfinder_renderer::gpu::options::{RendererMode, RendererOptions};
+use pathfinder_renderer::gpu::options::{RendererMode, RendererOptions, ToneMapping};
 use pathfinder_renderer::options::{BuildOptions, RenderTransform};
 use pathfinder_gpu::Device;
 
 pub const PF_RENDERER_OPTIONS_FLAGS_HAS_BACKGROUND_COLOR: u8 = 0x1;
 pub const PF_RENDERER_OPTIONS_FLAGS_SHOW_DEBUG_UI: u8 = 0x2;
+pub const PF_RENDERER_OPTIONS_FLAGS_LINEAR_BLENDING: u8 = 0x4;
 
 pub const PF_RENDERER_LEVEL_D3D9: u8 = 0x1;
 pub const PF_RENDERER_LEVEL_D3D11: u8 = 0x2;
@@ -107,9 +108,7 @@ fn to_rust_renderer_level(level: PFRendererLevel) -> RendererLevel {
 
 impl PFRendererMode {
     pub fn to_rust(&self) -> RendererMode {
-        RendererMode {
-            level: to_rust_renderer_level(self.level),
-        }
+        RendererMode::new(to_rust_renderer_level(self.level))
     }
 }
 
@@ -117,6 +116,7 @@ impl PFRendererOptions {
     pub fn to_rust<D>(&self) -> RendererOptions<D> where D: Device {
         let has_background_color = self.flags & PF_RENDERER_OPTIONS_FLAGS_HAS_BACKGROUND_COLOR;
         let show_debug_ui = (self.flags & PF_RENDERER_OPTIONS_FLAGS_SHOW_DEBUG_UI) != 0;
+        let linear_blending = (self.flags & PF_RENDERER_OPTIONS_FLAGS_LINEAR_BLENDING) != 0;
         unsafe {
             RendererOptions {
                 background_color: if has_background_color != 0 {
@@ -126,6 +126,8 @@ impl PFRendererOptions {
                 },
                 dest: *Box::from_raw(self.dest as *mut DestFramebuffer<D>),
                 show_debug_ui,
+                linear_blending,
+                tone_mapping: ToneMapping::None,
             }
         }
     }
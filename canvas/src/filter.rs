@@ -0,0 +1,515 @@
+// pathfinder/canvas/src/filter.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! The `filter` property of the canvas, which mirrors CSS filter functions.
+
+use pathfinder_color::ColorU;
+use pathfinder_color::matrix::ColorMatrix;
+use pathfinder_content::effects::{BlendMode, BlurDirection, PatternFilter};
+use pathfinder_content::outline::Outline;
+use pathfinder_content::pattern::Pattern;
+use pathfinder_content::render_target::RenderTargetId;
+use pathfinder_geometry::rect::{RectF, RectI};
+use pathfinder_geometry::transform2d::Transform2F;
+use pathfinder_geometry::vector::{Vector2F, Vector2I, vec2f};
+use pathfinder_renderer::paint::Paint;
+use pathfinder_renderer::scene::{ClipPathId, DrawPath, RenderTarget, Scene};
+use std::f32::consts::PI;
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+/// A chain of filter functions applied to everything subsequently drawn, like the `filter`
+/// property of the HTML canvas.
+///
+/// Filters can be parsed from CSS syntax; for example, `"blur(4px) saturate(1.5)"`.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Filter {
+    functions: Vec<FilterFunction>,
+}
+
+/// A single CSS filter function.
+///
+/// Lengths are in canvas pixels and are not affected by the current transform.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum FilterFunction {
+    /// A Gaussian blur with the given standard deviation.
+    Blur(f32),
+    /// Multiplies the color channels by the given factor.
+    Brightness(f32),
+    /// Scales the color channels around 50% gray by the given factor.
+    Contrast(f32),
+    /// Converts to grayscale by the given amount between 0 and 1.
+    Grayscale(f32),
+    /// Rotates hues by the given angle, in radians.
+    HueRotate(f32),
+    /// Inverts the color channels by the given amount between 0 and 1.
+    Invert(f32),
+    /// Multiplies alpha by the given amount between 0 and 1.
+    Opacity(f32),
+    /// Saturates by the given factor. Values above 1 oversaturate.
+    Saturate(f32),
+    /// Converts to sepia by the given amount between 0 and 1.
+    Sepia(f32),
+    /// Draws a blurred, offset copy of the alpha channel in the given color underneath the image.
+    DropShadow {
+        /// The offset of the shadow.
+        offset: Vector2F,
+        /// The standard deviation of the shadow blur.
+        sigma: f32,
+        /// The color of the shadow.
+        color: ColorU,
+    },
+}
+
+/// The error returned when a filter string is not valid CSS filter syntax.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct FilterParseError;
+
+// A rendering pass. Consecutive color matrix functions are merged into one pass.
+enum FilterPass {
+    ColorMatrix(ColorMatrix),
+    Blur(f32),
+    DropShadow { offset: Vector2F, sigma: f32, color: ColorU },
+}
+
+pub(crate) struct FilterRenderTargetInfo {
+    id: RenderTargetId,
+    pub(crate) bounds: RectI,
+}
+
+impl Filter {
+    /// Creates a filter that applies the given functions in order.
+    #[inline]
+    pub fn new(functions: Vec<FilterFunction>) -> Filter {
+        Filter { functions }
+    }
+
+    /// Returns the empty filter, which corresponds to the CSS value `none`.
+    #[inline]
+    pub fn none() -> Filter {
+        Filter::default()
+    }
+
+    /// Returns true if this filter has no functions and therefore has no effect.
+    #[inline]
+    pub fn is_none(&self) -> bool {
+        self.functions.is_empty()
+    }
+
+    /// Returns the functions that make up this filter, in the order they're applied.
+    #[inline]
+    pub fn functions(&self) -> &[FilterFunction] {
+        &self.functions
+    }
+
+    /// Appends a function to the end of this filter chain.
+    #[inline]
+    pub fn push(&mut self, function: FilterFunction) {
+        self.functions.push(function)
+    }
+
+    // Returns the distance that the content can spread beyond its bounds.
+    fn outset(&self) -> f32 {
+        self.functions.iter().map(|function| {
+            match *function {
+                FilterFunction::Blur(sigma) => sigma * 3.0,
+                FilterFunction::DropShadow { offset, sigma, .. } => {
+                    f32::max(f32::abs(offset.x()), f32::abs(offset.y())) + sigma * 3.0
+                }
+                _ => 0.0,
+            }
+        }).sum()
+    }
+
+    fn passes(&self) -> Vec<FilterPass> {
+        let mut passes = vec![];
+        for function in &self.functions {
+            let color_matrix = match *function {
+                FilterFunction::Blur(sigma) => {
+                    if sigma > 0.0 {
+                        passes.push(FilterPass::Blur(sigma));
+                    }
+                    continue;
+                }
+                FilterFunction::DropShadow { offset, sigma, color } => {
+                    passes.push(FilterPass::DropShadow { offset, sigma, color });
+                    continue;
+                }
+                FilterFunction::Brightness(amount) => ColorMatrix::brightness(amount),
+                FilterFunction::Contrast(amount) => ColorMatrix::contrast(amount),
+                FilterFunction::Grayscale(amount) => ColorMatrix::grayscale(amount),
+                FilterFunction::HueRotate(angle) => ColorMatrix::hue_rotate(angle),
+                FilterFunction::Invert(amount) => ColorMatrix::invert(amount),
+                FilterFunction::Opacity(amount) => ColorMatrix::opacity(amount),
+                FilterFunction::Saturate(amount) => ColorMatrix::saturate(amount),
+                FilterFunction::Sepia(amount) => ColorMatrix::sepia(amount),
+            };
+            match passes.last_mut() {
+                Some(FilterPass::ColorMatrix(ref mut last_matrix)) => {
+                    *last_matrix = color_matrix * *last_matrix;
+                }
+                _ => passes.push(FilterPass::ColorMatrix(color_matrix)),
+            }
+        }
+        passes
+    }
+}
+
+/// Pushes a render target to draw filtered content with the given bounds into.
+///
+/// The content must be drawn translated by the negation of the origin of the returned bounds.
+/// Returns `None` if the filtered content would be entirely outside the view box.
+pub(crate) fn push_filter_render_target(scene: &mut Scene, filter: &Filter, content_bounds: RectF)
+                                        -> Option<FilterRenderTargetInfo> {
+    let outset = filter.outset();
+    let view_box = scene.view_box().dilate(outset);
+    let bounds = content_bounds.dilate(outset).intersection(view_box)?.round_out().to_i32();
+    if bounds.width() <= 0 || bounds.height() <= 0 {
+        return None;
+    }
+
+    let id = scene.push_render_target(RenderTarget::new(bounds.size(), String::new()));
+    Some(FilterRenderTargetInfo { id, bounds })
+}
+
+/// Pops the render target pushed by `push_filter_render_target()`, runs the filter passes, and
+/// composites the result onto the current render target.
+pub(crate) fn composite_filter_render_target(scene: &mut Scene,
+                                             filter: &Filter,
+                                             info: FilterRenderTargetInfo,
+                                             clip_path: Option<ClipPathId>,
+                                             blend_mode: BlendMode) {
+    scene.pop_render_target();
+
+    let size = info.bounds.size();
+    let mut current = info.id;
+    for pass in filter.passes() {
+        match pass {
+            FilterPass::ColorMatrix(matrix) => {
+                current = push_filter_pass(scene, size, |scene| {
+                    draw_pattern(scene, current, size, Some(PatternFilter::ColorMatrix(matrix)),
+                                 Vector2F::zero());
+                });
+            }
+            FilterPass::Blur(sigma) => current = blur(scene, current, size, sigma),
+            FilterPass::DropShadow { offset, sigma, color } => {
+                let color = color.to_f32();
+                let shadow_matrix = ColorMatrix::from_rows([
+                    [0.0, 0.0, 0.0, 0.0,       color.r()],
+                    [0.0, 0.0, 0.0, 0.0,       color.g()],
+                    [0.0, 0.0, 0.0, 0.0,       color.b()],
+                    [0.0, 0.0, 0.0, color.a(), 0.0],
+                ]);
+                let mut shadow = push_filter_pass(scene, size, |scene| {
+                    draw_pattern(scene,
+                                 current,
+                                 size,
+                                 Some(PatternFilter::ColorMatrix(shadow_matrix)),
+                                 Vector2F::zero());
+                });
+                if sigma > 0.0 {
+                    shadow = blur(scene, shadow, size, sigma);
+                }
+                let source = current;
+                current = push_filter_pass(scene, size, |scene| {
+                    draw_pattern(scene, shadow, size, None, offset);
+                    draw_pattern(scene, source, size, None, Vector2F::zero());
+                });
+            }
+        }
+    }
+
+    let mut pattern = Pattern::from_render_target(current, size);
+    pattern.apply_transform(Transform2F::from_translation(info.bounds.origin().to_f32()));
+    let paint_id = scene.push_paint(&Paint::from_pattern(pattern));
+    let mut path = DrawPath::new(Outline::from_rect(info.bounds.to_f32()), paint_id);
+    path.set_clip_path(clip_path);
+    path.set_blend_mode(blend_mode);
+    scene.push_draw_path(path);
+}
+
+fn blur(scene: &mut Scene, src: RenderTargetId, size: Vector2I, sigma: f32) -> RenderTargetId {
+    let src = push_filter_pass(scene, size, |scene| {
+        let filter = PatternFilter::Blur { direction: BlurDirection::X, sigma };
+        draw_pattern(scene, src, size, Some(filter), Vector2F::zero());
+    });
+    push_filter_pass(scene, size, |scene| {
+        let filter = PatternFilter::Blur { direction: BlurDirection::Y, sigma };
+        draw_pattern(scene, src, size, Some(filter), Vector2F::zero());
+    })
+}
+
+fn push_filter_pass<F>(scene: &mut Scene, size: Vector2I, draw: F) -> RenderTargetId
+                       where F: FnOnce(&mut Scene) {
+    let id = scene.push_render_target(RenderTarget::new(size, String::new()));
+    draw(scene);
+    scene.pop_render_target();
+    id
+}
+
+fn draw_pattern(scene: &mut Scene,
+                src: RenderTargetId,
+                size: Vector2I,
+                filter: Option<PatternFilter>,
+                offset: Vector2F) {
+    let mut pattern = Pattern::from_render_target(src, size);
+    pattern.set_filter(filter);
+    pattern.apply_transform(Transform2F::from_translation(offset));
+    let paint_id = scene.push_paint(&Paint::from_pattern(pattern));
+    let outline = Outline::from_rect(RectF::new(Vector2F::zero(), size.to_f32()));
+    scene.push_draw_path(DrawPath::new(outline, paint_id));
+}
+
+// Parsing
+
+impl FromStr for Filter {
+    type Err = FilterParseError;
+
+    fn from_str(string: &str) -> Result<Filter, FilterParseError> {
+        let mut string = string.trim();
+        if string.eq_ignore_ascii_case("none") {
+            return Ok(Filter::none());
+        }
+
+        let mut filter = Filter::none();
+        while !string.is_empty() {
+            let open_paren = string.find('(').ok_or(FilterParseError)?;
+            let name = string[0..open_paren].trim().to_ascii_lowercase();
+            let close_paren = find_closing_paren(string, open_paren).ok_or(FilterParseError)?;
+            let args = string[(open_paren + 1)..close_paren].trim();
+            filter.push(parse_filter_function(&name, args)?);
+            string = string[(close_paren + 1)..].trim_start();
+        }
+
+        if filter.is_none() {
+            return Err(FilterParseError);
+        }
+        Ok(filter)
+    }
+}
+
+fn parse_filter_function(name: &str, args: &str) -> Result<FilterFunction, FilterParseError> {
+    match name {
+        "blur" => {
+            let sigma = if args.is_empty() { 0.0 } else { parse_length(args)? };
+            if sigma < 0.0 {
+                return Err(FilterParseError);
+            }
+            Ok(FilterFunction::Blur(sigma))
+        }
+        "brightness" => Ok(FilterFunction::Brightness(parse_amount(args, false)?)),
+        "contrast" => Ok(FilterFunction::Contrast(parse_amount(args, false)?)),
+        "grayscale" => Ok(FilterFunction::Grayscale(parse_amount(args, true)?)),
+        "invert" => Ok(FilterFunction::Invert(parse_amount(args, true)?)),
+        "opacity" => Ok(FilterFunction::Opacity(parse_amount(args, true)?)),
+        "saturate" => Ok(FilterFunction::Saturate(parse_amount(args, false)?)),
+        "sepia" => Ok(FilterFunction::Sepia(parse_amount(args, true)?)),
+        "hue-rotate" => {
+            let angle = if args.is_empty() { 0.0 } else { parse_angle(args)? };
+            Ok(FilterFunction::HueRotate(angle))
+        }
+        "drop-shadow" => parse_drop_shadow(args),
+        _ => Err(FilterParseError),
+    }
+}
+
+// Parses a number or percentage that defaults to 1 and can't be negative.
+fn parse_amount(arg: &str, clamp_to_one: bool) -> Result<f32, FilterParseError> {
+    if arg.is_empty() {
+        return Ok(1.0);
+    }
+    let amount = match strip_suffix(arg, "%") {
+        Some(percentage) => parse_number(percentage)? / 100.0,
+        None => parse_number(arg)?,
+    };
+    if amount < 0.0 {
+        return Err(FilterParseError);
+    }
+    Ok(if clamp_to_one { f32::min(amount, 1.0) } else { amount })
+}
+
+fn parse_length(arg: &str) -> Result<f32, FilterParseError> {
+    match strip_suffix(arg, "px") {
+        Some(length) => parse_number(length),
+        None if parse_number(arg)? == 0.0 => Ok(0.0),
+        None => Err(FilterParseError),
+    }
+}
+
+fn parse_angle(arg: &str) -> Result<f32, FilterParseError> {
+    const UNITS: [(&str, f32); 4] =
+        [("grad", PI / 200.0), ("turn", PI * 2.0), ("deg", PI / 180.0), ("rad", 1.0)];
+    for &(unit, scale) in &UNITS {
+        if let Some(angle) = strip_suffix(arg, unit) {
+            return Ok(parse_number(angle)? * scale);
+        }
+    }
+    if parse_number(arg)? == 0.0 {
+        return Ok(0.0);
+    }
+    Err(FilterParseError)
+}
+
+fn parse_drop_shadow(args: &str) -> Result<FilterFunction, FilterParseError> {
+    let (mut lengths, mut color) = (vec![], None);
+    for component in split_components(args) {
+        match parse_length(component) {
+            Ok(length) => lengths.push(length),
+            Err(_) if color.is_none() => color = Some(parse_color(component)?),
+            Err(err) => return Err(err),
+        }
+    }
+
+    let (offset, blur) = match lengths[..] {
+        [x, y] => (vec2f(x, y), 0.0),
+        [x, y, blur] if blur >= 0.0 => (vec2f(x, y), blur),
+        _ => return Err(FilterParseError),
+    };
+
+    // As with `box-shadow`, the blur radius is twice the standard deviation.
+    Ok(FilterFunction::DropShadow {
+        offset,
+        sigma: blur * 0.5,
+        color: color.unwrap_or(ColorU::black()),
+    })
+}
+
+// Parses a hex color, an `rgb()`/`rgba()` color, or one of a few common color keywords.
+fn parse_color(string: &str) -> Result<ColorU, FilterParseError> {
+    let string = string.to_ascii_lowercase();
+    if let Some(hex) = strip_prefix(&string, "#") {
+        let digits = hex.chars()
+                        .map(|digit| digit.to_digit(16).map(|digit| digit as u8))
+                        .collect::<Option<Vec<u8>>>()
+                        .ok_or(FilterParseError)?;
+        return match digits[..] {
+            [r, g, b] => Ok(ColorU::new(r * 17, g * 17, b * 17, 255)),
+            [r, g, b, a] => Ok(ColorU::new(r * 17, g * 17, b * 17, a * 17)),
+            [r0, r1, g0, g1, b0, b1] => Ok(ColorU::new(r0 * 16 + r1, g0 * 16 + g1, b0 * 16 + b1, 255)),
+            [r0, r1, g0, g1, b0, b1, a0, a1] => {
+                Ok(ColorU::new(r0 * 16 + r1, g0 * 16 + g1, b0 * 16 + b1, a0 * 16 + a1))
+            }
+            _ => Err(FilterParseError),
+        };
+    }
+
+    if string.ends_with(')') {
+        let open_paren = string.find('(').ok_or(FilterParseError)?;
+        let name = &string[0..open_paren];
+        if name != "rgb" && name != "rgba" {
+            return Err(FilterParseError);
+        }
+        let args = string[(open_paren + 1)..(string.len() - 1)]
+            .split(|c: char| c == ',' || c == '/' || c.is_whitespace())
+            .filter(|arg| !arg.is_empty())
+            .collect::<Vec<_>>();
+        let channel = |arg: &str| -> Result<u8, FilterParseError> {
+            let value = match strip_suffix(arg, "%") {
+                Some(percentage) => parse_number(percentage)? * 2.55,
+                None => parse_number(arg)?,
+            };
+            Ok(f32::round(value.max(0.0).min(255.0)) as u8)
+        };
+        let alpha = match args.get(3) {
+            None => 255,
+            Some(arg) => f32::round(parse_amount(arg, true)? * 255.0) as u8,
+        };
+        return match args[..] {
+            [r, g, b] | [r, g, b, _] => Ok(ColorU::new(channel(r)?, channel(g)?, channel(b)?, alpha)),
+            _ => Err(FilterParseError),
+        };
+    }
+
+    match &*string {
+        "transparent" => Ok(ColorU::transparent_black()),
+        "black" => Ok(ColorU::black()),
+        "white" => Ok(ColorU::white()),
+        "gray" | "grey" => Ok(ColorU::new(128, 128, 128, 255)),
+        "red" => Ok(ColorU::new(255, 0, 0, 255)),
+        "green" => Ok(ColorU::new(0, 128, 0, 255)),
+        "blue" => Ok(ColorU::new(0, 0, 255, 255)),
+        "yellow" => Ok(ColorU::new(255, 255, 0, 255)),
+        "cyan" | "aqua" => Ok(ColorU::new(0, 255, 255, 255)),
+        "magenta" | "fuchsia" => Ok(ColorU::new(255, 0, 255, 255)),
+        _ => Err(FilterParseError),
+    }
+}
+
+fn parse_number(string: &str) -> Result<f32, FilterParseError> {
+    let number = string.trim().parse::<f32>().map_err(|_| FilterParseError)?;
+    if !number.is_finite() {
+        return Err(FilterParseError);
+    }
+    Ok(number)
+}
+
+// Splits the string on whitespace, keeping parenthesized groups together.
+fn split_components(string: &str) -> Vec<&str> {
+    let (mut components, mut start, mut depth) = (vec![], None, 0);
+    for (index, c) in string.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            _ if c.is_whitespace() && depth == 0 => {
+                if let Some(start) = start.take() {
+                    components.push(&string[start..index]);
+                }
+                continue;
+            }
+            _ => {}
+        }
+        if start.is_none() {
+            start = Some(index);
+        }
+    }
+    if let Some(start) = start {
+        components.push(&string[start..]);
+    }
+    components
+}
+
+fn find_closing_paren(string: &str, open_paren: usize) -> Option<usize> {
+    let mut depth = 0;
+    for (index, c) in string[open_paren..].char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(open_paren + index);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn strip_prefix<'a>(string: &'a str, prefix: &str) -> Option<&'a str> {
+    if string.starts_with(prefix) { Some(&string[prefix.len()..]) } else { None }
+}
+
+// Units are matched case-insensitively. The string may contain non-ASCII characters, so the
+// suffix must start on a character boundary.
+fn strip_suffix<'a>(string: &'a str, suffix: &str) -> Option<&'a str> {
+    let suffix_start = string.len().checked_sub(suffix.len())?;
+    if string.is_char_boundary(suffix_start) &&
+            string[suffix_start..].eq_ignore_ascii_case(suffix) {
+        Some(&string[0..suffix_start])
+    } else {
+        None
+    }
+}
+
+impl Display for FilterParseError {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        write!(formatter, "invalid filter syntax")
+    }
+}
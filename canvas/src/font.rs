@@ -0,0 +1,277 @@
+// pathfinder/canvas/src/font.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Parsing of the CSS `font` shorthand, as accepted by the canvas `font` attribute.
+
+use crate::{DEFAULT_FONT_SIZE, FontStretch, FontVariantCaps};
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+/// A font parsed from the CSS `font` shorthand, for example `italic bold 16px Roboto, sans-serif`.
+///
+/// Pass the string to `CanvasRenderingContext2D::set_font_from_css()` to select the font; this
+/// structure is exposed so that the parsed values can be inspected.
+#[derive(Clone, PartialEq, Debug)]
+pub struct FontShorthand {
+    /// The slant of the font.
+    pub style: FontStyle,
+    /// The caps variant. Only `normal` and `small-caps` can be specified in the shorthand.
+    pub variant_caps: FontVariantCaps,
+    /// The weight of the font, from 1 to 1000. 400 is normal and 700 is bold.
+    pub weight: f32,
+    /// The width of the font.
+    pub stretch: FontStretch,
+    /// The size of the font in pixels.
+    pub size: f32,
+    /// The font families to try, in order of preference.
+    pub families: Vec<FontFamilyName>,
+}
+
+/// The slant of a font.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum FontStyle {
+    Normal,
+    Italic,
+    Oblique,
+}
+
+/// A font family name, either a specific family or one of the CSS generic families.
+#[derive(Clone, PartialEq, Debug)]
+pub enum FontFamilyName {
+    /// A specific family, such as `Roboto`.
+    Title(String),
+    Serif,
+    SansSerif,
+    Monospace,
+    Cursive,
+    Fantasy,
+}
+
+/// The error returned when a string is not a valid CSS `font` shorthand.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct FontParseError;
+
+impl FromStr for FontShorthand {
+    type Err = FontParseError;
+
+    fn from_str(string: &str) -> Result<FontShorthand, FontParseError> {
+        let (mut style, mut variant_caps, mut weight, mut stretch) = (None, None, None, None);
+        let mut rest = string.trim_start();
+
+        // Up to four style, variant, weight, and stretch keywords, in any order, precede the size.
+        // `normal` matches whichever of them hasn't been specified yet.
+        let size = loop {
+            let (token, after) = split_token(rest);
+            if token.is_empty() {
+                return Err(FontParseError);
+            }
+            rest = after;
+
+            let keyword = token.to_ascii_lowercase();
+            if keyword == "normal" {
+                if style.is_none() {
+                    style = Some(FontStyle::Normal);
+                } else if variant_caps.is_none() {
+                    variant_caps = Some(FontVariantCaps::Normal);
+                } else if weight.is_none() {
+                    weight = Some(400.0);
+                } else if stretch.is_none() {
+                    stretch = Some(FontStretch::Normal);
+                } else {
+                    return Err(FontParseError);
+                }
+            } else if let Some(new_style) = parse_style(&keyword) {
+                set_once(&mut style, new_style)?;
+            } else if keyword == "small-caps" {
+                set_once(&mut variant_caps, FontVariantCaps::SmallCaps)?;
+            } else if let Some(new_weight) = parse_weight(&keyword) {
+                set_once(&mut weight, new_weight)?;
+            } else if let Some(new_stretch) = parse_stretch(&keyword) {
+                set_once(&mut stretch, new_stretch)?;
+            } else {
+                // A size may be immediately followed by the line height, as in `16px/2`.
+                let (size, line_height) = match keyword.find('/') {
+                    None => (&keyword[..], None),
+                    Some(slash) => (&keyword[0..slash], Some(&keyword[(slash + 1)..])),
+                };
+                let size = parse_size(size)?;
+                match line_height {
+                    Some("") => rest = skip_line_height(rest)?,
+                    Some(line_height) => parse_line_height(line_height)?,
+                    None if rest.starts_with('/') => rest = skip_line_height(&rest[1..])?,
+                    None => {}
+                }
+                break size;
+            }
+        };
+
+        Ok(FontShorthand {
+            style: style.unwrap_or(FontStyle::Normal),
+            variant_caps: variant_caps.unwrap_or(FontVariantCaps::Normal),
+            weight: weight.unwrap_or(400.0),
+            stretch: stretch.unwrap_or(FontStretch::Normal),
+            size,
+            families: parse_families(rest)?,
+        })
+    }
+}
+
+fn set_once<T>(slot: &mut Option<T>, value: T) -> Result<(), FontParseError> {
+    if slot.is_some() {
+        return Err(FontParseError);
+    }
+    *slot = Some(value);
+    Ok(())
+}
+
+// Returns the first whitespace-separated token and the rest of the string.
+fn split_token(string: &str) -> (&str, &str) {
+    let end = string.find(char::is_whitespace).unwrap_or(string.len());
+    (&string[0..end], string[end..].trim_start())
+}
+
+fn parse_style(keyword: &str) -> Option<FontStyle> {
+    match keyword {
+        "italic" => Some(FontStyle::Italic),
+        "oblique" => Some(FontStyle::Oblique),
+        _ => None,
+    }
+}
+
+// `bolder` and `lighter` are relative to the weight of the parent element, which for the canvas is
+// always `normal`.
+fn parse_weight(keyword: &str) -> Option<f32> {
+    match keyword {
+        "bold" | "bolder" => Some(700.0),
+        "lighter" => Some(100.0),
+        _ => match keyword.parse::<f32>() {
+            Ok(weight) if weight >= 1.0 && weight <= 1000.0 => Some(weight),
+            _ => None,
+        },
+    }
+}
+
+fn parse_stretch(keyword: &str) -> Option<FontStretch> {
+    match keyword {
+        "ultra-condensed" => Some(FontStretch::UltraCondensed),
+        "extra-condensed" => Some(FontStretch::ExtraCondensed),
+        "condensed" => Some(FontStretch::Condensed),
+        "semi-condensed" => Some(FontStretch::SemiCondensed),
+        "semi-expanded" => Some(FontStretch::SemiExpanded),
+        "expanded" => Some(FontStretch::Expanded),
+        "extra-expanded" => Some(FontStretch::ExtraExpanded),
+        "ultra-expanded" => Some(FontStretch::UltraExpanded),
+        _ => None,
+    }
+}
+
+// Relative sizes are resolved against the canvas default font size, since a canvas has no parent
+// element to inherit from.
+fn parse_size(size: &str) -> Result<f32, FontParseError> {
+    const KEYWORDS: [(&str, f32); 8] = [
+        ("xx-small", 9.0), ("x-small", 10.0), ("small", 13.0), ("medium", 16.0),
+        ("large", 18.0), ("x-large", 24.0), ("xx-large", 32.0), ("xxx-large", 48.0),
+    ];
+    const UNITS: [(&str, f32); 9] = [
+        ("px", 1.0), ("pt", 4.0 / 3.0), ("pc", 16.0), ("in", 96.0), ("cm", 96.0 / 2.54),
+        ("mm", 96.0 / 25.4), ("rem", DEFAULT_FONT_SIZE), ("em", DEFAULT_FONT_SIZE),
+        ("%", DEFAULT_FONT_SIZE / 100.0),
+    ];
+
+    for &(keyword, keyword_size) in &KEYWORDS {
+        if size == keyword {
+            return Ok(keyword_size);
+        }
+    }
+    match size {
+        "larger" => return Ok(DEFAULT_FONT_SIZE * 1.2),
+        "smaller" => return Ok(DEFAULT_FONT_SIZE / 1.2),
+        _ => {}
+    }
+
+    for &(unit, scale) in &UNITS {
+        if size.ends_with(unit) {
+            return match size[0..(size.len() - unit.len())].parse::<f32>() {
+                Ok(size) if size.is_finite() && size >= 0.0 => Ok(size * scale),
+                _ => Err(FontParseError),
+            };
+        }
+    }
+    Err(FontParseError)
+}
+
+// The canvas ignores the line height, but it still has to be valid.
+fn parse_line_height(line_height: &str) -> Result<(), FontParseError> {
+    if line_height == "normal" || parse_size(line_height).is_ok() {
+        return Ok(());
+    }
+    match line_height.parse::<f32>() {
+        Ok(line_height) if line_height.is_finite() && line_height >= 0.0 => Ok(()),
+        _ => Err(FontParseError),
+    }
+}
+
+fn skip_line_height(string: &str) -> Result<&str, FontParseError> {
+    let (line_height, rest) = split_token(string.trim_start());
+    parse_line_height(&line_height.to_ascii_lowercase())?;
+    Ok(rest)
+}
+
+fn parse_families(string: &str) -> Result<Vec<FontFamilyName>, FontParseError> {
+    let mut families = vec![];
+    let mut rest = string.trim();
+    loop {
+        let family;
+        if rest.starts_with('"') || rest.starts_with('\'') {
+            let quote = &rest[0..1];
+            let end = rest[1..].find(quote).ok_or(FontParseError)? + 1;
+            family = FontFamilyName::Title(rest[1..end].to_owned());
+            rest = rest[(end + 1)..].trim_start();
+        } else {
+            let end = rest.find(',').unwrap_or(rest.len());
+            // Unquoted names are sequences of identifiers; runs of whitespace collapse to one
+            // space.
+            let name = rest[0..end].split_whitespace().collect::<Vec<_>>().join(" ");
+            if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) ||
+                    name.contains(|c: char| c == '"' || c == '\'' || c == '/') {
+                return Err(FontParseError);
+            }
+            family = match &*name.to_ascii_lowercase() {
+                "serif" => FontFamilyName::Serif,
+                "sans-serif" => FontFamilyName::SansSerif,
+                "monospace" => FontFamilyName::Monospace,
+                "cursive" => FontFamilyName::Cursive,
+                "fantasy" => FontFamilyName::Fantasy,
+                // These are reserved and can't be used as unquoted family names.
+                "inherit" | "initial" | "unset" | "default" => return Err(FontParseError),
+                _ => FontFamilyName::Title(name),
+            };
+            rest = &rest[end..];
+        }
+        families.push(family);
+
+        if rest.is_empty() {
+            return Ok(families);
+        }
+        if !rest.starts_with(',') {
+            return Err(FontParseError);
+        }
+        rest = rest[1..].trim_start();
+    }
+}
+
+impl Display for FontParseError {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        write!(formatter, "invalid font shorthand")
+    }
+}
+
+impl Error for FontParseError {}
@@ -24,33 +24,59 @@ use pathfinder_content::effects::{BlendMode, BlurDirection, PatternFilter};
 use pathfinder_content::gradient::Gradient;
 use pathfinder_content::outline::{Contour, Outline};
 use pathfinder_content::pattern::{Image, Pattern};
-use pathfinder_content::render_target::RenderTargetId;
 use pathfinder_content::stroke::{LineJoin as StrokeLineJoin};
 use pathfinder_content::stroke::{OutlineStrokeToFill, StrokeStyle};
 use pathfinder_geometry::line_segment::LineSegment2F;
-use pathfinder_renderer::paint::{Paint, PaintCompositeOp};
-use pathfinder_renderer::scene::{ClipPath, ClipPathId, DrawPath, RenderTarget, Scene};
+use pathfinder_gpu::{Device, RenderTarget as DeviceRenderTarget, TextureData, TextureFormat};
+use pathfinder_renderer::concurrent::executor::SequentialExecutor;
+use pathfinder_renderer::gpu::options::DestFramebuffer;
+use pathfinder_renderer::gpu::renderer::Renderer;
+use pathfinder_renderer::options::BuildOptions;
+use pathfinder_renderer::paint::{Paint, PaintCompositeOp, PaintId};
+use pathfinder_renderer::scene::{ClipPath, ClipPathId, DeviceStroke, DrawPath, RenderTarget};
+use pathfinder_renderer::scene::Scene;
 use std::borrow::Cow;
 use std::default::Default;
 use std::f32::consts::PI;
 use std::f32;
-use std::fmt::{Debug, Error as FmtError, Formatter};
+use std::error::Error;
+use std::fmt::{Debug, Display, Error as FmtError, Formatter};
 use std::mem;
 use std::sync::Arc;
 
+pub use filter::{Filter, FilterFunction, FilterParseError};
+pub use font::{FontFamilyName, FontParseError, FontShorthand, FontStyle};
+pub use path_data::{PathDataParseError, PathDataParseErrorKind};
 pub use text::CanvasFontContext;
 
 #[cfg(feature = "pf-text")]
 use skribo::FontCollection;
 #[cfg(not(feature = "pf-text"))]
-use crate::text::FontCollection;
+use crate::text::{FontCollection, FontVariation, SyntheticStyle};
 
+#[cfg(feature = "pf-software")]
+use pathfinder_renderer::gpu::options::{RendererLevel, RendererMode, RendererOptions};
+#[cfg(feature = "pf-software")]
+use pathfinder_resources::embedded::EmbeddedResourceLoader;
+#[cfg(feature = "pf-software")]
+use pathfinder_software::SoftwareDevice;
+
+#[cfg(feature = "pf-text")]
+pub use pathfinder_text::{FontVariation, SyntheticStyle};
 #[cfg(feature = "pf-text")]
 pub use text::TextMetrics;
 
 const HAIRLINE_STROKE_WIDTH: f32 = 0.0333;
 const DEFAULT_FONT_SIZE: f32 = 10.0;
 
+// The most pixels that `get_image_data()` reads back at once, to keep a bad rect from exhausting
+// memory. This is the area of a 16384×16384 canvas.
+const MAX_IMAGE_DATA_AREA: i64 = 1 << 28;
+
+mod filter;
+mod font;
+mod path_data;
+
 #[cfg(feature = "pf-text")]
 mod text;
 
@@ -67,6 +93,12 @@ mod text {
     }
 
     pub struct FontCollection;
+
+    #[derive(Clone, Copy)]
+    pub struct FontVariation;
+
+    #[derive(Clone, Copy, Default)]
+    pub struct SyntheticStyle;
 }
 
 #[cfg(test)]
@@ -244,6 +276,21 @@ impl CanvasRenderingContext2D {
         self.current_state.miter_limit = new_miter_limit
     }
 
+    /// Returns true if strokes are measured in device pixels instead of in user space.
+    #[inline]
+    pub fn non_scaling_stroke(&self) -> bool {
+        self.current_state.non_scaling_stroke
+    }
+
+    /// If true, the line width and dash pattern of subsequent strokes are measured in device
+    /// pixels and don't scale with the current transform or with the render transform, like SVG
+    /// `vector-effect="non-scaling-stroke"`. A non-scaling stroke with a line width of 1 is a
+    /// hairline.
+    #[inline]
+    pub fn set_non_scaling_stroke(&mut self, enabled: bool) {
+        self.current_state.non_scaling_stroke = enabled
+    }
+
     #[inline]
     pub fn line_dash(&mut self) -> &[f32] {
         &self.current_state.line_dash
@@ -315,17 +362,45 @@ impl CanvasRenderingContext2D {
         self.current_state.shadow_offset = new_shadow_offset;
     }
 
+    // Filters
+
+    #[inline]
+    pub fn filter(&self) -> &Filter {
+        &self.current_state.filter
+    }
+
+    #[inline]
+    pub fn set_filter(&mut self, new_filter: Filter) {
+        self.current_state.filter = new_filter;
+    }
+
     // Drawing paths
 
     #[inline]
     pub fn fill_path(&mut self, path: Path2D, fill_rule: FillRule) {
-        self.push_path(path.into_outline(), PathOp::Fill, fill_rule);
+        self.push_path(path.into_outline(), PathOp::Fill, fill_rule, None);
     }
 
     #[inline]
     pub fn stroke_path(&mut self, path: Path2D) {
         let mut stroke_style = self.current_state.resolve_stroke_style();
 
+        // Non-scaling strokes are converted to fills at build time, once the device transform is
+        // known.
+        if self.current_state.non_scaling_stroke {
+            stroke_style.line_width = f32::max(stroke_style.line_width, HAIRLINE_STROKE_WIDTH);
+            let device_stroke = DeviceStroke {
+                style: stroke_style,
+                dashes: self.current_state.line_dash.clone(),
+                dash_offset: self.current_state.line_dash_offset,
+            };
+            self.push_path(path.into_outline(),
+                           PathOp::Stroke,
+                           FillRule::Winding,
+                           Some(device_stroke));
+            return;
+        }
+
         // The smaller scale is relevant here, as we multiply by it and want to ensure it is always
         // bigger than `HAIRLINE_STROKE_WIDTH`.
         let transform_scales = self.current_state.transform.extract_scale();
@@ -349,7 +424,7 @@ impl CanvasRenderingContext2D {
         stroke_to_fill.offset();
         outline = stroke_to_fill.into_outline();
 
-        self.push_path(outline, PathOp::Stroke, FillRule::Winding);
+        self.push_path(outline, PathOp::Stroke, FillRule::Winding, None);
     }
 
     pub fn clip_path(&mut self, path: Path2D, fill_rule: FillRule) {
@@ -366,12 +441,15 @@ impl CanvasRenderingContext2D {
         self.current_state.clip_path = Some(clip_path_id);
     }
 
-    fn push_path(&mut self, mut outline: Outline, path_op: PathOp, fill_rule: FillRule) {
+    fn push_path(&mut self,
+                 mut outline: Outline,
+                 path_op: PathOp,
+                 fill_rule: FillRule,
+                 device_stroke: Option<DeviceStroke>) {
         let paint = self.current_state.resolve_paint(match path_op {
             PathOp::Fill => &self.current_state.fill_paint,
             PathOp::Stroke => &self.current_state.stroke_paint,
         });
-        let paint_id = self.canvas.scene.push_paint(&paint);
 
         let transform = self.current_state.transform;
         let clip_path = self.current_state.clip_path;
@@ -379,109 +457,77 @@ impl CanvasRenderingContext2D {
 
         outline.transform(&transform);
 
-        if !self.current_state.shadow_color.is_fully_transparent() {
-            let mut outline = outline.clone();
-            outline.transform(&Transform2F::from_translation(self.current_state.shadow_offset));
-
-            let shadow_blur_info =
-                push_shadow_blur_render_targets_if_needed(&mut self.canvas.scene,
-                                                          &self.current_state,
-                                                          outline.bounds());
-
-            if let Some(ref shadow_blur_info) = shadow_blur_info {
-                outline.transform(&Transform2F::from_translation(-shadow_blur_info.bounds
-                                                                                  .origin()
-                                                                                  .to_f32()));
+        if !self.current_state.filter.is_none() {
+            // Per spec, the filter is applied first, and then the shadow is drawn from the
+            // filtered image.
+            let mut filter = self.current_state.filter.clone();
+            if !self.current_state.shadow_color.is_fully_transparent() {
+                filter.push(FilterFunction::DropShadow {
+                    offset: self.current_state.shadow_offset,
+                    sigma: self.current_state.shadow_blur * 0.5,
+                    color: self.current_state.shadow_color,
+                });
             }
 
-            // Per spec the shadow must respect the alpha of the shadowed path, but otherwise have
-            // the color of the shadow paint.
-            let mut shadow_paint = (*paint).clone();
-            let shadow_base_alpha = shadow_paint.base_color().a;
-            let mut shadow_color = self.current_state.shadow_color.to_f32();
-            shadow_color.set_a(shadow_color.a() * shadow_base_alpha as f32 / 255.0);
-            shadow_paint.set_base_color(shadow_color.to_u8());
-            if let &mut Some(ref mut shadow_paint_overlay) = shadow_paint.overlay_mut() {
-                shadow_paint_overlay.set_composite_op(PaintCompositeOp::DestIn);
+            let mut bounds = outline.bounds();
+            if let Some(ref device_stroke) = device_stroke {
+                bounds = bounds.dilate(device_stroke.max_extent());
             }
-            let shadow_paint_id = self.canvas.scene.push_paint(&shadow_paint);
+            let filter_info = match filter::push_filter_render_target(&mut self.canvas.scene,
+                                                                      &filter,
+                                                                      bounds) {
+                None => return,
+                Some(filter_info) => filter_info,
+            };
 
-            let mut path = DrawPath::new(outline, shadow_paint_id);
-            if shadow_blur_info.is_none() {
-                path.set_clip_path(clip_path);
-            }
+            let translation = Transform2F::from_translation(-filter_info.bounds.origin().to_f32());
+            outline.transform(&translation);
+            let mut paint = paint.into_owned();
+            paint.apply_transform(&translation);
+            let paint_id = self.canvas.scene.push_paint(&paint);
+
+            let mut path = DrawPath::new(outline, paint_id);
             path.set_fill_rule(fill_rule);
-            path.set_blend_mode(blend_mode);
+            path.set_device_stroke(device_stroke);
             self.canvas.scene.push_draw_path(path);
 
-            composite_shadow_blur_render_targets_if_needed(&mut self.canvas.scene,
-                                                           shadow_blur_info,
-                                                           clip_path);
+            filter::composite_filter_render_target(&mut self.canvas.scene,
+                                                   &filter,
+                                                   filter_info,
+                                                   clip_path,
+                                                   blend_mode);
+            return;
+        }
+
+        if !self.current_state.shadow_color.is_fully_transparent() {
+            let mut bounds = outline.bounds();
+            if let Some(ref device_stroke) = device_stroke {
+                bounds = bounds.dilate(device_stroke.max_extent());
+            }
+
+            push_shadow(&mut self.canvas.scene,
+                        &self.current_state,
+                        &paint,
+                        bounds,
+                        |scene, shadow_paint_id, shadow_transform, clip_path, blend_mode| {
+                let mut outline = outline.clone();
+                outline.transform(&shadow_transform);
+                let mut path = DrawPath::new(outline, shadow_paint_id);
+                path.set_clip_path(clip_path);
+                path.set_fill_rule(fill_rule);
+                path.set_blend_mode(blend_mode);
+                path.set_device_stroke(device_stroke.clone());
+                scene.push_draw_path(path);
+            });
         }
 
+        let paint_id = self.canvas.scene.push_paint(&paint);
         let mut path = DrawPath::new(outline, paint_id);
         path.set_clip_path(clip_path);
         path.set_fill_rule(fill_rule);
         path.set_blend_mode(blend_mode);
+        path.set_device_stroke(device_stroke);
         self.canvas.scene.push_draw_path(path);
-
-        fn push_shadow_blur_render_targets_if_needed(scene: &mut Scene,
-                                                     current_state: &State,
-                                                     outline_bounds: RectF)
-                                                    -> Option<ShadowBlurRenderTargetInfo> {
-            if current_state.shadow_blur == 0.0 {
-                return None;
-            }
-
-            let sigma = current_state.shadow_blur * 0.5;
-            let bounds = outline_bounds.dilate(sigma * 3.0).round_out().to_i32();
-
-            let render_target_y = RenderTarget::new(bounds.size(), String::new());
-            let render_target_id_y = scene.push_render_target(render_target_y);
-            let render_target_x = RenderTarget::new(bounds.size(), String::new());
-            let render_target_id_x = scene.push_render_target(render_target_x);
-
-            Some(ShadowBlurRenderTargetInfo {
-                id_x: render_target_id_x,
-                id_y: render_target_id_y,
-                bounds,
-                sigma,
-            })
-        }
-
-        fn composite_shadow_blur_render_targets_if_needed(scene: &mut Scene,
-                                                          info: Option<ShadowBlurRenderTargetInfo>,
-                                                          clip_path: Option<ClipPathId>) {
-            let info = match info {
-                None => return,
-                Some(info) => info,
-            };
-
-            let mut paint_x = Pattern::from_render_target(info.id_x, info.bounds.size());
-            let mut paint_y = Pattern::from_render_target(info.id_y, info.bounds.size());
-            paint_y.apply_transform(Transform2F::from_translation(info.bounds.origin().to_f32()));
-
-            let sigma = info.sigma;
-            paint_x.set_filter(Some(PatternFilter::Blur { direction: BlurDirection::X, sigma }));
-            paint_y.set_filter(Some(PatternFilter::Blur { direction: BlurDirection::Y, sigma }));
-
-            let paint_id_x = scene.push_paint(&Paint::from_pattern(paint_x));
-            let paint_id_y = scene.push_paint(&Paint::from_pattern(paint_y));
-
-            // TODO(pcwalton): Apply clip as necessary.
-            let outline_x = Outline::from_rect(RectF::new(vec2f(0.0, 0.0),
-                                                        info.bounds.size().to_f32()));
-            let path_x = DrawPath::new(outline_x, paint_id_x);
-            let outline_y = Outline::from_rect(info.bounds.to_f32());
-            let mut path_y = DrawPath::new(outline_y, paint_id_y);
-            path_y.set_clip_path(clip_path);
-
-            scene.pop_render_target();
-            scene.push_draw_path(path_x);
-            scene.pop_render_target();
-            scene.push_draw_path(path_y);
-        }
-
     }
 
     // Transformations
@@ -574,6 +620,99 @@ impl CanvasRenderingContext2D {
         self.canvas.scene.push_draw_path(draw_path);
     }
 
+    /// Renders everything drawn so far with the given renderer and returns the pixels in `rect`,
+    /// in unpremultiplied RGBA.
+    ///
+    /// The scene is rendered into an offscreen framebuffer the size of the canvas; the renderer's
+    /// destination framebuffer is restored afterward. Pixels outside the canvas are transparent
+    /// black. As in HTML canvas, a negative width or height extends the rect to the left of or
+    /// above its origin. Returns an error if the rect has no pixels or too many of them.
+    pub fn get_image_data<D>(&self, renderer: &mut Renderer<D>, rect: RectI)
+                             -> Result<ImageData, ImageDataError>
+                             where D: Device {
+        let rect = RectI::from_points(rect.origin().min(rect.lower_right()),
+                                      rect.origin().max(rect.lower_right()));
+        let width = rect.max_x() as i64 - rect.min_x() as i64;
+        let height = rect.max_y() as i64 - rect.min_y() as i64;
+        if width == 0 || height == 0 {
+            return Err(ImageDataError::EmptyRect);
+        }
+        match width.checked_mul(height) {
+            Some(area) if area <= MAX_IMAGE_DATA_AREA => {}
+            _ => return Err(ImageDataError::RectTooLarge),
+        }
+
+        let mut image_data = ImageData::new(rect.size());
+        let canvas_size = self.canvas.size();
+        let canvas_rect = RectI::new(Vector2I::default(), canvas_size);
+        let src_rect = match rect.intersection(canvas_rect) {
+            Some(src_rect) if src_rect.width() > 0 && src_rect.height() > 0 => src_rect,
+            _ => return Ok(image_data),
+        };
+
+        let texture = renderer.device().create_texture(TextureFormat::RGBA8, canvas_size);
+        let framebuffer = renderer.device().create_framebuffer(texture);
+        let old_dest = mem::replace(&mut renderer.options_mut().dest,
+                                    DestFramebuffer::Other(framebuffer));
+        let old_background_color = renderer.options_mut().background_color.take();
+        renderer.dest_framebuffer_size_changed();
+
+        let mut scene = self.canvas.scene.clone();
+        scene.build_and_render(renderer, BuildOptions::default(), SequentialExecutor);
+
+        let our_dest = mem::replace(&mut renderer.options_mut().dest, old_dest);
+        renderer.options_mut().background_color = old_background_color;
+        renderer.dest_framebuffer_size_changed();
+
+        // We hold the only reference to the renderer, so the destination is still our
+        // framebuffer.
+        let framebuffer = match our_dest {
+            DestFramebuffer::Other(framebuffer) => framebuffer,
+            DestFramebuffer::Default { .. } => return Ok(image_data),
+        };
+        let pixels = {
+            let device = renderer.device();
+            let render_target = DeviceRenderTarget::Framebuffer(&framebuffer);
+            let receiver = device.read_pixels(&render_target, canvas_rect);
+            texture_data_to_rgba8(device.recv_texture_data(&receiver))
+        };
+        renderer.device().destroy_framebuffer(framebuffer);
+
+        // The framebuffer holds premultiplied colors.
+        for y in src_rect.min_y()..src_rect.max_y() {
+            for x in src_rect.min_x()..src_rect.max_x() {
+                let src_index = (y * canvas_size.x() + x) as usize * 4;
+                let dest_index = ((y - rect.min_y()) * rect.width() + x - rect.min_x()) as usize;
+                let (r, g, b, a) = (pixels[src_index + 0],
+                                    pixels[src_index + 1],
+                                    pixels[src_index + 2],
+                                    pixels[src_index + 3]);
+                let unpremultiply = |channel: u8| {
+                    if a == 0 {
+                        0
+                    } else {
+                        (channel as u32 * 255 + a as u32 / 2) / a as u32
+                    }
+                };
+                image_data.data[dest_index] = ColorU::new(unpremultiply(r).min(255) as u8,
+                                                          unpremultiply(g).min(255) as u8,
+                                                          unpremultiply(b).min(255) as u8,
+                                                          a);
+            }
+        }
+        Ok(image_data)
+    }
+
+    /// Like `get_image_data()`, but renders on the CPU, for when there's no GPU renderer at hand.
+    #[cfg(feature = "pf-software")]
+    pub fn get_image_data_cpu(&self, rect: RectI) -> Result<ImageData, ImageDataError> {
+        let mut renderer = Renderer::new(SoftwareDevice::new(self.canvas.size()),
+                                         &EmbeddedResourceLoader::new(),
+                                         RendererMode::new(RendererLevel::D3D9),
+                                         RendererOptions::default());
+        self.get_image_data(&mut renderer, rect)
+    }
+
     // Image smoothing
 
     #[inline]
@@ -610,6 +749,32 @@ impl CanvasRenderingContext2D {
         }
     }
 
+    // Gradients and patterns
+
+    /// Creates a conic gradient that sweeps clockwise around `center`, starting at `start_angle`
+    /// radians from the positive x axis, like `createConicGradient()` in HTML canvas.
+    ///
+    /// Color stops must be added before the gradient is used as a fill or stroke style.
+    #[inline]
+    pub fn create_conic_gradient(&self, start_angle: f32, center: Vector2F) -> Gradient {
+        Gradient::conic(center, start_angle)
+    }
+
+    /// Creates a pattern from an image source, like `createPattern()` in HTML canvas.
+    pub fn create_pattern<I>(&mut self, image: I, repetition: PatternRepetition) -> Pattern
+                             where I: CanvasImageSource {
+        let mut pattern = image.to_pattern(self, Transform2F::default());
+        pattern.set_repeat_x(match repetition {
+            PatternRepetition::Repeat | PatternRepetition::RepeatX => true,
+            PatternRepetition::RepeatY | PatternRepetition::NoRepeat => false,
+        });
+        pattern.set_repeat_y(match repetition {
+            PatternRepetition::Repeat | PatternRepetition::RepeatY => true,
+            PatternRepetition::RepeatX | PatternRepetition::NoRepeat => false,
+        });
+        pattern
+    }
+
     // Extensions
 
     pub fn create_pattern_from_canvas(&mut self, canvas: Canvas, transform: Transform2F)
@@ -638,13 +803,27 @@ struct State {
     miter_limit: f32,
     line_dash: Vec<f32>,
     line_dash_offset: f32,
+    non_scaling_stroke: bool,
     fill_paint: Paint,
     stroke_paint: Paint,
     shadow_color: ColorU,
     shadow_blur: f32,
     shadow_offset: Vector2F,
+    filter: Filter,
     text_align: TextAlign,
     text_baseline: TextBaseline,
+    direction: TextDirection,
+    letter_spacing: f32,
+    word_spacing: f32,
+    font_kerning: FontKerning,
+    font_stretch: FontStretch,
+    font_variant_caps: FontVariantCaps,
+    text_rendering: TextRendering,
+    // The font most recently selected with the CSS shorthand, so that the font can be selected
+    // again when the stretch changes.
+    font_shorthand: Option<FontShorthand>,
+    font_variations: Vec<FontVariation>,
+    synthetic_style: SyntheticStyle,
     image_smoothing_enabled: bool,
     image_smoothing_quality: ImageSmoothingQuality,
     global_alpha: f32,
@@ -664,13 +843,25 @@ impl State {
             miter_limit: 10.0,
             line_dash: vec![],
             line_dash_offset: 0.0,
+            non_scaling_stroke: false,
             fill_paint: Paint::black(),
             stroke_paint: Paint::black(),
             shadow_color: ColorU::transparent_black(),
             shadow_blur: 0.0,
             shadow_offset: Vector2F::zero(),
+            filter: Filter::none(),
             text_align: TextAlign::Left,
             text_baseline: TextBaseline::Alphabetic,
+            direction: TextDirection::Ltr,
+            letter_spacing: 0.0,
+            word_spacing: 0.0,
+            font_kerning: FontKerning::Auto,
+            font_stretch: FontStretch::Normal,
+            font_variant_caps: FontVariantCaps::Normal,
+            text_rendering: TextRendering::Auto,
+            font_shorthand: None,
+            font_variations: vec![],
+            synthetic_style: SyntheticStyle::default(),
             image_smoothing_enabled: true,
             image_smoothing_quality: ImageSmoothingQuality::Low,
             global_alpha: 1.0,
@@ -694,9 +885,9 @@ impl State {
         let mut paint = (*paint).clone();
         paint.apply_transform(&self.transform);
 
-        let mut base_color = paint.base_color().to_f32();
+        let mut base_color = paint.base_color_f();
         base_color.set_a(base_color.a() * self.global_alpha);
-        paint.set_base_color(base_color.to_u8());
+        paint.set_base_color_f(base_color);
 
         if let Some(ref mut pattern) = paint.pattern_mut() {
             pattern.set_smoothing_enabled(self.image_smoothing_enabled);
@@ -729,6 +920,14 @@ impl Path2D {
         Path2D { outline: Outline::new(), current_contour: Contour::new() }
     }
 
+    /// Parses a path from SVG path data, like the `d` attribute of the SVG `<path>` element.
+    ///
+    /// This is equivalent to passing a string to the `Path2D` constructor in HTML canvas.
+    #[inline]
+    pub fn from_svg_path_data(data: &str) -> Result<Path2D, PathDataParseError> {
+        path_data::parse_svg_path_data(data)
+    }
+
     #[inline]
     pub fn close_path(&mut self) {
         self.current_contour.close();
@@ -792,6 +991,40 @@ impl Path2D {
         self.current_contour.close();
     }
 
+    /// Adds a rectangle with rounded corners to the path, like `roundRect()` in HTML canvas.
+    ///
+    /// `radii` contains one to four corner radii, each of which is either a single radius or a
+    /// vector of horizontal and vertical radii. As with the CSS `border-radius` property, they
+    /// apply to the upper left, upper right, lower right, and lower left corners in that order,
+    /// with missing radii copied from the opposite corner.
+    ///
+    /// If `radii` is empty or has more than four elements, the path is left unchanged, much as
+    /// `roundRect()` throws without changing the path.
+    pub fn round_rect<R>(&mut self, rect: RectF, radii: &[R]) where R: IntoVector2F + Copy {
+        let radii: Vec<Vector2F> = radii.iter().map(|radius| radius.into_vector_2f()).collect();
+        let radii = match radii[..] {
+            [all] => [all, all, all, all],
+            [upper_left_and_lower_right, upper_right_and_lower_left] => {
+                [
+                    upper_left_and_lower_right,
+                    upper_right_and_lower_left,
+                    upper_left_and_lower_right,
+                    upper_right_and_lower_left,
+                ]
+            }
+            [upper_left, upper_right_and_lower_left, lower_right] => {
+                [upper_left, upper_right_and_lower_left, lower_right, upper_right_and_lower_left]
+            }
+            [upper_left, upper_right, lower_right, lower_left] => {
+                [upper_left, upper_right, lower_right, lower_left]
+            }
+            _ => return,
+        };
+
+        self.flush_current_contour();
+        self.current_contour = Contour::from_rect_rounded_corners(rect, radii);
+    }
+
     pub fn ellipse<A>(&mut self,
                       center: Vector2F,
                       axes: A,
@@ -855,6 +1088,10 @@ pub enum TextAlign {
     Left,
     Right,
     Center,
+    /// Left for left-to-right text and right for right-to-left text.
+    Start,
+    /// Right for left-to-right text and left for right-to-left text.
+    End,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -867,6 +1104,65 @@ pub enum TextBaseline {
     Bottom,
 }
 
+/// The base direction of text, which determines how `TextAlign::Start` and `TextAlign::End`
+/// align it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TextDirection {
+    Ltr,
+    Rtl,
+}
+
+/// Whether the kerning information in fonts is used.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FontKerning {
+    /// Kerning is used unless the text rendering mode is `TextRendering::OptimizeSpeed`.
+    Auto,
+    Normal,
+    None,
+}
+
+/// The width of the font to select, from most condensed to most expanded.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FontStretch {
+    UltraCondensed,
+    ExtraCondensed,
+    Condensed,
+    SemiCondensed,
+    Normal,
+    SemiExpanded,
+    Expanded,
+    ExtraExpanded,
+    UltraExpanded,
+}
+
+/// Alternate capital letter glyphs to use.
+///
+/// Small capitals are synthesized by drawing capitals at a reduced size. Petite capitals are drawn
+/// as small capitals, and titling capitals as normal capitals.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FontVariantCaps {
+    Normal,
+    /// Small capitals for lowercase letters.
+    SmallCaps,
+    /// Small capitals for both uppercase and lowercase letters.
+    AllSmallCaps,
+    PetiteCaps,
+    AllPetiteCaps,
+    /// Small capitals for uppercase letters, with lowercase letters left as they are.
+    Unicase,
+    TitlingCaps,
+}
+
+/// A hint as to what to optimize for when rendering text.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TextRendering {
+    Auto,
+    /// Favor speed over legibility. This disables kerning when `FontKerning::Auto` is in use.
+    OptimizeSpeed,
+    OptimizeLegibility,
+    GeometricPrecision,
+}
+
 // We duplicate `pathfinder_content::stroke::LineJoin` here because the HTML canvas API treats the
 // miter limit as part of the canvas state, while the native Pathfinder API treats the miter limit
 // as part of the line join. Pathfinder's choice is more logical, because the miter limit is
@@ -949,6 +1245,19 @@ pub enum ImageSmoothingQuality {
     High,
 }
 
+/// How a pattern created with `create_pattern()` repeats.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PatternRepetition {
+    /// The pattern repeats in both directions. This corresponds to `"repeat"`.
+    Repeat,
+    /// The pattern repeats horizontally only. This corresponds to `"repeat-x"`.
+    RepeatX,
+    /// The pattern repeats vertically only. This corresponds to `"repeat-y"`.
+    RepeatY,
+    /// The pattern is drawn once. This corresponds to `"no-repeat"`.
+    NoRepeat,
+}
+
 pub trait CanvasImageSource {
     fn to_pattern(self, dest_context: &mut CanvasRenderingContext2D, transform: Transform2F)
                   -> Pattern;
@@ -967,6 +1276,15 @@ impl CanvasImageSource for Pattern {
     }
 }
 
+impl CanvasImageSource for Image {
+    #[inline]
+    fn to_pattern(self, _: &mut CanvasRenderingContext2D, transform: Transform2F) -> Pattern {
+        let mut pattern = Pattern::from_image(self);
+        pattern.apply_transform(transform);
+        pattern
+    }
+}
+
 impl CanvasImageSource for Canvas {
     #[inline]
     fn to_pattern(self, dest_context: &mut CanvasRenderingContext2D, transform: Transform2F)
@@ -1005,7 +1323,8 @@ pub struct ImageData {
 impl ImageData {
     #[inline]
     pub fn new(size: Vector2I) -> ImageData {
-        ImageData { data: vec![ColorU::transparent_black(); size.area() as usize], size }
+        let pixel_count = size.x().max(0) as usize * size.y().max(0) as usize;
+        ImageData { data: vec![ColorU::transparent_black(); pixel_count], size }
     }
 
     #[inline]
@@ -1014,6 +1333,26 @@ impl ImageData {
     }
 }
 
+/// The error returned when pixels can't be read back from a rect.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ImageDataError {
+    /// The rect has no pixels.
+    EmptyRect,
+    /// The rect has more pixels than `get_image_data()` reads back at once.
+    RectTooLarge,
+}
+
+impl Display for ImageDataError {
+    fn fmt(&self, formatter: &mut Formatter) -> Result<(), FmtError> {
+        match *self {
+            ImageDataError::EmptyRect => write!(formatter, "the rect has no pixels"),
+            ImageDataError::RectTooLarge => write!(formatter, "the rect has too many pixels"),
+        }
+    }
+}
+
+impl Error for ImageDataError {}
+
 impl Debug for Path2D {
     fn fmt(&self, formatter: &mut Formatter) -> Result<(), FmtError> {
         self.clone().into_outline().fmt(formatter)
@@ -1041,11 +1380,95 @@ impl From<Pattern> for FillStyle {
     }
 }
 
-struct ShadowBlurRenderTargetInfo {
-    id_x: RenderTargetId,
-    id_y: RenderTargetId,
-    bounds: RectI,
-    sigma: f32,
+// Converts pixels read back from a framebuffer to 8-bit RGBA. Devices return RGBA8 framebuffers as
+// bytes, but converting the other formats costs nothing.
+fn texture_data_to_rgba8(data: TextureData) -> Vec<u8> {
+    let to_u8 = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+    match data {
+        TextureData::U8(pixels) => pixels,
+        TextureData::U16(pixels) => pixels.into_iter().map(|value| (value >> 8) as u8).collect(),
+        TextureData::F16(pixels) => pixels.into_iter().map(|value| to_u8(value.to_f32())).collect(),
+        TextureData::F32(pixels) => pixels.into_iter().map(to_u8).collect(),
+    }
+}
+
+// Draws the shadow of content with the given paint and device-space bounds, per the canvas
+// specification.
+//
+// The `draw` callback pushes the content with the shadow paint, transformed by the given
+// device-space transform, with the given clip path and blend mode. The shadow offset and blur are
+// in device pixels and are unaffected by the current transform. If the shadow is blurred, the
+// content is drawn into render targets and the blurred result is composited with the clip path
+// and blend mode instead.
+fn push_shadow<F>(scene: &mut Scene, state: &State, paint: &Paint, bounds: RectF, draw: F)
+                  where F: FnOnce(&mut Scene,
+                                  PaintId,
+                                  Transform2F,
+                                  Option<ClipPathId>,
+                                  BlendMode) {
+    let clip_path = state.clip_path;
+    let blend_mode = state.global_composite_operation.to_blend_mode();
+    let bounds = bounds + state.shadow_offset;
+
+    // Per spec the shadow must respect the alpha of the shadowed path, but otherwise have the
+    // color of the shadow paint.
+    let mut shadow_paint = (*paint).clone();
+    let mut shadow_color = state.shadow_color.to_f32();
+    shadow_color.set_a(shadow_color.a() * paint.base_color_f().a());
+    shadow_paint.set_base_color_f(shadow_color);
+    if let &mut Some(ref mut shadow_paint_overlay) = shadow_paint.overlay_mut() {
+        shadow_paint_overlay.set_composite_op(PaintCompositeOp::DestIn);
+    }
+
+    if state.shadow_blur <= 0.0 {
+        let transform = Transform2F::from_translation(state.shadow_offset);
+        shadow_paint.apply_transform(&transform);
+        let shadow_paint_id = scene.push_paint(&shadow_paint);
+        draw(scene, shadow_paint_id, transform, clip_path, blend_mode);
+        return;
+    }
+
+    // Blurred content outside the view box can still spread into it, so only cull beyond the
+    // blur extent.
+    let sigma = state.shadow_blur * 0.5;
+    let view_box = scene.view_box().dilate(sigma * 3.0);
+    let bounds = match bounds.dilate(sigma * 3.0).intersection(view_box) {
+        Some(bounds) => bounds.round_out().to_i32(),
+        None => return,
+    };
+    if bounds.width() <= 0 || bounds.height() <= 0 {
+        return;
+    }
+
+    let render_target_id_y = scene.push_render_target(RenderTarget::new(bounds.size(),
+                                                                        String::new()));
+    let render_target_id_x = scene.push_render_target(RenderTarget::new(bounds.size(),
+                                                                        String::new()));
+
+    let transform = Transform2F::from_translation(state.shadow_offset -
+                                                  bounds.origin().to_f32());
+    shadow_paint.apply_transform(&transform);
+    let shadow_paint_id = scene.push_paint(&shadow_paint);
+    draw(scene, shadow_paint_id, transform, None, BlendMode::SrcOver);
+
+    let mut pattern_x = Pattern::from_render_target(render_target_id_x, bounds.size());
+    let mut pattern_y = Pattern::from_render_target(render_target_id_y, bounds.size());
+    pattern_y.apply_transform(Transform2F::from_translation(bounds.origin().to_f32()));
+    pattern_x.set_filter(Some(PatternFilter::Blur { direction: BlurDirection::X, sigma }));
+    pattern_y.set_filter(Some(PatternFilter::Blur { direction: BlurDirection::Y, sigma }));
+    let paint_id_x = scene.push_paint(&Paint::from_pattern(pattern_x));
+    let paint_id_y = scene.push_paint(&Paint::from_pattern(pattern_y));
+
+    let outline_x = Outline::from_rect(RectF::new(Vector2F::zero(), bounds.size().to_f32()));
+    let path_x = DrawPath::new(outline_x, paint_id_x);
+    let mut path_y = DrawPath::new(Outline::from_rect(bounds.to_f32()), paint_id_y);
+    path_y.set_clip_path(clip_path);
+    path_y.set_blend_mode(blend_mode);
+
+    scene.pop_render_target();
+    scene.push_draw_path(path_x);
+    scene.pop_render_target();
+    scene.push_draw_path(path_y);
 }
 
 enum PathOp {
@@ -0,0 +1,340 @@
+// pathfinder/canvas/src/path_data.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Parsing of SVG path data, as accepted by the `Path2D` constructor.
+
+use crate::Path2D;
+use pathfinder_content::outline::ArcDirection;
+use pathfinder_geometry::vector::{Vector2F, vec2f};
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+
+/// The error returned when a string is not valid SVG path data.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct PathDataParseError {
+    /// The byte offset in the string at which the error was found.
+    pub position: usize,
+    /// What went wrong.
+    pub kind: PathDataParseErrorKind,
+}
+
+/// The kinds of errors that can occur when parsing SVG path data.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PathDataParseErrorKind {
+    /// The path data didn't start with a `M` or `m` command.
+    ExpectedMoveTo,
+    /// A number was expected but something else was found.
+    ExpectedNumber,
+    /// An arc flag, `0` or `1`, was expected but something else was found.
+    ExpectedFlag,
+    /// A character that isn't a command, a number, or a separator was found.
+    UnexpectedCharacter,
+}
+
+struct PathDataParser<'a> {
+    data: &'a [u8],
+    position: usize,
+    path: Path2D,
+    // The current point.
+    current: Vector2F,
+    // The start of the current subpath, where `Z` returns to.
+    subpath_start: Vector2F,
+    // The second control point of the last cubic curve, for `S`.
+    last_cubic_ctrl: Option<Vector2F>,
+    // The control point of the last quadratic curve, for `T`.
+    last_quadratic_ctrl: Option<Vector2F>,
+    // Whether the last command was `Z`, so the next drawing command starts a new subpath.
+    needs_move_to: bool,
+}
+
+pub(crate) fn parse_svg_path_data(data: &str) -> Result<Path2D, PathDataParseError> {
+    let mut parser = PathDataParser {
+        data: data.as_bytes(),
+        position: 0,
+        path: Path2D::new(),
+        current: Vector2F::zero(),
+        subpath_start: Vector2F::zero(),
+        last_cubic_ctrl: None,
+        last_quadratic_ctrl: None,
+        needs_move_to: false,
+    };
+    parser.parse()?;
+    Ok(parser.path)
+}
+
+impl<'a> PathDataParser<'a> {
+    fn parse(&mut self) -> Result<(), PathDataParseError> {
+        let mut command = None;
+        loop {
+            self.skip_whitespace();
+            let c = match self.peek() {
+                None => return Ok(()),
+                Some(c) => c,
+            };
+
+            if c.is_ascii_alphabetic() {
+                if command.is_none() && c != b'M' && c != b'm' {
+                    return Err(self.error(PathDataParseErrorKind::ExpectedMoveTo));
+                }
+                self.position += 1;
+                command = Some(c);
+
+                // The first argument follows the command without a comma.
+                self.skip_whitespace();
+                if self.peek() == Some(b',') {
+                    return Err(self.error(PathDataParseErrorKind::UnexpectedCharacter));
+                }
+            } else {
+                // One comma may separate the arguments of a repeated command from the last ones.
+                if c == b',' {
+                    self.skip_separator();
+                }
+                match self.peek() {
+                    Some(c) if is_number_start(c) => {}
+                    Some(_) => return Err(self.error(PathDataParseErrorKind::UnexpectedCharacter)),
+                    None => return Err(self.error(PathDataParseErrorKind::ExpectedNumber)),
+                }
+
+                match command {
+                    None => return Err(self.error(PathDataParseErrorKind::ExpectedMoveTo)),
+                    // Coordinates after a `Z` without another command are an error.
+                    Some(b'Z') | Some(b'z') => {
+                        return Err(self.error(PathDataParseErrorKind::UnexpectedCharacter))
+                    }
+                    // Extra coordinates after a move are implicit line-tos.
+                    Some(b'M') => command = Some(b'L'),
+                    Some(b'm') => command = Some(b'l'),
+                    Some(_) => {}
+                }
+            }
+
+            self.command(command.unwrap())?;
+        }
+    }
+
+    fn command(&mut self, command: u8) -> Result<(), PathDataParseError> {
+        let relative = command.is_ascii_lowercase();
+        let origin = if relative { self.current } else { Vector2F::zero() };
+
+        let command = command.to_ascii_uppercase();
+        match command {
+            b'M' | b'Z' => {}
+            _ if self.needs_move_to => {
+                // Drawing after a `Z` starts a new subpath at the start of the closed one.
+                self.path.move_to(self.current);
+                self.needs_move_to = false;
+            }
+            _ => {}
+        }
+
+        let (mut cubic_ctrl, mut quadratic_ctrl) = (None, None);
+        match command {
+            b'M' => {
+                let to = origin + self.point()?;
+                self.path.move_to(to);
+                self.current = to;
+                self.subpath_start = to;
+                self.needs_move_to = false;
+            }
+            b'Z' => {
+                self.path.close_path();
+                self.current = self.subpath_start;
+                self.needs_move_to = true;
+            }
+            b'L' => {
+                let to = origin + self.point()?;
+                self.path.line_to(to);
+                self.current = to;
+            }
+            b'H' => {
+                let to = vec2f(origin.x() + self.number()?, self.current.y());
+                self.path.line_to(to);
+                self.current = to;
+            }
+            b'V' => {
+                let to = vec2f(self.current.x(), origin.y() + self.number()?);
+                self.path.line_to(to);
+                self.current = to;
+            }
+            b'C' | b'S' => {
+                let ctrl0 = if command == b'C' {
+                    origin + self.point()?
+                } else {
+                    self.reflect(self.last_cubic_ctrl)
+                };
+                let ctrl1 = origin + self.point()?;
+                let to = origin + self.point()?;
+                self.path.bezier_curve_to(ctrl0, ctrl1, to);
+                self.current = to;
+                cubic_ctrl = Some(ctrl1);
+            }
+            b'Q' | b'T' => {
+                let ctrl = if command == b'Q' {
+                    origin + self.point()?
+                } else {
+                    self.reflect(self.last_quadratic_ctrl)
+                };
+                let to = origin + self.point()?;
+                self.path.quadratic_curve_to(ctrl, to);
+                self.current = to;
+                quadratic_ctrl = Some(ctrl);
+            }
+            b'A' => {
+                let radius = self.point()?;
+                let x_axis_rotation = self.number()?.to_radians();
+                let large_arc = self.flag()?;
+                let sweep = self.flag()?;
+                let to = origin + self.point()?;
+
+                // See "Out-of-range parameters" in the SVG specification.
+                if radius.x() == 0.0 || radius.y() == 0.0 {
+                    self.path.line_to(to);
+                } else if to != self.current {
+                    let direction = if sweep { ArcDirection::CW } else { ArcDirection::CCW };
+                    self.path
+                        .current_contour
+                        .push_svg_arc(radius, x_axis_rotation, large_arc, direction, to);
+                }
+                self.current = to;
+            }
+            _ => {
+                self.position -= 1;
+                return Err(self.error(PathDataParseErrorKind::UnexpectedCharacter));
+            }
+        }
+
+        self.last_cubic_ctrl = cubic_ctrl;
+        self.last_quadratic_ctrl = quadratic_ctrl;
+        Ok(())
+    }
+
+    // Reflects the given control point of the previous curve about the current point. If the
+    // previous command wasn't a curve of the same kind, the current point is used instead.
+    fn reflect(&self, ctrl: Option<Vector2F>) -> Vector2F {
+        match ctrl {
+            None => self.current,
+            Some(ctrl) => self.current * 2.0 - ctrl,
+        }
+    }
+
+    fn point(&mut self) -> Result<Vector2F, PathDataParseError> {
+        let x = self.number()?;
+        let y = self.number()?;
+        Ok(vec2f(x, y))
+    }
+
+    fn number(&mut self) -> Result<f32, PathDataParseError> {
+        self.skip_separator();
+        let start = self.position;
+
+        if let Some(b'+') | Some(b'-') = self.peek() {
+            self.position += 1;
+        }
+        let integer_digits = self.skip_digits();
+        let mut fraction_digits = 0;
+        if self.peek() == Some(b'.') {
+            self.position += 1;
+            fraction_digits = self.skip_digits();
+        }
+        if integer_digits == 0 && fraction_digits == 0 {
+            self.position = start;
+            return Err(self.error(PathDataParseErrorKind::ExpectedNumber));
+        }
+
+        // Only consume an exponent if digits follow it, so that `1e` isn't swallowed.
+        if let Some(b'e') | Some(b'E') = self.peek() {
+            let mantissa_end = self.position;
+            self.position += 1;
+            if let Some(b'+') | Some(b'-') = self.peek() {
+                self.position += 1;
+            }
+            if self.skip_digits() == 0 {
+                self.position = mantissa_end;
+            }
+        }
+
+        let string = std::str::from_utf8(&self.data[start..self.position]).unwrap();
+        match string.parse::<f32>() {
+            Ok(number) if number.is_finite() => Ok(number),
+            _ => {
+                self.position = start;
+                Err(self.error(PathDataParseErrorKind::ExpectedNumber))
+            }
+        }
+    }
+
+    // Arc flags may be written without separators, as in `a1 1 0 00 1 1`.
+    fn flag(&mut self) -> Result<bool, PathDataParseError> {
+        self.skip_separator();
+        match self.peek() {
+            Some(b'0') => {
+                self.position += 1;
+                Ok(false)
+            }
+            Some(b'1') => {
+                self.position += 1;
+                Ok(true)
+            }
+            _ => Err(self.error(PathDataParseErrorKind::ExpectedFlag)),
+        }
+    }
+
+    fn skip_digits(&mut self) -> usize {
+        let start = self.position;
+        while let Some(b'0'..=b'9') = self.peek() {
+            self.position += 1;
+        }
+        self.position - start
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ') | Some(b'\t') | Some(b'\n') | Some(b'\x0c') | Some(b'\r') =
+                self.peek() {
+            self.position += 1;
+        }
+    }
+
+    // Skips whitespace and at most one comma.
+    fn skip_separator(&mut self) {
+        self.skip_whitespace();
+        if self.peek() == Some(b',') {
+            self.position += 1;
+            self.skip_whitespace();
+        }
+    }
+
+    #[inline]
+    fn peek(&self) -> Option<u8> {
+        self.data.get(self.position).cloned()
+    }
+
+    #[inline]
+    fn error(&self, kind: PathDataParseErrorKind) -> PathDataParseError {
+        PathDataParseError { position: self.position, kind }
+    }
+}
+
+fn is_number_start(c: u8) -> bool {
+    c.is_ascii_digit() || c == b'.' || c == b'+' || c == b'-'
+}
+
+impl Display for PathDataParseError {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        let message = match self.kind {
+            PathDataParseErrorKind::ExpectedMoveTo => "path data must begin with a move-to",
+            PathDataParseErrorKind::ExpectedNumber => "expected a number",
+            PathDataParseErrorKind::ExpectedFlag => "expected an arc flag",
+            PathDataParseErrorKind::UnexpectedCharacter => "unexpected character",
+        };
+        write!(formatter, "{} at position {}", message, self.position)
+    }
+}
+
+impl Error for PathDataParseError {}
@@ -3,8 +3,32 @@
 // For this file only, any copyright is dedicated to the Public Domain.
 // https://creativecommons.org/publicdomain/zero/1.0/
 
-use pathfinder_geometry::vector::{Vector2F, vec2f};
-use super::Path2D;
+#[cfg(feature = "pf-text")]
+use font_kit::handle::Handle;
+use pathfinder_color::ColorU;
+use pathfinder_content::fill::FillRule;
+use pathfinder_content::gradient::Gradient;
+use pathfinder_geometry::rect::{RectF, RectI};
+use pathfinder_geometry::transform2d::Transform2F;
+use pathfinder_geometry::vector::{Vector2F, Vector2I, vec2f, vec2i};
+use pathfinder_renderer::gpu::options::{DestFramebuffer, RendererLevel, RendererMode};
+use pathfinder_renderer::gpu::options::RendererOptions;
+use pathfinder_renderer::gpu::renderer::Renderer;
+use pathfinder_renderer::scene::DrawPathId;
+#[cfg(feature = "pf-text")]
+use pathfinder_resources::ResourceLoader;
+use pathfinder_resources::embedded::EmbeddedResourceLoader;
+use pathfinder_software::SoftwareDevice;
+use std::f32::consts::PI;
+#[cfg(feature = "pf-text")]
+use std::iter;
+#[cfg(feature = "pf-text")]
+use std::sync::Arc;
+use super::{Canvas, CanvasFontContext, CanvasRenderingContext2D, CompositeOperation, Filter};
+use super::{FilterFunction, Path2D};
+use super::{FontFamilyName, FontShorthand, FontStretch, FontStyle, FontVariantCaps};
+use super::ImageDataError;
+use super::{PathDataParseError, PathDataParseErrorKind};
 
 #[test]
 pub fn test_path2d_formatting() {
@@ -17,3 +41,471 @@ pub fn test_path2d_formatting() {
     path.close_path();
     assert_eq!(format!("{:?}", path), "M 0 1 L 2 3 L 4 5 z");
 }
+
+#[test]
+pub fn test_filter_parsing() {
+    assert_eq!("none".parse::<Filter>(), Ok(Filter::none()));
+    assert_eq!("blur(4px) saturate(150%) hue-rotate(0.5turn) opacity()".parse::<Filter>(),
+               Ok(Filter::new(vec![
+                   FilterFunction::Blur(4.0),
+                   FilterFunction::Saturate(1.5),
+                   FilterFunction::HueRotate(PI),
+                   FilterFunction::Opacity(1.0),
+               ])));
+    assert_eq!("invert(2) drop-shadow(2px 3px 4px rgba(255, 0, 0, 0.5))".parse::<Filter>(),
+               Ok(Filter::new(vec![
+                   FilterFunction::Invert(1.0),
+                   FilterFunction::DropShadow {
+                       offset: vec2f(2.0, 3.0),
+                       sigma: 2.0,
+                       color: ColorU::new(255, 0, 0, 128),
+                   },
+               ])));
+    assert_eq!("drop-shadow(#0f0 -1px 0)".parse::<Filter>(),
+               Ok(Filter::new(vec![FilterFunction::DropShadow {
+                   offset: vec2f(-1.0, 0.0),
+                   sigma: 0.0,
+                   color: ColorU::new(0, 255, 0, 255),
+               }])));
+
+    assert!("".parse::<Filter>().is_err());
+    assert!("blur(4)".parse::<Filter>().is_err());
+    assert!("brightness(-1)".parse::<Filter>().is_err());
+    assert!("sepia(1) bogus(1)".parse::<Filter>().is_err());
+    assert!("drop-shadow(1px)".parse::<Filter>().is_err());
+    // Non-ASCII characters where a unit would be.
+    assert!("blur(éa)".parse::<Filter>().is_err());
+    assert!("opacity(5é)".parse::<Filter>().is_err());
+    assert!("hue-rotate(1ﬀ)".parse::<Filter>().is_err());
+}
+
+#[test]
+pub fn test_font_shorthand_parsing() {
+    assert_eq!("italic bold 16px Roboto, sans-serif".parse::<FontShorthand>(),
+               Ok(FontShorthand {
+                   style: FontStyle::Italic,
+                   variant_caps: FontVariantCaps::Normal,
+                   weight: 700.0,
+                   stretch: FontStretch::Normal,
+                   size: 16.0,
+                   families: vec![
+                       FontFamilyName::Title("Roboto".to_owned()),
+                       FontFamilyName::SansSerif,
+                   ],
+               }));
+    assert_eq!("small-caps normal 300 condensed 12pt/1.5 \"Sans Serif\",  Open   Sans"
+                   .parse::<FontShorthand>(),
+               Ok(FontShorthand {
+                   style: FontStyle::Normal,
+                   variant_caps: FontVariantCaps::SmallCaps,
+                   weight: 300.0,
+                   stretch: FontStretch::Condensed,
+                   size: 16.0,
+                   families: vec![
+                       FontFamilyName::Title("Sans Serif".to_owned()),
+                       FontFamilyName::Title("Open Sans".to_owned()),
+                   ],
+               }));
+
+    let font = "2em / normal monospace".parse::<FontShorthand>().unwrap();
+    assert_eq!((font.size, font.families), (20.0, vec![FontFamilyName::Monospace]));
+
+    assert!("".parse::<FontShorthand>().is_err());
+    assert!("16px".parse::<FontShorthand>().is_err());
+    assert!("bold Roboto".parse::<FontShorthand>().is_err());
+    assert!("italic italic 16px serif".parse::<FontShorthand>().is_err());
+    assert!("16px Roboto,".parse::<FontShorthand>().is_err());
+    assert!("16px 'Roboto".parse::<FontShorthand>().is_err());
+}
+
+#[test]
+pub fn test_path2d_from_svg_path_data() {
+    let path = Path2D::from_svg_path_data("M10 10 h 80 v 80 H10 Z").unwrap();
+    assert_eq!(format!("{:?}", path), "M 10 10 L 90 10 L 90 90 L 10 90 z");
+
+    // Implicit line-tos, relative commands, and numbers without separators.
+    let path = Path2D::from_svg_path_data("m1,2 3,4l-1-1.5.5.5").unwrap();
+    assert_eq!(format!("{:?}", path), "M 1 2 L 4 6 L 3 4.5 L 3.5 5");
+
+    // Smooth curves reflect the previous control point.
+    let path = Path2D::from_svg_path_data("M0 0 C 1 1 2 1 3 0 S 5 -1 6 0").unwrap();
+    assert_eq!(format!("{:?}", path), "M 0 0 C 1 1 2 1 3 0 C 4 -1 5 -1 6 0");
+    let path = Path2D::from_svg_path_data("M0 0 Q 1 1 2 0 t 2 0").unwrap();
+    assert_eq!(format!("{:?}", path), "M 0 0 Q 1 1 2 0 Q 3 -1 4 0");
+
+    // Drawing after a close starts a new subpath at the start of the closed one.
+    let path = Path2D::from_svg_path_data("M1 1 L 2 2 z l 1 0").unwrap();
+    assert_eq!(format!("{:?}", path), "M 1 1 L 2 2 z M 1 1 L 2 1");
+
+    // Arcs with a zero radius are lines, and arc flags may omit separators.
+    let path = Path2D::from_svg_path_data("M0 0 A 0 5 0 1 0 10 0").unwrap();
+    assert_eq!(format!("{:?}", path), "M 0 0 L 10 0");
+    let path = Path2D::from_svg_path_data("M0 0 a5 5 0 0010 0").unwrap();
+    let outline = path.into_outline();
+    let path_data = outline.to_svg_path_data();
+    assert!(path_data.starts_with("M 0 0 ") && path_data.contains(" A 5 "));
+    assert!(path_data.ends_with(" 10 0"));
+}
+
+#[test]
+pub fn test_path2d_svg_path_data_errors() {
+    let error = |data| Path2D::from_svg_path_data(data).err().unwrap();
+    assert_eq!(error("L 1 2"),
+               PathDataParseError { position: 0, kind: PathDataParseErrorKind::ExpectedMoveTo });
+    assert_eq!(error("M 1 2 L 3 x"),
+               PathDataParseError { position: 10, kind: PathDataParseErrorKind::ExpectedNumber });
+    assert_eq!(error("M 0 0 A 1 1 0 2 0 1 1"),
+               PathDataParseError { position: 14, kind: PathDataParseErrorKind::ExpectedFlag });
+    assert_eq!(error("M 0 0 X"),
+               PathDataParseError {
+                   position: 6,
+                   kind: PathDataParseErrorKind::UnexpectedCharacter,
+               });
+
+    // Arguments are separated by at most one comma, and a command isn't followed by one.
+    assert_eq!(format!("{:?}", Path2D::from_svg_path_data("M0,0,1,1").unwrap()), "M 0 0 L 1 1");
+    assert_eq!(error("M0,0,,1"),
+               PathDataParseError {
+                   position: 5,
+                   kind: PathDataParseErrorKind::UnexpectedCharacter,
+               });
+    assert_eq!(error("M0,0 L1,,1"),
+               PathDataParseError { position: 8, kind: PathDataParseErrorKind::ExpectedNumber });
+    assert_eq!(error("M,0,0"),
+               PathDataParseError {
+                   position: 1,
+                   kind: PathDataParseErrorKind::UnexpectedCharacter,
+               });
+    assert_eq!(error("M0,0,"),
+               PathDataParseError { position: 5, kind: PathDataParseErrorKind::ExpectedNumber });
+}
+
+#[test]
+pub fn test_path2d_round_rect() {
+    let rect = RectF::new(vec2f(0.0, 0.0), vec2f(10.0, 4.0));
+
+    // Corners without a radius are sharp.
+    let mut path = Path2D::new();
+    path.round_rect(rect, &[0.0]);
+    assert_eq!(format!("{:?}", path), "M 0 0 L 10 0 L 10 4 L 0 4 z");
+
+    // Two radii alternate, and radii that don't fit are scaled down uniformly.
+    let mut path = Path2D::new();
+    path.round_rect(rect, &[6.0, 0.0]);
+    let outline = path.into_outline();
+    assert_eq!(outline.bounds(), rect);
+    let path_data = outline.to_svg_path_data();
+    assert!(path_data.starts_with("M 0 4 C "));
+    assert!(path_data.contains(" 4 0 L 10 0 L 10 0 C "));
+    assert!(path_data.ends_with(" 6 4 L 0 4 z"));
+
+    // Calls without between one and four radii are ignored.
+    let mut path = Path2D::new();
+    path.move_to(vec2f(1.0, 2.0));
+    path.line_to(vec2f(3.0, 4.0));
+    path.round_rect::<f32>(rect, &[]);
+    path.round_rect(rect, &[1.0, 2.0, 3.0, 4.0, 5.0]);
+    assert_eq!(format!("{:?}", path), "M 1 2 L 3 4");
+}
+
+#[test]
+pub fn test_shadow_offset_is_in_device_space() {
+    let mut context = new_shadow_test_context();
+    context.scale(2.0);
+    context.set_fill_style(ColorU::new(255, 0, 0, 255));
+    context.set_shadow_color(ColorU::black());
+    context.set_shadow_offset(vec2f(3.0, 4.0));
+    context.fill_rect(shadow_test_rect() * 0.5);
+
+    let mut expected = new_shadow_test_context();
+    expected.set_fill_style(ColorU::black());
+    expected.fill_rect(shadow_test_rect() + vec2f(3.0, 4.0));
+    expected.set_fill_style(ColorU::new(255, 0, 0, 255));
+    expected.fill_rect(shadow_test_rect());
+    assert_pixels_close(&render_pixels(&context), &render_pixels(&expected));
+}
+
+#[test]
+pub fn test_shadow_paint_follows_shadow_offset() {
+    // The shadow takes its alpha from the gradient, shifted along with the shadow.
+    let mut gradient = Gradient::linear_from_points(vec2f(10.0, 0.0), vec2f(22.0, 0.0));
+    gradient.add_color_stop(ColorU::new(255, 0, 0, 255), 0.0);
+    gradient.add_color_stop(ColorU::new(255, 0, 0, 0), 1.0);
+    let mut context = new_shadow_test_context();
+    context.set_fill_style(gradient.clone());
+    context.set_shadow_color(ColorU::black());
+    context.set_shadow_offset(vec2f(5.0, 0.0));
+    context.fill_rect(shadow_test_rect());
+
+    let mut shadow_gradient = Gradient::linear_from_points(vec2f(15.0, 0.0), vec2f(27.0, 0.0));
+    shadow_gradient.add_color_stop(ColorU::black(), 0.0);
+    shadow_gradient.add_color_stop(ColorU::transparent_black(), 1.0);
+    let mut expected = new_shadow_test_context();
+    expected.set_fill_style(shadow_gradient);
+    expected.fill_rect(shadow_test_rect() + vec2f(5.0, 0.0));
+    expected.set_fill_style(gradient);
+    expected.fill_rect(shadow_test_rect());
+    assert_pixels_close(&render_pixels(&context), &render_pixels(&expected));
+}
+
+#[test]
+pub fn test_blurred_shadow() {
+    // A shadow blur is twice the standard deviation, like a `blur()` filter's radius.
+    let mut context = new_shadow_test_context();
+    context.set_fill_style(ColorU::new(255, 0, 0, 255));
+    context.set_shadow_color(ColorU::black());
+    context.set_shadow_blur(4.0);
+    context.set_shadow_offset(vec2f(4.0, 2.0));
+    context.fill_rect(shadow_test_rect());
+
+    let mut expected = new_shadow_test_context();
+    expected.set_filter("blur(2px)".parse().unwrap());
+    expected.set_fill_style(ColorU::black());
+    expected.fill_rect(shadow_test_rect() + vec2f(4.0, 2.0));
+    expected.set_filter(Filter::default());
+    expected.set_fill_style(ColorU::new(255, 0, 0, 255));
+    expected.fill_rect(shadow_test_rect());
+    assert_pixels_close(&render_pixels(&context), &render_pixels(&expected));
+
+    // Shadows that can't reach the canvas are culled.
+    let mut context = new_context(vec2i(32, 32));
+    context.set_shadow_color(ColorU::black());
+    context.set_shadow_blur(4.0);
+    context.set_shadow_offset(vec2f(1000.0, 0.0));
+    context.fill_rect(shadow_test_rect());
+    assert_eq!(context.canvas().scene().draw_path_count(), 1);
+}
+
+#[test]
+pub fn test_shadows_are_clipped() {
+    let clip_rect = RectF::new(vec2f(4.0, 4.0), vec2f(20.0, 15.0));
+    for &shadow_blur in &[0.0, 4.0] {
+        let mut clip_path = Path2D::new();
+        clip_path.rect(clip_rect);
+        let mut context = new_shadow_test_context();
+        context.clip_path(clip_path.clone(), FillRule::Winding);
+        context.set_fill_style(ColorU::new(255, 0, 0, 255));
+        context.set_shadow_color(ColorU::black());
+        context.set_shadow_blur(shadow_blur);
+        context.set_shadow_offset(vec2f(4.0, 4.0));
+        context.fill_rect(shadow_test_rect());
+
+        let mut expected = new_shadow_test_context();
+        expected.clip_path(clip_path, FillRule::Winding);
+        if shadow_blur > 0.0 {
+            expected.set_filter("blur(2px)".parse().unwrap());
+        }
+        expected.set_fill_style(ColorU::black());
+        expected.fill_rect(shadow_test_rect() + vec2f(4.0, 4.0));
+        expected.set_filter(Filter::default());
+        expected.set_fill_style(ColorU::new(255, 0, 0, 255));
+        expected.fill_rect(shadow_test_rect());
+        assert_pixels_close(&render_pixels(&context), &render_pixels(&expected));
+    }
+}
+
+#[test]
+pub fn test_shadows_of_transformed_paths() {
+    // The path is rotated, but the shadow offset stays in device space.
+    let transform = Transform2F::from_translation(vec2f(16.0, 16.0)) *
+        Transform2F::from_rotation(PI / 6.0) *
+        Transform2F::from_translation(vec2f(-16.0, -16.0));
+    let mut context = new_shadow_test_context();
+    context.set_transform(&transform);
+    context.set_fill_style(ColorU::new(255, 0, 0, 255));
+    context.set_shadow_color(ColorU::new(0, 0, 0, 128));
+    context.set_shadow_offset(vec2f(0.0, 5.0));
+    context.fill_rect(shadow_test_rect());
+
+    let mut expected = new_shadow_test_context();
+    expected.set_transform(&(Transform2F::from_translation(vec2f(0.0, 5.0)) * transform));
+    expected.set_fill_style(ColorU::new(0, 0, 0, 128));
+    expected.fill_rect(shadow_test_rect());
+    expected.set_transform(&transform);
+    expected.set_fill_style(ColorU::new(255, 0, 0, 255));
+    expected.fill_rect(shadow_test_rect());
+    assert_pixels_close(&render_pixels(&context), &render_pixels(&expected));
+}
+
+#[test]
+pub fn test_shadows_use_the_composite_operation() {
+    // The shadow and then the path are each composited with the current operation.
+    for &composite_operation in &[CompositeOperation::Lighter, CompositeOperation::Multiply] {
+        let mut context = new_shadow_test_context();
+        context.set_global_composite_operation(composite_operation);
+        context.set_fill_style(ColorU::new(255, 0, 0, 255));
+        context.set_shadow_color(ColorU::new(0, 96, 255, 255));
+        context.set_shadow_offset(vec2f(4.0, 4.0));
+        context.fill_rect(shadow_test_rect());
+
+        let mut expected = new_shadow_test_context();
+        expected.set_global_composite_operation(composite_operation);
+        expected.set_fill_style(ColorU::new(0, 96, 255, 255));
+        expected.fill_rect(shadow_test_rect() + vec2f(4.0, 4.0));
+        expected.set_fill_style(ColorU::new(255, 0, 0, 255));
+        expected.fill_rect(shadow_test_rect());
+        assert_pixels_close(&render_pixels(&context), &render_pixels(&expected));
+    }
+}
+
+#[cfg(feature = "pf-text")]
+#[test]
+pub fn test_text_shadows() {
+    let new_text_context = || {
+        let font_data = EmbeddedResourceLoader::new().slurp("fonts/Roboto-Regular.ttf").unwrap();
+        let font = Handle::from_memory(Arc::new(font_data), 0);
+        let mut context = Canvas::new(vec2f(32.0, 32.0))
+            .get_context_2d(CanvasFontContext::from_fonts(iter::once(font)));
+        context.set_fill_style(ColorU::white());
+        context.fill_rect(RectF::new(Vector2F::zero(), vec2f(32.0, 32.0)));
+        context.set_font_size(16.0);
+        context
+    };
+    let mut context = new_text_context();
+    context.set_fill_style(ColorU::new(255, 0, 0, 255));
+    context.set_shadow_color(ColorU::black());
+    context.set_shadow_offset(vec2f(2.0, 3.0));
+    context.fill_text("Hi", vec2f(4.0, 20.0));
+
+    let mut expected = new_text_context();
+    expected.set_fill_style(ColorU::black());
+    expected.fill_text("Hi", vec2f(6.0, 23.0));
+    expected.set_fill_style(ColorU::new(255, 0, 0, 255));
+    expected.fill_text("Hi", vec2f(4.0, 20.0));
+    let (pixels, expected_pixels) = (render_pixels(&context), render_pixels(&expected));
+    assert!(pixels.iter().any(|&pixel| pixel == ColorU::black()));
+    assert_pixels_close(&pixels, &expected_pixels);
+}
+
+#[test]
+pub fn test_non_scaling_stroke_width_ignores_the_transform() {
+    let mut context = new_context(vec2i(100, 100));
+    context.scale(3.0);
+    context.set_line_width(2.0);
+    context.set_line_dash(vec![4.0, 2.0]);
+    context.set_non_scaling_stroke(true);
+    context.stroke_rect(RectF::new(vec2f(1.0, 1.0), vec2f(10.0, 10.0)));
+
+    // The centerline is transformed, but the stroke is left in device pixels for the builder.
+    let scene = context.canvas().scene();
+    let draw_path = scene.get_draw_path(DrawPathId(0));
+    assert_eq!(draw_path.outline().bounds(), RectF::new(vec2f(3.0, 3.0), vec2f(30.0, 30.0)));
+    let device_stroke = draw_path.device_stroke().unwrap();
+    assert_eq!(device_stroke.style.line_width, 2.0);
+    assert_eq!(device_stroke.dashes, vec![4.0, 2.0]);
+
+    // Scaling strokes are converted to fills right away.
+    context.set_non_scaling_stroke(false);
+    context.stroke_rect(RectF::new(vec2f(1.0, 1.0), vec2f(10.0, 10.0)));
+    let draw_path = context.canvas().scene().get_draw_path(DrawPathId(1));
+    assert!(draw_path.device_stroke().is_none());
+}
+
+#[test]
+pub fn test_get_image_data_unpremultiplies() {
+    let size = vec2i(16, 16);
+    let mut renderer = Renderer::new(SoftwareDevice::new(size),
+                                     &EmbeddedResourceLoader::new(),
+                                     RendererMode::new(RendererLevel::D3D9),
+                                     RendererOptions {
+                                         dest: DestFramebuffer::full_window(size),
+                                         ..RendererOptions::default()
+                                     });
+    let mut context = new_context(size);
+    context.set_fill_style(ColorU::new(255, 0, 0, 128));
+    context.fill_rect(RectF::new(vec2f(0.0, 0.0), vec2f(8.0, 16.0)));
+
+    // The rect straddles the right edge of the canvas, which reads back as transparent black.
+    let rect = RectI::new(vec2i(4, 4), vec2i(16, 2));
+    let image_data = context.get_image_data(&mut renderer, rect).unwrap();
+    assert_eq!(image_data.size, vec2i(16, 2));
+    assert_eq!(image_data.data[0], ColorU::new(255, 0, 0, 128));
+    assert_eq!(image_data.data[16 + 3], ColorU::new(255, 0, 0, 128));
+    assert_eq!(image_data.data[4], ColorU::transparent_black());
+    assert_eq!(image_data.data[15], ColorU::transparent_black());
+
+    // The renderer's own destination is left alone.
+    match renderer.options().dest {
+        DestFramebuffer::Default { window_size, .. } => assert_eq!(window_size, size),
+        DestFramebuffer::Other(_) => panic!("The destination framebuffer wasn't restored!"),
+    }
+}
+
+#[test]
+pub fn test_get_image_data_normalizes_and_rejects_rects() {
+    let size = vec2i(16, 16);
+    let mut renderer = Renderer::new(SoftwareDevice::new(size),
+                                     &EmbeddedResourceLoader::new(),
+                                     RendererMode::new(RendererLevel::D3D9),
+                                     RendererOptions::default());
+    let mut context = new_context(size);
+    context.set_fill_style(ColorU::new(255, 0, 0, 255));
+    context.fill_rect(RectF::new(vec2f(0.0, 0.0), vec2f(8.0, 16.0)));
+
+    // A negative width and height extend the rect to the left and up.
+    let flipped = RectI::from_points(vec2i(10, 6), vec2i(6, 4));
+    let image_data = context.get_image_data(&mut renderer, flipped).unwrap();
+    assert_eq!(image_data.size, vec2i(4, 2));
+    assert_eq!(image_data.data[1], ColorU::new(255, 0, 0, 255));
+    assert_eq!(image_data.data[2], ColorU::transparent_black());
+
+    let empty = RectI::new(vec2i(4, 4), vec2i(0, 8));
+    assert_eq!(context.get_image_data(&mut renderer, empty).err(),
+               Some(ImageDataError::EmptyRect));
+    let huge = RectI::from_points(vec2i(i32::MIN, i32::MIN), vec2i(i32::MAX, i32::MAX));
+    assert_eq!(context.get_image_data(&mut renderer, huge).err(),
+               Some(ImageDataError::RectTooLarge));
+}
+
+#[cfg(feature = "pf-software")]
+#[test]
+pub fn test_get_image_data_cpu() {
+    let mut context = new_context(vec2i(16, 16));
+    context.set_fill_style(ColorU::new(0, 0, 255, 64));
+    context.fill_rect(RectF::new(vec2f(8.0, 0.0), vec2f(8.0, 16.0)));
+    let image_data = context.get_image_data_cpu(RectI::new(vec2i(6, 0), vec2i(4, 1))).unwrap();
+    assert_eq!(image_data.data[1], ColorU::transparent_black());
+    assert_eq!(image_data.data[2], ColorU::new(0, 0, 255, 64));
+}
+
+fn new_context(size: Vector2I) -> CanvasRenderingContext2D {
+    Canvas::new(size.to_f32()).get_context_2d(CanvasFontContext::from_system_source())
+}
+
+// Each shadow test draws a red square with a shadow and compares the result with the same picture
+// drawn by hand.
+fn shadow_test_rect() -> RectF {
+    RectF::new(vec2f(10.0, 10.0), vec2f(12.0, 12.0))
+}
+
+// A small canvas filled with opaque white, so that pixels read back without rounding from
+// unpremultiplying.
+fn new_shadow_test_context() -> CanvasRenderingContext2D {
+    let mut context = new_context(vec2i(32, 32));
+    context.set_fill_style(ColorU::white());
+    context.fill_rect(RectF::new(Vector2F::zero(), vec2f(32.0, 32.0)));
+    context
+}
+
+// Renders the canvas with the software device and returns its pixels.
+fn render_pixels(context: &CanvasRenderingContext2D) -> Vec<ColorU> {
+    let size = context.canvas().size();
+    let mut renderer = Renderer::new(SoftwareDevice::new(size),
+                                     &EmbeddedResourceLoader::new(),
+                                     RendererMode::new(RendererLevel::D3D9),
+                                     RendererOptions::default());
+    context.get_image_data(&mut renderer, RectI::new(Vector2I::zero(), size)).unwrap().data
+}
+
+fn assert_pixels_close(pixels: &[ColorU], expected_pixels: &[ColorU]) {
+    assert_eq!(pixels.len(), expected_pixels.len());
+    for (index, (pixel, expected_pixel)) in pixels.iter().zip(expected_pixels).enumerate() {
+        let channels = [pixel.r, pixel.g, pixel.b, pixel.a];
+        let expected_channels = [expected_pixel.r, expected_pixel.g, expected_pixel.b,
+                                 expected_pixel.a];
+        let close = channels.iter().zip(expected_channels.iter()).all(|(&value, &expected)| {
+            (value as i32 - expected as i32).abs() <= 1
+        });
+        assert!(close, "pixel {} was {:?}, expected {:?}", index, pixel, expected_pixel);
+    }
+}
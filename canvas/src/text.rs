@@ -8,23 +8,32 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use crate::{CanvasRenderingContext2D, State, TextAlign, TextBaseline};
+use crate::{CanvasRenderingContext2D, FontFamilyName, FontKerning, FontParseError};
+use crate::{FontShorthand, FontStretch, FontStyle, FontVariantCaps, State, TextAlign};
+use crate::{FilterFunction, TextBaseline, TextDirection, TextRendering, filter, push_shadow};
 use font_kit::canvas::RasterizationOptions;
 use font_kit::family_name::FamilyName;
 use font_kit::handle::Handle;
 use font_kit::hinting::HintingOptions;
 use font_kit::loaders::default::Font;
-use font_kit::properties::Properties;
+use font_kit::properties::{Properties, Stretch, Style, Weight};
 use font_kit::source::{Source, SystemSource};
 use font_kit::sources::mem::MemSource;
+use pathfinder_content::effects::BlendMode;
+use pathfinder_geometry::rect::RectF;
 use pathfinder_geometry::transform2d::Transform2F;
 use pathfinder_geometry::util;
 use pathfinder_geometry::vector::{Vector2F, vec2f};
-use pathfinder_renderer::paint::PaintId;
-use pathfinder_text::{FontContext, FontRenderOptions, TextRenderMode};
+use pathfinder_renderer::paint::{Paint, PaintId};
+use pathfinder_renderer::scene::{ClipPathId, Scene};
+use pathfinder_text::{FontContext, FontRenderOptions, FontVariation, SyntheticStyle};
+use pathfinder_text::TextRenderMode;
 use skribo::{FontCollection, FontFamily, FontRef, Layout as SkriboLayout, TextStyle};
 use std::borrow::Cow;
 use std::cell::{Cell, RefCell};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::iter;
 use std::rc::Rc;
 use std::sync::Arc;
 
@@ -36,9 +45,8 @@ impl CanvasRenderingContext2D {
     /// properties set at the time you called that function. This allows Pathfinder to skip having
     /// to lay out the text again.
     pub fn fill_text<T>(&mut self, text: &T, position: Vector2F) where T: ToTextLayout + ?Sized {
-        let paint = self.current_state.resolve_paint(&self.current_state.fill_paint);
-        let paint_id = self.canvas.scene.push_paint(&paint);
-        self.fill_or_stroke_text(text, position, paint_id, TextRenderMode::Fill);
+        let paint = self.current_state.resolve_paint(&self.current_state.fill_paint).into_owned();
+        self.fill_or_stroke_text(text, position, paint, TextRenderMode::Fill);
     }
 
     /// Strokes the given text using the current style.
@@ -48,10 +56,10 @@ impl CanvasRenderingContext2D {
     /// properties set at the time you called that function. This allows Pathfinder to skip having
     /// to lay out the text again.
     pub fn stroke_text<T>(&mut self, text: &T, position: Vector2F) where T: ToTextLayout + ?Sized {
-        let paint = self.current_state.resolve_paint(&self.current_state.stroke_paint);
-        let paint_id = self.canvas.scene.push_paint(&paint);
+        let paint = self.current_state.resolve_paint(&self.current_state.stroke_paint)
+                                      .into_owned();
         let render_mode = TextRenderMode::Stroke(self.current_state.resolve_stroke_style());
-        self.fill_or_stroke_text(text, position, paint_id, render_mode);
+        self.fill_or_stroke_text(text, position, paint, render_mode);
     }
 
     /// Returns metrics of the given text using the current style.
@@ -66,7 +74,7 @@ impl CanvasRenderingContext2D {
     fn fill_or_stroke_text<T>(&mut self,
                               text: &T,
                               mut position: Vector2F,
-                              paint_id: PaintId,
+                              paint: Paint,
                               render_mode: TextRenderMode)
                               where T: ToTextLayout + ?Sized {
         let layout = text.layout(CanvasState(&self.current_state));
@@ -77,22 +85,99 @@ impl CanvasRenderingContext2D {
         position += layout.text_origin();
         let transform = self.current_state.transform * Transform2F::from_translation(position);
 
-        // TODO(pcwalton): Report errors.
-        drop(self.canvas_font_context
-                 .0
-                 .borrow_mut()
-                 .font_context
-                 .push_layout(&mut self.canvas.scene,
-                              &layout.skribo_layout,
-                              &TextStyle { size: layout.font_size },
-                              &FontRenderOptions {
-                                  transform,
-                                  render_mode,
-                                  hinting_options: HintingOptions::None,
-                                  clip_path,
-                                  blend_mode,
-                                  paint_id,
-                              }));
+        let font_context = &self.canvas_font_context;
+        let (variations, synthetic_style) = (&self.current_state.font_variations,
+                                             self.current_state.synthetic_style);
+        let push_layout = |scene: &mut Scene,
+                           paint_id: PaintId,
+                           transform: Transform2F,
+                           clip_path: Option<ClipPathId>,
+                           blend_mode: BlendMode| {
+            let mut font_context = font_context.0.borrow_mut();
+            let small_caps_layout = layout.small_caps_layout.as_ref().map(|small_caps_layout| {
+                (&**small_caps_layout, small_caps_layout.size)
+            });
+            let skribo_layouts = iter::once((&*layout.skribo_layout, layout.font_size))
+                .chain(small_caps_layout);
+            for (skribo_layout, font_size) in skribo_layouts {
+                // TODO(pcwalton): Report errors.
+                drop(font_context.font_context
+                                 .push_layout(scene,
+                                              skribo_layout,
+                                              &TextStyle { size: font_size },
+                                              &FontRenderOptions {
+                                                  transform,
+                                                  render_mode,
+                                                  hinting_options: HintingOptions::None,
+                                                  clip_path,
+                                                  blend_mode,
+                                                  paint_id,
+                                                  variations,
+                                                  synthetic_style,
+                                              }));
+            }
+        };
+
+        // Glyphs can extend beyond their advances and the em box, so the bounds are padded
+        // generously; they only need to contain the text, not fit it tightly.
+        let font_size = layout.font_size;
+        let mut local_bounds = RectF::from_points(vec2f(-font_size, -font_size * 2.0),
+                                                  vec2f(layout.width() + font_size, font_size));
+        if let TextRenderMode::Stroke(ref stroke_style) = render_mode {
+            local_bounds = local_bounds.dilate(stroke_style.line_width * 0.5);
+        }
+        let bounds = transform * local_bounds;
+
+        if !self.current_state.filter.is_none() {
+            // As with paths, the filter is applied first, and then the shadow is drawn from the
+            // filtered image.
+            let mut filter = self.current_state.filter.clone();
+            if !self.current_state.shadow_color.is_fully_transparent() {
+                filter.push(FilterFunction::DropShadow {
+                    offset: self.current_state.shadow_offset,
+                    sigma: self.current_state.shadow_blur * 0.5,
+                    color: self.current_state.shadow_color,
+                });
+            }
+
+            let filter_info = match filter::push_filter_render_target(&mut self.canvas.scene,
+                                                                      &filter,
+                                                                      bounds) {
+                None => return,
+                Some(filter_info) => filter_info,
+            };
+
+            let translation = Transform2F::from_translation(-filter_info.bounds.origin().to_f32());
+            let mut paint = paint;
+            paint.apply_transform(&translation);
+            let paint_id = self.canvas.scene.push_paint(&paint);
+            push_layout(&mut self.canvas.scene,
+                        paint_id,
+                        translation * transform,
+                        None,
+                        BlendMode::SrcOver);
+
+            filter::composite_filter_render_target(&mut self.canvas.scene,
+                                                   &filter,
+                                                   filter_info,
+                                                   clip_path,
+                                                   blend_mode);
+            return;
+        }
+
+        if !self.current_state.shadow_color.is_fully_transparent() {
+            push_shadow(&mut self.canvas.scene,
+                        &self.current_state,
+                        &paint,
+                        bounds,
+                        |scene, shadow_paint_id, shadow_transform, clip_path, blend_mode| {
+                push_layout(scene, shadow_paint_id, shadow_transform * transform, clip_path,
+                            blend_mode)
+            });
+        }
+
+        let paint_id = self.canvas.scene.push_paint(&paint);
+        push_layout(&mut self.canvas.scene, paint_id, transform, clip_path, blend_mode);
     }
 
     // Text styles
@@ -105,7 +190,8 @@ impl CanvasRenderingContext2D {
     #[inline]
     pub fn set_font<FC>(&mut self, font_collection: FC) where FC: IntoFontCollection {
         let font_collection = font_collection.into_font_collection(&self.canvas_font_context);
-        self.current_state.font_collection = font_collection; 
+        self.current_state.font_collection = font_collection;
+        self.current_state.font_shorthand = None;
     }
 
     #[inline]
@@ -137,6 +223,136 @@ impl CanvasRenderingContext2D {
     pub fn set_text_baseline(&mut self, new_text_baseline: TextBaseline) {
         self.current_state.text_baseline = new_text_baseline;
     }
+
+    #[inline]
+    pub fn direction(&self) -> TextDirection {
+        self.current_state.direction
+    }
+
+    #[inline]
+    pub fn set_direction(&mut self, new_direction: TextDirection) {
+        self.current_state.direction = new_direction;
+    }
+
+    /// Returns the extra space added after each glyph, in pixels.
+    #[inline]
+    pub fn letter_spacing(&self) -> f32 {
+        self.current_state.letter_spacing
+    }
+
+    #[inline]
+    pub fn set_letter_spacing(&mut self, new_letter_spacing: f32) {
+        self.current_state.letter_spacing = new_letter_spacing;
+    }
+
+    /// Returns the extra space added after each space character, in pixels. This is in addition
+    /// to the letter spacing.
+    #[inline]
+    pub fn word_spacing(&self) -> f32 {
+        self.current_state.word_spacing
+    }
+
+    #[inline]
+    pub fn set_word_spacing(&mut self, new_word_spacing: f32) {
+        self.current_state.word_spacing = new_word_spacing;
+    }
+
+    #[inline]
+    pub fn font_kerning(&self) -> FontKerning {
+        self.current_state.font_kerning
+    }
+
+    /// Sets whether kerning is applied. Only kerning pairs in the `kern` table are supported,
+    /// unless the `shaping` feature is enabled, in which case the shaper always applies kerning.
+    #[inline]
+    pub fn set_font_kerning(&mut self, new_font_kerning: FontKerning) {
+        self.current_state.font_kerning = new_font_kerning;
+    }
+
+    #[inline]
+    pub fn font_stretch(&self) -> FontStretch {
+        self.current_state.font_stretch
+    }
+
+    /// Sets the font width. This only takes effect if the current font was selected with
+    /// `set_font_from_css()`, in which case the font is selected again with the new width.
+    pub fn set_font_stretch(&mut self, new_font_stretch: FontStretch) {
+        self.current_state.font_stretch = new_font_stretch;
+        if let Some(mut font_shorthand) = self.current_state.font_shorthand.take() {
+            font_shorthand.stretch = new_font_stretch;
+            let (font_collection, synthetic_style) =
+                self.canvas_font_context.select_font_collection(&font_shorthand);
+            self.current_state.font_collection = font_collection;
+            self.current_state.synthetic_style = synthetic_style;
+            self.current_state.font_shorthand = Some(font_shorthand);
+        }
+    }
+
+    #[inline]
+    pub fn font_variant_caps(&self) -> FontVariantCaps {
+        self.current_state.font_variant_caps
+    }
+
+    #[inline]
+    pub fn set_font_variant_caps(&mut self, new_font_variant_caps: FontVariantCaps) {
+        self.current_state.font_variant_caps = new_font_variant_caps;
+    }
+
+    #[inline]
+    pub fn text_rendering(&self) -> TextRendering {
+        self.current_state.text_rendering
+    }
+
+    #[inline]
+    pub fn set_text_rendering(&mut self, new_text_rendering: TextRendering) {
+        self.current_state.text_rendering = new_text_rendering;
+    }
+
+    /// Returns the variation axis values applied to variable fonts.
+    #[inline]
+    pub fn font_variation_settings(&self) -> &[FontVariation] {
+        &self.current_state.font_variations
+    }
+
+    /// Sets variation axis values for variable fonts, like CSS `font-variation-settings`. Axes
+    /// that the font doesn't have are ignored.
+    #[inline]
+    pub fn set_font_variation_settings(&mut self, new_font_variations: Vec<FontVariation>) {
+        self.current_state.font_variations = new_font_variations;
+    }
+
+    #[inline]
+    pub fn synthetic_style(&self) -> SyntheticStyle {
+        self.current_state.synthetic_style
+    }
+
+    /// Sets the bold and oblique styles to synthesize. `set_font_from_css()` sets these
+    /// automatically when the family has no bold or italic face.
+    #[inline]
+    pub fn set_synthetic_style(&mut self, new_synthetic_style: SyntheticStyle) {
+        self.current_state.synthetic_style = new_synthetic_style;
+    }
+
+    /// Sets the font, font size, stretch, and caps variant from a CSS `font` shorthand such as
+    /// `italic bold 16px Roboto, sans-serif`.
+    ///
+    /// The families are looked up in order in the font source of the font context, and the ones
+    /// that are found become the font collection, so later families are used as fallbacks. If
+    /// none are found, the default font is used. If the first family has no face with the
+    /// requested weight or style, bold and oblique are synthesized, as with CSS `font-synthesis`.
+    /// If the string can't be parsed, the state is left unchanged.
+    pub fn set_font_from_css(&mut self, font: &str) -> Result<(), FontParseError> {
+        let font_shorthand: FontShorthand = font.parse()?;
+        let (font_collection, synthetic_style) =
+            self.canvas_font_context.select_font_collection(&font_shorthand);
+        self.current_state.font_collection = font_collection;
+        self.current_state.synthetic_style = synthetic_style;
+        self.current_state.font_size = font_shorthand.size;
+        self.current_state.font_stretch = font_shorthand.stretch;
+        self.current_state.font_variant_caps = font_shorthand.variant_caps;
+        self.current_state.font_shorthand = Some(font_shorthand);
+        Ok(())
+    }
 }
 
 // Avoids leaking `State` to the outside.
@@ -152,13 +368,13 @@ pub trait ToTextLayout {
 
 impl ToTextLayout for str {
     fn layout(&self, state: CanvasState) -> Cow<TextMetrics> {
-        let skribo_layout = Rc::new(skribo::layout(&TextStyle { size: state.0.font_size },
-                                                   &state.0.font_collection,
-                                                   self));
-        Cow::Owned(TextMetrics::new(skribo_layout,
-                                    state.0.font_size,
-                                    state.0.text_align,
-                                    state.0.text_baseline))
+        let (skribo_layout, small_caps_layout) = lay_out_text(self, state.0);
+        let mut text_metrics = TextMetrics::new(Rc::new(skribo_layout),
+                                                state.0.font_size,
+                                                resolve_text_align(state.0),
+                                                state.0.text_baseline);
+        text_metrics.small_caps_layout = small_caps_layout.map(Rc::new);
+        Cow::Owned(text_metrics)
     }
 }
 
@@ -173,7 +389,7 @@ impl ToTextLayout for Rc<SkriboLayout> {
     fn layout(&self, state: CanvasState) -> Cow<TextMetrics> {
         Cow::Owned(TextMetrics::new((*self).clone(),
                                     state.0.font_size,
-                                    state.0.text_align,
+                                    resolve_text_align(state.0),
                                     state.0.text_baseline))
     }
 }
@@ -184,6 +400,213 @@ impl ToTextLayout for TextMetrics {
     }
 }
 
+// The lightest requested weight for which bold is synthesized, and the lightest font weight that
+// counts as bold.
+const SYNTHETIC_BOLD_MIN_WEIGHT: f32 = 600.0;
+
+// The size of synthesized small capitals relative to the font size.
+const SMALL_CAPS_SCALE: f32 = 0.7;
+
+// Lays out the text with kerning, letter and word spacing, and synthesized small capitals applied.
+// Since a layout has a single font size, small capitals are returned in a second layout.
+fn lay_out_text(text: &str, state: &State) -> (SkriboLayout, Option<SkriboLayout>) {
+    let font_collection = &*state.font_collection;
+    let empty_layout = |size| skribo::layout(&TextStyle { size }, font_collection, "");
+    let mut layout = empty_layout(state.font_size);
+    let mut small_caps_layout = None;
+
+    // With complex shaping enabled, the shaper applies kerning itself.
+    let kerning = match (state.font_kerning, state.text_rendering) {
+        _ if cfg!(feature = "shaping") => false,
+        (FontKerning::None, _) | (FontKerning::Auto, TextRendering::OptimizeSpeed) => false,
+        _ => true,
+    };
+    let mut kerning_tables = KerningTables::new();
+
+    // Each run is laid out separately and placed after the previous one. Spacing and kerning
+    // shift every glyph after them.
+    let (mut run_x, mut shift) = (0.0, 0.0);
+    let mut last_glyph: Option<(Arc<Font>, u32, bool)> = None;
+    for (run, small_caps) in split_caps_runs(text, state.font_variant_caps) {
+        let (run, size) = if small_caps {
+            (Cow::Owned(run.to_uppercase()), state.font_size * SMALL_CAPS_SCALE)
+        } else {
+            (Cow::Borrowed(run), state.font_size)
+        };
+        let run_layout = pathfinder_text::layout_with_variations(&TextStyle { size },
+                                                                 font_collection,
+                                                                 &run,
+                                                                 &state.font_variations);
+        let target_layout = if small_caps {
+            small_caps_layout.get_or_insert_with(|| empty_layout(size))
+        } else {
+            &mut layout
+        };
+
+        let mut run_width = 0.0;
+        for mut glyph in run_layout.glyphs {
+            let font = glyph.font.font.clone();
+            let scale_factor = size / font.metrics().units_per_em as f32;
+            if let Some((ref last_font, last_glyph_id, last_small_caps)) = last_glyph {
+                if kerning && last_small_caps == small_caps && Arc::ptr_eq(last_font, &font) {
+                    shift += kerning_tables.get(&font, last_glyph_id, glyph.glyph_id) *
+                        scale_factor;
+                }
+            }
+
+            let advance = pathfinder_text::glyph_advance(&font,
+                                                         glyph.glyph_id,
+                                                         &state.font_variations).x();
+            run_width = f32::max(run_width, glyph.offset.x() + advance * scale_factor);
+            glyph.offset = vec2f(run_x + shift + glyph.offset.x(), glyph.offset.y());
+            shift += state.letter_spacing;
+            if state.word_spacing != 0.0 && is_word_separator(&font, glyph.glyph_id) {
+                shift += state.word_spacing;
+            }
+
+            last_glyph = Some((font, glyph.glyph_id, small_caps));
+            target_layout.glyphs.push(glyph);
+        }
+        run_x += run_width;
+    }
+
+    (layout, small_caps_layout)
+}
+
+// Splits the text into runs that are and aren't drawn as synthesized small capitals.
+fn split_caps_runs(text: &str, font_variant_caps: FontVariantCaps) -> Vec<(&str, bool)> {
+    let is_small_caps = |c: char| {
+        match font_variant_caps {
+            FontVariantCaps::Normal | FontVariantCaps::TitlingCaps => false,
+            FontVariantCaps::SmallCaps | FontVariantCaps::PetiteCaps => c.is_lowercase(),
+            FontVariantCaps::AllSmallCaps | FontVariantCaps::AllPetiteCaps => c.is_alphabetic(),
+            FontVariantCaps::Unicase => c.is_uppercase(),
+        }
+    };
+
+    let (mut runs, mut run_start, mut run_small_caps) = (vec![], 0, None);
+    for (index, c) in text.char_indices() {
+        let small_caps = is_small_caps(c);
+        match run_small_caps {
+            Some(run_small_caps) if run_small_caps != small_caps => {
+                runs.push((&text[run_start..index], run_small_caps));
+                run_start = index;
+            }
+            _ => {}
+        }
+        run_small_caps = Some(small_caps);
+    }
+    if let Some(run_small_caps) = run_small_caps {
+        runs.push((&text[run_start..], run_small_caps));
+    }
+    runs
+}
+
+fn is_word_separator(font: &Font, glyph_id: u32) -> bool {
+    font.glyph_for_char(' ') == Some(glyph_id) || font.glyph_for_char('\u{a0}') == Some(glyph_id)
+}
+
+fn resolve_text_align(state: &State) -> TextAlign {
+    match (state.text_align, state.direction) {
+        (TextAlign::Start, TextDirection::Ltr) | (TextAlign::End, TextDirection::Rtl) => {
+            TextAlign::Left
+        }
+        (TextAlign::Start, TextDirection::Rtl) | (TextAlign::End, TextDirection::Ltr) => {
+            TextAlign::Right
+        }
+        (text_align, _) => text_align,
+    }
+}
+
+fn font_stretch_ratio(font_stretch: FontStretch) -> f32 {
+    match font_stretch {
+        FontStretch::UltraCondensed => Stretch::ULTRA_CONDENSED.0,
+        FontStretch::ExtraCondensed => Stretch::EXTRA_CONDENSED.0,
+        FontStretch::Condensed => Stretch::CONDENSED.0,
+        FontStretch::SemiCondensed => Stretch::SEMI_CONDENSED.0,
+        FontStretch::Normal => Stretch::NORMAL.0,
+        FontStretch::SemiExpanded => Stretch::SEMI_EXPANDED.0,
+        FontStretch::Expanded => Stretch::EXPANDED.0,
+        FontStretch::ExtraExpanded => Stretch::EXTRA_EXPANDED.0,
+        FontStretch::UltraExpanded => Stretch::ULTRA_EXPANDED.0,
+    }
+}
+
+// The OpenType `kern` table tag.
+const KERN_TABLE_TAG: u32 = 0x6b65726e;
+
+// Kerning pairs, loaded lazily for each font used in a layout.
+struct KerningTables {
+    tables: Vec<(Arc<Font>, HashMap<(u32, u32), i32>)>,
+}
+
+impl KerningTables {
+    fn new() -> KerningTables {
+        KerningTables { tables: vec![] }
+    }
+
+    // Returns the kerning between the two glyphs in font units.
+    fn get(&mut self, font: &Arc<Font>, left_glyph_id: u32, right_glyph_id: u32) -> f32 {
+        let position = self.tables.iter().position(|(table_font, _)| Arc::ptr_eq(table_font, font));
+        let index = match position {
+            Some(index) => index,
+            None => {
+                self.tables.push((font.clone(), load_kerning_pairs(font)));
+                self.tables.len() - 1
+            }
+        };
+        match self.tables[index].1.get(&(left_glyph_id, right_glyph_id)) {
+            None => 0.0,
+            Some(&kerning) => kerning as f32,
+        }
+    }
+}
+
+// Reads the horizontal format 0 subtables of the OpenType `kern` table. Apple's version of the
+// table and kerning in the `GPOS` table aren't supported.
+fn load_kerning_pairs(font: &Font) -> HashMap<(u32, u32), i32> {
+    let mut pairs = HashMap::new();
+    let table = match font.load_font_table(KERN_TABLE_TAG) {
+        None => return pairs,
+        Some(table) => table,
+    };
+    let read_u16 = |offset: usize| {
+        table.get(offset..(offset + 2)).map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+    };
+
+    if read_u16(0) != Some(0) {
+        return pairs;
+    }
+    let subtable_count = read_u16(2).unwrap_or(0);
+    let mut subtable_offset = 4;
+    for _ in 0..subtable_count {
+        let (length, coverage) = match (read_u16(subtable_offset + 2),
+                                        read_u16(subtable_offset + 4)) {
+            (Some(length), Some(coverage)) if length >= 6 => (length as usize, coverage),
+            _ => break,
+        };
+
+        // Skip minimum and cross-stream values, and formats other than 0.
+        if coverage & 0xff07 == 0x0001 {
+            let pair_count = read_u16(subtable_offset + 6).unwrap_or(0) as usize;
+            for pair_index in 0..pair_count {
+                let pair_offset = subtable_offset + 14 + pair_index * 6;
+                match (read_u16(pair_offset),
+                       read_u16(pair_offset + 2),
+                       read_u16(pair_offset + 4)) {
+                    (Some(left), Some(right), Some(value)) => {
+                        *pairs.entry((left as u32, right as u32)).or_insert(0) += value as i16 as i32;
+                    }
+                    _ => break,
+                }
+            }
+        }
+
+        subtable_offset += length;
+    }
+    pairs
+}
+
 #[cfg(feature = "pf-text")]
 #[derive(Clone)]
 pub struct CanvasFontContext(pub(crate) Rc<RefCell<CanvasFontContextData>>);
@@ -224,6 +647,55 @@ impl CanvasFontContext {
         CanvasFontContext::new(Arc::new(MemSource::from_fonts(fonts).unwrap()))
     }
 
+    // Selects the best match for each family in the shorthand from the font source. The default
+    // font is used if none of the families are found. Also returns the styles to synthesize if
+    // the font of the first family found lacks the requested weight or style.
+    fn select_font_collection(&self, font_shorthand: &FontShorthand)
+                              -> (Arc<FontCollection>, SyntheticStyle) {
+        let this = self.0.borrow();
+        let properties = Properties {
+            style: match font_shorthand.style {
+                FontStyle::Normal => Style::Normal,
+                FontStyle::Italic => Style::Italic,
+                FontStyle::Oblique => Style::Oblique,
+            },
+            weight: Weight(font_shorthand.weight),
+            stretch: Stretch(font_stretch_ratio(font_shorthand.stretch)),
+        };
+
+        let mut font_collection = FontCollection::new();
+        let mut synthetic_style = None;
+        for family_name in &font_shorthand.families {
+            let family_name = match *family_name {
+                FontFamilyName::Title(ref title) => FamilyName::Title(title.clone()),
+                FontFamilyName::Serif => FamilyName::Serif,
+                FontFamilyName::SansSerif => FamilyName::SansSerif,
+                FontFamilyName::Monospace => FamilyName::Monospace,
+                FontFamilyName::Cursive => FamilyName::Cursive,
+                FontFamilyName::Fantasy => FamilyName::Fantasy,
+            };
+            if let Ok(handle) = this.font_source.select_best_match(&[family_name], &properties) {
+                if let Ok(font) = handle.load() {
+                    if synthetic_style.is_none() {
+                        let font_properties = font.properties();
+                        synthetic_style = Some(SyntheticStyle {
+                            bold: properties.weight.0 >= SYNTHETIC_BOLD_MIN_WEIGHT &&
+                                font_properties.weight.0 < SYNTHETIC_BOLD_MIN_WEIGHT,
+                            oblique: properties.style != Style::Normal &&
+                                font_properties.style == Style::Normal,
+                        });
+                    }
+                    font_collection.add_family(FontFamily::new_from_font(font));
+                }
+            }
+        }
+
+        match synthetic_style {
+            None => (this.default_font_collection.clone(), SyntheticStyle::default()),
+            Some(synthetic_style) => (Arc::new(font_collection), synthetic_style),
+        }
+    }
+
     fn get_font_by_postscript_name(&self, postscript_name: &str) -> Font {
         let this = self.0.borrow();
         if let Some(cached_font) = this.font_context.get_cached_font(postscript_name) {
@@ -252,6 +724,8 @@ impl CanvasFontContext {
 #[derive(Clone)]
 pub struct TextMetrics {
     skribo_layout: Rc<SkriboLayout>,
+    // Synthesized small capitals, which are drawn at a smaller size than the rest of the text.
+    small_caps_layout: Option<Rc<SkriboLayout>>,
     font_size: f32,
     align: TextAlign,
     baseline: TextBaseline,
@@ -307,6 +781,7 @@ impl TextMetrics {
                -> TextMetrics {
         TextMetrics {
             skribo_layout,
+            small_caps_layout: None,
             font_size,
             align,
             baseline,
@@ -322,8 +797,8 @@ impl TextMetrics {
     pub fn text_x_offset(&self) -> f32 {
         if self.text_x_offset.get().is_none() {
             self.text_x_offset.set(Some(match self.align {
-                TextAlign::Left => 0.0,
-                TextAlign::Right => -self.width(),
+                TextAlign::Left | TextAlign::Start => 0.0,
+                TextAlign::Right | TextAlign::End => -self.width(),
                 TextAlign::Center => -0.5 * self.width(),
             }));
         }
@@ -354,14 +829,35 @@ impl TextMetrics {
         vec2f(self.text_x_offset(), self.text_y_offset())
     }
 
+    fn skribo_layouts(&self) -> impl Iterator<Item = &SkriboLayout> {
+        iter::once(&*self.skribo_layout).chain(self.small_caps_layout.as_ref().map(|layout| {
+            &**layout
+        }))
+    }
+
+    // Returns the layout containing the leftmost or rightmost glyph, and the index of that glyph.
+    fn outermost_glyph(&self, rightmost: bool) -> Option<(&SkriboLayout, usize)> {
+        let glyphs = self.skribo_layouts().filter(|layout| !layout.glyphs.is_empty()).map(|layout| {
+            (layout, if rightmost { layout.glyphs.len() - 1 } else { 0 })
+        });
+        let compare = |&(layout_a, index_a): &(&SkriboLayout, usize),
+                       &(layout_b, index_b): &(&SkriboLayout, usize)| {
+            let (x_a, x_b) = (layout_a.glyphs[index_a].offset.x(),
+                              layout_b.glyphs[index_b].offset.x());
+            x_a.partial_cmp(&x_b).unwrap_or(Ordering::Equal)
+        };
+        if rightmost { glyphs.max_by(compare) } else { glyphs.min_by(compare) }
+    }
+
     pub fn width(&self) -> f32 {
         if self.width.get().is_none() {
-            match self.skribo_layout.glyphs.last() {
+            match self.outermost_glyph(true) {
                 None => self.width.set(Some(0.0)),
-                Some(last_glyph) => {
+                Some((layout, index)) => {
+                    let last_glyph = &layout.glyphs[index];
                     let glyph_id = last_glyph.glyph_id;
                     let font_metrics = last_glyph.font.font.metrics();
-                    let scale_factor = self.skribo_layout.size / font_metrics.units_per_em as f32;
+                    let scale_factor = layout.size / font_metrics.units_per_em as f32;
                     let glyph_rect = last_glyph.font.font.typographic_bounds(glyph_id).unwrap();
                     self.width.set(Some(last_glyph.offset.x() +
                                         glyph_rect.max_x() * scale_factor));
@@ -374,7 +870,7 @@ impl TextMetrics {
 
     fn populate_vertical_metrics_if_necessary(&self) {
         if self.vertical_metrics.get().is_none() {
-            self.vertical_metrics.set(Some(VerticalMetrics::measure(&self.skribo_layout)));
+            self.vertical_metrics.set(Some(VerticalMetrics::measure(self.skribo_layouts())));
         }
     }
 
@@ -410,12 +906,13 @@ impl TextMetrics {
 
     pub fn actual_bounding_box_left(&self) -> f32 {
         if self.actual_left_extent.get().is_none() {
-            match self.skribo_layout.glyphs.get(0) {
+            match self.outermost_glyph(false) {
                 None => self.actual_left_extent.set(Some(0.0)),
-                Some(first_glyph) => {
+                Some((layout, index)) => {
+                    let first_glyph = &layout.glyphs[index];
                     let glyph_id = first_glyph.glyph_id;
                     let font_metrics = first_glyph.font.font.metrics();
-                    let scale_factor = self.skribo_layout.size / font_metrics.units_per_em as f32;
+                    let scale_factor = layout.size / font_metrics.units_per_em as f32;
                     let glyph_rect = first_glyph.font.font.raster_bounds(
                         glyph_id,
                         font_metrics.units_per_em as f32,
@@ -432,12 +929,13 @@ impl TextMetrics {
 
     pub fn actual_bounding_box_right(&self) -> f32 {
         if self.actual_right_extent.get().is_none() {
-            match self.skribo_layout.glyphs.last() {
+            match self.outermost_glyph(true) {
                 None => self.actual_right_extent.set(Some(0.0)),
-                Some(last_glyph) => {
+                Some((layout, index)) => {
+                    let last_glyph = &layout.glyphs[index];
                     let glyph_id = last_glyph.glyph_id;
                     let font_metrics = last_glyph.font.font.metrics();
-                    let scale_factor = self.skribo_layout.size / font_metrics.units_per_em as f32;
+                    let scale_factor = layout.size / font_metrics.units_per_em as f32;
                     let glyph_rect = last_glyph.font.font.raster_bounds(
                         glyph_id,
                         font_metrics.units_per_em as f32,
@@ -470,7 +968,8 @@ impl TextMetrics {
 }
 
 impl VerticalMetrics {
-    fn measure(skribo_layout: &SkriboLayout) -> VerticalMetrics {
+    fn measure<'a, I>(skribo_layouts: I) -> VerticalMetrics
+                      where I: Iterator<Item = &'a SkriboLayout> {
         let mut vertical_metrics = VerticalMetrics {
             font_bounding_box_ascent: 0.0,
             font_bounding_box_descent: 0.0,
@@ -483,42 +982,44 @@ impl VerticalMetrics {
             ideographic_baseline: 0.0,
         };
 
-        let mut last_font: Option<Arc<Font>> = None;
-        for glyph in &skribo_layout.glyphs {
-            match last_font {
-                Some(ref last_font) if Arc::ptr_eq(&last_font, &glyph.font.font) => {}
-                _ => {
-                    let font = glyph.font.font.clone();
-
-                    let font_metrics = font.metrics();
-                    let scale_factor = skribo_layout.size / font_metrics.units_per_em as f32;
-                    vertical_metrics.em_height_ascent =
-                        (font_metrics.ascent *
-                         scale_factor).max(vertical_metrics.em_height_ascent);
-                    vertical_metrics.em_height_descent =
-                        (font_metrics.descent *
-                         scale_factor).min(vertical_metrics.em_height_descent);
-                    vertical_metrics.font_bounding_box_ascent =
-                        (font_metrics.bounding_box.max_y() *
-                         scale_factor).max(vertical_metrics.font_bounding_box_ascent);
-                    vertical_metrics.font_bounding_box_descent =
-                        (font_metrics.bounding_box.min_y() *
-                         scale_factor).min(vertical_metrics.font_bounding_box_descent);
-
-                    last_font = Some(font);
+        for skribo_layout in skribo_layouts {
+            let mut last_font: Option<Arc<Font>> = None;
+            for glyph in &skribo_layout.glyphs {
+                match last_font {
+                    Some(ref last_font) if Arc::ptr_eq(&last_font, &glyph.font.font) => {}
+                    _ => {
+                        let font = glyph.font.font.clone();
+
+                        let font_metrics = font.metrics();
+                        let scale_factor = skribo_layout.size / font_metrics.units_per_em as f32;
+                        vertical_metrics.em_height_ascent =
+                            (font_metrics.ascent *
+                             scale_factor).max(vertical_metrics.em_height_ascent);
+                        vertical_metrics.em_height_descent =
+                            (font_metrics.descent *
+                             scale_factor).min(vertical_metrics.em_height_descent);
+                        vertical_metrics.font_bounding_box_ascent =
+                            (font_metrics.bounding_box.max_y() *
+                             scale_factor).max(vertical_metrics.font_bounding_box_ascent);
+                        vertical_metrics.font_bounding_box_descent =
+                            (font_metrics.bounding_box.min_y() *
+                             scale_factor).min(vertical_metrics.font_bounding_box_descent);
+
+                        last_font = Some(font);
+                    }
                 }
-            }
 
-            let font = last_font.as_ref().unwrap();
-            let glyph_rect = font.raster_bounds(glyph.glyph_id,
-                                                skribo_layout.size,
-                                                Transform2F::default(),
-                                                HintingOptions::None,
-                                                RasterizationOptions::GrayscaleAa).unwrap();
-            vertical_metrics.actual_bounding_box_ascent =
-                (glyph_rect.max_y() as f32).max(vertical_metrics.actual_bounding_box_ascent);
-            vertical_metrics.actual_bounding_box_descent =
-                (glyph_rect.min_y() as f32).min(vertical_metrics.actual_bounding_box_descent);
+                let font = last_font.as_ref().unwrap();
+                let glyph_rect = font.raster_bounds(glyph.glyph_id,
+                                                    skribo_layout.size,
+                                                    Transform2F::default(),
+                                                    HintingOptions::None,
+                                                    RasterizationOptions::GrayscaleAa).unwrap();
+                vertical_metrics.actual_bounding_box_ascent =
+                    (glyph_rect.max_y() as f32).max(vertical_metrics.actual_bounding_box_ascent);
+                vertical_metrics.actual_bounding_box_descent =
+                    (glyph_rect.min_y() as f32).min(vertical_metrics.actual_bounding_box_descent);
+            }
         }
 
         vertical_metrics
@@ -14,6 +14,7 @@ use std::fmt::{self, Debug, Formatter};
 use std::slice;
 
 pub mod matrix;
+pub mod space;
 
 // TODO(pcwalton): Maybe this should be a u32? Need to be aware of endianness issues if we do that.
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
@@ -170,6 +171,13 @@ impl ColorF {
         ColorF(self.0 + (other.0 - self.0) * F32x4::splat(t))
     }
 
+    /// Clamps all components to the range [0, 1], which is useful after converting out-of-gamut
+    /// colors between color spaces.
+    #[inline]
+    pub fn clamp(&self) -> ColorF {
+        ColorF(self.0.clamp(F32x4::default(), F32x4::splat(1.0)))
+    }
+
     #[inline]
     pub fn r(&self) -> f32 {
         self.0[0]
@@ -18,6 +18,17 @@ use std::ops::{Add, Mul, Deref};
 pub struct ColorMatrix(pub [F32x4; 5]);
 
 impl ColorMatrix {
+    /// Creates a color matrix that leaves colors unchanged.
+    #[inline]
+    pub fn identity() -> ColorMatrix {
+        ColorMatrix::from_rows([
+            [1.0, 0.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0, 0.0],
+        ])
+    }
+
     #[inline]
     pub fn from_rows(rows: [[f32; 5]; 4]) -> ColorMatrix {
         ColorMatrix([
@@ -73,6 +84,84 @@ impl ColorMatrix {
         a + b * saturation
     }
 
+    /// Creates a color matrix filter that multiplies the color channels by the given factor.
+    ///
+    /// See the `brightness()` function in the CSS Filter Effects specification.
+    pub fn brightness(amount: f32) -> ColorMatrix {
+        ColorMatrix::from_rows([
+            [amount, 0.0,    0.0,    0.0, 0.0],
+            [0.0,    amount, 0.0,    0.0, 0.0],
+            [0.0,    0.0,    amount, 0.0, 0.0],
+            [0.0,    0.0,    0.0,    1.0, 0.0],
+        ])
+    }
+
+    /// Creates a color matrix filter that scales the color channels around 0.5 by the given
+    /// factor.
+    ///
+    /// See the `contrast()` function in the CSS Filter Effects specification.
+    pub fn contrast(amount: f32) -> ColorMatrix {
+        let offset = 0.5 - 0.5 * amount;
+        ColorMatrix::from_rows([
+            [amount, 0.0,    0.0,    0.0, offset],
+            [0.0,    amount, 0.0,    0.0, offset],
+            [0.0,    0.0,    amount, 0.0, offset],
+            [0.0,    0.0,    0.0,    1.0, 0.0],
+        ])
+    }
+
+    /// Creates a grayscale color matrix filter with the given amount between 0 and 1.
+    ///
+    /// See the `grayscale()` function in the CSS Filter Effects specification.
+    pub fn grayscale(amount: f32) -> ColorMatrix {
+        let a = 1.0 - amount;
+        ColorMatrix::from_rows([
+            [0.2126 + 0.7874 * a, 0.7152 - 0.7152 * a, 0.0722 - 0.0722 * a, 0.0, 0.0],
+            [0.2126 - 0.2126 * a, 0.7152 + 0.2848 * a, 0.0722 - 0.0722 * a, 0.0, 0.0],
+            [0.2126 - 0.2126 * a, 0.7152 - 0.7152 * a, 0.0722 + 0.9278 * a, 0.0, 0.0],
+            [0.0,                 0.0,                 0.0,                 1.0, 0.0],
+        ])
+    }
+
+    /// Creates a sepia color matrix filter with the given amount between 0 and 1.
+    ///
+    /// See the `sepia()` function in the CSS Filter Effects specification.
+    pub fn sepia(amount: f32) -> ColorMatrix {
+        let a = 1.0 - amount;
+        ColorMatrix::from_rows([
+            [0.393 + 0.607 * a, 0.769 - 0.769 * a, 0.189 - 0.189 * a, 0.0, 0.0],
+            [0.349 - 0.349 * a, 0.686 + 0.314 * a, 0.168 - 0.168 * a, 0.0, 0.0],
+            [0.272 - 0.272 * a, 0.534 - 0.534 * a, 0.131 + 0.869 * a, 0.0, 0.0],
+            [0.0,               0.0,               0.0,               1.0, 0.0],
+        ])
+    }
+
+    /// Creates a color matrix filter that inverts the color channels by the given amount between
+    /// 0 and 1.
+    ///
+    /// See the `invert()` function in the CSS Filter Effects specification.
+    pub fn invert(amount: f32) -> ColorMatrix {
+        let scale = 1.0 - 2.0 * amount;
+        ColorMatrix::from_rows([
+            [scale, 0.0,   0.0,   0.0, amount],
+            [0.0,   scale, 0.0,   0.0, amount],
+            [0.0,   0.0,   scale, 0.0, amount],
+            [0.0,   0.0,   0.0,   1.0, 0.0],
+        ])
+    }
+
+    /// Creates a color matrix filter that multiplies alpha by the given amount between 0 and 1.
+    ///
+    /// See the `opacity()` function in the CSS Filter Effects specification.
+    pub fn opacity(amount: f32) -> ColorMatrix {
+        ColorMatrix::from_rows([
+            [1.0, 0.0, 0.0, 0.0,    0.0],
+            [0.0, 1.0, 0.0, 0.0,    0.0],
+            [0.0, 0.0, 1.0, 0.0,    0.0],
+            [0.0, 0.0, 0.0, amount, 0.0],
+        ])
+    }
+
     /// Creates a luminance-to-alpha color matrix filter.
     ///
     /// See the `luminanceToAlpha` attribute of the `feColorMatrix` element in the SVG
@@ -109,6 +198,26 @@ impl Add for ColorMatrix {
     }
 }
 
+/// Composes two color matrices. The resulting matrix applies `rhs` first, then `self`.
+impl Mul<ColorMatrix> for ColorMatrix {
+    type Output = ColorMatrix;
+
+    #[inline]
+    fn mul(self, rhs: ColorMatrix) -> ColorMatrix {
+        let transform_column = |column: F32x4| {
+            self[0] * column.xxxx() + self[1] * column.yyyy() + self[2] * column.zzzz() +
+                self[3] * column.wwww()
+        };
+        ColorMatrix([
+            transform_column(rhs[0]),
+            transform_column(rhs[1]),
+            transform_column(rhs[2]),
+            transform_column(rhs[3]),
+            transform_column(rhs[4]) + self[4],
+        ])
+    }
+}
+
 impl Mul<f32> for ColorMatrix {
     type Output = ColorMatrix;
 
@@ -0,0 +1,188 @@
+// pathfinder/color/src/space.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Color spaces and conversions between them.
+
+use crate::ColorF;
+
+/// The color space that the components of a color are expressed in.
+///
+/// All color spaces use the D65 white point. Alpha is never affected by conversions.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub enum ColorSpace {
+    /// The sRGB color space with the sRGB transfer function. This is the default, and matches the
+    /// way colors are specified in CSS.
+    Srgb,
+    /// The sRGB primaries without the transfer function; i.e. linear light.
+    LinearSrgb,
+    /// The Display P3 color space: DCI-P3 primaries with the sRGB transfer function.
+    DisplayP3,
+}
+
+impl Default for ColorSpace {
+    #[inline]
+    fn default() -> ColorSpace {
+        ColorSpace::Srgb
+    }
+}
+
+// Linear Display P3 to linear sRGB, row-major.
+const LINEAR_DISPLAY_P3_TO_LINEAR_SRGB: [[f32; 3]; 3] = [
+    [ 1.2249401, -0.2249404,  0.0000000],
+    [-0.0420569,  1.0420571,  0.0000000],
+    [-0.0196376, -0.0786361,  1.0982735],
+];
+
+// Linear sRGB to linear Display P3, row-major.
+const LINEAR_SRGB_TO_LINEAR_DISPLAY_P3: [[f32; 3]; 3] = [
+    [0.8224621, 0.1775380, 0.0000000],
+    [0.0331941, 0.9668058, 0.0000000],
+    [0.0170827, 0.0723974, 0.9105199],
+];
+
+impl ColorSpace {
+    /// Converts a color whose components are in this color space to linear sRGB.
+    ///
+    /// The result may lie outside the [0, 1] range if the color is outside the sRGB gamut.
+    pub fn to_linear_srgb(self, color: ColorF) -> ColorF {
+        match self {
+            ColorSpace::LinearSrgb => color,
+            ColorSpace::Srgb => map_rgb(color, srgb_to_linear),
+            ColorSpace::DisplayP3 => {
+                transform_rgb(&LINEAR_DISPLAY_P3_TO_LINEAR_SRGB, map_rgb(color, srgb_to_linear))
+            }
+        }
+    }
+
+    /// Converts a linear sRGB color to this color space.
+    pub fn from_linear_srgb(self, color: ColorF) -> ColorF {
+        match self {
+            ColorSpace::LinearSrgb => color,
+            ColorSpace::Srgb => map_rgb(color, linear_to_srgb),
+            ColorSpace::DisplayP3 => {
+                map_rgb(transform_rgb(&LINEAR_SRGB_TO_LINEAR_DISPLAY_P3, color), linear_to_srgb)
+            }
+        }
+    }
+
+    /// Converts a color from this color space to another one.
+    #[inline]
+    pub fn convert(self, color: ColorF, to: ColorSpace) -> ColorF {
+        if self == to {
+            color
+        } else {
+            to.from_linear_srgb(self.to_linear_srgb(color))
+        }
+    }
+}
+
+impl ColorF {
+    /// Converts this color from the given color space to another one.
+    #[inline]
+    pub fn convert(&self, from: ColorSpace, to: ColorSpace) -> ColorF {
+        from.convert(*self, to)
+    }
+
+    /// Converts this linear sRGB color to OKLab. The L, a, and b components are stored in the red,
+    /// green, and blue channels respectively.
+    ///
+    /// See https://bottosson.github.io/posts/oklab/.
+    pub fn linear_srgb_to_oklab(&self) -> ColorF {
+        let (r, g, b) = (self.r(), self.g(), self.b());
+        let l = f32::cbrt(0.4122214708 * r + 0.5363325363 * g + 0.0514459929 * b);
+        let m = f32::cbrt(0.2119034982 * r + 0.6806995451 * g + 0.1073969566 * b);
+        let s = f32::cbrt(0.0883024619 * r + 0.2817188376 * g + 0.6299787005 * b);
+        ColorF::new(0.2104542553 * l + 0.7936177850 * m - 0.0040720468 * s,
+                    1.9779984951 * l - 2.4285922050 * m + 0.4505937099 * s,
+                    0.0259040371 * l + 0.7827717662 * m - 0.8086757660 * s,
+                    self.a())
+    }
+
+    /// Converts this OKLab color, as produced by `linear_srgb_to_oklab()`, back to linear sRGB.
+    pub fn oklab_to_linear_srgb(&self) -> ColorF {
+        let (l, a, b) = (self.r(), self.g(), self.b());
+        let l_ = l + 0.3963377774 * a + 0.2158037573 * b;
+        let m_ = l - 0.1055613458 * a - 0.0638541728 * b;
+        let s_ = l - 0.0894841775 * a - 1.2914855480 * b;
+        let (l, m, s) = (l_ * l_ * l_, m_ * m_ * m_, s_ * s_ * s_);
+        ColorF::new( 4.0767416621 * l - 3.3077115913 * m + 0.2309699292 * s,
+                    -1.2684380046 * l + 2.6097574011 * m - 0.3413193965 * s,
+                    -0.0041960863 * l - 0.7034186147 * m + 1.7076147010 * s,
+                    self.a())
+    }
+}
+
+/// Applies the inverse sRGB transfer function to a single component.
+#[inline]
+pub fn srgb_to_linear(value: f32) -> f32 {
+    let magnitude = f32::abs(value);
+    let linear = if magnitude <= 0.04045 {
+        magnitude / 12.92
+    } else {
+        f32::powf((magnitude + 0.055) / 1.055, 2.4)
+    };
+    f32::copysign(linear, value)
+}
+
+/// Applies the sRGB transfer function to a single linear component.
+#[inline]
+pub fn linear_to_srgb(value: f32) -> f32 {
+    let magnitude = f32::abs(value);
+    let encoded = if magnitude <= 0.0031308 {
+        magnitude * 12.92
+    } else {
+        1.055 * f32::powf(magnitude, 1.0 / 2.4) - 0.055
+    };
+    f32::copysign(encoded, value)
+}
+
+fn map_rgb(color: ColorF, f: fn(f32) -> f32) -> ColorF {
+    ColorF::new(f(color.r()), f(color.g()), f(color.b()), color.a())
+}
+
+fn transform_rgb(matrix: &[[f32; 3]; 3], color: ColorF) -> ColorF {
+    let rgb = [color.r(), color.g(), color.b()];
+    let row = |i: usize| matrix[i][0] * rgb[0] + matrix[i][1] * rgb[1] + matrix[i][2] * rgb[2];
+    ColorF::new(row(0), row(1), row(2), color.a())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::ColorF;
+    use super::ColorSpace;
+
+    fn assert_close(a: ColorF, b: ColorF) {
+        for i in 0..4 {
+            assert!(f32::abs(a.0[i] - b.0[i]) < 0.001, "{:?} != {:?}", a, b);
+        }
+    }
+
+    #[test]
+    fn round_trip_conversions() {
+        let color = ColorF::new(0.8, 0.3, 0.1, 0.5);
+        for &space in &[ColorSpace::Srgb, ColorSpace::LinearSrgb, ColorSpace::DisplayP3] {
+            assert_close(space.from_linear_srgb(space.to_linear_srgb(color)), color);
+        }
+        let linear = ColorSpace::Srgb.to_linear_srgb(color);
+        assert_close(linear.linear_srgb_to_oklab().oklab_to_linear_srgb(), linear);
+    }
+
+    #[test]
+    fn known_values() {
+        assert_close(ColorSpace::Srgb.to_linear_srgb(ColorF::new(0.5, 1.0, 0.0, 1.0)),
+                     ColorF::new(0.214, 1.0, 0.0, 1.0));
+        // Pure P3 red lies outside the sRGB gamut.
+        let p3_red = ColorSpace::DisplayP3.convert(ColorF::new(1.0, 0.0, 0.0, 1.0),
+                                                  ColorSpace::Srgb);
+        assert!(p3_red.r() > 1.0 && p3_red.g() < 0.0);
+        // OKLab lightness of white is 1.
+        assert_close(ColorF::white().linear_srgb_to_oklab(), ColorF::new(1.0, 0.0, 0.0, 1.0));
+    }
+}
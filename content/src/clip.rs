@@ -43,11 +43,47 @@ impl TEdge for Edge {
         }
         results
     }
+
+    // Multiplying the rational form of the conic through by its denominator, which is positive,
+    // leaves a quadratic in Bernstein form whose roots are the intersections. These are found
+    // exactly, in the parameterization of the conic itself.
+    fn intersect_conic_segment(&self, segment: &Segment) -> ArrayVec<[f32; 3]> {
+        let (origin, vector) = (self.0.from(), self.0.vector());
+        let d0 = vector.det(segment.baseline.from() - origin);
+        let d1 = vector.det(segment.ctrl.from() - origin) * segment.weight;
+        let d2 = vector.det(segment.baseline.to() - origin);
+        let (a, b, c) = (d0 - 2.0 * d1 + d2, 2.0 * (d1 - d0), d0);
+
+        let mut roots: ArrayVec<[f32; 2]> = ArrayVec::new();
+        if a.abs() <= EPSILON * (d0.abs() + d1.abs() + d2.abs()) {
+            if b != 0.0 {
+                roots.push(-c / b);
+            }
+        } else {
+            let discriminant = b * b - 4.0 * a * c;
+            if discriminant >= 0.0 {
+                // Avoid catastrophic cancellation. See "Numerical Recipes", section 5.6.
+                let q = -0.5 * (b + b.signum() * discriminant.sqrt());
+                roots.push(q / a);
+                if q != 0.0 {
+                    roots.push(c / q);
+                }
+            }
+        }
+
+        let mut results: ArrayVec<[f32; 3]> =
+            roots.into_iter().filter(|t| (0.0..=1.0).contains(t)).collect();
+        results.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        return results;
+
+        const EPSILON: f32 = 0.000001;
+    }
 }
 
 trait TEdge: Debug {
     fn point_is_inside(&self, point: Vector2F) -> bool;
     fn intersect_line_segment(&self, segment: LineSegment2F) -> ArrayVec<[f32; 3]>;
+    fn intersect_conic_segment(&self, segment: &Segment) -> ArrayVec<[f32; 3]>;
 
     fn trivially_test_segment(&self, segment: &Segment) -> EdgeRelativeLocation {
         let from_inside = self.point_is_inside(segment.baseline.from());
@@ -64,7 +100,7 @@ trait TEdge: Debug {
             if from_inside != self.point_is_inside(segment.ctrl.from()) {
                 return EdgeRelativeLocation::Intersecting;
             }
-            if !segment.is_quadratic() {
+            if !segment.has_single_control_point() {
                 if from_inside != self.point_is_inside(segment.ctrl.to()) {
                     return EdgeRelativeLocation::Intersecting;
                 }
@@ -81,6 +117,9 @@ trait TEdge: Debug {
         if segment.is_line() {
             return self.intersect_line_segment(segment.baseline);
         }
+        if segment.is_conic() {
+            return self.intersect_conic_segment(segment);
+        }
 
         let mut segment = *segment;
         if segment.is_quadratic() {
@@ -180,7 +219,9 @@ where
         }
     }
 
-    fn clip_segment_against(&mut self, mut segment: Segment, edge: &Self::Edge) {
+    fn clip_segment_against(&mut self, original_segment: Segment, edge: &Self::Edge) {
+        let mut segment = original_segment;
+
         // Easy cases.
         match edge.trivially_test_segment(&segment) {
             EdgeRelativeLocation::Outside => return,
@@ -199,7 +240,14 @@ where
         let mut last_t = 0.0;
         debug!("... intersections: {:?}", intersection_ts);
         for t in intersection_ts {
-            let (before_split, after_split) = segment.split((t - last_t) / (1.0 - last_t));
+            // Splitting a conic puts the halves in standard form, which changes their
+            // parameterization, so cut pieces out of the original conic instead.
+            let (before_split, after_split) = if original_segment.is_conic() {
+                let conic = original_segment.as_conic_segment();
+                (conic.subsegment(last_t, t), conic.subsegment(t, 1.0))
+            } else {
+                segment.split((t - last_t) / (1.0 - last_t))
+            };
 
             // Push the split segment if appropriate.
             debug!(
@@ -572,3 +620,39 @@ bitflags! {
         const BOTTOM = 0x08;
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::clip::ContourPolygonClipper;
+    use crate::outline::{Contour, ContourIterFlags};
+    use pathfinder_geometry::transform2d::Transform2F;
+    use pathfinder_geometry::vector::{Vector2F, vec2f};
+
+    #[test]
+    fn clipped_arcs_end_on_the_clip_edge() {
+        let mut ellipse = Contour::new();
+        ellipse.push_ellipse(&Transform2F::from_scale(vec2f(2.0, 1.0)));
+        let clip_polygon = [vec2f(-3.0, -3.0), vec2f(1.0, -3.0), vec2f(1.0, 3.0), vec2f(-3.0, 3.0)];
+        let clipped = ContourPolygonClipper::new(&clip_polygon, ellipse).clip();
+
+        // Every conic left must still lie on the ellipse and inside the clip polygon, and two of
+        // them must end exactly on the clip edge x = 1.
+        let on_ellipse = |point: Vector2F| ((point * vec2f(0.5, 1.0)).length() - 1.0).abs() < 0.001;
+        let mut endpoints_on_edge = 0;
+        for segment in clipped.iter(ContourIterFlags::empty()) {
+            if !segment.is_conic() {
+                continue;
+            }
+            for step in 0..17 {
+                let point = segment.as_conic_segment().sample(step as f32 / 16.0);
+                assert!(on_ellipse(point) && point.x() <= 1.001, "{:?} {:?}", segment, point);
+            }
+            for &point in &[segment.baseline.from(), segment.baseline.to()] {
+                if (point.x() - 1.0).abs() < 0.001 {
+                    endpoints_on_edge += 1;
+                }
+            }
+        }
+        assert_eq!(endpoints_on_edge, 2);
+    }
+}
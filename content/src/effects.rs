@@ -55,6 +55,16 @@ pub enum Filter {
         uv_origin: Vector2F,
     },
 
+    /// Converts a linear gradient to a conic one.
+    ConicGradient {
+        /// The center of the gradient.
+        center: Vector2F,
+        /// The angle, in radians, at which the gradient starts.
+        angle: f32,
+        /// The origin of the linearized gradient in the texture.
+        uv_origin: Vector2F,
+    },
+
     /// One of the `PatternFilter` filters.
     PatternFilter(PatternFilter),
 }
@@ -11,25 +11,29 @@
 //! Gradient effects that paths can be filled with.
 
 use crate::util;
-use pathfinder_color::ColorU;
+use pathfinder_color::space::ColorSpace;
+use pathfinder_color::{ColorF, ColorU};
 use pathfinder_geometry::line_segment::LineSegment2F;
+use pathfinder_geometry::rect::RectF;
 use pathfinder_geometry::transform2d::Transform2F;
-use pathfinder_geometry::vector::Vector2F;
+use pathfinder_geometry::vector::{Vector2F, Vector2I, vec2f};
 use pathfinder_geometry::util as geometry_util;
-use pathfinder_simd::default::F32x2;
+use pathfinder_simd::default::{F32x2, F32x4};
 use std::cmp::Ordering;
 use std::convert;
 use std::hash::{Hash, Hasher};
 use std::mem;
 
-/// A gradient, either linear or radial.
+/// A gradient: linear, radial, conic, or mesh.
 #[derive(Clone, PartialEq, Debug)]
 pub struct Gradient {
-    /// Information specific to the type of gradient (linear or radial).
+    /// Information specific to the type of gradient (linear, radial, conic, or mesh).
     pub geometry: GradientGeometry,
     stops: Vec<ColorStop>,
     /// What should be rendered upon reaching the end of the color stops.
     pub wrap: GradientWrap,
+    /// How colors between the stops are computed.
+    pub interpolation: GradientInterpolation,
 }
 
 /// A color in a gradient. Points in a gradient between two stops interpolate linearly between the
@@ -43,7 +47,7 @@ pub struct ColorStop {
     pub color: ColorU,
 }
 
-/// The type of gradient: linear or radial.
+/// The type of gradient: linear, radial, conic, or mesh.
 #[derive(Clone, PartialEq, Debug)]
 pub enum GradientGeometry {
     /// A linear gradient that follows a line.
@@ -67,7 +71,47 @@ pub enum GradientGeometry {
         /// Like `gradientTransform` in SVG. Note that this is the inverse of Cairo's gradient
         /// transform.
         transform: Transform2F,
-    }
+    },
+    /// A conic gradient, also known as a sweep gradient, whose colors sweep around a center
+    /// point, like CSS `conic-gradient()` and canvas `createConicGradient()`.
+    Conic {
+        /// The center of the gradient.
+        ///
+        /// This is in scene coordinates, not relative to the bounding box of the path.
+        center: Vector2F,
+        /// The angle, in radians, at which the gradient starts, measured clockwise from the
+        /// positive x axis. (Clockwise assumes that the y axis points down.)
+        angle: f32,
+        /// Transform from conic gradient space into screen space.
+        transform: Transform2F,
+    },
+    /// A mesh of Coons or tensor-product patches, each with a color at each corner, like PDF
+    /// shading types 6 and 7.
+    ///
+    /// The color stops and wrap mode of the gradient are ignored. Areas outside the mesh are
+    /// transparent.
+    Mesh(MeshGradient),
+}
+
+/// A mesh gradient, made up of patches that are painted in order.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct MeshGradient {
+    patches: Vec<MeshPatch>,
+}
+
+/// A tensor-product bicubic Bézier patch with a color at each corner.
+///
+/// Colors are interpolated bilinearly in the parametric space of the patch.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct MeshPatch {
+    /// The control points of the patch, in scene coordinates.
+    ///
+    /// The point at index `i * 4 + j` is the control point that PDF calls p<sub>ij</sub>, which
+    /// is weighted by the Bernstein polynomials B<sub>i</sub>(u) B<sub>j</sub>(v).
+    pub points: [Vector2F; 16],
+    /// The colors of the corners p<sub>00</sub>, p<sub>03</sub>, p<sub>33</sub>, and
+    /// p<sub>30</sub>, in that order.
+    pub colors: [ColorU; 4],
 }
 
 /// What should be rendered outside the color stops.
@@ -80,6 +124,19 @@ pub enum GradientWrap {
     Repeat,
 }
 
+/// How a gradient interpolates between its color stops.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum GradientInterpolation {
+    /// Interpolates the gamma-encoded components of the stops directly, as CSS and canvas do by
+    /// default.
+    Encoded,
+    /// Interpolates in linear light. This avoids the dark bands that appear between saturated
+    /// colors when interpolating encoded components.
+    Linear,
+    /// Interpolates in the OKLab perceptual color space, like CSS `in oklab`.
+    Oklab,
+}
+
 impl Eq for Gradient {}
 
 impl Hash for Gradient {
@@ -101,8 +158,31 @@ impl Hash for Gradient {
                 util::hash_f32(transform.m22(), state);
                 util::hash_f32(transform.m23(), state);
             }
+            GradientGeometry::Conic { center, angle, transform } => {
+                (2).hash(state);
+                util::hash_f32(center.x(), state);
+                util::hash_f32(center.y(), state);
+                util::hash_f32(angle, state);
+                util::hash_f32(transform.m11(), state);
+                util::hash_f32(transform.m12(), state);
+                util::hash_f32(transform.m13(), state);
+                util::hash_f32(transform.m21(), state);
+                util::hash_f32(transform.m22(), state);
+                util::hash_f32(transform.m23(), state);
+            }
+            GradientGeometry::Mesh(ref mesh) => {
+                (3).hash(state);
+                for patch in &mesh.patches {
+                    for point in &patch.points {
+                        util::hash_f32(point.x(), state);
+                        util::hash_f32(point.y(), state);
+                    }
+                    patch.colors.hash(state);
+                }
+            }
         }
         self.stops.hash(state);
+        self.interpolation.hash(state);
     }
 }
 
@@ -128,6 +208,7 @@ impl Gradient {
             geometry: GradientGeometry::Linear(line),
             stops: Vec::new(),
             wrap: GradientWrap::Clamp,
+            interpolation: GradientInterpolation::Encoded,
         }
     }
 
@@ -151,6 +232,35 @@ impl Gradient {
             geometry: GradientGeometry::Radial { line: line.to_line(), radii, transform },
             stops: Vec::new(),
             wrap: GradientWrap::Clamp,
+            interpolation: GradientInterpolation::Encoded,
+        }
+    }
+
+    /// Creates a new conic gradient centered at `center` that starts at `angle` radians, measured
+    /// clockwise from the positive x axis.
+    ///
+    /// The center is in scene coordinates, not relative to the bounding box of the current path.
+    #[inline]
+    pub fn conic(center: Vector2F, angle: f32) -> Gradient {
+        let transform = Transform2F::default();
+        Gradient {
+            geometry: GradientGeometry::Conic { center, angle, transform },
+            stops: Vec::new(),
+            wrap: GradientWrap::Clamp,
+            interpolation: GradientInterpolation::Encoded,
+        }
+    }
+
+    /// Creates a new mesh gradient from the given patches.
+    ///
+    /// Mesh gradients have no color stops; colors come from the corners of the patches instead.
+    #[inline]
+    pub fn mesh(mesh: MeshGradient) -> Gradient {
+        Gradient {
+            geometry: GradientGeometry::Mesh(mesh),
+            stops: Vec::new(),
+            wrap: GradientWrap::Clamp,
+            interpolation: GradientInterpolation::Encoded,
         }
     }
 
@@ -184,10 +294,21 @@ impl Gradient {
 
     /// Returns the value of the gradient at offset `t`, which will be clamped between 0.0 and 1.0.
     ///
+    /// The stops are assumed to be in sRGB.
+    #[inline]
+    pub fn sample(&self, t: f32) -> ColorU {
+        self.sample_in_color_space(t, ColorSpace::Srgb).clamp().to_u8()
+    }
+
+    /// Returns the value of the gradient at offset `t`, which will be clamped between 0.0 and 1.0,
+    /// interpolating according to `interpolation`.
+    ///
+    /// The stops and the result are in `color_space`.
+    ///
     /// FIXME(pcwalton): This should probably take `wrap` into account…
-    pub fn sample(&self, mut t: f32) -> ColorU {
+    pub fn sample_in_color_space(&self, mut t: f32, color_space: ColorSpace) -> ColorF {
         if self.stops.is_empty() {
-            return ColorU::transparent_black();
+            return ColorF::transparent_black();
         }
 
         t = geometry_util::clamp(t, 0.0, 1.0);
@@ -203,24 +324,50 @@ impl Gradient {
 
         let denom = upper_stop.offset - lower_stop.offset;
         if denom == 0.0 {
-            return lower_stop.color;
+            return lower_stop.color.to_f32();
         }
 
         let ratio = ((t - lower_stop.offset) / denom).min(1.0);
-        lower_stop.color.to_f32().lerp(upper_stop.color.to_f32(), ratio).to_u8()
+        let (lower_color, upper_color) = (lower_stop.color.to_f32(), upper_stop.color.to_f32());
+        match self.interpolation {
+            GradientInterpolation::Encoded => lower_color.lerp(upper_color, ratio),
+            GradientInterpolation::Linear => {
+                let lower_color = color_space.to_linear_srgb(lower_color);
+                let upper_color = color_space.to_linear_srgb(upper_color);
+                color_space.from_linear_srgb(lower_color.lerp(upper_color, ratio))
+            }
+            GradientInterpolation::Oklab => {
+                let lower_color = color_space.to_linear_srgb(lower_color).linear_srgb_to_oklab();
+                let upper_color = color_space.to_linear_srgb(upper_color).linear_srgb_to_oklab();
+                let color = lower_color.lerp(upper_color, ratio).oklab_to_linear_srgb();
+                color_space.from_linear_srgb(color)
+            }
+        }
     }
 
     /// Returns true if all colors of all stops in this gradient are opaque (alpha is 1.0).
+    ///
+    /// Mesh gradients are never considered opaque, since they may not cover the whole path.
     #[inline]
     pub fn is_opaque(&self) -> bool {
-        self.stops.iter().all(|stop| stop.color.is_opaque())
+        match self.geometry {
+            GradientGeometry::Mesh(_) => false,
+            _ => self.stops.iter().all(|stop| stop.color.is_opaque()),
+        }
     }
 
     /// Returns true if all colors of all stops in this gradient are fully transparent (alpha is
     /// 0.0).
     #[inline]
     pub fn is_fully_transparent(&self) -> bool {
-        self.stops.iter().all(|stop| stop.color.is_fully_transparent())
+        match self.geometry {
+            GradientGeometry::Mesh(ref mesh) => {
+                mesh.patches.iter().all(|patch| {
+                    patch.colors.iter().all(|color| color.is_fully_transparent())
+                })
+            }
+            _ => self.stops.iter().all(|stop| stop.color.is_fully_transparent()),
+        }
     }
 
     /// Applies the given affine transform to this gradient.
@@ -234,9 +381,243 @@ impl Gradient {
 
         match self.geometry {
             GradientGeometry::Linear(ref mut line) => *line = new_transform * *line,
-            GradientGeometry::Radial { ref mut transform, .. } => {
+            GradientGeometry::Radial { ref mut transform, .. } |
+            GradientGeometry::Conic { ref mut transform, .. } => {
                 *transform = new_transform * *transform
             }
+            GradientGeometry::Mesh(ref mut mesh) => mesh.apply_transform(new_transform),
+        }
+    }
+}
+
+impl MeshGradient {
+    /// Creates a new empty mesh gradient.
+    #[inline]
+    pub fn new() -> MeshGradient {
+        MeshGradient::default()
+    }
+
+    /// Adds a patch to the mesh. Patches added later are painted over patches added earlier.
+    #[inline]
+    pub fn push_patch(&mut self, patch: MeshPatch) {
+        self.patches.push(patch)
+    }
+
+    /// Returns the patches in this mesh.
+    #[inline]
+    pub fn patches(&self) -> &[MeshPatch] {
+        &self.patches
+    }
+
+    /// Returns true if this mesh has no patches.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.patches.is_empty()
+    }
+
+    /// Returns a rectangle that encloses every control point of every patch, and therefore the
+    /// whole mesh.
+    pub fn bounds(&self) -> RectF {
+        let mut points = self.patches.iter().flat_map(|patch| patch.points.iter());
+        let first = match points.next() {
+            None => return RectF::default(),
+            Some(&first) => first,
+        };
+        points.fold(RectF::from_points(first, first), |bounds, &point| {
+            bounds.union_point(point)
+        })
+    }
+
+    /// Applies the given affine transform to every control point in this mesh.
+    pub fn apply_transform(&mut self, transform: Transform2F) {
+        for patch in &mut self.patches {
+            for point in &mut patch.points {
+                *point = transform * *point;
+            }
+        }
+    }
+
+    /// Renders this mesh into an image of the given size, with straight (not premultiplied)
+    /// alpha. The image covers `rect`, in scene coordinates. Texels that no patch covers are
+    /// transparent black.
+    ///
+    /// Each patch is diced into a grid of quads fine enough that linear interpolation across each
+    /// quad is within a texel or so of the true surface.
+    pub fn rasterize(&self, rect: RectF, size: Vector2I) -> Vec<ColorU> {
+        let mut texels = vec![ColorU::transparent_black(); size.x() as usize * size.y() as usize];
+        if rect.is_empty() || size.x() <= 0 || size.y() <= 0 {
+            return texels;
+        }
+
+        let to_texels = Transform2F::from_scale(size.to_f32() / rect.size()) *
+            Transform2F::from_translation(-rect.origin());
+
+        for patch in &self.patches {
+            let mut device_patch = *patch;
+            for point in &mut device_patch.points {
+                *point = to_texels * *point;
+            }
+
+            // The control polygon is longer than the curves it describes, so this is
+            // conservative.
+            let mut max_edge_length: f32 = 0.0;
+            for i in 0..4 {
+                for j in 0..3 {
+                    let (a, b) = (device_patch.point(i, j), device_patch.point(i, j + 1));
+                    let (c, d) = (device_patch.point(j, i), device_patch.point(j + 1, i));
+                    max_edge_length = max_edge_length.max((b - a).length() + (d - c).length());
+                }
+            }
+            let steps = f32::ceil(max_edge_length * 3.0 / MESH_DICE_TEXELS)
+                .max(1.0)
+                .min(MAX_MESH_DICE_STEPS as f32) as usize;
+
+            let mut grid = Vec::with_capacity((steps + 1) * (steps + 1));
+            for v_index in 0..(steps + 1) {
+                let v = v_index as f32 / steps as f32;
+                for u_index in 0..(steps + 1) {
+                    let u = u_index as f32 / steps as f32;
+                    grid.push((device_patch.sample_point(u, v), device_patch.sample_color(u, v)));
+                }
+            }
+
+            for v_index in 0..steps {
+                for u_index in 0..steps {
+                    let index = v_index * (steps + 1) + u_index;
+                    let (p0, p1) = (grid[index], grid[index + 1]);
+                    let (p2, p3) = (grid[index + steps + 2], grid[index + steps + 1]);
+                    fill_triangle(&mut texels, size, [p0, p1, p2]);
+                    fill_triangle(&mut texels, size, [p0, p2, p3]);
+                }
+            }
+        }
+
+        texels
+    }
+}
+
+impl MeshPatch {
+    /// Creates a tensor-product patch, as in PDF shading type 7.
+    ///
+    /// `points` is in the order that PDF uses: the twelve boundary points p<sub>00</sub>,
+    /// p<sub>01</sub>, p<sub>02</sub>, p<sub>03</sub>, p<sub>13</sub>, p<sub>23</sub>,
+    /// p<sub>33</sub>, p<sub>32</sub>, p<sub>31</sub>, p<sub>30</sub>, p<sub>20</sub>,
+    /// p<sub>10</sub>, followed by the four interior points p<sub>11</sub>, p<sub>12</sub>,
+    /// p<sub>22</sub>, p<sub>21</sub>.
+    pub fn tensor(points: [Vector2F; 16], colors: [ColorU; 4]) -> MeshPatch {
+        let mut patch = MeshPatch { points: [Vector2F::zero(); 16], colors };
+        for (&(i, j), &point) in TENSOR_POINT_ORDER.iter().zip(points.iter()) {
+            patch.points[i * 4 + j] = point;
+        }
+        patch
+    }
+
+    /// Creates a Coons patch, as in PDF shading type 6, from its twelve boundary points.
+    ///
+    /// The boundary points are in the same order as the first twelve points passed to
+    /// `MeshPatch::tensor()`. The interior control points are derived from them as the PDF
+    /// specification describes.
+    pub fn coons(boundary: [Vector2F; 12], colors: [ColorU; 4]) -> MeshPatch {
+        let mut patch = MeshPatch { points: [Vector2F::zero(); 16], colors };
+        for (&(i, j), &point) in TENSOR_POINT_ORDER.iter().zip(boundary.iter()) {
+            patch.points[i * 4 + j] = point;
+        }
+
+        let p = |i: usize, j: usize| patch.points[i * 4 + j];
+        let interior = |a: Vector2F, b: Vector2F, c: Vector2F, d: Vector2F, e: Vector2F| {
+            (a * -4.0 + b * 6.0 + c * -2.0 + d * 3.0 - e) * (1.0 / 9.0)
+        };
+        let p11 = interior(p(0, 0), p(0, 1) + p(1, 0), p(0, 3) + p(3, 0), p(3, 1) + p(1, 3),
+                           p(3, 3));
+        let p12 = interior(p(0, 3), p(0, 2) + p(1, 3), p(0, 0) + p(3, 3), p(3, 2) + p(1, 0),
+                           p(3, 0));
+        let p21 = interior(p(3, 0), p(3, 1) + p(2, 0), p(3, 3) + p(0, 0), p(0, 1) + p(2, 3),
+                           p(0, 3));
+        let p22 = interior(p(3, 3), p(3, 2) + p(2, 3), p(3, 0) + p(0, 3), p(0, 2) + p(2, 0),
+                           p(0, 0));
+        patch.points[1 * 4 + 1] = p11;
+        patch.points[1 * 4 + 2] = p12;
+        patch.points[2 * 4 + 1] = p21;
+        patch.points[2 * 4 + 2] = p22;
+        patch
+    }
+
+    /// Returns the control point p<sub>ij</sub>.
+    #[inline]
+    pub fn point(&self, i: usize, j: usize) -> Vector2F {
+        self.points[i * 4 + j]
+    }
+
+    /// Returns the control points in the order that `MeshPatch::tensor()` takes them.
+    pub fn points_in_pdf_order(&self) -> [Vector2F; 16] {
+        let mut points = [Vector2F::zero(); 16];
+        for (point, &(i, j)) in points.iter_mut().zip(TENSOR_POINT_ORDER.iter()) {
+            *point = self.point(i, j);
+        }
+        points
+    }
+
+    /// Returns the point on the surface of this patch at the parametric coordinates `(u, v)`.
+    pub fn sample_point(&self, u: f32, v: f32) -> Vector2F {
+        let (bu, bv) = (bernstein(u), bernstein(v));
+        let mut point = Vector2F::zero();
+        for i in 0..4 {
+            for j in 0..4 {
+                point += self.point(i, j) * (bu[i] * bv[j]);
+            }
+        }
+        point
+    }
+
+    /// Returns the color of this patch at the parametric coordinates `(u, v)`.
+    pub fn sample_color(&self, u: f32, v: f32) -> ColorF {
+        let [c00, c03, c33, c30] = self.colors;
+        let c0 = c00.to_f32().lerp(c03.to_f32(), v);
+        let c1 = c30.to_f32().lerp(c33.to_f32(), v);
+        c0.lerp(c1, u)
+    }
+}
+
+// The order in which PDF lists the control points of a tensor-product patch.
+static TENSOR_POINT_ORDER: [(usize, usize); 16] = [
+    (0, 0), (0, 1), (0, 2), (0, 3), (1, 3), (2, 3), (3, 3), (3, 2),
+    (3, 1), (3, 0), (2, 0), (1, 0), (1, 1), (1, 2), (2, 2), (2, 1),
+];
+
+// The approximate size, in texels, of each quad that mesh patches are diced into.
+const MESH_DICE_TEXELS: f32 = 4.0;
+
+// The maximum number of quads along each side of a diced mesh patch.
+const MAX_MESH_DICE_STEPS: usize = 128;
+
+fn bernstein(t: f32) -> [f32; 4] {
+    let s = 1.0 - t;
+    [s * s * s, 3.0 * s * s * t, 3.0 * s * t * t, t * t * t]
+}
+
+// Fills the texels whose centers lie inside the triangle, interpolating colors linearly.
+fn fill_triangle(texels: &mut [ColorU], size: Vector2I, vertices: [(Vector2F, ColorF); 3]) {
+    let [(p0, c0), (p1, c1), (p2, c2)] = vertices;
+    let area = (p1 - p0).det(p2 - p0);
+    if area == 0.0 {
+        return;
+    }
+
+    let bounds = RectF::from_points(p0.min(p1).min(p2), p0.max(p1).max(p2));
+    let min = bounds.origin().floor().to_i32().max(Vector2I::zero());
+    let max = bounds.lower_right().ceil().to_i32().min(size);
+    for y in min.y()..max.y() {
+        for x in min.x()..max.x() {
+            let point = vec2f(x as f32 + 0.5, y as f32 + 0.5);
+            let w0 = (p2 - p1).det(point - p1) / area;
+            let w1 = (p0 - p2).det(point - p2) / area;
+            let w2 = 1.0 - w0 - w1;
+            if w0 < 0.0 || w1 < 0.0 || w2 < 0.0 {
+                continue;
+            }
+            let color = c0.0 * F32x4::splat(w0) + c1.0 * F32x4::splat(w1) +
+                c2.0 * F32x4::splat(w2);
+            texels[y as usize * size.x() as usize + x as usize] = ColorF(color).to_u8();
         }
     }
 }
@@ -271,9 +652,43 @@ impl RadialGradientLine for Vector2F {
 
 #[cfg(test)]
 mod test {
-    use crate::gradient::Gradient;
+    use crate::gradient::{Gradient, GradientInterpolation, MeshGradient, MeshPatch};
     use pathfinder_color::ColorU;
-    use pathfinder_geometry::vector::Vector2F;
+    use pathfinder_geometry::rect::RectF;
+    use pathfinder_geometry::vector::{Vector2F, vec2f, vec2i};
+
+    // A Coons patch covering the square from (0, 0) to (3, 3) with straight edges.
+    fn square_coons_patch() -> MeshPatch {
+        let boundary = [
+            vec2f(0.0, 0.0), vec2f(0.0, 1.0), vec2f(0.0, 2.0), vec2f(0.0, 3.0),
+            vec2f(1.0, 3.0), vec2f(2.0, 3.0), vec2f(3.0, 3.0), vec2f(3.0, 2.0),
+            vec2f(3.0, 1.0), vec2f(3.0, 0.0), vec2f(2.0, 0.0), vec2f(1.0, 0.0),
+        ];
+        let colors = [ColorU::black(), ColorU::black(), ColorU::white(), ColorU::white()];
+        MeshPatch::coons(boundary, colors)
+    }
+
+    #[test]
+    fn coons_patch_interior_points() {
+        let patch = square_coons_patch();
+        assert_eq!(patch.point(1, 1), vec2f(1.0, 1.0));
+        assert_eq!(patch.point(1, 2), vec2f(1.0, 2.0));
+        assert_eq!(patch.point(2, 1), vec2f(2.0, 1.0));
+        assert_eq!(patch.point(2, 2), vec2f(2.0, 2.0));
+        assert_eq!(patch.sample_point(0.5, 0.5), vec2f(1.5, 1.5));
+    }
+
+    #[test]
+    fn rasterize_mesh() {
+        let mut mesh = MeshGradient::new();
+        mesh.push_patch(square_coons_patch());
+        let texels = mesh.rasterize(RectF::new(vec2f(0.0, 0.0), vec2f(6.0, 3.0)), vec2i(6, 3));
+
+        // Color varies along u, which runs along the x axis here.
+        assert!(texels[0].r < texels[1].r && texels[1].r < texels[2].r);
+        assert_eq!(texels[1].a, 255);
+        assert_eq!(texels[4], ColorU::transparent_black());
+    }
 
     #[test]
     fn stable_order() {
@@ -301,4 +716,22 @@ mod test {
             assert!(sample.r == 0, "{} {}", i, sample.r);
         }
     }
+
+    #[test]
+    fn interpolation_color_spaces() {
+        let mut gradient = Gradient::linear_from_points(Vector2F::zero(), vec2f(1.0, 0.0));
+        gradient.add_color_stop(ColorU::black(), 0.0);
+        gradient.add_color_stop(ColorU::white(), 1.0);
+        assert!((gradient.sample(0.5).r as i32 - 127).abs() <= 1);
+
+        // Halfway in linear light is about 73.5% in sRGB.
+        gradient.interpolation = GradientInterpolation::Linear;
+        assert!((gradient.sample(0.5).r as i32 - 187).abs() <= 1);
+
+        // Halfway in OKLab lightness is 12.5% luminance, or about 39% in sRGB.
+        gradient.interpolation = GradientInterpolation::Oklab;
+        let oklab_gray = gradient.sample(0.5);
+        assert!((oklab_gray.r as i32 - 99).abs() <= 1);
+        assert!((oklab_gray.r as i32 - oklab_gray.b as i32).abs() <= 1);
+    }
 }
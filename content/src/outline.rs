@@ -43,6 +43,9 @@ pub struct Outline {
 pub struct Contour {
     pub(crate) points: Vec<Vector2F>,
     pub(crate) flags: Vec<PointFlags>,
+    // The weight of each point, for conic sections. This is empty if the contour contains no
+    // conics; otherwise it has the same length as `points`.
+    pub(crate) weights: Vec<f32>,
     pub(crate) bounds: RectF,
     pub(crate) closed: bool,
 }
@@ -56,6 +59,9 @@ bitflags! {
         const CONTROL_POINT_0 = 0x01;
         /// This point is the second point of a quadratic Bézier curve.
         const CONTROL_POINT_1 = 0x02;
+        /// This point is the control point of a conic section. It will also have
+        /// `CONTROL_POINT_0` set, and its weight can be retrieved with `Contour::weight_of()`.
+        const CONIC = 0x04;
     }
 }
 
@@ -117,7 +123,9 @@ impl Outline {
                 continue;
             }
 
-            if !segment.is_line() {
+            if segment.is_conic() {
+                current_contour.push_conic_point(segment.ctrl.from(), segment.weight, true);
+            } else if !segment.is_line() {
                 current_contour.push_point(segment.ctrl.from(), PointFlags::CONTROL_POINT_0, true);
                 if !segment.is_quadratic() {
                     current_contour.push_point(
@@ -286,6 +294,22 @@ impl Outline {
         self.contours.iter().all(Contour::is_empty)
     }
 
+    /// Returns true if any subpath of this outline contains a conic section.
+    #[inline]
+    pub fn has_conics(&self) -> bool {
+        self.contours.iter().any(Contour::has_conics)
+    }
+
+    /// Returns this outline in SVG path data syntax, as accepted by the `d` attribute of the SVG
+    /// `<path>` element.
+    ///
+    /// This is the same as the `Debug` representation. Conic sections that aren't elliptical arcs
+    /// are approximated with quadratic curves.
+    #[inline]
+    pub fn to_svg_path_data(&self) -> String {
+        format!("{:?}", self)
+    }
+
     /// Returns the number of contours in this outline.
     #[inline]
     pub fn len(&self) -> usize {
@@ -327,6 +351,7 @@ impl Contour {
         Contour {
             points: vec![],
             flags: vec![],
+            weights: vec![],
             bounds: RectF::default(),
             closed: false,
         }
@@ -339,6 +364,7 @@ impl Contour {
         Contour {
             points: Vec::with_capacity(length),
             flags: Vec::with_capacity(length),
+            weights: vec![],
             bounds: RectF::default(),
             closed: false,
         }
@@ -360,67 +386,68 @@ impl Contour {
     /// Creates a closed subpath representing the given axis-aligned rounded rectangle.
     #[inline]
     pub fn from_rect_rounded(rect: RectF, radius: Vector2F) -> Contour {
-        use std::f32::consts::SQRT_2;
-        const QUARTER_ARC_CP_FROM_OUTSIDE: f32 = (3.0 - 4.0 * (SQRT_2 - 1.0)) / 3.0;
-
         if radius.is_zero() {
             return Contour::from_rect(rect);
         }
         let radius = radius.min(rect.size() * 0.5);
-        let contol_point_offset = radius * QUARTER_ARC_CP_FROM_OUTSIDE;
+        Contour::from_rect_rounded_corners(rect, [radius; 4])
+    }
 
-        let mut contour = Contour::with_capacity(8);
+    /// Creates a closed subpath representing the given axis-aligned rectangle with a separate
+    /// elliptical radius for each corner, in the order upper left, upper right, lower right, lower
+    /// left.
+    ///
+    /// As in CSS, if the radii of two adjacent corners add up to more than the length of the side
+    /// between them, all radii are scaled down uniformly so that they fit.
+    pub fn from_rect_rounded_corners(rect: RectF, mut radii: [Vector2F; 4]) -> Contour {
+        use std::f32::consts::SQRT_2;
+        const QUARTER_ARC_CP_FROM_OUTSIDE: f32 = (3.0 - 4.0 * (SQRT_2 - 1.0)) / 3.0;
 
-        // upper left corner
-        {
-            let p0 = rect.origin();
-            let p1 = p0 + contol_point_offset;
-            let p2 = p0 + radius;
-            contour.push_endpoint(vec2f(p0.x(), p2.y()));
-            contour.push_cubic(
-                vec2f(p0.x(), p1.y()),
-                vec2f(p1.x(), p0.y()),
-                vec2f(p2.x(), p0.y())
-            );
+        for radius in &mut radii {
+            *radius = radius.max(Vector2F::zero());
         }
 
-        // upper right
-        {
-            let p0 = rect.upper_right();
-            let p1 = p0 + contol_point_offset * vec2f(-1.0, 1.0);
-            let p2 = p0 + radius * vec2f(-1.0, 1.0);
-            contour.push_endpoint(vec2f(p2.x(), p0.y()));
-            contour.push_cubic(
-                vec2f(p1.x(), p0.y()),
-                vec2f(p0.x(), p1.y()),
-                vec2f(p0.x(), p2.y())
-            );
+        let [upper_left, upper_right, lower_right, lower_left] = radii;
+        let size = rect.size();
+        let scale = [
+            size.x() / (upper_left.x() + upper_right.x()),
+            size.y() / (upper_right.y() + lower_right.y()),
+            size.x() / (lower_right.x() + lower_left.x()),
+            size.y() / (lower_left.y() + upper_left.y()),
+        ].iter().cloned().filter(|scale| scale.is_finite()).fold(1.0, f32::min);
+        for radius in &mut radii {
+            *radius = *radius * scale;
         }
 
-        // lower right
-        {
-            let p0 = rect.lower_right();
-            let p1 = p0 + contol_point_offset * vec2f(-1.0, -1.0);
-            let p2 = p0 + radius * vec2f(-1.0, -1.0);
-            contour.push_endpoint(vec2f(p0.x(), p2.y()));
-            contour.push_cubic(
-                vec2f(p0.x(), p1.y()),
-                vec2f(p1.x(), p0.y()),
-                vec2f(p2.x(), p0.y())
-            );
-        }
+        let corners = [
+            (rect.origin(),      vec2f( 1.0,  1.0)),
+            (rect.upper_right(), vec2f(-1.0,  1.0)),
+            (rect.lower_right(), vec2f(-1.0, -1.0)),
+            (rect.lower_left(),  vec2f( 1.0, -1.0)),
+        ];
 
-        // lower left
-        {
-            let p0 = rect.lower_left();
-            let p1 = p0 + contol_point_offset * vec2f(1.0, -1.0);
-            let p2 = p0 + radius * vec2f(1.0, -1.0);
-            contour.push_endpoint(vec2f(p2.x(), p0.y()));
-            contour.push_cubic(
-                vec2f(p1.x(), p0.y()),
-                vec2f(p0.x(), p1.y()),
-                vec2f(p0.x(), p2.y())
-            );
+        let mut contour = Contour::with_capacity(8);
+        for (corner_index, (&(p0, sign), &radius)) in corners.iter().zip(radii.iter()).enumerate() {
+            if radius.x() == 0.0 || radius.y() == 0.0 {
+                contour.push_endpoint(p0);
+                continue;
+            }
+
+            let p1 = p0 + radius * sign * QUARTER_ARC_CP_FROM_OUTSIDE;
+            let p2 = p0 + radius * sign;
+
+            // Corners alternate between starting on a vertical and a horizontal side.
+            if corner_index % 2 == 0 {
+                contour.push_endpoint(vec2f(p0.x(), p2.y()));
+                contour.push_cubic(vec2f(p0.x(), p1.y()),
+                                   vec2f(p1.x(), p0.y()),
+                                   vec2f(p2.x(), p0.y()));
+            } else {
+                contour.push_endpoint(vec2f(p2.x(), p0.y()));
+                contour.push_cubic(vec2f(p1.x(), p0.y()),
+                                   vec2f(p0.x(), p1.y()),
+                                   vec2f(p0.x(), p2.y()));
+            }
         }
 
         contour.close();
@@ -436,6 +463,7 @@ impl Contour {
             Contour {
                 points: Vec::with_capacity(length),
                 flags: Vec::with_capacity(length),
+                weights: vec![],
                 bounds: RectF::default(),
                 closed: false,
             },
@@ -448,6 +476,7 @@ impl Contour {
     pub fn clear(&mut self) {
         self.points.clear();
         self.flags.clear();
+        self.weights.clear();
         self.bounds = RectF::default();
         self.closed = false;
     }
@@ -520,6 +549,25 @@ impl Contour {
         self.flags[index as usize]
     }
 
+    /// Returns the weight of the point with the given index.
+    ///
+    /// This is only meaningful for conic control points (those with `PointFlags::CONIC` set); all
+    /// other points have a weight of 1.0.
+    #[inline]
+    pub fn weight_of(&self, index: u32) -> f32 {
+        if self.weights.is_empty() {
+            1.0
+        } else {
+            self.weights[index as usize]
+        }
+    }
+
+    /// Returns true if this contour contains at least one conic section.
+    #[inline]
+    pub fn has_conics(&self) -> bool {
+        !self.weights.is_empty()
+    }
+
     /// Adds a new on-curve point at the given position to this contour.
     #[inline]
     pub fn push_endpoint(&mut self, to: Vector2F) {
@@ -543,6 +591,16 @@ impl Contour {
         self.push_point(to, PointFlags::empty(), true);
     }
 
+    /// Adds a new conic section (rational quadratic Bézier curve) to the given on-curve position
+    /// with the given control point and weight to this contour.
+    ///
+    /// See `Segment::conic()` for the meaning of the weight.
+    #[inline]
+    pub fn push_conic(&mut self, ctrl: Vector2F, to: Vector2F, weight: f32) {
+        self.push_conic_point(ctrl, weight, true);
+        self.push_point(to, PointFlags::empty(), true);
+    }
+
     /// Marks this contour as closed, which results in an implicit line from the end back to the
     /// starting point.
     #[inline]
@@ -564,6 +622,19 @@ impl Contour {
 
         self.points.push(point);
         self.flags.push(flags);
+        if !self.weights.is_empty() {
+            self.weights.push(1.0);
+        }
+    }
+
+    #[inline]
+    pub(crate) fn push_conic_point(&mut self, point: Vector2F, weight: f32, update_bounds: bool) {
+        debug_assert!(weight > 0.0);
+        if self.weights.is_empty() {
+            self.weights.resize(self.points.len(), 1.0);
+        }
+        self.push_point(point, PointFlags::CONTROL_POINT_0 | PointFlags::CONIC, update_bounds);
+        *self.weights.last_mut().unwrap() = weight;
     }
 
     #[inline]
@@ -575,7 +646,9 @@ impl Contour {
         let update_bounds = flags.contains(PushSegmentFlags::UPDATE_BOUNDS);
         self.push_point(segment.baseline.from(), PointFlags::empty(), update_bounds);
 
-        if !segment.is_line() {
+        if segment.is_conic() {
+            self.push_conic_point(segment.ctrl.from(), segment.weight, update_bounds);
+        } else if !segment.is_line() {
             self.push_point(
                 segment.ctrl.from(),
                 PointFlags::CONTROL_POINT_0,
@@ -593,7 +666,7 @@ impl Contour {
         self.push_point(segment.baseline.to(), PointFlags::empty(), update_bounds);
     }
 
-    /// Adds Bézier curves approximating a possibly-transformed unit arc to this contour.
+    /// Adds conic sections exactly representing a possibly-transformed unit arc to this contour.
     ///
     /// Arguments:
     ///
@@ -620,8 +693,8 @@ impl Contour {
         }
     }
 
-    /// Given the endpoints of a unit arc, adds Bézier curves to approximate that arc to the
-    /// current contour. The given transform is applied to the resulting arc.
+    /// Given the endpoints of a unit arc, adds conic sections representing that arc to the current
+    /// contour. The given transform is applied to the resulting arc.
     pub fn push_arc_from_unit_chord(&mut self,
                                     transform: &Transform2F,
                                     mut chord: LineSegment2F,
@@ -647,9 +720,9 @@ impl Contour {
             let mut segment;
             if !last {
                 sweep_vector = UnitVector(vec2f(0.0, 1.0));
-                segment = Segment::quarter_circle_arc();
+                segment = Segment::quarter_circle_conic_arc();
             } else {
-                segment = Segment::arc_from_cos(sweep_vector.0.x());
+                segment = Segment::conic_arc_from_cos(sweep_vector.0.x());
             }
 
             let half_sweep_vector = sweep_vector.halve_angle();
@@ -737,9 +810,10 @@ impl Contour {
 
     /// Adds an unit circle to this contour, transformed with the given transform.
     ///
-    /// Non-uniform scales can be used to transform this circle into an ellipse.
+    /// Non-uniform scales can be used to transform this circle into an ellipse. The circle is
+    /// represented exactly with four conic sections.
     pub fn push_ellipse(&mut self, transform: &Transform2F) {
-        let segment = Segment::quarter_circle_arc();
+        let segment = Segment::quarter_circle_conic_arc();
         let mut rotation;
         self.push_segment(&segment.transform(transform),
                           PushSegmentFlags::UPDATE_BOUNDS | PushSegmentFlags::INCLUDE_FROM_POINT);
@@ -776,7 +850,12 @@ impl Contour {
             let point2_index = self.add_to_point_index(point_index, 2);
             if self.point_is_endpoint(point2_index) {
                 segment.baseline.set_to(self.position_of(point2_index));
-                segment.kind = SegmentKind::Quadratic;
+                if self.flags_of(point1_index).contains(PointFlags::CONIC) {
+                    segment.weight = self.weight_of(point1_index);
+                    segment.kind = SegmentKind::Conic;
+                } else {
+                    segment.kind = SegmentKind::Quadratic;
+                }
             } else {
                 segment.ctrl.set_to(self.position_of(point2_index));
                 segment.kind = SegmentKind::Cubic;
@@ -953,6 +1032,32 @@ impl Debug for Contour {
                         segment.baseline.to_y()
                     )?;
                 }
+                SegmentKind::Conic => {
+                    match segment.as_conic_segment().to_elliptical_arc() {
+                        Some((radius, x_axis_rotation, direction)) => {
+                            write!(formatter,
+                                   " A {} {} {} 0 {} {} {}",
+                                   radius.x(),
+                                   radius.y(),
+                                   x_axis_rotation.to_degrees(),
+                                   (direction == ArcDirection::CW) as u8,
+                                   segment.baseline.to_x(),
+                                   segment.baseline.to_y())?;
+                        }
+                        None => {
+                            // Parabolas and hyperbolas have no SVG equivalent.
+                            let tolerance = CONIC_DEBUG_TOLERANCE;
+                            for quadratic in segment.as_conic_segment().to_quadratics(tolerance) {
+                                write!(formatter,
+                                       " Q {} {} {} {}",
+                                       quadratic.ctrl.from_x(),
+                                       quadratic.ctrl.from_y(),
+                                       quadratic.baseline.to_x(),
+                                       quadratic.baseline.to_y())?;
+                            }
+                        }
+                    }
+                }
                 SegmentKind::Cubic => {
                     write!(
                         formatter,
@@ -972,7 +1077,9 @@ impl Debug for Contour {
             write!(formatter, " z")?;
         }
 
-        Ok(())
+        return Ok(());
+
+        const CONIC_DEBUG_TOLERANCE: f32 = 0.01;
     }
 }
 
@@ -1044,7 +1151,11 @@ impl<'a> Iterator for ContourIter<'a> {
         let point2 = contour.position_of(point2_index);
         self.index += 1;
         if contour.point_is_endpoint(point2_index) {
-            return Some(Segment::quadratic(LineSegment2F::new(point0, point2), point1));
+            let baseline = LineSegment2F::new(point0, point2);
+            if contour.flags_of(point1_index).contains(PointFlags::CONIC) {
+                return Some(Segment::conic(baseline, point1, contour.weight_of(point1_index)));
+            }
+            return Some(Segment::quadratic(baseline, point1));
         }
 
         let point3_index = self.index;
@@ -10,6 +10,7 @@
 
 //! Single line or Bézier curve segments, optimized with SIMD.
 
+use crate::outline::ArcDirection;
 use pathfinder_geometry::line_segment::LineSegment2F;
 use pathfinder_geometry::transform2d::Transform2F;
 use pathfinder_geometry::util::EPSILON;
@@ -17,6 +18,10 @@ use pathfinder_geometry::vector::{Vector2F, vec2f};
 use pathfinder_simd::default::F32x4;
 use std::f32::consts::SQRT_2;
 
+// The fraction of the flattening tolerance that may be spent approximating cubic curves and conic
+// sections with quadratic curves.
+const CUBIC_TO_QUADRATIC_TOLERANCE_FRACTION: f32 = 0.1;
+
 /// A single line or Bézier curve segment, with explicit start and end points.
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Segment {
@@ -26,10 +31,15 @@ pub struct Segment {
     ///
     /// If this is a line (which can be determined by examining the segment kind), this field is
     /// ignored. If this is a quadratic Bézier curve, the start point of this line represents the
-    /// control point, and the endpoint of this line is ignored. Otherwise, if this is a cubic
-    /// Bézier curve, both the start and endpoints are used.
+    /// control point, and the endpoint of this line is ignored. Conic sections use the control
+    /// point in the same way as quadratic Bézier curves. Otherwise, if this is a cubic Bézier
+    /// curve, both the start and endpoints are used.
     pub ctrl: LineSegment2F,
-    /// The type of segment this is: invalid, line, quadratic, or cubic Bézier curve.
+    /// The weight of the control point, if this is a conic section.
+    ///
+    /// This is ignored for all other segment kinds and is conventionally 1.0 for them.
+    pub weight: f32,
+    /// The type of segment this is: invalid, line, quadratic or cubic Bézier curve, or conic.
     pub kind: SegmentKind,
     /// Various flags that describe information about this segment in a path.
     pub flags: SegmentFlags,
@@ -42,6 +52,7 @@ impl Segment {
         Segment {
             baseline: LineSegment2F::default(),
             ctrl: LineSegment2F::default(),
+            weight: 1.0,
             kind: SegmentKind::None,
             flags: SegmentFlags::empty(),
         }
@@ -53,6 +64,7 @@ impl Segment {
         Segment {
             baseline: line,
             ctrl: LineSegment2F::default(),
+            weight: 1.0,
             kind: SegmentKind::Line,
             flags: SegmentFlags::empty(),
         }
@@ -64,17 +76,36 @@ impl Segment {
         Segment {
             baseline,
             ctrl: LineSegment2F::new(ctrl, Vector2F::zero()),
+            weight: 1.0,
             kind: SegmentKind::Quadratic,
             flags: SegmentFlags::empty(),
         }
     }
 
+    /// Returns a segment representing a conic section (a rational quadratic Bézier curve) with
+    /// the given control point weight.
+    ///
+    /// Weights less than 1 produce elliptical arcs, a weight of 1 produces a parabola (i.e. an
+    /// ordinary quadratic Bézier curve), and weights greater than 1 produce hyperbolas.
+    #[inline]
+    pub fn conic(baseline: LineSegment2F, ctrl: Vector2F, weight: f32) -> Segment {
+        debug_assert!(weight > 0.0);
+        Segment {
+            baseline,
+            ctrl: LineSegment2F::new(ctrl, Vector2F::zero()),
+            weight,
+            kind: SegmentKind::Conic,
+            flags: SegmentFlags::empty(),
+        }
+    }
+
     /// Returns a segment representing a cubic Bézier curve.
     #[inline]
     pub fn cubic(baseline: LineSegment2F, ctrl: LineSegment2F) -> Segment {
         Segment {
             baseline,
             ctrl,
+            weight: 1.0,
             kind: SegmentKind::Cubic,
             flags: SegmentFlags::empty(),
         }
@@ -121,6 +152,40 @@ impl Segment {
         Segment::cubic(LineSegment2F::new(p3, p0), LineSegment2F::new(p2, p1))
     }
 
+    /// Returns a conic section that exactly represents a unit-length arc centered on the +x axis.
+    ///
+    /// The maximum supported sweep angle is π/2 (i.e. 90°).
+    pub fn conic_arc(sweep_angle: f32) -> Segment {
+        Segment::conic_arc_from_cos(f32::cos(sweep_angle))
+    }
+
+    /// Returns a conic section that exactly represents a unit-length arc centered on the +x axis,
+    /// given the cosine of the sweep angle.
+    ///
+    /// The maximum supported sweep angle is π/2 (i.e. 90°).
+    pub fn conic_arc_from_cos(cos_sweep_angle: f32) -> Segment {
+        if cos_sweep_angle >= 1.0 - EPSILON {
+            return Segment::line(LineSegment2F::new(vec2f(1.0, 0.0), vec2f(1.0, 0.0)));
+        }
+
+        // The control point is the intersection of the tangents at the endpoints, and the weight
+        // is the cosine of half the sweep angle.
+        let cos_half_sweep = ((1.0 + cos_sweep_angle) * 0.5).sqrt();
+        let sin_half_sweep = ((1.0 - cos_sweep_angle) * 0.5).sqrt();
+        let baseline = LineSegment2F::new(vec2f(cos_half_sweep, -sin_half_sweep),
+                                          vec2f(cos_half_sweep, sin_half_sweep));
+        Segment::conic(baseline, vec2f(1.0 / cos_half_sweep, 0.0), cos_half_sweep)
+    }
+
+    /// Returns a conic section that exactly represents a quarter of an arc, centered on the +x
+    /// axis.
+    #[inline]
+    pub fn quarter_circle_conic_arc() -> Segment {
+        let p0 = Vector2F::splat(SQRT_2 * 0.5);
+        let flip = vec2f(1.0, -1.0);
+        Segment::conic(LineSegment2F::new(p0 * flip, p0), vec2f(SQRT_2, 0.0), SQRT_2 * 0.5)
+    }
+
     /// If this segment is a line, returns it. In debug builds, panics otherwise.
     #[inline]
     pub fn as_line_segment(&self) -> LineSegment2F {
@@ -152,6 +217,26 @@ impl Segment {
         self.kind == SegmentKind::Cubic
     }
 
+    /// Returns true if this segment represents a conic section.
+    #[inline]
+    pub fn is_conic(&self) -> bool {
+        self.kind == SegmentKind::Conic
+    }
+
+    /// Returns true if this segment has exactly one control point, i.e. if it is a quadratic
+    /// Bézier curve or a conic section.
+    #[inline]
+    pub fn has_single_control_point(&self) -> bool {
+        self.is_quadratic() || self.is_conic()
+    }
+
+    /// If this segment is a quadratic Bézier curve, returns it. In debug builds, panics otherwise.
+    #[inline]
+    pub fn as_quadratic_segment(&self) -> QuadraticSegment<'_> {
+        debug_assert!(self.is_quadratic());
+        QuadraticSegment(self)
+    }
+
     /// If this segment is a cubic Bézier curve, returns it. In debug builds, panics otherwise.
     #[inline]
     pub fn as_cubic_segment(&self) -> CubicSegment {
@@ -159,11 +244,19 @@ impl Segment {
         CubicSegment(self)
     }
 
+    /// If this segment is a conic section, returns it. In debug builds, panics otherwise.
+    #[inline]
+    pub fn as_conic_segment(&self) -> ConicSegment {
+        debug_assert!(self.is_conic());
+        ConicSegment(self)
+    }
+
     /// If this segment is a quadratic Bézier curve, elevates it to a cubic Bézier curve and
     /// returns it. If this segment is a cubic Bézier curve, this method simply returns it.
     ///
     /// If this segment is neither a quadratic Bézier curve nor a cubic Bézier curve, this method
-    /// returns an unspecified result.
+    /// returns an unspecified result. In particular, conic sections cannot be represented exactly
+    /// by cubic Bézier curves; use `ConicSegment::to_quadratics()` to approximate them instead.
     ///
     /// FIXME(pcwalton): Handle lines!
     // FIXME(pcwalton): We should basically never use this function.
@@ -186,11 +279,12 @@ impl Segment {
     pub fn reversed(&self) -> Segment {
         Segment {
             baseline: self.baseline.reversed(),
-            ctrl: if self.is_quadratic() {
+            ctrl: if self.has_single_control_point() {
                 self.ctrl
             } else {
                 self.ctrl.reversed()
             },
+            weight: self.weight,
             kind: self.kind,
             flags: self.flags,
         }
@@ -213,6 +307,8 @@ impl Segment {
         if self.is_line() {
             let (before, after) = self.as_line_segment().split(t);
             (Segment::line(before), Segment::line(after))
+        } else if self.is_conic() {
+            self.as_conic_segment().split(t)
         } else {
             self.to_cubic().as_cubic_segment().split(t)
         }
@@ -227,6 +323,8 @@ impl Segment {
         // FIXME(pcwalton): Don't degree elevate!
         if self.is_line() {
             self.as_line_segment().sample(t)
+        } else if self.is_conic() {
+            self.as_conic_segment().sample(t)
         } else {
             self.to_cubic().as_cubic_segment().sample(t)
         }
@@ -238,11 +336,39 @@ impl Segment {
         Segment {
             baseline: *transform * self.baseline,
             ctrl: *transform * self.ctrl,
+            weight: self.weight,
             kind: self.kind,
             flags: self.flags,
         }
     }
 
+    /// Approximates this segment with a series of lines whose maximum deviation from it is less
+    /// than `tolerance`, and calls `f` with each of them in order.
+    ///
+    /// Quadratic curves are flattened with `QuadraticSegment::flatten()`. Cubic curves and conic
+    /// sections are first approximated with quadratic curves, which takes a small part of the
+    /// tolerance, and those are flattened with the rest.
+    pub fn flatten<F>(&self, tolerance: f32, mut f: F) where F: FnMut(LineSegment2F) {
+        if self.is_quadratic() {
+            self.as_quadratic_segment().flatten(tolerance).for_each(f);
+            return;
+        }
+
+        let quadratic_tolerance = CUBIC_TO_QUADRATIC_TOLERANCE_FRACTION * tolerance;
+        let line_tolerance = tolerance - quadratic_tolerance;
+        if self.is_cubic() {
+            for quadratic in self.as_cubic_segment().to_quadratics(quadratic_tolerance) {
+                quadratic.as_quadratic_segment().flatten(line_tolerance).for_each(&mut f);
+            }
+        } else if self.is_conic() {
+            for quadratic in self.as_conic_segment().to_quadratics(quadratic_tolerance) {
+                quadratic.as_quadratic_segment().flatten(line_tolerance).for_each(&mut f);
+            }
+        } else if self.is_line() {
+            f(self.baseline);
+        }
+    }
+
     pub(crate) fn arc_length(&self) -> f32 {
         // FIXME(pcwalton)
         self.baseline.vector().length()
@@ -266,6 +392,8 @@ pub enum SegmentKind {
     Quadratic,
     /// A cubic Bézier curve.
     Cubic,
+    /// A conic section (rational quadratic Bézier curve), such as an exact elliptical arc.
+    Conic,
 }
 
 bitflags! {
@@ -279,6 +407,133 @@ bitflags! {
     }
 }
 
+/// A wrapper for a `Segment` that contains methods specific to quadratic Bézier curves.
+#[derive(Clone, Copy, Debug)]
+pub struct QuadraticSegment<'s>(pub &'s Segment);
+
+impl<'s> QuadraticSegment<'s> {
+    /// Returns the position of the point on this curve at parametric time `t`, which will be
+    /// clamped between 0.0 and 1.0.
+    #[inline]
+    pub fn sample(self, t: f32) -> Vector2F {
+        let t = t.clamp(0.0, 1.0);
+        let (p0, p1, p2) = (self.0.baseline.from(), self.0.ctrl.from(), self.0.baseline.to());
+        p0.lerp(p1, t).lerp(p1.lerp(p2, t), t)
+    }
+
+    /// Approximates this curve with a series of lines whose maximum deviation from it is less
+    /// than `tolerance`, and returns an iterator over them.
+    ///
+    /// Rather than subdividing recursively, this maps the curve onto a segment of the parabola
+    /// y = x² and uses a closed-form approximation of the integral of the square root of its
+    /// curvature to choose the number of lines and to spread the error evenly among them. See
+    /// Raph Levien, "Flattening quadratic Béziers", 2019.
+    pub fn flatten(self, tolerance: f32) -> QuadraticFlattener {
+        let (p0, p1, p2) = (self.0.baseline.from(), self.0.ctrl.from(), self.0.baseline.to());
+
+        // Map the curve onto the basic parabola.
+        let dd = p1 * 2.0 - p0 - p2;
+        let cross = (p2 - p0).det(dd);
+        let x0 = (p1 - p0).dot(dd) / cross;
+        let x2 = (p2 - p1).dot(dd) / cross;
+        let scale = cross.abs() / (dd.length() * (x2 - x0).abs());
+
+        let (a0, a2) = (approx_parabola_integral(x0), approx_parabola_integral(x2));
+        let sqrt_tolerance = tolerance.sqrt();
+        let val = if !scale.is_finite() {
+            // The curve is a straight line.
+            0.0
+        } else if x0.signum() == x2.signum() {
+            (a2 - a0).abs() * scale.sqrt()
+        } else {
+            // The curve passes through the vertex of the parabola, where its curvature peaks.
+            let x_min = sqrt_tolerance / scale.sqrt();
+            sqrt_tolerance * (a2 - a0).abs() / approx_parabola_integral(x_min)
+        };
+
+        let line_count = (0.5 * val / sqrt_tolerance).ceil();
+        let (u0, u2) = (approx_parabola_inv_integral(a0), approx_parabola_inv_integral(a2));
+        QuadraticFlattener {
+            segment: *self.0,
+            // This is also false if numerical trouble produced NaN.
+            line_count: if line_count > 1.0 {
+                line_count.min(MAX_FLATTENED_LINES) as u32
+            } else {
+                1
+            },
+            next_line_index: 0,
+            a0,
+            a2,
+            u0,
+            u_scale: 1.0 / (u2 - u0),
+        }
+    }
+}
+
+// Bounds the number of lines a single curve is flattened into, in case of numerical trouble.
+const MAX_FLATTENED_LINES: f32 = 1024.0;
+
+/// An iterator over the lines approximating a quadratic Bézier curve.
+///
+/// See `QuadraticSegment::flatten()`.
+#[derive(Clone, Debug)]
+pub struct QuadraticFlattener {
+    segment: Segment,
+    line_count: u32,
+    next_line_index: u32,
+    a0: f32,
+    a2: f32,
+    u0: f32,
+    u_scale: f32,
+}
+
+impl QuadraticFlattener {
+    // Returns the end point of the line with the given index.
+    fn line_end(&self, line_index: u32) -> Vector2F {
+        if line_index == 0 {
+            return self.segment.baseline.from();
+        }
+        if line_index == self.line_count {
+            return self.segment.baseline.to();
+        }
+        let x = line_index as f32 / self.line_count as f32;
+        let u = approx_parabola_inv_integral(self.a0 + (self.a2 - self.a0) * x);
+        self.segment.as_quadratic_segment().sample((u - self.u0) * self.u_scale)
+    }
+}
+
+impl Iterator for QuadraticFlattener {
+    type Item = LineSegment2F;
+
+    #[inline]
+    fn next(&mut self) -> Option<LineSegment2F> {
+        if self.next_line_index == self.line_count {
+            return None;
+        }
+        let from = self.line_end(self.next_line_index);
+        self.next_line_index += 1;
+        Some(LineSegment2F::new(from, self.line_end(self.next_line_index)))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = (self.line_count - self.next_line_index) as usize;
+        (remaining, Some(remaining))
+    }
+}
+
+// An approximation of the integral of the square root of the curvature of y = x².
+fn approx_parabola_integral(x: f32) -> f32 {
+    const D: f32 = 0.67;
+    x / (1.0 - D + (D * D * D * D + 0.25 * x * x).sqrt().sqrt())
+}
+
+// An approximation of the inverse of `approx_parabola_integral()`.
+fn approx_parabola_inv_integral(x: f32) -> f32 {
+    const B: f32 = 0.39;
+    x * (1.0 - B + (B * B + 0.25 * x * x).sqrt())
+}
+
 /// A wrapper for a `Segment` that contains method specific to cubic Bézier curves.
 #[derive(Clone, Copy, Debug)]
 pub struct CubicSegment<'s>(pub &'s Segment);
@@ -346,12 +601,14 @@ impl<'s> CubicSegment<'s> {
             Segment {
                 baseline: baseline0,
                 ctrl: ctrl0,
+                weight: 1.0,
                 kind: SegmentKind::Cubic,
                 flags: self.0.flags & SegmentFlags::FIRST_IN_SUBPATH,
             },
             Segment {
                 baseline: baseline1,
                 ctrl: ctrl1,
+                weight: 1.0,
                 kind: SegmentKind::Cubic,
                 flags: self.0.flags & SegmentFlags::CLOSES_SUBPATH,
             },
@@ -379,6 +636,39 @@ impl<'s> CubicSegment<'s> {
         self.split(t).0.baseline.to()
     }
 
+    /// Approximates this curve with a series of quadratic Bézier curves whose maximum deviation
+    /// from it is less than `tolerance`, and returns an iterator over them.
+    ///
+    /// The curve is divided evenly in parametric space. The error of each quadratic is
+    /// proportional to the third derivative of the curve, which is constant, so it shrinks with
+    /// the cube of the number of quadratics. See
+    /// http://caffeineowl.com/graphics/2d/vectorial/cubic2quad01.html for the error bound.
+    pub fn to_quadratics(self, tolerance: f32) -> CubicToQuadratics {
+        let (p0, p3) = (self.0.baseline.from(), self.0.baseline.to());
+        let (p1, p2) = (self.0.ctrl.from(), self.0.ctrl.to());
+        let square_error = ((p2 * 3.0 - p3) - (p1 * 3.0 - p0)).square_length();
+        let quadratic_count = (square_error / (432.0 * tolerance * tolerance)).powf(1.0 / 6.0);
+        CubicToQuadratics {
+            segment: *self.0,
+            // This is also false if numerical trouble produced NaN.
+            quadratic_count: if quadratic_count > 1.0 {
+                quadratic_count.ceil().min(MAX_CUBIC_QUADRATICS) as u32
+            } else {
+                1
+            },
+            next_quadratic_index: 0,
+        }
+    }
+
+    // Returns the tangent of this curve at parametric time `t`, scaled by the parametric length
+    // of the curve.
+    fn derivative(self, t: f32) -> Vector2F {
+        let (p0, p3) = (self.0.baseline.from(), self.0.baseline.to());
+        let (p1, p2) = (self.0.ctrl.from(), self.0.ctrl.to());
+        let u = 1.0 - t;
+        ((p1 - p0) * (u * u) + (p2 - p1) * (2.0 * u * t) + (p3 - p2) * (t * t)) * 3.0
+    }
+
     /// Returns the left extent of this curve's axis-aligned bounding box.
     #[inline]
     pub fn min_x(&self) -> f32 {
@@ -400,3 +690,444 @@ impl<'s> CubicSegment<'s> {
         f32::max(self.0.baseline.max_y(), self.0.ctrl.max_y())
     }
 }
+
+// Bounds the number of quadratics a single cubic is approximated with, in case of numerical
+// trouble.
+const MAX_CUBIC_QUADRATICS: f32 = 64.0;
+
+/// An iterator over the quadratic Bézier curves approximating a cubic Bézier curve.
+///
+/// See `CubicSegment::to_quadratics()`.
+#[derive(Clone, Debug)]
+pub struct CubicToQuadratics {
+    segment: Segment,
+    quadratic_count: u32,
+    next_quadratic_index: u32,
+}
+
+impl CubicToQuadratics {
+    // Returns the point on the curve at the start of the quadratic with the given index.
+    fn quadratic_start(&self, quadratic_index: u32) -> (f32, Vector2F) {
+        if quadratic_index == 0 {
+            return (0.0, self.segment.baseline.from());
+        }
+        if quadratic_index == self.quadratic_count {
+            return (1.0, self.segment.baseline.to());
+        }
+        let t = quadratic_index as f32 / self.quadratic_count as f32;
+        (t, self.segment.as_cubic_segment().sample(t))
+    }
+}
+
+impl Iterator for CubicToQuadratics {
+    type Item = Segment;
+
+    fn next(&mut self) -> Option<Segment> {
+        if self.next_quadratic_index == self.quadratic_count {
+            return None;
+        }
+        let (t0, from) = self.quadratic_start(self.next_quadratic_index);
+        self.next_quadratic_index += 1;
+        let (t1, to) = self.quadratic_start(self.next_quadratic_index);
+
+        // The best control point is the average of the ones that match the tangents of the piece
+        // of the cubic at each end.
+        let cubic = self.segment.as_cubic_segment();
+        let tangents = cubic.derivative(t0) - cubic.derivative(t1);
+        let ctrl = (from + to) * 0.5 + tangents * ((t1 - t0) * 0.25);
+
+        let mut quadratic = Segment::quadratic(LineSegment2F::new(from, to), ctrl);
+        if t0 == 0.0 {
+            quadratic.flags |= self.segment.flags & SegmentFlags::FIRST_IN_SUBPATH;
+        }
+        if t1 == 1.0 {
+            quadratic.flags |= self.segment.flags & SegmentFlags::CLOSES_SUBPATH;
+        }
+        Some(quadratic)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = (self.quadratic_count - self.next_quadratic_index) as usize;
+        (remaining, Some(remaining))
+    }
+}
+
+/// A wrapper for a `Segment` that contains methods specific to conic sections.
+#[derive(Clone, Copy, Debug)]
+pub struct ConicSegment<'s>(pub &'s Segment);
+
+impl<'s> ConicSegment<'s> {
+    /// Returns true if the maximum deviation of this curve from the straight line connecting its
+    /// endpoints is less than `tolerance`.
+    ///
+    /// Every point on the curve is a convex combination of the endpoints and the control point in
+    /// which the control point contributes at most `w / (1 + w)`, so the deviation is bounded by
+    /// that fraction of the distance from the control point to the baseline.
+    #[inline]
+    pub fn is_flat(self, tolerance: f32) -> bool {
+        let (baseline, ctrl, weight) = (self.0.baseline, self.0.ctrl.from(), self.0.weight);
+        let vector = baseline.vector();
+        let square_length = vector.square_length();
+        let t = if square_length < EPSILON {
+            0.0
+        } else {
+            ((ctrl - baseline.from()).dot(vector) / square_length).max(0.0).min(1.0)
+        };
+        let ratio = weight / (1.0 + weight);
+        let distance = (ctrl - baseline.sample(t)).length();
+        ratio * distance <= tolerance
+    }
+
+    /// Splits this conic section into two at the given parametric t value, which will be clamped
+    /// to the range 0.0 to 1.0.
+    ///
+    /// This uses de Casteljau subdivision in homogeneous coordinates. The halves are returned in
+    /// standard form, with unit weights at the endpoints.
+    pub fn split(self, t: f32) -> (Segment, Segment) {
+        let t = t.max(0.0).min(1.0);
+        let (p0, p2, w) = (self.0.baseline.from(), self.0.baseline.to(), self.0.weight);
+        let p1 = self.0.ctrl.from() * w;
+
+        // Homogeneous de Casteljau: (point * weight, weight) pairs.
+        let (p01, w01) = (p0.lerp(p1, t), 1.0 + (w - 1.0) * t);
+        let (p12, w12) = (p1.lerp(p2, t), w + (1.0 - w) * t);
+        let (p012, w012) = (p01.lerp(p12, t), w01 + (w12 - w01) * t);
+
+        let mid = p012 * (1.0 / w012);
+        let w012_sqrt_recip = 1.0 / w012.sqrt();
+
+        let mut before = Segment::conic(LineSegment2F::new(p0, mid),
+                                        p01 * (1.0 / w01),
+                                        w01 * w012_sqrt_recip);
+        let mut after = Segment::conic(LineSegment2F::new(mid, p2),
+                                       p12 * (1.0 / w12),
+                                       w12 * w012_sqrt_recip);
+        before.flags = self.0.flags & SegmentFlags::FIRST_IN_SUBPATH;
+        after.flags = self.0.flags & SegmentFlags::CLOSES_SUBPATH;
+        (before, after)
+    }
+
+    /// Returns the part of this conic section between the parametric t values `t0` and `t1`,
+    /// which will be clamped to the range 0.0 to 1.0, in standard form.
+    ///
+    /// Unlike repeated calls to `split()`, which reparameterize each half, `t0` and `t1` are both
+    /// parametric values of this conic section.
+    pub fn subsegment(self, t0: f32, t1: f32) -> Segment {
+        let (t0, t1) = (t0.clamp(0.0, 1.0), t1.clamp(0.0, 1.0));
+        let (p0, p2, w) = (self.0.baseline.from(), self.0.baseline.to(), self.0.weight);
+        let p1 = self.0.ctrl.from() * w;
+
+        // Evaluate the blossom of the curve in homogeneous coordinates, as (point * weight,
+        // weight) pairs.
+        let blossom = |u: f32, v: f32| {
+            let (b0, b1, b2) = ((1.0 - u) * (1.0 - v), (1.0 - u) * v + u * (1.0 - v), u * v);
+            (p0 * b0 + p1 * b1 + p2 * b2, b0 + b1 * w + b2)
+        };
+        let ((q0, w0), (q1, w1), (q2, w2)) = (blossom(t0, t0), blossom(t0, t1), blossom(t1, t1));
+
+        let mut subsegment = Segment::conic(LineSegment2F::new(q0 * (1.0 / w0), q2 * (1.0 / w2)),
+                                            q1 * (1.0 / w1),
+                                            w1 / (w0 * w2).sqrt());
+        if t0 == 0.0 {
+            subsegment.flags |= self.0.flags & SegmentFlags::FIRST_IN_SUBPATH;
+        }
+        if t1 == 1.0 {
+            subsegment.flags |= self.0.flags & SegmentFlags::CLOSES_SUBPATH;
+        }
+        subsegment
+    }
+
+    /// Returns the position of the point on this curve at parametric time `t`, which will be
+    /// clamped between 0.0 and 1.0.
+    #[inline]
+    pub fn sample(self, t: f32) -> Vector2F {
+        let t = t.max(0.0).min(1.0);
+        let (p0, p2, w) = (self.0.baseline.from(), self.0.baseline.to(), self.0.weight);
+        let p1 = self.0.ctrl.from();
+        let (b0, b1, b2) = ((1.0 - t) * (1.0 - t), 2.0 * w * t * (1.0 - t), t * t);
+        (p0 * b0 + p1 * b1 + p2 * b2) * (1.0 / (b0 + b1 + b2))
+    }
+
+    /// Approximates this conic section with a series of quadratic Bézier curves whose maximum
+    /// deviation from the true curve is less than `tolerance`, and returns them.
+    ///
+    /// See Sederberg, "Computer Aided Geometric Design", section 2.9, for the error bound.
+    pub fn to_quadratics(self, tolerance: f32) -> Vec<Segment> {
+        let mut error = self.quadratic_approximation_error();
+        let mut subdivision_count = 0;
+        while error > tolerance && subdivision_count < MAX_QUADRATIC_SUBDIVISIONS {
+            // Each subdivision reduces the error by (roughly) a factor of 4.
+            error *= 0.25;
+            subdivision_count += 1;
+        }
+
+        let mut quadratics = Vec::with_capacity(1 << subdivision_count);
+        push_quadratics(self.0, subdivision_count, &mut quadratics);
+        return quadratics;
+
+        const MAX_QUADRATIC_SUBDIVISIONS: u32 = 8;
+
+        fn push_quadratics(segment: &Segment, levels: u32, quadratics: &mut Vec<Segment>) {
+            if levels == 0 {
+                let mut quadratic = Segment::quadratic(segment.baseline, segment.ctrl.from());
+                quadratic.flags = segment.flags;
+                quadratics.push(quadratic);
+                return;
+            }
+            let (before, after) = segment.as_conic_segment().split(0.5);
+            push_quadratics(&before, levels - 1, quadratics);
+            push_quadratics(&after, levels - 1, quadratics);
+        }
+    }
+
+    /// If this conic section is an elliptical arc (i.e. its weight is less than 1), returns the
+    /// radii of the ellipse, the rotation of its x axis in radians, and the direction in which the
+    /// arc is swept, in the form expected by `Contour::push_svg_arc()`.
+    ///
+    /// The arc described by a conic section in standard form always spans less than π radians.
+    pub fn to_elliptical_arc(self) -> Option<(Vector2F, f32, ArcDirection)> {
+        let (p0, p2, w) = (self.0.baseline.from(), self.0.baseline.to(), self.0.weight);
+        let p1 = self.0.ctrl.from();
+        let one_minus_w2 = 1.0 - w * w;
+        if w <= 0.0 || one_minus_w2 <= EPSILON {
+            return None;
+        }
+
+        // The center lies on the line from the control point through the midpoint of the chord.
+        // The vector from the center to the curve midpoint and the half-chord scaled by
+        // `1 / sqrt(1 - w²)` form a pair of conjugate semi-diameters of the ellipse.
+        let chord_midpoint = (p0 + p2) * 0.5;
+        let center = p1 + (chord_midpoint - p1) * (1.0 / one_minus_w2);
+        let axis_a = self.sample(0.5) - center;
+        let axis_b = (p2 - p0) * (0.5 / one_minus_w2.sqrt());
+
+        // Extract the radii and rotation with a closed-form 2x2 singular value decomposition.
+        let (m11, m12, m21, m22) = (axis_a.x(), axis_b.x(), axis_a.y(), axis_b.y());
+        let (e, f) = ((m11 + m22) * 0.5, (m11 - m22) * 0.5);
+        let (g, h) = ((m21 + m12) * 0.5, (m21 - m12) * 0.5);
+        let (q, r) = ((e * e + h * h).sqrt(), (f * f + g * g).sqrt());
+        let radii = vec2f(q + r, (q - r).abs());
+        if radii.x() <= EPSILON || radii.y() <= EPSILON {
+            return None;
+        }
+        let x_axis_rotation = (f32::atan2(h, e) + f32::atan2(g, f)) * 0.5;
+        let direction = if axis_a.det(axis_b) >= 0.0 { ArcDirection::CW } else { ArcDirection::CCW };
+        Some((radii, x_axis_rotation, direction))
+    }
+
+    // Sederberg's error bound for approximating a conic with the quadratic Bézier curve that has
+    // the same control points.
+    fn quadratic_approximation_error(self) -> f32 {
+        let a = self.0.weight - 1.0;
+        let k = a / (4.0 * (2.0 + a));
+        let (p0, p2) = (self.0.baseline.from(), self.0.baseline.to());
+        let p1 = self.0.ctrl.from();
+        (p0 - p1 * 2.0 + p2).length() * k.abs()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{approx_parabola_integral, approx_parabola_inv_integral};
+    use crate::outline::ArcDirection;
+    use crate::segment::Segment;
+    use pathfinder_geometry::line_segment::LineSegment2F;
+    use pathfinder_geometry::transform2d::Transform2F;
+    use pathfinder_geometry::vector::vec2f;
+
+    #[test]
+    fn conic_arcs_lie_on_the_unit_circle() {
+        let quarter = Segment::quarter_circle_conic_arc();
+        let (before, after) = quarter.split(0.3);
+        for segment in &[quarter, before, after, Segment::conic_arc(0.5)] {
+            for step in 0..17 {
+                let radius = segment.sample(step as f32 / 16.0).length();
+                assert!((radius - 1.0).abs() < 0.0001, "{:?} {}", segment, radius);
+            }
+        }
+    }
+
+    #[test]
+    fn conic_subsegments_keep_the_parameterization() {
+        let conic = Segment::conic(LineSegment2F::new(vec2f(2.0, 0.0), vec2f(0.0, 1.0)),
+                                   vec2f(2.0, 1.0),
+                                   f32::sqrt(0.5));
+        let subsegment = conic.as_conic_segment().subsegment(0.25, 0.75);
+        assert!(subsegment.is_conic());
+        for &(t, subsegment_t) in &[(0.25, 0.0), (0.75, 1.0)] {
+            let expected = conic.as_conic_segment().sample(t);
+            let point = subsegment.as_conic_segment().sample(subsegment_t);
+            assert!((point - expected).length() < 0.0001, "{:?} {:?}", point, expected);
+        }
+
+        // The subsegment still lies on the ellipse.
+        for step in 0..9 {
+            let point = subsegment.as_conic_segment().sample(step as f32 / 8.0) * vec2f(0.5, 1.0);
+            assert!((point.length() - 1.0).abs() < 0.0001, "{:?}", point);
+        }
+    }
+
+    #[test]
+    fn conic_to_elliptical_arc() {
+        let baseline = LineSegment2F::new(vec2f(2.0, 0.0), vec2f(0.0, 1.0));
+        let conic = Segment::conic(baseline, vec2f(2.0, 1.0), f32::sqrt(0.5));
+        let (radius, x_axis_rotation, direction) =
+            conic.as_conic_segment().to_elliptical_arc().unwrap();
+        assert!((radius - vec2f(2.0, 1.0)).length() < 0.0001, "{:?}", radius);
+        assert!(x_axis_rotation.abs() < 0.0001, "{}", x_axis_rotation);
+        assert_eq!(direction, ArcDirection::CW);
+    }
+
+    #[test]
+    fn flattened_quadratics_are_within_tolerance() {
+        let quadratics = [
+            Segment::quadratic(LineSegment2F::new(vec2f(0.0, 0.0), vec2f(100.0, 0.0)),
+                               vec2f(50.0, 80.0)),
+            // A cusp-like curve passing through the vertex of its parabola.
+            Segment::quadratic(LineSegment2F::new(vec2f(0.0, 0.0), vec2f(10.0, 0.0)),
+                               vec2f(200.0, 1.0)),
+            // A straight line.
+            Segment::quadratic(LineSegment2F::new(vec2f(0.0, 0.0), vec2f(10.0, 10.0)),
+                               vec2f(5.0, 5.0)),
+        ];
+        for quadratic in &quadratics {
+            for &tolerance in &[1.0, 0.25, 0.01] {
+                let lines: Vec<_> = quadratic.as_quadratic_segment().flatten(tolerance).collect();
+                assert_eq!(lines[0].from(), quadratic.baseline.from());
+                assert_eq!(lines[lines.len() - 1].to(), quadratic.baseline.to());
+                for step in 0..257 {
+                    let point = quadratic.as_quadratic_segment().sample(step as f32 / 256.0);
+                    let distance = lines.iter().map(|line| {
+                        let t = (point - line.from()).dot(line.vector()) / line.square_length();
+                        (point - line.sample(t.max(0.0).min(1.0))).length()
+                    }).fold(f32::INFINITY, f32::min);
+                    assert!(distance <= tolerance * 1.01,
+                            "{:?} {} {} {}", quadratic, tolerance, lines.len(), distance);
+                }
+            }
+        }
+        let line = quadratics[2].as_quadratic_segment().flatten(0.25);
+        assert_eq!(line.count(), 1);
+    }
+
+    #[test]
+    fn flattened_line_counts_follow_the_tolerance() {
+        let quadratic = Segment::quadratic(LineSegment2F::new(vec2f(0.0, 0.0), vec2f(100.0, 0.0)),
+                                           vec2f(50.0, 80.0));
+        let line_count = |tolerance| quadratic.as_quadratic_segment().flatten(tolerance).count();
+
+        // The number of lines grows with the inverse square root of the tolerance.
+        let (coarse, fine) = (line_count(1.0), line_count(0.01));
+        assert!(coarse > 1);
+        assert!(fine >= coarse * 9 && fine <= coarse * 11, "{} {}", coarse, fine);
+
+        // Scaling the curve up is like scaling the tolerance down.
+        let scaled = Segment::quadratic(LineSegment2F::new(vec2f(0.0, 0.0), vec2f(400.0, 0.0)),
+                                        vec2f(200.0, 320.0));
+        assert_eq!(scaled.as_quadratic_segment().flatten(1.0).count(), line_count(0.25));
+
+        let flattener = quadratic.as_quadratic_segment().flatten(0.25);
+        assert_eq!(flattener.size_hint(), (line_count(0.25), Some(line_count(0.25))));
+    }
+
+    #[test]
+    fn degenerate_quadratics_flatten_to_a_bounded_number_of_lines() {
+        let point = Segment::quadratic(LineSegment2F::new(vec2f(1.0, 1.0), vec2f(1.0, 1.0)),
+                                       vec2f(1.0, 1.0));
+        let lines: Vec<_> = point.as_quadratic_segment().flatten(0.25).collect();
+        assert_eq!(lines, vec![LineSegment2F::new(vec2f(1.0, 1.0), vec2f(1.0, 1.0))]);
+
+        // A curve that doubles back on itself.
+        let doubled_back = Segment::quadratic(LineSegment2F::new(vec2f(0.0, 0.0),
+                                                                 vec2f(0.0, 0.0)),
+                                              vec2f(10.0, 0.0));
+        let line_count = doubled_back.as_quadratic_segment().flatten(0.25).count();
+        assert!(line_count >= 1 && line_count <= 1024, "{}", line_count);
+
+        // A tiny tolerance hits the cap.
+        let huge = Segment::quadratic(LineSegment2F::new(vec2f(0.0, 0.0), vec2f(1.0e6, 0.0)),
+                                      vec2f(5.0e5, 1.0e6));
+        assert_eq!(huge.as_quadratic_segment().flatten(0.0001).count(), 1024);
+    }
+
+    #[test]
+    fn parabola_integral_approximations_are_inverses() {
+        for step in -20..21 {
+            let x = step as f32 * 0.5;
+            let round_trip = approx_parabola_inv_integral(approx_parabola_integral(x));
+            assert!((round_trip - x).abs() <= 0.05 * x.abs().max(1.0), "{} {}", x, round_trip);
+        }
+    }
+
+    #[test]
+    fn cubic_to_quadratics_is_within_tolerance() {
+        let cubic = Segment::cubic(LineSegment2F::new(vec2f(0.0, 0.0), vec2f(100.0, 0.0)),
+                                   LineSegment2F::new(vec2f(10.0, 150.0), vec2f(120.0, -80.0)));
+        for &tolerance in &[1.0, 0.1] {
+            let quadratics: Vec<_> = cubic.as_cubic_segment().to_quadratics(tolerance).collect();
+            assert!(quadratics.len() > 1);
+            for (index, quadratic) in quadratics.iter().enumerate() {
+                assert!(quadratic.is_quadratic());
+                for step in 0..9 {
+                    let t = (index as f32 + step as f32 / 8.0) / quadratics.len() as f32;
+                    let point = quadratic.as_quadratic_segment().sample(step as f32 / 8.0);
+                    let distance = (point - cubic.as_cubic_segment().sample(t)).length();
+                    assert!(distance <= tolerance, "{:?} {} {}", quadratic, tolerance, distance);
+                }
+            }
+            let (first, last) = (quadratics[0], quadratics[quadratics.len() - 1]);
+            assert_eq!(first.baseline.from(), cubic.baseline.from());
+            assert_eq!(last.baseline.to(), cubic.baseline.to());
+        }
+    }
+
+    #[test]
+    fn conic_to_quadratics_is_within_tolerance() {
+        let conic = Segment::quarter_circle_conic_arc();
+        let quadratics = conic.as_conic_segment().to_quadratics(0.001);
+        assert!(quadratics.len() > 1);
+        for quadratic in &quadratics {
+            assert!(quadratic.is_quadratic());
+            for step in 0..9 {
+                let radius = quadratic.sample(step as f32 / 8.0).length();
+                assert!((radius - 1.0).abs() < 0.001, "{:?} {}", quadratic, radius);
+            }
+        }
+        let (first, last) = (quadratics[0], quadratics[quadratics.len() - 1]);
+        assert_eq!(first.baseline.from(), conic.baseline.from());
+        assert!((last.baseline.to() - conic.baseline.to()).length() < 0.0001);
+    }
+
+    #[test]
+    fn flattened_segments_are_within_tolerance() {
+        let segments = [
+            Segment::cubic(LineSegment2F::new(vec2f(0.0, 0.0), vec2f(100.0, 0.0)),
+                           LineSegment2F::new(vec2f(10.0, 150.0), vec2f(120.0, -80.0))),
+            Segment::quarter_circle_conic_arc().transform(&Transform2F::from_scale(100.0)),
+        ];
+        for segment in &segments {
+            let mut lines = vec![];
+            segment.flatten(0.25, |line| lines.push(line));
+            assert!(lines.len() > 1);
+            assert_eq!(lines[0].from(), segment.baseline.from());
+            assert!((lines[lines.len() - 1].to() - segment.baseline.to()).length() < 0.001);
+            for pair in lines.windows(2) {
+                assert!((pair[0].to() - pair[1].from()).length() < 0.001, "{:?}", pair);
+            }
+            for step in 0..257 {
+                let point = segment.sample(step as f32 / 256.0);
+                let distance = lines.iter().map(|line| {
+                    let t = (point - line.from()).dot(line.vector()) / line.square_length();
+                    (point - line.sample(t.max(0.0).min(1.0))).length()
+                }).fold(f32::INFINITY, f32::min);
+                assert!(distance <= 0.25 * 1.01, "{:?} {}", segment, distance);
+            }
+        }
+
+        let line = LineSegment2F::new(vec2f(1.0, 2.0), vec2f(3.0, 4.0));
+        let mut lines = vec![];
+        Segment::line(line).flatten(0.25, |line| lines.push(line));
+        assert_eq!(lines, [line]);
+    }
+}
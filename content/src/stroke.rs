@@ -275,8 +275,8 @@ impl Offset for Segment {
             let p4 = if self.is_line() {
                 self.baseline.to()
             } else {
-                // NB: If you change the representation of quadratic curves, you will need to
-                // change this.
+                // NB: If you change the representation of quadratic curves or conic sections, you
+                // will need to change this.
                 self.ctrl.from()
             };
 
@@ -293,7 +293,7 @@ impl Offset for Segment {
             return Segment::line(self.baseline.offset(distance));
         }
 
-        if self.is_quadratic() {
+        if self.has_single_control_point() {
             let mut segment_0 = LineSegment2F::new(self.baseline.from(), self.ctrl.from());
             let mut segment_1 = LineSegment2F::new(self.ctrl.from(), self.baseline.to());
             segment_0 = segment_0.offset(distance);
@@ -303,6 +303,11 @@ impl Offset for Segment {
                 None => segment_0.to().lerp(segment_1.from(), 0.5),
             };
             let baseline = LineSegment2F::new(segment_0.from(), segment_1.to());
+            // Keeping the weight makes offsets of circular arcs exact, since the weight of an
+            // arc depends only on its sweep angle.
+            if self.is_conic() {
+                return Segment::conic(baseline, ctrl, self.weight);
+            }
             return Segment::quadratic(baseline, ctrl);
         }
 
@@ -40,7 +40,7 @@ where
             segment.baseline.set_to(self.transform * segment.baseline.to());
             if !segment.is_line() {
                 segment.ctrl.set_from(self.transform * segment.ctrl.from());
-                if !segment.is_quadratic() {
+                if !segment.has_single_control_point() {
                     segment.ctrl.set_to(self.transform * segment.ctrl.to());
                 }
             }
@@ -87,7 +87,7 @@ where
             segment.baseline.set_to(self.perspective * segment.baseline.to());
             if !segment.is_line() {
                 segment.ctrl.set_from(self.perspective * segment.ctrl.from());
-                if !segment.is_quadratic() {
+                if !segment.has_single_control_point() {
                     segment.ctrl.set_to(self.perspective * segment.ctrl.to());
                 }
             }
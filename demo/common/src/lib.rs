@@ -22,6 +22,7 @@ use crate::device::{GroundProgram, GroundVertexArray};
 use crate::ui::{DemoUIModel, DemoUIPresenter, ScreenshotInfo, ScreenshotType, UIAction};
 use crate::window::{Event, Keycode, DataPath, Window, WindowSize};
 use clap::{App, Arg};
+use pathfinder_color::space::ColorSpace;
 use pathfinder_content::effects::DEFRINGING_KERNEL_CORE_GRAPHICS;
 use pathfinder_content::effects::PatternFilter;
 use pathfinder_content::effects::STEM_DARKENING_FACTORS;
@@ -36,7 +37,7 @@ use pathfinder_geometry::vector::{Vector2F, Vector2I, Vector4F, vec2f, vec2i};
 use pathfinder_gpu::Device;
 use pathfinder_renderer::concurrent::scene_proxy::SceneProxy;
 use pathfinder_renderer::gpu::options::{DestFramebuffer, RendererLevel};
-use pathfinder_renderer::gpu::options::{RendererMode, RendererOptions};
+use pathfinder_renderer::gpu::options::{RendererMode, RendererOptions, ToneMapping};
 use pathfinder_renderer::gpu::renderer::{DebugUIPresenterInfo, Renderer};
 use pathfinder_renderer::options::{BuildOptions, RenderTransform};
 use pathfinder_renderer::paint::Paint;
@@ -156,11 +157,13 @@ impl<W> DemoApp<W> where W: Window {
             viewport,
             window_size: window_size.device_size(),
         };
-        let render_mode = RendererMode { level };
+        let render_mode = RendererMode::new(level);
         let render_options = RendererOptions {
             dest: dest_framebuffer,
             background_color: None,
             show_debug_ui: true,
+            linear_blending: false,
+            tone_mapping: ToneMapping::None,
         };
 
         let filter = build_filter(&ui_model);
@@ -277,6 +280,9 @@ impl<W> DemoApp<W> where W: Window {
                 Vector2F::zero()
             },
             subpixel_aa_enabled: self.ui_model.subpixel_aa_effect_enabled,
+            color_space: ColorSpace::Srgb,
+            tile_cache: false,
+            ..BuildOptions::default()
         };
 
         self.scene_proxy.build(build_options);
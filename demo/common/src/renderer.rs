@@ -20,7 +20,7 @@ use pathfinder_gpu::{RenderState, RenderTarget, TextureData, TextureFormat, Unif
 use pathfinder_geometry::rect::RectI;
 use pathfinder_geometry::transform3d::Transform4F;
 use pathfinder_geometry::vector::{Vector2I, Vector4F};
-use pathfinder_renderer::gpu::options::{DestFramebuffer, RendererOptions};
+use pathfinder_renderer::gpu::options::{DestFramebuffer, RendererOptions, ToneMapping};
 use pathfinder_renderer::options::RenderTransform;
 use std::mem;
 use std::path::PathBuf;
@@ -78,6 +78,8 @@ impl<W> DemoApp<W> where W: Window {
                     dest: DestFramebuffer::Other(self.scene_framebuffer.take().unwrap()),
                     background_color: clear_color,
                     show_debug_ui: self.options.ui != UIVisibility::None,
+                    linear_blending: false,
+                    tone_mapping: ToneMapping::None,
                 };
                 2
             }
@@ -89,6 +91,8 @@ impl<W> DemoApp<W> where W: Window {
                     },
                     background_color: clear_color,
                     show_debug_ui: self.options.ui != UIVisibility::None,
+                    linear_blending: false,
+                    tone_mapping: ToneMapping::None,
                 };
                 1
             }
@@ -8,15 +8,33 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
+use pathfinder_color::ColorU;
+use pathfinder_content::gradient::{ColorStop, Gradient, GradientGeometry, GradientWrap};
+use pathfinder_content::gradient::{MeshGradient, MeshPatch};
 use pathfinder_content::outline::ContourIterFlags;
 use pathfinder_content::segment::SegmentKind;
-use pathfinder_geometry::vector::{Vector2F, vec2f};
-use pathfinder_renderer::scene::{DrawPathId, Scene};
+use pathfinder_geometry::rect::RectF;
+use pathfinder_geometry::transform2d::Transform2F;
+use pathfinder_geometry::util as geometry_util;
+use pathfinder_geometry::vector::{Vector2F, Vector2I, vec2f};
+use pathfinder_renderer::scene::{DrawPath, DrawPathId, Scene};
+use std::f32::consts::PI;
 use std::fmt;
 use std::io::{self, Write};
 
 mod pdf;
-use pdf::Pdf;
+use pdf::{Pdf, Shading};
+
+// The maximum error, in scene units, when approximating conics for formats that lack them.
+const CONIC_TOLERANCE: f32 = 0.01;
+
+// The maximum angle, in radians, that each patch covers when converting conic gradients to
+// meshes.
+const MAX_CONIC_GRADIENT_PATCH_ANGLE: f32 = PI * 0.25;
+
+// The length of each side of the image that a mesh gradient is rasterized into to pick the
+// fallback color for SVG viewers without mesh gradient support.
+const MESH_GRADIENT_FALLBACK_LENGTH: i32 = 16;
 
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
 pub enum FileFormat {
@@ -57,16 +75,309 @@ fn export_svg<W: Write>(scene: &Scene, writer: &mut W) -> io::Result<()> {
     for draw_path_index in 0..scene.draw_path_count() {
         let draw_path_id = DrawPathId(draw_path_index);
         let draw_path = scene.get_draw_path(draw_path_id);
-        write!(writer, "    <path")?;
-        if !draw_path.name.is_empty() {
-            write!(writer, " id=\"{}\"", draw_path.name)?;
+        let paint = scene.get_paint(draw_path.paint);
+        let gradient = match paint.gradient() {
+            None => {
+                let fill = svg_color_attributes("fill", paint.base_color());
+                write_svg_path(writer, draw_path, &fill)?;
+                continue;
+            }
+            Some(gradient) => gradient,
+        };
+
+        let id = format!("paint{}", draw_path_index);
+        let spread_method = match gradient.wrap {
+            GradientWrap::Clamp => "pad",
+            GradientWrap::Repeat => "repeat",
+        };
+        match gradient.geometry {
+            GradientGeometry::Linear(line) => {
+                writeln!(writer,
+                         "    <defs><linearGradient id=\"{}\" gradientUnits=\"userSpaceOnUse\" \
+                          x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" spreadMethod=\"{}\">",
+                         id,
+                         line.from_x(),
+                         line.from_y(),
+                         line.to_x(),
+                         line.to_y(),
+                         spread_method)?;
+                write_svg_stops(writer, gradient.stops())?;
+                writeln!(writer, "    </linearGradient></defs>")?;
+            }
+            GradientGeometry::Radial { line, radii, transform } => {
+                writeln!(writer,
+                         "    <defs><radialGradient id=\"{}\" gradientUnits=\"userSpaceOnUse\" \
+                          fx=\"{}\" fy=\"{}\" fr=\"{}\" cx=\"{}\" cy=\"{}\" r=\"{}\" \
+                          spreadMethod=\"{}\" gradientTransform=\"{}\">",
+                         id,
+                         line.from_x(),
+                         line.from_y(),
+                         radii.x(),
+                         line.to_x(),
+                         line.to_y(),
+                         radii.y(),
+                         spread_method,
+                         svg_matrix(transform))?;
+                write_svg_stops(writer, gradient.stops())?;
+                writeln!(writer, "    </radialGradient></defs>")?;
+            }
+            GradientGeometry::Conic { .. } | GradientGeometry::Mesh(_) => {
+                // SVG has no conic gradients, so convert them to meshes.
+                let conic_mesh;
+                let mesh = match gradient.geometry {
+                    GradientGeometry::Mesh(ref mesh) => mesh,
+                    _ => {
+                        conic_mesh = conic_gradient_to_mesh(gradient, draw_path.outline.bounds());
+                        &conic_mesh
+                    }
+                };
+                if mesh.is_empty() {
+                    write_svg_path(writer, draw_path, "fill=\"none\"")?;
+                    continue;
+                }
+
+                // Viewers that don't support SVG 2 mesh gradients fall back to a solid color.
+                write_svg_mesh_gradient(writer, &id, mesh)?;
+                let fallback = mesh_gradient_fallback_color(mesh);
+                let fill = format!("fill=\"url(#{}) #{:02x}{:02x}{:02x}\"",
+                                   id,
+                                   fallback.r,
+                                   fallback.g,
+                                   fallback.b);
+                write_svg_path(writer, draw_path, &fill)?;
+                continue;
+            }
         }
-        writeln!(writer, " fill=\"{:?}\" d=\"{:?}\" />", draw_path.paint, draw_path.outline)?;
+        write_svg_path(writer, draw_path, &format!("fill=\"url(#{})\"", id))?;
     }
     writeln!(writer, "</svg>")?;
     Ok(())
 }
 
+fn write_svg_path<W: Write>(writer: &mut W, draw_path: &DrawPath, fill: &str) -> io::Result<()> {
+    write!(writer, "    <path")?;
+    if !draw_path.name.is_empty() {
+        write!(writer, " id=\"{}\"", draw_path.name)?;
+    }
+    writeln!(writer, " {} d=\"{:?}\" />", fill, draw_path.outline)
+}
+
+fn write_svg_stops<W: Write>(writer: &mut W, stops: &[ColorStop]) -> io::Result<()> {
+    for stop in stops {
+        writeln!(writer,
+                 "        <stop offset=\"{}\" {} />",
+                 stop.offset,
+                 svg_color_attributes("stop-color", stop.color))?;
+    }
+    Ok(())
+}
+
+// Writes a mesh as an SVG 2 mesh gradient. SVG meshes are Coons patches, so the interior control
+// points of tensor-product patches are lost.
+//
+// An SVG mesh is a grid of patches in which neighbors share edges and corner colors. The patches
+// of a `MeshGradient` needn't touch at all, so they're laid out in a single row, joined by
+// degenerate patches that enclose no area.
+fn write_svg_mesh_gradient<W: Write>(writer: &mut W, id: &str, mesh: &MeshGradient)
+                                     -> io::Result<()> {
+    let patches = mesh.patches();
+    let origin = patches[0].point(0, 0);
+    writeln!(writer,
+             "    <defs><meshgradient id=\"{}\" gradientUnits=\"userSpaceOnUse\" x=\"{}\" \
+              y=\"{}\"><meshrow>",
+             id,
+             origin.x(),
+             origin.y())?;
+
+    write_svg_mesh_patch(writer, &patches[0], true)?;
+    for pair in patches.windows(2) {
+        for bridge in svg_mesh_bridge_patches(&pair[0], &pair[1]) {
+            write_svg_mesh_patch(writer, &bridge, false)?;
+        }
+        write_svg_mesh_patch(writer, &pair[1], false)?;
+    }
+
+    writeln!(writer, "    </meshrow></meshgradient></defs>")
+}
+
+// Writes one patch of a mesh row. Every patch but the first shares its left edge, and the colors
+// at either end of that edge, with the previous patch.
+fn write_svg_mesh_patch<W: Write>(writer: &mut W, patch: &MeshPatch, first: bool)
+                                  -> io::Result<()> {
+    writeln!(writer, "        <meshpatch>")?;
+
+    // Walk the boundary from corner to corner, starting at p00.
+    let corners = [(0, 0), (0, 3), (3, 3), (3, 0), (0, 0)];
+    let edge_count = if first { 4 } else { 3 };
+    for (edge_index, edge) in corners.windows(2).enumerate().take(edge_count) {
+        let ((i0, j0), (i1, j1)) = (edge[0], edge[1]);
+        let from = patch.point(i0, j0);
+        let ctrl0 = patch.point((i0 * 2 + i1) / 3, (j0 * 2 + j1) / 3) - from;
+        let ctrl1 = patch.point((i0 + i1 * 2) / 3, (j0 + j1 * 2) / 3) - from;
+        let to = patch.point(i1, j1) - from;
+        write!(writer,
+               "            <stop path=\"c {},{} {},{}",
+               ctrl0.x(),
+               ctrl0.y(),
+               ctrl1.x(),
+               ctrl1.y())?;
+        // The last edge implicitly returns to the first corner.
+        if edge_index < 3 {
+            write!(writer, " {},{}", to.x(), to.y())?;
+        }
+        write!(writer, "\"")?;
+        // The color at the start of the top edge of a later patch comes from the previous patch.
+        if first || edge_index > 0 {
+            write!(writer, " {}", svg_color_attributes("stop-color", patch.colors[edge_index]))?;
+        }
+        writeln!(writer, " />")?;
+    }
+
+    writeln!(writer, "        </meshpatch>")
+}
+
+// Returns the patches needed to join the right edge of `prev` to the left edge of `next` in an SVG
+// mesh row. None of them cover any area.
+fn svg_mesh_bridge_patches(prev: &MeshPatch, next: &MeshPatch) -> Vec<MeshPatch> {
+    let right = [prev.point(0, 3), prev.point(1, 3), prev.point(2, 3), prev.point(3, 3)];
+    let left = [next.point(0, 0), next.point(1, 0), next.point(2, 0), next.point(3, 0)];
+    let (prev_top, prev_bottom) = (prev.colors[1], prev.colors[2]);
+    let (next_top, next_bottom) = (next.colors[0], next.colors[3]);
+
+    // Boundary points are in the order that `MeshPatch::coons()` takes them: along the top edge,
+    // down the right edge, back along the bottom edge, and up the left edge.
+    if right == left {
+        if (prev_top, prev_bottom) == (next_top, next_bottom) {
+            return vec![];
+        }
+
+        // Only the colors change, so a single sliver along the shared edge will do.
+        let [a0, a1, a2, a3] = right;
+        return vec![MeshPatch::coons([a0, a0, a0, a0, a1, a2, a3, a3, a3, a3, a2, a1],
+                                     [prev_top, next_top, next_bottom, prev_bottom])];
+    }
+
+    // Shrink the right edge of `prev` to its top point, move that point to the top of `next`,
+    // and grow it into the left edge of `next`.
+    let [a0, a1, a2, a3] = right;
+    let [b0, b1, b2, b3] = left;
+    let (c1, c2) = (a0.lerp(b0, 1.0 / 3.0), a0.lerp(b0, 2.0 / 3.0));
+    vec![
+        MeshPatch::coons([a0, a0, a0, a0, a0, a0, a0, a1, a2, a3, a2, a1],
+                         [prev_top, prev_top, prev_top, prev_bottom]),
+        MeshPatch::coons([a0, c1, c2, b0, b0, b0, b0, c2, c1, a0, a0, a0],
+                         [prev_top, next_top, next_top, prev_top]),
+        MeshPatch::coons([b0, b0, b0, b0, b1, b2, b3, b2, b1, b0, b0, b0],
+                         [next_top, next_top, next_bottom, next_top]),
+    ]
+}
+
+// Rasterizes the mesh at a low resolution and averages the covered texels.
+fn mesh_gradient_fallback_color(mesh: &MeshGradient) -> ColorU {
+    let size = Vector2I::splat(MESH_GRADIENT_FALLBACK_LENGTH);
+    let (mut sum, mut total_alpha) = ([0.0; 3], 0.0);
+    for texel in mesh.rasterize(mesh.bounds(), size) {
+        let alpha = texel.a as f32;
+        sum[0] += texel.r as f32 * alpha;
+        sum[1] += texel.g as f32 * alpha;
+        sum[2] += texel.b as f32 * alpha;
+        total_alpha += alpha;
+    }
+    if total_alpha == 0.0 {
+        return ColorU::black();
+    }
+    let channel = |sum: f32| (sum / total_alpha).round() as u8;
+    ColorU::new(channel(sum[0]), channel(sum[1]), channel(sum[2]), 255)
+}
+
+fn svg_color_attributes(name: &str, color: ColorU) -> String {
+    let mut attributes = format!("{}=\"#{:02x}{:02x}{:02x}\"", name, color.r, color.g, color.b);
+    if !color.is_opaque() {
+        let opacity_name = match name {
+            "stop-color" => "stop-opacity".to_owned(),
+            _ => format!("{}-opacity", name),
+        };
+        attributes.push_str(&format!(" {}=\"{}\"", opacity_name, color.a as f32 / 255.0));
+    }
+    attributes
+}
+
+fn svg_matrix(transform: Transform2F) -> String {
+    format!("matrix({} {} {} {} {} {})",
+            transform.m11(),
+            transform.m21(),
+            transform.m12(),
+            transform.m22(),
+            transform.m13(),
+            transform.m23())
+}
+
+// Converts a conic gradient to a mesh of pie-slice Coons patches large enough to cover `bounds`.
+//
+// The slices are split at every color stop, so that the bilinear interpolation within each patch
+// matches the gradient.
+fn conic_gradient_to_mesh(gradient: &Gradient, bounds: RectF) -> MeshGradient {
+    let (center, angle, transform) = match gradient.geometry {
+        GradientGeometry::Conic { center, angle, transform } => (center, angle, transform),
+        _ => panic!("`conic_gradient_to_mesh()` requires a conic gradient!"),
+    };
+
+    let inverse_transform = transform.inverse();
+    let corners = [bounds.origin(), bounds.upper_right(), bounds.lower_right(),
+                   bounds.lower_left()];
+    let radius = corners.iter().fold(1.0, |radius: f32, &corner| {
+        radius.max(((inverse_transform * corner) - center).length())
+    });
+
+    let mut offsets = vec![0.0];
+    for stop in gradient.stops() {
+        if stop.offset > 0.0 && stop.offset < 1.0 {
+            offsets.push(stop.offset);
+        }
+    }
+    offsets.push(1.0);
+
+    let mut mesh = MeshGradient::new();
+    let max_offset_step = MAX_CONIC_GRADIENT_PATCH_ANGLE / (PI * 2.0);
+    for pair in offsets.windows(2) {
+        let (start_offset, end_offset) = (pair[0], pair[1]);
+        if end_offset <= start_offset {
+            continue;
+        }
+
+        let step_count = f32::ceil((end_offset - start_offset) / max_offset_step) as usize;
+        for step in 0..step_count {
+            let t0 = geometry_util::lerp(start_offset, end_offset, step as f32 / step_count as f32);
+            let t1 = geometry_util::lerp(start_offset,
+                                         end_offset,
+                                         (step + 1) as f32 / step_count as f32);
+
+            // Sample just inside the slice so that hard stops get the right colors.
+            let epsilon = (t1 - t0) * 0.0001;
+            let (color0, color1) = (gradient.sample(t0 + epsilon), gradient.sample(t1 - epsilon));
+
+            let (angle0, angle1) = (angle + t0 * PI * 2.0, angle + t1 * PI * 2.0);
+            let direction0 = vec2f(f32::cos(angle0), f32::sin(angle0));
+            let direction1 = vec2f(f32::cos(angle1), f32::sin(angle1));
+            let handle_length = 4.0 / 3.0 * f32::tan((angle1 - angle0) * 0.25) * radius;
+            let (p0, p3) = (center + direction0 * radius, center + direction1 * radius);
+            let p1 = p0 + vec2f(-direction0.y(), direction0.x()) * handle_length;
+            let p2 = p3 - vec2f(-direction1.y(), direction1.x()) * handle_length;
+
+            let third = |point: Vector2F, t: f32| center.lerp(point, t);
+            let boundary = [
+                center, center, center, center,
+                third(p3, 1.0 / 3.0), third(p3, 2.0 / 3.0), p3, p2,
+                p1, p0, third(p0, 2.0 / 3.0), third(p0, 1.0 / 3.0),
+            ];
+            mesh.push_patch(MeshPatch::coons(boundary, [color0, color1, color1, color0]));
+        }
+    }
+
+    mesh.apply_transform(transform);
+    mesh
+}
+
 fn export_pdf<W: Write>(scene: &Scene, writer: &mut W) -> io::Result<()> {
     let mut pdf = Pdf::new();
     let view_box = scene.view_box();
@@ -78,14 +389,41 @@ fn export_pdf<W: Write>(scene: &Scene, writer: &mut W) -> io::Result<()> {
         vec2f(r.x(), height - r.y())
     };
 
+    // Maps scene coordinates to PDF coordinates, like `tr`.
+    let page_transform = Transform2F::row_major(1.0, 0.0, -view_box.origin_x(),
+                                                0.0, -1.0, height + view_box.origin_y());
+
     for draw_path_index in 0..scene.draw_path_count() {
         let draw_path_id = DrawPathId(draw_path_index);
         let draw_path = scene.get_draw_path(draw_path_id);
 
-        // TODO(pcwalton): Gradients and patterns.
+        // TODO(pcwalton): Patterns.
+        // FIXME: PDF shadings can't repeat, so repeating gradients are clamped.
         let paint = scene.get_paint(draw_path.paint);
         if paint.is_color() {
             pdf.set_fill_color(paint.base_color());
+        } else if let Some(gradient) = paint.gradient() {
+            match gradient.geometry {
+                GradientGeometry::Linear(line) => {
+                    pdf.set_fill_shading(Shading::Axial { line, stops: gradient.stops() },
+                                         page_transform);
+                }
+                GradientGeometry::Radial { line, radii, transform } => {
+                    let shading = Shading::Radial {
+                        line,
+                        radii: [radii.x(), radii.y()],
+                        stops: gradient.stops(),
+                    };
+                    pdf.set_fill_shading(shading, page_transform * transform);
+                }
+                GradientGeometry::Conic { .. } => {
+                    let mesh = conic_gradient_to_mesh(gradient, draw_path.outline.bounds());
+                    pdf.set_fill_shading(Shading::Mesh(&mesh), page_transform);
+                }
+                GradientGeometry::Mesh(ref mesh) => {
+                    pdf.set_fill_shading(Shading::Mesh(mesh), page_transform);
+                }
+            }
         }
 
         for contour in draw_path.outline.contours() {
@@ -105,6 +443,16 @@ fn export_pdf<W: Write>(scene: &Scene, writer: &mut W) -> io::Result<()> {
                         let c2 = c * (2.0 / 3.0) + p * (1.0 / 3.0);
                         pdf.cubic_to(c1, c2, p);
                     }
+                    SegmentKind::Conic => {
+                        // PDF has no rational curves, so approximate with cubic curves.
+                        let conic = segment.as_conic_segment();
+                        for quadratic in conic.to_quadratics(CONIC_TOLERANCE) {
+                            let cubic = quadratic.to_cubic();
+                            pdf.cubic_to(tr(cubic.ctrl.from()),
+                                         tr(cubic.ctrl.to()),
+                                         tr(cubic.baseline.to()));
+                        }
+                    }
                     SegmentKind::Cubic => {
                         pdf.cubic_to(tr(segment.ctrl.from()),
                                      tr(segment.ctrl.to()),
@@ -173,6 +521,18 @@ fn export_ps<W: Write>(scene: &Scene, writer: &mut W) -> io::Result<()> {
                         let c2 = c * (2.0 / 3.0) + p * (1.0 / 3.0);
                         writeln!(writer, "{} {} {} curveto", P(c1), P(c2), P(p))?;
                     }
+                    SegmentKind::Conic => {
+                        // PostScript has no rational curves, so approximate with cubic curves.
+                        let conic = segment.as_conic_segment();
+                        for quadratic in conic.to_quadratics(CONIC_TOLERANCE) {
+                            let cubic = quadratic.to_cubic();
+                            writeln!(writer, "{} {} {} curveto",
+                                P(cubic.ctrl.from()),
+                                P(cubic.ctrl.to()),
+                                P(cubic.baseline.to())
+                            )?;
+                        }
+                    }
                     SegmentKind::Cubic => {
                         writeln!(writer, "{} {} {} curveto",
                             P(segment.ctrl.from()),
@@ -13,6 +13,9 @@
 
 use deflate::Compression;
 use pathfinder_color::ColorU;
+use pathfinder_content::gradient::{ColorStop, MeshGradient};
+use pathfinder_geometry::line_segment::LineSegment2F;
+use pathfinder_geometry::transform2d::Transform2F;
 use pathfinder_geometry::vector::Vector2F;
 use std::io::{self, Write};
 
@@ -66,6 +69,18 @@ pub struct Pdf {
     objects: Vec<PdfObject>,
     page_size: Option<Vector2F>,
     compression: Option<Compression>,
+    /// The object IDs of the patterns used on the current page
+    page_patterns: Vec<usize>,
+}
+
+/// A smooth shading that paths can be filled with
+pub enum Shading<'a> {
+    /// An axial shading along a line (shading type 2)
+    Axial { line: LineSegment2F, stops: &'a [ColorStop] },
+    /// A radial shading between two circles (shading type 3)
+    Radial { line: LineSegment2F, radii: [f32; 2], stops: &'a [ColorStop] },
+    /// A tensor-product patch mesh (shading type 7)
+    Mesh(&'a MeshGradient),
 }
 
 impl Default for Pdf {
@@ -95,7 +110,8 @@ impl Pdf {
                 },
             ],
             page_size: None,
-            compression: Some(Compression::Fast)
+            compression: Some(Compression::Fast),
+            page_patterns: Vec::new(),
         }
     }
 
@@ -120,6 +136,39 @@ impl Pdf {
         ).unwrap();
     }
 
+    /// Use the given shading, transformed by `matrix`, for all subsequent drawing operations
+    pub fn set_fill_shading(&mut self, shading: Shading, matrix: Transform2F) {
+        let shading_dictionary = match shading {
+            Shading::Axial { line, stops } => {
+                format!("<< /ShadingType 2 /ColorSpace /DeviceRGB /Coords [{} {} {} {}] \
+                         /Function {} /Extend [true true] >>",
+                        line.from_x(), line.from_y(), line.to_x(), line.to_y(),
+                        stops_function(stops))
+            }
+            Shading::Radial { line, radii, stops } => {
+                format!("<< /ShadingType 3 /ColorSpace /DeviceRGB \
+                         /Coords [{} {} {} {} {} {}] /Function {} /Extend [true true] >>",
+                        line.from_x(), line.from_y(), radii[0],
+                        line.to_x(), line.to_y(), radii[1],
+                        stops_function(stops))
+            }
+            Shading::Mesh(mesh) => {
+                let stream = mesh_shading_stream(mesh);
+                let shading_id = self.add_object(stream, false, false);
+                format!("{} 0 R", shading_id)
+            }
+        };
+
+        let pattern = format!("<< /Type /Pattern /PatternType 2 /Shading {} \
+                               /Matrix [{} {} {} {} {} {}] >>\n",
+                              shading_dictionary,
+                              matrix.m11(), matrix.m21(), matrix.m12(), matrix.m22(),
+                              matrix.m13(), matrix.m23());
+        let pattern_id = self.add_object(pattern.into_bytes(), false, false);
+        writeln!(self.page_buffer, "/Pattern cs /P{} scn", self.page_patterns.len()).unwrap();
+        self.page_patterns.push(pattern_id);
+    }
+
     /// Move to a new page in the PDF document
     #[inline]
     pub fn add_page(&mut self, size: Vector2F) {
@@ -189,6 +238,14 @@ impl Pdf {
             write!(page_object, "/XObject {} 0 R ", idx+1).unwrap();
         }
 
+        if !self.page_patterns.is_empty() {
+            write!(page_object, "/Pattern << ").unwrap();
+            for (index, pattern_id) in self.page_patterns.drain(..).enumerate() {
+                write!(page_object, "/P{} {} 0 R ", index, pattern_id).unwrap();
+            }
+            write!(page_object, ">> ").unwrap();
+        }
+
         write!(page_object,
             " >>\n \
                 /MediaBox [0 0 {} {}]\n \
@@ -258,3 +315,80 @@ impl Pdf {
         Ok(())
     }
 }
+
+fn color_components(color: ColorU) -> String {
+    let norm = |color| f32::from(color) / 255.0;
+    format!("{} {} {}", norm(color.r), norm(color.g), norm(color.b))
+}
+
+/// Builds a function that maps the domain [0 1] onto the given color stops
+fn stops_function(stops: &[ColorStop]) -> String {
+    let interpolate = |from: ColorU, to: ColorU| {
+        format!("<< /FunctionType 2 /Domain [0 1] /C0 [{}] /C1 [{}] /N 1 >>",
+                color_components(from),
+                color_components(to))
+    };
+
+    let (first, last) = match (stops.first(), stops.last()) {
+        (Some(first), Some(last)) => (first, last),
+        _ => return interpolate(ColorU::black(), ColorU::black()),
+    };
+
+    // Extend the outermost colors to the ends of the domain.
+    let mut stops = stops.to_vec();
+    if first.offset > 0.0 {
+        stops.insert(0, ColorStop::new(first.color, 0.0));
+    }
+    if last.offset < 1.0 {
+        stops.push(ColorStop::new(last.color, 1.0));
+    }
+    if stops.len() == 1 {
+        return interpolate(stops[0].color, stops[0].color);
+    }
+
+    let mut functions = String::new();
+    let mut bounds = String::new();
+    let mut encode = String::new();
+    for (index, pair) in stops.windows(2).enumerate() {
+        functions.push_str(&interpolate(pair[0].color, pair[1].color));
+        encode.push_str("0 1 ");
+        if index > 0 {
+            bounds.push_str(&format!("{} ", pair[0].offset));
+        }
+    }
+    format!("<< /FunctionType 3 /Domain [0 1] /Functions [{}] /Bounds [{}] /Encode [{}] >>",
+            functions,
+            bounds.trim_end(),
+            encode.trim_end())
+}
+
+/// Encodes the patches of a mesh gradient as a type 7 shading stream
+fn mesh_shading_stream(mesh: &MeshGradient) -> Vec<u8> {
+    let bounds = mesh.bounds();
+    let (origin, size) = (bounds.origin(), bounds.size().max(Vector2F::splat(1.0)));
+
+    let mut data = vec![];
+    for patch in mesh.patches() {
+        // Each patch is independent, so its edge flag is always 0.
+        data.push(0);
+        for point in patch.points_in_pdf_order().iter() {
+            let normalized = (*point - origin) / size;
+            for &coordinate in &[normalized.x(), normalized.y()] {
+                let value = (coordinate.max(0.0).min(1.0) as f64 * u32::MAX as f64) as u32;
+                data.extend_from_slice(&value.to_be_bytes());
+            }
+        }
+        for color in &patch.colors {
+            data.extend_from_slice(&[color.r, color.g, color.b]);
+        }
+    }
+
+    let mut stream = format!(
+        "<< /ShadingType 7 /ColorSpace /DeviceRGB /BitsPerCoordinate 32 /BitsPerComponent 8 \
+         /BitsPerFlag 8 /Decode [{} {} {} {} 0 1 0 1 0 1] /Length {} >>\nstream\n",
+        origin.x(), origin.x() + size.x(), origin.y(), origin.y() + size.y(), data.len()
+    ).into_bytes();
+    stream.extend(data);
+    stream.extend(b"\nendstream\n");
+    stream
+}
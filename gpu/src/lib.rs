@@ -16,6 +16,7 @@ extern crate bitflags;
 extern crate log;
 
 pub mod allocator;
+pub mod recording;
 
 use half::f16;
 use image::ImageFormat;
@@ -267,7 +268,7 @@ pub struct ComputeDimensions {
     pub z: u32,
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug)]
 pub enum UniformData {
     Float(f32),
     IVec2(I32x2),
@@ -280,7 +281,7 @@ pub enum UniformData {
     Vec4(F32x4),
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Primitive {
     Triangles,
     Lines,
@@ -497,6 +498,16 @@ impl TextureFormat {
             TextureFormat::RGBA32F => 16,
         }
     }
+
+    /// Returns true if this format stores floating-point components, which can hold values
+    /// outside the [0, 1] range.
+    #[inline]
+    pub fn is_float(self) -> bool {
+        match self {
+            TextureFormat::R8 | TextureFormat::RGBA8 => false,
+            TextureFormat::R16F | TextureFormat::RGBA16F | TextureFormat::RGBA32F => true,
+        }
+    }
 }
 
 impl ClearOps {
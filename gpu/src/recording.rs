@@ -0,0 +1,594 @@
+// pathfinder/gpu/src/recording.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A device that records every call made to it instead of rendering anything.
+//!
+//! This is useful for testing code that drives a `Device`, like the renderer, without a GPU. The
+//! recorded commands can be inspected to check batching, texture uploads, and render target
+//! usage. Reads return zeroed data, and timer queries complete immediately with zero durations.
+
+use crate::{BufferData, BufferTarget, BufferUploadMode, ComputeDimensions, ComputeState, Device};
+use crate::{FeatureLevel, ImageAccess, Primitive, ProgramKind, RenderOptions, RenderState};
+use crate::{RenderTarget, ShaderKind, TextureData, TextureDataRef, TextureFormat};
+use crate::{TextureSamplingFlags, UniformData, VertexAttrDescriptor};
+use half::f16;
+use pathfinder_geometry::rect::RectI;
+use pathfinder_geometry::vector::Vector2I;
+use pathfinder_resources::ResourceLoader;
+use std::cell::{Cell, Ref, RefCell};
+use std::mem;
+use std::ops::Range;
+use std::time::Duration;
+
+/// A `Device` that records the calls made to it.
+pub struct RecordingDevice {
+    feature_level: FeatureLevel,
+    commands: RefCell<Vec<RecordedCommand>>,
+    next_id: Cell<u32>,
+}
+
+/// A call made to a `RecordingDevice`.
+///
+/// Objects are referred to by the IDs of their handles, which are unique across all objects
+/// created by the device.
+#[derive(Clone, Debug)]
+pub enum RecordedCommand {
+    CreateTexture { texture: u32, format: TextureFormat, size: Vector2I },
+    UploadToTexture { texture: u32, rect: RectI },
+    SetTextureSamplingMode { texture: u32, flags: TextureSamplingFlags },
+    CreateFramebuffer { framebuffer: u32, texture: u32 },
+    DestroyFramebuffer { framebuffer: u32 },
+    CreateBuffer { buffer: u32, mode: BufferUploadMode },
+    /// Allocates storage for a buffer. The size is in bytes, and `initialized` is true if data
+    /// was uploaded along with the allocation.
+    AllocateBuffer { buffer: u32, size: usize, target: BufferTarget, initialized: bool },
+    /// Uploads data to a buffer. The position and size are in bytes.
+    UploadToBuffer { buffer: u32, position: usize, size: usize, target: BufferTarget },
+    CreateShader { name: String, kind: ShaderKind },
+    CreateProgram { program: u32, name: String },
+    CreateVertexArray { vertex_array: u32 },
+    BindBuffer { vertex_array: u32, buffer: u32, target: BufferTarget },
+    ConfigureVertexAttr { vertex_array: u32, attr: String, descriptor: VertexAttrDescriptor },
+    ReadPixels { target: RecordedRenderTarget, viewport: RectI },
+    ReadBuffer { buffer: u32, target: BufferTarget, range: Range<usize> },
+    BeginCommands,
+    EndCommands,
+    Draw(RecordedDraw),
+    DispatchCompute(RecordedDispatch),
+    AddFence,
+    WaitForFence,
+    BeginTimerQuery,
+    EndTimerQuery,
+}
+
+/// The kind of draw call.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DrawCall {
+    Arrays,
+    Elements,
+    ElementsInstanced { instance_count: u32 },
+}
+
+/// Where a draw call renders to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RecordedRenderTarget {
+    Default,
+    Framebuffer(u32),
+}
+
+/// A draw call, with the render state it was made with.
+#[derive(Clone, Debug)]
+pub struct RecordedDraw {
+    pub call: DrawCall,
+    pub index_count: u32,
+    pub target: RecordedRenderTarget,
+    pub program: u32,
+    pub program_name: String,
+    pub vertex_array: u32,
+    pub primitive: Primitive,
+    pub uniforms: Vec<(String, UniformData)>,
+    /// The texture parameters and the IDs of the textures bound to them.
+    pub textures: Vec<(String, u32)>,
+    pub images: Vec<(String, u32, ImageAccess)>,
+    /// The storage buffers and the IDs of the buffers bound to them.
+    pub storage_buffers: Vec<(String, u32)>,
+    pub viewport: RectI,
+    pub options: RenderOptions,
+}
+
+/// A compute shader dispatch, with the state it was made with.
+#[derive(Clone, Debug)]
+pub struct RecordedDispatch {
+    pub dimensions: ComputeDimensions,
+    pub program: u32,
+    pub program_name: String,
+    pub uniforms: Vec<(String, UniformData)>,
+    pub textures: Vec<(String, u32)>,
+    pub images: Vec<(String, u32, ImageAccess)>,
+    pub storage_buffers: Vec<(String, u32)>,
+}
+
+pub struct RecordingBuffer {
+    pub id: u32,
+    pub mode: BufferUploadMode,
+}
+
+pub struct RecordingBufferDataReceiver {
+    pub size: usize,
+}
+
+pub struct RecordingFence;
+
+pub struct RecordingFramebuffer {
+    pub id: u32,
+    pub texture: RecordingTexture,
+}
+
+pub struct RecordingImageParameter {
+    pub name: String,
+}
+
+pub struct RecordingProgram {
+    pub id: u32,
+    pub name: String,
+    pub local_size: Option<ComputeDimensions>,
+}
+
+pub struct RecordingShader {
+    pub name: String,
+    pub kind: ShaderKind,
+}
+
+pub struct RecordingStorageBuffer {
+    pub name: String,
+    pub binding: u32,
+}
+
+pub struct RecordingTexture {
+    pub id: u32,
+    pub format: TextureFormat,
+    pub size: Vector2I,
+}
+
+pub struct RecordingTextureDataReceiver {
+    pub format: TextureFormat,
+    pub size: Vector2I,
+}
+
+pub struct RecordingTextureParameter {
+    pub name: String,
+}
+
+pub struct RecordingTimerQuery;
+
+pub struct RecordingUniform {
+    pub name: String,
+}
+
+pub struct RecordingVertexArray {
+    pub id: u32,
+}
+
+pub struct RecordingVertexAttr {
+    pub name: String,
+}
+
+impl RecordingDevice {
+    /// Creates a new recording device that reports the given feature level.
+    #[inline]
+    pub fn new(feature_level: FeatureLevel) -> RecordingDevice {
+        RecordingDevice {
+            feature_level,
+            commands: RefCell::new(vec![]),
+            next_id: Cell::new(0),
+        }
+    }
+
+    /// Returns the commands recorded so far.
+    #[inline]
+    pub fn commands(&self) -> Ref<'_, [RecordedCommand]> {
+        Ref::map(self.commands.borrow(), |commands| &commands[..])
+    }
+
+    /// Returns the commands recorded so far and clears the log.
+    #[inline]
+    pub fn take_commands(&self) -> Vec<RecordedCommand> {
+        mem::take(&mut *self.commands.borrow_mut())
+    }
+
+    /// Returns the draw calls recorded so far.
+    pub fn draws(&self) -> Vec<RecordedDraw> {
+        self.commands.borrow().iter().filter_map(|command| {
+            match *command {
+                RecordedCommand::Draw(ref draw) => Some(draw.clone()),
+                _ => None,
+            }
+        }).collect()
+    }
+
+    fn record(&self, command: RecordedCommand) {
+        self.commands.borrow_mut().push(command);
+    }
+
+    fn allocate_id(&self) -> u32 {
+        let id = self.next_id.get();
+        self.next_id.set(id + 1);
+        id
+    }
+
+    fn record_draw(&self, call: DrawCall, index_count: u32, render_state: &RenderState<Self>) {
+        self.record(RecordedCommand::Draw(RecordedDraw {
+            call,
+            index_count,
+            target: record_render_target(render_state.target),
+            program: render_state.program.id,
+            program_name: render_state.program.name.clone(),
+            vertex_array: render_state.vertex_array.id,
+            primitive: render_state.primitive,
+            uniforms: record_uniforms(render_state.uniforms),
+            textures: record_textures(render_state.textures),
+            images: record_images(render_state.images),
+            storage_buffers: record_storage_buffers(render_state.storage_buffers),
+            viewport: render_state.viewport,
+            options: render_state.options.clone(),
+        }));
+    }
+}
+
+impl Device for RecordingDevice {
+    type Buffer = RecordingBuffer;
+    type BufferDataReceiver = RecordingBufferDataReceiver;
+    type Fence = RecordingFence;
+    type Framebuffer = RecordingFramebuffer;
+    type ImageParameter = RecordingImageParameter;
+    type Program = RecordingProgram;
+    type Shader = RecordingShader;
+    type StorageBuffer = RecordingStorageBuffer;
+    type Texture = RecordingTexture;
+    type TextureDataReceiver = RecordingTextureDataReceiver;
+    type TextureParameter = RecordingTextureParameter;
+    type TimerQuery = RecordingTimerQuery;
+    type Uniform = RecordingUniform;
+    type VertexArray = RecordingVertexArray;
+    type VertexAttr = RecordingVertexAttr;
+
+    #[inline]
+    fn backend_name(&self) -> &'static str {
+        "Recording"
+    }
+
+    #[inline]
+    fn device_name(&self) -> String {
+        "Recording device".to_owned()
+    }
+
+    #[inline]
+    fn feature_level(&self) -> FeatureLevel {
+        self.feature_level
+    }
+
+    fn create_texture(&self, format: TextureFormat, size: Vector2I) -> RecordingTexture {
+        let texture = RecordingTexture { id: self.allocate_id(), format, size };
+        self.record(RecordedCommand::CreateTexture { texture: texture.id, format, size });
+        texture
+    }
+
+    fn create_texture_from_data(&self, format: TextureFormat, size: Vector2I, data: TextureDataRef)
+                                -> RecordingTexture {
+        let texture = self.create_texture(format, size);
+        self.upload_to_texture(&texture, RectI::new(Vector2I::zero(), size), data);
+        texture
+    }
+
+    fn create_shader(&self, _: &dyn ResourceLoader, name: &str, kind: ShaderKind)
+                     -> RecordingShader {
+        self.record(RecordedCommand::CreateShader { name: name.to_owned(), kind });
+        RecordingShader { name: name.to_owned(), kind }
+    }
+
+    fn create_shader_from_source(&self, name: &str, _: &[u8], kind: ShaderKind)
+                                 -> RecordingShader {
+        self.record(RecordedCommand::CreateShader { name: name.to_owned(), kind });
+        RecordingShader { name: name.to_owned(), kind }
+    }
+
+    fn create_vertex_array(&self) -> RecordingVertexArray {
+        let vertex_array = RecordingVertexArray { id: self.allocate_id() };
+        self.record(RecordedCommand::CreateVertexArray { vertex_array: vertex_array.id });
+        vertex_array
+    }
+
+    fn create_program_from_shaders(&self,
+                                   _: &dyn ResourceLoader,
+                                   name: &str,
+                                   _: ProgramKind<RecordingShader>)
+                                   -> RecordingProgram {
+        let program = RecordingProgram {
+            id: self.allocate_id(),
+            name: name.to_owned(),
+            local_size: None,
+        };
+        self.record(RecordedCommand::CreateProgram {
+            program: program.id,
+            name: program.name.clone(),
+        });
+        program
+    }
+
+    fn set_compute_program_local_size(&self,
+                                      program: &mut RecordingProgram,
+                                      local_size: ComputeDimensions) {
+        program.local_size = Some(local_size);
+    }
+
+    fn get_vertex_attr(&self, _: &RecordingProgram, name: &str) -> Option<RecordingVertexAttr> {
+        Some(RecordingVertexAttr { name: name.to_owned() })
+    }
+
+    fn get_uniform(&self, _: &RecordingProgram, name: &str) -> RecordingUniform {
+        RecordingUniform { name: name.to_owned() }
+    }
+
+    fn get_texture_parameter(&self, _: &RecordingProgram, name: &str)
+                             -> RecordingTextureParameter {
+        RecordingTextureParameter { name: name.to_owned() }
+    }
+
+    fn get_image_parameter(&self, _: &RecordingProgram, name: &str) -> RecordingImageParameter {
+        RecordingImageParameter { name: name.to_owned() }
+    }
+
+    fn get_storage_buffer(&self, _: &RecordingProgram, name: &str, binding: u32)
+                          -> RecordingStorageBuffer {
+        RecordingStorageBuffer { name: name.to_owned(), binding }
+    }
+
+    fn bind_buffer(&self,
+                   vertex_array: &RecordingVertexArray,
+                   buffer: &RecordingBuffer,
+                   target: BufferTarget) {
+        self.record(RecordedCommand::BindBuffer {
+            vertex_array: vertex_array.id,
+            buffer: buffer.id,
+            target,
+        });
+    }
+
+    fn configure_vertex_attr(&self,
+                             vertex_array: &RecordingVertexArray,
+                             attr: &RecordingVertexAttr,
+                             descriptor: &VertexAttrDescriptor) {
+        self.record(RecordedCommand::ConfigureVertexAttr {
+            vertex_array: vertex_array.id,
+            attr: attr.name.clone(),
+            descriptor: *descriptor,
+        });
+    }
+
+    fn create_framebuffer(&self, texture: RecordingTexture) -> RecordingFramebuffer {
+        let framebuffer = RecordingFramebuffer { id: self.allocate_id(), texture };
+        self.record(RecordedCommand::CreateFramebuffer {
+            framebuffer: framebuffer.id,
+            texture: framebuffer.texture.id,
+        });
+        framebuffer
+    }
+
+    fn create_buffer(&self, mode: BufferUploadMode) -> RecordingBuffer {
+        let buffer = RecordingBuffer { id: self.allocate_id(), mode };
+        self.record(RecordedCommand::CreateBuffer { buffer: buffer.id, mode });
+        buffer
+    }
+
+    fn allocate_buffer<T>(&self,
+                          buffer: &RecordingBuffer,
+                          data: BufferData<T>,
+                          target: BufferTarget) {
+        let (len, initialized) = match data {
+            BufferData::Uninitialized(len) => (len, false),
+            BufferData::Memory(data) => (data.len(), true),
+        };
+        self.record(RecordedCommand::AllocateBuffer {
+            buffer: buffer.id,
+            size: len * mem::size_of::<T>(),
+            target,
+            initialized,
+        });
+    }
+
+    fn upload_to_buffer<T>(&self,
+                           buffer: &RecordingBuffer,
+                           position: usize,
+                           data: &[T],
+                           target: BufferTarget) {
+        self.record(RecordedCommand::UploadToBuffer {
+            buffer: buffer.id,
+            position,
+            size: mem::size_of_val(data),
+            target,
+        });
+    }
+
+    #[inline]
+    fn framebuffer_texture<'f>(&self, framebuffer: &'f RecordingFramebuffer)
+                               -> &'f RecordingTexture {
+        &framebuffer.texture
+    }
+
+    fn destroy_framebuffer(&self, framebuffer: RecordingFramebuffer) -> RecordingTexture {
+        self.record(RecordedCommand::DestroyFramebuffer { framebuffer: framebuffer.id });
+        framebuffer.texture
+    }
+
+    #[inline]
+    fn texture_format(&self, texture: &RecordingTexture) -> TextureFormat {
+        texture.format
+    }
+
+    #[inline]
+    fn texture_size(&self, texture: &RecordingTexture) -> Vector2I {
+        texture.size
+    }
+
+    fn set_texture_sampling_mode(&self, texture: &RecordingTexture, flags: TextureSamplingFlags) {
+        self.record(RecordedCommand::SetTextureSamplingMode { texture: texture.id, flags });
+    }
+
+    fn upload_to_texture(&self, texture: &RecordingTexture, rect: RectI, _: TextureDataRef) {
+        self.record(RecordedCommand::UploadToTexture { texture: texture.id, rect });
+    }
+
+    fn read_pixels(&self, target: &RenderTarget<RecordingDevice>, viewport: RectI)
+                   -> RecordingTextureDataReceiver {
+        self.record(RecordedCommand::ReadPixels {
+            target: record_render_target(target),
+            viewport,
+        });
+        let format = match *target {
+            RenderTarget::Default => TextureFormat::RGBA8,
+            RenderTarget::Framebuffer(framebuffer) => framebuffer.texture.format,
+        };
+        RecordingTextureDataReceiver { format, size: viewport.size() }
+    }
+
+    fn read_buffer(&self, buffer: &RecordingBuffer, target: BufferTarget, range: Range<usize>)
+                   -> RecordingBufferDataReceiver {
+        let size = range.end - range.start;
+        self.record(RecordedCommand::ReadBuffer { buffer: buffer.id, target, range });
+        RecordingBufferDataReceiver { size }
+    }
+
+    fn begin_commands(&self) {
+        self.record(RecordedCommand::BeginCommands);
+    }
+
+    fn end_commands(&self) {
+        self.record(RecordedCommand::EndCommands);
+    }
+
+    fn draw_arrays(&self, index_count: u32, render_state: &RenderState<Self>) {
+        self.record_draw(DrawCall::Arrays, index_count, render_state);
+    }
+
+    fn draw_elements(&self, index_count: u32, render_state: &RenderState<Self>) {
+        self.record_draw(DrawCall::Elements, index_count, render_state);
+    }
+
+    fn draw_elements_instanced(&self,
+                               index_count: u32,
+                               instance_count: u32,
+                               render_state: &RenderState<Self>) {
+        self.record_draw(DrawCall::ElementsInstanced { instance_count },
+                         index_count,
+                         render_state);
+    }
+
+    fn dispatch_compute(&self, dimensions: ComputeDimensions, state: &ComputeState<Self>) {
+        self.record(RecordedCommand::DispatchCompute(RecordedDispatch {
+            dimensions,
+            program: state.program.id,
+            program_name: state.program.name.clone(),
+            uniforms: record_uniforms(state.uniforms),
+            textures: record_textures(state.textures),
+            images: record_images(state.images),
+            storage_buffers: record_storage_buffers(state.storage_buffers),
+        }));
+    }
+
+    fn add_fence(&self) -> RecordingFence {
+        self.record(RecordedCommand::AddFence);
+        RecordingFence
+    }
+
+    fn wait_for_fence(&self, _: &RecordingFence) {
+        self.record(RecordedCommand::WaitForFence);
+    }
+
+    #[inline]
+    fn create_timer_query(&self) -> RecordingTimerQuery {
+        RecordingTimerQuery
+    }
+
+    fn begin_timer_query(&self, _: &RecordingTimerQuery) {
+        self.record(RecordedCommand::BeginTimerQuery);
+    }
+
+    fn end_timer_query(&self, _: &RecordingTimerQuery) {
+        self.record(RecordedCommand::EndTimerQuery);
+    }
+
+    #[inline]
+    fn try_recv_timer_query(&self, _: &RecordingTimerQuery) -> Option<Duration> {
+        Some(Duration::default())
+    }
+
+    #[inline]
+    fn recv_timer_query(&self, _: &RecordingTimerQuery) -> Duration {
+        Duration::default()
+    }
+
+    #[inline]
+    fn try_recv_texture_data(&self, receiver: &RecordingTextureDataReceiver)
+                             -> Option<TextureData> {
+        Some(self.recv_texture_data(receiver))
+    }
+
+    fn recv_texture_data(&self, receiver: &RecordingTextureDataReceiver) -> TextureData {
+        let len = receiver.size.x() as usize * receiver.size.y() as usize *
+            receiver.format.channels();
+        match receiver.format {
+            TextureFormat::R8 | TextureFormat::RGBA8 => TextureData::U8(vec![0; len]),
+            TextureFormat::R16F | TextureFormat::RGBA16F => {
+                TextureData::F16(vec![f16::default(); len])
+            }
+            TextureFormat::RGBA32F => TextureData::F32(vec![0.0; len]),
+        }
+    }
+
+    #[inline]
+    fn try_recv_buffer(&self, receiver: &RecordingBufferDataReceiver) -> Option<Vec<u8>> {
+        Some(self.recv_buffer(receiver))
+    }
+
+    #[inline]
+    fn recv_buffer(&self, receiver: &RecordingBufferDataReceiver) -> Vec<u8> {
+        vec![0; receiver.size]
+    }
+}
+
+fn record_render_target(target: &RenderTarget<RecordingDevice>) -> RecordedRenderTarget {
+    match *target {
+        RenderTarget::Default => RecordedRenderTarget::Default,
+        RenderTarget::Framebuffer(framebuffer) => RecordedRenderTarget::Framebuffer(framebuffer.id),
+    }
+}
+
+fn record_uniforms(uniforms: &[(&RecordingUniform, UniformData)]) -> Vec<(String, UniformData)> {
+    uniforms.iter().map(|&(uniform, data)| (uniform.name.clone(), data)).collect()
+}
+
+fn record_textures(textures: &[(&RecordingTextureParameter, &RecordingTexture)])
+                   -> Vec<(String, u32)> {
+    textures.iter().map(|&(parameter, texture)| (parameter.name.clone(), texture.id)).collect()
+}
+
+fn record_images(images: &[(&RecordingImageParameter, &RecordingTexture, ImageAccess)])
+                 -> Vec<(String, u32, ImageAccess)> {
+    images.iter().map(|&(parameter, texture, access)| {
+        (parameter.name.clone(), texture.id, access)
+    }).collect()
+}
+
+fn record_storage_buffers(storage_buffers: &[(&RecordingStorageBuffer, &RecordingBuffer)])
+                          -> Vec<(String, u32)> {
+    storage_buffers.iter().map(|&(storage_buffer, buffer)| {
+        (storage_buffer.name.clone(), buffer.id)
+    }).collect()
+}
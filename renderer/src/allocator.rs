@@ -13,9 +13,13 @@
 use crate::gpu_data::{TextureLocation, TexturePageId};
 use pathfinder_geometry::rect::RectI;
 use pathfinder_geometry::vector::{Vector2F, Vector2I, vec2f, vec2i};
+use pathfinder_gpu::TextureFormat;
 
 const ATLAS_TEXTURE_LENGTH: u32 = 1024;
 
+// How many frames a page may stay empty, waiting to be reused, before it's freed.
+const EMPTY_PAGE_RETENTION_FRAMES: u32 = 2;
+
 #[derive(Clone, Debug)]
 pub struct TextureAllocator {
     pages: Vec<Option<TexturePage>>,
@@ -24,15 +28,19 @@ pub struct TextureAllocator {
 #[derive(Clone, Debug)]
 pub struct TexturePage {
     allocator: TexturePageAllocator,
+    format: TextureFormat,
+    usage: TexturePageUsage,
     is_new: bool,
+    // The number of frames in a row that this page has been empty.
+    empty_frame_count: u32,
 }
 
 #[derive(Clone, Debug)]
 pub enum TexturePageAllocator {
     // An atlas allocated with our quadtree allocator.
     Atlas(TextureAtlasAllocator),
-    // A single image.
-    Image { size: Vector2I },
+    // A single image, or nothing if the image has been freed and the page awaits reuse.
+    Image { size: Vector2I, is_allocated: bool },
 }
 
 #[derive(Clone, Debug)]
@@ -50,10 +58,21 @@ enum TreeNode {
 }
 
 #[derive(Clone, Copy, PartialEq, Debug)]
-#[allow(dead_code)]
+pub enum TexturePageUsage {
+    // Textures that the CPU uploads: gradients, images, and so on.
+    Paint,
+    // Render targets that the GPU draws to.
+    RenderTarget,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub enum AllocationMode {
     Atlas,
+    // A page of its own. The page may have held an allocation of the same size before, so the
+    // caller must overwrite all of it.
     OwnPage,
+    // A newly-created page of its own, for callers that leave parts of it untouched.
+    FreshPage,
 }
 
 impl TextureAllocator {
@@ -64,58 +83,122 @@ impl TextureAllocator {
 
     pub fn allocate(&mut self, requested_size: Vector2I, mode: AllocationMode) -> TextureLocation {
         // If requested, or if the image is too big, use a separate page.
-        if mode == AllocationMode::OwnPage ||
+        if mode != AllocationMode::Atlas ||
                 requested_size.x() > ATLAS_TEXTURE_LENGTH as i32 ||
                 requested_size.y() > ATLAS_TEXTURE_LENGTH as i32 {
-            return self.allocate_image(requested_size);
+            return self.allocate_own_page(requested_size,
+                                          TextureFormat::RGBA8,
+                                          TexturePageUsage::Paint,
+                                          mode != AllocationMode::FreshPage);
+        }
+
+        self.allocate_in_atlas(requested_size, TextureFormat::RGBA8, TexturePageUsage::Paint)
+    }
+
+    /// Allocates space for a render target.
+    ///
+    /// Render targets are packed into atlases of their own, with a texel of space around each so
+    /// that filtering at the edges doesn't pick up the neighbors. The returned location excludes
+    /// that space. Render targets that are too big for an atlas, or whose mode asks for it, get a
+    /// page of their own instead.
+    pub fn allocate_render_target(&mut self,
+                                  requested_size: Vector2I,
+                                  format: TextureFormat,
+                                  mode: AllocationMode)
+                                  -> TextureLocation {
+        let padded_size = requested_size + vec2i(2, 2);
+        if mode != AllocationMode::Atlas ||
+                padded_size.x() > ATLAS_TEXTURE_LENGTH as i32 ||
+                padded_size.y() > ATLAS_TEXTURE_LENGTH as i32 {
+            return self.allocate_own_page(requested_size,
+                                          format,
+                                          TexturePageUsage::RenderTarget,
+                                          mode != AllocationMode::FreshPage);
         }
 
+        let location = self.allocate_in_atlas(padded_size, format, TexturePageUsage::RenderTarget);
+        TextureLocation {
+            page: location.page,
+            rect: RectI::new(location.rect.origin() + vec2i(1, 1), requested_size),
+        }
+    }
+
+    fn allocate_in_atlas(&mut self,
+                         requested_size: Vector2I,
+                         format: TextureFormat,
+                         usage: TexturePageUsage)
+                         -> TextureLocation {
         // Try to add to each atlas.
-        let mut first_free_page_index = self.pages.len();
         for (page_index, page) in self.pages.iter_mut().enumerate() {
-            match *page {
-                Some(ref mut page) => {
-                    match page.allocator {
-                        TexturePageAllocator::Image { .. } => {}
-                        TexturePageAllocator::Atlas(ref mut allocator) => {
-                            if let Some(rect) = allocator.allocate(requested_size) {
-                                return TextureLocation {
-                                    page: TexturePageId(page_index as u32),
-                                    rect
-                                };
-                            }
-                        }
+            let page = match *page {
+                Some(ref mut page) if page.format == format && page.usage == usage => page,
+                _ => continue,
+            };
+            match page.allocator {
+                TexturePageAllocator::Image { .. } => {}
+                TexturePageAllocator::Atlas(ref mut allocator) => {
+                    if let Some(rect) = allocator.allocate(requested_size) {
+                        return TextureLocation { page: TexturePageId(page_index as u32), rect };
                     }
                 }
-                None => first_free_page_index = first_free_page_index.min(page_index),
             }
         }
 
         // Add a new atlas.
-        let page = self.get_first_free_page_id();
         let mut allocator = TextureAtlasAllocator::new();
         let rect = allocator.allocate(requested_size).expect("Allocation failed!");
-        while (page.0 as usize) >= self.pages.len() {
-            self.pages.push(None);
+        let page = self.push_page(TexturePageAllocator::Atlas(allocator), format, usage);
+        TextureLocation { page, rect }
+    }
+
+    fn allocate_own_page(&mut self,
+                         requested_size: Vector2I,
+                         format: TextureFormat,
+                         usage: TexturePageUsage,
+                         may_reuse_page: bool)
+                         -> TextureLocation {
+        let rect = RectI::new(Vector2I::default(), requested_size);
+
+        // Reuse an empty page of the same size if we can.
+        if may_reuse_page {
+            for (page_index, page) in self.pages.iter_mut().enumerate() {
+                let page = match *page {
+                    Some(ref mut page) if page.format == format && page.usage == usage => page,
+                    _ => continue,
+                };
+                match page.allocator {
+                    TexturePageAllocator::Image { size, ref mut is_allocated } if
+                            size == requested_size && !*is_allocated => {
+                        *is_allocated = true;
+                        return TextureLocation { page: TexturePageId(page_index as u32), rect };
+                    }
+                    _ => {}
+                }
+            }
         }
-        self.pages[page.0 as usize] = Some(TexturePage {
-            is_new: true,
-            allocator: TexturePageAllocator::Atlas(allocator),
-        });
+
+        let allocator = TexturePageAllocator::Image { size: requested_size, is_allocated: true };
+        let page = self.push_page(allocator, format, usage);
         TextureLocation { page, rect }
     }
 
-    pub fn allocate_image(&mut self, requested_size: Vector2I) -> TextureLocation {
+    fn push_page(&mut self,
+                 allocator: TexturePageAllocator,
+                 format: TextureFormat,
+                 usage: TexturePageUsage)
+                 -> TexturePageId {
         let page = self.get_first_free_page_id();
-        let rect = RectI::new(Vector2I::default(), requested_size);
         while (page.0 as usize) >= self.pages.len() {
             self.pages.push(None);
         }
         self.pages[page.0 as usize] = Some(TexturePage {
+            allocator,
+            format,
+            usage,
             is_new: true,
-            allocator: TexturePageAllocator::Image { size: rect.size() },
+            empty_frame_count: 0,
         });
-        TextureLocation { page, rect }
+        page
     }
 
     fn get_first_free_page_id(&self) -> TexturePageId {
@@ -127,27 +210,55 @@ impl TextureAllocator {
         TexturePageId(self.pages.len() as u32)
     }
 
+    /// Frees the given allocation.
+    ///
+    /// Pages that become empty stay around for a few frames, so that new allocations can reuse
+    /// them, until `free_unused_pages()` frees them for good.
     pub fn free(&mut self, location: TextureLocation) {
-        //println!("free({:?})", location);
-        match self.pages[location.page.0 as usize]
-                  .as_mut()
-                  .expect("Texture page is not allocated!")
-                  .allocator {
-            TexturePageAllocator::Image { size } => {
+        let page = self.pages[location.page.0 as usize]
+                       .as_mut()
+                       .expect("Texture page is not allocated!");
+        match page.allocator {
+            TexturePageAllocator::Image { size, ref mut is_allocated } => {
                 debug_assert_eq!(location.rect, RectI::new(Vector2I::default(), size));
+                debug_assert!(*is_allocated);
+                *is_allocated = false;
             }
             TexturePageAllocator::Atlas(ref mut atlas_allocator) => {
-                atlas_allocator.free(location.rect);
-                if !atlas_allocator.is_empty() {
-                    // Keep the page around.
-                    return;
+                match page.usage {
+                    TexturePageUsage::Paint => atlas_allocator.free(location.rect),
+                    TexturePageUsage::RenderTarget => {
+                        // Include the space around the render target.
+                        atlas_allocator.free(RectI::new(location.rect.origin() - vec2i(1, 1),
+                                                        location.rect.size() + vec2i(2, 2)))
+                    }
                 }
             }
         }
+    }
 
-        // If we got here, free the page.
-        // TODO(pcwalton): Actually tell the renderer to free this page!
-        self.pages[location.page.0 as usize] = None;
+    /// Frees pages that have been empty for too long and returns their IDs so that the renderer
+    /// can free them too.
+    ///
+    /// Call this once per frame, after allocating.
+    pub fn free_unused_pages(&mut self) -> Vec<TexturePageId> {
+        let mut freed_pages = vec![];
+        for (page_index, page_slot) in self.pages.iter_mut().enumerate() {
+            let page = match *page_slot {
+                None => continue,
+                Some(ref mut page) => page,
+            };
+            if !page.is_empty() {
+                page.empty_frame_count = 0;
+                continue;
+            }
+            page.empty_frame_count += 1;
+            if page.empty_frame_count > EMPTY_PAGE_RETENTION_FRAMES {
+                *page_slot = None;
+                freed_pages.push(TexturePageId(page_index as u32));
+            }
+        }
+        freed_pages
     }
 
     pub fn page_size(&self, page_id: TexturePageId) -> Vector2I {
@@ -157,6 +268,10 @@ impl TextureAllocator {
         }
     }
 
+    pub fn page_format(&self, page_id: TexturePageId) -> TextureFormat {
+        self.pages[page_id.0 as usize].as_ref().expect("No such texture page!").format
+    }
+
     pub fn page_scale(&self, page_id: TexturePageId) -> Vector2F {
         vec2f(1.0, 1.0) / self.page_size(page_id).to_f32()
     }
@@ -182,6 +297,15 @@ impl TextureAllocator {
     }
 }
 
+impl TexturePage {
+    fn is_empty(&self) -> bool {
+        match self.allocator {
+            TexturePageAllocator::Atlas(ref atlas_allocator) => atlas_allocator.is_empty(),
+            TexturePageAllocator::Image { is_allocated, .. } => !is_allocated,
+        }
+    }
+}
+
 impl TextureAtlasAllocator {
     #[inline]
     fn new() -> TextureAtlasAllocator {
@@ -202,12 +326,11 @@ impl TextureAtlasAllocator {
 
     #[inline]
     fn free(&mut self, rect: RectI) {
-        let requested_length = rect.width() as u32;
+        let requested_length = (rect.width().max(rect.height()) as u32).next_power_of_two();
         self.root.free(Vector2I::default(), self.size, rect.origin(), requested_length)
     }
 
     #[inline]
-    #[allow(dead_code)]
     fn is_empty(&self) -> bool {
         match self.root {
             TreeNode::EmptyLeaf => true,
@@ -364,10 +487,13 @@ impl<'a> Iterator for TexturePageIter<'a> {
 
 #[cfg(test)]
 mod test {
+    use pathfinder_geometry::rect::RectI;
     use pathfinder_geometry::vector::vec2i;
+    use pathfinder_gpu::TextureFormat;
     use quickcheck;
     use std::u32;
 
+    use super::{AllocationMode, EMPTY_PAGE_RETENTION_FRAMES, TextureAllocator};
     use super::TextureAtlasAllocator;
 
     #[test]
@@ -401,4 +527,111 @@ mod test {
             true
         }
     }
+
+    #[test]
+    fn test_freed_atlas_space_is_reused() {
+        let mut allocator = TextureAtlasAllocator::with_length(64);
+        let a = allocator.allocate(vec2i(32, 32)).unwrap();
+        let b = allocator.allocate(vec2i(20, 20)).unwrap();
+        assert!(!a.intersects(b));
+
+        // Quadrants are full once all four are taken.
+        let c = allocator.allocate(vec2i(32, 32)).unwrap();
+        let d = allocator.allocate(vec2i(32, 32)).unwrap();
+        assert!(allocator.allocate(vec2i(32, 32)).is_none());
+
+        // Freed space goes to the next allocation that fits in it.
+        allocator.free(a);
+        assert_eq!(allocator.allocate(vec2i(32, 32)), Some(a));
+        allocator.free(a);
+        allocator.free(b);
+        allocator.free(c);
+        allocator.free(d);
+        assert!(allocator.is_empty());
+
+        // Once empty, the whole atlas is available again.
+        let whole = RectI::new(vec2i(0, 0), vec2i(64, 64));
+        assert_eq!(allocator.allocate(vec2i(64, 64)), Some(whole));
+    }
+
+    #[test]
+    fn test_freed_paints_leave_their_page_for_reuse() {
+        let mut allocator = TextureAllocator::new();
+        let size = vec2i(16, 16);
+        let a = allocator.allocate(size, AllocationMode::Atlas);
+        allocator.free(a);
+        for _ in 0..EMPTY_PAGE_RETENTION_FRAMES {
+            assert!(allocator.free_unused_pages().is_empty());
+        }
+        let b = allocator.allocate(size, AllocationMode::Atlas);
+        assert_eq!(b, a);
+
+        // Using the page resets its empty frame count.
+        assert!(allocator.free_unused_pages().is_empty());
+        allocator.free(b);
+        for _ in 0..EMPTY_PAGE_RETENTION_FRAMES {
+            assert!(allocator.free_unused_pages().is_empty());
+        }
+        assert_eq!(allocator.free_unused_pages(), vec![a.page]);
+
+        // Freed page IDs are handed out again.
+        let c = allocator.allocate(size, AllocationMode::Atlas);
+        assert_eq!(c.page, a.page);
+        assert!(allocator.page_is_new(c.page));
+    }
+
+    #[test]
+    fn test_render_targets_share_pages_that_are_recycled() {
+        let mut allocator = TextureAllocator::new();
+        let size = vec2i(30, 30);
+        let a = allocator.allocate_render_target(size, TextureFormat::RGBA8, AllocationMode::Atlas);
+        let b = allocator.allocate_render_target(size, TextureFormat::RGBA8, AllocationMode::Atlas);
+        assert_eq!(a.page, b.page);
+        assert_eq!(a.rect.size(), size);
+        assert!(!a.rect.contract(vec2i(-1, -1)).intersects(b.rect));
+
+        // Render targets don't share pages with paints or with other formats.
+        let paint = allocator.allocate(size, AllocationMode::Atlas);
+        let hdr = allocator.allocate_render_target(size,
+                                                   TextureFormat::RGBA16F,
+                                                   AllocationMode::Atlas);
+        assert_ne!(paint.page, a.page);
+        assert_ne!(hdr.page, a.page);
+        assert_ne!(hdr.page, paint.page);
+        allocator.free(paint);
+        allocator.free(hdr);
+
+        // An empty page lingers for a few frames so that it can be reused.
+        allocator.free(a);
+        allocator.free(b);
+        for _ in 0..EMPTY_PAGE_RETENTION_FRAMES {
+            assert!(allocator.free_unused_pages().is_empty());
+        }
+        let c = allocator.allocate_render_target(size, TextureFormat::RGBA8, AllocationMode::Atlas);
+        assert_eq!(c.page, a.page);
+
+        // The pages that weren't reused are freed now.
+        let mut freed_pages = allocator.free_unused_pages();
+        freed_pages.sort_by_key(|page| page.0);
+        assert_eq!(freed_pages, vec![paint.page, hdr.page]);
+        allocator.free(c);
+        for _ in 0..EMPTY_PAGE_RETENTION_FRAMES {
+            assert!(allocator.free_unused_pages().is_empty());
+        }
+        assert_eq!(allocator.free_unused_pages(), vec![a.page]);
+        assert_eq!(allocator.page_ids().count(), 0);
+    }
+
+    #[test]
+    fn test_own_pages_are_reused_only_when_allowed() {
+        let mut allocator = TextureAllocator::new();
+        let size = vec2i(256, 256);
+        let a = allocator.allocate(size, AllocationMode::OwnPage);
+        allocator.free(a);
+        let b = allocator.allocate(size, AllocationMode::OwnPage);
+        assert_eq!(b.page, a.page);
+        allocator.free(b);
+        let c = allocator.allocate(size, AllocationMode::FreshPage);
+        assert_ne!(c.page, a.page);
+    }
 }
@@ -11,25 +11,30 @@
 //! Packs data onto the GPU.
 
 use crate::concurrent::executor::Executor;
+use crate::culling::{DrawPathOcclusion, TileOcclusion};
 use crate::gpu::blend::BlendModeExt;
 use crate::gpu::options::RendererLevel;
+use crate::gpu::perf::BuildPhaseTimes;
 use crate::gpu_data::{AlphaTileId, BackdropInfoD3D11, Clip, ClippedPathInfo, DiceMetadataD3D11};
 use crate::gpu_data::{DrawTileBatch, DrawTileBatchD3D9, DrawTileBatchD3D11, Fill, GlobalPathId};
 use crate::gpu_data::{PathBatchIndex, PathSource, PrepareTilesInfoD3D11, PropagateMetadataD3D11};
 use crate::gpu_data::{RenderCommand, SegmentIndicesD3D11, SegmentsD3D11, TileBatchDataD3D11};
 use crate::gpu_data::{TileBatchId, TileBatchTexture, TileObjectPrimitive, TilePathInfoD3D11};
+use crate::layers::LayerPlan;
 use crate::options::{PrepareMode, PreparedBuildOptions, PreparedRenderTransform};
 use crate::paint::{PaintId, PaintInfo, PaintMetadata};
-use crate::scene::{ClipPathId, DisplayItem, DrawPath, DrawPathId, LastSceneInfo, PathId};
-use crate::scene::{Scene, SceneSink};
+use crate::scene::{ClipPathId, DisplayItem, DrawPath, DrawPathId, LastSceneInfo, LayerId};
+use crate::scene::{PathId, Scene, SceneSink};
+use crate::tile_cache::TileInvalidation;
 use crate::tile_map::DenseTileMap;
 use crate::tiler::Tiler;
-use crate::tiles::{self, DrawTilingPathInfo, TILE_HEIGHT, TILE_WIDTH, TilingPathInfo};
+use crate::tiles::{self, DrawTilingPathInfo, TilingPathInfo};
 use fxhash::FxHashMap;
 use instant::Instant;
 use pathfinder_content::effects::{BlendMode, Filter};
 use pathfinder_content::fill::FillRule;
 use pathfinder_content::outline::{Outline, PointFlags};
+use pathfinder_content::segment::Segment;
 use pathfinder_geometry::line_segment::{LineSegment2F, LineSegmentU16};
 use pathfinder_geometry::rect::{RectF, RectI};
 use pathfinder_geometry::transform2d::Transform2F;
@@ -38,7 +43,7 @@ use pathfinder_gpu::TextureSamplingFlags;
 use pathfinder_simd::default::F32x4;
 use std::borrow::Cow;
 use std::ops::Range;
-use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::u32;
 
 pub(crate) const ALPHA_TILE_LEVEL_COUNT: usize = 2;
@@ -47,12 +52,23 @@ pub(crate) const ALPHA_TILES_PER_LEVEL: usize = 1 << (32 - ALPHA_TILE_LEVEL_COUN
 const CURVE_IS_QUADRATIC: u32 = 0x80000000;
 const CURVE_IS_CUBIC:     u32 = 0x40000000;
 
+// The maximum error, in device pixels, when approximating conics with quadratic curves for the
+// GPU.
+const CONIC_TOLERANCE_D3D11: f32 = 0.01;
+
 const MAX_CLIP_BATCHES: u32 = 32;
 
+// Clip paths used inside layers are prepared in batches of their own, since they don't share the
+// render transform with clip paths outside. Their batch IDs start here.
+const FIRST_LAYER_CLIP_BATCH_ID: u32 = MAX_CLIP_BATCHES / 2;
+
 pub(crate) struct SceneBuilder<'a, 'b, 'c, 'd> {
     pub(crate) scene: &'a mut Scene,
-    built_options: &'b PreparedBuildOptions,
+    pub(crate) built_options: &'b PreparedBuildOptions,
     next_alpha_tile_indices: [AtomicUsize; ALPHA_TILE_LEVEL_COUNT],
+    culled_path_count: AtomicUsize,
+    pub(crate) culled_contour_count: AtomicUsize,
+    culled_tile_count: AtomicUsize,
     pub(crate) sink: &'c mut SceneSink<'d>,
 }
 
@@ -141,6 +157,9 @@ impl<'a, 'b, 'c, 'd> SceneBuilder<'a, 'b, 'c, 'd> {
             scene,
             built_options,
             next_alpha_tile_indices: [AtomicUsize::new(0), AtomicUsize::new(0)],
+            culled_path_count: AtomicUsize::new(0),
+            culled_contour_count: AtomicUsize::new(0),
+            culled_tile_count: AtomicUsize::new(0),
             sink,
         }
     }
@@ -161,6 +180,7 @@ impl<'a, 'b, 'c, 'd> SceneBuilder<'a, 'b, 'c, 'd> {
             bounding_quad,
             path_count: total_path_count,
             needs_readable_framebuffer,
+            tile_size: self.built_options.tile_size,
         });
 
         let prepare_mode = self.built_options.to_prepare_mode(self.sink.renderer_level);
@@ -170,35 +190,79 @@ impl<'a, 'b, 'c, 'd> SceneBuilder<'a, 'b, 'c, 'd> {
             _ => Transform2F::default()
         };
 
+        // Find out which layers changed, so that their render targets can be kept if they didn't.
+        let mut layer_plan = LayerPlan::new(self.scene, self.built_options);
+        let render_target_cache_keys = layer_plan.render_target_cache_keys(self.scene);
+
         // Build paint data.
+        let mut build_phase_times = BuildPhaseTimes::default();
+        let phase_start_time = Instant::now();
         let PaintInfo {
             render_commands,
             paint_metadata,
-        } = self.scene.build_paint_info(&mut self.sink.paint_texture_manager, render_transform);
+            cached_render_targets,
+        } = self.scene.build_paint_info(executor,
+                                        &mut self.sink.paint_texture_manager,
+                                        render_transform,
+                                        self.built_options.color_space,
+                                        self.built_options.max_gradient_ramp_length,
+                                        &render_target_cache_keys);
         for render_command in render_commands {
             self.sink.listener.send(render_command);
         }
+        layer_plan.mark_reused_layers(self.scene, &cached_render_targets);
+        build_phase_times.paint_time = Instant::now() - phase_start_time;
 
+        let phase_start_time = Instant::now();
+        let occlusion = DrawPathOcclusion::new(self.scene, self.built_options, &paint_metadata);
         let built_paths = match prepare_mode {
             PrepareMode::CPU | PrepareMode::TransformCPUBinGPU => {
-                Some(self.build_paths_on_cpu(executor, &paint_metadata, &prepare_mode))
+                Some(self.build_paths_on_cpu(executor,
+                                             &paint_metadata,
+                                             &prepare_mode,
+                                             &layer_plan,
+                                             &occlusion))
             }
             PrepareMode::GPU { .. } => None,
         };
+        build_phase_times.path_time = Instant::now() - phase_start_time;
 
         // TODO(pcwalton): Do this earlier?
+        //
+        // Device strokes depend on the transform, so they have to be rebuilt whenever it changes.
+        let device_stroke_transform = match prepare_mode {
+            PrepareMode::GPU { transform } if self.scene.has_device_strokes() => Some(transform),
+            _ => None,
+        };
+        // Likewise, conics are approximated finely enough for the scale of the transform.
+        let conic_tolerance = match prepare_mode {
+            PrepareMode::GPU { transform } if self.scene.has_conics() => {
+                Some(conic_tolerance_d3d11(&transform))
+            }
+            _ => None,
+        };
         let scene_is_dirty = match (&prepare_mode, &self.sink.last_scene) {
             (&PrepareMode::GPU { .. }, &None) => true,
             (&PrepareMode::GPU { .. }, &Some(LastSceneInfo {
                  scene_id: ref last_scene_id,
                  scene_epoch: ref last_scene_epoch,
+                 device_stroke_transform: ref last_device_stroke_transform,
+                 conic_tolerance: ref last_conic_tolerance,
                  ..
-            })) => *last_scene_id != self.scene.id() || *last_scene_epoch != self.scene.epoch(),
+            })) => {
+                *last_scene_id != self.scene.id() || *last_scene_epoch != self.scene.epoch() ||
+                    *last_device_stroke_transform != device_stroke_transform ||
+                    *last_conic_tolerance != conic_tolerance
+            }
             _ => false,
         };
 
+        let phase_start_time = Instant::now();
         if scene_is_dirty {
-            let built_segments = BuiltSegments::from_scene(&self.scene);
+            let built_segments = BuiltSegments::from_scene(executor,
+                                                           self.scene,
+                                                           device_stroke_transform,
+                                                           conic_tolerance.unwrap_or_default());
             self.sink.listener.send(RenderCommand::UploadSceneD3D11 {
                 draw_segments: built_segments.draw_segments,
                 clip_segments: built_segments.clip_segments,
@@ -208,56 +272,136 @@ impl<'a, 'b, 'c, 'd> SceneBuilder<'a, 'b, 'c, 'd> {
                 scene_epoch: self.scene.epoch(),
                 draw_segment_ranges: built_segments.draw_segment_ranges,
                 clip_segment_ranges: built_segments.clip_segment_ranges,
+                device_stroke_transform,
+                conic_tolerance,
             });
         }
 
-        self.finish_building(&paint_metadata, built_paths, &prepare_mode);
+        build_phase_times.segment_time = Instant::now() - phase_start_time;
+
+        let phase_start_time = Instant::now();
+        self.finish_building(&paint_metadata,
+                             built_paths,
+                             &prepare_mode,
+                             &layer_plan,
+                             &occlusion);
+        build_phase_times.batch_time = Instant::now() - phase_start_time;
 
         let cpu_build_time = Instant::now() - start_time;
-        self.sink.listener.send(RenderCommand::Finish { cpu_build_time });
+        self.sink.listener.send(RenderCommand::Finish {
+            cpu_build_time,
+            build_phase_times,
+            culled_path_count: self.culled_path_count.load(Ordering::Relaxed),
+            culled_contour_count: self.culled_contour_count.load(Ordering::Relaxed),
+            culled_tile_count: self.culled_tile_count.load(Ordering::Relaxed),
+        });
     }
 
     fn build_paths_on_cpu<E>(&mut self,
                              executor: &E,
                              paint_metadata: &[PaintMetadata],
-                             prepare_mode: &PrepareMode)
+                             prepare_mode: &PrepareMode,
+                             layer_plan: &LayerPlan,
+                             occlusion: &DrawPathOcclusion)
                              -> BuiltPaths
                              where E: Executor {
         let clip_path_count = self.scene.clip_paths().len();
         let draw_path_count = self.scene.draw_paths().len();
         let effective_view_box = self.scene.effective_view_box(self.built_options);
+        let layer_options = LayerPlan::layer_options(self.built_options);
+        let layer_prepare_mode = LayerPlan::layer_prepare_mode(prepare_mode);
+
+        // If the tile cache is in use, transform the draw paths up front so that we can find out
+        // which tiles changed before tiling anything.
+        let (draw_outlines, tile_invalidation) = if TileInvalidation::is_supported(
+                &self.scene,
+                self.built_options,
+                self.sink.renderer_level) {
+            let draw_outlines = executor.build_vector(draw_path_count, |path_index| {
+                let draw_path = self.scene.get_draw_path(DrawPathId(path_index as u32));
+                self.scene.apply_render_options(draw_path.outline(),
+                                                draw_path.device_stroke(),
+                                                self.built_options)
+            });
+            let tile_invalidation = TileInvalidation::new(&self.scene,
+                                                          self.built_options,
+                                                          &draw_outlines,
+                                                          &mut self.sink.tile_cache);
+            self.sink
+                .listener
+                .send(RenderCommand::InvalidateTilesD3D9(tile_invalidation.to_dirty_tiles()));
+            (Some(draw_outlines), Some(tile_invalidation))
+        } else {
+            self.sink.tile_cache = None;
+            (None, None)
+        };
 
+        // Paths inside layers are built in layer space.
         let built_clip_paths = executor.build_vector(clip_path_count, |path_index| {
+            let clip_path_id = ClipPathId(path_index as u32);
+            let (view_box, built_options, prepare_mode) =
+                match layer_plan.clip_path_view_box(clip_path_id) {
+                    None => (effective_view_box, self.built_options, *prepare_mode),
+                    Some(view_box) => (view_box, &layer_options, layer_prepare_mode),
+                };
             self.build_clip_path_on_cpu(PathBuildParams {
                 path_id: PathId(path_index as u32),
-                view_box: effective_view_box,
-                prepare_mode: *prepare_mode,
-                built_options: &self.built_options,
+                view_box,
+                prepare_mode,
+                built_options,
                 scene: &self.scene,
+                is_reused: layer_plan.clip_path_is_reused(clip_path_id),
             })
         });
 
         let built_draw_paths = executor.build_vector(draw_path_count, |path_index| {
+            let draw_path_id = DrawPathId(path_index as u32);
+            let (view_box, built_options, prepare_mode) =
+                match layer_plan.draw_path_layer(draw_path_id) {
+                    None => (effective_view_box, self.built_options, *prepare_mode),
+                    Some(layer_id) => {
+                        (LayerPlan::layer_view_box(self.scene, layer_id),
+                         &layer_options,
+                         layer_prepare_mode)
+                    }
+                };
             self.build_draw_path_on_cpu(DrawPathBuildParams {
                 path_build_params: PathBuildParams {
                     path_id: PathId(path_index as u32),
-                    view_box: effective_view_box,
-                    prepare_mode: *prepare_mode,
-                    built_options: &self.built_options,
+                    view_box,
+                    prepare_mode,
+                    built_options,
                     scene: &self.scene,
+                    is_reused: layer_plan.draw_path_is_reused(draw_path_id),
                 },
                 paint_metadata: &paint_metadata,
                 built_clip_paths: &built_clip_paths,
+                draw_outline: draw_outlines.as_ref().map(|draw_outlines| {
+                    &draw_outlines[path_index]
+                }),
+                tile_invalidation: tile_invalidation.as_ref(),
+                is_occluded: occlusion.draw_path_is_occluded(draw_path_id),
             })
         });
 
-        BuiltPaths { draw: built_draw_paths }
+        BuiltPaths { draw: built_draw_paths, tile_invalidation }
     }
 
     fn build_clip_path_on_cpu(&self, params: PathBuildParams) -> BuiltPath {
-        let PathBuildParams { path_id, view_box, built_options, scene, prepare_mode } = params;
+        let PathBuildParams {
+            path_id,
+            view_box,
+            built_options,
+            scene,
+            prepare_mode,
+            is_reused,
+        } = params;
         let path_object = &scene.get_clip_path(path_id.to_clip_path_id());
-        let outline = scene.apply_render_options(path_object.outline(), built_options);
+        let outline = if is_reused {
+            Outline::new()
+        } else {
+            scene.apply_render_options(path_object.outline(), None, built_options)
+        };
 
         let mut tiler = Tiler::new(self,
                                    path_id,
@@ -269,8 +413,11 @@ impl<'a, 'b, 'c, 'd> SceneBuilder<'a, 'b, 'c, 'd> {
                                    &[],
                                    TilingPathInfo::Clip);
 
-        tiler.generate_tiles();
-        self.send_fills(tiler.object_builder.fills);
+        // Clip paths outside the view box have no tiles, so there's nothing to tile.
+        if !is_reused && tiler.object_builder.built_path.tile_bounds.area() > 0 {
+            tiler.generate_tiles();
+            self.send_fills(tiler.object_builder.fills);
+        }
         tiler.object_builder.built_path
     }
 
@@ -282,13 +429,28 @@ impl<'a, 'b, 'c, 'd> SceneBuilder<'a, 'b, 'c, 'd> {
                 built_options,
                 prepare_mode,
                 scene,
+                is_reused,
             },
             paint_metadata,
             built_clip_paths,
+            draw_outline,
+            tile_invalidation,
+            is_occluded,
         } = params;
 
+        // Paths inside reused layers are never drawn, and neither are paths hidden behind opaque
+        // ones, so give them an empty outline and no tiles.
         let path_object = scene.get_draw_path(path_id.to_draw_path_id());
-        let outline = scene.apply_render_options(path_object.outline(), built_options);
+        let outline = match draw_outline {
+            _ if is_occluded => Cow::Owned(Outline::new()),
+            Some(draw_outline) => Cow::Borrowed(draw_outline),
+            None if is_reused => Cow::Owned(Outline::new()),
+            None => {
+                Cow::Owned(scene.apply_render_options(path_object.outline(),
+                                                      path_object.device_stroke(),
+                                                      built_options))
+            }
+        };
 
         let paint_id = path_object.paint();
         let paint_metadata = &paint_metadata[paint_id.0 as usize];
@@ -307,8 +469,22 @@ impl<'a, 'b, 'c, 'd> SceneBuilder<'a, 'b, 'c, 'd> {
             fill_rule: path_object.fill_rule(),
         }));
 
-        tiler.generate_tiles();
-        self.send_fills(tiler.object_builder.fills);
+        // Paths outside the view box have no tiles, so they're culled before tiling.
+        let tile_bounds = tiler.object_builder.built_path.tile_bounds;
+        let is_culled = is_occluded || (!is_reused && tile_bounds.area() == 0);
+        if is_culled {
+            self.culled_path_count.fetch_add(1, Ordering::Relaxed);
+        }
+
+        // Paths that only touch tiles reused from the last frame don't need to be tiled at all.
+        let needs_tiling = match tile_invalidation {
+            None => !is_reused,
+            Some(tile_invalidation) => tile_invalidation.any_dirty_in(tile_bounds),
+        };
+        if needs_tiling && !is_culled {
+            tiler.generate_tiles();
+            self.send_fills(tiler.object_builder.fills);
+        }
 
         BuiltDrawPath::new(tiler.object_builder.built_path, path_object, paint_metadata)
     }
@@ -322,53 +498,162 @@ impl<'a, 'b, 'c, 'd> SceneBuilder<'a, 'b, 'c, 'd> {
     fn build_tile_batches(&mut self,
                           paint_metadata: &[PaintMetadata],
                           prepare_mode: &PrepareMode,
-                          built_paths: Option<BuiltPaths>) {
+                          built_paths: Option<BuiltPaths>,
+                          layer_plan: &LayerPlan,
+                          occlusion: &DrawPathOcclusion) {
+        let view_box = self.scene.effective_view_box(self.built_options);
+        let tile_occlusion = built_paths.as_ref().map(|built_paths| {
+            self.build_tile_occlusion(built_paths, view_box)
+        });
+
         let mut tile_batch_builder = TileBatchBuilder::new(built_paths);
 
-        // Prepare display items.
+        let layer_options = LayerPlan::layer_options(self.built_options);
+        let scene_space = DrawSpace {
+            view_box,
+            built_options: self.built_options,
+            prepare_mode: *prepare_mode,
+            layer: None,
+            tile_occlusion: tile_occlusion.as_ref(),
+        };
+
+        // Prepare display items. Layers that are reused from the last frame are skipped
+        // entirely, along with everything inside them; only their composite paths are drawn.
+        let mut layer_stack: Vec<LayerId> = vec![];
+        let mut reused_layer_depth = 0;
+        let mut render_target_depth = 0;
         for display_item in self.scene.display_list() {
             match *display_item {
+                DisplayItem::PushLayer(layer_id) => {
+                    if reused_layer_depth > 0 || layer_plan.layer_is_reused(layer_id) {
+                        reused_layer_depth += 1;
+                    } else {
+                        let layer_info = &self.scene.layers()[layer_id.0 as usize];
+                        let render_target_id = layer_info.render_target_id;
+                        tile_batch_builder.draw_commands
+                                          .push(RenderCommand::PushRenderTarget(render_target_id));
+                        layer_stack.push(layer_id);
+                    }
+                }
+                DisplayItem::PopLayer => {
+                    if reused_layer_depth > 0 {
+                        reused_layer_depth -= 1;
+                    } else {
+                        layer_stack.pop();
+                        tile_batch_builder.draw_commands.push(RenderCommand::PopRenderTarget);
+                    }
+                }
+                _ if reused_layer_depth > 0 => {}
                 DisplayItem::PushRenderTarget(render_target_id) => {
+                    render_target_depth += 1;
                     tile_batch_builder.draw_commands
                                       .push(RenderCommand::PushRenderTarget(render_target_id))
                 }
                 DisplayItem::PopRenderTarget => {
+                    render_target_depth -= 1;
                     tile_batch_builder.draw_commands.push(RenderCommand::PopRenderTarget)
                 }
                 DisplayItem::DrawPaths(ref path_id_range) => {
+                    let space = match layer_stack.last() {
+                        None if render_target_depth > 0 => {
+                            DrawSpace { tile_occlusion: None, ..scene_space }
+                        }
+                        None => scene_space,
+                        Some(&layer_id) => {
+                            DrawSpace {
+                                view_box: LayerPlan::layer_view_box(self.scene, layer_id),
+                                built_options: &layer_options,
+                                prepare_mode: LayerPlan::layer_prepare_mode(prepare_mode),
+                                layer: Some(layer_id),
+                                tile_occlusion: None,
+                            }
+                        }
+                    };
                     tile_batch_builder.build_tile_batches_for_draw_path_display_item(
                         &self.scene,
                         &self.sink,
-                        self.built_options,
+                        &space,
                         path_id_range.start..path_id_range.end,
                         paint_metadata,
-                        prepare_mode);
+                        occlusion);
                 }
             }
         }
 
         // Send commands.
+        self.culled_path_count.fetch_add(tile_batch_builder.culled_path_count, Ordering::Relaxed);
+        self.culled_tile_count.fetch_add(tile_batch_builder.culled_tile_count, Ordering::Relaxed);
         tile_batch_builder.send_to(&self.sink);
     }
 
+    // Finds the tiles drawn directly to the destination framebuffer that solid tiles of later
+    // opaque paths cover entirely.
+    fn build_tile_occlusion(&self, built_paths: &BuiltPaths, view_box: RectF) -> TileOcclusion {
+        let tile_rect = tiles::round_rect_out_to_tile_bounds(view_box,
+                                                             self.built_options.tile_size);
+        let mut tile_occlusion = TileOcclusion::new(tile_rect);
+
+        let mut framebuffer_nesting = 0;
+        for display_item in self.scene.display_list() {
+            match *display_item {
+                DisplayItem::PushRenderTarget(_) | DisplayItem::PushLayer(_) => {
+                    framebuffer_nesting += 1
+                }
+                DisplayItem::PopRenderTarget | DisplayItem::PopLayer => framebuffer_nesting -= 1,
+                DisplayItem::DrawPaths(_) if framebuffer_nesting > 0 => {}
+                DisplayItem::DrawPaths(ref draw_path_id_range) => {
+                    for draw_path_id in draw_path_id_range.start.0..draw_path_id_range.end.0 {
+                        let draw_path = &built_paths.draw[draw_path_id as usize];
+                        if !draw_path.occludes || draw_path.blend_mode != BlendMode::SrcOver {
+                            continue;
+                        }
+                        let cpu_data = match draw_path.path.data {
+                            BuiltPathData::CPU(ref cpu_data) => cpu_data,
+                            BuiltPathData::GPU | BuiltPathData::TransformCPUBinGPU(_) => continue,
+                        };
+                        for tile in &cpu_data.tiles.data {
+                            if tile.alpha_tile_id != AlphaTileId(!0) {
+                                continue;
+                            }
+                            let covered = match draw_path.mask_0_fill_rule {
+                                FillRule::Winding => tile.backdrop != 0,
+                                FillRule::EvenOdd => tile.backdrop % 2 != 0,
+                            };
+                            if covered {
+                                let tile_coords = vec2i(tile.tile_x as i32, tile.tile_y as i32);
+                                tile_occlusion.add_occluder(tile_coords, DrawPathId(draw_path_id));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        tile_occlusion
+    }
+
     fn finish_building(&mut self,
                        paint_metadata: &[PaintMetadata],
                        built_paths: Option<BuiltPaths>,
-                       prepare_mode: &PrepareMode) {
+                       prepare_mode: &PrepareMode,
+                       layer_plan: &LayerPlan,
+                       occlusion: &DrawPathOcclusion) {
         match self.sink.renderer_level {
             RendererLevel::D3D9 => self.sink.listener.send(RenderCommand::FlushFillsD3D9),
             RendererLevel::D3D11 => {}
         }
 
-        self.build_tile_batches(paint_metadata, prepare_mode, built_paths);
+        self.build_tile_batches(paint_metadata, prepare_mode, built_paths, layer_plan, occlusion);
     }
 
     fn needs_readable_framebuffer(&self) -> bool {
         let mut framebuffer_nesting = 0;
         for display_item in self.scene.display_list() {
             match *display_item {
-                DisplayItem::PushRenderTarget(_) => framebuffer_nesting += 1,
-                DisplayItem::PopRenderTarget => framebuffer_nesting -= 1,
+                DisplayItem::PushRenderTarget(_) | DisplayItem::PushLayer(_) => {
+                    framebuffer_nesting += 1
+                }
+                DisplayItem::PopRenderTarget | DisplayItem::PopLayer => framebuffer_nesting -= 1,
                 DisplayItem::DrawPaths(ref draw_path_id_range) => {
                     if framebuffer_nesting > 0 {
                         continue;
@@ -389,6 +674,19 @@ impl<'a, 'b, 'c, 'd> SceneBuilder<'a, 'b, 'c, 'd> {
 
 struct BuiltPaths {
     draw: Vec<BuiltDrawPath>,
+    tile_invalidation: Option<TileInvalidation>,
+}
+
+// The coordinate space that a display item is drawn in: either the scene or a layer.
+#[derive(Clone, Copy)]
+struct DrawSpace<'a> {
+    view_box: RectF,
+    built_options: &'a PreparedBuildOptions,
+    prepare_mode: PrepareMode,
+    layer: Option<LayerId>,
+    // Which tiles are hidden behind opaque tiles. Only present for paths binned on CPU and drawn
+    // directly to the destination framebuffer.
+    tile_occlusion: Option<&'a TileOcclusion>,
 }
 
 struct PathBuildParams<'a> {
@@ -397,18 +695,25 @@ struct PathBuildParams<'a> {
     built_options: &'a PreparedBuildOptions,
     prepare_mode: PrepareMode,
     scene: &'a Scene,
+    // True if the path is only drawn into layers reused from the last frame.
+    is_reused: bool,
 }
 
 struct DrawPathBuildParams<'a> {
     path_build_params: PathBuildParams<'a>,
     paint_metadata: &'a [PaintMetadata],
     built_clip_paths: &'a [BuiltPath],
+    draw_outline: Option<&'a Outline>,
+    tile_invalidation: Option<&'a TileInvalidation>,
+    // True if the path is hidden behind opaque paths drawn after it.
+    is_occluded: bool,
 }
 
 impl BuiltPath {
     fn new(path_id: PathId,
            path_bounds: RectF,
            view_box_bounds: RectF,
+           tile_size: Vector2I,
            fill_rule: FillRule,
            prepare_mode: &PrepareMode,
            clip_path_id: Option<ClipPathId>,
@@ -427,7 +732,7 @@ impl BuiltPath {
             path_bounds
         };
 
-        let tile_bounds = tiles::round_rect_out_to_tile_bounds(tile_map_bounds);
+        let tile_bounds = tiles::round_rect_out_to_tile_bounds(tile_map_bounds, tile_size);
 
         let data = match *prepare_mode {
             PrepareMode::CPU => {
@@ -485,6 +790,7 @@ impl ObjectBuilder {
     pub(crate) fn new(path_id: PathId,
                       path_bounds: RectF,
                       view_box_bounds: RectF,
+                      tile_size: Vector2I,
                       fill_rule: FillRule,
                       prepare_mode: &PrepareMode,
                       clip_path_id: Option<ClipPathId>,
@@ -493,6 +799,7 @@ impl ObjectBuilder {
         let built_path = BuiltPath::new(path_id,
                                         path_bounds,
                                         view_box_bounds,
+                                        tile_size,
                                         fill_rule,
                                         prepare_mode,
                                         clip_path_id,
@@ -511,15 +818,13 @@ impl ObjectBuilder {
             return;
         }
 
-        debug_assert_eq!(TILE_WIDTH, TILE_HEIGHT);
-
         // Compute the upper left corner of the tile.
-        let tile_size = F32x4::splat(TILE_WIDTH as f32);
+        let tile_size = scene_builder.built_options.tile_size.to_f32().0.to_f32x4().xyxy();
         let tile_upper_left = tile_coords.to_f32().0.to_f32x4().xyxy() * tile_size;
 
         // Convert to 8.8 fixed point.
         let segment = (segment.0 - tile_upper_left) * F32x4::splat(256.0);
-        let (min, max) = (F32x4::default(), F32x4::splat((TILE_WIDTH * 256 - 1) as f32));
+        let (min, max) = (F32x4::default(), tile_size * F32x4::splat(256.0) - F32x4::splat(1.0));
         let segment = segment.clamp(min, max).to_i32x4();
         let (from_x, from_y, to_x, to_y) = (segment[0], segment[1], segment[2], segment[3]);
 
@@ -753,6 +1058,20 @@ impl TileBatchDataD3D11 {
     }
 }
 
+// Returns the tolerance, in scene units, for approximating conics with quadratic curves under the
+// given transform.
+//
+// The scale is rounded up to a power of two, so that zooming in or out only rebuilds the scene
+// when it crosses one.
+fn conic_tolerance_d3d11(transform: &Transform2F) -> f32 {
+    // The Frobenius norm bounds how much the transform can stretch any vector.
+    let scale = transform.extract_scale().length();
+    if scale <= 0.0 || !scale.is_finite() {
+        return CONIC_TOLERANCE_D3D11;
+    }
+    CONIC_TOLERANCE_D3D11 / scale.log2().ceil().exp2()
+}
+
 fn init_backdrops(backdrops: &mut Vec<BackdropInfoD3D11>,
                   path_index: PathBatchIndex,
                   tile_rect: RectI) {
@@ -769,24 +1088,40 @@ struct BuiltSegments {
 }
 
 impl BuiltSegments {
-    fn from_scene(scene: &Scene) -> BuiltSegments {
-        let mut built_segments = BuiltSegments {
-            draw_segments: SegmentsD3D11::new(),
-            clip_segments: SegmentsD3D11::new(),
-            draw_segment_ranges: Vec::with_capacity(scene.draw_paths().len()),
-            clip_segment_ranges: Vec::with_capacity(scene.clip_paths().len()),
-        };
+    // Device strokes are stroked in device space according to `device_stroke_transform` and then
+    // mapped back into scene space, since the GPU applies the transform itself.
+    //
+    // Each path's segments are built independently, in parallel, and then concatenated.
+    fn from_scene<E>(executor: &E,
+                     scene: &Scene,
+                     device_stroke_transform: Option<Transform2F>,
+                     conic_tolerance: f32)
+                     -> BuiltSegments
+                     where E: Executor {
+        let clip_paths = scene.clip_paths();
+        let clip_path_segments = executor.build_vector(clip_paths.len(), |path_index| {
+            SegmentsD3D11::from_outline(clip_paths[path_index].outline(), conic_tolerance)
+        });
 
-        for clip_path in scene.clip_paths() {
-            let range = built_segments.clip_segments.add_path(clip_path.outline());
-            built_segments.clip_segment_ranges.push(range);
-        }
-        for draw_path in scene.draw_paths() {
-            let range = built_segments.draw_segments.add_path(draw_path.outline());
-            built_segments.draw_segment_ranges.push(range);
-        }
+        let draw_paths = scene.draw_paths();
+        let draw_path_segments = executor.build_vector(draw_paths.len(), |path_index| {
+            let draw_path = &draw_paths[path_index];
+            match draw_path.device_stroke() {
+                None => SegmentsD3D11::from_outline(draw_path.outline(), conic_tolerance),
+                Some(device_stroke) => {
+                    let transform = device_stroke_transform.unwrap_or_default();
+                    let mut outline = draw_path.outline().clone();
+                    outline.transform(&transform);
+                    outline = device_stroke.stroke(&outline);
+                    outline.transform(&transform.inverse());
+                    SegmentsD3D11::from_outline(&outline, conic_tolerance)
+                }
+            }
+        });
 
-        built_segments
+        let (clip_segments, clip_segment_ranges) = SegmentsD3D11::concat(clip_path_segments);
+        let (draw_segments, draw_segment_ranges) = SegmentsD3D11::concat(draw_path_segments);
+        BuiltSegments { draw_segments, clip_segments, draw_segment_ranges, clip_segment_ranges }
     }
 }
 
@@ -795,19 +1130,60 @@ impl SegmentsD3D11 {
         SegmentsD3D11 { points: vec![], indices: vec![] }
     }
 
-    fn add_path(&mut self, outline: &Outline) -> Range<u32> {
+    fn from_outline(outline: &Outline, conic_tolerance: f32) -> SegmentsD3D11 {
+        let mut segments = SegmentsD3D11::new();
+        segments.add_path(outline, conic_tolerance);
+        segments
+    }
+
+    // Concatenates the segments of each path, returning the range of segment indices that each
+    // path ended up in.
+    fn concat(path_segments: Vec<SegmentsD3D11>) -> (SegmentsD3D11, Vec<Range<u32>>) {
+        let point_count = path_segments.iter().map(|segments| segments.points.len()).sum();
+        let index_count = path_segments.iter().map(|segments| segments.indices.len()).sum();
+        let mut segments = SegmentsD3D11 {
+            points: Vec::with_capacity(point_count),
+            indices: Vec::with_capacity(index_count),
+        };
+
+        let mut ranges = Vec::with_capacity(path_segments.len());
+        for path_segments in path_segments {
+            let first_point_index = segments.points.len() as u32;
+            let first_segment_index = segments.indices.len() as u32;
+            segments.points.extend_from_slice(&path_segments.points);
+            segments.indices.extend(path_segments.indices.iter().map(|indices| {
+                SegmentIndicesD3D11 {
+                    first_point_index: first_point_index + indices.first_point_index,
+                    flags: indices.flags,
+                }
+            }));
+            ranges.push(first_segment_index..(segments.indices.len() as u32));
+        }
+        (segments, ranges)
+    }
+
+    fn add_path(&mut self, outline: &Outline, conic_tolerance: f32) -> Range<u32> {
         let first_segment_index = self.indices.len() as u32;
         for contour in outline.contours() {
             let point_count = contour.len() as u32;
             self.points.reserve(point_count as usize);
 
-            for point_index in 0..point_count {
+            let mut point_index = 0;
+            while point_index < point_count {
                 if !contour.flags_of(point_index).intersects(PointFlags::CONTROL_POINT_0 |
                                                              PointFlags::CONTROL_POINT_1) {
                     let mut flags = 0;
                     if point_index + 1 < point_count &&
                             contour.flags_of(point_index + 1)
                                    .contains(PointFlags::CONTROL_POINT_0) {
+                        if contour.flags_of(point_index + 1).contains(PointFlags::CONIC) {
+                            // The GPU tiler has no notion of conics, so approximate them with
+                            // quadratic curves here.
+                            self.add_conic(&contour.segment_after(point_index),
+                                           conic_tolerance);
+                            point_index += 2;
+                            continue;
+                        }
                         if point_index + 2 < point_count &&
                                 contour.flags_of(point_index + 2)
                                        .contains(PointFlags::CONTROL_POINT_1) {
@@ -824,6 +1200,7 @@ impl SegmentsD3D11 {
                 }
 
                 self.points.push(contour.position_of(point_index));
+                point_index += 1;
             }
 
             self.points.push(contour.position_of(0));
@@ -832,14 +1209,32 @@ impl SegmentsD3D11 {
         let last_segment_index = self.indices.len() as u32;
         first_segment_index..last_segment_index
     }
+
+    // Pushes the quadratic curves approximating a conic, except for the final endpoint, which is
+    // pushed as the start of the next segment.
+    fn add_conic(&mut self, conic: &Segment, tolerance: f32) {
+        for quadratic in conic.as_conic_segment().to_quadratics(tolerance) {
+            self.indices.push(SegmentIndicesD3D11 {
+                first_point_index: self.points.len() as u32,
+                flags: CURVE_IS_QUADRATIC,
+            });
+            self.points.push(quadratic.baseline.from());
+            self.points.push(quadratic.ctrl.from());
+        }
+    }
 }
 
 struct TileBatchBuilder {
     prepare_commands: Vec<RenderCommand>,
     draw_commands: Vec<RenderCommand>,
     clip_batches_d3d11: Option<ClipBatchesD3D11>,
+    layer_clip_batches_d3d11: Option<ClipBatchesD3D11>,
     next_batch_id: TileBatchId,
     level: TileBatchBuilderLevel,
+    // The number of draw paths culled while preparing them for binning on GPU.
+    culled_path_count: usize,
+    // The number of tiles binned on CPU that were hidden behind opaque tiles.
+    culled_tile_count: usize,
 }
 
 enum TileBatchBuilderLevel {
@@ -854,39 +1249,46 @@ impl TileBatchBuilder {
             draw_commands: vec![],
             next_batch_id: TileBatchId(MAX_CLIP_BATCHES),
             clip_batches_d3d11: match built_paths {
-                None => {
-                    Some(ClipBatchesD3D11 {
-                        prepare_batches: vec![],
-                        clip_id_to_path_batch_index: FxHashMap::default(),
-                    })
-                }
+                None => Some(ClipBatchesD3D11::new(0)),
+                Some(_) => None,
+            },
+            layer_clip_batches_d3d11: match built_paths {
+                None => Some(ClipBatchesD3D11::new(FIRST_LAYER_CLIP_BATCH_ID)),
                 Some(_) => None,
             },
             level: match built_paths {
                 None => TileBatchBuilderLevel::D3D11,
                 Some(built_paths) => TileBatchBuilderLevel::D3D9 { built_paths },
             },
+            culled_path_count: 0,
+            culled_tile_count: 0,
         }
     }
 
     fn build_tile_batches_for_draw_path_display_item(&mut self,
                                                      scene: &Scene,
                                                      sink: &SceneSink,
-                                                     built_options: &PreparedBuildOptions,
+                                                     space: &DrawSpace,
                                                      draw_path_id_range: Range<DrawPathId>,
                                                      paint_metadata: &[PaintMetadata],
-                                                     prepare_mode: &PrepareMode) {
+                                                     occlusion: &DrawPathOcclusion) {
         let mut draw_tile_batch = None;
         for draw_path_id in draw_path_id_range.start.0..draw_path_id_range.end.0 {
             let draw_path_id = DrawPathId(draw_path_id);
             let draw_path = match self.level {
                 TileBatchBuilderLevel::D3D11 { .. } => {
+                    if occlusion.draw_path_is_occluded(draw_path_id) {
+                        self.culled_path_count += 1;
+                        continue;
+                    }
                     match self.prepare_draw_path_for_gpu_binning(scene,
-                                                                 built_options,
+                                                                 space,
                                                                  draw_path_id,
-                                                                 prepare_mode,
                                                                  paint_metadata) {
-                        None => continue,
+                        None => {
+                            self.culled_path_count += 1;
+                            continue;
+                        }
                         Some(built_draw_path) => Cow::Owned(built_draw_path),
                     }
                 }
@@ -925,7 +1327,13 @@ impl TileBatchBuilder {
             if draw_tile_batch.is_none() {
                 draw_tile_batch = match self.level {
                     TileBatchBuilderLevel::D3D9 { .. } => {
-                        let tile_bounds = tiles::round_rect_out_to_tile_bounds(scene.view_box());
+                        let z_buffer_rect = match space.layer {
+                            None => scene.view_box(),
+                            Some(_) => space.view_box,
+                        };
+                        let tile_bounds =
+                            tiles::round_rect_out_to_tile_bounds(z_buffer_rect,
+                                                                 space.built_options.tile_size);
                         Some(DrawTileBatch::D3D9(DrawTileBatchD3D9 {
                             tiles: vec![],
                             clips: vec![],
@@ -938,7 +1346,7 @@ impl TileBatchBuilder {
                     TileBatchBuilderLevel::D3D11 { .. } => {
                         Some(DrawTileBatch::D3D11(DrawTileBatchD3D11 {
                             tile_batch_data: TileBatchDataD3D11::new(self.next_batch_id,
-                                                                     &prepare_mode,
+                                                                     &space.prepare_mode,
                                                                      PathSource::Draw),
                             color_texture: draw_path.color_texture,
                         }))
@@ -948,14 +1356,18 @@ impl TileBatchBuilder {
             }
 
             // Add clip path if necessary.
-            let clip_path = match self.clip_batches_d3d11 {
+            let clip_batches_d3d11 = match space.layer {
+                None => self.clip_batches_d3d11.as_mut(),
+                Some(_) => self.layer_clip_batches_d3d11.as_mut(),
+            };
+            let clip_path = match clip_batches_d3d11 {
                 None => None,
-                Some(ref mut clip_batches_d3d11) => {
+                Some(clip_batches_d3d11) => {
                     add_clip_path_to_batch(scene,
                                            sink,
-                                           built_options,
+                                           space.built_options,
                                            draw_path.clip_path_id,
-                                           prepare_mode,
+                                           &space.prepare_mode,
                                            0,
                                            clip_batches_d3d11)
                 }
@@ -988,13 +1400,26 @@ impl TileBatchBuilder {
                             continue;
                         }
 
+                        let tile_coords = vec2i(tile.tile_x as i32, tile.tile_y as i32);
+                        if let Some(ref tile_invalidation) = built_paths.tile_invalidation {
+                            if !tile_invalidation.is_dirty(tile_coords) {
+                                continue;
+                            }
+                        }
+
+                        if let Some(tile_occlusion) = space.tile_occlusion {
+                            if tile_occlusion.tile_is_occluded(tile_coords, draw_path_id) {
+                                self.culled_tile_count += 1;
+                                continue;
+                            }
+                        }
+
                         draw_tile_batch.tiles.push(*tile);
 
                         if !draw_path.occludes || tile.alpha_tile_id != AlphaTileId(!0) {
                             continue;
                         }
 
-                        let tile_coords = vec2i(tile.tile_x as i32, tile.tile_y as i32);
                         let z_value = draw_tile_batch.z_buffer_data
                                                      .get_mut(tile_coords)
                                                      .expect("Z value out of bounds!");
@@ -1028,22 +1453,24 @@ impl TileBatchBuilder {
 
     fn prepare_draw_path_for_gpu_binning(&self,
                                          scene: &Scene,
-                                         built_options: &PreparedBuildOptions,
+                                         space: &DrawSpace,
                                          draw_path_id: DrawPathId,
-                                         prepare_mode: &PrepareMode,
                                          paint_metadata: &[PaintMetadata])
                                          -> Option<BuiltDrawPath> {
-        let transform = match *prepare_mode {
+        let transform = match space.prepare_mode {
             PrepareMode::GPU { transform } => transform,
             PrepareMode::CPU | PrepareMode::TransformCPUBinGPU => {
                 panic!("`prepare_draw_path_for_gpu_binning()` requires a GPU prepare mode!")
             }
         };
 
-        let effective_view_box = scene.effective_view_box(built_options);
+        let effective_view_box = space.view_box;
         let draw_path = scene.get_draw_path(draw_path_id);
 
         let mut path_bounds = transform * draw_path.outline().bounds();
+        if let Some(device_stroke) = draw_path.device_stroke() {
+            path_bounds = path_bounds.dilate(device_stroke.max_extent());
+        }
         match path_bounds.intersection(effective_view_box) {
             Some(intersection) => path_bounds = intersection,
             None => return None,
@@ -1054,8 +1481,9 @@ impl TileBatchBuilder {
         let built_path = BuiltPath::new(draw_path_id.to_path_id(),
                                         path_bounds,
                                         effective_view_box,
+                                        space.built_options.tile_size,
                                         draw_path.fill_rule(),
-                                        &prepare_mode,
+                                        &space.prepare_mode,
                                         draw_path.clip_path(),
                                         &TilingPathInfo::Draw(DrawTilingPathInfo {
                                             paint_id,
@@ -1066,7 +1494,10 @@ impl TileBatchBuilder {
     }
 
     fn send_to(self, sink: &SceneSink) {
-        if let Some(clip_batches_d3d11) = self.clip_batches_d3d11 {
+        let all_clip_batches_d3d11 = self.clip_batches_d3d11
+                                         .into_iter()
+                                         .chain(self.layer_clip_batches_d3d11);
+        for clip_batches_d3d11 in all_clip_batches_d3d11 {
             for prepare_batch in clip_batches_d3d11.prepare_batches.into_iter().rev() {
                 if prepare_batch.path_count > 0 {
                     sink.listener.send(RenderCommand::PrepareClipTilesD3D11(prepare_batch));
@@ -1087,6 +1518,18 @@ struct ClipBatchesD3D11 {
     // Will be submitted in reverse (LIFO) order.
     prepare_batches: Vec<TileBatchDataD3D11>,
     clip_id_to_path_batch_index: FxHashMap<ClipPathId, PathBatchIndex>,
+    // The batch ID of the clip paths at level 0.
+    first_batch_id: u32,
+}
+
+impl ClipBatchesD3D11 {
+    fn new(first_batch_id: u32) -> ClipBatchesD3D11 {
+        ClipBatchesD3D11 {
+            prepare_batches: vec![],
+            clip_id_to_path_batch_index: FxHashMap::default(),
+            first_batch_id,
+        }
+    }
 }
 
 fn add_clip_path_to_batch(scene: &Scene,
@@ -1100,10 +1543,11 @@ fn add_clip_path_to_batch(scene: &Scene,
     match clip_path_id {
         None => None,
         Some(clip_path_id) => {
+            let clip_batch_id = TileBatchId(clip_batches_d3d11.first_batch_id + clip_level as u32);
             match clip_batches_d3d11.clip_id_to_path_batch_index.get(&clip_path_id) {
                 Some(&clip_path_batch_index) => {
                     Some(GlobalPathId {
-                        batch_id: TileBatchId(clip_level as u32),
+                        batch_id: clip_batch_id,
                         path_index: clip_path_batch_index,
                     })
                 }
@@ -1120,7 +1564,8 @@ fn add_clip_path_to_batch(scene: &Scene,
                                                           clip_batches_d3d11);
                     while clip_level >= clip_batches_d3d11.prepare_batches.len() {
                         let clip_tile_batch_id =
-                            TileBatchId(clip_batches_d3d11.prepare_batches.len() as u32);
+                            TileBatchId(clip_batches_d3d11.first_batch_id +
+                                        clip_batches_d3d11.prepare_batches.len() as u32);
                         clip_batches_d3d11.prepare_batches
                                           .push(TileBatchDataD3D11::new(clip_tile_batch_id,
                                                                         &prepare_mode,
@@ -1136,7 +1581,7 @@ fn add_clip_path_to_batch(scene: &Scene,
                     clip_batches_d3d11.clip_id_to_path_batch_index.insert(clip_path_id,
                                                                           clip_path_batch_index);
                     Some(GlobalPathId {
-                        batch_id: TileBatchId(clip_level as u32),
+                        batch_id: clip_batch_id,
                         path_index: clip_path_batch_index,
                     })
                 }
@@ -1171,13 +1616,14 @@ fn prepare_clip_path_for_gpu_binning(scene: &Scene,
                                             clip_level + 1,
                                             clip_batches_d3d11);
 
-    let path_bounds = transform * clip_path.outline().bounds();
-
-    // TODO(pcwalton): Clip to view box!
+    // Clip paths outside the view box get no tiles, which culls everything they clip.
+    let path_bounds = (transform * clip_path.outline().bounds()).intersection(effective_view_box)
+                                                                 .unwrap_or_default();
 
     let built_path = BuiltPath::new(clip_path_id.to_path_id(),
                                     path_bounds,
                                     effective_view_box,
+                                    built_options.tile_size,
                                     clip_path.fill_rule(),
                                     &prepare_mode,
                                     clip_path.clip_path(),
@@ -1208,3 +1654,74 @@ fn fixup_batch_for_new_path_if_possible(batch_color_texture: &mut Option<TileBat
     }
     true
 }
+
+#[cfg(test)]
+mod test {
+    use super::{SegmentsD3D11, conic_tolerance_d3d11};
+    use crate::concurrent::executor::SequentialExecutor;
+    use crate::gpu::options::RendererLevel;
+    use crate::gpu_data::RenderCommand;
+    use crate::options::{BuildOptions, RenderCommandListener, RenderTransform};
+    use crate::paint::Paint;
+    use crate::scene::{DeviceStroke, DrawPath, Scene, SceneSink};
+    use pathfinder_color::ColorU;
+    use pathfinder_content::outline::{Contour, Outline};
+    use pathfinder_geometry::transform2d::Transform2F;
+    use pathfinder_geometry::vector::vec2f;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn conic_tolerance_scales_with_zoom() {
+        let tolerance = conic_tolerance_d3d11(&Transform2F::default());
+        let zoomed_tolerance = conic_tolerance_d3d11(&Transform2F::from_scale(vec2f(100.0, 100.0)));
+        assert!(zoomed_tolerance * 100.0 <= tolerance);
+
+        // Small changes in scale don't change the tolerance.
+        let nearby_tolerance = conic_tolerance_d3d11(&Transform2F::from_scale(vec2f(95.0, 95.0)));
+        assert_eq!(nearby_tolerance, zoomed_tolerance);
+
+        let mut circle = Contour::new();
+        circle.push_ellipse(&Transform2F::from_scale(vec2f(10.0, 10.0)));
+        let mut outline = Outline::new();
+        outline.push_contour(circle);
+        let segments = SegmentsD3D11::from_outline(&outline, tolerance);
+        let zoomed_segments = SegmentsD3D11::from_outline(&outline, zoomed_tolerance);
+        assert!(zoomed_segments.indices.len() > segments.indices.len());
+    }
+
+    #[test]
+    fn device_strokes_are_rebuilt_when_the_transform_changes() {
+        let mut contour = Contour::new();
+        contour.push_endpoint(vec2f(0.0, 0.0));
+        contour.push_endpoint(vec2f(10.0, 10.0));
+        let mut outline = Outline::new();
+        outline.push_contour(contour);
+        let mut scene = Scene::new();
+        let paint = scene.push_paint(&Paint::from_color(ColorU::black()));
+        let mut draw_path = DrawPath::new(outline, paint);
+        draw_path.set_device_stroke(Some(DeviceStroke::hairline()));
+        scene.push_draw_path(draw_path);
+
+        let upload_count = Arc::new(AtomicUsize::new(0));
+        let upload_count_for_listener = upload_count.clone();
+        let listener = RenderCommandListener::new(Box::new(move |command| {
+            if let RenderCommand::UploadSceneD3D11 { .. } = command {
+                upload_count_for_listener.fetch_add(1, Ordering::SeqCst);
+            }
+        }));
+        let mut sink = SceneSink::new(listener, RendererLevel::D3D11);
+        let mut build = |scale: f32| {
+            let transform = Transform2F::from_scale(vec2f(scale, scale));
+            let build_options = BuildOptions {
+                transform: RenderTransform::Transform2D(transform),
+                ..BuildOptions::default()
+            };
+            scene.build(build_options, &mut sink, &SequentialExecutor);
+            upload_count.load(Ordering::SeqCst)
+        };
+        assert_eq!(build(1.0), 1);
+        assert_eq!(build(1.0), 1);
+        assert_eq!(build(2.0), 2);
+    }
+}
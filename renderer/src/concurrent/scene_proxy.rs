@@ -83,6 +83,14 @@ impl SceneProxy {
         self.sender.send(MainToWorkerMsg::SetViewBox(new_view_box)).unwrap();
     }
 
+    /// Makes the next build redraw every tile, as if the tile cache were empty.
+    ///
+    /// See `SceneSink::invalidate_tile_cache()`.
+    #[inline]
+    pub fn invalidate_tile_cache(&self) {
+        self.sender.send(MainToWorkerMsg::InvalidateTileCache).unwrap();
+    }
+
     /// Constructs a scene and queues up the commands needed to render it.
     #[inline]
     pub fn build(&self, options: BuildOptions) {
@@ -139,6 +147,7 @@ fn scene_thread<E>(mut scene: Scene,
             MainToWorkerMsg::ReplaceScene(new_scene) => scene = new_scene,
             MainToWorkerMsg::CopyScene(sender) => sender.send(scene.clone()).unwrap(),
             MainToWorkerMsg::SetViewBox(new_view_box) => scene.set_view_box(new_view_box),
+            MainToWorkerMsg::InvalidateTileCache => sink.invalidate_tile_cache(),
             MainToWorkerMsg::Build(options) => scene.build(options, &mut sink, &executor),
         }
     }
@@ -148,5 +157,6 @@ enum MainToWorkerMsg {
     ReplaceScene(Scene),
     CopyScene(Sender<Scene>),
     SetViewBox(RectF),
+    InvalidateTileCache,
     Build(BuildOptions),
 }
@@ -0,0 +1,282 @@
+// pathfinder/renderer/src/culling.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Finds draw paths and tiles that can't affect the output because opaque paths drawn later hide
+//! them.
+//!
+//! Path culling is conservative: only axis-aligned rectangles painted with an opaque paint count
+//! as occluders, and a path is only culled if its bounds lie entirely inside the pixels that one
+//! of them fully covers. Tile culling works on the tiles binned on CPU and hides any tile beneath
+//! a solid tile of a later opaque path.
+
+use crate::options::{PreparedBuildOptions, PreparedRenderTransform};
+use crate::paint::PaintMetadata;
+use crate::scene::{DisplayItem, DrawPath, DrawPathId, Scene};
+use crate::tile_map::DenseTileMap;
+use pathfinder_content::outline::{Outline, PointFlags};
+use pathfinder_geometry::rect::{RectF, RectI};
+use pathfinder_geometry::transform2d::Transform2F;
+use pathfinder_geometry::vector::Vector2I;
+
+// The number of occluders that paths are tested against. Only the largest ones are kept, so that
+// culling stays cheap for scenes with tens of thousands of paths.
+const MAX_OCCLUDERS: usize = 16;
+
+/// Which draw paths are hidden behind opaque paths drawn after them.
+pub(crate) struct DrawPathOcclusion {
+    occluded_draw_paths: Vec<bool>,
+}
+
+impl DrawPathOcclusion {
+    /// Finds the draw paths hidden behind others.
+    ///
+    /// Only paths drawn directly to the destination framebuffer are considered. Nothing is culled
+    /// with perspective transforms, subpixel antialiasing, or dilation.
+    pub(crate) fn new(scene: &Scene,
+                      built_options: &PreparedBuildOptions,
+                      paint_metadata: &[PaintMetadata])
+                      -> DrawPathOcclusion {
+        let mut occlusion = DrawPathOcclusion { occluded_draw_paths: vec![] };
+        let transform = match built_options.transform {
+            PreparedRenderTransform::None => Transform2F::default(),
+            PreparedRenderTransform::Transform2D(transform) => transform,
+            PreparedRenderTransform::Perspective { .. } => return occlusion,
+        };
+        if built_options.subpixel_aa_enabled || !built_options.dilation.is_zero() {
+            return occlusion;
+        }
+
+        occlusion.occluded_draw_paths = vec![false; scene.draw_paths().len()];
+
+        // Walk the paths drawn to the framebuffer back to front, remembering the largest
+        // occluders seen so far, i.e. those drawn later.
+        let mut occluders: Vec<RectF> = vec![];
+        let mut framebuffer_nesting = 0;
+        for display_item in scene.display_list().iter().rev() {
+            match *display_item {
+                DisplayItem::PopRenderTarget | DisplayItem::PopLayer => framebuffer_nesting += 1,
+                DisplayItem::PushRenderTarget(_) | DisplayItem::PushLayer(_) => {
+                    framebuffer_nesting -= 1
+                }
+                DisplayItem::DrawPaths(_) if framebuffer_nesting > 0 => {}
+                DisplayItem::DrawPaths(ref draw_path_id_range) => {
+                    for draw_path_index in (draw_path_id_range.start.0..
+                                            draw_path_id_range.end.0).rev() {
+                        let draw_path = scene.get_draw_path(DrawPathId(draw_path_index));
+                        let bounds = device_bounds(draw_path, &transform);
+                        if !draw_path.blend_mode().is_destructive() &&
+                                occluders.iter().any(|occluder| occluder.contains_rect(bounds)) {
+                            occlusion.occluded_draw_paths[draw_path_index as usize] = true;
+                            continue;
+                        }
+
+                        if let Some(occluder) = occluder_rect(draw_path,
+                                                              &transform,
+                                                              paint_metadata) {
+                            add_occluder(&mut occluders, occluder);
+                        }
+                    }
+                }
+            }
+        }
+
+        occlusion
+    }
+
+    /// Returns true if the given draw path is hidden behind opaque paths drawn after it.
+    #[inline]
+    pub(crate) fn draw_path_is_occluded(&self, draw_path_id: DrawPathId) -> bool {
+        self.occluded_draw_paths.get(draw_path_id.0 as usize).cloned().unwrap_or(false)
+    }
+}
+
+/// For each tile of the destination framebuffer, the last draw path that covers it entirely with
+/// an opaque solid tile.
+pub(crate) struct TileOcclusion {
+    // The ID of the occluding draw path plus one, or zero if nothing occludes the tile.
+    occluders: DenseTileMap<u32>,
+}
+
+impl TileOcclusion {
+    #[inline]
+    pub(crate) fn new(tile_rect: RectI) -> TileOcclusion {
+        TileOcclusion { occluders: DenseTileMap::from_builder(|_| 0, tile_rect) }
+    }
+
+    /// Records that the given draw path fully covers the tile at the given coordinates.
+    ///
+    /// Tiles outside the framebuffer are ignored.
+    #[inline]
+    pub(crate) fn add_occluder(&mut self, tile_coords: Vector2I, draw_path_id: DrawPathId) {
+        if let Some(occluder) = self.occluders.get_mut(tile_coords) {
+            *occluder = (*occluder).max(draw_path_id.0 + 1);
+        }
+    }
+
+    /// Returns true if a draw path drawn after the given one fully covers the tile at the given
+    /// coordinates.
+    #[inline]
+    pub(crate) fn tile_is_occluded(&self, tile_coords: Vector2I, draw_path_id: DrawPathId)
+                                   -> bool {
+        match self.occluders.get(tile_coords) {
+            None => false,
+            Some(&occluder) => occluder > draw_path_id.0 + 1,
+        }
+    }
+}
+
+// Returns a conservative bounding box of the pixels that the path may touch.
+fn device_bounds(draw_path: &DrawPath, transform: &Transform2F) -> RectF {
+    let mut bounds = *transform * draw_path.outline().bounds();
+    if let Some(device_stroke) = draw_path.device_stroke() {
+        bounds = bounds.dilate(device_stroke.max_extent());
+    }
+    bounds
+}
+
+// Returns the pixels that the path fully covers with an opaque color, if the path is an opaque
+// axis-aligned rectangle.
+fn occluder_rect(draw_path: &DrawPath,
+                 transform: &Transform2F,
+                 paint_metadata: &[PaintMetadata])
+                 -> Option<RectF> {
+    let paint_metadata = &paint_metadata[draw_path.paint().0 as usize];
+    if !paint_metadata.is_opaque || !draw_path.blend_mode().occludes_backdrop() ||
+            draw_path.clip_path().is_some() || draw_path.device_stroke().is_some() ||
+            !is_axis_aligned_rect(draw_path.outline()) {
+        return None;
+    }
+
+    let preserves_axes = (transform.m12() == 0.0 && transform.m21() == 0.0) ||
+        (transform.m11() == 0.0 && transform.m22() == 0.0);
+    if !preserves_axes {
+        return None;
+    }
+
+    // Pixels along the edges are only partially covered.
+    let rect = *transform * draw_path.outline().bounds();
+    let inner_rect = RectF::from_points(rect.origin().ceil(), rect.lower_right().floor());
+    if inner_rect.width() <= 0.0 || inner_rect.height() <= 0.0 {
+        return None;
+    }
+    Some(inner_rect)
+}
+
+fn is_axis_aligned_rect(outline: &Outline) -> bool {
+    let contour = match outline.contours() {
+        [contour] if contour.len() == 4 && contour.is_closed() => contour,
+        _ => return false,
+    };
+
+    // Each point must be a corner of the bounds, and each side must be horizontal or vertical.
+    let bounds = contour.bounds();
+    (0..4).all(|point_index| {
+        let point = contour.position_of(point_index);
+        let next_point = contour.position_of((point_index + 1) % 4);
+        !contour.flags_of(point_index).intersects(PointFlags::CONTROL_POINT_0 |
+                                                  PointFlags::CONTROL_POINT_1) &&
+            (point.x() == bounds.min_x() || point.x() == bounds.max_x()) &&
+            (point.y() == bounds.min_y() || point.y() == bounds.max_y()) &&
+            (point.x() == next_point.x()) != (point.y() == next_point.y())
+    })
+}
+
+// Keeps the largest occluders, sorted by decreasing area.
+fn add_occluder(occluders: &mut Vec<RectF>, occluder: RectF) {
+    let area = occluder.width() * occluder.height();
+    let index = occluders.iter()
+                         .position(|other| other.width() * other.height() < area)
+                         .unwrap_or(occluders.len());
+    if index < MAX_OCCLUDERS {
+        occluders.insert(index, occluder);
+        occluders.truncate(MAX_OCCLUDERS);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{MAX_OCCLUDERS, add_occluder, is_axis_aligned_rect};
+    use pathfinder_content::outline::{Contour, Outline};
+    use pathfinder_geometry::rect::RectF;
+    use pathfinder_geometry::transform2d::Transform2F;
+    use pathfinder_geometry::vector::{Vector2F, vec2f};
+
+    fn outline_from_points(points: &[Vector2F], closed: bool) -> Outline {
+        let mut contour = Contour::new();
+        for &point in points {
+            contour.push_endpoint(point);
+        }
+        if closed {
+            contour.close();
+        }
+        let mut outline = Outline::new();
+        outline.push_contour(contour);
+        outline
+    }
+
+    #[test]
+    fn rects_are_axis_aligned() {
+        let rect = RectF::new(vec2f(1.0, 2.0), vec2f(10.0, 20.0));
+        assert!(is_axis_aligned_rect(&Outline::from_rect(rect)));
+
+        // Winding doesn't matter.
+        let points = [rect.origin(), rect.lower_left(), rect.lower_right(), rect.upper_right()];
+        assert!(is_axis_aligned_rect(&outline_from_points(&points, true)));
+    }
+
+    #[test]
+    fn other_shapes_are_not_axis_aligned_rects() {
+        let rect = RectF::new(vec2f(0.0, 0.0), vec2f(10.0, 10.0));
+
+        let rotated = Outline::from_rect(rect).transformed(&Transform2F::from_rotation(0.5));
+        assert!(!is_axis_aligned_rect(&rotated));
+
+        let corners = [rect.origin(), rect.upper_right(), rect.lower_right(), rect.lower_left()];
+        assert!(!is_axis_aligned_rect(&outline_from_points(&corners, false)));
+
+        let bowtie = [rect.origin(), rect.lower_right(), rect.upper_right(), rect.lower_left()];
+        assert!(!is_axis_aligned_rect(&outline_from_points(&bowtie, true)));
+
+        let triangle = [rect.origin(), rect.upper_right(), rect.lower_left()];
+        assert!(!is_axis_aligned_rect(&outline_from_points(&triangle, true)));
+
+        let rounded = Outline::from_rect_rounded(rect, vec2f(2.0, 2.0));
+        assert!(!is_axis_aligned_rect(&rounded));
+
+        let mut curved = Contour::new();
+        curved.push_endpoint(rect.origin());
+        curved.push_quadratic(vec2f(5.0, -5.0), rect.upper_right());
+        curved.push_endpoint(rect.lower_right());
+        curved.close();
+        let mut outline = Outline::new();
+        outline.push_contour(curved);
+        assert!(!is_axis_aligned_rect(&outline));
+
+        let mut two_rects = Outline::from_rect(rect);
+        two_rects.push_contour(Contour::from_rect(RectF::new(vec2f(20.0, 0.0), rect.size())));
+        assert!(!is_axis_aligned_rect(&two_rects));
+    }
+
+    #[test]
+    fn only_the_largest_occluders_are_kept() {
+        let mut occluders = vec![];
+        for side in 1..(MAX_OCCLUDERS as i32 + 4) {
+            let side = side as f32;
+            add_occluder(&mut occluders, RectF::new(Vector2F::zero(), vec2f(side, side)));
+        }
+        assert_eq!(occluders.len(), MAX_OCCLUDERS);
+        assert_eq!(occluders[0].width(), MAX_OCCLUDERS as f32 + 3.0);
+        assert_eq!(occluders[MAX_OCCLUDERS - 1].width(), 4.0);
+
+        // Smaller occluders than all the kept ones are dropped.
+        add_occluder(&mut occluders, RectF::new(Vector2F::zero(), vec2f(2.0, 2.0)));
+        assert_eq!(occluders[MAX_OCCLUDERS - 1].width(), 4.0);
+    }
+}
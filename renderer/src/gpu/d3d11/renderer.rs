@@ -25,7 +25,7 @@ use pathfinder_geometry::transform2d::Transform2F;
 use pathfinder_geometry::vector::Vector2F;
 use pathfinder_gpu::allocator::{BufferTag, GeneralBufferID, GPUMemoryAllocator};
 use pathfinder_gpu::{BufferTarget, ComputeDimensions, ComputeState, Device, ImageAccess};
-use pathfinder_gpu::{RenderTarget, UniformData};
+use pathfinder_gpu::{RenderTarget, TextureFormat, UniformData};
 use pathfinder_resources::ResourceLoader;
 use pathfinder_simd::default::{F32x4, I32x2};
 use std::ops::Range;
@@ -716,7 +716,18 @@ impl<D> RendererD3D11<D> where D: Device {
         let timer_query = core.timer_query_cache.start_timing_draw_call(&core.device,
                                                                         &core.options);
 
-        let tile_program = &self.programs.tile_program;
+        let dest_texture = match core.draw_render_target() {
+            RenderTarget::Default => panic!("Can't draw to the default framebuffer with compute!"),
+            RenderTarget::Framebuffer(ref framebuffer) => {
+                core.device.framebuffer_texture(framebuffer)
+            }
+        };
+
+        // The image format is baked into the shader, so pick the variant matching the target.
+        let tile_program = match core.device.texture_format(dest_texture) {
+            TextureFormat::RGBA16F => &self.programs.tile_rgba16f_program,
+            _ => &self.programs.tile_program,
+        };
 
         let (mut textures, mut uniforms, mut images) = (vec![], vec![], vec![]);
 
@@ -728,13 +739,7 @@ impl<D> RendererD3D11<D> where D: Device {
         uniforms.push((&tile_program.framebuffer_tile_size_uniform,
                        UniformData::IVec2(core.framebuffer_tile_size().0)));
 
-        match core.draw_render_target() {
-            RenderTarget::Default => panic!("Can't draw to the default framebuffer with compute!"),
-            RenderTarget::Framebuffer(ref framebuffer) => {
-                let dest_texture = core.device.framebuffer_texture(framebuffer);
-                images.push((&tile_program.dest_image, dest_texture, ImageAccess::ReadWrite));
-            }
-        }
+        images.push((&tile_program.dest_image, dest_texture, ImageAccess::ReadWrite));
 
         let clear_color = core.clear_color_for_draw_operation();
         match clear_color {
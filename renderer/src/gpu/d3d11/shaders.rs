@@ -29,6 +29,7 @@ pub(crate) struct ProgramsD3D11<D> where D: Device {
     pub(crate) sort_program: SortProgramD3D11<D>,
     pub(crate) fill_program: FillProgramD3D11<D>,
     pub(crate) tile_program: TileProgramD3D11<D>,
+    pub(crate) tile_rgba16f_program: TileProgramD3D11<D>,
 }
 
 impl<D> ProgramsD3D11<D> where D: Device {
@@ -40,7 +41,8 @@ impl<D> ProgramsD3D11<D> where D: Device {
             propagate_program: PropagateProgramD3D11::new(device, resources),
             sort_program: SortProgramD3D11::new(device, resources),
             fill_program: FillProgramD3D11::new(device, resources),
-            tile_program: TileProgramD3D11::new(device, resources),
+            tile_program: TileProgramD3D11::new(device, resources, "d3d11/tile"),
+            tile_rgba16f_program: TileProgramD3D11::new(device, resources, "d3d11/tile_rgba16f"),
         }
     }
 }
@@ -141,8 +143,8 @@ pub(crate) struct TileProgramD3D11<D> where D: Device {
 }
 
 impl<D> TileProgramD3D11<D> where D: Device {
-    fn new(device: &D, resources: &dyn ResourceLoader) -> TileProgramD3D11<D> {
-        let mut program = device.create_compute_program(resources, "d3d11/tile");
+    fn new(device: &D, resources: &dyn ResourceLoader, name: &str) -> TileProgramD3D11<D> {
+        let mut program = device.create_compute_program(resources, name);
         device.set_compute_program_local_size(&mut program,
                                               ComputeDimensions { x: 16, y: 4, z: 1 });
 
@@ -15,27 +15,24 @@
 
 use crate::gpu::blend::{BlendModeExt, ToBlendState};
 use crate::gpu::perf::TimeCategory;
-use crate::gpu::renderer::{FramebufferFlags, MASK_FRAMEBUFFER_HEIGHT, MASK_FRAMEBUFFER_WIDTH};
-use crate::gpu::renderer::{RendererCore, RendererFlags};
+use crate::gpu::renderer::{FramebufferFlags, RendererCore, RendererFlags};
 use crate::gpu::d3d9::shaders::{ClipTileCombineVertexArrayD3D9, ClipTileCopyVertexArrayD3D9};
 use crate::gpu::d3d9::shaders::{CopyTileVertexArray, FillVertexArrayD3D9};
 use crate::gpu::d3d9::shaders::{ProgramsD3D9, TileVertexArrayD3D9};
 use crate::gpu_data::{Clip, DrawTileBatchD3D9, Fill, TileBatchTexture, TileObjectPrimitive};
 use crate::tile_map::DenseTileMap;
-use crate::tiles::{TILE_HEIGHT, TILE_WIDTH};
 use byte_slice_cast::AsByteSlice;
 use pathfinder_color::ColorF;
 use pathfinder_content::effects::BlendMode;
 use pathfinder_geometry::rect::RectI;
 use pathfinder_geometry::transform3d::Transform4F;
-use pathfinder_geometry::vector::{Vector2I, Vector4F, vec2i};
+use pathfinder_geometry::vector::{Vector2I, Vector4F};
 use pathfinder_gpu::allocator::{BufferTag, FramebufferID, FramebufferTag, GeneralBufferID};
 use pathfinder_gpu::allocator::{IndexBufferID, TextureID, TextureTag};
 use pathfinder_gpu::{BlendFactor, BlendState, BufferTarget, ClearOps, Device, Primitive};
 use pathfinder_gpu::{RenderOptions, RenderState, RenderTarget, StencilFunc, StencilState};
 use pathfinder_gpu::{TextureDataRef, TextureFormat, UniformData};
 use pathfinder_resources::ResourceLoader;
-use pathfinder_simd::default::F32x2;
 use std::u32;
 
 const MAX_FILLS_PER_BATCH: usize = 0x10000;
@@ -244,7 +241,7 @@ impl<D> RendererD3D9<D> where D: Device {
                 (&fill_raster_program.framebuffer_size_uniform,
                  UniformData::Vec2(mask_viewport.size().to_f32().0)),
                 (&fill_raster_program.tile_size_uniform,
-                 UniformData::Vec2(F32x2::new(TILE_WIDTH as f32, TILE_HEIGHT as f32))),
+                 UniformData::Vec2(core.tile_pixel_size.to_f32().0)),
             ],
             images: &[],
             storage_buffers: &[],
@@ -323,6 +320,8 @@ impl<D> RendererD3D9<D> where D: Device {
             uniforms: &[
                 (&self.programs.tile_clip_copy_program.framebuffer_size_uniform,
                  UniformData::Vec2(mask_texture_size.to_f32().0)),
+                (&self.programs.tile_clip_copy_program.tile_size_uniform,
+                 UniformData::Vec2(core.tile_pixel_size.to_f32().0)),
             ],
             storage_buffers: &[],
             viewport: RectI::new(Vector2I::zero(), mask_texture_size),
@@ -349,6 +348,8 @@ impl<D> RendererD3D9<D> where D: Device {
             uniforms: &[
                 (&self.programs.tile_clip_combine_program.framebuffer_size_uniform,
                  UniformData::Vec2(mask_texture_size.to_f32().0)),
+                (&self.programs.tile_clip_combine_program.tile_size_uniform,
+                 UniformData::Vec2(core.tile_pixel_size.to_f32().0)),
             ],
             storage_buffers: &[],
             viewport: RectI::new(Vector2I::zero(), mask_texture_size),
@@ -482,7 +483,7 @@ impl<D> RendererD3D9<D> where D: Device {
             (&self.programs.tile_copy_program.transform_uniform,
              UniformData::Mat4(self.tile_transform(core).to_columns())),
             (&self.programs.tile_copy_program.tile_size_uniform,
-             UniformData::Vec2(F32x2::new(TILE_WIDTH as f32, TILE_HEIGHT as f32))),
+             UniformData::Vec2(core.tile_pixel_size.to_f32().0)),
         ];
 
         let draw_framebuffer = match core.draw_render_target() {
@@ -546,11 +547,10 @@ impl<D> RendererD3D9<D> where D: Device {
 
     fn mask_viewport(&self, core: &RendererCore<D>) -> RectI {
         let page_count = match core.mask_storage {
-            Some(ref mask_storage) => mask_storage.allocated_page_count as i32,
+            Some(ref mask_storage) => mask_storage.allocated_page_count,
             None => 0,
         };
-        let height = MASK_FRAMEBUFFER_HEIGHT * page_count;
-        RectI::new(Vector2I::default(), vec2i(MASK_FRAMEBUFFER_WIDTH, height))
+        RectI::new(Vector2I::default(), core.mask_framebuffer_size(page_count))
     }
 
     fn tile_transform(&self, core: &RendererCore<D>) -> Transform4F {
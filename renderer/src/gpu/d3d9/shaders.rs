@@ -351,6 +351,7 @@ pub(crate) struct ClipTileCombineProgramD3D9<D> where D: Device {
     pub(crate) program: D::Program,
     pub(crate) src_texture: D::TextureParameter,
     pub(crate) framebuffer_size_uniform: D::Uniform,
+    pub(crate) tile_size_uniform: D::Uniform,
 }
 
 impl<D> ClipTileCombineProgramD3D9<D> where D: Device {
@@ -359,7 +360,8 @@ impl<D> ClipTileCombineProgramD3D9<D> where D: Device {
         let program = device.create_raster_program(resources, "d3d9/tile_clip_combine");
         let src_texture = device.get_texture_parameter(&program, "Src");
         let framebuffer_size_uniform = device.get_uniform(&program, "FramebufferSize");
-        ClipTileCombineProgramD3D9 { program, src_texture, framebuffer_size_uniform }
+        let tile_size_uniform = device.get_uniform(&program, "TileSize");
+        ClipTileCombineProgramD3D9 { program, src_texture, framebuffer_size_uniform, tile_size_uniform }
     }
 }
 
@@ -367,6 +369,7 @@ pub(crate) struct ClipTileCopyProgramD3D9<D> where D: Device {
     pub(crate) program: D::Program,
     pub(crate) src_texture: D::TextureParameter,
     pub(crate) framebuffer_size_uniform: D::Uniform,
+    pub(crate) tile_size_uniform: D::Uniform,
 }
 
 impl<D> ClipTileCopyProgramD3D9<D> where D: Device {
@@ -374,7 +377,8 @@ impl<D> ClipTileCopyProgramD3D9<D> where D: Device {
         let program = device.create_raster_program(resources, "d3d9/tile_clip_copy");
         let src_texture = device.get_texture_parameter(&program, "Src");
         let framebuffer_size_uniform = device.get_uniform(&program, "FramebufferSize");
-        ClipTileCopyProgramD3D9 { program, src_texture, framebuffer_size_uniform }
+        let tile_size_uniform = device.get_uniform(&program, "TileSize");
+        ClipTileCopyProgramD3D9 { program, src_texture, framebuffer_size_uniform, tile_size_uniform }
     }
 }
 
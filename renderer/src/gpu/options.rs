@@ -15,10 +15,22 @@ use pathfinder_geometry::rect::RectI;
 use pathfinder_geometry::vector::Vector2I;
 use pathfinder_gpu::{Device, FeatureLevel};
 
+const DEFAULT_ALPHA_TILES_PER_PAGE: u32 = 65536;
+// D3D9 tiles refer to their alpha tiles with 24 bits, so bigger pages would never fill up.
+const MAX_ALPHA_TILES_PER_PAGE: u32 = 1 << 24;
+
 /// Renderer options that can't be changed after the renderer is created.
+#[derive(Clone, Copy, Debug)]
 pub struct RendererMode {
     /// The level of hardware features that the renderer will attempt to use.
     pub level: RendererLevel,
+    /// How many alpha tiles the mask texture grows by whenever it runs out of room.
+    ///
+    /// This should be a multiple of 256 between 256 and 2^24; the renderer rounds other values to
+    /// the nearest such multiple. The default, 65536, allocates masks in large steps;
+    /// memory-constrained devices may want smaller ones, at the cost of reallocating the mask more
+    /// often in complex scenes.
+    pub alpha_tiles_per_page: u32,
 }
 
 /// Options that influence rendering that can be changed at runtime.
@@ -30,6 +42,33 @@ pub struct RendererOptions<D> where D: Device {
     pub background_color: Option<ColorF>,
     /// Whether to display the debug UI.
     pub show_debug_ui: bool,
+    /// Whether to composite in linear light instead of directly on the gamma-encoded colors.
+    ///
+    /// An 8-bit destination is still assumed to be sRGB-encoded; colors are decoded before
+    /// blending and encoded again afterward. A floating-point destination holds linear-light colors
+    /// directly. This makes antialiased edges and translucent overlaps match browsers that blend in
+    /// linear light. Content drawn into render targets (for example, to apply blurs) is still
+    /// composited in gamma space. On the D3D9 level, an 8-bit destination requires an intermediate
+    /// floating-point framebuffer.
+    pub linear_blending: bool,
+    /// How to map colors brighter than white into the displayable range when presenting to an
+    /// 8-bit destination.
+    ///
+    /// Anything other than `ToneMapping::None` implies linear blending and requires an
+    /// intermediate floating-point framebuffer. Floating-point destinations receive the unmapped
+    /// colors, so that the caller can process them further.
+    pub tone_mapping: ToneMapping,
+}
+
+/// A curve that maps high-dynamic-range colors, in linear light, to the range [0, 1].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ToneMapping {
+    /// Colors are clamped.
+    None,
+    /// The simple Reinhard operator, `c / (1 + c)`.
+    Reinhard,
+    /// An approximation of the ACES filmic curve, which keeps more contrast than Reinhard.
+    Aces,
 }
 
 /// The GPU API level that Pathfinder will use.
@@ -46,10 +85,24 @@ pub enum RendererLevel {
 }
 
 impl RendererMode {
+    /// Creates a new `RendererMode` with the given API level and the default alpha tile page
+    /// size.
+    #[inline]
+    pub fn new(level: RendererLevel) -> RendererMode {
+        RendererMode { level, alpha_tiles_per_page: DEFAULT_ALPHA_TILES_PER_PAGE }
+    }
+
     /// Creates a new `RendererMode` with a suitable API level for the given GPU device.
     #[inline]
     pub fn default_for_device<D>(device: &D) -> RendererMode where D: Device {
-        RendererMode { level: RendererLevel::default_for_device(device) }
+        RendererMode::new(RendererLevel::default_for_device(device))
+    }
+
+    // Returns this mode with the alpha tile page size rounded to a valid one.
+    pub(crate) fn prepare(self) -> RendererMode {
+        let alpha_tiles_per_page = (self.alpha_tiles_per_page.saturating_add(128) / 256 * 256)
+            .clamp(256, MAX_ALPHA_TILES_PER_PAGE);
+        RendererMode { alpha_tiles_per_page, ..self }
     }
 }
 
@@ -60,10 +113,19 @@ impl<D> Default for RendererOptions<D> where D: Device {
             dest: DestFramebuffer::default(),
             background_color: None,
             show_debug_ui: false,
+            linear_blending: false,
+            tone_mapping: ToneMapping::None,
         }
     }
 }
 
+impl Default for ToneMapping {
+    #[inline]
+    fn default() -> ToneMapping {
+        ToneMapping::None
+    }
+}
+
 impl RendererLevel {
     /// Returns a suitable renderer level for the given device.
     pub fn default_for_device<D>(device: &D) -> RendererLevel where D: Device {
@@ -117,3 +179,24 @@ impl<D> DestFramebuffer<D> where D: Device {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{RendererLevel, RendererMode};
+
+    #[test]
+    fn alpha_tile_page_sizes_are_rounded_to_valid_ones() {
+        let prepared_page_size = |alpha_tiles_per_page| {
+            let mode = RendererMode {
+                alpha_tiles_per_page,
+                ..RendererMode::new(RendererLevel::D3D9)
+            };
+            mode.prepare().alpha_tiles_per_page
+        };
+        assert_eq!(prepared_page_size(65536), 65536);
+        assert_eq!(prepared_page_size(0), 256);
+        assert_eq!(prepared_page_size(300), 256);
+        assert_eq!(prepared_page_size(400), 512);
+        assert_eq!(prepared_page_size(u32::MAX), 1 << 24);
+    }
+}
@@ -30,8 +30,30 @@ pub struct RenderStats {
     /// The total number of 16x16 tiles needed to render the scene, including both alpha tiles and
     /// solid-color tiles.
     pub total_tile_count: usize,
+    /// The number of 16x16 tiles that were redrawn because their contents changed.
+    ///
+    /// This is only counted when the tile cache is enabled via `BuildOptions::tile_cache`.
+    pub dirty_tile_count: usize,
+    /// The number of 16x16 tiles whose contents were reused from the previous frame.
+    ///
+    /// This is only counted when the tile cache is enabled via `BuildOptions::tile_cache`.
+    pub reused_tile_count: usize,
+    /// The number of draw paths that weren't tiled at all because they lie outside the view box
+    /// or behind an opaque rectangle drawn after them.
+    pub culled_path_count: usize,
+    /// The number of contours that weren't tiled because they lie outside the view box.
+    ///
+    /// This is only counted when paths are tiled on CPU, as the D3D9 renderer does.
+    pub culled_contour_count: usize,
+    /// The number of tiles that weren't drawn because a solid tile of an opaque path drawn after
+    /// them covers them entirely.
+    ///
+    /// This is only counted when paths are tiled on CPU, as the D3D9 renderer does.
+    pub culled_tile_count: usize,
     /// The amount of CPU time it took to build the scene.
     pub cpu_build_time: Duration,
+    /// The amount of CPU time it took to build the scene, broken up into phases.
+    pub cpu_build_phase_times: BuildPhaseTimes,
     /// The number of GPU API draw calls it took to render the scene.
     pub drawcall_count: u32,
     /// The number of bytes of VRAM Pathfinder has allocated.
@@ -41,6 +63,12 @@ pub struct RenderStats {
     pub gpu_bytes_allocated: u64,
     /// The number of bytes of VRAM Pathfinder actually used for the frame.
     pub gpu_bytes_committed: u64,
+    /// The number of texture pages holding paints and render targets.
+    pub texture_page_count: usize,
+    /// The number of bytes of VRAM those texture pages take up.
+    ///
+    /// This is part of `gpu_bytes_allocated`.
+    pub texture_page_bytes: u64,
 }
 
 impl Add<RenderStats> for RenderStats {
@@ -50,11 +78,19 @@ impl Add<RenderStats> for RenderStats {
             path_count: self.path_count + other.path_count,
             alpha_tile_count: self.alpha_tile_count + other.alpha_tile_count,
             total_tile_count: self.total_tile_count + other.total_tile_count,
+            dirty_tile_count: self.dirty_tile_count + other.dirty_tile_count,
+            reused_tile_count: self.reused_tile_count + other.reused_tile_count,
+            culled_path_count: self.culled_path_count + other.culled_path_count,
+            culled_contour_count: self.culled_contour_count + other.culled_contour_count,
+            culled_tile_count: self.culled_tile_count + other.culled_tile_count,
             fill_count: self.fill_count + other.fill_count,
             cpu_build_time: self.cpu_build_time + other.cpu_build_time,
+            cpu_build_phase_times: self.cpu_build_phase_times + other.cpu_build_phase_times,
             drawcall_count: self.drawcall_count + other.drawcall_count,
             gpu_bytes_allocated: self.gpu_bytes_allocated + other.gpu_bytes_allocated,
             gpu_bytes_committed: self.gpu_bytes_committed + other.gpu_bytes_committed,
+            texture_page_count: self.texture_page_count + other.texture_page_count,
+            texture_page_bytes: self.texture_page_bytes + other.texture_page_bytes,
         }
     }
 }
@@ -66,11 +102,19 @@ impl Div<usize> for RenderStats {
             path_count: self.path_count / divisor,
             alpha_tile_count: self.alpha_tile_count / divisor,
             total_tile_count: self.total_tile_count / divisor,
+            dirty_tile_count: self.dirty_tile_count / divisor,
+            reused_tile_count: self.reused_tile_count / divisor,
+            culled_path_count: self.culled_path_count / divisor,
+            culled_contour_count: self.culled_contour_count / divisor,
+            culled_tile_count: self.culled_tile_count / divisor,
             fill_count: self.fill_count / divisor,
             cpu_build_time: self.cpu_build_time / divisor as u32,
+            cpu_build_phase_times: self.cpu_build_phase_times / divisor,
             drawcall_count: self.drawcall_count / divisor as u32,
             gpu_bytes_allocated: self.gpu_bytes_allocated / divisor as u64,
             gpu_bytes_committed: self.gpu_bytes_committed / divisor as u64,
+            texture_page_count: self.texture_page_count / divisor,
+            texture_page_bytes: self.texture_page_bytes / divisor as u64,
         }
     }
 }
@@ -289,3 +333,52 @@ impl Div<usize> for RenderTime {
         }
     }
 }
+
+/// The amount of CPU time it took to build the scene, broken up into phases.
+///
+/// Phases that a renderer level doesn't need take no time.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BuildPhaseTimes {
+    /// How much CPU time it took to assign texture locations to paints and to render gradients.
+    pub paint_time: Duration,
+    /// How much CPU time it took to transform and tile paths.
+    ///
+    /// This will be zero in the D3D11-level backend, unless paths are transformed on CPU.
+    pub path_time: Duration,
+    /// How much CPU time it took to convert outlines to segments for upload to the GPU.
+    ///
+    /// This will be zero in the D3D9-level backend, and whenever the scene didn't change since
+    /// the last frame.
+    pub segment_time: Duration,
+    /// How much CPU time it took to sort tiles into batches and to generate draw commands.
+    pub batch_time: Duration,
+}
+
+impl Add<BuildPhaseTimes> for BuildPhaseTimes {
+    type Output = BuildPhaseTimes;
+
+    #[inline]
+    fn add(self, other: BuildPhaseTimes) -> BuildPhaseTimes {
+        BuildPhaseTimes {
+            paint_time: self.paint_time + other.paint_time,
+            path_time: self.path_time + other.path_time,
+            segment_time: self.segment_time + other.segment_time,
+            batch_time: self.batch_time + other.batch_time,
+        }
+    }
+}
+
+impl Div<usize> for BuildPhaseTimes {
+    type Output = BuildPhaseTimes;
+
+    #[inline]
+    fn div(self, divisor: usize) -> BuildPhaseTimes {
+        let divisor = divisor as u32;
+        BuildPhaseTimes {
+            paint_time: self.paint_time / divisor,
+            path_time: self.path_time / divisor,
+            segment_time: self.segment_time / divisor,
+            batch_time: self.batch_time / divisor,
+        }
+    }
+}
@@ -14,16 +14,20 @@ use crate::gpu::blend::{ToBlendState, ToCompositeCtrl};
 use crate::gpu::d3d9::renderer::RendererD3D9;
 use crate::gpu::d3d11::renderer::RendererD3D11;
 use crate::gpu::debug::DebugUIPresenter;
-use crate::gpu::options::{DestFramebuffer, RendererLevel, RendererMode, RendererOptions};
+use crate::gpu::options::{DestFramebuffer, RendererLevel, RendererMode};
+use crate::gpu::options::{RendererOptions, ToneMapping};
 use crate::gpu::perf::{PendingTimer, RenderStats, RenderTime, TimeCategory, TimerQueryCache};
-use crate::gpu::shaders::{BlitProgram, BlitVertexArray, ClearProgram, ClearVertexArray};
-use crate::gpu::shaders::{ProgramsCore, ReprojectionProgram, ReprojectionVertexArray};
-use crate::gpu::shaders::{StencilProgram, StencilVertexArray, TileProgramCommon, VertexArraysCore};
-use crate::gpu_data::{ColorCombineMode, RenderCommand, TextureLocation, TextureMetadataEntry};
-use crate::gpu_data::{TexturePageDescriptor, TexturePageId, TileBatchTexture};
+use crate::gpu::shaders::{BlitLinearProgram, BlitProgram, BlitVertexArray, ClearProgram};
+use crate::gpu::shaders::{ClearVertexArray, ProgramsCore, ReprojectionProgram};
+use crate::gpu::shaders::{ReprojectionVertexArray, StencilProgram, StencilVertexArray};
+use crate::gpu::shaders::{TileProgramCommon, VertexArraysCore};
+use crate::gpu_data::{ColorCombineMode, DirtyTilesD3D9, RenderCommand, TextureLocation};
+use crate::gpu_data::{TextureMetadataEntry, TexturePageDescriptor, TexturePageId};
+use crate::gpu_data::TileBatchTexture;
 use crate::options::BoundingQuad;
 use crate::tiles::{TILE_HEIGHT, TILE_WIDTH};
 use half::f16;
+use pathfinder_color::space::ColorSpace;
 use pathfinder_color::{self as color, ColorF, ColorU};
 use pathfinder_content::effects::{BlendMode, BlurDirection, Filter, PatternFilter};
 use pathfinder_content::render_target::RenderTargetId;
@@ -46,8 +50,10 @@ use std::u32;
 static QUAD_VERTEX_POSITIONS: [u16; 8] = [0, 0, 1, 0, 1, 1, 0, 1];
 static QUAD_VERTEX_INDICES: [u32; 6] = [0, 1, 3, 1, 2, 3];
 
-pub(crate) const MASK_TILES_ACROSS: u32 = 256;
-pub(crate) const MASK_TILES_DOWN: u32 = 256;
+const MASK_TILES_ACROSS: u32 = 256;
+// The widest that a mask framebuffer gets. Masks of wide tiles have fewer tiles across, so that
+// they stay within the texture size limits of OpenGL 3 and WebGL 2 devices.
+const MAX_MASK_FRAMEBUFFER_WIDTH: i32 = 4096;
 
 // 1.0 / sqrt(2*pi)
 const SQRT_2_PI_INV: f32 = 0.3989422804014327;
@@ -56,14 +62,11 @@ const TEXTURE_METADATA_ENTRIES_PER_ROW: i32 = 128;
 const TEXTURE_METADATA_TEXTURE_WIDTH:   i32 = TEXTURE_METADATA_ENTRIES_PER_ROW * 10;
 const TEXTURE_METADATA_TEXTURE_HEIGHT:  i32 = 65536 / TEXTURE_METADATA_ENTRIES_PER_ROW;
 
-// FIXME(pcwalton): Shrink this again!
-pub(crate) const MASK_FRAMEBUFFER_WIDTH:  i32 = TILE_WIDTH as i32      * MASK_TILES_ACROSS as i32;
-pub(crate) const MASK_FRAMEBUFFER_HEIGHT: i32 = TILE_HEIGHT as i32 / 4 * MASK_TILES_DOWN as i32;
-
 const COMBINER_CTRL_FILTER_RADIAL_GRADIENT: i32 =   0x1;
 const COMBINER_CTRL_FILTER_TEXT: i32 =              0x2;
 const COMBINER_CTRL_FILTER_BLUR: i32 =              0x3;
 const COMBINER_CTRL_FILTER_COLOR_MATRIX: i32 =      0x4;
+const COMBINER_CTRL_FILTER_CONIC_GRADIENT: i32 =    0x5;
 
 const COMBINER_CTRL_COLOR_FILTER_SHIFT: i32 =       4;
 const COMBINER_CTRL_COLOR_COMBINE_SHIFT: i32 =      8;
@@ -77,6 +80,7 @@ pub struct Renderer<D> where D: Device {
 
     // Shaders
     blit_program: BlitProgram<D>,
+    blit_linear_program: BlitLinearProgram<D>,
     clear_program: ClearProgram<D>,
     stencil_program: StencilProgram<D>,
     reprojection_program: ReprojectionProgram<D>,
@@ -123,6 +127,7 @@ pub(crate) struct RendererCore<D> where D: Device {
     // Read-write static core resources
     intermediate_dest_framebuffer_id: FramebufferID,
     intermediate_dest_framebuffer_size: Vector2I,
+    intermediate_dest_framebuffer_format: TextureFormat,
     pub(crate) texture_metadata_texture_id: TextureID,
 
     // Dynamic resources and associated metadata
@@ -131,12 +136,15 @@ pub(crate) struct RendererCore<D> where D: Device {
     pub(crate) pattern_texture_pages: Vec<Option<PatternTexturePage>>,
     pub(crate) mask_storage: Option<MaskStorage>,
     pub(crate) alpha_tile_count: u32,
+    // The size of a tile in device pixels, as chosen by the scene builder.
+    pub(crate) tile_pixel_size: Vector2I,
     pub(crate) framebuffer_flags: FramebufferFlags,
 }
 
 // TODO(pcwalton): Remove this.
 struct Frame<D> where D: Device {
     blit_vertex_array: BlitVertexArray<D>,
+    blit_linear_vertex_array: BlitVertexArray<D>,
     clear_vertex_array: ClearVertexArray<D>,
     stencil_vertex_array: StencilVertexArray<D>,
     reprojection_vertex_array: ReprojectionVertexArray<D>,
@@ -145,6 +153,7 @@ struct Frame<D> where D: Device {
 pub(crate) struct MaskStorage {
     pub(crate) framebuffer_id: FramebufferID,
     pub(crate) allocated_page_count: u32,
+    pub(crate) tile_pixel_size: Vector2I,
 }
 
 impl<D> Renderer<D> where D: Device {
@@ -233,7 +242,7 @@ impl<D> Renderer<D> where D: Device {
         let mut core = RendererCore {
             device,
             allocator,
-            mode,
+            mode: mode.prepare(),
             options,
             stats: RenderStats::default(),
             current_timer: None,
@@ -250,6 +259,7 @@ impl<D> Renderer<D> where D: Device {
 
             intermediate_dest_framebuffer_id,
             intermediate_dest_framebuffer_size: window_size,
+            intermediate_dest_framebuffer_format: TextureFormat::RGBA8,
 
             texture_metadata_texture_id,
             render_targets: vec![],
@@ -257,6 +267,7 @@ impl<D> Renderer<D> where D: Device {
             pattern_texture_pages: vec![],
             mask_storage: None,
             alpha_tile_count: 0,
+            tile_pixel_size: vec2i(TILE_WIDTH as i32, TILE_HEIGHT as i32),
             framebuffer_flags: FramebufferFlags::empty(),
         };
 
@@ -270,6 +281,7 @@ impl<D> Renderer<D> where D: Device {
         };
 
         let blit_program = BlitProgram::new(&core.device, resources);
+        let blit_linear_program = BlitLinearProgram::new(&core.device, resources);
         let clear_program = ClearProgram::new(&core.device, resources);
         let stencil_program = StencilProgram::new(&core.device, resources);
         let reprojection_program = ReprojectionProgram::new(&core.device, resources);
@@ -283,6 +295,7 @@ impl<D> Renderer<D> where D: Device {
         let frame = Frame::new(&core.device,
                                &mut core.allocator,
                                &blit_program,
+                               &blit_linear_program.blit,
                                &clear_program,
                                &reprojection_program,
                                &stencil_program,
@@ -296,6 +309,7 @@ impl<D> Renderer<D> where D: Device {
             level_impl,
 
             blit_program,
+            blit_linear_program,
             clear_program,
 
             frame,
@@ -337,12 +351,19 @@ impl<D> Renderer<D> where D: Device {
     pub fn render_command(&mut self, command: &RenderCommand) {
         debug!("render command: {:?}", command);
         match *command {
-            RenderCommand::Start { bounding_quad, path_count, needs_readable_framebuffer } => {
+            RenderCommand::Start {
+                bounding_quad,
+                path_count,
+                needs_readable_framebuffer,
+                tile_size,
+            } => {
+                self.core.tile_pixel_size = tile_size;
                 self.start_rendering(bounding_quad, path_count, needs_readable_framebuffer);
             }
             RenderCommand::AllocateTexturePage { page_id, ref descriptor } => {
                 self.allocate_pattern_texture_page(page_id, descriptor)
             }
+            RenderCommand::FreeTexturePage(page_id) => self.free_pattern_texture_page(page_id),
             RenderCommand::UploadTexelData { ref texels, location } => {
                 self.upload_texel_data(texels, location)
             }
@@ -358,6 +379,9 @@ impl<D> Renderer<D> where D: Device {
             RenderCommand::FlushFillsD3D9 => {
                 self.level_impl.require_d3d9().draw_buffered_fills(&mut self.core);
             }
+            RenderCommand::InvalidateTilesD3D9(ref dirty_tiles) => {
+                self.invalidate_tiles(dirty_tiles)
+            }
             RenderCommand::UploadSceneD3D11 { ref draw_segments, ref clip_segments } => {
                 self.level_impl
                     .require_d3d11()
@@ -376,8 +400,18 @@ impl<D> Renderer<D> where D: Device {
             RenderCommand::DrawTilesD3D11(ref batch) => {
                 self.level_impl.require_d3d11().prepare_and_draw_tiles(&mut self.core, batch)
             }
-            RenderCommand::Finish { cpu_build_time } => {
+            RenderCommand::Finish {
+                cpu_build_time,
+                build_phase_times,
+                culled_path_count,
+                culled_contour_count,
+                culled_tile_count,
+            } => {
                 self.core.stats.cpu_build_time = cpu_build_time;
+                self.core.stats.cpu_build_phase_times = build_phase_times;
+                self.core.stats.culled_path_count = culled_path_count;
+                self.core.stats.culled_contour_count = culled_contour_count;
+                self.core.stats.culled_tile_count = culled_tile_count;
             }
         }
     }
@@ -395,6 +429,8 @@ impl<D> Renderer<D> where D: Device {
 
         self.core.stats.gpu_bytes_allocated = self.core.allocator.bytes_allocated();
         self.core.stats.gpu_bytes_committed = self.core.allocator.bytes_committed();
+        self.core.stats.texture_page_count = self.core.texture_page_count();
+        self.core.stats.texture_page_bytes = self.core.texture_page_bytes();
 
         match self.level_impl {
             RendererLevelImpl::D3D9(_) => {}
@@ -441,6 +477,13 @@ impl<D> Renderer<D> where D: Device {
             }
         }
 
+        // Blending in linear light at the D3D9 level and tone mapping both need a floating-point
+        // framebuffer to render into, which we then encode to the destination.
+        if self.core.presents_from_linear_light() {
+            self.core.renderer_flags.insert(RendererFlags::INTERMEDIATE_DEST_FRAMEBUFFER_NEEDED);
+        }
+        self.core.reallocate_intermediate_dest_framebuffer_if_necessary();
+
         if self.core.renderer_flags.contains(RendererFlags::USE_DEPTH) {
             self.draw_stencil(&bounding_quad);
         }
@@ -612,12 +655,11 @@ impl<D> Renderer<D> where D: Device {
         }
 
         // Allocate texture.
-        let texture_size = descriptor.size;
         let framebuffer_id = self.core
                                  .allocator
                                  .allocate_framebuffer(&self.core.device,
-                                                       texture_size,
-                                                       TextureFormat::RGBA8,
+                                                       descriptor.size,
+                                                       descriptor.format,
                                                        FramebufferTag("PatternPage"));
         self.core.pattern_texture_pages[page_index] = Some(PatternTexturePage {
             framebuffer_id,
@@ -625,6 +667,15 @@ impl<D> Renderer<D> where D: Device {
         });
     }
 
+    fn free_pattern_texture_page(&mut self, page_id: TexturePageId) {
+        let texture_page = self.core
+                               .pattern_texture_pages
+                               .get_mut(page_id.0 as usize)
+                               .and_then(|texture_page| texture_page.take())
+                               .expect("Texture page not allocated!");
+        self.core.allocator.free_framebuffer(texture_page.framebuffer_id);
+    }
+
     fn upload_texel_data(&mut self, texels: &[ColorU], location: TextureLocation) {
         let texture_page = self.core
                                .pattern_texture_pages[location.page.0 as usize]
@@ -658,7 +709,7 @@ impl<D> Renderer<D> where D: Device {
              TEXTURE_METADATA_TEXTURE_WIDTH * 4) as usize;
         let mut texels = Vec::with_capacity(padded_texel_size);
         for entry in metadata {
-            let base_color = entry.base_color.to_f32();
+            let base_color = entry.base_color;
             let filter_params = self.compute_filter_params(&entry.filter,
                                                            entry.blend_mode,
                                                            entry.color_0_combine_mode);
@@ -810,12 +861,98 @@ impl<D> Renderer<D> where D: Device {
 
     fn push_render_target(&mut self, render_target_id: RenderTargetId) {
         self.core.render_target_stack.push(render_target_id);
+        self.clear_render_target(render_target_id);
+    }
+
+    // Render targets share texture pages, so we clear each one, along with the texel of space
+    // around it, when we start drawing to it instead of clearing the whole page.
+    fn clear_render_target(&mut self, render_target_id: RenderTargetId) {
+        let viewport = self.core.draw_viewport();
+        let page_id = self.core.render_target_location(render_target_id).page;
+        let page_size = self.core.device.texture_size(self.core.texture_page(page_id));
+        let clear_rect = RectI::new(viewport.origin() - vec2i(1, 1), viewport.size() + vec2i(2, 2))
+            .intersection(RectI::new(Vector2I::default(), page_size))
+            .unwrap_or(viewport);
+
+        let uniforms = [
+            (&self.clear_program.rect_uniform,
+             UniformData::Vec4(RectI::new(Vector2I::default(), clear_rect.size()).to_f32().0)),
+            (&self.clear_program.framebuffer_size_uniform,
+             UniformData::Vec2(clear_rect.size().to_f32().0)),
+            (&self.clear_program.color_uniform,
+             UniformData::Vec4(ColorF::transparent_black().0)),
+        ];
+
+        self.core.device.draw_elements(6, &RenderState {
+            target: &self.core.draw_render_target(),
+            program: &self.clear_program.program,
+            vertex_array: &self.frame.clear_vertex_array.vertex_array,
+            primitive: Primitive::Triangles,
+            textures: &[],
+            images: &[],
+            storage_buffers: &[],
+            uniforms: &uniforms[..],
+            viewport: clear_rect,
+            options: RenderOptions::default(),
+        });
+
+        self.core.stats.drawcall_count += 1;
+
+        self.core.preserve_draw_framebuffer();
     }
 
     fn pop_render_target(&mut self) {
         self.core.render_target_stack.pop().expect("Render target stack underflow!");
     }
 
+    // Prepares to draw only the dirty tiles on top of the previous frame.
+    //
+    // The intermediate framebuffer persists from frame to frame, so we always render into it when
+    // the tile cache is in use, and then clear the dirty tiles so they can be drawn from scratch.
+    fn invalidate_tiles(&mut self, dirty_tiles: &DirtyTilesD3D9) {
+        self.core.renderer_flags.insert(RendererFlags::INTERMEDIATE_DEST_FRAMEBUFFER_NEEDED);
+        if self.core.reallocate_intermediate_dest_framebuffer_if_necessary() &&
+                dirty_tiles.reused_tile_count > 0 {
+            warn!("Reusing tiles from a framebuffer that was just reallocated! Call \
+                   `SceneSink::invalidate_tile_cache()` when the destination changes.");
+        }
+
+        self.core.stats.dirty_tile_count = dirty_tiles.dirty_tile_count as usize;
+        self.core.stats.reused_tile_count = dirty_tiles.reused_tile_count as usize;
+
+        let clear_color = self.core.clear_color_for_draw_operation().unwrap_or_default();
+        let main_viewport = self.core.main_viewport();
+        let tile_size = self.core.tile_pixel_size;
+
+        for dirty_rect in &dirty_tiles.rects {
+            let rect = RectI::from_points(dirty_rect.origin() * tile_size,
+                                          dirty_rect.lower_right() * tile_size);
+            let uniforms = [
+                (&self.clear_program.rect_uniform, UniformData::Vec4(rect.to_f32().0)),
+                (&self.clear_program.framebuffer_size_uniform,
+                 UniformData::Vec2(main_viewport.size().to_f32().0)),
+                (&self.clear_program.color_uniform, UniformData::Vec4(clear_color.0)),
+            ];
+
+            self.core.device.draw_elements(6, &RenderState {
+                target: &self.core.draw_render_target(),
+                program: &self.clear_program.program,
+                vertex_array: &self.frame.clear_vertex_array.vertex_array,
+                primitive: Primitive::Triangles,
+                textures: &[],
+                images: &[],
+                storage_buffers: &[],
+                uniforms: &uniforms[..],
+                viewport: main_viewport,
+                options: RenderOptions::default(),
+            });
+
+            self.core.stats.drawcall_count += 1;
+        }
+
+        self.core.preserve_draw_framebuffer();
+    }
+
     fn clear_dest_framebuffer_if_necessary(&mut self) {
         let background_color = match self.core.options.background_color {
             None => return,
@@ -859,38 +996,43 @@ impl<D> Renderer<D> where D: Device {
 
         let main_viewport = self.core.main_viewport();
 
-        if self.core.intermediate_dest_framebuffer_size != main_viewport.size() {
-            self.core.allocator.free_framebuffer(self.core.intermediate_dest_framebuffer_id);
-            self.core.intermediate_dest_framebuffer_id =
-                self.core.allocator.allocate_framebuffer(&self.core.device,
-                                                         main_viewport.size(),
-                                                         TextureFormat::RGBA8,
-                                                         FramebufferTag("IntermediateDest"));
-            self.core.intermediate_dest_framebuffer_size = main_viewport.size();
-        }
-
         let intermediate_dest_framebuffer =
             self.core.allocator.get_framebuffer(self.core.intermediate_dest_framebuffer_id);
 
+        // If the intermediate framebuffer is in linear light, tone map and encode it as we copy.
+        let mut uniforms = vec![];
+        let (blit_program, blit_vertex_array) = if self.core.presents_from_linear_light() {
+            uniforms.push((&self.blit_linear_program.tone_mapping_uniform,
+                           UniformData::Int(tone_mapping_to_int(self.core.options.tone_mapping))));
+            (&self.blit_linear_program.blit, &self.frame.blit_linear_vertex_array)
+        } else {
+            (&self.blit_program, &self.frame.blit_vertex_array)
+        };
+        uniforms.push((&blit_program.framebuffer_size_uniform,
+                       UniformData::Vec2(main_viewport.size().to_f32().0)));
+        uniforms.push((&blit_program.dest_rect_uniform,
+                       UniformData::Vec4(RectF::new(Vector2F::zero(),
+                                                    main_viewport.size().to_f32()).0)));
+
         let textures = [
-            (&self.blit_program.src_texture,
+            (&blit_program.src_texture,
              self.core.device.framebuffer_texture(intermediate_dest_framebuffer))
         ];
 
+        let target = match self.core.options.dest {
+            DestFramebuffer::Default { .. } => RenderTarget::Default,
+            DestFramebuffer::Other(ref framebuffer) => RenderTarget::Framebuffer(framebuffer),
+        };
+
         self.core.device.draw_elements(6, &RenderState {
-            target: &RenderTarget::Default,
-            program: &self.blit_program.program,
-            vertex_array: &self.frame.blit_vertex_array.vertex_array,
+            target: &target,
+            program: &blit_program.program,
+            vertex_array: &blit_vertex_array.vertex_array,
             primitive: Primitive::Triangles,
             textures: &textures[..],
             images: &[],
             storage_buffers: &[],
-            uniforms: &[
-                (&self.blit_program.framebuffer_size_uniform,
-                 UniformData::Vec2(main_viewport.size().to_f32().0)),
-                (&self.blit_program.dest_rect_uniform,
-                 UniformData::Vec4(RectF::new(Vector2F::zero(), main_viewport.size().to_f32()).0)),
-            ],
+            uniforms: &uniforms,
             viewport: main_viewport,
             options: RenderOptions {
                 clear_ops: ClearOps {
@@ -938,6 +1080,17 @@ impl<D> Renderer<D> where D: Device {
                                   COMBINER_CTRL_COLOR_FILTER_SHIFT)
                 }
             }
+            Filter::ConicGradient { center, angle, uv_origin } => {
+                FilterParams {
+                    p0: center.0.concat_xy_xy(F32x2::new(angle, 0.0)),
+                    p1: F32x2::default().concat_xy_xy(uv_origin.0),
+                    p2: F32x4::default(),
+                    p3: F32x4::default(),
+                    p4: F32x4::default(),
+                    ctrl: ctrl | (COMBINER_CTRL_FILTER_CONIC_GRADIENT <<
+                                  COMBINER_CTRL_COLOR_FILTER_SHIFT)
+                }
+            }
             Filter::PatternFilter(PatternFilter::Blur { sigma, direction }) => {
                 let sigma_inv = 1.0 / sigma;
                 let gauss_coeff_x = SQRT_2_PI_INV * sigma_inv;
@@ -1010,16 +1163,34 @@ impl<D> RendererCore<D> where D: Device {
         }
     }
 
+    // Returns the size of a mask framebuffer that holds the given number of alpha tile pages.
+    //
+    // Alpha tiles are laid out in rows, in order. The shaders find the number of tiles in each row
+    // from the width of the framebuffer.
+    pub(crate) fn mask_framebuffer_size(&self, page_count: u32) -> Vector2I {
+        let tiles_across = (MAX_MASK_FRAMEBUFFER_WIDTH / self.tile_pixel_size.x())
+            .min(MASK_TILES_ACROSS as i32);
+        let tile_count = page_count * self.mode.alpha_tiles_per_page;
+        let tile_rows = tile_count.div_ceil(tiles_across as u32);
+        vec2i(self.tile_pixel_size.x() * tiles_across,
+              self.tile_pixel_size.y() / 4 * tile_rows as i32)
+    }
+
     pub(crate) fn reallocate_alpha_tile_pages_if_necessary(&mut self, copy_existing: bool) {
-        let alpha_tile_pages_needed = ((self.alpha_tile_count + 0xffff) >> 16) as u32;
-        if let Some(ref mask_storage) = self.mask_storage {
-            if alpha_tile_pages_needed <= mask_storage.allocated_page_count {
-                return;
+        let alpha_tile_pages_needed =
+            self.alpha_tile_count.div_ceil(self.mode.alpha_tiles_per_page);
+        let copy_existing = match self.mask_storage {
+            Some(ref mask_storage) if mask_storage.tile_pixel_size == self.tile_pixel_size => {
+                if alpha_tile_pages_needed <= mask_storage.allocated_page_count {
+                    return;
+                }
+                copy_existing
             }
-        }
+            // The tiles in the old mask are a different size, so its contents are useless.
+            Some(_) | None => false,
+        };
 
-        let new_size = vec2i(MASK_FRAMEBUFFER_WIDTH,
-                             MASK_FRAMEBUFFER_HEIGHT * alpha_tile_pages_needed as i32);
+        let new_size = self.mask_framebuffer_size(alpha_tile_pages_needed);
         let format = self.mask_texture_format();
         let mask_framebuffer_id =
             self.allocator.allocate_framebuffer(&self.device,
@@ -1031,12 +1202,17 @@ impl<D> RendererCore<D> where D: Device {
         self.mask_storage = Some(MaskStorage {
             framebuffer_id: mask_framebuffer_id,
             allocated_page_count: alpha_tile_pages_needed,
+            tile_pixel_size: self.tile_pixel_size,
         });
 
         // Copy over existing content if needed.
         let old_mask_framebuffer_id = match old_mask_storage {
             Some(old_storage) if copy_existing => old_storage.framebuffer_id,
-            Some(_) | None => return,
+            Some(old_storage) => {
+                self.allocator.free_framebuffer(old_storage.framebuffer_id);
+                return;
+            }
+            None => return,
         };
         let old_mask_framebuffer = self.allocator.get_framebuffer(old_mask_framebuffer_id);
         let old_mask_texture = self.device.framebuffer_texture(old_mask_framebuffer);
@@ -1072,6 +1248,8 @@ impl<D> RendererCore<D> where D: Device {
         self.stats.drawcall_count += 1;
         self.finish_timing_draw_call(&timer_query);
         self.current_timer.as_mut().unwrap().push_query(TimeCategory::Other, timer_query);
+
+        self.allocator.free_framebuffer(old_mask_framebuffer_id);
     }
 
     pub(crate) fn set_uniforms_for_drawing_tiles<'a>(
@@ -1091,12 +1269,14 @@ impl<D> RendererCore<D> where D: Device {
         textures.push((&tile_program.texture_metadata_texture, texture_metadata_texture));
 
         uniforms.push((&tile_program.tile_size_uniform,
-                       UniformData::Vec2(F32x2::new(TILE_WIDTH as f32, TILE_HEIGHT as f32))));
+                       UniformData::Vec2(self.tile_pixel_size.to_f32().0)));
         uniforms.push((&tile_program.framebuffer_size_uniform,
                        UniformData::Vec2(draw_viewport.size().to_f32().0)));
         uniforms.push((&tile_program.texture_metadata_size_uniform,
                        UniformData::IVec2(I32x2::new(TEXTURE_METADATA_TEXTURE_WIDTH,
                                                      TEXTURE_METADATA_TEXTURE_HEIGHT))));
+        uniforms.push((&tile_program.linear_blending_uniform,
+                       UniformData::Int(self.linear_blending_for_draw_operation() as i32)));
 
         if let Some(ref mask_storage) = self.mask_storage {
             let mask_framebuffer_id = mask_storage.framebuffer_id;
@@ -1140,6 +1320,22 @@ impl<D> RendererCore<D> where D: Device {
         self.allocator.get_framebuffer(framebuffer_id)
     }
 
+    fn texture_page_count(&self) -> usize {
+        self.pattern_texture_pages.iter().filter(|texture_page| texture_page.is_some()).count()
+    }
+
+    fn texture_page_bytes(&self) -> u64 {
+        let mut bytes = 0;
+        for texture_page in self.pattern_texture_pages.iter().flatten() {
+            let framebuffer = self.allocator.get_framebuffer(texture_page.framebuffer_id);
+            let texture = self.device.framebuffer_texture(framebuffer);
+            let size = self.device.texture_size(texture);
+            let bytes_per_pixel = self.device.texture_format(texture).bytes_per_pixel();
+            bytes += size.x() as u64 * size.y() as u64 * bytes_per_pixel as u64;
+        }
+        bytes
+    }
+
     pub(crate) fn clear_color_for_draw_operation(&self) -> Option<ColorF> {
         let must_preserve_contents = match self.render_target_stack.last() {
             Some(&render_target_id) => {
@@ -1157,22 +1353,89 @@ impl<D> RendererCore<D> where D: Device {
         if must_preserve_contents {
             None
         } else if self.render_target_stack.is_empty() {
-            self.options.background_color
+            // The framebuffer holds colors in the space we blend in.
+            self.options.background_color.map(|background_color| {
+                if self.linear_blending_for_draw_operation() {
+                    ColorSpace::Srgb.to_linear_srgb(background_color)
+                } else {
+                    background_color
+                }
+            })
         } else {
             Some(ColorF::default())
         }
     }
 
+    // Returns true if the current draw operation blends in linear light.
+    //
+    // Render targets are always blended in gamma space. Tone mapping implies linear blending.
+    pub(crate) fn linear_blending_for_draw_operation(&self) -> bool {
+        (self.options.linear_blending || self.options.tone_mapping != ToneMapping::None) &&
+            self.render_target_stack.is_empty()
+    }
+
+    // Returns true if the destination framebuffer stores floating-point colors, which we render
+    // into directly without encoding or tone mapping.
+    fn dest_framebuffer_is_float(&self) -> bool {
+        match self.options.dest {
+            DestFramebuffer::Default { .. } => false,
+            DestFramebuffer::Other(ref framebuffer) => {
+                let texture = self.device.framebuffer_texture(framebuffer);
+                self.device.texture_format(texture).is_float()
+            }
+        }
+    }
+
+    // Returns true if we render into a floating-point intermediate framebuffer in linear light
+    // and encode it (tone mapping as necessary) when copying it to the destination.
+    fn presents_from_linear_light(&self) -> bool {
+        if self.dest_framebuffer_is_float() {
+            return false;
+        }
+        self.options.tone_mapping != ToneMapping::None ||
+            (self.mode.level == RendererLevel::D3D9 && self.options.linear_blending)
+    }
+
+    fn intermediate_dest_framebuffer_format(&self) -> TextureFormat {
+        if self.presents_from_linear_light() || self.dest_framebuffer_is_float() {
+            TextureFormat::RGBA16F
+        } else {
+            TextureFormat::RGBA8
+        }
+    }
+
+    // Returns true if the framebuffer was reallocated, losing its previous contents.
+    fn reallocate_intermediate_dest_framebuffer_if_necessary(&mut self) -> bool {
+        if !self.renderer_flags.contains(RendererFlags::INTERMEDIATE_DEST_FRAMEBUFFER_NEEDED) {
+            return false;
+        }
+
+        let size = self.main_viewport().size();
+        let format = self.intermediate_dest_framebuffer_format();
+        if self.intermediate_dest_framebuffer_size == size &&
+                self.intermediate_dest_framebuffer_format == format {
+            return false;
+        }
+
+        self.allocator.free_framebuffer(self.intermediate_dest_framebuffer_id);
+        self.intermediate_dest_framebuffer_id =
+            self.allocator.allocate_framebuffer(&self.device,
+                                                size,
+                                                format,
+                                                FramebufferTag("IntermediateDest"));
+        self.intermediate_dest_framebuffer_size = size;
+        self.intermediate_dest_framebuffer_format = format;
+        true
+    }
+
     // Sizing
 
     pub(crate) fn tile_size(&self) -> Vector2I {
-        let temp = self.draw_viewport().size() +
-            vec2i(TILE_WIDTH as i32 - 1, TILE_HEIGHT as i32 - 1);
-        vec2i(temp.x() / TILE_WIDTH as i32, temp.y() / TILE_HEIGHT as i32)
+        pixel_size_to_tile_size(self.draw_viewport().size(), self.tile_pixel_size)
     }
 
     pub(crate) fn framebuffer_tile_size(&self) -> Vector2I {
-        pixel_size_to_tile_size(self.options.dest.window_size(&self.device))
+        pixel_size_to_tile_size(self.options.dest.window_size(&self.device), self.tile_pixel_size)
     }
 
     // Viewport calculation
@@ -1251,6 +1514,7 @@ impl<D> Frame<D> where D: Device {
     fn new(device: &D,
            allocator: &mut GPUMemoryAllocator<D>,
            blit_program: &BlitProgram<D>,
+           blit_linear_program: &BlitProgram<D>,
            clear_program: &ClearProgram<D>,
            reprojection_program: &ReprojectionProgram<D>,
            stencil_program: &StencilProgram<D>,
@@ -1266,6 +1530,10 @@ impl<D> Frame<D> where D: Device {
                                                      &blit_program,
                                                      &quad_vertex_positions_buffer,
                                                      &quad_vertex_indices_buffer);
+        let blit_linear_vertex_array = BlitVertexArray::new(device,
+                                                            &blit_linear_program,
+                                                            &quad_vertex_positions_buffer,
+                                                            &quad_vertex_indices_buffer);
         let clear_vertex_array = ClearVertexArray::new(device,
                                                        &clear_program,
                                                        &quad_vertex_positions_buffer,
@@ -1278,6 +1546,7 @@ impl<D> Frame<D> where D: Device {
 
         Frame {
             blit_vertex_array,
+            blit_linear_vertex_array,
             clear_vertex_array,
             reprojection_vertex_array,
             stencil_vertex_array,
@@ -1332,11 +1601,19 @@ bitflags! {
     }
 }
 
-fn pixel_size_to_tile_size(pixel_size: Vector2I) -> Vector2I {
+// Must match the `TONE_MAPPING_*` constants in `blit_linear.fs.glsl`.
+fn tone_mapping_to_int(tone_mapping: ToneMapping) -> i32 {
+    match tone_mapping {
+        ToneMapping::None => 0,
+        ToneMapping::Reinhard => 1,
+        ToneMapping::Aces => 2,
+    }
+}
+
+fn pixel_size_to_tile_size(pixel_size: Vector2I, tile_pixel_size: Vector2I) -> Vector2I {
     // Round up.
-    let tile_size = vec2i(TILE_WIDTH as i32 - 1, TILE_HEIGHT as i32 - 1);
-    let size = pixel_size + tile_size;
-    vec2i(size.x() / TILE_WIDTH as i32, size.y() / TILE_HEIGHT as i32)
+    let size = pixel_size + tile_pixel_size - Vector2I::splat(1);
+    vec2i(size.x() / tile_pixel_size.x(), size.y() / tile_pixel_size.y())
 }
 
 struct FilterParams {
@@ -1364,3 +1641,71 @@ pub struct DebugUIPresenterInfo<'a, D> where D: Device {
     /// The debug UI presenter, useful for drawing custom debug widgets on screen.
     pub debug_ui_presenter: &'a mut DebugUIPresenter<D>,
 }
+
+#[cfg(test)]
+mod test {
+    use crate::concurrent::executor::SequentialExecutor;
+    use crate::gpu::options::{DestFramebuffer, RendererLevel, RendererMode, RendererOptions};
+    use crate::options::BuildOptions;
+    use crate::paint::Paint;
+    use crate::scene::{DrawPath, Scene};
+    use pathfinder_color::ColorU;
+    use pathfinder_content::outline::Outline;
+    use pathfinder_geometry::rect::{RectF, RectI};
+    use pathfinder_geometry::vector::{vec2f, vec2i};
+    use pathfinder_gpu::FeatureLevel;
+    use pathfinder_gpu::recording::{RecordedCommand, RecordedRenderTarget, RecordingDevice};
+    use pathfinder_resources::embedded::EmbeddedResourceLoader;
+    use super::Renderer;
+
+    fn render_rect(feature_level: FeatureLevel, level: RendererLevel) -> RecordingDevice {
+        let window_size = vec2i(64, 64);
+        let device = RecordingDevice::new(feature_level);
+        let options = RendererOptions {
+            dest: DestFramebuffer::full_window(window_size),
+            ..RendererOptions::default()
+        };
+        let mut renderer = Renderer::new(device,
+                                         &EmbeddedResourceLoader::new(),
+                                         RendererMode::new(level),
+                                         options);
+
+        let mut scene = Scene::new();
+        scene.set_view_box(RectF::new(vec2f(0.0, 0.0), window_size.to_f32()));
+        let paint = scene.push_paint(&Paint::from_color(ColorU::black()));
+        let outline = Outline::from_rect(RectF::new(vec2f(8.5, 8.5), vec2f(32.0, 16.0)));
+        scene.push_draw_path(DrawPath::new(outline, paint));
+        scene.build_and_render(&mut renderer, BuildOptions::default(), SequentialExecutor);
+
+        renderer.destroy()
+    }
+
+    #[test]
+    fn test_d3d9_draws_to_default_framebuffer() {
+        let device = render_rect(FeatureLevel::D3D10, RendererLevel::D3D9);
+        let draws = device.draws();
+        assert!(!draws.is_empty());
+        assert!(draws.iter().any(|draw| {
+            draw.target == RecordedRenderTarget::Default &&
+                draw.viewport == RectI::new(vec2i(0, 0), vec2i(64, 64))
+        }));
+
+        let commands = device.commands();
+        let begin_count = commands.iter().filter(|command| {
+            matches!(command, RecordedCommand::BeginCommands)
+        }).count();
+        let end_count = commands.iter().filter(|command| {
+            matches!(command, RecordedCommand::EndCommands)
+        }).count();
+        assert_eq!(begin_count, end_count);
+    }
+
+    #[test]
+    fn test_d3d11_dispatches_compute() {
+        let device = render_rect(FeatureLevel::D3D11, RendererLevel::D3D11);
+        assert!(device.commands().iter().any(|command| {
+            matches!(command, RecordedCommand::DispatchCompute(_))
+        }));
+        assert!(!device.draws().is_empty());
+    }
+}
@@ -8,7 +8,7 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use pathfinder_gpu::{BufferTarget, BufferUploadMode, Device, VertexAttrClass};
+use pathfinder_gpu::{BufferTarget, BufferUploadMode, Device, ProgramKind, VertexAttrClass};
 use pathfinder_gpu::{VertexAttrDescriptor, VertexAttrType};
 use pathfinder_resources::ResourceLoader;
 
@@ -102,6 +102,10 @@ pub(crate) struct BlitProgram<D> where D: Device {
 impl<D> BlitProgram<D> where D: Device {
     pub(crate) fn new(device: &D, resources: &dyn ResourceLoader) -> BlitProgram<D> {
         let program = device.create_raster_program(resources, "blit");
+        BlitProgram::from_program(device, program)
+    }
+
+    fn from_program(device: &D, program: D::Program) -> BlitProgram<D> {
         let dest_rect_uniform = device.get_uniform(&program, "DestRect");
         let framebuffer_size_uniform = device.get_uniform(&program, "FramebufferSize");
         let src_texture = device.get_texture_parameter(&program, "Src");
@@ -109,6 +113,26 @@ impl<D> BlitProgram<D> where D: Device {
     }
 }
 
+// A blit program that converts premultiplied colors in linear light to sRGB, optionally tone mapping
+// them first.
+pub(crate) struct BlitLinearProgram<D> where D: Device {
+    pub(crate) blit: BlitProgram<D>,
+    pub(crate) tone_mapping_uniform: D::Uniform,
+}
+
+impl<D> BlitLinearProgram<D> where D: Device {
+    pub(crate) fn new(device: &D, resources: &dyn ResourceLoader) -> BlitLinearProgram<D> {
+        let program = device.create_program_from_shader_names(resources,
+                                                              "blit_linear",
+                                                              ProgramKind::Raster {
+                                                                  vertex: "blit",
+                                                                  fragment: "blit_linear",
+                                                              });
+        let tone_mapping_uniform = device.get_uniform(&program, "ToneMapping");
+        BlitLinearProgram { blit: BlitProgram::from_program(device, program), tone_mapping_uniform }
+    }
+}
+
 pub(crate) struct ProgramsCore<D> where D: Device {
     pub(crate) blit_program: BlitProgram<D>,
 }
@@ -151,6 +175,7 @@ pub(crate) struct TileProgramCommon<D> where D: Device {
     pub(crate) mask_texture_size_0_uniform: D::Uniform,
     pub(crate) gamma_lut_texture: D::TextureParameter,
     pub(crate) framebuffer_size_uniform: D::Uniform,
+    pub(crate) linear_blending_uniform: D::Uniform,
 }
 
 impl<D> TileProgramCommon<D> where D: Device {
@@ -166,6 +191,7 @@ impl<D> TileProgramCommon<D> where D: Device {
         let mask_texture_size_0_uniform = device.get_uniform(&program, "MaskTextureSize0");
         let gamma_lut_texture = device.get_texture_parameter(&program, "GammaLUT");
         let framebuffer_size_uniform = device.get_uniform(&program, "FramebufferSize");
+        let linear_blending_uniform = device.get_uniform(&program, "LinearBlending");
 
         TileProgramCommon {
             program,
@@ -180,6 +206,7 @@ impl<D> TileProgramCommon<D> where D: Device {
             mask_texture_size_0_uniform,
             gamma_lut_texture,
             framebuffer_size_uniform,
+            linear_blending_uniform,
         }
     }
 }
@@ -11,18 +11,19 @@
 //! Packed data ready to be sent to the GPU.
 
 use crate::builder::{ALPHA_TILES_PER_LEVEL, ALPHA_TILE_LEVEL_COUNT};
+use crate::gpu::perf::BuildPhaseTimes;
 use crate::options::BoundingQuad;
 use crate::paint::PaintCompositeOp;
 use crate::scene::PathId;
 use crate::tile_map::DenseTileMap;
-use pathfinder_color::ColorU;
+use pathfinder_color::{ColorF, ColorU};
 use pathfinder_content::effects::{BlendMode, Filter};
 use pathfinder_content::render_target::RenderTargetId;
 use pathfinder_geometry::line_segment::{LineSegment2F, LineSegmentU16};
 use pathfinder_geometry::rect::RectI;
 use pathfinder_geometry::transform2d::Transform2F;
 use pathfinder_geometry::vector::{Vector2F, Vector2I};
-use pathfinder_gpu::TextureSamplingFlags;
+use pathfinder_gpu::{TextureFormat, TextureSamplingFlags};
 use std::fmt::{Debug, Formatter, Result as DebugResult};
 use std::sync::Arc;
 use std::sync::atomic::{AtomicUsize, Ordering};
@@ -48,17 +49,22 @@ pub enum RenderCommand {
         /// This is needed if a path that renders directly to the output framebuffer (i.e. not to a
         /// render target) uses one of the more exotic blend modes.
         needs_readable_framebuffer: bool,
+
+        /// The size of each tile in device pixels.
+        tile_size: Vector2I,
     },
 
     // Allocates a texture page.
     AllocateTexturePage { page_id: TexturePageId, descriptor: TexturePageDescriptor },
 
+    // Frees a texture page that is no longer needed.
+    FreeTexturePage(TexturePageId),
+
     // Uploads data to a texture page.
     UploadTexelData { texels: Arc<Vec<ColorU>>, location: TextureLocation },
 
-    // Associates a render target with a texture page.
-    //
-    // TODO(pcwalton): Add a rect to this so we can render to subrects of a page.
+    // Associates a render target with a rect of a texture page. Several render targets may share
+    // a page.
     DeclareRenderTarget { id: RenderTargetId, location: TextureLocation },
 
     // Upload texture metadata.
@@ -70,6 +76,11 @@ pub enum RenderCommand {
     // Flushes the queue of fills.
     FlushFillsD3D9,
 
+    // Keeps the contents of the previous frame, clearing only the tiles that will be redrawn.
+    //
+    // This is sent instead of clearing the whole destination when the tile cache is in use.
+    InvalidateTilesD3D9(DirtyTilesD3D9),
+
     /// Upload a scene to GPU.
     /// 
     /// This will only be sent if dicing and binning is done on GPU.
@@ -95,7 +106,13 @@ pub enum RenderCommand {
     DrawTilesD3D11(DrawTileBatchD3D11),
 
     // Presents a rendered frame.
-    Finish { cpu_build_time: Duration },
+    Finish {
+        cpu_build_time: Duration,
+        build_phase_times: BuildPhaseTimes,
+        culled_path_count: usize,
+        culled_contour_count: usize,
+        culled_tile_count: usize,
+    },
 }
 
 #[derive(Clone, Copy, PartialEq, Debug, Default)]
@@ -104,6 +121,7 @@ pub struct TexturePageId(pub u32);
 #[derive(Clone, Copy, Debug)]
 pub struct TexturePageDescriptor {
     pub size: Vector2I,
+    pub format: TextureFormat,
 }
 
 #[derive(Clone, Copy, PartialEq, Debug, Default)]
@@ -228,6 +246,18 @@ pub struct DrawTileBatchD3D9 {
     pub blend_mode: BlendMode,
 }
 
+/// The tiles that must be redrawn in a D3D9 frame rendered with the tile cache.
+#[derive(Clone, Debug)]
+pub struct DirtyTilesD3D9 {
+    /// Rectangles, in tile coordinates, that cover the dirty tiles. Everything outside them keeps
+    /// the contents it had at the end of the previous frame.
+    pub rects: Vec<RectI>,
+    /// The number of tiles that will be redrawn.
+    pub dirty_tile_count: u32,
+    /// The number of tiles whose contents are reused from the previous frame.
+    pub reused_tile_count: u32,
+}
+
 /// Information needed to draw a batch of tiles in D3D11.
 #[derive(Clone, Debug)]
 pub struct DrawTileBatchD3D11 {
@@ -330,7 +360,7 @@ pub struct DiceMetadataD3D11 {
 pub struct TextureMetadataEntry {
     pub color_0_transform: Transform2F,
     pub color_0_combine_mode: ColorCombineMode,
-    pub base_color: ColorU,
+    pub base_color: ColorF,
     pub filter: Filter,
     pub blend_mode: BlendMode,
 }
@@ -473,6 +503,9 @@ impl Debug for RenderCommand {
             RenderCommand::AllocateTexturePage { page_id, descriptor: _ } => {
                 write!(formatter, "AllocateTexturePage({})", page_id.0)
             }
+            RenderCommand::FreeTexturePage(page_id) => {
+                write!(formatter, "FreeTexturePage({})", page_id.0)
+            }
             RenderCommand::UploadTexelData { ref texels, location } => {
                 write!(formatter, "UploadTexelData(x{:?}, {:?})", texels.len(), location)
             }
@@ -486,6 +519,12 @@ impl Debug for RenderCommand {
                 write!(formatter, "AddFillsD3D9(x{})", fills.len())
             }
             RenderCommand::FlushFillsD3D9 => write!(formatter, "FlushFills"),
+            RenderCommand::InvalidateTilesD3D9(ref dirty_tiles) => {
+                write!(formatter,
+                       "InvalidateTilesD3D9(x{}, R x{})",
+                       dirty_tiles.dirty_tile_count,
+                       dirty_tiles.reused_tile_count)
+            }
             RenderCommand::UploadSceneD3D11 { ref draw_segments, ref clip_segments } => {
                 write!(formatter,
                        "UploadSceneD3D11(DP x{}, DI x{}, CP x{}, CI x{})",
@@ -517,7 +556,7 @@ impl Debug for RenderCommand {
                        batch.tile_batch_data.batch_id,
                        batch.color_texture)
             }
-            RenderCommand::Finish { cpu_build_time } => {
+            RenderCommand::Finish { cpu_build_time, .. } => {
                 write!(formatter, "Finish({} ms)", cpu_build_time.as_secs_f64() * 1000.0)
             }
         }
@@ -0,0 +1,431 @@
+// pathfinder/renderer/src/layers.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Decides which layers have to be rasterized again and which can be reused from the last frame.
+//!
+//! Each layer is keyed by a hash of everything drawn into it, in layer space. The render target
+//! backing a layer is kept alive from frame to frame under that key, so a layer whose contents
+//! haven't changed is simply composited again, no matter how its transform, opacity, or clip, or
+//! the render transform, changed.
+
+use crate::options::{PrepareMode, PreparedBuildOptions, PreparedRenderTransform};
+use crate::scene::{ClipPathId, DisplayItem, DrawPathId, LayerId, Scene};
+use crate::tile_cache;
+use fxhash::FxHasher;
+use pathfinder_geometry::rect::RectF;
+use pathfinder_geometry::transform2d::Transform2F;
+use pathfinder_geometry::vector::Vector2F;
+use std::borrow::Cow;
+use std::hash::{Hash, Hasher};
+
+/// Where each path of a scene is drawn, and which layers are reused this frame.
+pub(crate) struct LayerPlan {
+    // The innermost layer that each draw path is drawn into, if any.
+    draw_path_layers: Vec<Option<LayerId>>,
+    // The view box, in layer space, of each clip path that is used inside layers.
+    clip_path_view_boxes: Vec<Option<RectF>>,
+    // A hash of the contents of each layer.
+    layer_hashes: Vec<u64>,
+    // Whether each layer is reused from the last frame, either because its own contents are
+    // cached or because it's inside a layer whose contents are.
+    reused_layers: Vec<bool>,
+    // Whether each clip path is used only by paths inside reused layers.
+    reused_clip_paths: Vec<bool>,
+}
+
+impl LayerPlan {
+    pub(crate) fn new(scene: &Scene, built_options: &PreparedBuildOptions) -> LayerPlan {
+        let layer_count = scene.layers().len();
+        let mut plan = LayerPlan {
+            draw_path_layers: vec![],
+            clip_path_view_boxes: vec![],
+            layer_hashes: vec![0; layer_count],
+            reused_layers: vec![false; layer_count],
+            reused_clip_paths: vec![],
+        };
+        if layer_count == 0 {
+            return plan;
+        }
+
+        let layer_options = LayerPlan::layer_options(built_options);
+        let clip_hashes = tile_cache::hash_clip_paths(scene, &layer_options);
+        let options_hash = tile_cache::hash_options(&layer_options);
+
+        plan.draw_path_layers = vec![None; scene.draw_paths().len()];
+        let mut layer_stack: Vec<(LayerId, FxHasher)> = vec![];
+        for display_item in scene.display_list() {
+            match *display_item {
+                DisplayItem::PushLayer(layer_id) => {
+                    let layer = &scene.layers()[layer_id.0 as usize].layer;
+                    let mut hasher = FxHasher::default();
+                    options_hash.hash(&mut hasher);
+                    layer.size().x().hash(&mut hasher);
+                    layer.size().y().hash(&mut hasher);
+                    layer_stack.push((layer_id, hasher));
+                }
+                DisplayItem::PopLayer => {
+                    if let Some((layer_id, hasher)) = layer_stack.pop() {
+                        plan.finish_layer_hash(layer_id, hasher, &mut layer_stack);
+                    }
+                }
+                DisplayItem::PushRenderTarget(_) | DisplayItem::PopRenderTarget => {}
+                DisplayItem::DrawPaths(ref draw_path_id_range) => {
+                    let (layer_id, hasher) = match layer_stack.last_mut() {
+                        None => continue,
+                        Some(&mut (layer_id, ref mut hasher)) => (layer_id, hasher),
+                    };
+                    for draw_path_index in draw_path_id_range.start.0..draw_path_id_range.end.0 {
+                        plan.draw_path_layers[draw_path_index as usize] = Some(layer_id);
+
+                        // Layer space has no render transform, so only device strokes need the
+                        // render options applied to tell paths apart.
+                        let draw_path = scene.get_draw_path(DrawPathId(draw_path_index));
+                        let outline = match draw_path.device_stroke() {
+                            None => Cow::Borrowed(draw_path.outline()),
+                            Some(device_stroke) => {
+                                Cow::Owned(scene.apply_render_options(draw_path.outline(),
+                                                                     Some(device_stroke),
+                                                                     &layer_options))
+                            }
+                        };
+                        tile_cache::hash_draw_path(scene, draw_path, &outline, &clip_hashes)
+                            .hash(hasher);
+                    }
+                }
+            }
+        }
+
+        // Building a scene closes the layers left open, but the scene may not have been built.
+        while let Some((layer_id, hasher)) = layer_stack.pop() {
+            plan.finish_layer_hash(layer_id, hasher, &mut layer_stack);
+        }
+
+        // A clip path used inside a layer is tiled in layer space, covering every layer that uses
+        // it. Clip paths that clip such a clip path are too.
+        plan.clip_path_view_boxes = vec![None; scene.clip_paths().len()];
+        for (draw_path_index, draw_path) in scene.draw_paths().iter().enumerate() {
+            let layer_id = plan.draw_path_layers[draw_path_index];
+            if let (Some(clip_path_id), Some(layer_id)) = (draw_path.clip_path(), layer_id) {
+                let layer_view_box = LayerPlan::layer_view_box(scene, layer_id);
+                plan.union_clip_path_view_box(clip_path_id, layer_view_box);
+            }
+        }
+        for clip_path_index in (0..scene.clip_paths().len()).rev() {
+            let view_box = match plan.clip_path_view_boxes[clip_path_index] {
+                None => continue,
+                Some(view_box) => view_box,
+            };
+            if let Some(subclip_path_id) = scene.clip_paths()[clip_path_index].clip_path() {
+                plan.union_clip_path_view_box(subclip_path_id, view_box);
+            }
+        }
+
+        plan
+    }
+
+    fn finish_layer_hash(&mut self,
+                         layer_id: LayerId,
+                         hasher: FxHasher,
+                         layer_stack: &mut [(LayerId, FxHasher)]) {
+        let layer_hash = hasher.finish();
+        self.layer_hashes[layer_id.0 as usize] = layer_hash;
+        if let Some(&mut (_, ref mut parent_hasher)) = layer_stack.last_mut() {
+            layer_hash.hash(parent_hasher);
+        }
+    }
+
+    fn union_clip_path_view_box(&mut self, clip_path_id: ClipPathId, view_box: RectF) {
+        let clip_path_view_box = &mut self.clip_path_view_boxes[clip_path_id.0 as usize];
+        *clip_path_view_box = Some(match *clip_path_view_box {
+            None => view_box,
+            Some(clip_path_view_box) => clip_path_view_box.union_rect(view_box),
+        });
+    }
+
+    /// Returns the key under which the render target of each layer is cached, indexed by render
+    /// target. Render targets that don't back layers have no key.
+    pub(crate) fn render_target_cache_keys(&self, scene: &Scene) -> Vec<Option<u64>> {
+        let mut cache_keys = vec![];
+        for (layer_info, &layer_hash) in scene.layers().iter().zip(self.layer_hashes.iter()) {
+            let render_target_index = layer_info.render_target_id.render_target as usize;
+            if cache_keys.len() <= render_target_index {
+                cache_keys.resize(render_target_index + 1, None);
+            }
+            cache_keys[render_target_index] = Some(layer_hash);
+        }
+        cache_keys
+    }
+
+    /// Records which layers still have their contents from the last frame, given which render
+    /// targets were found in the cache.
+    pub(crate) fn mark_reused_layers(&mut self, scene: &Scene, cached_render_targets: &[bool]) {
+        // Parents are always pushed before their children, so one pass suffices.
+        for (layer_index, layer_info) in scene.layers().iter().enumerate() {
+            let render_target_index = layer_info.render_target_id.render_target as usize;
+            let parent_is_reused = match layer_info.parent {
+                None => false,
+                Some(parent) => self.reused_layers[parent.0 as usize],
+            };
+            self.reused_layers[layer_index] =
+                cached_render_targets[render_target_index] || parent_is_reused;
+        }
+
+        // Clip paths are only needed if some path outside a reused layer uses them.
+        let mut needed_clip_paths = vec![false; scene.clip_paths().len()];
+        for (draw_path_index, draw_path) in scene.draw_paths().iter().enumerate() {
+            if !self.draw_path_is_reused(DrawPathId(draw_path_index as u32)) {
+                if let Some(clip_path_id) = draw_path.clip_path() {
+                    needed_clip_paths[clip_path_id.0 as usize] = true;
+                }
+            }
+        }
+        for clip_path_index in (0..scene.clip_paths().len()).rev() {
+            if !needed_clip_paths[clip_path_index] {
+                continue;
+            }
+            if let Some(subclip_path_id) = scene.clip_paths()[clip_path_index].clip_path() {
+                needed_clip_paths[subclip_path_id.0 as usize] = true;
+            }
+        }
+        self.reused_clip_paths = needed_clip_paths.into_iter().map(|needed| !needed).collect();
+    }
+
+    /// Returns the innermost layer that the given draw path is drawn into, if any.
+    #[inline]
+    pub(crate) fn draw_path_layer(&self, draw_path_id: DrawPathId) -> Option<LayerId> {
+        self.draw_path_layers.get(draw_path_id.0 as usize).cloned().flatten()
+    }
+
+    /// Returns the view box in layer space if the given clip path is used inside layers.
+    #[inline]
+    pub(crate) fn clip_path_view_box(&self, clip_path_id: ClipPathId) -> Option<RectF> {
+        self.clip_path_view_boxes.get(clip_path_id.0 as usize).cloned().flatten()
+    }
+
+    /// Returns true if the given layer's contents are reused from the last frame.
+    #[inline]
+    pub(crate) fn layer_is_reused(&self, layer_id: LayerId) -> bool {
+        self.reused_layers[layer_id.0 as usize]
+    }
+
+    /// Returns true if the given draw path is inside a reused layer and so needn't be built.
+    #[inline]
+    pub(crate) fn draw_path_is_reused(&self, draw_path_id: DrawPathId) -> bool {
+        match self.draw_path_layer(draw_path_id) {
+            None => false,
+            Some(layer_id) => self.layer_is_reused(layer_id),
+        }
+    }
+
+    /// Returns true if the given clip path is only used inside reused layers.
+    #[inline]
+    pub(crate) fn clip_path_is_reused(&self, clip_path_id: ClipPathId) -> bool {
+        self.reused_clip_paths.get(clip_path_id.0 as usize).cloned().unwrap_or(false)
+    }
+
+    /// Returns the options that paths inside layers are built with: those of the scene, minus the
+    /// render transform and subpixel antialiasing.
+    pub(crate) fn layer_options(built_options: &PreparedBuildOptions) -> PreparedBuildOptions {
+        PreparedBuildOptions {
+            transform: PreparedRenderTransform::None,
+            dilation: built_options.dilation,
+            subpixel_aa_enabled: false,
+            color_space: built_options.color_space,
+            tile_cache: false,
+            tile_size: built_options.tile_size,
+            max_gradient_ramp_length: built_options.max_gradient_ramp_length,
+            flattening_tolerance: built_options.flattening_tolerance,
+        }
+    }
+
+    /// Returns the prepare mode that paths inside layers are built with.
+    pub(crate) fn layer_prepare_mode(prepare_mode: &PrepareMode) -> PrepareMode {
+        match *prepare_mode {
+            PrepareMode::CPU => PrepareMode::CPU,
+            PrepareMode::TransformCPUBinGPU => PrepareMode::TransformCPUBinGPU,
+            PrepareMode::GPU { .. } => PrepareMode::GPU { transform: Transform2F::default() },
+        }
+    }
+
+    /// Returns the visible area of a layer, in layer space.
+    #[inline]
+    pub(crate) fn layer_view_box(scene: &Scene, layer_id: LayerId) -> RectF {
+        let layer = &scene.layers()[layer_id.0 as usize].layer;
+        RectF::new(Vector2F::zero(), layer.size().to_f32())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::LayerPlan;
+    use crate::concurrent::executor::SequentialExecutor;
+    use crate::gpu::options::RendererLevel;
+    use crate::options::{BuildOptions, RenderCommandListener, RenderTransform};
+    use crate::paint::Paint;
+    use crate::scene::{ClipPath, ClipPathId, DrawPath, DrawPathId, Layer, LayerId, Scene};
+    use crate::scene::SceneSink;
+    use pathfinder_color::ColorU;
+    use pathfinder_content::outline::Outline;
+    use pathfinder_geometry::rect::RectF;
+    use pathfinder_geometry::transform2d::Transform2F;
+    use pathfinder_geometry::vector::{Vector2F, vec2f, vec2i};
+
+    // A clipped path in an outer layer, a path in an inner layer nested in the outer one, and a
+    // path outside both.
+    struct LayerScene {
+        scene: Scene,
+        outer_layer: LayerId,
+        inner_layer: LayerId,
+        clip_path: ClipPathId,
+        outer_path: DrawPathId,
+        inner_path: DrawPathId,
+        inner_composite_path: DrawPathId,
+        outer_composite_path: DrawPathId,
+        outside_path: DrawPathId,
+    }
+
+    impl LayerScene {
+        fn new(inner_color: ColorU) -> LayerScene {
+            let rect = RectF::new(Vector2F::zero(), vec2f(16.0, 16.0));
+            let mut scene = Scene::new();
+            let black = scene.push_paint(&Paint::from_color(ColorU::black()));
+            let inner_paint = scene.push_paint(&Paint::from_color(inner_color));
+            let clip_path = scene.push_clip_path(ClipPath::new(Outline::from_rect(rect)));
+
+            let outer_layer = scene.push_layer(Layer::new(vec2i(32, 32)));
+            let mut outer_path = DrawPath::new(Outline::from_rect(rect), black);
+            outer_path.set_clip_path(Some(clip_path));
+            let outer_path = scene.push_draw_path(outer_path);
+            let inner_layer = scene.push_layer(Layer::new(vec2i(16, 16)));
+            let inner_path = scene.push_draw_path(DrawPath::new(Outline::from_rect(rect),
+                                                                inner_paint));
+            let inner_composite_path = scene.pop_layer().unwrap();
+            let outer_composite_path = scene.pop_layer().unwrap();
+            let outside_path = scene.push_draw_path(DrawPath::new(Outline::from_rect(rect),
+                                                                  black));
+
+            LayerScene {
+                scene,
+                outer_layer,
+                inner_layer,
+                clip_path,
+                outer_path,
+                inner_path,
+                inner_composite_path,
+                outer_composite_path,
+                outside_path,
+            }
+        }
+
+        fn plan(&self, build_options: BuildOptions) -> LayerPlan {
+            let built_options = build_options.prepare(self.scene.bounds(), RendererLevel::D3D9);
+            LayerPlan::new(&self.scene, &built_options)
+        }
+
+        fn cache_keys(&self, build_options: BuildOptions) -> Vec<Option<u64>> {
+            self.plan(build_options).render_target_cache_keys(&self.scene)
+        }
+    }
+
+    #[test]
+    fn draw_paths_are_assigned_to_their_innermost_layer() {
+        let layer_scene = LayerScene::new(ColorU::white());
+        let plan = layer_scene.plan(BuildOptions::default());
+        assert_eq!(plan.draw_path_layer(layer_scene.outer_path), Some(layer_scene.outer_layer));
+        assert_eq!(plan.draw_path_layer(layer_scene.inner_path), Some(layer_scene.inner_layer));
+        assert_eq!(plan.draw_path_layer(layer_scene.inner_composite_path),
+                   Some(layer_scene.outer_layer));
+        assert_eq!(plan.draw_path_layer(layer_scene.outer_composite_path), None);
+        assert_eq!(plan.draw_path_layer(layer_scene.outside_path), None);
+
+        // The clip path is tiled in the space of the layer that uses it.
+        assert_eq!(plan.clip_path_view_box(layer_scene.clip_path),
+                   Some(RectF::new(Vector2F::zero(), vec2f(32.0, 32.0))));
+    }
+
+    #[test]
+    fn cache_keys_only_change_with_layer_contents() {
+        let layer_scene = LayerScene::new(ColorU::white());
+        let cache_keys = layer_scene.cache_keys(BuildOptions::default());
+        assert_eq!(cache_keys.len(), 2);
+        assert!(cache_keys.iter().all(|cache_key| cache_key.is_some()));
+        assert_ne!(cache_keys[0], cache_keys[1]);
+
+        // Moving a layer or zooming the scene doesn't change its contents.
+        let mut layer_scene = layer_scene;
+        let transform = Transform2F::from_translation(vec2f(5.0, 7.0));
+        layer_scene.scene.set_layer_transform(layer_scene.outer_layer, transform);
+        layer_scene.scene.set_layer_opacity(layer_scene.outer_layer, 0.5);
+        let zoom = BuildOptions {
+            transform: RenderTransform::Transform2D(Transform2F::from_scale(2.0)),
+            ..BuildOptions::default()
+        };
+        assert_eq!(layer_scene.cache_keys(zoom), cache_keys);
+
+        // Moving the inner layer changes the contents of the outer one only.
+        layer_scene.scene.set_layer_transform(layer_scene.inner_layer, transform);
+        let moved_cache_keys = layer_scene.cache_keys(BuildOptions::default());
+        assert_ne!(moved_cache_keys[0], cache_keys[0]);
+        assert_eq!(moved_cache_keys[1], cache_keys[1]);
+
+        // Different contents get a different key. (The outer layer refers to the render target of
+        // the inner one, which belongs to its scene, so only the inner layer can be compared
+        // across scenes.)
+        let recolored_cache_keys = LayerScene::new(ColorU::black())
+            .cache_keys(BuildOptions::default());
+        assert_ne!(recolored_cache_keys[1], cache_keys[1]);
+    }
+
+    #[test]
+    fn layers_inside_reused_layers_are_reused() {
+        let layer_scene = LayerScene::new(ColorU::white());
+        let scene = &layer_scene.scene;
+
+        let mut plan = layer_scene.plan(BuildOptions::default());
+        plan.mark_reused_layers(scene, &[true, false]);
+        assert!(plan.layer_is_reused(layer_scene.outer_layer));
+        assert!(plan.layer_is_reused(layer_scene.inner_layer));
+        assert!(plan.draw_path_is_reused(layer_scene.outer_path));
+        assert!(plan.draw_path_is_reused(layer_scene.inner_composite_path));
+        assert!(!plan.draw_path_is_reused(layer_scene.outer_composite_path));
+        assert!(!plan.draw_path_is_reused(layer_scene.outside_path));
+        assert!(plan.clip_path_is_reused(layer_scene.clip_path));
+
+        // An inner layer can be reused while the outer one is drawn again.
+        let mut plan = layer_scene.plan(BuildOptions::default());
+        plan.mark_reused_layers(scene, &[false, true]);
+        assert!(!plan.layer_is_reused(layer_scene.outer_layer));
+        assert!(plan.layer_is_reused(layer_scene.inner_layer));
+        assert!(!plan.draw_path_is_reused(layer_scene.outer_path));
+        assert!(plan.draw_path_is_reused(layer_scene.inner_path));
+        assert!(!plan.clip_path_is_reused(layer_scene.clip_path));
+    }
+
+    #[test]
+    fn unbalanced_layers_are_closed() {
+        let rect = RectF::new(Vector2F::zero(), vec2f(16.0, 16.0));
+        let mut scene = Scene::new();
+        scene.set_view_box(rect);
+        assert_eq!(scene.pop_layer(), None);
+
+        let paint = scene.push_paint(&Paint::from_color(ColorU::white()));
+        scene.push_layer(Layer::new(vec2i(16, 16)));
+        scene.push_draw_path(DrawPath::new(Outline::from_rect(rect), paint));
+        let built_options = BuildOptions::default().prepare(scene.bounds(), RendererLevel::D3D9);
+        assert_ne!(LayerPlan::new(&scene, &built_options).layer_hashes[0], 0);
+
+        // Building the scene composites the open layer like any other.
+        let listener = RenderCommandListener::new(Box::new(|_| {}));
+        let mut sink = SceneSink::new(listener, RendererLevel::D3D9);
+        scene.build(BuildOptions::default(), &mut sink, &SequentialExecutor);
+        assert_eq!(scene.draw_paths().len(), 2);
+        assert_eq!(scene.pop_layer(), None);
+    }
+}
@@ -25,7 +25,10 @@ pub mod scene;
 
 mod allocator;
 mod builder;
+mod culling;
 mod gpu_data;
+mod layers;
+mod tile_cache;
 mod tile_map;
 mod tiler;
 mod tiles;
@@ -12,14 +12,19 @@
 
 use crate::gpu::options::RendererLevel;
 use crate::gpu_data::RenderCommand;
+use crate::tiles::{TILE_HEIGHT, TILE_WIDTH};
+use pathfinder_color::space::ColorSpace;
 use pathfinder_geometry::rect::RectF;
 use pathfinder_geometry::transform2d::Transform2F;
 use pathfinder_geometry::transform3d::Perspective;
-use pathfinder_geometry::vector::{Vector2F, Vector4F};
+use pathfinder_geometry::vector::{Vector2F, Vector2I, Vector4F, vec2i};
 
 #[allow(deprecated)]
 use pathfinder_content::clip::PolygonClipper3D;
 
+const DEFAULT_MAX_GRADIENT_RAMP_LENGTH: u32 = 2048;
+const DEFAULT_FLATTENING_TOLERANCE: f32 = 0.25;
+
 /// A sink for the render commands that scenes build.
 /// 
 /// In single-threaded operation, this object typically buffers commands into an array and then,
@@ -49,7 +54,7 @@ impl<'a> RenderCommandListener<'a> {
 }
 
 /// Options that influence scene building.
-#[derive(Clone, Default)]
+#[derive(Clone)]
 pub struct BuildOptions {
     /// A global transform to be applied to the scene.
     pub transform: RenderTransform,
@@ -58,18 +63,90 @@ pub struct BuildOptions {
     pub dilation: Vector2F,
     /// True if subpixel antialiasing for LCD screens is to be performed.
     pub subpixel_aa_enabled: bool,
+    /// The color space of the destination. The colors of paints are converted to this color space
+    /// when the scene is built.
+    pub color_space: ColorSpace,
+    /// True if tiles whose contents haven't changed since the previous frame should be reused
+    /// instead of redrawn.
+    ///
+    /// This only takes effect at the D3D9 level, and only if the same `SceneSink` is used from
+    /// frame to frame (for example, via a `SceneProxy`). Call `SceneSink::invalidate_tile_cache()`
+    /// whenever the renderer's destination or background color changes.
+    pub tile_cache: bool,
+    /// The size of each tile, in device pixels.
+    ///
+    /// Both sides must be multiples of 4 between 4 and 64; other sizes are rounded to the nearest
+    /// such multiple. Smaller tiles need less alpha mask memory for thin or sparse content, while
+    /// larger ones mean fewer tiles to sort and draw. The default is 16×16.
+    ///
+    /// The D3D11 level ignores this and always uses 16×16 tiles, since its compute shaders are
+    /// written for that size.
+    pub tile_size: Vector2I,
+    /// The maximum length of the color ramp that each gradient is rendered into, in texels.
+    ///
+    /// Each gradient gets a ramp about as long as the gradient is in device pixels, rounded up to
+    /// a power of two and at least 256 texels long. Raising this avoids banding in gradients that
+    /// span very large canvases, at the cost of texture memory. The default is 2048.
+    pub max_gradient_ramp_length: u32,
+    /// The maximum distance, in device pixels, between a curve and the lines that approximate it.
+    ///
+    /// Lower values render curves more accurately but produce more line segments to tile. The
+    /// default is 0.25, which is also used in place of values that aren't positive and finite.
+    /// This only takes effect when paths are tiled on CPU, as the D3D9 renderer does.
+    pub flattening_tolerance: f32,
+}
+
+impl Default for BuildOptions {
+    #[inline]
+    fn default() -> BuildOptions {
+        BuildOptions {
+            transform: RenderTransform::default(),
+            dilation: Vector2F::zero(),
+            subpixel_aa_enabled: false,
+            color_space: ColorSpace::default(),
+            tile_cache: false,
+            tile_size: vec2i(TILE_WIDTH as i32, TILE_HEIGHT as i32),
+            max_gradient_ramp_length: DEFAULT_MAX_GRADIENT_RAMP_LENGTH,
+            flattening_tolerance: DEFAULT_FLATTENING_TOLERANCE,
+        }
+    }
 }
 
 impl BuildOptions {
-    pub(crate) fn prepare(self, bounds: RectF) -> PreparedBuildOptions {
+    pub(crate) fn prepare(self, bounds: RectF, renderer_level: RendererLevel)
+                          -> PreparedBuildOptions {
+        // The D3D11 compute shaders are written for the default tile size.
+        let tile_size = match renderer_level {
+            RendererLevel::D3D9 => {
+                vec2i(round_tile_side(self.tile_size.x()), round_tile_side(self.tile_size.y()))
+            }
+            RendererLevel::D3D11 => vec2i(TILE_WIDTH as i32, TILE_HEIGHT as i32),
+        };
+        let flattening_tolerance = if self.flattening_tolerance > 0.0 &&
+                self.flattening_tolerance.is_finite() {
+            self.flattening_tolerance
+        } else {
+            DEFAULT_FLATTENING_TOLERANCE
+        };
+
         PreparedBuildOptions {
             transform: self.transform.prepare(bounds),
             dilation: self.dilation,
             subpixel_aa_enabled: self.subpixel_aa_enabled,
+            color_space: self.color_space,
+            tile_cache: self.tile_cache,
+            tile_size,
+            max_gradient_ramp_length: self.max_gradient_ramp_length,
+            flattening_tolerance,
         }
     }
 }
 
+// Rounds a tile side to the nearest multiple of 4 between 4 and 64.
+fn round_tile_side(side: i32) -> i32 {
+    (side.saturating_add(2) / 4 * 4).clamp(4, 64)
+}
+
 /// A global transform to apply to the scene.
 #[derive(Clone)]
 pub enum RenderTransform {
@@ -143,6 +220,11 @@ pub(crate) struct PreparedBuildOptions {
     pub(crate) transform: PreparedRenderTransform,
     pub(crate) dilation: Vector2F,
     pub(crate) subpixel_aa_enabled: bool,
+    pub(crate) color_space: ColorSpace,
+    pub(crate) tile_cache: bool,
+    pub(crate) tile_size: Vector2I,
+    pub(crate) max_gradient_ramp_length: u32,
+    pub(crate) flattening_tolerance: f32,
 }
 
 #[derive(Clone, Copy)]
@@ -201,3 +283,38 @@ impl PreparedRenderTransform {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::gpu::options::RendererLevel;
+    use pathfinder_geometry::rect::RectF;
+    use pathfinder_geometry::vector::vec2i;
+
+    use super::{BuildOptions, DEFAULT_FLATTENING_TOLERANCE};
+
+    #[test]
+    fn invalid_tile_sizes_are_rounded_to_valid_ones() {
+        let tile_size = |tile_size, renderer_level| {
+            let options = BuildOptions { tile_size, ..BuildOptions::default() };
+            options.prepare(RectF::default(), renderer_level).tile_size
+        };
+        assert_eq!(tile_size(vec2i(8, 32), RendererLevel::D3D9), vec2i(8, 32));
+        assert_eq!(tile_size(vec2i(7, 9), RendererLevel::D3D9), vec2i(8, 8));
+        assert_eq!(tile_size(vec2i(0, -16), RendererLevel::D3D9), vec2i(4, 4));
+        assert_eq!(tile_size(vec2i(65, 1000), RendererLevel::D3D9), vec2i(64, 64));
+        // The D3D11 level always uses 16×16 tiles.
+        assert_eq!(tile_size(vec2i(8, 32), RendererLevel::D3D11), vec2i(16, 16));
+    }
+
+    #[test]
+    fn invalid_flattening_tolerances_fall_back_to_the_default() {
+        let flattening_tolerance = |flattening_tolerance| {
+            let options = BuildOptions { flattening_tolerance, ..BuildOptions::default() };
+            options.prepare(RectF::default(), RendererLevel::D3D9).flattening_tolerance
+        };
+        assert_eq!(flattening_tolerance(0.1), 0.1);
+        for &invalid in &[0.0, -1.0, f32::NAN, f32::INFINITY] {
+            assert_eq!(flattening_tolerance(invalid), DEFAULT_FLATTENING_TOLERANCE);
+        }
+    }
+}
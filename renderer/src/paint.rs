@@ -11,13 +11,15 @@
 //! Defines how a path is to be filled.
 
 use crate::allocator::{AllocationMode, TextureAllocator};
+use crate::concurrent::executor::Executor;
 use crate::gpu_data::{ColorCombineMode, RenderCommand, TextureLocation, TextureMetadataEntry};
 use crate::gpu_data::{TexturePageDescriptor, TexturePageId, TileBatchTexture};
 use crate::scene::{RenderTarget, SceneId};
 use hashbrown::{HashMap, HashSet};
-use pathfinder_color::ColorU;
+use pathfinder_color::{ColorF, ColorU};
+use pathfinder_color::space::ColorSpace;
 use pathfinder_content::effects::{BlendMode, Filter, PatternFilter};
-use pathfinder_content::gradient::{Gradient, GradientGeometry, GradientWrap};
+use pathfinder_content::gradient::{Gradient, GradientGeometry, GradientWrap, MeshGradient};
 use pathfinder_content::pattern::{ImageHash, Pattern, PatternSource};
 use pathfinder_content::render_target::RenderTargetId;
 use pathfinder_geometry::line_segment::LineSegment2F;
@@ -28,12 +30,18 @@ use pathfinder_gpu::TextureSamplingFlags;
 use pathfinder_simd::default::{F32x2, F32x4};
 use std::f32;
 use std::fmt::{self, Debug, Formatter};
+use std::hash::{Hash, Hasher};
 use std::sync::Arc;
 
-// The size of a gradient tile.
-//
-// TODO(pcwalton): Choose this size dynamically!
-const GRADIENT_TILE_LENGTH: u32 = 256;
+// The shortest color ramp that a gradient is rendered into.
+const MIN_GRADIENT_RAMP_LENGTH: u32 = 256;
+
+// The number of texels in a gradient tile. Each tile holds as many ramps of one length as fit.
+const GRADIENT_TILE_AREA: u32 = 256 * 256;
+
+// The maximum length of each side of the texture that a mesh gradient is rendered into, not
+// counting the border. Mesh gradients zoomed in further than this are magnified bilinearly.
+const MAX_MESH_GRADIENT_TEXTURE_LENGTH: i32 = 2048;
 
 // Stores all paints in a scene.
 #[derive(Clone)]
@@ -41,6 +49,11 @@ pub(crate) struct Palette {
     pub(crate) paints: Vec<Paint>,
     render_targets: Vec<RenderTarget>,
     cache: HashMap<Paint, PaintId>,
+    // Paints that are in the coordinate space of a layer, to which the render transform doesn't
+    // apply.
+    layer_paints: HashSet<PaintId>,
+    // Maps each paint to its copy in layer space, if one has been made.
+    layer_paint_mapping: HashMap<PaintId, PaintId>,
     scene_id: SceneId,
 }
 
@@ -48,12 +61,16 @@ pub(crate) struct Palette {
 pub(crate) struct PaintTextureManager {
     allocator: TextureAllocator,
     cached_images: HashMap<ImageHash, TextureLocation>,
+    // Render targets whose contents are kept from frame to frame, keyed by a hash of what was
+    // drawn into them.
+    cached_render_targets: HashMap<u64, TextureLocation>,
 }
 
 /// Defines how a path is to be filled: with a solid color, gradient, or pattern.
-#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+#[derive(Clone, PartialEq, Debug)]
 pub struct Paint {
-    base_color: ColorU,
+    base_color: ColorF,
+    color_space: ColorSpace,
     overlay: Option<PaintOverlay>,
 }
 
@@ -102,6 +119,30 @@ impl Debug for PaintContents {
     }
 }
 
+impl Eq for Paint {}
+
+impl Hash for Paint {
+    fn hash<H>(&self, state: &mut H) where H: Hasher {
+        for index in 0..4 {
+            canonical_f32_bits(self.base_color.0[index]).hash(state);
+        }
+        self.color_space.hash(state);
+        self.overlay.hash(state);
+    }
+}
+
+// Returns the bits of a float, with -0.0 mapped to 0.0 and every NaN mapped to the same NaN, so
+// that paints that differ only in those don't get different hashes.
+fn canonical_f32_bits(value: f32) -> u32 {
+    if value == 0.0 {
+        0
+    } else if value.is_nan() {
+        f32::NAN.to_bits()
+    } else {
+        value.to_bits()
+    }
+}
+
 impl Palette {
     #[inline]
     pub(crate) fn new(scene_id: SceneId) -> Palette {
@@ -109,6 +150,8 @@ impl Palette {
             paints: vec![],
             render_targets: vec![],
             cache: HashMap::new(),
+            layer_paints: HashSet::new(),
+            layer_paint_mapping: HashMap::new(),
             scene_id,
         }
     }
@@ -118,14 +161,24 @@ impl Paint {
     /// Creates a simple paint from a single base color.
     #[inline]
     pub fn from_color(color: ColorU) -> Paint {
-        Paint { base_color: color, overlay: None }
+        Paint::from_color_f(color.to_f32())
+    }
+
+    /// Creates a simple paint from a single floating-point base color.
+    ///
+    /// The color components may exceed 1.0 for high-dynamic-range content. They're preserved when
+    /// rendering to floating-point targets and clamped when rendering to 8-bit ones.
+    #[inline]
+    pub fn from_color_f(color: ColorF) -> Paint {
+        Paint { base_color: color, color_space: ColorSpace::Srgb, overlay: None }
     }
 
     /// Creates a paint from a gradient.
     #[inline]
     pub fn from_gradient(gradient: Gradient) -> Paint {
         Paint {
-            base_color: ColorU::white(),
+            base_color: ColorF::white(),
+            color_space: ColorSpace::Srgb,
             overlay: Some(PaintOverlay {
                 composite_op: PaintCompositeOp::SrcIn,
                 contents: PaintContents::Gradient(gradient),
@@ -137,7 +190,8 @@ impl Paint {
     #[inline]
     pub fn from_pattern(pattern: Pattern) -> Paint {
         Paint {
-            base_color: ColorU::white(),
+            base_color: ColorF::white(),
+            color_space: ColorSpace::Srgb,
             overlay: Some(PaintOverlay {
                 composite_op: PaintCompositeOp::SrcIn,
                 contents: PaintContents::Pattern(pattern),
@@ -161,7 +215,8 @@ impl Paint {
     ///
     /// Even if the paint is opaque, this function might return false.
     pub fn is_opaque(&self) -> bool {
-        if !self.base_color.is_opaque() {
+        // Check the unrounded alpha, as alpha just below 1.0 rounds up to 255.
+        if self.base_color.a() < 1.0 {
             return false;
         }
 
@@ -180,7 +235,7 @@ impl Paint {
     ///
     /// Even if the paint is fully transparent, this function might return false.
     pub fn is_fully_transparent(&self) -> bool {
-        if !self.base_color.is_fully_transparent() {
+        if self.base_color.a() > 0.0 {
             return false;
         }
 
@@ -220,9 +275,10 @@ impl Paint {
     /// Returns the *base color* of this paint.
     ///
     /// The base color is the color that goes underneath the gradient or pattern, if there is one.
+    /// Components outside the [0, 1] range are clamped; use `base_color_f()` to retrieve them.
     #[inline]
     pub fn base_color(&self) -> ColorU {
-        self.base_color
+        self.base_color.clamp().to_u8()
     }
 
     /// Changes the *base color* of this paint.
@@ -230,9 +286,38 @@ impl Paint {
     /// The base color is the color that goes underneath the gradient or pattern, if there is one.
     #[inline]
     pub fn set_base_color(&mut self, new_base_color: ColorU) {
+        self.base_color = new_base_color.to_f32();
+    }
+
+    /// Returns the *base color* of this paint at full precision.
+    #[inline]
+    pub fn base_color_f(&self) -> ColorF {
+        self.base_color
+    }
+
+    /// Changes the *base color* of this paint to a floating-point color, the components of which
+    /// may exceed 1.0.
+    #[inline]
+    pub fn set_base_color_f(&mut self, new_base_color: ColorF) {
         self.base_color = new_base_color;
     }
 
+    /// Returns the color space that the base color and the gradient colors of this paint are in.
+    ///
+    /// Colors are converted to the color space of the destination, as specified in the build
+    /// options, when the scene is built. Image patterns are assumed to already be in the color
+    /// space of the destination.
+    #[inline]
+    pub fn color_space(&self) -> ColorSpace {
+        self.color_space
+    }
+
+    /// Changes the color space that the base color and the gradient colors of this paint are in.
+    #[inline]
+    pub fn set_color_space(&mut self, new_color_space: ColorSpace) {
+        self.color_space = new_color_space;
+    }
+
     /// Returns the paint overlay, which is the portion of the paint on top of the base color.
     #[inline]
     pub fn overlay(&self) -> &Option<PaintOverlay> {
@@ -317,6 +402,11 @@ pub(crate) struct PaintInfo {
     ///
     /// The indices of this vector are paint IDs.
     pub(crate) paint_metadata: Vec<PaintMetadata>,
+    /// Whether each render target still holds its contents from a previous frame, so that it
+    /// doesn't need to be drawn again.
+    ///
+    /// The indices of this vector are render target indices.
+    pub(crate) cached_render_targets: Vec<bool>,
 }
 
 #[derive(Debug)]
@@ -324,7 +414,7 @@ pub(crate) struct PaintMetadata {
     /// Metadata associated with the color texture, if applicable.
     pub(crate) color_texture_metadata: Option<PaintColorTextureMetadata>,
     /// The base color that the color texture gets mixed into.
-    pub(crate) base_color: ColorU,
+    pub(crate) base_color: ColorF,
     pub(crate) blend_mode: BlendMode,
     /// True if this paint is fully opaque.
     pub(crate) is_opaque: bool,
@@ -362,6 +452,8 @@ pub(crate) struct RadialGradientMetadata {
 pub(crate) struct RenderTargetMetadata {
     /// The location of the render target.
     pub(crate) location: TextureLocation,
+    /// True if the render target's contents were kept from a previous frame.
+    pub(crate) cached: bool,
 }
 
 #[derive(Debug)]
@@ -373,6 +465,12 @@ pub(crate) enum PaintFilter {
         /// The radii of the two circles.
         radii: F32x2,
     },
+    ConicGradient {
+        /// The center of the gradient.
+        center: Vector2F,
+        /// The angle at which the gradient starts.
+        angle: f32,
+    },
     PatternFilter(PatternFilter),
 }
 
@@ -389,82 +487,213 @@ impl Palette {
         paint_id
     }
 
+    // Adds a paint that is never shared with other paints, so that it can be changed in place
+    // later with `replace_paint()`.
+    pub(crate) fn push_unique_paint(&mut self, paint: Paint, in_layer_space: bool) -> PaintId {
+        let paint_id = PaintId(self.paints.len() as u16);
+        self.paints.push(paint);
+        if in_layer_space {
+            self.layer_paints.insert(paint_id);
+        }
+        paint_id
+    }
+
+    // Only valid for paints added with `push_unique_paint()`.
+    pub(crate) fn replace_paint(&mut self, paint_id: PaintId, new_paint: Paint) {
+        self.paints[paint_id.0 as usize] = new_paint;
+    }
+
+    // Returns a paint that looks like the given one when drawn in layer space.
+    //
+    // Solid colors look the same in every coordinate space, so only paints with overlays need
+    // copies.
+    pub(crate) fn layer_paint(&mut self, paint_id: PaintId) -> PaintId {
+        if self.layer_paints.contains(&paint_id) ||
+                self.paints[paint_id.0 as usize].overlay().is_none() {
+            return paint_id;
+        }
+        if let Some(&layer_paint_id) = self.layer_paint_mapping.get(&paint_id) {
+            return layer_paint_id;
+        }
+        let layer_paint_id = PaintId(self.paints.len() as u16);
+        self.paints.push(self.paints[paint_id.0 as usize].clone());
+        self.layer_paints.insert(layer_paint_id);
+        self.layer_paint_mapping.insert(paint_id, layer_paint_id);
+        layer_paint_id
+    }
+
     pub(crate) fn push_render_target(&mut self, render_target: RenderTarget) -> RenderTargetId {
         let id = self.render_targets.len() as u32;
         self.render_targets.push(render_target);
         RenderTargetId { scene: self.scene_id.0, render_target: id }
     }
 
-    pub(crate) fn build_paint_info(&mut self,
-                                   texture_manager: &mut PaintTextureManager,
-                                   render_transform: Transform2F)
-                                   -> PaintInfo {
+    pub(crate) fn build_paint_info<E>(&mut self,
+                                      executor: &E,
+                                      texture_manager: &mut PaintTextureManager,
+                                      render_transform: Transform2F,
+                                      color_space: ColorSpace,
+                                      max_gradient_ramp_length: u32,
+                                      render_target_cache_keys: &[Option<u64>])
+                                      -> PaintInfo
+                                      where E: Executor {
         // Assign render target locations.
         let mut transient_paint_locations = vec![];
-        let render_target_metadata =
-            self.assign_render_target_locations(texture_manager, &mut transient_paint_locations);
+        let (render_target_metadata, used_render_target_keys) =
+            self.assign_render_target_locations(texture_manager,
+                                                &mut transient_paint_locations,
+                                                render_target_cache_keys);
+        let cached_render_targets =
+            render_target_metadata.iter().map(|metadata| metadata.cached).collect();
 
         // Assign paint locations.
         let PaintLocationsInfo {
             mut paint_metadata,
             gradient_tile_builder,
             image_texel_info,
+            mesh_gradient_texel_info,
             used_image_hashes,
         } = self.assign_paint_locations(&render_target_metadata,
                                         texture_manager,
-                                        &mut transient_paint_locations);
+                                        &mut transient_paint_locations,
+                                        render_transform,
+                                        color_space,
+                                        max_gradient_ramp_length);
 
         // Calculate texture transforms.
-        self.calculate_texture_transforms(&mut paint_metadata, texture_manager, render_transform);
+        self.calculate_texture_transforms(executor,
+                                          &mut paint_metadata,
+                                          texture_manager,
+                                          render_transform);
 
         // Create texture metadata.
-        let texture_metadata = self.create_texture_metadata(&paint_metadata);
+        let texture_metadata = self.create_texture_metadata(executor, &paint_metadata);
         let mut render_commands = vec![RenderCommand::UploadTextureMetadata(texture_metadata)];
 
         // Allocate textures.
         self.allocate_textures(&mut render_commands, texture_manager);
 
-        // Create render commands.
-        self.create_render_commands(&mut render_commands,
+        // Create render commands. This is where gradient ramps and mesh gradients get rendered.
+        self.create_render_commands(executor,
+                                    &mut render_commands,
                                     render_target_metadata,
                                     gradient_tile_builder,
-                                    image_texel_info);
+                                    image_texel_info,
+                                    mesh_gradient_texel_info,
+                                    color_space);
 
         // Free transient locations and unused images, now that they're no longer needed.
         self.free_transient_locations(texture_manager, transient_paint_locations);
         self.free_unused_images(texture_manager, used_image_hashes);
+        self.free_unused_render_targets(texture_manager, used_render_target_keys);
 
-        PaintInfo { render_commands, paint_metadata }
+        PaintInfo { render_commands, paint_metadata, cached_render_targets }
     }
 
+    // Render targets with a cache key keep their location for as long as a render target with
+    // the same key is declared each frame. All others are transient.
     fn assign_render_target_locations(&self,
                                       texture_manager: &mut PaintTextureManager,
-                                      transient_paint_locations: &mut Vec<TextureLocation>)
-                                      -> Vec<RenderTargetMetadata> {
+                                      transient_paint_locations: &mut Vec<TextureLocation>,
+                                      render_target_cache_keys: &[Option<u64>])
+                                      -> (Vec<RenderTargetMetadata>, HashSet<u64>) {
+        // Render targets share atlas pages unless a pattern repeats them, which needs the whole
+        // page.
+        let mut repeated_render_targets = vec![false; self.render_targets.len()];
+        for paint in &self.paints {
+            if let Some(PaintOverlay { contents: PaintContents::Pattern(ref pattern), .. }) =
+                    paint.overlay {
+                if let PatternSource::RenderTarget { id, .. } = *pattern.source() {
+                    if pattern.repeat_x() || pattern.repeat_y() {
+                        repeated_render_targets[id.render_target as usize] = true;
+                    }
+                }
+            }
+        }
+
         let mut render_target_metadata = vec![];
-        for render_target in &self.render_targets {
-            let location = texture_manager.allocator.allocate_image(render_target.size());
-            render_target_metadata.push(RenderTargetMetadata { location });
-            transient_paint_locations.push(location);
+        let mut used_render_target_keys = HashSet::new();
+        for (render_target_index, render_target) in self.render_targets.iter().enumerate() {
+            let cache_key = render_target_cache_keys.get(render_target_index).cloned().flatten();
+            let cached_location = cache_key.and_then(|cache_key| {
+                texture_manager.cached_render_targets.get(&cache_key).cloned()
+            });
+            let metadata = match cached_location {
+                Some(location) => RenderTargetMetadata { location, cached: true },
+                None => {
+                    let allocation_mode = if repeated_render_targets[render_target_index] {
+                        AllocationMode::OwnPage
+                    } else {
+                        AllocationMode::Atlas
+                    };
+                    let location =
+                        texture_manager.allocator
+                                       .allocate_render_target(render_target.size(),
+                                                               render_target.format(),
+                                                               allocation_mode);
+                    match cache_key {
+                        Some(cache_key) => {
+                            texture_manager.cached_render_targets.insert(cache_key, location);
+                        }
+                        None => transient_paint_locations.push(location),
+                    }
+                    RenderTargetMetadata { location, cached: false }
+                }
+            };
+            if let Some(cache_key) = cache_key {
+                used_render_target_keys.insert(cache_key);
+            }
+            render_target_metadata.push(metadata);
         }
-        render_target_metadata
+        (render_target_metadata, used_render_target_keys)
     }
 
     fn assign_paint_locations(&self,
                               render_target_metadata: &[RenderTargetMetadata],
                               texture_manager: &mut PaintTextureManager,
-                              transient_paint_locations: &mut Vec<TextureLocation>)
-                              -> PaintLocationsInfo {
+                              transient_paint_locations: &mut Vec<TextureLocation>,
+                              render_transform: Transform2F,
+                              dest_color_space: ColorSpace,
+                              max_gradient_ramp_length: u32)
+                              -> PaintLocationsInfo<'_> {
         let mut paint_metadata = vec![];
         let mut gradient_tile_builder = GradientTileBuilder::new();
         let mut image_texel_info = vec![];
+        let mut mesh_gradient_texel_info = vec![];
         let mut used_image_hashes = HashSet::new();
-        for paint in &self.paints {
+        for (paint_index, paint) in self.paints.iter().enumerate() {
             let allocator = &mut texture_manager.allocator;
+            let paint_to_device = self.paint_to_device(PaintId(paint_index as u16),
+                                                       render_transform);
             let color_texture_metadata = match paint.overlay {
                 None => None,
                 Some(ref overlay) => {
                     match overlay.contents {
+                        PaintContents::Gradient(Gradient {
+                            geometry: GradientGeometry::Mesh(ref mesh),
+                            ..
+                        }) => {
+                            // Mesh gradients don't fit in a gradient tile, so render them on the
+                            // CPU to their own texture. That happens later, in parallel.
+                            let (rect, size) = mesh_gradient_texture_rect(mesh, paint_to_device);
+                            let location = allocator.allocate(size, AllocationMode::OwnPage);
+                            transient_paint_locations.push(location);
+                            mesh_gradient_texel_info.push(MeshGradientTexelInfo {
+                                mesh,
+                                color_space: paint.color_space,
+                                rect,
+                                location,
+                            });
+                            Some(PaintColorTextureMetadata {
+                                location,
+                                page_scale: allocator.page_scale(location.page),
+                                sampling_flags: TextureSamplingFlags::empty(),
+                                filter: PaintFilter::None,
+                                transform: Transform2F::default(),
+                                composite_op: overlay.composite_op(),
+                                border: Vector2I::zero(),
+                            })
+                        }
                         PaintContents::Gradient(ref gradient) => {
                             let mut sampling_flags = TextureSamplingFlags::empty();
                             match gradient.wrap {
@@ -474,12 +703,15 @@ impl Palette {
                                 GradientWrap::Clamp => {}
                             }
 
-                            // FIXME(pcwalton): The gradient size might not be big enough. Detect
-                            // this.
+                            let ramp_length = gradient_ramp_length(gradient,
+                                                                   paint_to_device,
+                                                                   max_gradient_ramp_length);
                             let location =
                                 gradient_tile_builder.allocate(allocator,
                                                                transient_paint_locations,
-                                                               gradient);
+                                                               gradient,
+                                                               ramp_length,
+                                                               paint.color_space);
                             Some(PaintColorTextureMetadata {
                                 location,
                                 page_scale: allocator.page_scale(location.page),
@@ -489,6 +721,10 @@ impl Palette {
                                     GradientGeometry::Radial { line, radii, .. } => {
                                         PaintFilter::RadialGradient { line, radii }
                                     }
+                                    GradientGeometry::Conic { center, angle, .. } => {
+                                        PaintFilter::ConicGradient { center, angle }
+                                    }
+                                    GradientGeometry::Mesh(_) => unreachable!(),
                                 },
                                 transform: Transform2F::default(),
                                 composite_op: overlay.composite_op(),
@@ -515,8 +751,9 @@ impl Palette {
                                             used_image_hashes.insert(image_hash);
                                         }
                                         None => {
-                                            // Leave a pixel of border on the side.
-                                            let allocation_mode = AllocationMode::OwnPage;
+                                            // Leave a pixel of border on the side. The border
+                                            // isn't uploaded, so it needs a fresh page.
+                                            let allocation_mode = AllocationMode::FreshPage;
                                             location = allocator.allocate(
                                                 image.size() + border * 2,
                                                 allocation_mode);
@@ -568,7 +805,12 @@ impl Palette {
             paint_metadata.push(PaintMetadata {
                 color_texture_metadata,
                 is_opaque: paint.is_opaque(),
-                base_color: paint.base_color(),
+                // Keep components above 1.0 for high-dynamic-range targets, but clip the negative
+                // ones that out-of-gamut conversions produce.
+                base_color: ColorF(paint.base_color
+                                        .convert(paint.color_space, dest_color_space)
+                                        .0
+                                        .max(F32x4::default())),
                 // FIXME(pcwalton)
                 blend_mode: BlendMode::SrcOver,
             });
@@ -578,92 +820,152 @@ impl Palette {
             paint_metadata,
             gradient_tile_builder,
             image_texel_info,
+            mesh_gradient_texel_info,
             used_image_hashes,
         }
     }
 
-    fn calculate_texture_transforms(&self,
-                                    paint_metadata: &mut [PaintMetadata],
-                                    texture_manager: &mut PaintTextureManager,
-                                    render_transform: Transform2F) {
-        for (paint, metadata) in self.paints.iter().zip(paint_metadata.iter_mut()) {
-            let mut color_texture_metadata = match metadata.color_texture_metadata {
-                None => continue,
-                Some(ref mut color_texture_metadata) => color_texture_metadata,
-            };
+    fn calculate_texture_transforms<E>(&self,
+                                       executor: &E,
+                                       paint_metadata: &mut [PaintMetadata],
+                                       texture_manager: &PaintTextureManager,
+                                       render_transform: Transform2F)
+                                       where E: Executor {
+        let transforms = {
+            let paint_metadata = &*paint_metadata;
+            executor.build_vector(paint_metadata.len(), |paint_index| {
+                let color_texture_metadata =
+                    paint_metadata[paint_index].color_texture_metadata.as_ref()?;
+                Some(self.calculate_texture_transform(PaintId(paint_index as u16),
+                                                      color_texture_metadata,
+                                                      texture_manager,
+                                                      render_transform))
+            })
+        };
+        for (metadata, transform) in paint_metadata.iter_mut().zip(transforms) {
+            if let (Some(ref mut color_texture_metadata), Some(transform)) =
+                    (&mut metadata.color_texture_metadata, transform) {
+                color_texture_metadata.transform = transform;
+            }
+        }
+    }
 
-            let texture_scale = texture_manager.allocator
-                                               .page_scale(color_texture_metadata.location.page);
-            let texture_rect = color_texture_metadata.location.rect;
-            color_texture_metadata.transform = match paint.overlay    
-                                                          .as_ref()
-                                                          .expect("Why do we have color texture \
-                                                                   metadata but no overlay?")
-                                                          .contents {
-                PaintContents::Gradient(Gradient {
-                    geometry: GradientGeometry::Linear(gradient_line),
-                    ..
-                }) => {
-                    // Project gradient line onto (0.0-1.0, v0).
-                    let v0 = texture_rect.to_f32().center().y() * texture_scale.y();
-                    let dp = gradient_line.vector();
-                    let m0 = dp.0.concat_xy_xy(dp.0) / F32x4::splat(gradient_line.square_length());
-                    let m13 = m0.zw() * -gradient_line.from().0;
-                    Transform2F::row_major(m0.x(), m0.y(), m13.x() + m13.y(), 0.0, 0.0, v0)
-                }
-                PaintContents::Gradient(Gradient {
-                    geometry: GradientGeometry::Radial { ref transform, .. },
-                    ..
-                }) => transform.inverse(),
-                PaintContents::Pattern(ref pattern) => {
-                    match pattern.source() {
-                        PatternSource::Image(_) => {
-                            let texture_origin_uv =
-                                rect_to_uv(texture_rect, texture_scale).origin();
-                            Transform2F::from_scale(texture_scale).translate(texture_origin_uv) *
-                                pattern.transform().inverse()
-                        }
-                        PatternSource::RenderTarget { .. } => {
-                            // FIXME(pcwalton): Only do this in GL, not Metal!
-                            let texture_origin_uv =
-                                rect_to_uv(texture_rect, texture_scale).lower_left();
-                            Transform2F::from_translation(texture_origin_uv) *
-                                Transform2F::from_scale(texture_scale * vec2f(1.0, -1.0)) *
-                                pattern.transform().inverse()
-                        }
+    // Returns the transform from the space of the given paint to device pixels. Paints in layer
+    // space are already in device pixels.
+    fn paint_to_device(&self, paint_id: PaintId, render_transform: Transform2F) -> Transform2F {
+        if self.layer_paints.contains(&paint_id) {
+            Transform2F::default()
+        } else {
+            render_transform.inverse()
+        }
+    }
+
+    fn calculate_texture_transform(&self,
+                                   paint_id: PaintId,
+                                   color_texture_metadata: &PaintColorTextureMetadata,
+                                   texture_manager: &PaintTextureManager,
+                                   render_transform: Transform2F)
+                                   -> Transform2F {
+        let paint = &self.paints[paint_id.0 as usize];
+        let texture_scale = texture_manager.allocator
+                                           .page_scale(color_texture_metadata.location.page);
+        let texture_rect = color_texture_metadata.location.rect;
+        let transform = match paint.overlay
+                                   .as_ref()
+                                   .expect("Why do we have color texture metadata but no overlay?")
+                                   .contents {
+            PaintContents::Gradient(Gradient {
+                geometry: GradientGeometry::Linear(gradient_line),
+                ..
+            }) => {
+                // Project gradient line onto (0.0-1.0, v0).
+                let v0 = texture_rect.to_f32().center().y() * texture_scale.y();
+                let dp = gradient_line.vector();
+                let m0 = dp.0.concat_xy_xy(dp.0) / F32x4::splat(gradient_line.square_length());
+                let m13 = m0.zw() * -gradient_line.from().0;
+                Transform2F::row_major(m0.x(), m0.y(), m13.x() + m13.y(), 0.0, 0.0, v0)
+            }
+            PaintContents::Gradient(Gradient {
+                geometry: GradientGeometry::Radial { ref transform, .. },
+                ..
+            }) |
+            PaintContents::Gradient(Gradient {
+                geometry: GradientGeometry::Conic { ref transform, .. },
+                ..
+            }) => transform.inverse(),
+            PaintContents::Gradient(Gradient {
+                geometry: GradientGeometry::Mesh(ref mesh),
+                ..
+            }) => {
+                let paint_to_device = self.paint_to_device(paint_id, render_transform);
+                let (rect, size) = mesh_gradient_texture_rect(mesh, paint_to_device);
+                let texture_origin_uv = rect_to_uv(texture_rect, texture_scale).origin();
+                Transform2F::from_scale(texture_scale).translate(texture_origin_uv) *
+                    Transform2F::from_scale(size.to_f32() / rect.size()) *
+                    Transform2F::from_translation(-rect.origin())
+            }
+            PaintContents::Pattern(ref pattern) => {
+                match pattern.source() {
+                    PatternSource::Image(_) => {
+                        let texture_origin_uv =
+                            rect_to_uv(texture_rect, texture_scale).origin();
+                        Transform2F::from_scale(texture_scale).translate(texture_origin_uv) *
+                            pattern.transform().inverse()
+                    }
+                    PatternSource::RenderTarget { .. } => {
+                        // FIXME(pcwalton): Only do this in GL, not Metal!
+                        let texture_origin_uv =
+                            rect_to_uv(texture_rect, texture_scale).lower_left();
+                        Transform2F::from_translation(texture_origin_uv) *
+                            Transform2F::from_scale(texture_scale * vec2f(1.0, -1.0)) *
+                            pattern.transform().inverse()
                     }
                 }
-            };
-            color_texture_metadata.transform *= render_transform;
+            }
+        };
+        if self.layer_paints.contains(&paint_id) {
+            transform
+        } else {
+            transform * render_transform
         }
     }
 
-    fn create_texture_metadata(&self, paint_metadata: &[PaintMetadata])
-                               -> Vec<TextureMetadataEntry> {
-        paint_metadata.iter().map(|paint_metadata| {
+    fn create_texture_metadata<E>(&self, executor: &E, paint_metadata: &[PaintMetadata])
+                                  -> Vec<TextureMetadataEntry>
+                                  where E: Executor {
+        executor.build_vector(paint_metadata.len(), |paint_index| {
+            let paint_metadata = &paint_metadata[paint_index];
             TextureMetadataEntry {
                 color_0_transform: match paint_metadata.color_texture_metadata {
                     None => Transform2F::default(),
                     Some(ref color_texture_metadata) => color_texture_metadata.transform,
                 },
-                color_0_combine_mode: if paint_metadata.color_texture_metadata.is_some() {
-                    ColorCombineMode::SrcIn
-                } else {
-                    ColorCombineMode::None
+                color_0_combine_mode: match paint_metadata.color_texture_metadata {
+                    None => ColorCombineMode::None,
+                    Some(ref color_texture_metadata) => {
+                        match color_texture_metadata.composite_op {
+                            PaintCompositeOp::SrcIn => ColorCombineMode::SrcIn,
+                            PaintCompositeOp::DestIn => ColorCombineMode::DestIn,
+                        }
+                    }
                 },
                 base_color: paint_metadata.base_color,
                 filter: paint_metadata.filter(),
                 blend_mode: paint_metadata.blend_mode,
             }
-        }).collect()
+        })
     }
 
     fn allocate_textures(&self,
                          render_commands: &mut Vec<RenderCommand>,
                          texture_manager: &mut PaintTextureManager) {
+        for page_id in texture_manager.allocator.free_unused_pages() {
+            render_commands.push(RenderCommand::FreeTexturePage(page_id));
+        }
         for page_id in texture_manager.allocator.page_ids() {
             let page_size = texture_manager.allocator.page_size(page_id);
-            let descriptor = TexturePageDescriptor { size: page_size };
+            let format = texture_manager.allocator.page_format(page_id);
+            let descriptor = TexturePageDescriptor { size: page_size, format };
 
             if texture_manager.allocator.page_is_new(page_id) {
                 render_commands.push(RenderCommand::AllocateTexturePage { page_id, descriptor });
@@ -672,11 +974,16 @@ impl Palette {
         texture_manager.allocator.mark_all_pages_as_allocated();
     }
 
-    fn create_render_commands(&self,
-                              render_commands: &mut Vec<RenderCommand>,
-                              render_target_metadata: Vec<RenderTargetMetadata>,
-                              gradient_tile_builder: GradientTileBuilder,
-                              image_texel_info: Vec<ImageTexelInfo>) {
+    #[allow(clippy::too_many_arguments)]
+    fn create_render_commands<E>(&self,
+                                 executor: &E,
+                                 render_commands: &mut Vec<RenderCommand>,
+                                 render_target_metadata: Vec<RenderTargetMetadata>,
+                                 gradient_tile_builder: GradientTileBuilder,
+                                 image_texel_info: Vec<ImageTexelInfo>,
+                                 mesh_gradient_texel_info: Vec<MeshGradientTexelInfo>,
+                                 dest_color_space: ColorSpace)
+                                 where E: Executor {
         for (index, metadata) in render_target_metadata.iter().enumerate() {
             let id = RenderTargetId { scene: self.scene_id.0, render_target: index as u32 };
             render_commands.push(RenderCommand::DeclareRenderTarget {
@@ -684,13 +991,23 @@ impl Palette {
                 location: metadata.location,
             });
         }
-        gradient_tile_builder.create_render_commands(render_commands);
+        gradient_tile_builder.create_render_commands(executor, render_commands, dest_color_space);
         for image_texel_info in image_texel_info {
             render_commands.push(RenderCommand::UploadTexelData {
                 texels: image_texel_info.texels,
                 location: image_texel_info.location,
             });
         }
+
+        let mesh_gradient_texels = executor.build_vector(mesh_gradient_texel_info.len(), |index| {
+            mesh_gradient_texel_info[index].rasterize(dest_color_space)
+        });
+        for (info, texels) in mesh_gradient_texel_info.iter().zip(mesh_gradient_texels) {
+            render_commands.push(RenderCommand::UploadTexelData {
+                texels: Arc::new(texels),
+                location: info.location,
+            });
+        }
     }
 
     fn free_transient_locations(&self,
@@ -716,6 +1033,21 @@ impl Palette {
         });
     }
 
+    // Frees cached render targets that weren't declared this frame.
+    fn free_unused_render_targets(&self,
+                                  texture_manager: &mut PaintTextureManager,
+                                  used_render_target_keys: HashSet<u64>) {
+        let cached_render_targets = &mut texture_manager.cached_render_targets;
+        let allocator = &mut texture_manager.allocator;
+        cached_render_targets.retain(|cache_key, location| {
+            let keep = used_render_target_keys.contains(cache_key);
+            if !keep {
+                allocator.free(*location);
+            }
+            keep
+        });
+    }
+
     pub(crate) fn append_palette(&mut self, palette: Palette) -> MergedPaletteInfo {
         // Merge render targets.
         let mut render_target_mapping = HashMap::new();
@@ -741,8 +1073,10 @@ impl Palette {
                         PaintContents::Pattern(ref pattern) => {
                             match pattern.source() {
                                 PatternSource::RenderTarget { id: old_render_target_id, size } => {
+                                    let new_render_target_id =
+                                        render_target_mapping[old_render_target_id];
                                     let mut new_pattern =
-                                        Pattern::from_render_target(*old_render_target_id, *size);
+                                        Pattern::from_render_target(new_render_target_id, *size);
                                     new_pattern.set_filter(pattern.filter());
                                     new_pattern.apply_transform(pattern.transform());
                                     new_pattern.set_repeat_x(pattern.repeat_x());
@@ -757,6 +1091,11 @@ impl Palette {
                     }
                 }
             };
+            let new_paint_id = if palette.layer_paints.contains(&old_paint_id) {
+                self.layer_paint(new_paint_id)
+            } else {
+                new_paint_id
+            };
             paint_mapping.insert(old_paint_id, new_paint_id);
         }
 
@@ -769,6 +1108,7 @@ impl PaintTextureManager {
         PaintTextureManager {
             allocator: TextureAllocator::new(),
             cached_images: HashMap::new(),
+            cached_render_targets: HashMap::new(),
         }
     }
 }
@@ -791,6 +1131,12 @@ impl PaintMetadata {
                             vec2f(0.0, color_metadata.page_scale.y() * 0.5));
                         Filter::RadialGradient { line, radii, uv_origin: uv_rect.origin() }
                     }
+                    PaintFilter::ConicGradient { center, angle } => {
+                        let uv_rect = rect_to_uv(color_metadata.location.rect,
+                                                 color_metadata.page_scale).contract(
+                            vec2f(0.0, color_metadata.page_scale.y() * 0.5));
+                        Filter::ConicGradient { center, angle, uv_origin: uv_rect.origin() }
+                    }
                     PaintFilter::PatternFilter(pattern_filter) => {
                         Filter::PatternFilter(pattern_filter)
                     }
@@ -804,83 +1150,167 @@ impl PaintMetadata {
     }
 }
 
+fn convert_color(color: ColorU, from: ColorSpace, to: ColorSpace) -> ColorU {
+    if from == to {
+        color
+    } else {
+        color.to_f32().convert(from, to).clamp().to_u8()
+    }
+}
+
 fn rect_to_uv(rect: RectI, texture_scale: Vector2F) -> RectF {
     rect.to_f32() * texture_scale
 }
 
+// Returns the area, in scene coordinates, that the texture for a mesh gradient covers, along with
+// the size of that texture.
+//
+// The texture has about one texel per device pixel along each side of the mesh's bounds, so that
+// zooming in doesn't blur it. It has a one-texel transparent border, so that clamping to the edge
+// renders areas outside the mesh as transparent.
+fn mesh_gradient_texture_rect(mesh: &MeshGradient, paint_to_device: Transform2F)
+                              -> (RectF, Vector2I) {
+    let bounds = mesh.bounds();
+    let bounds = RectF::new(bounds.origin(), bounds.size().max(vec2f(1.0, 1.0)));
+    let device_size = vec2f((paint_to_device.matrix * vec2f(bounds.width(), 0.0)).length(),
+                            (paint_to_device.matrix * vec2f(0.0, bounds.height())).length());
+    let inner_size = device_size.ceil()
+                                .to_i32()
+                                .max(Vector2I::splat(1))
+                                .min(Vector2I::splat(MAX_MESH_GRADIENT_TEXTURE_LENGTH));
+    let texel_size = bounds.size() / inner_size.to_f32();
+    (bounds.dilate(texel_size), inner_size + Vector2I::splat(2))
+}
+
 // Gradient allocation
 
-struct GradientTileBuilder {
+// Gradient ramps are only allocated here. Their texels are sampled afterward, in parallel.
+struct GradientTileBuilder<'a> {
     tiles: Vec<GradientTile>,
+    ramps: Vec<GradientRamp<'a>>,
 }
 
 struct GradientTile {
-    texels: Vec<ColorU>,
     page: TexturePageId,
+    ramp_length: u32,
     next_index: u32,
 }
 
-impl GradientTileBuilder {
-    fn new() -> GradientTileBuilder {
-        GradientTileBuilder { tiles: vec![] }
+struct GradientRamp<'a> {
+    gradient: &'a Gradient,
+    color_space: ColorSpace,
+    tile_index: usize,
+    row: u32,
+}
+
+impl<'a> GradientTileBuilder<'a> {
+    fn new() -> GradientTileBuilder<'a> {
+        GradientTileBuilder { tiles: vec![], ramps: vec![] }
     }
 
     fn allocate(&mut self,
                 allocator: &mut TextureAllocator,
                 transient_paint_locations: &mut Vec<TextureLocation>,
-                gradient: &Gradient)
+                gradient: &'a Gradient,
+                ramp_length: u32,
+                color_space: ColorSpace)
                 -> TextureLocation {
-        if self.tiles.is_empty() ||
-                self.tiles.last().unwrap().next_index == GRADIENT_TILE_LENGTH {
-            let size = Vector2I::splat(GRADIENT_TILE_LENGTH as i32);
-            let area = size.x() as usize * size.y() as usize;
-            let page_location = allocator.allocate(size, AllocationMode::OwnPage);
-            transient_paint_locations.push(page_location);
-            self.tiles.push(GradientTile {
-                texels: vec![ColorU::black(); area],
-                page: page_location.page,
-                next_index: 0,
-            })
-        }
+        // Only the most recent tile with a given ramp length can have room left.
+        let ramps_per_tile = GRADIENT_TILE_AREA / ramp_length;
+        let tile_index = match self.tiles.iter().rposition(|tile| tile.ramp_length == ramp_length) {
+            Some(tile_index) if self.tiles[tile_index].next_index < ramps_per_tile => tile_index,
+            Some(_) | None => {
+                let size = vec2i(ramp_length as i32, ramps_per_tile as i32);
+                let page_location = allocator.allocate(size, AllocationMode::OwnPage);
+                transient_paint_locations.push(page_location);
+                self.tiles.push(GradientTile {
+                    page: page_location.page,
+                    ramp_length,
+                    next_index: 0,
+                });
+                self.tiles.len() - 1
+            }
+        };
 
-        let mut data = self.tiles.last_mut().unwrap();
+        let data = &mut self.tiles[tile_index];
         let location = TextureLocation {
             page: data.page,
-            rect: RectI::new(vec2i(0, data.next_index as i32),
-                             vec2i(GRADIENT_TILE_LENGTH as i32, 1)),
+            rect: RectI::new(vec2i(0, data.next_index as i32), vec2i(ramp_length as i32, 1)),
         };
+        self.ramps.push(GradientRamp { gradient, color_space, tile_index, row: data.next_index });
         data.next_index += 1;
+        location
+    }
+
+    fn create_render_commands<E>(self,
+                                 executor: &E,
+                                 render_commands: &mut Vec<RenderCommand>,
+                                 dest_color_space: ColorSpace)
+                                 where E: Executor {
+        let ramp_texels = executor.build_vector(self.ramps.len(), |ramp_index| {
+            let ramp = &self.ramps[ramp_index];
+            ramp.sample(self.tiles[ramp.tile_index].ramp_length, dest_color_space)
+        });
+
+        let mut tile_texels: Vec<Vec<ColorU>> = self.tiles.iter().map(|tile| {
+            let ramps_per_tile = GRADIENT_TILE_AREA / tile.ramp_length;
+            vec![ColorU::black(); (tile.ramp_length * ramps_per_tile) as usize]
+        }).collect();
+        for (ramp, texels) in self.ramps.iter().zip(ramp_texels) {
+            let first_address = ramp.row as usize * texels.len();
+            tile_texels[ramp.tile_index][first_address..(first_address + texels.len())]
+                .copy_from_slice(&texels);
+        }
+
+        for (tile, texels) in self.tiles.into_iter().zip(tile_texels) {
+            let ramps_per_tile = GRADIENT_TILE_AREA / tile.ramp_length;
+            let size = vec2i(tile.ramp_length as i32, ramps_per_tile as i32);
+            render_commands.push(RenderCommand::UploadTexelData {
+                texels: Arc::new(texels),
+                location: TextureLocation { rect: RectI::new(vec2i(0, 0), size), page: tile.page },
+            });
+        }
+    }
+}
 
+impl<'a> GradientRamp<'a> {
+    fn sample(&self, ramp_length: u32, dest_color_space: ColorSpace) -> Vec<ColorU> {
         // FIXME(pcwalton): Paint transparent if gradient line has zero size, per spec.
         // TODO(pcwalton): Optimize this:
         // 1. Calculate ∇t up front and use differencing in the inner loop.
         // 2. Go four pixels at a time with SIMD.
-        let first_address = location.rect.origin_y() as usize * GRADIENT_TILE_LENGTH as usize;
-        for x in 0..(GRADIENT_TILE_LENGTH as i32) {
-            let t = (x as f32 + 0.5) / GRADIENT_TILE_LENGTH as f32;
-            data.texels[first_address + x as usize] = gradient.sample(t);
-        }
-
-        location
+        (0..ramp_length).map(|x| {
+            let t = (x as f32 + 0.5) / ramp_length as f32;
+            let color = self.gradient.sample_in_color_space(t, self.color_space);
+            color.convert(self.color_space, dest_color_space).clamp().to_u8()
+        }).collect()
     }
+}
 
-    fn create_render_commands(self, render_commands: &mut Vec<RenderCommand>) {
-        for tile in self.tiles {
-            render_commands.push(RenderCommand::UploadTexelData {
-                texels: Arc::new(tile.texels),
-                location: TextureLocation {
-                    rect: RectI::new(vec2i(0, 0), Vector2I::splat(GRADIENT_TILE_LENGTH as i32)),
-                    page: tile.page,
-                },
-            });
+// Returns the length of the color ramp to render a gradient into: about as many texels as the
+// gradient is long in device pixels, so that gradients spanning large canvases don't band.
+fn gradient_ramp_length(gradient: &Gradient, paint_to_device: Transform2F, max_length: u32)
+                        -> u32 {
+    let device_length = match gradient.geometry {
+        GradientGeometry::Linear(line) => (paint_to_device * line).vector().length(),
+        GradientGeometry::Radial { line, radii, transform } => {
+            (line.vector().length() + radii.x().max(radii.y())) *
+                (paint_to_device * transform).scale_factor()
         }
-    }
+        // How long a conic gradient is depends on the size of the path it fills, which isn't
+        // known here.
+        GradientGeometry::Conic { .. } | GradientGeometry::Mesh(_) => 0.0,
+    };
+    let max_length = max_length.clamp(MIN_GRADIENT_RAMP_LENGTH, GRADIENT_TILE_AREA);
+    let length = (device_length.min(max_length as f32).ceil() as u32).next_power_of_two();
+    length.max(MIN_GRADIENT_RAMP_LENGTH).min(max_length)
 }
 
-struct PaintLocationsInfo {
+struct PaintLocationsInfo<'a> {
     paint_metadata: Vec<PaintMetadata>,
-    gradient_tile_builder: GradientTileBuilder,
+    gradient_tile_builder: GradientTileBuilder<'a>,
     image_texel_info: Vec<ImageTexelInfo>,
+    mesh_gradient_texel_info: Vec<MeshGradientTexelInfo<'a>>,
     used_image_hashes: HashSet<ImageHash>,
 }
 
@@ -889,6 +1319,26 @@ struct ImageTexelInfo {
     texels: Arc<Vec<ColorU>>,
 }
 
+struct MeshGradientTexelInfo<'a> {
+    mesh: &'a MeshGradient,
+    color_space: ColorSpace,
+    // The area of the scene that the texture covers.
+    rect: RectF,
+    location: TextureLocation,
+}
+
+impl<'a> MeshGradientTexelInfo<'a> {
+    fn rasterize(&self, dest_color_space: ColorSpace) -> Vec<ColorU> {
+        let mut texels = self.mesh.rasterize(self.rect, self.location.rect.size());
+        if self.color_space != dest_color_space {
+            for texel in &mut texels {
+                *texel = convert_color(*texel, self.color_space, dest_color_space);
+            }
+        }
+        texels
+    }
+}
+
 impl PaintColorTextureMetadata {
     pub(crate) fn as_tile_batch_texture(&self) -> TileBatchTexture {
         TileBatchTexture {
@@ -898,3 +1348,65 @@ impl PaintColorTextureMetadata {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{MAX_MESH_GRADIENT_TEXTURE_LENGTH, Paint, mesh_gradient_texture_rect};
+    use pathfinder_color::{ColorF, ColorU};
+    use pathfinder_content::gradient::{MeshGradient, MeshPatch};
+    use pathfinder_geometry::transform2d::Transform2F;
+    use pathfinder_geometry::vector::{vec2f, vec2i};
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+
+    #[test]
+    fn mesh_gradient_textures_follow_the_device_size() {
+        let mut boundary = [vec2f(0.0, 0.0); 12];
+        for (index, point) in boundary.iter_mut().enumerate() {
+            let t = (index % 3) as f32 / 3.0;
+            *point = match index / 3 {
+                0 => vec2f(t, 0.0),
+                1 => vec2f(1.0, t),
+                2 => vec2f(1.0 - t, 1.0),
+                _ => vec2f(0.0, 1.0 - t),
+            } * vec2f(40.0, 20.0);
+        }
+        let mut mesh = MeshGradient::new();
+        mesh.push_patch(MeshPatch::coons(boundary, [ColorU::black(); 4]));
+
+        let (rect, size) = mesh_gradient_texture_rect(&mesh, Transform2F::default());
+        assert_eq!(size, vec2i(42, 22));
+        assert!(rect.contains_rect(mesh.bounds()));
+
+        let zoom = Transform2F::from_scale(vec2f(4.0, 2.0));
+        assert_eq!(mesh_gradient_texture_rect(&mesh, zoom).1, vec2i(162, 42));
+
+        let huge_zoom = Transform2F::from_scale(vec2f(1000.0, 1000.0));
+        assert_eq!(mesh_gradient_texture_rect(&mesh, huge_zoom).1,
+                   vec2i(MAX_MESH_GRADIENT_TEXTURE_LENGTH + 2, MAX_MESH_GRADIENT_TEXTURE_LENGTH + 2));
+    }
+
+    #[test]
+    fn opacity_is_decided_on_the_unrounded_alpha() {
+        assert!(Paint::from_color_f(ColorF::new(1.0, 1.0, 1.0, 1.0)).is_opaque());
+        assert!(Paint::from_color_f(ColorF::new(4.0, 2.0, 1.0, 1.5)).is_opaque());
+
+        let nearly_opaque = Paint::from_color_f(ColorF::new(1.0, 1.0, 1.0, 0.999));
+        assert_eq!(nearly_opaque.base_color().a, 255);
+        assert!(!nearly_opaque.is_opaque());
+    }
+
+    #[test]
+    fn paint_hashes_ignore_the_sign_of_zero_and_nan_payloads() {
+        let hash = |color| {
+            let mut hasher = DefaultHasher::new();
+            Paint::from_color_f(color).hash(&mut hasher);
+            hasher.finish()
+        };
+        assert_eq!(hash(ColorF::new(-0.0, 0.5, 0.0, 1.0)), hash(ColorF::new(0.0, 0.5, -0.0, 1.0)));
+        let other_nan = f32::from_bits(f32::NAN.to_bits() ^ 1);
+        assert_eq!(hash(ColorF::new(f32::NAN, 0.0, 0.0, 1.0)),
+                   hash(ColorF::new(-other_nan, 0.0, 0.0, 1.0)));
+        assert_ne!(hash(ColorF::new(0.0, 0.5, 0.0, 1.0)), hash(ColorF::new(0.0, 0.25, 0.0, 1.0)));
+    }
+}
@@ -18,14 +18,21 @@ use crate::gpu_data::RenderCommand;
 use crate::options::{BuildOptions, PreparedBuildOptions};
 use crate::options::{PreparedRenderTransform, RenderCommandListener};
 use crate::paint::{MergedPaletteInfo, Paint, PaintId, PaintInfo, PaintTextureManager, Palette};
+use crate::tile_cache::TileCache;
+use pathfinder_color::ColorF;
+use pathfinder_color::space::ColorSpace;
+use pathfinder_content::dash::OutlineDash;
 use pathfinder_content::effects::BlendMode;
 use pathfinder_content::fill::FillRule;
 use pathfinder_content::outline::Outline;
+use pathfinder_content::pattern::Pattern;
 use pathfinder_content::render_target::RenderTargetId;
+use pathfinder_content::stroke::{LineCap, LineJoin, OutlineStrokeToFill, StrokeStyle};
 use pathfinder_geometry::rect::RectF;
 use pathfinder_geometry::transform2d::Transform2F;
-use pathfinder_geometry::vector::{Vector2I, vec2f};
-use pathfinder_gpu::Device;
+use pathfinder_geometry::vector::{Vector2F, Vector2I, vec2f};
+use pathfinder_gpu::{Device, TextureFormat};
+use std::f32::consts::SQRT_2;
 use std::mem;
 use std::ops::Range;
 use std::sync::atomic::{AtomicUsize, Ordering};
@@ -45,6 +52,10 @@ pub struct Scene {
     view_box: RectF,
     id: SceneId,
     epoch: SceneEpoch,
+    has_device_strokes: bool,
+    has_conics: bool,
+    layers: Vec<LayerInfo>,
+    layer_stack: Vec<LayerId>,
 }
 
 /// A globally-unique identifier for the scene.
@@ -65,16 +76,24 @@ impl Scene {
             view_box: RectF::default(),
             id: scene_id,
             epoch: SceneEpoch::new(0, 1),
+            has_device_strokes: false,
+            has_conics: false,
+            layers: vec![],
+            layer_stack: vec![],
         }
     }
 
     /// Adds a path to the scene, to be drawn on top of all previously-added paths.
     ///
     /// If a render target is on the stack (see `push_render_target()`), the path goes to the
-    /// render target. Otherwise, it goes to the main output.
+    /// render target. Otherwise, it goes to the main output. If a layer is open (see
+    /// `push_layer()`), the path goes to the layer and is in that layer's coordinate space.
     ///
     /// Returns an ID which can later be used to retrieve the path via `get_draw_path()`.
-    pub fn push_draw_path(&mut self, draw_path: DrawPath) -> DrawPathId {
+    pub fn push_draw_path(&mut self, mut draw_path: DrawPath) -> DrawPathId {
+        if !self.layer_stack.is_empty() {
+            draw_path.paint = self.palette.layer_paint(draw_path.paint);
+        }
         let draw_path_index = DrawPathId(self.draw_paths.len() as u32);
         self.draw_paths.push(draw_path);
         self.push_draw_path_with_index(draw_path_index);
@@ -82,8 +101,19 @@ impl Scene {
     }
 
     fn push_draw_path_with_index(&mut self, draw_path_id: DrawPathId) {
-        let new_path_bounds = self.draw_paths[draw_path_id.0 as usize].outline.bounds();
-        self.bounds = self.bounds.union_rect(new_path_bounds);
+        let draw_path = &self.draw_paths[draw_path_id.0 as usize];
+        let mut new_path_bounds = draw_path.outline.bounds();
+        if let Some(ref device_stroke) = draw_path.device_stroke {
+            // Approximate the stroke extent as though scene units were device pixels.
+            new_path_bounds = new_path_bounds.dilate(device_stroke.max_extent());
+            self.has_device_strokes = true;
+        }
+        self.has_conics |= draw_path.outline.has_conics();
+        // Paths inside layers are in layer space. The composite path of the layer accounts for
+        // them.
+        if self.layer_stack.is_empty() {
+            self.bounds = self.bounds.union_rect(new_path_bounds);
+        }
 
         let end_path_id = DrawPathId(draw_path_id.0 + 1);
         match self.display_list.last_mut() {
@@ -95,8 +125,13 @@ impl Scene {
     }
 
     /// Defines a clip path. Returns an ID that can be used to later clip draw paths.
+    ///
+    /// Clip paths used by paths inside a layer must be in that layer's coordinate space.
     pub fn push_clip_path(&mut self, clip_path: ClipPath) -> ClipPathId {
-        self.bounds = self.bounds.union_rect(clip_path.outline.bounds());
+        if self.layer_stack.is_empty() {
+            self.bounds = self.bounds.union_rect(clip_path.outline.bounds());
+        }
+        self.has_conics |= clip_path.outline.has_conics();
         let clip_path_id = ClipPathId(self.clip_paths.len() as u32);
         self.clip_paths.push(clip_path);
         self.epoch.next();
@@ -122,6 +157,104 @@ impl Scene {
         self.display_list.push(DisplayItem::PopRenderTarget);
     }
 
+    /// Opens a layer. Subsequent draw paths are drawn into the layer until `pop_layer()` is
+    /// called, at which point the layer is composited onto whatever is below it.
+    ///
+    /// The contents of a layer are in layer space, where (0, 0) is the top left corner of the
+    /// layer and one unit is one pixel of the layer's texture; the render transform doesn't apply
+    /// to them. Only the layer itself is transformed, by its own transform followed by the render
+    /// transform.
+    ///
+    /// Layers are rasterized once and kept from frame to frame, as long as the scene is built with
+    /// the same `SceneSink` and nothing drawn inside the layer changes. Changing a layer's
+    /// transform, opacity, or clip path with `set_layer_transform()` and friends, or changing the
+    /// render transform, just composites the cached contents again. This makes scrolling and
+    /// zooming large, static content cheap.
+    pub fn push_layer(&mut self, layer: Layer) -> LayerId {
+        let layer_id = LayerId(self.layers.len() as u32);
+        let render_target = RenderTarget::new(layer.size, layer.name.clone());
+        let render_target_id = self.palette.push_render_target(render_target);
+        let composite_paint = layer.composite_paint(render_target_id);
+        let parent = self.layer_stack.last().cloned();
+        let composite_paint_id = self.palette.push_unique_paint(composite_paint, parent.is_some());
+        self.layers.push(LayerInfo {
+            layer,
+            render_target_id,
+            composite_paint_id,
+            composite_path_id: None,
+            parent,
+        });
+        self.layer_stack.push(layer_id);
+        self.display_list.push(DisplayItem::PushLayer(layer_id));
+        self.epoch.next();
+        layer_id
+    }
+
+    /// Closes the most recently opened layer and composites it onto whatever is below it.
+    ///
+    /// The layer is drawn with a draw path covering its transformed bounds. That path is returned
+    /// and is clipped by the layer's clip path, if it has one. If no layer is open, this does
+    /// nothing and returns `None`.
+    ///
+    /// Layers that are still open when the scene is built are closed then.
+    pub fn pop_layer(&mut self) -> Option<DrawPathId> {
+        let layer_id = self.layer_stack.pop()?;
+        self.display_list.push(DisplayItem::PopLayer);
+
+        let layer_info = &self.layers[layer_id.0 as usize];
+        let composite_path = layer_info.layer.composite_path(layer_info.composite_paint_id);
+        let composite_path_id = DrawPathId(self.draw_paths.len() as u32);
+        self.draw_paths.push(composite_path);
+        self.push_draw_path_with_index(composite_path_id);
+        self.layers[layer_id.0 as usize].composite_path_id = Some(composite_path_id);
+        Some(composite_path_id)
+    }
+
+    /// Returns the layer with the given ID.
+    #[inline]
+    pub fn get_layer(&self, layer_id: LayerId) -> &Layer {
+        &self.layers[layer_id.0 as usize].layer
+    }
+
+    /// Changes the transform that a layer is composited with.
+    ///
+    /// This doesn't cause the layer's contents to be drawn again.
+    pub fn set_layer_transform(&mut self, layer_id: LayerId, new_transform: Transform2F) {
+        self.layers[layer_id.0 as usize].layer.transform = new_transform;
+        self.update_layer_composite(layer_id);
+    }
+
+    /// Changes the opacity that a layer is composited with.
+    ///
+    /// This doesn't cause the layer's contents to be drawn again.
+    pub fn set_layer_opacity(&mut self, layer_id: LayerId, new_opacity: f32) {
+        self.layers[layer_id.0 as usize].layer.opacity = new_opacity;
+        self.update_layer_composite(layer_id);
+    }
+
+    /// Changes the clip path that a layer is composited with.
+    ///
+    /// This doesn't cause the layer's contents to be drawn again.
+    pub fn set_layer_clip_path(&mut self, layer_id: LayerId, new_clip_path: Option<ClipPathId>) {
+        self.layers[layer_id.0 as usize].layer.clip_path = new_clip_path;
+        self.update_layer_composite(layer_id);
+    }
+
+    // Rewrites the paint and the draw path that composite the layer to match its properties.
+    fn update_layer_composite(&mut self, layer_id: LayerId) {
+        let layer_info = &self.layers[layer_id.0 as usize];
+        let composite_paint = layer_info.layer.composite_paint(layer_info.render_target_id);
+        self.palette.replace_paint(layer_info.composite_paint_id, composite_paint);
+        if let Some(composite_path_id) = layer_info.composite_path_id {
+            let composite_path = layer_info.layer.composite_path(layer_info.composite_paint_id);
+            if layer_info.parent.is_none() {
+                self.bounds = self.bounds.union_rect(composite_path.outline.bounds());
+            }
+            self.draw_paths[composite_path_id.0 as usize] = composite_path;
+        }
+        self.epoch.next();
+    }
+
     /// Adds all elements in a scene to this one.
     ///
     /// This includes draw paths, clip paths, render targets, and paints.
@@ -134,26 +267,58 @@ impl Scene {
         // Merge clip paths.
         let mut clip_path_mapping = Vec::with_capacity(scene.clip_paths.len());
         for clip_path in scene.clip_paths {
+            self.has_conics |= clip_path.outline.has_conics();
             clip_path_mapping.push(self.clip_paths.len());
             self.clip_paths.push(clip_path);
         }
 
-        // Merge draw paths.
+        // Merge draw paths. Paths in layers of the appended scene stay in layer space. If a layer
+        // is open in this scene, the appended scene goes into it.
         let mut draw_path_mapping = Vec::with_capacity(scene.draw_paths.len());
         for draw_path in scene.draw_paths {
             draw_path_mapping.push(self.draw_paths.len() as u32);
+            let mut paint = paint_mapping[&draw_path.paint];
+            if !self.layer_stack.is_empty() {
+                paint = self.palette.layer_paint(paint);
+            }
             self.draw_paths.push(DrawPath {
                 outline: draw_path.outline,
-                paint: paint_mapping[&draw_path.paint],
+                paint,
                 clip_path: draw_path.clip_path.map(|clip_path_id| {
                     ClipPathId(clip_path_mapping[clip_path_id.0 as usize] as u32)
                 }),
                 fill_rule: draw_path.fill_rule,
                 blend_mode: draw_path.blend_mode,
+                device_stroke: draw_path.device_stroke,
                 name: draw_path.name,
             });
         }
 
+        // Merge layers. Each gets a paint of its own, so that it can be changed independently.
+        let first_layer_index = self.layers.len() as u32;
+        for layer_info in scene.layers {
+            let parent = match layer_info.parent {
+                Some(parent) => Some(LayerId(first_layer_index + parent.0)),
+                None => self.layer_stack.last().cloned(),
+            };
+            let render_target_id = render_target_mapping[&layer_info.render_target_id];
+            let composite_paint = layer_info.layer.composite_paint(render_target_id);
+            let composite_paint_id = self.palette.push_unique_paint(composite_paint,
+                                                                    parent.is_some());
+            let composite_path_id = layer_info.composite_path_id.map(|old_path_id| {
+                let new_path_id = draw_path_mapping[old_path_id.0 as usize];
+                self.draw_paths[new_path_id as usize].paint = composite_paint_id;
+                DrawPathId(new_path_id)
+            });
+            self.layers.push(LayerInfo {
+                layer: layer_info.layer,
+                render_target_id,
+                composite_paint_id,
+                composite_path_id,
+                parent,
+            });
+        }
+
         // Merge display items.
         for display_item in scene.display_list {
             match display_item {
@@ -164,6 +329,15 @@ impl Scene {
                 DisplayItem::PopRenderTarget => {
                     self.display_list.push(DisplayItem::PopRenderTarget);
                 }
+                DisplayItem::PushLayer(old_layer_id) => {
+                    let new_layer_id = LayerId(first_layer_index + old_layer_id.0);
+                    self.layer_stack.push(new_layer_id);
+                    self.display_list.push(DisplayItem::PushLayer(new_layer_id));
+                }
+                DisplayItem::PopLayer => {
+                    self.layer_stack.pop();
+                    self.display_list.push(DisplayItem::PopLayer);
+                }
                 DisplayItem::DrawPaths(range) => {
                     for old_path_index in (range.start.0 as usize)..(range.end.0 as usize) {
                         let old_draw_path_id = DrawPathId(draw_path_mapping[old_path_index]);
@@ -178,11 +352,21 @@ impl Scene {
     }
 
     #[inline]
-    pub(crate) fn build_paint_info(&mut self,
-                                   texture_manager: &mut PaintTextureManager,
-                                   render_transform: Transform2F)
-                                   -> PaintInfo {
-        self.palette.build_paint_info(texture_manager, render_transform)
+    pub(crate) fn build_paint_info<E>(&mut self,
+                                      executor: &E,
+                                      texture_manager: &mut PaintTextureManager,
+                                      render_transform: Transform2F,
+                                      color_space: ColorSpace,
+                                      max_gradient_ramp_length: u32,
+                                      render_target_cache_keys: &[Option<u64>])
+                                      -> PaintInfo
+                                      where E: Executor {
+        self.palette.build_paint_info(executor,
+                                      texture_manager,
+                                      render_transform,
+                                      color_space,
+                                      max_gradient_ramp_length,
+                                      render_target_cache_keys)
     }
 
     /// Defines a new paint, which specifies how paths are to be filled or stroked. Returns a paint
@@ -224,9 +408,12 @@ impl Scene {
         self.epoch.next();
     }
 
+    // If `device_stroke` is present, `original_outline` is treated as the centerline of a stroke,
+    // which is converted to a fill after the render transform has been applied.
     #[allow(deprecated)]
     pub(crate) fn apply_render_options(&self,
                                        original_outline: &Outline,
+                                       device_stroke: Option<&DeviceStroke>,
                                        options: &PreparedBuildOptions)
                                        -> Outline {
         let mut outline;
@@ -240,9 +427,14 @@ impl Scene {
                     outline = Outline::new();
                 } else {
                     outline = (*original_outline).clone();
-                    outline.close_all_contours();
+                    if device_stroke.is_none() {
+                        outline.close_all_contours();
+                    }
                     outline.clip_against_polygon(clip_polygon);
                     outline.apply_perspective(perspective);
+                    if let Some(device_stroke) = device_stroke {
+                        outline = device_stroke.stroke(&outline);
+                    }
 
                     // TODO(pcwalton): Support subpixel AA in 3D.
                 }
@@ -250,17 +442,33 @@ impl Scene {
             _ => {
                 // TODO(pcwalton): Short circuit.
                 outline = (*original_outline).clone();
-                outline.close_all_contours();
-                if options.transform.is_2d() || options.subpixel_aa_enabled {
-                    let mut transform = match options.transform {
-                        PreparedRenderTransform::Transform2D(transform) => transform,
-                        PreparedRenderTransform::None => Transform2F::default(),
-                        PreparedRenderTransform::Perspective { .. } => unreachable!(),
-                    };
-                    if options.subpixel_aa_enabled {
-                        transform *= Transform2F::from_scale(vec2f(3.0, 1.0))
+                let mut transform = match options.transform {
+                    PreparedRenderTransform::Transform2D(transform) => transform,
+                    PreparedRenderTransform::None => Transform2F::default(),
+                    PreparedRenderTransform::Perspective { .. } => unreachable!(),
+                };
+                let subpixel_scale = Transform2F::from_scale(vec2f(3.0, 1.0));
+                match device_stroke {
+                    None => {
+                        outline.close_all_contours();
+                        if options.transform.is_2d() || options.subpixel_aa_enabled {
+                            if options.subpixel_aa_enabled {
+                                transform *= subpixel_scale
+                            }
+                            outline.transform(&transform);
+                        }
+                    }
+                    Some(device_stroke) => {
+                        // Stroke before applying the subpixel scale so that the stroke width
+                        // isn't stretched horizontally.
+                        if options.transform.is_2d() {
+                            outline.transform(&transform);
+                        }
+                        outline = device_stroke.stroke(&outline);
+                        if options.subpixel_aa_enabled {
+                            outline.transform(&(transform * subpixel_scale * transform.inverse()));
+                        }
                     }
-                    outline.transform(&transform);
                 }
             }
         }
@@ -292,7 +500,8 @@ impl Scene {
                             sink: &'b mut SceneSink<'a>,
                             executor: &E)
                             where E: Executor {
-        let prepared_options = options.prepare(self.bounds);
+        while self.pop_layer().is_some() {}
+        let prepared_options = options.prepare(self.bounds, sink.renderer_level);
         SceneBuilder::new(self, &prepared_options, sink).build(executor)
     }
 
@@ -311,6 +520,11 @@ impl Scene {
         &self.clip_paths
     }
 
+    #[inline]
+    pub(crate) fn layers(&self) -> &[LayerInfo] {
+        &self.layers
+    }
+
     /// Returns the number of draw paths in this scene.
     #[inline]
     pub fn draw_path_count(&self) -> u32 {
@@ -346,6 +560,20 @@ impl Scene {
         self.epoch
     }
 
+    /// Returns true if any draw path in this scene has a device stroke, in which case the geometry
+    /// depends on the render transform.
+    #[inline]
+    pub(crate) fn has_device_strokes(&self) -> bool {
+        self.has_device_strokes
+    }
+
+    /// Returns true if any path in this scene contains a conic section, in which case the
+    /// segments sent to the GPU depend on the scale of the render transform.
+    #[inline]
+    pub(crate) fn has_conics(&self) -> bool {
+        self.has_conics
+    }
+
     /// A convenience method to build a scene and accumulate commands into a vector.
     pub fn build_into_vector<D, E>(&mut self,
                                    renderer: &mut Renderer<D>,
@@ -386,6 +614,7 @@ pub struct SceneSink<'a> {
     pub(crate) renderer_level: RendererLevel,
     pub(crate) last_scene: Option<LastSceneInfo>,
     pub(crate) paint_texture_manager: PaintTextureManager,
+    pub(crate) tile_cache: Option<TileCache>,
 }
 
 pub(crate) struct LastSceneInfo {
@@ -393,6 +622,10 @@ pub(crate) struct LastSceneInfo {
     pub(crate) scene_epoch: SceneEpoch,
     pub(crate) draw_segment_ranges: Vec<Range<u32>>,
     pub(crate) clip_segment_ranges: Vec<Range<u32>>,
+    // The transform that device strokes were built with, if the scene has any.
+    pub(crate) device_stroke_transform: Option<Transform2F>,
+    // The tolerance, in scene units, that conics were approximated with, if the scene has any.
+    pub(crate) conic_tolerance: Option<f32>,
 }
 
 #[derive(Clone, Copy, PartialEq, Debug)]
@@ -432,8 +665,19 @@ impl<'a> SceneSink<'a> {
             renderer_level,
             last_scene: None,
             paint_texture_manager: PaintTextureManager::new(),
+            tile_cache: None,
         }
     }
+
+    /// Forgets which tiles were drawn in the previous frame, so that the next frame built with
+    /// `BuildOptions::tile_cache` enabled redraws everything.
+    ///
+    /// Call this whenever the renderer's output changes in a way the scene doesn't know about:
+    /// for example, if the destination framebuffer is resized or the background color changes.
+    #[inline]
+    pub fn invalidate_tile_cache(&mut self) {
+        self.tile_cache = None;
+    }
 }
 
 /// A path drawn to the output or to a render target.
@@ -449,12 +693,34 @@ pub struct DrawPath {
     pub fill_rule: FillRule,
     /// How to blend this path with everything below it.
     pub blend_mode: BlendMode,
+    /// If present, `outline` is the centerline of a stroke that will be converted to a fill at
+    /// build time, after the render transform has been applied.
+    ///
+    /// See `DeviceStroke` for details.
+    pub device_stroke: Option<DeviceStroke>,
     /// The name of this path, for debugging.
     ///
     /// Pass the empty string (which does not allocate) if debugging is not needed.
     pub name: String,
 }
 
+/// A stroke whose dimensions are measured in device pixels rather than scene units.
+///
+/// Strokes attached to draw paths this way don't scale with the transform of the scene or with
+/// the render transform, which makes them suitable for SVG `vector-effect="non-scaling-stroke"`
+/// and for hairlines that stay exactly one device pixel wide at every zoom level. Because the
+/// stroke is computed late, it is recomputed whenever the render transform changes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DeviceStroke {
+    /// How the stroke should be drawn. The line width is in device pixels.
+    pub style: StrokeStyle,
+    /// Alternating lengths of dashes and gaps, in device pixels. If this is empty, the stroke is
+    /// solid.
+    pub dashes: Vec<f32>,
+    /// The offset into the dash pattern at which to start, in device pixels.
+    pub dash_offset: f32,
+}
+
 /// Describes a path that can be used to clip other paths.
 #[derive(Clone, Debug)]
 pub struct ClipPath {
@@ -493,6 +759,35 @@ pub struct PathId(pub u32);
 pub struct RenderTarget {
     size: Vector2I,
     name: String,
+    format: TextureFormat,
+}
+
+/// A group of paths that is rasterized once into a texture of its own and then composited with
+/// a transform, opacity, and clip that can change every frame.
+///
+/// See `Scene::push_layer()`.
+#[derive(Clone, Debug)]
+pub struct Layer {
+    size: Vector2I,
+    transform: Transform2F,
+    opacity: f32,
+    clip_path: Option<ClipPathId>,
+    name: String,
+}
+
+/// The ID of a layer, unique to a single scene.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct LayerId(pub u32);
+
+#[derive(Clone, Debug)]
+pub(crate) struct LayerInfo {
+    pub(crate) layer: Layer,
+    pub(crate) render_target_id: RenderTargetId,
+    composite_paint_id: PaintId,
+    // Set once the layer has been popped.
+    composite_path_id: Option<DrawPathId>,
+    // The layer that this layer is drawn into, if any.
+    pub(crate) parent: Option<LayerId>,
 }
 
 /// High-level drawing commands.
@@ -506,6 +801,13 @@ pub enum DisplayItem {
 
     /// Pops a render target from the stack.
     PopRenderTarget,
+
+    /// Starts drawing into a layer.
+    PushLayer(LayerId),
+
+    /// Stops drawing into the most recently pushed layer. The path that composites the layer
+    /// follows in a `DrawPaths` item.
+    PopLayer,
 }
 
 impl DrawPath {
@@ -521,6 +823,7 @@ impl DrawPath {
             clip_path: None,
             fill_rule: FillRule::Winding,
             blend_mode: BlendMode::SrcOver,
+            device_stroke: None,
             name: String::new(),
         }
     }
@@ -573,6 +876,21 @@ impl DrawPath {
         self.blend_mode = new_blend_mode
     }
 
+    /// Returns the device stroke applied to this path, if any.
+    #[inline]
+    pub fn device_stroke(&self) -> Option<&DeviceStroke> {
+        self.device_stroke.as_ref()
+    }
+
+    /// Treats the outline of this path as the centerline of a stroke with dimensions in device
+    /// pixels, or as an ordinary fill if `None` is passed.
+    ///
+    /// The fill rule should be left as winding for stroked paths.
+    #[inline]
+    pub fn set_device_stroke(&mut self, new_device_stroke: Option<DeviceStroke>) {
+        self.device_stroke = new_device_stroke
+    }
+
     /// Assigns a name to this path, for debugging.
     #[inline]
     pub fn set_name(&mut self, new_name: String) {
@@ -580,6 +898,49 @@ impl DrawPath {
     }
 }
 
+impl DeviceStroke {
+    /// Creates a new solid device stroke with the given style.
+    #[inline]
+    pub fn new(style: StrokeStyle) -> DeviceStroke {
+        DeviceStroke { style, dashes: vec![], dash_offset: 0.0 }
+    }
+
+    /// Creates a solid stroke exactly one device pixel wide.
+    #[inline]
+    pub fn hairline() -> DeviceStroke {
+        DeviceStroke::new(StrokeStyle { line_width: 1.0, ..StrokeStyle::default() })
+    }
+
+    // Dashes and strokes the given device-space centerline.
+    pub(crate) fn stroke(&self, centerline: &Outline) -> Outline {
+        let dashed;
+        let mut outline = centerline;
+        if !self.dashes.is_empty() {
+            let mut dash = OutlineDash::new(centerline, &self.dashes, self.dash_offset);
+            dash.dash();
+            dashed = dash.into_outline();
+            outline = &dashed;
+        }
+
+        let mut stroke_to_fill = OutlineStrokeToFill::new(outline, self.style);
+        stroke_to_fill.offset();
+        stroke_to_fill.into_outline()
+    }
+
+    /// Returns the maximum distance, in device pixels, that the stroke can extend from its
+    /// centerline.
+    pub fn max_extent(&self) -> f32 {
+        let mut extent: f32 = 1.0;
+        if let LineJoin::Miter(miter_limit) = self.style.line_join {
+            extent = extent.max(miter_limit);
+        }
+        if self.style.line_cap == LineCap::Square {
+            extent = extent.max(SQRT_2);
+        }
+        self.style.line_width * 0.5 * extent
+    }
+}
+
 impl ClipPath {
     /// Creates a new clip path with the given outline.
     ///
@@ -635,7 +996,18 @@ impl RenderTarget {
     /// Pass the empty string (which does not allocate) if a name is not needed.
     #[inline]
     pub fn new(size: Vector2I, name: String) -> RenderTarget {
-        RenderTarget { size, name }
+        RenderTarget { size, name, format: TextureFormat::RGBA8 }
+    }
+
+    /// Declares a new render target with the given pixel format.
+    ///
+    /// Only `TextureFormat::RGBA8` and `TextureFormat::RGBA16F` are supported. The latter keeps
+    /// colors outside the [0, 1] range and the extra precision when the render target is later
+    /// used as a pattern.
+    #[inline]
+    pub fn with_format(size: Vector2I, name: String, format: TextureFormat) -> RenderTarget {
+        debug_assert!(format == TextureFormat::RGBA8 || format == TextureFormat::RGBA16F);
+        RenderTarget { size, name, format }
     }
 
     /// Returns the device pixel size of this render target.
@@ -643,6 +1015,94 @@ impl RenderTarget {
     pub fn size(&self) -> Vector2I {
         self.size
     }
+
+    /// Returns the pixel format of this render target.
+    #[inline]
+    pub fn format(&self) -> TextureFormat {
+        self.format
+    }
+}
+
+impl Layer {
+    /// Creates a new layer whose texture has the given size in device pixels.
+    ///
+    /// Initially, the layer has the identity transform, full opacity, no clip path, and no name.
+    #[inline]
+    pub fn new(size: Vector2I) -> Layer {
+        Layer {
+            size,
+            transform: Transform2F::default(),
+            opacity: 1.0,
+            clip_path: None,
+            name: String::new(),
+        }
+    }
+
+    /// Returns the size of this layer's texture in device pixels.
+    #[inline]
+    pub fn size(&self) -> Vector2I {
+        self.size
+    }
+
+    /// Returns the transform from layer space to scene space.
+    #[inline]
+    pub fn transform(&self) -> Transform2F {
+        self.transform
+    }
+
+    /// Sets the transform from layer space to scene space.
+    #[inline]
+    pub fn set_transform(&mut self, new_transform: Transform2F) {
+        self.transform = new_transform;
+    }
+
+    /// Returns the opacity that this layer is composited with.
+    #[inline]
+    pub fn opacity(&self) -> f32 {
+        self.opacity
+    }
+
+    /// Sets the opacity that this layer is composited with, from 0.0 to 1.0.
+    #[inline]
+    pub fn set_opacity(&mut self, new_opacity: f32) {
+        self.opacity = new_opacity;
+    }
+
+    /// Returns the clip path, in scene space, that this layer is composited with.
+    #[inline]
+    pub fn clip_path(&self) -> Option<ClipPathId> {
+        self.clip_path
+    }
+
+    /// Sets the clip path, in scene space, that this layer is composited with.
+    #[inline]
+    pub fn set_clip_path(&mut self, new_clip_path: Option<ClipPathId>) {
+        self.clip_path = new_clip_path;
+    }
+
+    /// Sets the name of this layer, for debugging.
+    #[inline]
+    pub fn set_name(&mut self, new_name: String) {
+        self.name = new_name;
+    }
+
+    fn composite_paint(&self, render_target_id: RenderTargetId) -> Paint {
+        let mut pattern = Pattern::from_render_target(render_target_id, self.size);
+        pattern.apply_transform(self.transform);
+        pattern.set_smoothing_enabled(true);
+        let mut paint = Paint::from_pattern(pattern);
+        paint.set_base_color_f(ColorF::new(1.0, 1.0, 1.0, self.opacity));
+        paint
+    }
+
+    fn composite_path(&self, composite_paint_id: PaintId) -> DrawPath {
+        let mut outline = Outline::from_rect(RectF::new(Vector2F::zero(), self.size.to_f32()));
+        outline.transform(&self.transform);
+        let mut draw_path = DrawPath::new(outline, composite_paint_id);
+        draw_path.set_clip_path(self.clip_path);
+        draw_path.set_name(self.name.clone());
+        draw_path
+    }
 }
 
 impl DrawPathId {
@@ -0,0 +1,418 @@
+// pathfinder/renderer/src/tile_cache.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Remembers what went into each tile of the previous frame so that tiles whose inputs haven't
+//! changed can be reused instead of redrawn.
+//!
+//! Every draw path is hashed from its transformed outline, paint, fill rule, blend mode, and clip
+//! path. Each tile's hash then combines, in drawing order, the hashes of all paths whose bounds
+//! touch it. A tile whose hash matches the one from the last frame must look exactly the same, so
+//! the builder doesn't send it and the renderer keeps its old contents.
+
+use crate::gpu::options::RendererLevel;
+use crate::gpu_data::DirtyTilesD3D9;
+use crate::options::{PreparedBuildOptions, PreparedRenderTransform};
+use crate::scene::{ClipPathId, DisplayItem, DrawPath, DrawPathId, Scene};
+use crate::tile_map::DenseTileMap;
+use crate::tiles;
+use fxhash::FxHasher;
+use pathfinder_content::outline::Outline;
+use pathfinder_geometry::rect::RectI;
+use pathfinder_geometry::vector::{Vector2I, vec2i};
+use std::hash::{Hash, Hasher};
+use std::mem;
+
+/// Per-tile hashes of the last frame that was built with the tile cache.
+pub(crate) struct TileCache {
+    key: TileCacheKey,
+    tile_hashes: DenseTileMap<u64>,
+}
+
+// Inputs that affect every tile. If any of these change, the whole frame is redrawn.
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct TileCacheKey {
+    tile_rect: RectI,
+    options_hash: u64,
+}
+
+/// Which tiles of the frame being built must be redrawn.
+pub(crate) struct TileInvalidation {
+    dirty_tiles: DenseTileMap<bool>,
+    dirty_tile_count: u32,
+}
+
+impl TileInvalidation {
+    /// Returns true if the tile cache can be used for this scene with these options.
+    ///
+    /// Scenes that render to render targets or layers, perspective transforms, and subpixel
+    /// antialiasing always redraw everything.
+    pub(crate) fn is_supported(scene: &Scene,
+                               built_options: &PreparedBuildOptions,
+                               renderer_level: RendererLevel)
+                               -> bool {
+        if !built_options.tile_cache || renderer_level != RendererLevel::D3D9 ||
+                built_options.subpixel_aa_enabled {
+            return false;
+        }
+        if let PreparedRenderTransform::Perspective { .. } = built_options.transform {
+            return false;
+        }
+        scene.display_list().iter().all(|display_item| {
+            match *display_item {
+                DisplayItem::DrawPaths(_) => true,
+                DisplayItem::PushRenderTarget(_) | DisplayItem::PopRenderTarget |
+                DisplayItem::PushLayer(_) | DisplayItem::PopLayer => false,
+            }
+        })
+    }
+
+    /// Hashes the inputs of every tile, compares them against the last frame, and replaces the
+    /// cache with the new hashes.
+    ///
+    /// `draw_outlines` are the outlines of the draw paths with the render options already applied.
+    pub(crate) fn new(scene: &Scene,
+                      built_options: &PreparedBuildOptions,
+                      draw_outlines: &[Outline],
+                      cache: &mut Option<TileCache>)
+                      -> TileInvalidation {
+        let view_box = scene.effective_view_box(built_options);
+        let tile_size = built_options.tile_size;
+        let tile_rect = tiles::round_rect_out_to_tile_bounds(view_box, tile_size);
+        let key = TileCacheKey { tile_rect, options_hash: hash_options(built_options) };
+
+        let clip_hashes = hash_clip_paths(scene, built_options);
+
+        let mut tile_hashes = DenseTileMap::from_builder(|_| 0, tile_rect);
+        for (draw_path_index, outline) in draw_outlines.iter().enumerate() {
+            let draw_path = scene.get_draw_path(DrawPathId(draw_path_index as u32));
+
+            // Destructive blend modes affect the whole view box, not just the outline.
+            let path_tile_rect = if draw_path.blend_mode().is_destructive() {
+                tile_rect
+            } else {
+                match tiles::round_rect_out_to_tile_bounds(outline.bounds(), tile_size)
+                            .intersection(tile_rect) {
+                    None => continue,
+                    Some(path_tile_rect) => path_tile_rect,
+                }
+            };
+
+            let path_hash = hash_draw_path(scene, draw_path, outline, &clip_hashes);
+
+            for y in path_tile_rect.min_y()..path_tile_rect.max_y() {
+                for x in path_tile_rect.min_x()..path_tile_rect.max_x() {
+                    let index = tile_hashes.coords_to_index_unchecked(vec2i(x, y));
+                    let mut hasher = FxHasher::default();
+                    hasher.write_u64(tile_hashes.data[index]);
+                    hasher.write_u64(path_hash);
+                    tile_hashes.data[index] = hasher.finish();
+                }
+            }
+        }
+
+        let dirty_tiles = match *cache {
+            Some(ref cache) if cache.key == key => {
+                DenseTileMap::from_builder(|coords| {
+                    tile_hashes.get(coords) != cache.tile_hashes.get(coords)
+                }, tile_rect)
+            }
+            _ => DenseTileMap::from_builder(|_| true, tile_rect),
+        };
+        let dirty_tile_count = dirty_tiles.data.iter().filter(|&&dirty| dirty).count() as u32;
+
+        *cache = Some(TileCache { key, tile_hashes });
+        TileInvalidation { dirty_tiles, dirty_tile_count }
+    }
+
+    /// Returns true if the tile at the given coordinates must be redrawn.
+    #[inline]
+    pub(crate) fn is_dirty(&self, coords: Vector2I) -> bool {
+        self.dirty_tiles.get(coords).cloned().unwrap_or(false)
+    }
+
+    /// Returns true if any tile within the given rectangle must be redrawn.
+    pub(crate) fn any_dirty_in(&self, tile_rect: RectI) -> bool {
+        let tile_rect = match tile_rect.intersection(self.dirty_tiles.rect) {
+            None => return false,
+            Some(tile_rect) => tile_rect,
+        };
+        for y in tile_rect.min_y()..tile_rect.max_y() {
+            for x in tile_rect.min_x()..tile_rect.max_x() {
+                if self.dirty_tiles.data[self.dirty_tiles.coords_to_index_unchecked(vec2i(x, y))] {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    /// Packages up the dirty tiles for the renderer.
+    ///
+    /// Runs of dirty tiles are merged horizontally, and runs with the same extent in consecutive
+    /// rows are merged vertically, so that a fully-dirty frame becomes a single rectangle.
+    pub(crate) fn to_dirty_tiles(&self) -> DirtyTilesD3D9 {
+        let tile_rect = self.dirty_tiles.rect;
+        let mut rects: Vec<RectI> = vec![];
+
+        // Indices of the rectangles that reach down to the previous row.
+        let mut open_rect_indices: Vec<usize> = vec![];
+        for y in tile_rect.min_y()..tile_rect.max_y() {
+            let mut next_open_rect_indices = vec![];
+            let mut x = tile_rect.min_x();
+            while x < tile_rect.max_x() {
+                if !self.is_dirty(vec2i(x, y)) {
+                    x += 1;
+                    continue;
+                }
+                let start_x = x;
+                while x < tile_rect.max_x() && self.is_dirty(vec2i(x, y)) {
+                    x += 1;
+                }
+
+                let open_rect_index = open_rect_indices.iter().cloned().find(|&index| {
+                    rects[index].min_x() == start_x && rects[index].max_x() == x
+                });
+                match open_rect_index {
+                    Some(index) => {
+                        rects[index] = RectI::from_points(rects[index].origin(), vec2i(x, y + 1));
+                        next_open_rect_indices.push(index);
+                    }
+                    None => {
+                        next_open_rect_indices.push(rects.len());
+                        rects.push(RectI::from_points(vec2i(start_x, y), vec2i(x, y + 1)));
+                    }
+                }
+            }
+            open_rect_indices = next_open_rect_indices;
+        }
+
+        let total_tile_count = self.dirty_tiles.data.len() as u32;
+        DirtyTilesD3D9 {
+            rects,
+            dirty_tile_count: self.dirty_tile_count,
+            reused_tile_count: total_tile_count - self.dirty_tile_count,
+        }
+    }
+}
+
+pub(crate) fn hash_options(built_options: &PreparedBuildOptions) -> u64 {
+    let mut hasher = FxHasher::default();
+    match built_options.transform {
+        PreparedRenderTransform::Transform2D(transform) => {
+            let translation = transform.translation();
+            for value in &[transform.m11(), transform.m12(), transform.m21(), transform.m22(),
+                           translation.x(), translation.y()] {
+                value.to_bits().hash(&mut hasher);
+            }
+        }
+        PreparedRenderTransform::None | PreparedRenderTransform::Perspective { .. } => {}
+    }
+    built_options.dilation.x().to_bits().hash(&mut hasher);
+    built_options.dilation.y().to_bits().hash(&mut hasher);
+    mem::discriminant(&built_options.color_space).hash(&mut hasher);
+    built_options.tile_size.hash(&mut hasher);
+    built_options.flattening_tolerance.to_bits().hash(&mut hasher);
+    hasher.finish()
+}
+
+// Hashes each clip path, including the clip paths it is itself clipped by.
+pub(crate) fn hash_clip_paths(scene: &Scene, built_options: &PreparedBuildOptions) -> Vec<u64> {
+    let clip_path_count = scene.clip_paths().len();
+    let mut clip_hashes: Vec<Option<u64>> = vec![None; clip_path_count];
+    for clip_path_index in 0..clip_path_count {
+        hash_clip_path(scene, built_options, ClipPathId(clip_path_index as u32), &mut clip_hashes);
+    }
+    clip_hashes.into_iter().map(|clip_hash| clip_hash.unwrap()).collect()
+}
+
+fn hash_clip_path(scene: &Scene,
+                  built_options: &PreparedBuildOptions,
+                  clip_path_id: ClipPathId,
+                  clip_hashes: &mut [Option<u64>])
+                  -> u64 {
+    if let Some(clip_hash) = clip_hashes[clip_path_id.0 as usize] {
+        return clip_hash;
+    }
+
+    let clip_path = scene.get_clip_path(clip_path_id);
+    let outline = scene.apply_render_options(clip_path.outline(), None, built_options);
+
+    let mut hasher = FxHasher::default();
+    hash_outline(&outline, &mut hasher);
+    mem::discriminant(&clip_path.fill_rule()).hash(&mut hasher);
+    if let Some(subclip_path_id) = clip_path.clip_path() {
+        hash_clip_path(scene, built_options, subclip_path_id, clip_hashes).hash(&mut hasher);
+    }
+
+    let clip_hash = hasher.finish();
+    clip_hashes[clip_path_id.0 as usize] = Some(clip_hash);
+    clip_hash
+}
+
+// Hashes everything about a draw path that affects the pixels it covers. `outline` is the outline
+// of the draw path with the render options already applied.
+pub(crate) fn hash_draw_path(scene: &Scene,
+                             draw_path: &DrawPath,
+                             outline: &Outline,
+                             clip_hashes: &[u64])
+                             -> u64 {
+    let mut hasher = FxHasher::default();
+    hash_outline(outline, &mut hasher);
+    scene.get_paint(draw_path.paint()).hash(&mut hasher);
+    mem::discriminant(&draw_path.fill_rule()).hash(&mut hasher);
+    mem::discriminant(&draw_path.blend_mode()).hash(&mut hasher);
+    draw_path.clip_path().map(|clip_path_id| clip_hashes[clip_path_id.0 as usize])
+                         .hash(&mut hasher);
+    hasher.finish()
+}
+
+fn hash_outline<H>(outline: &Outline, hasher: &mut H) where H: Hasher {
+    for contour in outline.contours() {
+        contour.len().hash(hasher);
+        contour.is_closed().hash(hasher);
+        for point_index in 0..contour.len() {
+            let position = contour.position_of(point_index);
+            position.x().to_bits().hash(hasher);
+            position.y().to_bits().hash(hasher);
+            contour.flags_of(point_index).bits().hash(hasher);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{TileCache, TileInvalidation};
+    use crate::gpu::options::RendererLevel;
+    use crate::options::{BuildOptions, RenderTransform};
+    use crate::paint::Paint;
+    use crate::scene::{DrawPath, Layer, Scene};
+    use pathfinder_color::ColorU;
+    use pathfinder_content::outline::Outline;
+    use pathfinder_geometry::rect::{RectF, RectI};
+    use pathfinder_geometry::transform2d::Transform2F;
+    use pathfinder_geometry::vector::{Vector2F, vec2f, vec2i};
+
+    // A 64×64 scene, which is 4×4 tiles of the default size.
+    fn new_scene(rects: &[(RectF, ColorU)]) -> Scene {
+        let mut scene = Scene::new();
+        scene.set_view_box(RectF::new(Vector2F::zero(), vec2f(64.0, 64.0)));
+        for &(rect, color) in rects {
+            let paint = scene.push_paint(&Paint::from_color(color));
+            scene.push_draw_path(DrawPath::new(Outline::from_rect(rect), paint));
+        }
+        scene
+    }
+
+    fn invalidate(scene: &Scene, build_options: BuildOptions, cache: &mut Option<TileCache>)
+                  -> TileInvalidation {
+        let built_options = build_options.prepare(scene.bounds(), RendererLevel::D3D9);
+        let draw_outlines: Vec<Outline> = scene.draw_paths().iter().map(|draw_path| {
+            scene.apply_render_options(draw_path.outline(), None, &built_options)
+        }).collect();
+        TileInvalidation::new(scene, &built_options, &draw_outlines, cache)
+    }
+
+    fn square(origin: Vector2F) -> RectF {
+        RectF::new(origin, vec2f(8.0, 8.0))
+    }
+
+    #[test]
+    fn unchanged_frames_reuse_every_tile() {
+        let scene = new_scene(&[(square(vec2f(4.0, 4.0)), ColorU::black())]);
+        let mut cache = None;
+
+        // Without a cache, everything is drawn, as one rect.
+        let dirty_tiles = invalidate(&scene, BuildOptions::default(), &mut cache).to_dirty_tiles();
+        assert_eq!(dirty_tiles.rects, vec![RectI::new(vec2i(0, 0), vec2i(4, 4))]);
+        assert_eq!((dirty_tiles.dirty_tile_count, dirty_tiles.reused_tile_count), (16, 0));
+
+        let dirty_tiles = invalidate(&scene, BuildOptions::default(), &mut cache).to_dirty_tiles();
+        assert!(dirty_tiles.rects.is_empty());
+        assert_eq!((dirty_tiles.dirty_tile_count, dirty_tiles.reused_tile_count), (0, 16));
+    }
+
+    #[test]
+    fn only_tiles_touched_by_changed_paths_are_dirty() {
+        let mut cache = None;
+        let scene = new_scene(&[(square(vec2f(4.0, 4.0)), ColorU::black()),
+                                (square(vec2f(36.0, 36.0)), ColorU::black())]);
+        invalidate(&scene, BuildOptions::default(), &mut cache);
+
+        // Move the first square one tile to the right.
+        let scene = new_scene(&[(square(vec2f(20.0, 4.0)), ColorU::black()),
+                                (square(vec2f(36.0, 36.0)), ColorU::black())]);
+        let invalidation = invalidate(&scene, BuildOptions::default(), &mut cache);
+        assert!(invalidation.is_dirty(vec2i(0, 0)) && invalidation.is_dirty(vec2i(1, 0)));
+        assert!(!invalidation.is_dirty(vec2i(2, 2)));
+        assert!(invalidation.any_dirty_in(RectI::new(vec2i(1, 0), vec2i(3, 3))));
+        assert!(!invalidation.any_dirty_in(RectI::new(vec2i(0, 1), vec2i(4, 3))));
+        assert_eq!(invalidation.to_dirty_tiles().rects,
+                   vec![RectI::new(vec2i(0, 0), vec2i(2, 1))]);
+
+        // Change the color of the second square.
+        let scene = new_scene(&[(square(vec2f(20.0, 4.0)), ColorU::black()),
+                                (square(vec2f(36.0, 36.0)), ColorU::white())]);
+        let dirty_tiles = invalidate(&scene, BuildOptions::default(), &mut cache).to_dirty_tiles();
+        assert_eq!(dirty_tiles.rects, vec![RectI::new(vec2i(2, 2), vec2i(1, 1))]);
+        assert_eq!(dirty_tiles.dirty_tile_count, 1);
+    }
+
+    #[test]
+    fn dirty_columns_merge_into_one_rect() {
+        let mut cache = None;
+        let tall_rect = RectF::new(vec2f(20.0, 4.0), vec2f(8.0, 36.0));
+        let scene = new_scene(&[(tall_rect, ColorU::black())]);
+        invalidate(&scene, BuildOptions::default(), &mut cache);
+        let dirty_tiles =
+            invalidate(&new_scene(&[]), BuildOptions::default(), &mut cache).to_dirty_tiles();
+        assert_eq!(dirty_tiles.rects, vec![RectI::new(vec2i(1, 0), vec2i(1, 3))]);
+        assert_eq!(dirty_tiles.dirty_tile_count, 3);
+    }
+
+    #[test]
+    fn changing_the_cache_key_redraws_everything() {
+        let scene = new_scene(&[(square(vec2f(4.0, 4.0)), ColorU::black())]);
+        let mut cache = None;
+        invalidate(&scene, BuildOptions::default(), &mut cache);
+
+        // Options that affect every tile.
+        let translated = BuildOptions {
+            transform: RenderTransform::Transform2D(Transform2F::from_translation(vec2f(1.0, 0.0))),
+            ..BuildOptions::default()
+        };
+        let invalidation = invalidate(&scene, translated, &mut cache);
+        assert_eq!(invalidation.to_dirty_tiles().dirty_tile_count, 16);
+        let small_tiles = BuildOptions { tile_size: vec2i(8, 8), ..BuildOptions::default() };
+        let invalidation = invalidate(&scene, small_tiles, &mut cache);
+        assert_eq!(invalidation.to_dirty_tiles().dirty_tile_count, 64);
+
+        // The view box.
+        let mut scene = scene;
+        scene.set_view_box(RectF::new(Vector2F::zero(), vec2f(64.0, 48.0)));
+        let invalidation = invalidate(&scene, BuildOptions::default(), &mut cache);
+        assert_eq!(invalidation.to_dirty_tiles().dirty_tile_count, 12);
+    }
+
+    #[test]
+    fn scenes_with_layers_are_not_supported() {
+        let mut scene = new_scene(&[(square(vec2f(4.0, 4.0)), ColorU::black())]);
+        let build_options = BuildOptions { tile_cache: true, ..BuildOptions::default() };
+        let is_supported = |scene: &Scene, renderer_level| {
+            let built_options = build_options.clone().prepare(scene.bounds(), renderer_level);
+            TileInvalidation::is_supported(scene, &built_options, renderer_level)
+        };
+        assert!(is_supported(&scene, RendererLevel::D3D9));
+        assert!(!is_supported(&scene, RendererLevel::D3D11));
+
+        scene.push_layer(Layer::new(vec2i(16, 16)));
+        scene.pop_layer();
+        assert!(!is_supported(&scene, RendererLevel::D3D9));
+    }
+}
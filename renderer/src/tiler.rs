@@ -16,18 +16,17 @@ use crate::gpu::options::RendererLevel;
 use crate::gpu_data::AlphaTileId;
 use crate::options::PrepareMode;
 use crate::scene::{ClipPathId, PathId};
-use crate::tiles::{TILE_HEIGHT, TILE_WIDTH, TilingPathInfo};
+use crate::tiles::TilingPathInfo;
 use pathfinder_content::clip;
 use pathfinder_content::fill::FillRule;
 use pathfinder_content::outline::{ContourIterFlags, Outline};
 use pathfinder_content::segment::Segment;
 use pathfinder_geometry::line_segment::LineSegment2F;
-use pathfinder_geometry::rect::RectF;
+use pathfinder_geometry::rect::{RectF, RectI};
 use pathfinder_geometry::vector::{Vector2F, Vector2I, vec2f, vec2i};
 use pathfinder_simd::default::{F32x2, U32x2};
 use std::f32::NEG_INFINITY;
-
-const FLATTENING_TOLERANCE: f32 = 0.25;
+use std::sync::atomic::Ordering;
 
 pub(crate) struct Tiler<'a, 'b, 'c, 'd> {
     scene_builder: &'a SceneBuilder<'b, 'a, 'c, 'd>,
@@ -57,6 +56,7 @@ impl<'a, 'b, 'c, 'd> Tiler<'a, 'b, 'c, 'd> {
         let object_builder = ObjectBuilder::new(path_id,
                                                 bounds,
                                                 view_box,
+                                                scene_builder.built_options.tile_size,
                                                 fill_rule,
                                                 prepare_mode,
                                                 clip_path_id,
@@ -83,11 +83,51 @@ impl<'a, 'b, 'c, 'd> Tiler<'a, 'b, 'c, 'd> {
     fn generate_fills(&mut self) {
         debug_assert_eq!(self.scene_builder.sink.renderer_level, RendererLevel::D3D9);
 
+        // Only fills inside the tile rect are kept, so contours and segments that lie entirely
+        // to its left, to its right, or below it can be skipped.
+        let tile_size = self.scene_builder.built_options.tile_size;
+        let tile_bounds = self.object_builder.built_path.tile_bounds;
+        let tile_rect = RectI::new(tile_bounds.origin() * tile_size,
+                                   tile_bounds.size() * tile_size).to_f32();
+
+        let mut culled_contour_count = 0;
         for contour in self.outline.contours() {
+            match cull_position(contour.bounds(), tile_rect) {
+                CullPosition::Outside => {
+                    culled_contour_count += 1;
+                    continue;
+                }
+                // A closed contour above the tile rect crosses each column as many times going
+                // left as going right, so it leaves every backdrop unchanged.
+                CullPosition::Above if contour.is_closed() => {
+                    culled_contour_count += 1;
+                    continue;
+                }
+                CullPosition::Above | CullPosition::Inside => {}
+            }
+
             for segment in contour.iter(ContourIterFlags::empty()) {
-                process_segment(&segment, self.scene_builder, &mut self.object_builder);
+                match cull_position(segment_bounds(&segment), tile_rect) {
+                    CullPosition::Outside => {}
+                    // Above the tile rect, segments only adjust backdrops, and a curve changes
+                    // them just like its baseline does. So there's no need to flatten it.
+                    CullPosition::Above => {
+                        process_line_segment(segment.baseline,
+                                             self.scene_builder,
+                                             &mut self.object_builder)
+                    }
+                    CullPosition::Inside => {
+                        process_segment(&segment, self.scene_builder, &mut self.object_builder)
+                    }
+                }
             }
         }
+
+        if culled_contour_count > 0 {
+            self.scene_builder
+                .culled_contour_count
+                .fetch_add(culled_contour_count, Ordering::Relaxed);
+        }
     }
 
     fn prepare_tiles(&mut self) {
@@ -166,21 +206,10 @@ impl<'a, 'b, 'c, 'd> Tiler<'a, 'b, 'c, 'd> {
 fn process_segment(segment: &Segment,
                    scene_builder: &SceneBuilder,
                    object_builder: &mut ObjectBuilder) {
-    // TODO(pcwalton): Stop degree elevating.
-    if segment.is_quadratic() {
-        let cubic = segment.to_cubic();
-        return process_segment(&cubic, scene_builder, object_builder);
-    }
-
-    if segment.is_line() ||
-            (segment.is_cubic() && segment.as_cubic_segment().is_flat(FLATTENING_TOLERANCE)) {
-        return process_line_segment(segment.baseline, scene_builder, object_builder);
-    }
-
-    // TODO(pcwalton): Use a smarter flattening algorithm.
-    let (prev, next) = segment.split(0.5);
-    process_segment(&prev, scene_builder, object_builder);
-    process_segment(&next, scene_builder, object_builder);
+    let tolerance = scene_builder.built_options.flattening_tolerance;
+    segment.flatten(tolerance, |line_segment| {
+        process_line_segment(line_segment, scene_builder, object_builder)
+    });
 }
 
 // This is the meat of the technique. It implements the fast lattice-clipping algorithm from
@@ -199,7 +228,7 @@ fn process_line_segment(line_segment: LineSegment2F,
         Some(line_segment) => line_segment,
     };
 
-    let tile_size = vec2f(TILE_WIDTH as f32, TILE_HEIGHT as f32);
+    let tile_size = scene_builder.built_options.tile_size.to_f32();
     let tile_size_recip = Vector2F::splat(1.0) / tile_size;
 
     let tile_line_segment =
@@ -306,3 +335,39 @@ enum StepDirection {
     X,
     Y,
 }
+
+// Where something lies relative to the tile rect of the path being tiled.
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum CullPosition {
+    // Entirely to the left of, to the right of, or below the tile rect. It adds no fills.
+    Outside,
+    // Entirely above the tile rect. It adds no fills, but it may adjust backdrops.
+    Above,
+    // It may touch the tile rect.
+    Inside,
+}
+
+fn cull_position(bounds: RectF, tile_rect: RectF) -> CullPosition {
+    if bounds.max_x() < tile_rect.min_x() || bounds.min_x() > tile_rect.max_x() ||
+            bounds.min_y() > tile_rect.max_y() {
+        CullPosition::Outside
+    } else if bounds.max_y() < tile_rect.min_y() {
+        CullPosition::Above
+    } else {
+        CullPosition::Inside
+    }
+}
+
+// Returns the bounds of the segment's control polygon, which contains the segment.
+fn segment_bounds(segment: &Segment) -> RectF {
+    let baseline = segment.baseline;
+    let mut bounds = RectF::from_points(baseline.from().min(baseline.to()),
+                                        baseline.from().max(baseline.to()));
+    if !segment.is_line() {
+        bounds = bounds.union_point(segment.ctrl.from());
+    }
+    if segment.is_cubic() {
+        bounds = bounds.union_point(segment.ctrl.to());
+    }
+    bounds
+}
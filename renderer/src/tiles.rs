@@ -14,8 +14,9 @@ use crate::paint::PaintId;
 use pathfinder_content::effects::BlendMode;
 use pathfinder_content::fill::FillRule;
 use pathfinder_geometry::rect::{RectF, RectI};
-use pathfinder_geometry::vector::vec2f;
+use pathfinder_geometry::vector::{Vector2F, Vector2I};
 
+// The default tile size. The D3D11 renderer always uses this size.
 pub const TILE_WIDTH: u32 = 16;
 pub const TILE_HEIGHT: u32 = 16;
 
@@ -61,8 +62,8 @@ impl TilingPathInfo {
     }
 }
 
-pub fn round_rect_out_to_tile_bounds(rect: RectF) -> RectI {
-    (rect * vec2f(1.0 / TILE_WIDTH as f32, 1.0 / TILE_HEIGHT as f32)).round_out().to_i32()
+pub fn round_rect_out_to_tile_bounds(rect: RectF, tile_size: Vector2I) -> RectI {
+    (rect * (Vector2F::splat(1.0) / tile_size.to_f32())).round_out().to_i32()
 }
 
 impl TileObjectPrimitive {
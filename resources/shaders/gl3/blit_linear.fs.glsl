@@ -0,0 +1,59 @@
+#version {{version}}
+// Automatically generated from files in pathfinder/shaders/. Do not edit!
+
+
+
+
+
+
+
+
+
+
+
+
+
+
+
+precision highp float;
+
+
+
+
+
+
+
+
+
+
+
+uniform sampler2D uSrc;
+uniform int uToneMapping;
+
+in vec2 vTexCoord;
+
+out vec4 oFragColor;
+
+vec3 toneMap(vec3 color, int toneMapping){
+    color = max(color, vec3(0.0));
+    if(toneMapping == 1)
+        return color /(1.0 + color);
+    if(toneMapping == 2){
+
+        return(color *(2.51 * color + 0.03))/(color *(2.43 * color + 0.59)+ 0.14);
+    }
+    return color;
+}
+
+vec3 linearToSRGB(vec3 color){
+    color = clamp(color, 0.0, 1.0);
+    return mix(color * 12.92, 1.055 * pow(color, vec3(1.0 / 2.4))- 0.055, step(0.0031308, color));
+}
+
+void main(){
+    vec4 color = texture(uSrc, vTexCoord);
+    if(color . a > 0.0)
+        color . rgb = linearToSRGB(toneMap(color . rgb / color . a, uToneMapping))* color . a;
+    oFragColor = color;
+}
+
@@ -328,6 +328,23 @@ vec4 filterRadialGradient(vec2 colorTexCoord,
 
 
 
+vec4 filterConicGradient(vec2 colorTexCoord,
+                         sampler2D colorTexture,
+                         vec4 filterParams0,
+                         vec4 filterParams1){
+    vec2 center = filterParams0 . xy, uvOrigin = filterParams1 . zw;
+    float angle = filterParams0 . z;
+
+    vec2 dP = colorTexCoord - center;
+
+    float t = fract((atan(dP . y, dP . x)- angle)* 0.15915494);
+    return texture(colorTexture, uvOrigin + vec2(t, 0.0));
+}
+
+
+
+
+
 
 vec4 filterBlur(vec2 colorTexCoord,
                 sampler2D colorTexture,
@@ -406,6 +423,11 @@ vec4 filterColor(vec2 colorTexCoord,
                                     framebufferSize,
                                     filterParams0,
                                     filterParams1);
+    case 0x5 :
+        return filterConicGradient(colorTexCoord,
+                                   colorTexture,
+                                   filterParams0,
+                                   filterParams1);
     case 0x3 :
         return filterBlur(colorTexCoord,
                           colorTexture,
@@ -580,6 +602,20 @@ float sampleMask(float maskAlpha,
 
 
 
+
+
+vec3 sRGBToLinear(vec3 color){
+    color = max(color, vec3(0.0));
+    return mix(color / 12.92, pow((color + 0.055)/ 1.055, vec3(2.4)), step(0.04045, color));
+}
+
+vec3 linearToSRGB(vec3 color){
+    color = max(color, vec3(0.0));
+    return mix(color * 12.92, 1.055 * pow(color, vec3(1.0 / 2.4))- 0.055, step(0.0031308, color));
+}
+
+
+
 vec4 calculateColor(vec2 fragCoord,
                     sampler2D colorTexture0,
                     sampler2D maskTexture0,
@@ -597,7 +633,8 @@ vec4 calculateColor(vec2 fragCoord,
                     vec3 maskTexCoord0,
                     vec2 colorTexCoord0,
                     vec4 baseColor,
-                    int tileCtrl){
+                    int tileCtrl,
+                    bool linearBlending){
 
     int maskCtrl0 =(tileCtrl >> 0)& 0x3;
     float maskAlpha = 1.0;
@@ -628,6 +665,10 @@ vec4 calculateColor(vec2 fragCoord,
     color . a *= maskAlpha;
 
 
+    if(linearBlending)
+        color . rgb = sRGBToLinear(color . rgb);
+
+
     int compositeOp =(ctrl >> 10)& 0xf;
     color = composite(color, destTexture, framebufferSize, fragCoord, compositeOp);
 
@@ -644,6 +685,7 @@ uniform sampler2D uGammaLUT;
 uniform vec2 uColorTextureSize0;
 uniform vec2 uMaskTextureSize0;
 uniform vec2 uFramebufferSize;
+uniform int uLinearBlending;
 
 in vec3 vMaskTexCoord0;
 in vec2 vColorTexCoord0;
@@ -680,6 +722,7 @@ void main(){
                                 vMaskTexCoord0,
                                 vColorTexCoord0,
                                 vBaseColor,
-                                int(vTileCtrl));
+                                int(vTileCtrl),
+                                uLinearBlending != 0);
 }
 
@@ -330,6 +330,23 @@ vec4 filterRadialGradient(vec2 colorTexCoord,
 
 
 
+vec4 filterConicGradient(vec2 colorTexCoord,
+                         sampler2D colorTexture,
+                         vec4 filterParams0,
+                         vec4 filterParams1){
+    vec2 center = filterParams0 . xy, uvOrigin = filterParams1 . zw;
+    float angle = filterParams0 . z;
+
+    vec2 dP = colorTexCoord - center;
+
+    float t = fract((atan(dP . y, dP . x)- angle)* 0.15915494);
+    return texture(colorTexture, uvOrigin + vec2(t, 0.0));
+}
+
+
+
+
+
 
 vec4 filterBlur(vec2 colorTexCoord,
                 sampler2D colorTexture,
@@ -408,6 +425,11 @@ vec4 filterColor(vec2 colorTexCoord,
                                     framebufferSize,
                                     filterParams0,
                                     filterParams1);
+    case 0x5 :
+        return filterConicGradient(colorTexCoord,
+                                   colorTexture,
+                                   filterParams0,
+                                   filterParams1);
     case 0x3 :
         return filterBlur(colorTexCoord,
                           colorTexture,
@@ -582,6 +604,20 @@ float sampleMask(float maskAlpha,
 
 
 
+
+
+vec3 sRGBToLinear(vec3 color){
+    color = max(color, vec3(0.0));
+    return mix(color / 12.92, pow((color + 0.055)/ 1.055, vec3(2.4)), step(0.04045, color));
+}
+
+vec3 linearToSRGB(vec3 color){
+    color = max(color, vec3(0.0));
+    return mix(color * 12.92, 1.055 * pow(color, vec3(1.0 / 2.4))- 0.055, step(0.0031308, color));
+}
+
+
+
 vec4 calculateColor(vec2 fragCoord,
                     sampler2D colorTexture0,
                     sampler2D maskTexture0,
@@ -599,7 +635,8 @@ vec4 calculateColor(vec2 fragCoord,
                     vec3 maskTexCoord0,
                     vec2 colorTexCoord0,
                     vec4 baseColor,
-                    int tileCtrl){
+                    int tileCtrl,
+                    bool linearBlending){
 
     int maskCtrl0 =(tileCtrl >> 0)& 0x3;
     float maskAlpha = 1.0;
@@ -630,6 +667,10 @@ vec4 calculateColor(vec2 fragCoord,
     color . a *= maskAlpha;
 
 
+    if(linearBlending)
+        color . rgb = sRGBToLinear(color . rgb);
+
+
     int compositeOp =(ctrl >> 10)& 0xf;
     color = composite(color, destTexture, framebufferSize, fragCoord, compositeOp);
 
@@ -709,6 +750,7 @@ uniform vec2 uColorTextureSize0;
 uniform vec2 uMaskTextureSize0;
 uniform vec2 uFramebufferSize;
 uniform ivec2 uFramebufferTileSize;
+uniform int uLinearBlending;
 layout(rgba8)uniform image2D uDestImage;
 
 layout(std430, binding = 0)buffer bTiles {
@@ -732,6 +774,21 @@ ivec2 toImageCoords(ivec2 coords){
     return ivec2(coords . x, uFramebufferSize . y - coords . y);
 }
 
+
+
+vec4 decodeDestColor(vec4 color){
+    if(uLinearBlending == 0 || color . a == 0.0)
+        return color;
+    return vec4(sRGBToLinear(color . rgb / color . a)* color . a, color . a);
+}
+
+
+vec4 encodeDestColor(vec4 color){
+    if(uLinearBlending == 0 || color . a == 0.0)
+        return color;
+    return vec4(linearToSRGB(color . rgb / color . a)* color . a, color . a);
+}
+
 void main(){
     ivec2 tileCoord = ivec2(gl_WorkGroupID . xy);
     ivec2 firstTileSubCoord = ivec2(gl_LocalInvocationID . xy)* ivec2(1, 4);
@@ -748,7 +805,7 @@ void main(){
             destColors[subY]= uClearColor;
         } else {
             ivec2 imageCoords = toImageCoords(firstFragCoord + ivec2(0, subY));
-            destColors[subY]= imageLoad(uDestImage, imageCoords);
+            destColors[subY]= decodeDestColor(imageLoad(uDestImage, imageCoords));
         }
     }
 
@@ -814,7 +871,8 @@ void main(){
                                            maskTexCoord0,
                                            colorTexCoord0,
                                            baseColor,
-                                           tileCtrl);
+                                           tileCtrl,
+                                           uLinearBlending != 0);
 
             destColors[subY]= destColors[subY]*(1.0 - srcColor . a)+ srcColor;
         }
@@ -823,6 +881,8 @@ void main(){
     }
 
     for(int subY = 0;subY < 4;subY ++)
-        imageStore(uDestImage, toImageCoords(firstFragCoord + ivec2(0, subY)), destColors[subY]);
+        imageStore(uDestImage,
+                   toImageCoords(firstFragCoord + ivec2(0, subY)),
+                   encodeDestColor(destColors[subY]));
 }
 
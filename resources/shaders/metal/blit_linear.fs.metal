@@ -0,0 +1,55 @@
+// Automatically generated from files in pathfinder/shaders/. Do not edit!
+#include <metal_stdlib>
+#include <simd/simd.h>
+
+using namespace metal;
+
+struct main0_out
+{
+    float4 oFragColor [[color(0)]];
+};
+
+struct main0_in
+{
+    float2 vTexCoord [[user(locn0)]];
+};
+
+static inline __attribute__((always_inline))
+float3 toneMap(thread float3& color, thread const int& toneMapping)
+{
+    color = fast::max(color, float3(0.0));
+    if (toneMapping == 1)
+    {
+        return color / (float3(1.0) + color);
+    }
+    if (toneMapping == 2)
+    {
+        return (color * ((color * 2.5099999904632568359375) + float3(0.02999999932944774627685546875))) / ((color * ((color * 2.4300000667572021484375) + float3(0.589999973773956298828125))) + float3(0.14000000059604644775390625));
+    }
+    return color;
+}
+
+static inline __attribute__((always_inline))
+float3 linearToSRGB(thread float3& color)
+{
+    color = fast::clamp(color, float3(0.0), float3(1.0));
+    return mix(color * 12.9200000762939453125, (pow(color, float3(0.4166666567325592041015625)) * 1.05499994754791259765625) - float3(0.054999999701976776123046875), step(float3(0.003130800090730190277099609375), color));
+}
+
+fragment main0_out main0(main0_in in [[stage_in]], constant int& uToneMapping [[buffer(0)]], texture2d<float> uSrc [[texture(0)]], sampler uSrcSmplr [[sampler(0)]])
+{
+    main0_out out = {};
+    float4 color = uSrc.sample(uSrcSmplr, in.vTexCoord);
+    if (color.w > 0.0)
+    {
+        float3 param = color.xyz / float3(color.w);
+        int param_1 = uToneMapping;
+        float3 _87 = toneMap(param, param_1);
+        float3 param_2 = _87;
+        float3 _90 = linearToSRGB(param_2) * color.w;
+        color = float4(_90.x, _90.y, _90.z, color.w);
+    }
+    out.oFragColor = color;
+    return out;
+}
+
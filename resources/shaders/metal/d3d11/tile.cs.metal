@@ -148,6 +148,17 @@ float4 filterRadialGradient(thread const float2& colorTexCoord, thread const tex
     return color;
 }
 
+static inline __attribute__((always_inline))
+float4 filterConicGradient(thread const float2& colorTexCoord, thread const texture2d<float> colorTexture, thread const sampler colorTextureSmplr, thread const float4& filterParams0, thread const float4& filterParams1)
+{
+    float2 center = filterParams0.xy;
+    float2 uvOrigin = filterParams1.zw;
+    float angle = filterParams0.z;
+    float2 dP = colorTexCoord - center;
+    float t = fract((precise::atan2(dP.y, dP.x) - angle) * 0.15915493667125701904296875);
+    return colorTexture.sample(colorTextureSmplr, (uvOrigin + float2(t, 0.0)));
+}
+
 static inline __attribute__((always_inline))
 float4 filterBlur(thread const float2& colorTexCoord, thread const texture2d<float> colorTexture, thread const sampler colorTextureSmplr, thread const float2& colorTextureSize, thread const float4& filterParams0, thread const float4& filterParams1)
 {
@@ -331,36 +342,43 @@ float4 filterColor(thread const float2& colorTexCoord, thread const texture2d<fl
             float4 param_5 = filterParams1;
             return filterRadialGradient(param, colorTexture, colorTextureSmplr, param_1, param_2, param_3, param_4, param_5);
         }
-        case 3:
+        case 5:
         {
             float2 param_6 = colorTexCoord;
-            float2 param_7 = colorTextureSize;
-            float4 param_8 = filterParams0;
-            float4 param_9 = filterParams1;
-            return filterBlur(param_6, colorTexture, colorTextureSmplr, param_7, param_8, param_9);
+            float4 param_7 = filterParams0;
+            float4 param_8 = filterParams1;
+            return filterConicGradient(param_6, colorTexture, colorTextureSmplr, param_7, param_8);
+        }
+        case 3:
+        {
+            float2 param_9 = colorTexCoord;
+            float2 param_10 = colorTextureSize;
+            float4 param_11 = filterParams0;
+            float4 param_12 = filterParams1;
+            return filterBlur(param_9, colorTexture, colorTextureSmplr, param_10, param_11, param_12);
         }
         case 2:
         {
-            float2 param_10 = colorTexCoord;
-            float2 param_11 = colorTextureSize;
-            float4 param_12 = filterParams0;
-            float4 param_13 = filterParams1;
-            float4 param_14 = filterParams2;
-            return filterText(param_10, colorTexture, colorTextureSmplr, gammaLUT, gammaLUTSmplr, param_11, param_12, param_13, param_14);
+            float2 param_13 = colorTexCoord;
+            float2 param_14 = colorTextureSize;
+            float4 param_15 = filterParams0;
+            float4 param_16 = filterParams1;
+            float4 param_17 = filterParams2;
+            return filterText(param_13, colorTexture, colorTextureSmplr, gammaLUT, gammaLUTSmplr, param_14, param_15, param_16, param_17);
         }
         case 4:
         {
-            float2 param_15 = colorTexCoord;
-            float4 param_16 = filterParams0;
-            float4 param_17 = filterParams1;
-            float4 param_18 = filterParams2;
-            float4 param_19 = filterParams3;
-            float4 param_20 = filterParams4;
-            return filterColorMatrix(param_15, colorTexture, colorTextureSmplr, param_16, param_17, param_18, param_19, param_20);
+            float2 param_18 = colorTexCoord;
+            float4 param_19 = filterParams0;
+            float4 param_20 = filterParams1;
+            float4 param_21 = filterParams2;
+            float4 param_22 = filterParams3;
+            float4 param_23 = filterParams4;
+            return filterColorMatrix(param_18, colorTexture, colorTextureSmplr, param_19, param_20, param_21, param_22, param_23);
         }
     }
-    float2 param_21 = colorTexCoord;
-    return filterNone(param_21, colorTexture, colorTextureSmplr);
+    float2 param_24 = colorTexCoord;
+    return filterNone(param_24, colorTexture, colorTextureSmplr);
 }
 
 static inline __attribute__((always_inline))
@@ -635,7 +653,14 @@ float4 composite(thread const float4& srcColor, thread const texture2d<float> de
 }
 
 static inline __attribute__((always_inline))
-float4 calculateColor(thread const float2& fragCoord, thread const texture2d<float> colorTexture0, thread const sampler colorTexture0Smplr, thread const texture2d<float> maskTexture0, thread const sampler maskTexture0Smplr, thread const texture2d<float> destTexture, thread const sampler destTextureSmplr, thread const texture2d<float> gammaLUT, thread const sampler gammaLUTSmplr, thread const float2& colorTextureSize0, thread const float2& maskTextureSize0, thread const float4& filterParams0, thread const float4& filterParams1, thread const float4& filterParams2, thread const float4& filterParams3, thread const float4& filterParams4, thread const float2& framebufferSize, thread const int& ctrl, thread const float3& maskTexCoord0, thread const float2& colorTexCoord0, thread const float4& baseColor, thread const int& tileCtrl)
+float3 sRGBToLinear(thread float3& color)
+{
+    color = fast::max(color, float3(0.0));
+    return mix(color / float3(12.9200000762939453125), pow((color + float3(0.054999999701976776123046875)) / float3(1.05499994754791259765625), float3(2.400000095367431640625)), step(float3(0.040449999272823333740234375), color));
+}
+
+static inline __attribute__((always_inline))
+float4 calculateColor(thread const float2& fragCoord, thread const texture2d<float> colorTexture0, thread const sampler colorTexture0Smplr, thread const texture2d<float> maskTexture0, thread const sampler maskTexture0Smplr, thread const texture2d<float> destTexture, thread const sampler destTextureSmplr, thread const texture2d<float> gammaLUT, thread const sampler gammaLUTSmplr, thread const float2& colorTextureSize0, thread const float2& maskTextureSize0, thread const float4& filterParams0, thread const float4& filterParams1, thread const float4& filterParams2, thread const float4& filterParams3, thread const float4& filterParams4, thread const float2& framebufferSize, thread const int& ctrl, thread const float3& maskTexCoord0, thread const float2& colorTexCoord0, thread const float4& baseColor, thread const int& tileCtrl, thread const bool& linearBlending)
 {
     int maskCtrl0 = (tileCtrl >> 0) & 3;
     float maskAlpha = 1.0;
@@ -666,18 +691,75 @@ float4 calculateColor(thread const float2& fragCoord, thread const texture2d<flo
         color = combineColor0(param_14, param_15, param_16);
     }
     color.w *= maskAlpha;
+    if (linearBlending)
+    {
+        float3 param_17 = color.xyz;
+        float3 _1441 = sRGBToLinear(param_17);
+        color = float4(_1441.x, _1441.y, _1441.z, color.w);
+    }
     int compositeOp = (ctrl >> 10) & 15;
-    float4 param_17 = color;
-    float2 param_18 = framebufferSize;
-    float2 param_19 = fragCoord;
-    int param_20 = compositeOp;
-    color = composite(param_17, destTexture, destTextureSmplr, param_18, param_19, param_20);
+    float4 param_18 = color;
+    float2 param_19 = framebufferSize;
+    float2 param_20 = fragCoord;
+    int param_21 = compositeOp;
+    color = composite(param_18, destTexture, destTextureSmplr, param_19, param_20, param_21);
     float3 _1435 = color.xyz * color.w;
     color = float4(_1435.x, _1435.y, _1435.z, color.w);
     return color;
 }
 
-kernel void main0(constant int2& uFramebufferTileSize [[buffer(3)]], constant int& uLoadAction [[buffer(4)]], constant int2& uTextureMetadataSize [[buffer(7)]], constant float2& uFramebufferSize [[buffer(0)]], constant float2& uTileSize [[buffer(1)]], constant float4& uClearColor [[buffer(5)]], constant float2& uColorTextureSize0 [[buffer(8)]], constant float2& uMaskTextureSize0 [[buffer(9)]], const device bFirstTileMap& _1599 [[buffer(2)]], const device bTiles& _1690 [[buffer(6)]], texture2d<float, access::read_write> uDestImage [[texture(0)]], texture2d<float> uTextureMetadata [[texture(1)]], texture2d<float> uColorTexture0 [[texture(2)]], texture2d<float> uMaskTexture0 [[texture(3)]], texture2d<float> uGammaLUT [[texture(4)]], sampler uTextureMetadataSmplr [[sampler(0)]], sampler uColorTexture0Smplr [[sampler(1)]], sampler uMaskTexture0Smplr [[sampler(2)]], sampler uGammaLUTSmplr [[sampler(3)]], uint3 gl_WorkGroupID [[threadgroup_position_in_grid]], uint3 gl_LocalInvocationID [[thread_position_in_threadgroup]])
+static inline __attribute__((always_inline))
+float4 decodeDestColor(thread const float4& color, thread int uLinearBlending)
+{
+    bool _1631 = uLinearBlending == 0;
+    bool _1638;
+    if (!_1631)
+    {
+        _1638 = color.w == 0.0;
+    }
+    else
+    {
+        _1638 = _1631;
+    }
+    if (_1638)
+    {
+        return color;
+    }
+    float3 param = color.xyz / float3(color.w);
+    float3 _1651 = sRGBToLinear(param);
+    return float4(_1651 * color.w, color.w);
+}
+
+static inline __attribute__((always_inline))
+float3 linearToSRGB(thread float3& color)
+{
+    color = fast::max(color, float3(0.0));
+    return mix(color * 12.9200000762939453125, (pow(color, float3(0.4166666567325592041015625)) * 1.05499994754791259765625) - float3(0.054999999701976776123046875), step(float3(0.003130800090730190277099609375), color));
+}
+
+static inline __attribute__((always_inline))
+float4 encodeDestColor(thread const float4& color, thread int uLinearBlending)
+{
+    bool _1664 = uLinearBlending == 0;
+    bool _1671;
+    if (!_1664)
+    {
+        _1671 = color.w == 0.0;
+    }
+    else
+    {
+        _1671 = _1664;
+    }
+    if (_1671)
+    {
+        return color;
+    }
+    float3 param = color.xyz / float3(color.w);
+    float3 _1684 = linearToSRGB(param);
+    return float4(_1684 * color.w, color.w);
+}
+
+kernel void main0(constant int2& uFramebufferTileSize [[buffer(3)]], constant int& uLoadAction [[buffer(4)]], constant int2& uTextureMetadataSize [[buffer(7)]], constant float2& uFramebufferSize [[buffer(0)]], constant float2& uTileSize [[buffer(1)]], constant float4& uClearColor [[buffer(5)]], constant float2& uColorTextureSize0 [[buffer(8)]], constant float2& uMaskTextureSize0 [[buffer(9)]], constant int& uLinearBlending [[buffer(10)]], const device bFirstTileMap& _1599 [[buffer(2)]], const device bTiles& _1690 [[buffer(6)]], texture2d<float, access::read_write> uDestImage [[texture(0)]], texture2d<float> uTextureMetadata [[texture(1)]], texture2d<float> uColorTexture0 [[texture(2)]], texture2d<float> uMaskTexture0 [[texture(3)]], texture2d<float> uGammaLUT [[texture(4)]], sampler uTextureMetadataSmplr [[sampler(0)]], sampler uColorTexture0Smplr [[sampler(1)]], sampler uMaskTexture0Smplr [[sampler(2)]], sampler uGammaLUTSmplr [[sampler(3)]], uint3 gl_WorkGroupID [[threadgroup_position_in_grid]], uint3 gl_LocalInvocationID [[thread_position_in_threadgroup]])
 {
     int2 tileCoord = int2(gl_WorkGroupID.xy);
     int2 firstTileSubCoord = int2(gl_LocalInvocationID.xy) * int2(1, 4);
@@ -698,19 +780,20 @@ kernel void main0(constant int2& uFramebufferTileSize [[buffer(3)]], constant in
         {
             int2 param = firstFragCoord + int2(0, subY);
             int2 imageCoords = toImageCoords(param, uFramebufferSize);
-            destColors[subY] = uDestImage.read(uint2(imageCoords));
+            float4 param_1 = uDestImage.read(uint2(imageCoords));
+            destColors[subY] = decodeDestColor(param_1, uLinearBlending);
         }
     }
     int backdrop;
     uint2 maskTileCoord;
-    float2 param_4;
-    float4 param_5;
+    float2 param_5;
     float4 param_6;
     float4 param_7;
     float4 param_8;
     float4 param_9;
     float4 param_10;
-    int param_11;
+    float4 param_11;
+    int param_12;
     while (tileIndex >= 0)
     {
         for (int subY_1 = 0; subY_1 < 4; subY_1++)
@@ -733,41 +816,43 @@ kernel void main0(constant int2& uFramebufferTileSize [[buffer(3)]], constant in
                 tileCtrl &= (-4);
             }
             float3 maskTexCoord0 = float3(float2(int2(maskTileCoord) + tileSubCoord), float(backdrop));
-            float2 param_1 = fragCoord;
-            int param_2 = int(colorEntry);
-            int2 param_3 = uTextureMetadataSize;
-            computeTileVaryings(param_1, param_2, uTextureMetadata, uTextureMetadataSmplr, param_3, param_4, param_5, param_6, param_7, param_8, param_9, param_10, param_11);
-            float2 colorTexCoord0 = param_4;
-            float4 baseColor = param_5;
-            float4 filterParams0 = param_6;
-            float4 filterParams1 = param_7;
-            float4 filterParams2 = param_8;
-            float4 filterParams3 = param_9;
-            float4 filterParams4 = param_10;
-            int ctrl = param_11;
-            float2 param_12 = fragCoord;
-            float2 param_13 = uColorTextureSize0;
-            float2 param_14 = uMaskTextureSize0;
-            float4 param_15 = filterParams0;
-            float4 param_16 = filterParams1;
-            float4 param_17 = filterParams2;
-            float4 param_18 = filterParams3;
-            float4 param_19 = filterParams4;
-            float2 param_20 = uFramebufferSize;
-            int param_21 = ctrl;
-            float3 param_22 = maskTexCoord0;
-            float2 param_23 = colorTexCoord0;
-            float4 param_24 = baseColor;
-            int param_25 = tileCtrl;
-            float4 srcColor = calculateColor(param_12, uColorTexture0, uColorTexture0Smplr, uMaskTexture0, uMaskTexture0Smplr, uColorTexture0, uColorTexture0Smplr, uGammaLUT, uGammaLUTSmplr, param_13, param_14, param_15, param_16, param_17, param_18, param_19, param_20, param_21, param_22, param_23, param_24, param_25);
+            float2 param_2 = fragCoord;
+            int param_3 = int(colorEntry);
+            int2 param_4 = uTextureMetadataSize;
+            computeTileVaryings(param_2, param_3, uTextureMetadata, uTextureMetadataSmplr, param_4, param_5, param_6, param_7, param_8, param_9, param_10, param_11, param_12);
+            float2 colorTexCoord0 = param_5;
+            float4 baseColor = param_6;
+            float4 filterParams0 = param_7;
+            float4 filterParams1 = param_8;
+            float4 filterParams2 = param_9;
+            float4 filterParams3 = param_10;
+            float4 filterParams4 = param_11;
+            int ctrl = param_12;
+            float2 param_13 = fragCoord;
+            float2 param_14 = uColorTextureSize0;
+            float2 param_15 = uMaskTextureSize0;
+            float4 param_16 = filterParams0;
+            float4 param_17 = filterParams1;
+            float4 param_18 = filterParams2;
+            float4 param_19 = filterParams3;
+            float4 param_20 = filterParams4;
+            float2 param_21 = uFramebufferSize;
+            int param_22 = ctrl;
+            float3 param_23 = maskTexCoord0;
+            float2 param_24 = colorTexCoord0;
+            float4 param_25 = baseColor;
+            int param_26 = tileCtrl;
+            bool param_27 = uLinearBlending != 0;
+            float4 srcColor = calculateColor(param_13, uColorTexture0, uColorTexture0Smplr, uMaskTexture0, uMaskTexture0Smplr, uColorTexture0, uColorTexture0Smplr, uGammaLUT, uGammaLUTSmplr, param_14, param_15, param_16, param_17, param_18, param_19, param_20, param_21, param_22, param_23, param_24, param_25, param_26, param_27);
             destColors[subY_1] = (destColors[subY_1] * (1.0 - srcColor.w)) + srcColor;
         }
         tileIndex = int(_1690.iTiles[(tileIndex * 4) + 0]);
     }
     for (int subY_2 = 0; subY_2 < 4; subY_2++)
     {
-        int2 param_26 = firstFragCoord + int2(0, subY_2);
-        uDestImage.write(destColors[subY_2], uint2(toImageCoords(param_26, uFramebufferSize)));
+        int2 param_28 = firstFragCoord + int2(0, subY_2);
+        float4 param_29 = destColors[subY_2];
+        uDestImage.write(encodeDestColor(param_29, uLinearBlending), uint2(toImageCoords(param_28, uFramebufferSize)));
     }
 }
 
@@ -92,6 +92,17 @@ float4 filterRadialGradient(thread const float2& colorTexCoord, thread const tex
     return color;
 }
 
+static inline __attribute__((always_inline))
+float4 filterConicGradient(thread const float2& colorTexCoord, thread const texture2d<float> colorTexture, thread const sampler colorTextureSmplr, thread const float4& filterParams0, thread const float4& filterParams1)
+{
+    float2 center = filterParams0.xy;
+    float2 uvOrigin = filterParams1.zw;
+    float angle = filterParams0.z;
+    float2 dP = colorTexCoord - center;
+    float t = fract((precise::atan2(dP.y, dP.x) - angle) * 0.15915493667125701904296875);
+    return colorTexture.sample(colorTextureSmplr, (uvOrigin + float2(t, 0.0)));
+}
+
 static inline __attribute__((always_inline))
 float4 filterBlur(thread const float2& colorTexCoord, thread const texture2d<float> colorTexture, thread const sampler colorTextureSmplr, thread const float2& colorTextureSize, thread const float4& filterParams0, thread const float4& filterParams1)
 {
@@ -275,36 +286,43 @@ float4 filterColor(thread const float2& colorTexCoord, thread const texture2d<fl
             float4 param_5 = filterParams1;
             return filterRadialGradient(param, colorTexture, colorTextureSmplr, param_1, param_2, param_3, param_4, param_5);
         }
-        case 3:
+        case 5:
         {
             float2 param_6 = colorTexCoord;
-            float2 param_7 = colorTextureSize;
-            float4 param_8 = filterParams0;
-            float4 param_9 = filterParams1;
-            return filterBlur(param_6, colorTexture, colorTextureSmplr, param_7, param_8, param_9);
+            float4 param_7 = filterParams0;
+            float4 param_8 = filterParams1;
+            return filterConicGradient(param_6, colorTexture, colorTextureSmplr, param_7, param_8);
+        }
+        case 3:
+        {
+            float2 param_9 = colorTexCoord;
+            float2 param_10 = colorTextureSize;
+            float4 param_11 = filterParams0;
+            float4 param_12 = filterParams1;
+            return filterBlur(param_9, colorTexture, colorTextureSmplr, param_10, param_11, param_12);
         }
         case 2:
         {
-            float2 param_10 = colorTexCoord;
-            float2 param_11 = colorTextureSize;
-            float4 param_12 = filterParams0;
-            float4 param_13 = filterParams1;
-            float4 param_14 = filterParams2;
-            return filterText(param_10, colorTexture, colorTextureSmplr, gammaLUT, gammaLUTSmplr, param_11, param_12, param_13, param_14);
+            float2 param_13 = colorTexCoord;
+            float2 param_14 = colorTextureSize;
+            float4 param_15 = filterParams0;
+            float4 param_16 = filterParams1;
+            float4 param_17 = filterParams2;
+            return filterText(param_13, colorTexture, colorTextureSmplr, gammaLUT, gammaLUTSmplr, param_14, param_15, param_16, param_17);
         }
         case 4:
         {
-            float2 param_15 = colorTexCoord;
-            float4 param_16 = filterParams0;
-            float4 param_17 = filterParams1;
-            float4 param_18 = filterParams2;
-            float4 param_19 = filterParams3;
-            float4 param_20 = filterParams4;
-            return filterColorMatrix(param_15, colorTexture, colorTextureSmplr, param_16, param_17, param_18, param_19, param_20);
+            float2 param_18 = colorTexCoord;
+            float4 param_19 = filterParams0;
+            float4 param_20 = filterParams1;
+            float4 param_21 = filterParams2;
+            float4 param_22 = filterParams3;
+            float4 param_23 = filterParams4;
+            return filterColorMatrix(param_18, colorTexture, colorTextureSmplr, param_19, param_20, param_21, param_22, param_23);
         }
     }
-    float2 param_21 = colorTexCoord;
-    return filterNone(param_21, colorTexture, colorTextureSmplr);
+    float2 param_24 = colorTexCoord;
+    return filterNone(param_24, colorTexture, colorTextureSmplr);
 }
 
 static inline __attribute__((always_inline))
@@ -579,7 +597,14 @@ float4 composite(thread const float4& srcColor, thread const texture2d<float> de
 }
 
 static inline __attribute__((always_inline))
-float4 calculateColor(thread const float2& fragCoord, thread const texture2d<float> colorTexture0, thread const sampler colorTexture0Smplr, thread const texture2d<float> maskTexture0, thread const sampler maskTexture0Smplr, thread const texture2d<float> destTexture, thread const sampler destTextureSmplr, thread const texture2d<float> gammaLUT, thread const sampler gammaLUTSmplr, thread const float2& colorTextureSize0, thread const float2& maskTextureSize0, thread const float4& filterParams0, thread const float4& filterParams1, thread const float4& filterParams2, thread const float4& filterParams3, thread const float4& filterParams4, thread const float2& framebufferSize, thread const int& ctrl, thread const float3& maskTexCoord0, thread const float2& colorTexCoord0, thread const float4& baseColor, thread const int& tileCtrl)
+float3 sRGBToLinear(thread float3& color)
+{
+    color = fast::max(color, float3(0.0));
+    return mix(color / float3(12.9200000762939453125), pow((color + float3(0.054999999701976776123046875)) / float3(1.05499994754791259765625), float3(2.400000095367431640625)), step(float3(0.040449999272823333740234375), color));
+}
+
+static inline __attribute__((always_inline))
+float4 calculateColor(thread const float2& fragCoord, thread const texture2d<float> colorTexture0, thread const sampler colorTexture0Smplr, thread const texture2d<float> maskTexture0, thread const sampler maskTexture0Smplr, thread const texture2d<float> destTexture, thread const sampler destTextureSmplr, thread const texture2d<float> gammaLUT, thread const sampler gammaLUTSmplr, thread const float2& colorTextureSize0, thread const float2& maskTextureSize0, thread const float4& filterParams0, thread const float4& filterParams1, thread const float4& filterParams2, thread const float4& filterParams3, thread const float4& filterParams4, thread const float2& framebufferSize, thread const int& ctrl, thread const float3& maskTexCoord0, thread const float2& colorTexCoord0, thread const float4& baseColor, thread const int& tileCtrl, thread const bool& linearBlending)
 {
     int maskCtrl0 = (tileCtrl >> 0) & 3;
     float maskAlpha = 1.0;
@@ -610,18 +635,24 @@ float4 calculateColor(thread const float2& fragCoord, thread const texture2d<flo
         color = combineColor0(param_14, param_15, param_16);
     }
     color.w *= maskAlpha;
+    if (linearBlending)
+    {
+        float3 param_17 = color.xyz;
+        float3 _1415 = sRGBToLinear(param_17);
+        color = float4(_1415.x, _1415.y, _1415.z, color.w);
+    }
     int compositeOp = (ctrl >> 10) & 15;
-    float4 param_17 = color;
-    float2 param_18 = framebufferSize;
-    float2 param_19 = fragCoord;
-    int param_20 = compositeOp;
-    color = composite(param_17, destTexture, destTextureSmplr, param_18, param_19, param_20);
+    float4 param_18 = color;
+    float2 param_19 = framebufferSize;
+    float2 param_20 = fragCoord;
+    int param_21 = compositeOp;
+    color = composite(param_18, destTexture, destTextureSmplr, param_19, param_20, param_21);
     float3 _1409 = color.xyz * color.w;
     color = float4(_1409.x, _1409.y, _1409.z, color.w);
     return color;
 }
 
-fragment main0_out main0(main0_in in [[stage_in]], constant float2& uColorTextureSize0 [[buffer(0)]], constant float2& uMaskTextureSize0 [[buffer(1)]], constant float2& uFramebufferSize [[buffer(2)]], texture2d<float> uColorTexture0 [[texture(0)]], texture2d<float> uMaskTexture0 [[texture(1)]], texture2d<float> uDestTexture [[texture(2)]], texture2d<float> uGammaLUT [[texture(3)]], sampler uColorTexture0Smplr [[sampler(0)]], sampler uMaskTexture0Smplr [[sampler(1)]], sampler uDestTextureSmplr [[sampler(2)]], sampler uGammaLUTSmplr [[sampler(3)]], float4 gl_FragCoord [[position]])
+fragment main0_out main0(main0_in in [[stage_in]], constant float2& uColorTextureSize0 [[buffer(0)]], constant float2& uMaskTextureSize0 [[buffer(1)]], constant float2& uFramebufferSize [[buffer(2)]], constant int& uLinearBlending [[buffer(3)]], texture2d<float> uColorTexture0 [[texture(0)]], texture2d<float> uMaskTexture0 [[texture(1)]], texture2d<float> uDestTexture [[texture(2)]], texture2d<float> uGammaLUT [[texture(3)]], sampler uColorTexture0Smplr [[sampler(0)]], sampler uMaskTexture0Smplr [[sampler(1)]], sampler uDestTextureSmplr [[sampler(2)]], sampler uGammaLUTSmplr [[sampler(3)]], float4 gl_FragCoord [[position]])
 {
     main0_out out = {};
     float2 param = gl_FragCoord.xy;
@@ -638,7 +669,8 @@ fragment main0_out main0(main0_in in [[stage_in]], constant float2& uColorTextur
     float2 param_11 = in.vColorTexCoord0;
     float4 param_12 = in.vBaseColor;
     int param_13 = int(in.vTileCtrl);
-    out.oFragColor = calculateColor(param, uColorTexture0, uColorTexture0Smplr, uMaskTexture0, uMaskTexture0Smplr, uDestTexture, uDestTextureSmplr, uGammaLUT, uGammaLUTSmplr, param_1, param_2, param_3, param_4, param_5, param_6, param_7, param_8, param_9, param_10, param_11, param_12, param_13);
+    bool param_14 = uLinearBlending != 0;
+    out.oFragColor = calculateColor(param, uColorTexture0, uColorTexture0Smplr, uMaskTexture0, uMaskTexture0Smplr, uDestTexture, uDestTextureSmplr, uGammaLUT, uGammaLUTSmplr, param_1, param_2, param_3, param_4, param_5, param_6, param_7, param_8, param_9, param_10, param_11, param_12, param_13, param_14);
     return out;
 }
 
@@ -88,7 +88,7 @@ void computeTileVaryings(thread const float2& position, thread const int& colorE
     outCtrl = int(extra.x);
 }
 
-vertex main0_out main0(main0_in in [[stage_in]], constant int2& uZBufferSize [[buffer(1)]], constant int2& uTextureMetadataSize [[buffer(2)]], constant float2& uTileSize [[buffer(0)]], constant float4x4& uTransform [[buffer(3)]], texture2d<float> uZBuffer [[texture(0)]], texture2d<float> uTextureMetadata [[texture(1)]], sampler uZBufferSmplr [[sampler(0)]], sampler uTextureMetadataSmplr [[sampler(1)]])
+vertex main0_out main0(main0_in in [[stage_in]], constant int2& uZBufferSize [[buffer(1)]], constant int2& uTextureMetadataSize [[buffer(2)]], constant float2& uTileSize [[buffer(0)]], constant float4x4& uTransform [[buffer(3)]], constant float2& uMaskTextureSize0 [[buffer(4)]], texture2d<float> uZBuffer [[texture(0)]], texture2d<float> uTextureMetadata [[texture(1)]], sampler uZBufferSmplr [[sampler(0)]], sampler uTextureMetadataSmplr [[sampler(1)]])
 {
     main0_out out = {};
     float2 tileOrigin = float2(in.aTileOrigin);
@@ -100,7 +100,9 @@ vertex main0_out main0(main0_in in [[stage_in]], constant int2& uZBufferSize [[b
         out.gl_Position = float4(0.0);
         return out;
     }
-    uint2 maskTileCoord = uint2(in.aMaskTexCoord0.x, in.aMaskTexCoord0.y + (256u * in.aMaskTexCoord0.z));
+    uint maskTileIndex = (in.aMaskTexCoord0.x | (in.aMaskTexCoord0.y << uint(8))) | (in.aMaskTexCoord0.z << uint(16));
+    uint maskTilesPerRow = max(uint(uMaskTextureSize0.x / uTileSize.x), 1u);
+    uint2 maskTileCoord = uint2(maskTileIndex % maskTilesPerRow, maskTileIndex / maskTilesPerRow);
     float2 maskTexCoord0 = (float2(maskTileCoord) + tileOffset) * uTileSize;
     bool _264 = in.aCtrlBackdrop.y == 0;
     bool _270;
@@ -22,13 +22,14 @@ struct main0_in
     int aSrcBackdrop [[attribute(4)]];
 };
 
-vertex main0_out main0(main0_in in [[stage_in]], constant float2& uFramebufferSize [[buffer(0)]])
+vertex main0_out main0(main0_in in [[stage_in]], constant float2& uFramebufferSize [[buffer(0)]], constant float2& uTileSize [[buffer(1)]])
 {
     main0_out out = {};
-    float2 destPosition = float2(int2(in.aDestTileIndex % 256, in.aDestTileIndex / 256) + in.aTileOffset);
-    float2 srcPosition = float2(int2(in.aSrcTileIndex % 256, in.aSrcTileIndex / 256) + in.aTileOffset);
-    destPosition *= (float2(16.0, 4.0) / uFramebufferSize);
-    srcPosition *= (float2(16.0, 4.0) / uFramebufferSize);
+    int tilesPerRow = int(uFramebufferSize.x / uTileSize.x);
+    float2 destPosition = float2(int2(in.aDestTileIndex % tilesPerRow, in.aDestTileIndex / tilesPerRow) + in.aTileOffset);
+    float2 srcPosition = float2(int2(in.aSrcTileIndex % tilesPerRow, in.aSrcTileIndex / tilesPerRow) + in.aTileOffset);
+    destPosition *= (float2(uTileSize.x, uTileSize.y * 0.25) / uFramebufferSize);
+    srcPosition *= (float2(uTileSize.x, uTileSize.y * 0.25) / uFramebufferSize);
     out.vTexCoord0 = destPosition;
     out.vTexCoord1 = srcPosition;
     out.vBackdrop0 = float(in.aDestBackdrop);
@@ -16,11 +16,12 @@ struct main0_in
     int aTileIndex [[attribute(1)]];
 };
 
-vertex main0_out main0(main0_in in [[stage_in]], constant float2& uFramebufferSize [[buffer(0)]])
+vertex main0_out main0(main0_in in [[stage_in]], constant float2& uFramebufferSize [[buffer(0)]], constant float2& uTileSize [[buffer(1)]])
 {
     main0_out out = {};
-    float2 position = float2(int2(in.aTileIndex % 256, in.aTileIndex / 256) + in.aTileOffset);
-    position *= (float2(16.0, 4.0) / uFramebufferSize);
+    int tilesPerRow = int(uFramebufferSize.x / uTileSize.x);
+    float2 position = float2(int2(in.aTileIndex % tilesPerRow, in.aTileIndex / tilesPerRow) + in.aTileOffset);
+    position *= (float2(uTileSize.x, uTileSize.y * 0.25) / uFramebufferSize);
     out.vTexCoord = position;
     if (in.aTileIndex < 0)
     {
@@ -0,0 +1,54 @@
+#version 330
+
+// pathfinder/shaders/blit_linear.fs.glsl
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Copies premultiplied colors in linear light to an sRGB-encoded framebuffer, tone mapping them
+// first if requested.
+
+precision highp float;
+
+#ifdef GL_ES
+precision highp sampler2D;
+#endif
+
+#define TONE_MAPPING_NONE       0
+#define TONE_MAPPING_REINHARD   1
+#define TONE_MAPPING_ACES       2
+
+uniform sampler2D uSrc;
+uniform int uToneMapping;
+
+in vec2 vTexCoord;
+
+out vec4 oFragColor;
+
+vec3 toneMap(vec3 color, int toneMapping) {
+    color = max(color, vec3(0.0));
+    if (toneMapping == TONE_MAPPING_REINHARD)
+        return color / (1.0 + color);
+    if (toneMapping == TONE_MAPPING_ACES) {
+        // Krzysztof Narkowicz's fit: https://knarkowicz.wordpress.com/2016/01/06/
+        return (color * (2.51 * color + 0.03)) / (color * (2.43 * color + 0.59) + 0.14);
+    }
+    return color;
+}
+
+vec3 linearToSRGB(vec3 color) {
+    color = clamp(color, 0.0, 1.0);
+    return mix(color * 12.92, 1.055 * pow(color, vec3(1.0 / 2.4)) - 0.055, step(0.0031308, color));
+}
+
+void main() {
+    vec4 color = texture(uSrc, vTexCoord);
+    if (color.a > 0.0)
+        color.rgb = linearToSRGB(toneMap(color.rgb / color.a, uToneMapping)) * color.a;
+    oFragColor = color;
+}
@@ -0,0 +1,26 @@
+#version 430
+
+// pathfinder/shaders/d3d11/tile_rgba16f.cs.glsl
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+#extension GL_GOOGLE_include_directive : enable
+
+precision highp float;
+
+#ifdef GL_ES
+precision highp sampler2D;
+#endif
+
+layout(local_size_x = 16, local_size_y = 4) in;
+
+#define PF_DEST_IMAGE_FORMAT   rgba16f
+#define PF_DEST_IMAGE_FLOAT
+
+#include "tile_compute.inc.glsl"
@@ -22,6 +22,7 @@ precision highp sampler2D;
 
 uniform mat4 uTransform;
 uniform vec2 uTileSize;
+uniform vec2 uMaskTextureSize0;
 uniform sampler2D uTextureMetadata;
 uniform ivec2 uTextureMetadataSize;
 uniform sampler2D uZBuffer;
@@ -55,7 +56,10 @@ void main() {
         return;
     }
 
-    uvec2 maskTileCoord = uvec2(aMaskTexCoord0.x, aMaskTexCoord0.y + 256u * aMaskTexCoord0.z);
+    // Mask rows hold fewer than 256 tiles when the tiles are wide.
+    uint maskTileIndex = aMaskTexCoord0.x | (aMaskTexCoord0.y << 8) | (aMaskTexCoord0.z << 16);
+    uint maskTilesPerRow = max(uint(uMaskTextureSize0.x / uTileSize.x), 1u);
+    uvec2 maskTileCoord = uvec2(maskTileIndex % maskTilesPerRow, maskTileIndex / maskTilesPerRow);
     vec2 maskTexCoord0 = (vec2(maskTileCoord) + tileOffset) * uTileSize;
     if (aCtrlBackdrop.y == 0 && aMaskTexCoord0.w != 0u) {
         gl_Position = vec4(0.0);
@@ -17,6 +17,7 @@ precision highp sampler2D;
 #endif
 
 uniform vec2 uFramebufferSize;
+uniform vec2 uTileSize;
 
 in ivec2 aTileOffset;
 in int aDestTileIndex;
@@ -30,10 +31,13 @@ out vec2 vTexCoord1;
 out float vBackdrop1;
 
 void main() {
-    vec2 destPosition = vec2(ivec2(aDestTileIndex % 256, aDestTileIndex / 256) + aTileOffset);
-    vec2 srcPosition  = vec2(ivec2(aSrcTileIndex  % 256, aSrcTileIndex  / 256) + aTileOffset);
-    destPosition *= vec2(16.0, 4.0) / uFramebufferSize;
-    srcPosition  *= vec2(16.0, 4.0) / uFramebufferSize;
+    int tilesPerRow = int(uFramebufferSize.x / uTileSize.x);
+    vec2 destPosition = vec2(ivec2(aDestTileIndex % tilesPerRow, aDestTileIndex / tilesPerRow) +
+                             aTileOffset);
+    vec2 srcPosition  = vec2(ivec2(aSrcTileIndex  % tilesPerRow, aSrcTileIndex  / tilesPerRow) +
+                             aTileOffset);
+    destPosition *= vec2(uTileSize.x, uTileSize.y * 0.25) / uFramebufferSize;
+    srcPosition  *= vec2(uTileSize.x, uTileSize.y * 0.25) / uFramebufferSize;
 
     vTexCoord0 = destPosition;
     vTexCoord1 = srcPosition;
@@ -17,6 +17,7 @@ precision highp sampler2D;
 #endif
 
 uniform vec2 uFramebufferSize;
+uniform vec2 uTileSize;
 
 in ivec2 aTileOffset;
 in int aTileIndex;
@@ -24,8 +25,9 @@ in int aTileIndex;
 out vec2 vTexCoord;
 
 void main() {
-    vec2 position = vec2(ivec2(aTileIndex % 256, aTileIndex / 256) + aTileOffset);
-    position *= vec2(16.0, 4.0) / uFramebufferSize;
+    int tilesPerRow = int(uFramebufferSize.x / uTileSize.x);
+    vec2 position = vec2(ivec2(aTileIndex % tilesPerRow, aTileIndex / tilesPerRow) + aTileOffset);
+    position *= vec2(uTileSize.x, uTileSize.y * 0.25) / uFramebufferSize;
 
     vTexCoord = position;
 
@@ -47,6 +47,7 @@
 #define COMBINER_CTRL_FILTER_TEXT               0x2
 #define COMBINER_CTRL_FILTER_BLUR               0x3
 #define COMBINER_CTRL_FILTER_COLOR_MATRIX       0x4
+#define COMBINER_CTRL_FILTER_CONIC_GRADIENT     0x5
 
 #define COMBINER_CTRL_COMPOSITE_MASK            0xf
 #define COMBINER_CTRL_COMPOSITE_NORMAL          0x0
@@ -301,6 +302,23 @@ vec4 filterRadialGradient(vec2 colorTexCoord,
     return color;
 }
 
+//                | x             y             z             w
+//  --------------+----------------------------------------------------
+//  filterParams0 | center.x      center.y      angle         -
+//  filterParams1 | -             -             uvOrigin.x    uvOrigin.y
+vec4 filterConicGradient(vec2 colorTexCoord,
+                         sampler2D colorTexture,
+                         vec4 filterParams0,
+                         vec4 filterParams1) {
+    vec2 center = filterParams0.xy, uvOrigin = filterParams1.zw;
+    float angle = filterParams0.z;
+
+    vec2 dP = colorTexCoord - center;
+    // Divide by 2π to map the angle onto the gradient.
+    float t = fract((atan(dP.y, dP.x) - angle) * 0.15915494);
+    return texture(colorTexture, uvOrigin + vec2(t, 0.0));
+}
+
 //                | x             y             z             w
 //  --------------+----------------------------------------------------
 //  filterParams0 | srcOffset.x   srcOffset.y   support       -
@@ -383,6 +401,11 @@ vec4 filterColor(vec2 colorTexCoord,
                                     framebufferSize,
                                     filterParams0,
                                     filterParams1);
+    case COMBINER_CTRL_FILTER_CONIC_GRADIENT:
+        return filterConicGradient(colorTexCoord,
+                                   colorTexture,
+                                   filterParams0,
+                                   filterParams1);
     case COMBINER_CTRL_FILTER_BLUR:
         return filterBlur(colorTexCoord,
                           colorTexture,
@@ -555,6 +578,20 @@ float sampleMask(float maskAlpha,
     return min(maskAlpha, coverage);
 }
 
+// Color spaces
+//
+// Components above 1.0 are left alone so that high-dynamic-range colors survive conversion.
+
+vec3 sRGBToLinear(vec3 color) {
+    color = max(color, vec3(0.0));
+    return mix(color / 12.92, pow((color + 0.055) / 1.055, vec3(2.4)), step(0.04045, color));
+}
+
+vec3 linearToSRGB(vec3 color) {
+    color = max(color, vec3(0.0));
+    return mix(color * 12.92, 1.055 * pow(color, vec3(1.0 / 2.4)) - 0.055, step(0.0031308, color));
+}
+
 // Main function
 
 vec4 calculateColor(vec2 fragCoord,
@@ -574,7 +611,8 @@ vec4 calculateColor(vec2 fragCoord,
                     vec3 maskTexCoord0,
                     vec2 colorTexCoord0,
                     vec4 baseColor,
-                    int tileCtrl) {
+                    int tileCtrl,
+                    bool linearBlending) {
     // Sample mask.
     int maskCtrl0 = (tileCtrl >> TILE_CTRL_MASK_0_SHIFT) & TILE_CTRL_MASK_MASK;
     float maskAlpha = 1.0;
@@ -604,6 +642,10 @@ vec4 calculateColor(vec2 fragCoord,
     // Apply mask.
     color.a *= maskAlpha;
 
+    // Convert to linear light if we're blending there.
+    if (linearBlending)
+        color.rgb = sRGBToLinear(color.rgb);
+
     // Apply composite.
     int compositeOp = (ctrl >> COMBINER_CTRL_COMPOSITE_SHIFT) & COMBINER_CTRL_COMPOSITE_MASK;
     color = composite(color, destTexture, framebufferSize, fragCoord, compositeOp);
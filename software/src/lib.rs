@@ -0,0 +1,1029 @@
+// pathfinder/software/src/lib.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A device that renders on the CPU.
+//!
+//! Textures, framebuffers, and buffers live in memory, and draw calls run Rust ports of
+//! Pathfinder's shaders through a simple rasterizer that emulates OpenGL's fixed-function
+//! pipeline. This is slow, but it produces real images without a GPU, which makes it useful for
+//! testing the renderer's output and for headless rendering.
+//!
+//! Shaders are looked up by name among the built-in ports; the shader source is ignored. Only the
+//! programs used by the D3D9 renderer level and the debug UI are ported, so the device reports
+//! `FeatureLevel::D3D10`. Compute shaders aren't supported.
+
+use crate::raster::{BufferData as BufferBytes, Indices, VertexArrayState};
+use crate::shaders::ProgramPort;
+use pathfinder_geometry::rect::RectI;
+use pathfinder_geometry::vector::Vector2I;
+use pathfinder_gpu::{BufferData, BufferTarget, BufferUploadMode, ComputeDimensions, ComputeState};
+use pathfinder_gpu::{Device, FeatureLevel, ProgramKind, RenderState, RenderTarget, ShaderKind};
+use pathfinder_gpu::{TextureData, TextureDataRef, TextureFormat, TextureSamplingFlags};
+use pathfinder_gpu::VertexAttrDescriptor;
+use pathfinder_resources::ResourceLoader;
+use pathfinder_simd::default::F32x4;
+use std::cell::{Cell, RefCell};
+use std::mem;
+use std::ops::Range;
+use std::rc::Rc;
+use std::slice;
+use std::time::{Duration, Instant};
+
+mod raster;
+mod shaders;
+mod texture;
+
+/// A `Device` that renders on the CPU.
+pub struct SoftwareDevice {
+    default_framebuffer: SoftwareFramebuffer,
+}
+
+impl SoftwareDevice {
+    /// Creates a new software device with an RGBA8 default framebuffer of the given size.
+    #[inline]
+    pub fn new(default_framebuffer_size: Vector2I) -> SoftwareDevice {
+        SoftwareDevice {
+            default_framebuffer: SoftwareFramebuffer::new(SoftwareTexture::new(
+                TextureFormat::RGBA8,
+                default_framebuffer_size)),
+        }
+    }
+
+    /// Resizes the default framebuffer, discarding its contents.
+    pub fn resize_default_framebuffer(&mut self, new_size: Vector2I) {
+        self.default_framebuffer =
+            SoftwareFramebuffer::new(SoftwareTexture::new(TextureFormat::RGBA8, new_size));
+    }
+
+    fn render_target_framebuffer<'a>(&'a self, target: &'a RenderTarget<SoftwareDevice>)
+                                     -> &'a SoftwareFramebuffer {
+        match *target {
+            RenderTarget::Default => &self.default_framebuffer,
+            RenderTarget::Framebuffer(framebuffer) => framebuffer,
+        }
+    }
+}
+
+pub struct SoftwareBuffer {
+    data: BufferBytes,
+    pub mode: BufferUploadMode,
+}
+
+pub struct SoftwareBufferDataReceiver {
+    data: Vec<u8>,
+}
+
+pub struct SoftwareFence;
+
+pub struct SoftwareFramebuffer {
+    texture: SoftwareTexture,
+    depth: RefCell<Vec<f32>>,
+    stencil: RefCell<Vec<u8>>,
+}
+
+impl SoftwareFramebuffer {
+    fn new(texture: SoftwareTexture) -> SoftwareFramebuffer {
+        let pixel_count = texture.texels.borrow().len();
+        SoftwareFramebuffer {
+            texture,
+            depth: RefCell::new(vec![1.0; pixel_count]),
+            stencil: RefCell::new(vec![0; pixel_count]),
+        }
+    }
+}
+
+pub struct SoftwareImageParameter;
+
+pub struct SoftwareProgram {
+    port: &'static ProgramPort,
+}
+
+pub struct SoftwareShader {
+    pub name: String,
+    pub kind: ShaderKind,
+}
+
+pub struct SoftwareStorageBuffer;
+
+pub struct SoftwareTexture {
+    format: TextureFormat,
+    size: Vector2I,
+    texels: RefCell<Vec<F32x4>>,
+    sampling_flags: Cell<TextureSamplingFlags>,
+}
+
+impl SoftwareTexture {
+    fn new(format: TextureFormat, size: Vector2I) -> SoftwareTexture {
+        let texel_count = size.x().max(0) as usize * size.y().max(0) as usize;
+        SoftwareTexture {
+            format,
+            size,
+            texels: RefCell::new(vec![texture::quantize(F32x4::default(), format); texel_count]),
+            sampling_flags: Cell::new(TextureSamplingFlags::empty()),
+        }
+    }
+}
+
+pub struct SoftwareTextureDataReceiver {
+    data: TextureData,
+}
+
+pub struct SoftwareTextureParameter {
+    unit: Option<usize>,
+}
+
+pub struct SoftwareTimerQuery {
+    start: Cell<Option<Instant>>,
+    elapsed: Cell<Option<Duration>>,
+}
+
+pub struct SoftwareUniform {
+    location: Option<usize>,
+}
+
+pub struct SoftwareVertexArray {
+    state: RefCell<VertexArrayState>,
+}
+
+pub struct SoftwareVertexAttr {
+    location: usize,
+}
+
+impl Device for SoftwareDevice {
+    type Buffer = SoftwareBuffer;
+    type BufferDataReceiver = SoftwareBufferDataReceiver;
+    type Fence = SoftwareFence;
+    type Framebuffer = SoftwareFramebuffer;
+    type ImageParameter = SoftwareImageParameter;
+    type Program = SoftwareProgram;
+    type Shader = SoftwareShader;
+    type StorageBuffer = SoftwareStorageBuffer;
+    type Texture = SoftwareTexture;
+    type TextureDataReceiver = SoftwareTextureDataReceiver;
+    type TextureParameter = SoftwareTextureParameter;
+    type TimerQuery = SoftwareTimerQuery;
+    type Uniform = SoftwareUniform;
+    type VertexArray = SoftwareVertexArray;
+    type VertexAttr = SoftwareVertexAttr;
+
+    #[inline]
+    fn backend_name(&self) -> &'static str {
+        "Software"
+    }
+
+    #[inline]
+    fn device_name(&self) -> String {
+        "Software rasterizer".to_owned()
+    }
+
+    #[inline]
+    fn feature_level(&self) -> FeatureLevel {
+        FeatureLevel::D3D10
+    }
+
+    #[inline]
+    fn create_texture(&self, format: TextureFormat, size: Vector2I) -> SoftwareTexture {
+        SoftwareTexture::new(format, size)
+    }
+
+    fn create_texture_from_data(&self, format: TextureFormat, size: Vector2I, data: TextureDataRef)
+                                -> SoftwareTexture {
+        let texture = SoftwareTexture::new(format, size);
+        self.upload_to_texture(&texture, RectI::new(Vector2I::zero(), size), data);
+        texture
+    }
+
+    fn create_shader(&self, _: &dyn ResourceLoader, name: &str, kind: ShaderKind)
+                     -> SoftwareShader {
+        self.create_shader_from_source(name, &[], kind)
+    }
+
+    fn create_shader_from_source(&self, name: &str, _: &[u8], kind: ShaderKind)
+                                 -> SoftwareShader {
+        if !shaders::has_shader(name, kind) {
+            panic!("The software device has no port of the {:?} shader `{}`!", kind, name);
+        }
+        SoftwareShader { name: name.to_owned(), kind }
+    }
+
+    #[inline]
+    fn create_vertex_array(&self) -> SoftwareVertexArray {
+        SoftwareVertexArray { state: RefCell::new(VertexArrayState::default()) }
+    }
+
+    fn create_program_from_shaders(&self,
+                                   _: &dyn ResourceLoader,
+                                   name: &str,
+                                   shaders: ProgramKind<SoftwareShader>)
+                                   -> SoftwareProgram {
+        match shaders {
+            ProgramKind::Raster { vertex, fragment } => {
+                match shaders::find_program(&vertex.name, &fragment.name) {
+                    Some(port) => SoftwareProgram { port },
+                    None => panic!("The software device has no port of the program `{}`!", name),
+                }
+            }
+            ProgramKind::Compute(_) => {
+                panic!("The software device doesn't support compute shaders!")
+            }
+        }
+    }
+
+    #[inline]
+    fn set_compute_program_local_size(&self, _: &mut SoftwareProgram, _: ComputeDimensions) {}
+
+    fn get_vertex_attr(&self, program: &SoftwareProgram, name: &str)
+                       -> Option<SoftwareVertexAttr> {
+        program.port
+               .attributes
+               .iter()
+               .position(|&attribute| attribute == name)
+               .map(|location| SoftwareVertexAttr { location })
+    }
+
+    fn get_uniform(&self, program: &SoftwareProgram, name: &str) -> SoftwareUniform {
+        SoftwareUniform {
+            location: program.port.uniforms.iter().position(|&uniform| uniform == name),
+        }
+    }
+
+    fn get_texture_parameter(&self, program: &SoftwareProgram, name: &str)
+                             -> SoftwareTextureParameter {
+        SoftwareTextureParameter {
+            unit: program.port.textures.iter().position(|&texture| texture == name),
+        }
+    }
+
+    #[inline]
+    fn get_image_parameter(&self, _: &SoftwareProgram, _: &str) -> SoftwareImageParameter {
+        SoftwareImageParameter
+    }
+
+    #[inline]
+    fn get_storage_buffer(&self, _: &SoftwareProgram, _: &str, _: u32) -> SoftwareStorageBuffer {
+        SoftwareStorageBuffer
+    }
+
+    fn bind_buffer(&self,
+                   vertex_array: &SoftwareVertexArray,
+                   buffer: &SoftwareBuffer,
+                   target: BufferTarget) {
+        let mut state = vertex_array.state.borrow_mut();
+        match target {
+            BufferTarget::Vertex => state.vertex_buffer = Some(buffer.data.clone()),
+            BufferTarget::Index => state.index_buffer = Some(buffer.data.clone()),
+            BufferTarget::Storage => {}
+        }
+    }
+
+    fn configure_vertex_attr(&self,
+                             vertex_array: &SoftwareVertexArray,
+                             attr: &SoftwareVertexAttr,
+                             descriptor: &VertexAttrDescriptor) {
+        let mut state = vertex_array.state.borrow_mut();
+        let buffer = state.vertex_buffer
+                          .clone()
+                          .expect("A vertex buffer must be bound before configuring attributes!");
+        if state.attributes.len() <= attr.location {
+            state.attributes.resize(attr.location + 1, None);
+        }
+        state.attributes[attr.location] = Some((buffer, *descriptor));
+    }
+
+    #[inline]
+    fn create_framebuffer(&self, texture: SoftwareTexture) -> SoftwareFramebuffer {
+        SoftwareFramebuffer::new(texture)
+    }
+
+    #[inline]
+    fn create_buffer(&self, mode: BufferUploadMode) -> SoftwareBuffer {
+        SoftwareBuffer { data: Rc::new(RefCell::new(vec![])), mode }
+    }
+
+    fn allocate_buffer<T>(&self, buffer: &SoftwareBuffer, data: BufferData<T>, _: BufferTarget) {
+        *buffer.data.borrow_mut() = match data {
+            BufferData::Uninitialized(len) => vec![0; len * mem::size_of::<T>()],
+            BufferData::Memory(data) => as_bytes(data).to_vec(),
+        };
+    }
+
+    fn upload_to_buffer<T>(&self,
+                           buffer: &SoftwareBuffer,
+                           position: usize,
+                           data: &[T],
+                           _: BufferTarget) {
+        let data = as_bytes(data);
+        buffer.data.borrow_mut()[position..(position + data.len())].copy_from_slice(data);
+    }
+
+    #[inline]
+    fn framebuffer_texture<'f>(&self, framebuffer: &'f SoftwareFramebuffer)
+                               -> &'f SoftwareTexture {
+        &framebuffer.texture
+    }
+
+    #[inline]
+    fn destroy_framebuffer(&self, framebuffer: SoftwareFramebuffer) -> SoftwareTexture {
+        framebuffer.texture
+    }
+
+    #[inline]
+    fn texture_format(&self, texture: &SoftwareTexture) -> TextureFormat {
+        texture.format
+    }
+
+    #[inline]
+    fn texture_size(&self, texture: &SoftwareTexture) -> Vector2I {
+        texture.size
+    }
+
+    #[inline]
+    fn set_texture_sampling_mode(&self, texture: &SoftwareTexture, flags: TextureSamplingFlags) {
+        texture.sampling_flags.set(flags);
+    }
+
+    fn upload_to_texture(&self, texture: &SoftwareTexture, rect: RectI, data: TextureDataRef) {
+        assert!(rect.size().x() >= 0);
+        assert!(rect.size().y() >= 0);
+        assert!(rect.min_x() >= 0 && rect.min_y() >= 0);
+        assert!(rect.max_x() <= texture.size.x());
+        assert!(rect.max_y() <= texture.size.y());
+
+        let width = rect.width() as usize;
+        let new_texels = texture::texels_from_data(texture.format, data, rect.area() as usize);
+        let mut texels = texture.texels.borrow_mut();
+        if width > 0 {
+            for (row_index, row) in new_texels.chunks(width).enumerate() {
+                let start = ((rect.min_y() + row_index as i32) * texture.size.x() +
+                             rect.min_x()) as usize;
+                texels[start..(start + width)].copy_from_slice(row);
+            }
+        }
+        drop(texels);
+
+        self.set_texture_sampling_mode(texture, TextureSamplingFlags::empty());
+    }
+
+    fn read_pixels(&self, target: &RenderTarget<SoftwareDevice>, viewport: RectI)
+                   -> SoftwareTextureDataReceiver {
+        let texture = &self.render_target_framebuffer(target).texture;
+        let mut data = texture::texels_to_data(&texture.texels.borrow(),
+                                               texture.size.x(),
+                                               viewport,
+                                               texture.format);
+
+        // Like `glReadPixels()` followed by the flip that the OpenGL backend performs.
+        let (size, channels) = (viewport.size(), texture.format.channels());
+        match data {
+            TextureData::U8(ref mut pixels) => flip_y(pixels, size, channels),
+            TextureData::U16(ref mut pixels) => flip_y(pixels, size, channels),
+            TextureData::F16(ref mut pixels) => flip_y(pixels, size, channels),
+            TextureData::F32(ref mut pixels) => flip_y(pixels, size, channels),
+        }
+        SoftwareTextureDataReceiver { data }
+    }
+
+    fn read_buffer(&self, buffer: &SoftwareBuffer, _: BufferTarget, range: Range<usize>)
+                   -> SoftwareBufferDataReceiver {
+        SoftwareBufferDataReceiver { data: buffer.data.borrow()[range].to_vec() }
+    }
+
+    #[inline]
+    fn begin_commands(&self) {}
+
+    #[inline]
+    fn end_commands(&self) {}
+
+    fn draw_arrays(&self, index_count: u32, render_state: &RenderState<SoftwareDevice>) {
+        raster::draw(self, render_state, Indices::Sequential, index_count, 1);
+    }
+
+    fn draw_elements(&self, index_count: u32, render_state: &RenderState<SoftwareDevice>) {
+        raster::draw(self, render_state, Indices::IndexBuffer, index_count, 1);
+    }
+
+    fn draw_elements_instanced(&self,
+                               index_count: u32,
+                               instance_count: u32,
+                               render_state: &RenderState<SoftwareDevice>) {
+        raster::draw(self, render_state, Indices::IndexBuffer, index_count, instance_count);
+    }
+
+    fn dispatch_compute(&self, _: ComputeDimensions, _: &ComputeState<SoftwareDevice>) {
+        panic!("The software device doesn't support compute shaders!")
+    }
+
+    #[inline]
+    fn add_fence(&self) -> SoftwareFence {
+        SoftwareFence
+    }
+
+    #[inline]
+    fn wait_for_fence(&self, _: &SoftwareFence) {}
+
+    #[inline]
+    fn create_timer_query(&self) -> SoftwareTimerQuery {
+        SoftwareTimerQuery { start: Cell::new(None), elapsed: Cell::new(None) }
+    }
+
+    #[inline]
+    fn begin_timer_query(&self, query: &SoftwareTimerQuery) {
+        query.start.set(Some(Instant::now()));
+        query.elapsed.set(None);
+    }
+
+    #[inline]
+    fn end_timer_query(&self, query: &SoftwareTimerQuery) {
+        query.elapsed.set(query.start.get().map(|start| start.elapsed()));
+    }
+
+    #[inline]
+    fn try_recv_timer_query(&self, query: &SoftwareTimerQuery) -> Option<Duration> {
+        query.elapsed.get()
+    }
+
+    #[inline]
+    fn recv_timer_query(&self, query: &SoftwareTimerQuery) -> Duration {
+        query.elapsed.get().unwrap_or_default()
+    }
+
+    #[inline]
+    fn try_recv_texture_data(&self, receiver: &SoftwareTextureDataReceiver)
+                             -> Option<TextureData> {
+        Some(receiver.data.clone())
+    }
+
+    #[inline]
+    fn recv_texture_data(&self, receiver: &SoftwareTextureDataReceiver) -> TextureData {
+        receiver.data.clone()
+    }
+
+    #[inline]
+    fn try_recv_buffer(&self, receiver: &SoftwareBufferDataReceiver) -> Option<Vec<u8>> {
+        Some(receiver.data.clone())
+    }
+
+    #[inline]
+    fn recv_buffer(&self, receiver: &SoftwareBufferDataReceiver) -> Vec<u8> {
+        receiver.data.clone()
+    }
+}
+
+fn as_bytes<T>(data: &[T]) -> &[u8] {
+    unsafe { slice::from_raw_parts(data.as_ptr() as *const u8, mem::size_of_val(data)) }
+}
+
+// Flips a buffer of image data upside-down.
+fn flip_y<T>(pixels: &mut [T], size: Vector2I, channels: usize) {
+    let stride = size.x() as usize * channels;
+    for y in 0..(size.y() as usize / 2) {
+        let (index_a, index_b) = (y * stride, (size.y() as usize - y - 1) * stride);
+        for offset in 0..stride {
+            pixels.swap(index_a + offset, index_b + offset);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use pathfinder_color::{ColorF, ColorU};
+    use pathfinder_content::gradient::Gradient;
+    use pathfinder_content::outline::{Contour, Outline};
+    use pathfinder_content::stroke::StrokeStyle;
+    use pathfinder_geometry::rect::{RectF, RectI};
+    use pathfinder_geometry::transform2d::Transform2F;
+    use pathfinder_geometry::vector::{Vector2I, vec2f, vec2i};
+    use pathfinder_gpu::{Device, RenderTarget, TextureData, TextureFormat};
+    use pathfinder_renderer::concurrent::executor::{Executor, SequentialExecutor};
+    use pathfinder_renderer::gpu::options::{DestFramebuffer, RendererLevel, RendererMode};
+    use pathfinder_renderer::gpu::options::RendererOptions;
+    use pathfinder_renderer::gpu::perf::RenderStats;
+    use pathfinder_renderer::gpu::renderer::Renderer;
+    use pathfinder_renderer::options::{BuildOptions, RenderCommandListener, RenderTransform};
+    use pathfinder_renderer::paint::Paint;
+    use pathfinder_renderer::scene::{ClipPath, DeviceStroke, DrawPath, Layer, Scene, SceneSink};
+    use pathfinder_resources::embedded::EmbeddedResourceLoader;
+    use pathfinder_simd::default::F32x2;
+    use std::mem;
+    use std::sync::{Arc, Mutex};
+    use super::SoftwareDevice;
+
+    const WINDOW_SIZE: i32 = 64;
+
+    // Builds vectors back to front, the way a parallel executor might finish them.
+    struct ReverseExecutor;
+
+    impl Executor for ReverseExecutor {
+        fn build_vector<T, F>(&self, length: usize, builder: F) -> Vec<T>
+                              where T: Send, F: Fn(usize) -> T + Send + Sync {
+            let mut vector: Vec<T> = (0..length).rev().map(builder).collect();
+            vector.reverse();
+            vector
+        }
+    }
+
+    fn new_renderer() -> Renderer<SoftwareDevice> {
+        new_renderer_with_mode(RendererMode::new(RendererLevel::D3D9))
+    }
+
+    fn new_renderer_with_mode(mode: RendererMode) -> Renderer<SoftwareDevice> {
+        let window_size = vec2i(WINDOW_SIZE, WINDOW_SIZE);
+        let options = RendererOptions {
+            dest: DestFramebuffer::full_window(window_size),
+            background_color: Some(ColorF::white()),
+            ..RendererOptions::default()
+        };
+        Renderer::new(SoftwareDevice::new(window_size),
+                      &EmbeddedResourceLoader::new(),
+                      mode,
+                      options)
+    }
+
+    fn new_scene(rects: &[RectF]) -> Scene {
+        let mut scene = Scene::new();
+        scene.set_view_box(RectF::new(vec2f(0.0, 0.0), vec2i(WINDOW_SIZE, WINDOW_SIZE).to_f32()));
+        let paint = scene.push_paint(&Paint::from_color(ColorU::black()));
+        for &rect in rects {
+            scene.push_draw_path(DrawPath::new(Outline::from_rect(rect), paint));
+        }
+        scene
+    }
+
+    // Returns the RGBA8 pixels of the default framebuffer, top row first.
+    fn read_pixels(renderer: &Renderer<SoftwareDevice>) -> Vec<u8> {
+        let device = renderer.device();
+        let viewport = RectI::new(Vector2I::zero(), vec2i(WINDOW_SIZE, WINDOW_SIZE));
+        let receiver = device.read_pixels(&RenderTarget::Default, viewport);
+        match device.recv_texture_data(&receiver) {
+            TextureData::U8(pixels) => pixels,
+            _ => panic!("Expected RGBA8 pixels!"),
+        }
+    }
+
+    // Renders the given rect in black on white and returns the RGBA8 pixels, top row first.
+    fn render_rect(rect: RectF) -> Vec<u8> {
+        render_rects(&[rect])
+    }
+
+    fn render_rects(rects: &[RectF]) -> Vec<u8> {
+        let mut renderer = new_renderer();
+        new_scene(rects).build_and_render(&mut renderer,
+                                          BuildOptions::default(),
+                                          SequentialExecutor);
+        read_pixels(&renderer)
+    }
+
+    // Builds all the scenes with one scene sink, so that whatever it caches carries over from
+    // frame to frame, and renders them in order. Returns the pixels and stats of each frame.
+    fn render_frames(scenes: Vec<Scene>, build_options: BuildOptions)
+                     -> Vec<(Vec<u8>, RenderStats)> {
+        render_frames_with_mode(scenes, build_options, RendererMode::new(RendererLevel::D3D9))
+    }
+
+    fn render_frames_with_mode(scenes: Vec<Scene>, build_options: BuildOptions, mode: RendererMode)
+                               -> Vec<(Vec<u8>, RenderStats)> {
+        let commands = Arc::new(Mutex::new(vec![]));
+        let commands_for_listener = commands.clone();
+        let listener = RenderCommandListener::new(Box::new(move |command| {
+            commands_for_listener.lock().unwrap().push(command)
+        }));
+        let mut sink = SceneSink::new(listener, mode.level);
+        let mut renderer = new_renderer_with_mode(mode);
+
+        scenes.into_iter().map(|mut scene| {
+            scene.build(build_options.clone(), &mut sink, &SequentialExecutor);
+            renderer.begin_scene();
+            for command in mem::take(&mut *commands.lock().unwrap()) {
+                renderer.render_command(&command);
+            }
+            renderer.end_scene();
+            (read_pixels(&renderer), *renderer.stats())
+        }).collect()
+    }
+
+    fn pixel(pixels: &[u8], x: i32, y: i32) -> [u8; 4] {
+        let index = (y * WINDOW_SIZE + x) as usize * 4;
+        [pixels[index], pixels[index + 1], pixels[index + 2], pixels[index + 3]]
+    }
+
+    // Sampling a texture may be off by one in the last bit compared to drawing directly.
+    fn assert_pixels_close(pixels: &[u8], expected_pixels: &[u8]) {
+        assert_pixels_within(pixels, expected_pixels, 1)
+    }
+
+    fn assert_pixels_within(pixels: &[u8], expected_pixels: &[u8], tolerance: i32) {
+        assert_eq!(pixels.len(), expected_pixels.len());
+        for (index, (&value, &expected_value)) in pixels.iter()
+                                                        .zip(expected_pixels.iter())
+                                                        .enumerate() {
+            assert!((value as i32 - expected_value as i32).abs() <= tolerance,
+                    "pixel {} was {}, expected {}",
+                    index / 4,
+                    value,
+                    expected_value);
+        }
+    }
+
+    #[test]
+    fn test_render_rect() {
+        let pixels = render_rect(RectF::new(vec2f(8.0, 16.0), vec2f(32.0, 24.0)));
+        assert_eq!(pixel(&pixels, 8, 16), [0, 0, 0, 255]);
+        assert_eq!(pixel(&pixels, 39, 39), [0, 0, 0, 255]);
+        assert_eq!(pixel(&pixels, 7, 16), [255, 255, 255, 255]);
+        assert_eq!(pixel(&pixels, 40, 39), [255, 255, 255, 255]);
+        assert_eq!(pixel(&pixels, 8, 15), [255, 255, 255, 255]);
+        assert_eq!(pixel(&pixels, 8, 40), [255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn test_render_antialiased_edge() {
+        let pixels = render_rect(RectF::new(vec2f(8.5, 8.0), vec2f(16.0, 16.0)));
+        let edge = pixel(&pixels, 8, 12);
+        assert!(edge[0] > 96 && edge[0] < 160, "edge pixel was {:?}", edge);
+        assert_eq!(edge[0], edge[1]);
+        assert_eq!(edge[0], edge[2]);
+        assert_eq!(pixel(&pixels, 9, 12), [0, 0, 0, 255]);
+        assert_eq!(pixel(&pixels, 7, 12), [255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn test_tile_cache_reuses_unchanged_tiles() {
+        let square = RectF::new(vec2f(8.5, 8.5), vec2f(16.0, 16.0));
+        let rects = [square, RectF::new(vec2f(40.0, 40.0), vec2f(16.0, 16.0))];
+        let moved_rects = [square, RectF::new(vec2f(40.0, 8.0), vec2f(16.0, 16.0))];
+        let build_options = BuildOptions { tile_cache: true, ..BuildOptions::default() };
+        let scenes = vec![new_scene(&rects), new_scene(&rects), new_scene(&moved_rects)];
+        let frames = render_frames(scenes, build_options);
+
+        let (ref first_pixels, stats) = frames[0];
+        assert_eq!(stats.dirty_tile_count, 16);
+        assert_eq!(stats.reused_tile_count, 0);
+
+        // Nothing changed, so nothing should be redrawn.
+        let (ref pixels, stats) = frames[1];
+        assert_eq!(stats.dirty_tile_count, 0);
+        assert_eq!(stats.reused_tile_count, 16);
+        assert_eq!(stats.total_tile_count, 0);
+        assert!(pixels == first_pixels);
+
+        // Moving the second square should only redraw the tiles it moved from and to.
+        let (ref pixels, stats) = frames[2];
+        assert_eq!(stats.dirty_tile_count, 8);
+        assert_eq!(stats.reused_tile_count, 8);
+        assert!(*pixels == render_rects(&moved_rects));
+    }
+
+    #[test]
+    fn test_layer_is_reused_when_only_its_transform_changes() {
+        let mut scene = new_scene(&[]);
+        let paint = scene.push_paint(&Paint::from_color(ColorU::black()));
+        let layer_id = scene.push_layer(Layer::new(vec2i(32, 32)));
+        let square = RectF::new(vec2f(0.0, 0.0), vec2f(16.0, 16.0));
+        scene.push_draw_path(DrawPath::new(Outline::from_rect(square), paint));
+        scene.pop_layer();
+        scene.set_layer_transform(layer_id, Transform2F::from_translation(vec2f(8.0, 8.0)));
+
+        let mut moved_scene = scene.clone();
+        moved_scene.set_layer_transform(layer_id,
+                                        Transform2F::from_translation(vec2f(24.0, 16.0)));
+
+        let (_, uncached_stats) = render_frames(vec![moved_scene.clone()],
+                                                BuildOptions::default())[0];
+        let frames = render_frames(vec![scene, moved_scene], BuildOptions::default());
+
+        let (ref pixels, _) = frames[0];
+        assert_pixels_close(pixels, &render_rect(RectF::new(vec2f(8.0, 8.0), vec2f(16.0, 16.0))));
+
+        // Only the path compositing the layer should have been drawn the second time.
+        let (ref pixels, stats) = frames[1];
+        assert!(stats.total_tile_count < uncached_stats.total_tile_count);
+        assert_pixels_close(pixels,
+                            &render_rect(RectF::new(vec2f(24.0, 16.0), vec2f(16.0, 16.0))));
+    }
+
+    #[test]
+    fn test_layers_share_a_texture_page_that_is_freed_once_unused() {
+        let mut scene = new_scene(&[]);
+        let paint = scene.push_paint(&Paint::from_color(ColorU::black()));
+        for &(origin, height) in &[(vec2f(8.0, 8.0), 16.0), (vec2f(40.0, 8.0), 8.0)] {
+            let layer_id = scene.push_layer(Layer::new(vec2i(16, 16)));
+            let square = RectF::new(vec2f(0.0, 0.0), vec2f(16.0, height));
+            scene.push_draw_path(DrawPath::new(Outline::from_rect(square), paint));
+            scene.pop_layer();
+            scene.set_layer_transform(layer_id, Transform2F::from_translation(origin));
+        }
+
+        let mut scenes = vec![scene];
+        scenes.extend((0..4).map(|_| new_scene(&[])));
+        let frames = render_frames(scenes, BuildOptions::default());
+
+        let (ref pixels, stats) = frames[0];
+        assert_eq!(stats.texture_page_count, 1);
+        assert_pixels_close(pixels, &render_rects(&[
+            RectF::new(vec2f(8.0, 8.0), vec2f(16.0, 16.0)),
+            RectF::new(vec2f(40.0, 8.0), vec2f(16.0, 8.0)),
+        ]));
+
+        // The page is kept for a few frames in case it's needed again, then freed.
+        assert_eq!(frames[3].1.texture_page_count, 1);
+        assert_eq!(frames[4].1.texture_page_count, 0);
+        assert_eq!(frames[4].1.texture_page_bytes, 0);
+    }
+
+    #[test]
+    fn test_tile_size_and_alpha_tile_pages_are_configurable() {
+        // A clipped gradient and an antialiased rect exercise fills, clips and solid tiles.
+        let mut scene = new_scene(&[RectF::new(vec2f(30.5, 36.25), vec2f(28.0, 20.0))]);
+        let mut gradient = Gradient::linear_from_points(vec2f(0.0, 0.0), vec2f(64.0, 0.0));
+        gradient.add_color_stop(ColorU::new(255, 0, 0, 255), 0.0);
+        gradient.add_color_stop(ColorU::new(0, 0, 255, 255), 1.0);
+        let paint = scene.push_paint(&Paint::from_gradient(gradient));
+        let clip_rect = RectF::new(vec2f(10.5, 2.25), vec2f(30.25, 56.5));
+        let clip_path = scene.push_clip_path(ClipPath::new(Outline::from_rect(clip_rect)));
+        let rect = RectF::new(vec2f(4.25, 6.5), vec2f(50.5, 40.75));
+        let mut draw_path = DrawPath::new(Outline::from_rect(rect), paint);
+        draw_path.set_clip_path(Some(clip_path));
+        scene.push_draw_path(draw_path);
+
+        let (ref expected_pixels, _) =
+            render_frames(vec![scene.clone()], BuildOptions::default())[0];
+
+        // Allocate the alpha mask in the smallest pages allowed.
+        let mode = RendererMode {
+            alpha_tiles_per_page: 256,
+            ..RendererMode::new(RendererLevel::D3D9)
+        };
+        for &tile_size in &[vec2i(8, 8), vec2i(32, 32), vec2i(4, 16)] {
+            let build_options = BuildOptions { tile_size, ..BuildOptions::default() };
+            let (ref pixels, stats) =
+                render_frames_with_mode(vec![scene.clone()], build_options, mode)[0];
+            assert!(stats.alpha_tile_count > 0);
+
+            // Fills are clamped to just inside their tile, so each path may leave a faint seam
+            // along the last column of a tile, wherever the tile boundaries happen to be.
+            assert_pixels_within(pixels, expected_pixels, 2);
+        }
+    }
+
+    #[test]
+    fn test_wide_tiles_wrap_mask_rows() {
+        // Each of these nested clipped paths needs its own alpha tile, which with 64×64 tiles is
+        // more than fit in one row of the mask.
+        let mut scene = new_scene(&[]);
+        let clip_rect = RectF::new(vec2f(2.5, 3.25), vec2f(58.5, 60.75));
+        let clip_path = scene.push_clip_path(ClipPath::new(Outline::from_rect(clip_rect)));
+        for index in 0..80 {
+            let color = ColorU::new(index * 3, 255 - index * 3, index % 2 * 255, 255);
+            let paint = scene.push_paint(&Paint::from_color(color));
+            let inset = index as f32 * 0.3 + 0.25;
+            let rect = RectF::new(vec2f(inset, inset), vec2f(64.0 - inset * 2.0, 64.0 - inset));
+            let mut draw_path = DrawPath::new(Outline::from_rect(rect), paint);
+            draw_path.set_clip_path(Some(clip_path));
+            scene.push_draw_path(draw_path);
+        }
+
+        let (ref expected_pixels, _) =
+            render_frames(vec![scene.clone()], BuildOptions::default())[0];
+
+        // Page sizes that aren't multiples of 256 are rounded to ones that are.
+        let build_options = BuildOptions { tile_size: vec2i(64, 64), ..BuildOptions::default() };
+        for &alpha_tiles_per_page in &[0, 300] {
+            let mode = RendererMode {
+                alpha_tiles_per_page,
+                ..RendererMode::new(RendererLevel::D3D9)
+            };
+            let (ref pixels, stats) =
+                render_frames_with_mode(vec![scene.clone()], build_options.clone(), mode)[0];
+            assert!(stats.alpha_tile_count > 64);
+            assert_pixels_within(pixels, expected_pixels, 2);
+        }
+    }
+
+    #[test]
+    fn test_paints_build_the_same_in_any_order() {
+        // Gradients of different lengths get ramps of different lengths, in different tiles.
+        let mut scene = new_scene(&[RectF::new(vec2f(2.0, 50.0), vec2f(60.0, 10.0))]);
+        for (index, &length) in [8.0, 64.0, 600.0, 24.0].iter().enumerate() {
+            let y = index as f32 * 12.0;
+            let mut gradient = Gradient::linear_from_points(vec2f(0.0, y), vec2f(length, y));
+            gradient.add_color_stop(ColorU::new(255, 0, 0, 255), 0.0);
+            gradient.add_color_stop(ColorU::new(0, 0, 255, 255), 1.0);
+            let paint = scene.push_paint(&Paint::from_gradient(gradient));
+            let rect = RectF::new(vec2f(2.0, y), vec2f(60.0, 10.0));
+            scene.push_draw_path(DrawPath::new(Outline::from_rect(rect), paint));
+        }
+        let mut gradient = Gradient::radial(vec2f(48.0, 24.0), F32x2::new(0.0, 12.0));
+        gradient.add_color_stop(ColorU::new(0, 255, 0, 255), 0.0);
+        gradient.add_color_stop(ColorU::new(255, 255, 0, 128), 1.0);
+        let paint = scene.push_paint(&Paint::from_gradient(gradient));
+        let rect = RectF::new(vec2f(36.0, 12.0), vec2f(24.0, 24.0));
+        scene.push_draw_path(DrawPath::new(Outline::from_rect(rect), paint));
+
+        let mut renderer = new_renderer();
+        scene.clone().build_and_render(&mut renderer,
+                                       BuildOptions::default(),
+                                       SequentialExecutor);
+        let expected_pixels = read_pixels(&renderer);
+
+        let mut renderer = new_renderer();
+        scene.build_and_render(&mut renderer, BuildOptions::default(), ReverseExecutor);
+        assert!(read_pixels(&renderer) == expected_pixels);
+
+        let stats = renderer.stats();
+        let phase_times = stats.cpu_build_phase_times;
+        assert!(phase_times.paint_time + phase_times.path_time + phase_times.segment_time +
+                phase_times.batch_time <= stats.cpu_build_time);
+    }
+
+    #[test]
+    fn test_paths_and_contours_outside_the_view_box_are_culled() {
+        // An ellipse reaching far above the view box.
+        let mut outline = Outline::new();
+        let mut ellipse = Contour::new();
+        ellipse.push_ellipse(&(Transform2F::from_translation(vec2f(32.0, -40.0)) *
+                               Transform2F::from_scale(vec2f(50.0, 60.0))));
+        outline.push_contour(ellipse);
+        let mut scene = new_scene(&[]);
+        let paint = scene.push_paint(&Paint::from_color(ColorU::black()));
+        scene.push_draw_path(DrawPath::new(outline.clone(), paint));
+        let (ref expected_pixels, stats) =
+            render_frames(vec![scene.clone()], BuildOptions::default())[0];
+        assert_eq!(stats.culled_path_count, 0);
+        assert_eq!(stats.culled_contour_count, 0);
+
+        // Add a square on each side of the view box to the ellipse, and a rect outside it.
+        for &origin in &[vec2f(-40.0, 20.0), vec2f(80.0, 20.0), vec2f(20.0, 80.0),
+                         vec2f(20.0, -40.0)] {
+            outline.push_contour(Contour::from_rect(RectF::new(origin, vec2f(16.0, 16.0))));
+        }
+        let mut scene = new_scene(&[RectF::new(vec2f(100.0, 8.0), vec2f(8.0, 8.0))]);
+        let paint = scene.push_paint(&Paint::from_color(ColorU::black()));
+        scene.push_draw_path(DrawPath::new(outline, paint));
+        let (ref pixels, stats) = render_frames(vec![scene], BuildOptions::default())[0];
+        assert_eq!(stats.culled_path_count, 1);
+        assert_eq!(stats.culled_contour_count, 4);
+        assert!(pixels == expected_pixels);
+    }
+
+    #[test]
+    fn test_paths_behind_opaque_rects_are_culled() {
+        let hidden_rect = RectF::new(vec2f(10.5, 10.5), vec2f(20.0, 20.0));
+        let occluding_rect = RectF::new(vec2f(8.25, 8.0), vec2f(30.0, 30.0));
+        let (ref pixels, stats) =
+            render_frames(vec![new_scene(&[hidden_rect, occluding_rect])],
+                          BuildOptions::default())[0];
+        assert_eq!(stats.culled_path_count, 1);
+        assert!(*pixels == render_rects(&[occluding_rect]));
+
+        // A translucent rect doesn't hide anything.
+        let mut scene = new_scene(&[hidden_rect]);
+        let paint = scene.push_paint(&Paint::from_color(ColorU::new(0, 0, 0, 128)));
+        scene.push_draw_path(DrawPath::new(Outline::from_rect(occluding_rect), paint));
+        let (_, stats) = render_frames(vec![scene], BuildOptions::default())[0];
+        assert_eq!(stats.culled_path_count, 0);
+    }
+
+    #[test]
+    fn test_tiles_behind_opaque_solid_tiles_are_culled() {
+        // The occluding rect covers the top two rows of tiles, hiding only part of the rect below.
+        let hidden_rect = RectF::new(vec2f(4.5, 4.5), vec2f(36.0, 36.0));
+        let occluding_rect = RectF::new(vec2f(-8.0, -8.0), vec2f(80.0, 44.0));
+        let (ref pixels, stats) =
+            render_frames(vec![new_scene(&[hidden_rect, occluding_rect])],
+                          BuildOptions::default())[0];
+        assert_eq!(stats.culled_path_count, 0);
+        assert_eq!(stats.culled_tile_count, 6);
+        let visible_rect = RectF::from_points(vec2f(4.5, 36.0), hidden_rect.lower_right());
+        assert_pixels_close(pixels, &render_rects(&[visible_rect, occluding_rect]));
+
+        // Translucent tiles don't hide anything.
+        let mut scene = new_scene(&[hidden_rect]);
+        let paint = scene.push_paint(&Paint::from_color(ColorU::new(0, 0, 0, 128)));
+        scene.push_draw_path(DrawPath::new(Outline::from_rect(occluding_rect), paint));
+        let (_, stats) = render_frames(vec![scene], BuildOptions::default())[0];
+        assert_eq!(stats.culled_tile_count, 0);
+    }
+
+    #[test]
+    fn test_flattening_tolerance_is_configurable() {
+        // A lens made of two quadratic curves.
+        let mut contour = Contour::new();
+        contour.push_endpoint(vec2f(6.0, 32.0));
+        contour.push_quadratic(vec2f(32.0, -12.0), vec2f(58.0, 32.0));
+        contour.push_quadratic(vec2f(32.0, 76.0), vec2f(6.0, 32.0));
+        contour.close();
+        let mut outline = Outline::new();
+        outline.push_contour(contour);
+        let mut scene = new_scene(&[]);
+        let paint = scene.push_paint(&Paint::from_color(ColorU::black()));
+        scene.push_draw_path(DrawPath::new(outline, paint));
+
+        let render = |flattening_tolerance| {
+            let build_options = BuildOptions { flattening_tolerance, ..BuildOptions::default() };
+            render_frames(vec![scene.clone()], build_options).pop().unwrap()
+        };
+        let (ref fine_pixels, fine_stats) = render(0.01);
+        let (ref pixels, stats) = render(BuildOptions::default().flattening_tolerance);
+        let (_, coarse_stats) = render(4.0);
+        assert!(coarse_stats.fill_count < stats.fill_count);
+        assert!(stats.fill_count < fine_stats.fill_count);
+
+        // Each pixel's coverage is off by no more than the tolerance.
+        assert_pixels_within(pixels, fine_pixels, 64);
+    }
+
+    #[test]
+    fn test_conics_are_flattened_within_the_tolerance() {
+        // An ellipse made of four conic sections.
+        let mut contour = Contour::new();
+        contour.push_ellipse(&(Transform2F::from_translation(vec2f(32.0, 32.0)) *
+                               Transform2F::from_scale(vec2f(26.0, 20.0))));
+        let mut outline = Outline::new();
+        outline.push_contour(contour);
+        let mut scene = new_scene(&[]);
+        let paint = scene.push_paint(&Paint::from_color(ColorU::black()));
+        scene.push_draw_path(DrawPath::new(outline, paint));
+
+        let render = |flattening_tolerance| {
+            let build_options = BuildOptions { flattening_tolerance, ..BuildOptions::default() };
+            render_frames(vec![scene.clone()], build_options).pop().unwrap()
+        };
+        let (ref fine_pixels, fine_stats) = render(0.01);
+        let (ref pixels, stats) = render(BuildOptions::default().flattening_tolerance);
+        let (_, coarse_stats) = render(4.0);
+        assert!(coarse_stats.fill_count < stats.fill_count);
+        assert!(stats.fill_count < fine_stats.fill_count);
+        assert_pixels_within(pixels, fine_pixels, 64);
+    }
+
+    #[test]
+    fn test_device_strokes_keep_their_width_when_zoomed() {
+        // A horizontal line, stroked 4 device pixels wide.
+        let mut contour = Contour::new();
+        contour.push_endpoint(vec2f(4.0, 12.0));
+        contour.push_endpoint(vec2f(28.0, 12.0));
+        let mut outline = Outline::new();
+        outline.push_contour(contour);
+        let mut scene = new_scene(&[]);
+        let paint = scene.push_paint(&Paint::from_color(ColorU::black()));
+        let mut draw_path = DrawPath::new(outline, paint);
+        let style = StrokeStyle { line_width: 4.0, ..StrokeStyle::default() };
+        draw_path.set_device_stroke(Some(DeviceStroke::new(style)));
+        scene.push_draw_path(draw_path);
+
+        // Returns the total coverage of the given column, in pixels.
+        let stroke_width = |scale: f32, x: i32| {
+            let transform = Transform2F::from_scale(vec2f(scale, scale));
+            let build_options = BuildOptions {
+                transform: RenderTransform::Transform2D(transform),
+                ..BuildOptions::default()
+            };
+            let (ref pixels, _) = render_frames(vec![scene.clone()], build_options)[0];
+            (0..WINDOW_SIZE).map(|y| 255 - pixel(pixels, x, y)[0] as i32).sum::<i32>() as f32 /
+                255.0
+        };
+        assert!((stroke_width(1.0, 16) - 4.0).abs() < 0.1);
+        assert!((stroke_width(2.0, 32) - 4.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_hdr_colors_survive_to_a_floating_point_destination() {
+        let window_size = vec2i(WINDOW_SIZE, WINDOW_SIZE);
+        let device = SoftwareDevice::new(window_size);
+        let texture = device.create_texture(TextureFormat::RGBA16F, window_size);
+        let options = RendererOptions {
+            dest: DestFramebuffer::Other(device.create_framebuffer(texture)),
+            background_color: Some(ColorF::transparent_black()),
+            ..RendererOptions::default()
+        };
+        let mut renderer = Renderer::new(device,
+                                         &EmbeddedResourceLoader::new(),
+                                         RendererMode::new(RendererLevel::D3D9),
+                                         options);
+
+        let mut scene = new_scene(&[]);
+        let paint = scene.push_paint(&Paint::from_color_f(ColorF::new(4.0, 1.5, 0.25, 1.0)));
+        let rect = RectF::new(vec2f(8.0, 8.0), vec2f(32.0, 32.0));
+        scene.push_draw_path(DrawPath::new(Outline::from_rect(rect), paint));
+        scene.build_and_render(&mut renderer, BuildOptions::default(), SequentialExecutor);
+
+        let device = renderer.device();
+        let viewport = RectI::new(Vector2I::zero(), window_size);
+        let receiver = device.read_pixels(&renderer.draw_render_target(), viewport);
+        let pixels = match device.recv_texture_data(&receiver) {
+            TextureData::F16(pixels) => pixels,
+            _ => panic!("Expected RGBA16F pixels!"),
+        };
+        let index = (16 * WINDOW_SIZE + 16) as usize * 4;
+        let color: Vec<f32> = pixels[index..(index + 4)].iter().map(|value| value.to_f32())
+                                                          .collect();
+        assert_eq!(color, vec![4.0, 1.5, 0.25, 1.0]);
+    }
+}
@@ -0,0 +1,520 @@
+// pathfinder/software/src/raster.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! The fixed-function parts of the pipeline: vertex fetch, rasterization, and the per-fragment
+//! depth, stencil, and blending operations.
+//!
+//! This follows OpenGL conventions: the origin of the framebuffer is at the lower left, and
+//! pixels are sampled at their centers. Triangle edges follow a top-left fill rule, so pixels on
+//! an edge shared by two triangles are drawn exactly once, as additive blending requires.
+//! Primitives with a vertex behind the eye (`w <= 0`) are dropped instead of clipped.
+
+use crate::shaders::{FragmentInput, ProgramPort, ShaderEnv, VertexInput, VertexOutput};
+use crate::texture::{self, Sampler};
+use crate::{SoftwareDevice, SoftwareTexture};
+use pathfinder_geometry::rect::RectI;
+use pathfinder_geometry::vector::{Vector2I, vec2i};
+use pathfinder_gpu::{BlendFactor, BlendOp, BlendState, DepthFunc, Primitive, RenderOptions};
+use pathfinder_gpu::{RenderState, RenderTarget, StencilFunc, TextureFormat, UniformData};
+use pathfinder_gpu::{VertexAttrClass, VertexAttrDescriptor, VertexAttrType};
+use pathfinder_simd::default::{F32x2, F32x4};
+use std::cell::{Ref, RefCell};
+use std::ptr;
+use std::rc::Rc;
+
+const SUBPIXEL_BITS: u32 = 8;
+const SUBPIXEL_SCALE: f32 = (1 << SUBPIXEL_BITS) as f32;
+
+// Keeps edge function products within `i64`.
+const MAX_COORDINATE: f32 = (1 << 20) as f32;
+
+pub(crate) type BufferData = Rc<RefCell<Vec<u8>>>;
+
+/// The state of a vertex array object.
+#[derive(Default)]
+pub(crate) struct VertexArrayState {
+    /// The buffers and layouts of the attributes, indexed by attribute location.
+    pub(crate) attributes: Vec<Option<(BufferData, VertexAttrDescriptor)>>,
+    /// The buffer that subsequently configured attributes read from.
+    pub(crate) vertex_buffer: Option<BufferData>,
+    pub(crate) index_buffer: Option<BufferData>,
+}
+
+/// Where a draw call gets its vertex indices from.
+#[derive(Clone, Copy)]
+pub(crate) enum Indices {
+    Sequential,
+    IndexBuffer,
+}
+
+/// Executes a draw call.
+pub(crate) fn draw(device: &SoftwareDevice,
+                   render_state: &RenderState<SoftwareDevice>,
+                   indices: Indices,
+                   index_count: u32,
+                   instance_count: u32) {
+    let framebuffer = match *render_state.target {
+        RenderTarget::Default => &device.default_framebuffer,
+        RenderTarget::Framebuffer(framebuffer) => framebuffer,
+    };
+    let target_texture = &framebuffer.texture;
+    let port = render_state.program.port;
+
+    clear(framebuffer, &render_state.options);
+
+    // Gather uniforms and textures. A texture that's also the render target is copied first, as
+    // reading and writing the same texture in one draw call is undefined anyway.
+    let mut uniforms: Vec<Option<UniformData>> = vec![None; port.uniforms.len()];
+    for &(uniform, data) in render_state.uniforms {
+        if let Some(location) = uniform.location {
+            uniforms[location] = Some(data);
+        }
+    }
+    let texels: Vec<(usize, TexelData, &SoftwareTexture)> =
+        render_state.textures.iter().filter_map(|&(parameter, texture)| {
+            let unit = parameter.unit?;
+            let texels = if ptr::eq(texture, target_texture) {
+                TexelData::Owned(texture.texels.borrow().clone())
+            } else {
+                TexelData::Borrowed(texture.texels.borrow())
+            };
+            Some((unit, texels, texture))
+        }).collect();
+    let mut samplers: Vec<Option<Sampler>> = vec![None; port.textures.len()];
+    for &(unit, ref texels, texture) in &texels {
+        samplers[unit] = Some(Sampler::new(texels.as_slice(),
+                                           texture.size,
+                                           texture.sampling_flags.get()));
+    }
+    let env = ShaderEnv { uniforms: &uniforms, textures: &samplers };
+
+    // Gather vertex data.
+    let vertex_array = render_state.vertex_array.state.borrow();
+    let attributes: Vec<Option<(Ref<Vec<u8>>, VertexAttrDescriptor)>> =
+        vertex_array.attributes.iter().map(|attribute| {
+            attribute.as_ref().map(|&(ref buffer, descriptor)| (buffer.borrow(), descriptor))
+        }).collect();
+    let index_list: Vec<u32> = match indices {
+        Indices::Sequential => (0..index_count).collect(),
+        Indices::IndexBuffer => {
+            let index_buffer = vertex_array.index_buffer
+                                           .as_ref()
+                                           .expect("No index buffer bound!")
+                                           .borrow();
+            index_buffer.chunks_exact(4).take(index_count as usize).map(|bytes| {
+                u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+            }).collect()
+        }
+    };
+    let vertex_count = index_list.iter().map(|&index| index as usize + 1).max().unwrap_or(0);
+
+    let mut target = Target {
+        texels: &mut target_texture.texels.borrow_mut(),
+        depth: &mut framebuffer.depth.borrow_mut(),
+        stencil: &mut framebuffer.stencil.borrow_mut(),
+        size: target_texture.size,
+        format: target_texture.format,
+    };
+    let viewport = render_state.viewport;
+    let scissor = viewport.intersection(RectI::new(Vector2I::zero(), target.size))
+                          .unwrap_or_default();
+    let rasterizer = Rasterizer { port, env: &env, options: &render_state.options, scissor };
+
+    let mut inputs = vec![VertexInput::default(); port.attributes.len()];
+    let mut vertex_cache: Vec<Option<ClipVertex>> = vec![None; vertex_count];
+    for instance in 0..instance_count {
+        vertex_cache.iter_mut().for_each(|vertex| *vertex = None);
+        let mut shade_vertex = |index: u32| -> ClipVertex {
+            if let Some(vertex) = vertex_cache[index as usize] {
+                return vertex;
+            }
+            for (location, input) in inputs.iter_mut().enumerate() {
+                *input = match attributes.get(location) {
+                    Some(Some((ref data, ref descriptor))) => {
+                        fetch_attribute(data, descriptor, index, instance)
+                    }
+                    _ => VertexInput::default(),
+                };
+            }
+            let mut output = VertexOutput::default();
+            (port.vertex)(&env, &inputs, &mut output);
+            let vertex = ClipVertex::new(&output, viewport);
+            vertex_cache[index as usize] = Some(vertex);
+            vertex
+        };
+
+        match render_state.primitive {
+            Primitive::Triangles => {
+                for triangle in index_list.chunks_exact(3) {
+                    let vertices = [
+                        shade_vertex(triangle[0]),
+                        shade_vertex(triangle[1]),
+                        shade_vertex(triangle[2]),
+                    ];
+                    rasterizer.draw_triangle(&mut target, &vertices);
+                }
+            }
+            Primitive::Lines => {
+                for line in index_list.chunks_exact(2) {
+                    let vertices = [shade_vertex(line[0]), shade_vertex(line[1])];
+                    rasterizer.draw_line(&mut target, &vertices);
+                }
+            }
+        }
+    }
+}
+
+/// Applies the clear operations of a draw call to the whole render target, ignoring the
+/// viewport, as `glClear()` does.
+fn clear(framebuffer: &crate::SoftwareFramebuffer, options: &RenderOptions) {
+    if let Some(color) = options.clear_ops.color {
+        let texture = &framebuffer.texture;
+        let color = texture::quantize(color.0, texture.format);
+        texture.texels.borrow_mut().iter_mut().for_each(|texel| *texel = color);
+    }
+    if let Some(depth) = options.clear_ops.depth {
+        framebuffer.depth.borrow_mut().iter_mut().for_each(|value| *value = depth);
+    }
+    if let Some(stencil) = options.clear_ops.stencil {
+        framebuffer.stencil.borrow_mut().iter_mut().for_each(|value| *value = stencil);
+    }
+}
+
+enum TexelData<'a> {
+    Borrowed(Ref<'a, Vec<F32x4>>),
+    Owned(Vec<F32x4>),
+}
+
+impl<'a> TexelData<'a> {
+    fn as_slice(&self) -> &[F32x4] {
+        match *self {
+            TexelData::Borrowed(ref texels) => texels,
+            TexelData::Owned(ref texels) => texels,
+        }
+    }
+}
+
+fn fetch_attribute(data: &[u8], descriptor: &VertexAttrDescriptor, vertex: u32, instance: u32)
+                   -> VertexInput {
+    let element = instance.checked_div(descriptor.divisor).unwrap_or(vertex);
+    let component_size = match descriptor.attr_type {
+        VertexAttrType::F32 | VertexAttrType::I32 => 4,
+        VertexAttrType::I16 | VertexAttrType::U16 => 2,
+        VertexAttrType::I8 | VertexAttrType::U8 => 1,
+    };
+    let start = descriptor.offset + descriptor.stride * element as usize;
+    let mut input = VertexInput::default();
+    if start + component_size * descriptor.size > data.len() {
+        return input;
+    }
+
+    for component in 0..descriptor.size.min(4) {
+        let bytes = &data[(start + component * component_size)..];
+        let (int, float) = match descriptor.attr_type {
+            VertexAttrType::F32 => {
+                let value = f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+                (value as i32, value)
+            }
+            VertexAttrType::I32 => {
+                let value = i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+                (value, value as f32)
+            }
+            VertexAttrType::I16 => {
+                let value = i16::from_le_bytes([bytes[0], bytes[1]]);
+                (value as i32, normalize(value as f32, i16::MAX as f32, descriptor.class))
+            }
+            VertexAttrType::U16 => {
+                let value = u16::from_le_bytes([bytes[0], bytes[1]]);
+                (value as i32, normalize(value as f32, u16::MAX as f32, descriptor.class))
+            }
+            VertexAttrType::I8 => {
+                let value = bytes[0] as i8;
+                (value as i32, normalize(value as f32, i8::MAX as f32, descriptor.class))
+            }
+            VertexAttrType::U8 => {
+                let value = bytes[0];
+                (value as i32, normalize(value as f32, u8::MAX as f32, descriptor.class))
+            }
+        };
+        input.int[component] = int;
+        input.float[component] = float;
+    }
+    input
+}
+
+fn normalize(value: f32, max: f32, class: VertexAttrClass) -> f32 {
+    match class {
+        VertexAttrClass::FloatNorm => (value / max).max(-1.0),
+        VertexAttrClass::Float | VertexAttrClass::Int => value,
+    }
+}
+
+/// A shaded vertex in window coordinates.
+#[derive(Clone, Copy)]
+struct ClipVertex {
+    /// Window position and depth, or `None` if the vertex is behind the eye.
+    position: Option<(F32x2, f32)>,
+    inv_w: f32,
+    output: VertexOutput,
+}
+
+impl ClipVertex {
+    fn new(output: &VertexOutput, viewport: RectI) -> ClipVertex {
+        let w = output.position.w();
+        if w.is_nan() || w <= 0.0 {
+            return ClipVertex { position: None, inv_w: 0.0, output: *output };
+        }
+        let ndc = output.position * F32x4::splat(1.0 / w);
+        let window = viewport.origin().to_f32().0 +
+            (ndc.xy() + F32x2::splat(1.0)) * F32x2::splat(0.5) * viewport.size().to_f32().0;
+        let depth = (ndc.z() + 1.0) * 0.5;
+        let position = if window.x().abs() < MAX_COORDINATE && window.y().abs() < MAX_COORDINATE {
+            Some((window, depth))
+        } else {
+            None
+        };
+        ClipVertex { position, inv_w: 1.0 / w, output: *output }
+    }
+}
+
+struct Target<'a> {
+    texels: &'a mut [F32x4],
+    depth: &'a mut [f32],
+    stencil: &'a mut [u8],
+    size: Vector2I,
+    format: TextureFormat,
+}
+
+struct Rasterizer<'a> {
+    port: &'static ProgramPort,
+    env: &'a ShaderEnv<'a>,
+    options: &'a RenderOptions,
+    scissor: RectI,
+}
+
+impl<'a> Rasterizer<'a> {
+    fn draw_triangle(&self, target: &mut Target, vertices: &[ClipVertex; 3]) {
+        let mut positions = [(F32x2::default(), 0.0); 3];
+        let mut fixed = [(0i64, 0i64); 3];
+        for (index, vertex) in vertices.iter().enumerate() {
+            positions[index] = match vertex.position {
+                Some(position) => position,
+                None => return,
+            };
+            let window = positions[index].0;
+            fixed[index] = ((window.x() * SUBPIXEL_SCALE).round() as i64,
+                            (window.y() * SUBPIXEL_SCALE).round() as i64);
+        }
+
+        // Orient the triangle counterclockwise.
+        let mut order = [0, 1, 2];
+        let mut area = edge(fixed[0], fixed[1], fixed[2]);
+        if area == 0 {
+            return;
+        }
+        if area < 0 {
+            order = [0, 2, 1];
+            area = -area;
+        }
+        let p = [fixed[order[0]], fixed[order[1]], fixed[order[2]]];
+        let v = [&vertices[order[0]], &vertices[order[1]], &vertices[order[2]]];
+        let z = [positions[order[0]].1, positions[order[1]].1, positions[order[2]].1];
+        let edges = [(p[1], p[2]), (p[2], p[0]), (p[0], p[1])];
+        let top_left = [
+            is_top_left(edges[0].0, edges[0].1),
+            is_top_left(edges[1].0, edges[1].1),
+            is_top_left(edges[2].0, edges[2].1),
+        ];
+
+        // Find the pixels whose centers might be inside.
+        let min_x = p.iter().map(|p| p.0).min().unwrap() >> SUBPIXEL_BITS;
+        let min_y = p.iter().map(|p| p.1).min().unwrap() >> SUBPIXEL_BITS;
+        let max_x = (p.iter().map(|p| p.0).max().unwrap() >> SUBPIXEL_BITS) + 1;
+        let max_y = (p.iter().map(|p| p.1).max().unwrap() >> SUBPIXEL_BITS) + 1;
+        let bounds = RectI::from_points(vec2i(min_x as i32, min_y as i32),
+                                        vec2i(max_x as i32, max_y as i32));
+        let bounds = match bounds.intersection(self.scissor) {
+            Some(bounds) => bounds,
+            None => return,
+        };
+
+        let varying_count = self.port.varying_count;
+        let mut varyings = [0.0; crate::shaders::MAX_VARYINGS];
+        let half_pixel = 1 << (SUBPIXEL_BITS - 1);
+        for y in bounds.min_y()..bounds.max_y() {
+            for x in bounds.min_x()..bounds.max_x() {
+                let sample = (((x as i64) << SUBPIXEL_BITS) + half_pixel,
+                              ((y as i64) << SUBPIXEL_BITS) + half_pixel);
+                let mut weights = [0i64; 3];
+                let mut inside = true;
+                for index in 0..3 {
+                    let weight = edge(edges[index].0, edges[index].1, sample);
+                    if weight < 0 || (weight == 0 && !top_left[index]) {
+                        inside = false;
+                        break;
+                    }
+                    weights[index] = weight;
+                }
+                if !inside {
+                    continue;
+                }
+
+                // Barycentric coordinates, corrected for perspective for the varyings.
+                let l1 = weights[1] as f32 / area as f32;
+                let l2 = weights[2] as f32 / area as f32;
+                let depth = z[0] + l1 * (z[1] - z[0]) + l2 * (z[2] - z[0]);
+                let b = [
+                    weights[0] as f32 * v[0].inv_w,
+                    weights[1] as f32 * v[1].inv_w,
+                    weights[2] as f32 * v[2].inv_w,
+                ];
+                let sum = b[0] + b[1] + b[2];
+                let (b1, b2) = (b[1] / sum, b[2] / sum);
+                let (v0, v1, v2) = (&v[0].output.varyings,
+                                    &v[1].output.varyings,
+                                    &v[2].output.varyings);
+                // Written relative to the first vertex so that varyings that are the same at all
+                // vertices come out exactly.
+                for index in 0..varying_count {
+                    varyings[index] = v0[index] + b1 * (v1[index] - v0[index]) +
+                        b2 * (v2[index] - v0[index]);
+                }
+
+                self.draw_fragment(target, vec2i(x, y), depth, &varyings[0..varying_count]);
+            }
+        }
+    }
+
+    fn draw_line(&self, target: &mut Target, vertices: &[ClipVertex; 2]) {
+        let (from, to) = match (vertices[0].position, vertices[1].position) {
+            (Some(from), Some(to)) => (from, to),
+            _ => return,
+        };
+        let delta = to.0 - from.0;
+        let steps = delta.x().abs().max(delta.y().abs()).ceil().max(1.0) as i32;
+        let varying_count = self.port.varying_count;
+        let mut varyings = [0.0; crate::shaders::MAX_VARYINGS];
+        let (v0, v1) = (&vertices[0].output.varyings, &vertices[1].output.varyings);
+        for step in 0..steps {
+            let t = (step as f32 + 0.5) / steps as f32;
+            let point = (from.0 + delta * F32x2::splat(t)).floor().to_i32x2();
+            let point = vec2i(point.x(), point.y());
+            if !self.scissor.contains_point(point) {
+                continue;
+            }
+            for index in 0..varying_count {
+                varyings[index] = v0[index] + t * (v1[index] - v0[index]);
+            }
+            let depth = from.1 + t * (to.1 - from.1);
+            self.draw_fragment(target, point, depth, &varyings[0..varying_count]);
+        }
+    }
+
+    fn draw_fragment(&self, target: &mut Target, point: Vector2I, depth: f32, varyings: &[f32]) {
+        let index = (point.y() * target.size.x() + point.x()) as usize;
+
+        if let Some(stencil) = self.options.stencil {
+            let stencil_value = target.stencil[index] as u32;
+            let passed = match stencil.func {
+                StencilFunc::Always => true,
+                StencilFunc::Equal => {
+                    (stencil.reference & stencil.mask) == (stencil_value & stencil.mask)
+                }
+            };
+            if !passed {
+                return;
+            }
+        }
+
+        if let Some(depth_state) = self.options.depth {
+            let passed = match depth_state.func {
+                DepthFunc::Always => true,
+                DepthFunc::Less => depth < target.depth[index],
+            };
+            if !passed {
+                return;
+            }
+            if depth_state.write {
+                target.depth[index] = depth;
+            }
+        }
+
+        if let Some(stencil) = self.options.stencil {
+            if stencil.write {
+                let stencil_value = target.stencil[index] as u32;
+                target.stencil[index] = ((stencil_value & !stencil.mask) |
+                                         (stencil.reference & stencil.mask)) as u8;
+            }
+        }
+
+        if !self.options.color_mask {
+            return;
+        }
+
+        let frag_coord = point.to_f32().0 + F32x2::splat(0.5);
+        let mut color = (self.port.fragment)(self.env, &FragmentInput { frag_coord, varyings });
+        if !target.format.is_float() {
+            color = color.clamp(F32x4::default(), F32x4::splat(1.0));
+        }
+        if let Some(ref blend) = self.options.blend {
+            color = apply_blend(blend, color, target.texels[index]);
+        }
+        target.texels[index] = texture::quantize(color, target.format);
+    }
+}
+
+fn edge(from: (i64, i64), to: (i64, i64), point: (i64, i64)) -> i64 {
+    (to.0 - from.0) * (point.1 - from.1) - (to.1 - from.1) * (point.0 - from.0)
+}
+
+// Any rule works as long as each edge shared by two triangles, which traverse it in opposite
+// directions, belongs to exactly one of them.
+fn is_top_left(from: (i64, i64), to: (i64, i64)) -> bool {
+    let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+    dy < 0 || (dy == 0 && dx > 0)
+}
+
+fn apply_blend(blend: &BlendState, src: F32x4, dest: F32x4) -> F32x4 {
+    let rgb = blend_op(blend.op,
+                       src * blend_factor(blend.src_rgb_factor, src, dest),
+                       dest * blend_factor(blend.dest_rgb_factor, src, dest),
+                       src,
+                       dest);
+    let alpha = blend_op(blend.op,
+                         src * blend_factor(blend.src_alpha_factor, src, dest),
+                         dest * blend_factor(blend.dest_alpha_factor, src, dest),
+                         src,
+                         dest);
+    F32x4::new(rgb.x(), rgb.y(), rgb.z(), alpha.w())
+}
+
+fn blend_factor(factor: BlendFactor, src: F32x4, dest: F32x4) -> F32x4 {
+    match factor {
+        BlendFactor::Zero => F32x4::default(),
+        BlendFactor::One => F32x4::splat(1.0),
+        BlendFactor::SrcAlpha => F32x4::splat(src.w()),
+        BlendFactor::OneMinusSrcAlpha => F32x4::splat(1.0 - src.w()),
+        BlendFactor::DestAlpha => F32x4::splat(dest.w()),
+        BlendFactor::OneMinusDestAlpha => F32x4::splat(1.0 - dest.w()),
+        BlendFactor::DestColor => dest,
+    }
+}
+
+fn blend_op(op: BlendOp, weighted_src: F32x4, weighted_dest: F32x4, src: F32x4, dest: F32x4)
+            -> F32x4 {
+    match op {
+        BlendOp::Add => weighted_src + weighted_dest,
+        BlendOp::Subtract => weighted_src - weighted_dest,
+        BlendOp::ReverseSubtract => weighted_dest - weighted_src,
+        BlendOp::Min => src.min(dest),
+        BlendOp::Max => src.max(dest),
+    }
+}
+
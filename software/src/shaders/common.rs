@@ -0,0 +1,176 @@
+// pathfinder/software/src/shaders/common.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Ports of the programs shared by all renderer levels.
+
+use pathfinder_simd::default::{F32x2, F32x4};
+use super::{FragmentInput, ProgramPort, ShaderEnv, VertexInput, VertexOutput};
+use super::{mat4_mul, mix2, with_w};
+
+// blit.vs.glsl, blit.fs.glsl
+
+const BLIT_DEST_RECT: usize = 0;
+const BLIT_FRAMEBUFFER_SIZE: usize = 1;
+const BLIT_TONE_MAPPING: usize = 2;
+const BLIT_SRC: usize = 0;
+
+const V_TEX_COORD: usize = 0;
+
+pub(crate) static BLIT: ProgramPort = ProgramPort {
+    vertex_shader: "blit",
+    fragment_shader: "blit",
+    attributes: &["Position"],
+    uniforms: &["DestRect", "FramebufferSize"],
+    textures: &["Src"],
+    varying_count: 2,
+    vertex: blit_vertex,
+    fragment: blit_fragment,
+};
+
+pub(crate) static BLIT_LINEAR: ProgramPort = ProgramPort {
+    vertex_shader: "blit",
+    fragment_shader: "blit_linear",
+    attributes: &["Position"],
+    uniforms: &["DestRect", "FramebufferSize", "ToneMapping"],
+    textures: &["Src"],
+    varying_count: 2,
+    vertex: blit_vertex,
+    fragment: blit_linear_fragment,
+};
+
+fn blit_vertex(env: &ShaderEnv, inputs: &[VertexInput], output: &mut VertexOutput) {
+    let dest_rect = env.vec4(BLIT_DEST_RECT);
+    let position = inputs[0].ivec2().to_f32x2();
+    let tex_coord = position;
+    let position = mix2(dest_rect.xy(), dest_rect.zw(), position) / env.vec2(BLIT_FRAMEBUFFER_SIZE);
+    output.set_vec2(V_TEX_COORD, tex_coord);
+    let position = mix2(F32x2::splat(-1.0), F32x2::splat(1.0), position);
+    output.position = position.concat_xy_xy(F32x2::new(0.0, 1.0));
+}
+
+fn blit_fragment(env: &ShaderEnv, input: &FragmentInput) -> F32x4 {
+    env.texture(BLIT_SRC, input.vec2(V_TEX_COORD))
+}
+
+const TONE_MAPPING_REINHARD: i32 = 1;
+const TONE_MAPPING_ACES: i32 = 2;
+
+fn blit_linear_fragment(env: &ShaderEnv, input: &FragmentInput) -> F32x4 {
+    let color = env.texture(BLIT_SRC, input.vec2(V_TEX_COORD));
+    if color.w() <= 0.0 {
+        return color;
+    }
+    let rgb = tone_map(color / F32x4::splat(color.w()), env.int(BLIT_TONE_MAPPING));
+    with_w(linear_to_srgb(rgb) * F32x4::splat(color.w()), color.w())
+}
+
+fn tone_map(color: F32x4, tone_mapping: i32) -> F32x4 {
+    let color = color.max(F32x4::default());
+    match tone_mapping {
+        TONE_MAPPING_REINHARD => color / (F32x4::splat(1.0) + color),
+        TONE_MAPPING_ACES => {
+            // Krzysztof Narkowicz's fit: https://knarkowicz.wordpress.com/2016/01/06/
+            (color * (F32x4::splat(2.51) * color + F32x4::splat(0.03))) /
+                (color * (F32x4::splat(2.43) * color + F32x4::splat(0.59)) + F32x4::splat(0.14))
+        }
+        _ => color,
+    }
+}
+
+fn linear_to_srgb(color: F32x4) -> F32x4 {
+    let color = color.clamp(F32x4::default(), F32x4::splat(1.0));
+    let encode = |value: f32| {
+        if value < 0.0031308 { value * 12.92 } else { 1.055 * value.powf(1.0 / 2.4) - 0.055 }
+    };
+    F32x4::new(encode(color.x()), encode(color.y()), encode(color.z()), color.w())
+}
+
+// clear.vs.glsl, clear.fs.glsl
+
+const CLEAR_RECT: usize = 0;
+const CLEAR_FRAMEBUFFER_SIZE: usize = 1;
+const CLEAR_COLOR: usize = 2;
+
+pub(crate) static CLEAR: ProgramPort = ProgramPort {
+    vertex_shader: "clear",
+    fragment_shader: "clear",
+    attributes: &["Position"],
+    uniforms: &["Rect", "FramebufferSize", "Color"],
+    textures: &[],
+    varying_count: 0,
+    vertex: clear_vertex,
+    fragment: clear_fragment,
+};
+
+fn clear_vertex(env: &ShaderEnv, inputs: &[VertexInput], output: &mut VertexOutput) {
+    let rect = env.vec4(CLEAR_RECT);
+    let position = mix2(rect.xy(), rect.zw(), inputs[0].ivec2().to_f32x2()) /
+        env.vec2(CLEAR_FRAMEBUFFER_SIZE) * F32x2::splat(2.0) - F32x2::splat(1.0);
+    output.position = F32x4::new(position.x(), -position.y(), 0.0, 1.0);
+}
+
+fn clear_fragment(env: &ShaderEnv, _: &FragmentInput) -> F32x4 {
+    let color = env.vec4(CLEAR_COLOR);
+    with_w(color, 1.0) * F32x4::splat(color.w())
+}
+
+// reproject.vs.glsl, reproject.fs.glsl
+
+const REPROJECT_NEW_TRANSFORM: usize = 0;
+const REPROJECT_OLD_TRANSFORM: usize = 1;
+const REPROJECT_TEXTURE: usize = 0;
+
+pub(crate) static REPROJECT: ProgramPort = ProgramPort {
+    vertex_shader: "reproject",
+    fragment_shader: "reproject",
+    attributes: &["Position"],
+    uniforms: &["NewTransform", "OldTransform"],
+    textures: &["Texture"],
+    varying_count: 2,
+    vertex: reproject_vertex,
+    fragment: reproject_fragment,
+};
+
+fn reproject_vertex(env: &ShaderEnv, inputs: &[VertexInput], output: &mut VertexOutput) {
+    let position = inputs[0].ivec2().to_f32x2();
+    output.set_vec2(V_TEX_COORD, position);
+    output.position = mat4_mul(&env.mat4(REPROJECT_NEW_TRANSFORM),
+                               position.concat_xy_xy(F32x2::new(0.0, 1.0)));
+}
+
+fn reproject_fragment(env: &ShaderEnv, input: &FragmentInput) -> F32x4 {
+    let tex_coord = input.vec2(V_TEX_COORD).concat_xy_xy(F32x2::new(0.0, 1.0));
+    let norm_tex_coord = mat4_mul(&env.mat4(REPROJECT_OLD_TRANSFORM), tex_coord);
+    let tex_coord = (norm_tex_coord.xy() / F32x2::splat(norm_tex_coord.w()) + F32x2::splat(1.0)) *
+        F32x2::splat(0.5);
+    env.texture(REPROJECT_TEXTURE, tex_coord)
+}
+
+// stencil.vs.glsl, stencil.fs.glsl
+
+pub(crate) static STENCIL: ProgramPort = ProgramPort {
+    vertex_shader: "stencil",
+    fragment_shader: "stencil",
+    attributes: &["Position"],
+    uniforms: &[],
+    textures: &[],
+    varying_count: 0,
+    vertex: stencil_vertex,
+    fragment: stencil_fragment,
+};
+
+fn stencil_vertex(_: &ShaderEnv, inputs: &[VertexInput], output: &mut VertexOutput) {
+    output.position = with_w(inputs[0].float, 1.0);
+}
+
+fn stencil_fragment(_: &ShaderEnv, _: &FragmentInput) -> F32x4 {
+    // This should be color masked out.
+    F32x4::new(1.0, 0.0, 0.0, 1.0)
+}
@@ -0,0 +1,400 @@
+// pathfinder/software/src/shaders/d3d9.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Ports of the programs used by the D3D9 renderer level.
+
+use pathfinder_simd::default::{F32x2, F32x4};
+use super::tile_fragment::{self, TileFragmentParams};
+use super::{FragmentInput, ProgramPort, ShaderEnv, VertexInput, VertexOutput, mat4_mul, mix2};
+
+// d3d9/fill.vs.glsl, d3d9/fill.fs.glsl
+
+const FILL_TESS_COORD_ATTR: usize = 0;
+const FILL_LINE_SEGMENT_ATTR: usize = 1;
+const FILL_TILE_INDEX_ATTR: usize = 2;
+
+const FILL_FRAMEBUFFER_SIZE: usize = 0;
+const FILL_TILE_SIZE: usize = 1;
+const FILL_AREA_LUT: usize = 0;
+
+const V_FROM: usize = 0;
+const V_TO: usize = 2;
+
+pub(crate) static FILL: ProgramPort = ProgramPort {
+    vertex_shader: "d3d9/fill",
+    fragment_shader: "d3d9/fill",
+    attributes: &["TessCoord", "LineSegment", "TileIndex"],
+    uniforms: &["FramebufferSize", "TileSize"],
+    textures: &["AreaLUT"],
+    varying_count: 4,
+    vertex: fill_vertex,
+    fragment: fill_fragment,
+};
+
+fn compute_tile_offset(tile_index: u32, stencil_texture_width: f32, tile_size: F32x2) -> F32x2 {
+    let tiles_per_row = (stencil_texture_width / tile_size.x()) as u32;
+    let tile_offset = F32x2::new((tile_index % tiles_per_row) as f32,
+                                 (tile_index / tiles_per_row) as f32);
+    tile_offset * tile_size * F32x2::new(1.0, 0.25)
+}
+
+fn fill_vertex(env: &ShaderEnv, inputs: &[VertexInput], output: &mut VertexOutput) {
+    let (tile_size, framebuffer_size) = (env.vec2(FILL_TILE_SIZE),
+                                         env.vec2(FILL_FRAMEBUFFER_SIZE));
+    let tess_coord = inputs[FILL_TESS_COORD_ATTR].ivec2();
+    let tile_index = inputs[FILL_TILE_INDEX_ATTR].int() as u32;
+
+    let tile_origin = compute_tile_offset(tile_index, framebuffer_size.x(), tile_size);
+    let line_segment = inputs[FILL_LINE_SEGMENT_ATTR].int.to_f32x4() * F32x4::splat(1.0 / 256.0);
+    let (from, to) = (line_segment.xy(), line_segment.zw());
+
+    let mut position = F32x2::default();
+    if tess_coord.x() == 0 {
+        position.set_x(from.x().min(to.x()).floor());
+    } else {
+        position.set_x(from.x().max(to.x()).ceil());
+    }
+    if tess_coord.y() == 0 {
+        position.set_y(from.y().min(to.y()).floor());
+    } else {
+        position.set_y(tile_size.y());
+    }
+    position.set_y((position.y() * 0.25).floor());
+
+    // Since each fragment corresponds to 4 pixels on a scanline, the varying interpolation will
+    // land the fragment halfway between the four-pixel strip, at pixel offset 2.0. But we want to
+    // do our coverage calculation on the center of the first pixel in the strip instead, at pixel
+    // offset 0.5. This adjustment of 1.5 accomplishes that.
+    let offset = F32x2::new(0.0, 1.5) - position * F32x2::new(1.0, 4.0);
+    output.set_vec2(V_FROM, from + offset);
+    output.set_vec2(V_TO, to + offset);
+
+    let global_position = (tile_origin + position) / framebuffer_size * F32x2::splat(2.0) -
+        F32x2::splat(1.0);
+    output.position = global_position.concat_xy_xy(F32x2::new(0.0, 1.0));
+}
+
+fn fill_fragment(env: &ShaderEnv, input: &FragmentInput) -> F32x4 {
+    compute_coverage(env, input.vec2(V_FROM), input.vec2(V_TO))
+}
+
+// fill_area.inc.glsl
+fn compute_coverage(env: &ShaderEnv, from: F32x2, to: F32x2) -> F32x4 {
+    // Determine winding, and sort into a consistent order so we only need to find one root below.
+    let (left, right) = if from.x() < to.x() { (from, to) } else { (to, from) };
+
+    // Shoot a vertical ray toward the curve.
+    let window = F32x2::new(from.x(), to.x()).clamp(F32x2::splat(-0.5), F32x2::splat(0.5));
+    let dx = window.x() - window.y();
+    // A vertical line covers no area. GPUs return finite values for the NaN texture coordinates
+    // below, so the product is zero there, but that isn't true here.
+    if dx == 0.0 {
+        return F32x4::default();
+    }
+    let offset = window.x() + (window.y() - window.x()) * 0.5 - left.x();
+    let t = offset / (right.x() - left.x());
+
+    // Compute position and derivative to form a line approximation.
+    let y = left.y() + (right.y() - left.y()) * t;
+    let d = (right.y() - left.y()) / (right.x() - left.x());
+
+    // Look up area under that line, and scale horizontally to the window size.
+    let tex_coord = F32x2::new(y + 8.0, (d * dx).abs()) * F32x2::splat(1.0 / 16.0);
+    env.texture(FILL_AREA_LUT, tex_coord) * F32x4::splat(dx)
+}
+
+// d3d9/tile.vs.glsl, d3d9/tile.fs.glsl
+
+const TILE_TILE_OFFSET_ATTR: usize = 0;
+const TILE_TILE_ORIGIN_ATTR: usize = 1;
+const TILE_MASK_TEX_COORD_0_ATTR: usize = 2;
+const TILE_CTRL_BACKDROP_ATTR: usize = 3;
+const TILE_PATH_INDEX_ATTR: usize = 4;
+const TILE_COLOR_ATTR: usize = 5;
+
+const TILE_TRANSFORM: usize = 0;
+const TILE_TILE_SIZE: usize = 1;
+const TILE_TEXTURE_METADATA_SIZE: usize = 2;
+const TILE_Z_BUFFER_SIZE: usize = 3;
+const TILE_COLOR_TEXTURE_SIZE_0: usize = 4;
+const TILE_MASK_TEXTURE_SIZE_0: usize = 5;
+const TILE_FRAMEBUFFER_SIZE: usize = 6;
+const TILE_LINEAR_BLENDING: usize = 7;
+
+const TILE_TEXTURE_METADATA: usize = 0;
+const TILE_Z_BUFFER: usize = 1;
+const TILE_COLOR_TEXTURE_0: usize = 2;
+const TILE_MASK_TEXTURE_0: usize = 3;
+const TILE_DEST_TEXTURE: usize = 4;
+const TILE_GAMMA_LUT: usize = 5;
+
+const V_MASK_TEX_COORD_0: usize = 0;
+const V_COLOR_TEX_COORD_0: usize = 3;
+const V_BASE_COLOR: usize = 5;
+const V_TILE_CTRL: usize = 9;
+const V_FILTER_PARAMS_0: usize = 10;
+const V_FILTER_PARAMS_1: usize = 14;
+const V_FILTER_PARAMS_2: usize = 18;
+const V_FILTER_PARAMS_3: usize = 22;
+const V_FILTER_PARAMS_4: usize = 26;
+const V_CTRL: usize = 30;
+
+pub(crate) static TILE: ProgramPort = ProgramPort {
+    vertex_shader: "d3d9/tile",
+    fragment_shader: "d3d9/tile",
+    attributes: &[
+        "TileOffset",
+        "TileOrigin",
+        "MaskTexCoord0",
+        "CtrlBackdrop",
+        "PathIndex",
+        "Color",
+    ],
+    uniforms: &[
+        "Transform",
+        "TileSize",
+        "TextureMetadataSize",
+        "ZBufferSize",
+        "ColorTextureSize0",
+        "MaskTextureSize0",
+        "FramebufferSize",
+        "LinearBlending",
+    ],
+    textures: &[
+        "TextureMetadata",
+        "ZBuffer",
+        "ColorTexture0",
+        "MaskTexture0",
+        "DestTexture",
+        "GammaLUT",
+    ],
+    varying_count: 31,
+    vertex: tile_vertex,
+    fragment: tile_fragment,
+};
+
+fn tile_vertex(env: &ShaderEnv, inputs: &[VertexInput], output: &mut VertexOutput) {
+    let tile_size = env.vec2(TILE_TILE_SIZE);
+    let tile_origin = inputs[TILE_TILE_ORIGIN_ATTR].ivec2().to_f32x2();
+    let tile_offset = inputs[TILE_TILE_OFFSET_ATTR].ivec2().to_f32x2();
+    let position = (tile_origin + tile_offset) * tile_size;
+
+    // GLSL truncates here, but `k / 255.0 * 255.0` can land just below `k` in Rust, so round.
+    let z_buffer_size = env.ivec2(TILE_Z_BUFFER_SIZE).to_f32x2();
+    let z_texel = env.texture(TILE_Z_BUFFER, (tile_origin + F32x2::splat(0.5)) / z_buffer_size);
+    let z_value = (z_texel * F32x4::splat(255.0) + F32x4::splat(0.5)).floor().to_i32x4();
+    let z_value = z_value[0] | (z_value[1] << 8) | (z_value[2] << 16) | (z_value[3] << 24);
+    if inputs[TILE_PATH_INDEX_ATTR].int() < z_value {
+        output.position = F32x4::default();
+        return;
+    }
+
+    let mask_tex_coord_0 = inputs[TILE_MASK_TEX_COORD_0_ATTR].int;
+    let mask_tile_index =
+        mask_tex_coord_0[0] | (mask_tex_coord_0[1] << 8) | (mask_tex_coord_0[2] << 16);
+    let mask_tiles_per_row =
+        ((env.vec2(TILE_MASK_TEXTURE_SIZE_0).x() / tile_size.x()) as i32).max(1);
+    let mask_tile_coord = F32x2::new((mask_tile_index % mask_tiles_per_row) as f32,
+                                     (mask_tile_index / mask_tiles_per_row) as f32);
+    let mask_tex_coord_0_xy = (mask_tile_coord + tile_offset) * tile_size;
+    let ctrl_backdrop = inputs[TILE_CTRL_BACKDROP_ATTR].ivec2();
+    if ctrl_backdrop.y() == 0 && mask_tex_coord_0[3] != 0 {
+        output.position = F32x4::default();
+        return;
+    }
+
+    let ctrl = compute_tile_varyings(env, position, inputs[TILE_COLOR_ATTR].int(), output);
+    output.varyings[V_TILE_CTRL] = ctrl_backdrop.x() as f32;
+    output.varyings[V_CTRL] = ctrl as f32;
+    output.set_vec2(V_MASK_TEX_COORD_0, mask_tex_coord_0_xy);
+    output.varyings[V_MASK_TEX_COORD_0 + 2] = ctrl_backdrop.y() as f32;
+    output.position = mat4_mul(&env.mat4(TILE_TRANSFORM),
+                               position.concat_xy_xy(F32x2::new(0.0, 1.0)));
+}
+
+// tile_vertex.inc.glsl
+fn compute_tile_varyings(env: &ShaderEnv,
+                         position: F32x2,
+                         color_entry: i32,
+                         output: &mut VertexOutput)
+                         -> i32 {
+    let metadata_scale = F32x2::splat(1.0) /
+        env.ivec2(TILE_TEXTURE_METADATA_SIZE).to_f32x2();
+    let metadata_entry_coord = F32x2::new((color_entry % 128 * 10) as f32,
+                                          (color_entry / 128) as f32);
+    let fetch_unscaled = |entry: i32| {
+        let tex_coord = (metadata_entry_coord + F32x2::splat(0.5) +
+                         F32x2::new(entry as f32, 0.0)) * metadata_scale;
+        env.texture(TILE_TEXTURE_METADATA, tex_coord)
+    };
+
+    let color_tex_matrix_0 = fetch_unscaled(0);
+    let color_tex_offsets = fetch_unscaled(1);
+    let color_tex_coord_0 = color_tex_matrix_0.xy() * F32x2::splat(position.x()) +
+        color_tex_matrix_0.zw() * F32x2::splat(position.y()) + color_tex_offsets.xy();
+    output.set_vec2(V_COLOR_TEX_COORD_0, color_tex_coord_0);
+    output.set_vec4(V_BASE_COLOR, fetch_unscaled(2));
+    output.set_vec4(V_FILTER_PARAMS_0, fetch_unscaled(3));
+    output.set_vec4(V_FILTER_PARAMS_1, fetch_unscaled(4));
+    output.set_vec4(V_FILTER_PARAMS_2, fetch_unscaled(5));
+    output.set_vec4(V_FILTER_PARAMS_3, fetch_unscaled(6));
+    output.set_vec4(V_FILTER_PARAMS_4, fetch_unscaled(7));
+    fetch_unscaled(8).x() as i32
+}
+
+fn tile_fragment(env: &ShaderEnv, input: &FragmentInput) -> F32x4 {
+    let mask_tex_coord_0 = F32x4::new(input.float(V_MASK_TEX_COORD_0),
+                                      input.float(V_MASK_TEX_COORD_0 + 1),
+                                      input.float(V_MASK_TEX_COORD_0 + 2),
+                                      0.0);
+    tile_fragment::calculate_color(env, &TileFragmentParams {
+        frag_coord: input.frag_coord,
+        color_texture_0: TILE_COLOR_TEXTURE_0,
+        mask_texture_0: TILE_MASK_TEXTURE_0,
+        dest_texture: TILE_DEST_TEXTURE,
+        gamma_lut: TILE_GAMMA_LUT,
+        color_texture_size_0: env.vec2(TILE_COLOR_TEXTURE_SIZE_0),
+        mask_texture_size_0: env.vec2(TILE_MASK_TEXTURE_SIZE_0),
+        filter_params_0: input.vec4(V_FILTER_PARAMS_0),
+        filter_params_1: input.vec4(V_FILTER_PARAMS_1),
+        filter_params_2: input.vec4(V_FILTER_PARAMS_2),
+        filter_params_3: input.vec4(V_FILTER_PARAMS_3),
+        filter_params_4: input.vec4(V_FILTER_PARAMS_4),
+        framebuffer_size: env.vec2(TILE_FRAMEBUFFER_SIZE),
+        ctrl: input.float(V_CTRL) as i32,
+        mask_tex_coord_0,
+        color_tex_coord_0: input.vec2(V_COLOR_TEX_COORD_0),
+        base_color: input.vec4(V_BASE_COLOR),
+        tile_ctrl: input.float(V_TILE_CTRL) as i32,
+        linear_blending: env.int(TILE_LINEAR_BLENDING) != 0,
+    })
+}
+
+// d3d9/tile_clip_combine.vs.glsl, d3d9/tile_clip_combine.fs.glsl
+
+const CLIP_COMBINE_TILE_OFFSET_ATTR: usize = 0;
+const CLIP_COMBINE_DEST_TILE_INDEX_ATTR: usize = 1;
+const CLIP_COMBINE_DEST_BACKDROP_ATTR: usize = 2;
+const CLIP_COMBINE_SRC_TILE_INDEX_ATTR: usize = 3;
+const CLIP_COMBINE_SRC_BACKDROP_ATTR: usize = 4;
+
+const CLIP_FRAMEBUFFER_SIZE: usize = 0;
+const CLIP_TILE_SIZE: usize = 1;
+const CLIP_SRC: usize = 0;
+
+const V_TEX_COORD_0: usize = 0;
+const V_BACKDROP_0: usize = 2;
+const V_TEX_COORD_1: usize = 3;
+const V_BACKDROP_1: usize = 5;
+
+pub(crate) static TILE_CLIP_COMBINE: ProgramPort = ProgramPort {
+    vertex_shader: "d3d9/tile_clip_combine",
+    fragment_shader: "d3d9/tile_clip_combine",
+    attributes: &["TileOffset", "DestTileIndex", "DestBackdrop", "SrcTileIndex", "SrcBackdrop"],
+    uniforms: &["FramebufferSize", "TileSize"],
+    textures: &["Src"],
+    varying_count: 6,
+    vertex: tile_clip_combine_vertex,
+    fragment: tile_clip_combine_fragment,
+};
+
+fn clip_tile_position(env: &ShaderEnv, tile_index: i32, tile_offset: F32x2) -> F32x2 {
+    let (framebuffer_size, tile_size) = (env.vec2(CLIP_FRAMEBUFFER_SIZE), env.vec2(CLIP_TILE_SIZE));
+    let tiles_per_row = ((framebuffer_size.x() / tile_size.x()) as i32).max(1);
+    let position = F32x2::new((tile_index % tiles_per_row) as f32,
+                              (tile_index / tiles_per_row) as f32) + tile_offset;
+    position * F32x2::new(tile_size.x(), tile_size.y() * 0.25) / framebuffer_size
+}
+
+fn clip_position(tile_index: i32, position: F32x2) -> F32x4 {
+    let position = if tile_index < 0 { F32x2::default() } else { position };
+    mix2(F32x2::splat(-1.0), F32x2::splat(1.0), position).concat_xy_xy(F32x2::new(0.0, 1.0))
+}
+
+fn tile_clip_combine_vertex(env: &ShaderEnv, inputs: &[VertexInput], output: &mut VertexOutput) {
+    let tile_offset = inputs[CLIP_COMBINE_TILE_OFFSET_ATTR].ivec2().to_f32x2();
+    let dest_tile_index = inputs[CLIP_COMBINE_DEST_TILE_INDEX_ATTR].int();
+    let src_tile_index = inputs[CLIP_COMBINE_SRC_TILE_INDEX_ATTR].int();
+    let dest_position = clip_tile_position(env, dest_tile_index, tile_offset);
+    let src_position = clip_tile_position(env, src_tile_index, tile_offset);
+
+    output.set_vec2(V_TEX_COORD_0, dest_position);
+    output.set_vec2(V_TEX_COORD_1, src_position);
+    output.varyings[V_BACKDROP_0] = inputs[CLIP_COMBINE_DEST_BACKDROP_ATTR].int() as f32;
+    output.varyings[V_BACKDROP_1] = inputs[CLIP_COMBINE_SRC_BACKDROP_ATTR].int() as f32;
+    output.position = clip_position(dest_tile_index, dest_position);
+}
+
+fn tile_clip_combine_fragment(env: &ShaderEnv, input: &FragmentInput) -> F32x4 {
+    let color_0 = env.texture(CLIP_SRC, input.vec2(V_TEX_COORD_0)) +
+        F32x4::splat(input.float(V_BACKDROP_0));
+    let color_1 = env.texture(CLIP_SRC, input.vec2(V_TEX_COORD_1)) +
+        F32x4::splat(input.float(V_BACKDROP_1));
+    color_0.abs().min(color_1.abs())
+}
+
+// d3d9/tile_clip_copy.vs.glsl, d3d9/tile_clip_copy.fs.glsl
+
+const CLIP_COPY_TILE_OFFSET_ATTR: usize = 0;
+const CLIP_COPY_TILE_INDEX_ATTR: usize = 1;
+
+const V_TEX_COORD: usize = 0;
+
+pub(crate) static TILE_CLIP_COPY: ProgramPort = ProgramPort {
+    vertex_shader: "d3d9/tile_clip_copy",
+    fragment_shader: "d3d9/tile_clip_copy",
+    attributes: &["TileOffset", "TileIndex"],
+    uniforms: &["FramebufferSize", "TileSize"],
+    textures: &["Src"],
+    varying_count: 2,
+    vertex: tile_clip_copy_vertex,
+    fragment: tile_clip_copy_fragment,
+};
+
+fn tile_clip_copy_vertex(env: &ShaderEnv, inputs: &[VertexInput], output: &mut VertexOutput) {
+    let tile_offset = inputs[CLIP_COPY_TILE_OFFSET_ATTR].ivec2().to_f32x2();
+    let tile_index = inputs[CLIP_COPY_TILE_INDEX_ATTR].int();
+    let position = clip_tile_position(env, tile_index, tile_offset);
+    output.set_vec2(V_TEX_COORD, position);
+    output.position = clip_position(tile_index, position);
+}
+
+fn tile_clip_copy_fragment(env: &ShaderEnv, input: &FragmentInput) -> F32x4 {
+    env.texture(CLIP_SRC, input.vec2(V_TEX_COORD))
+}
+
+// d3d9/tile_copy.vs.glsl, d3d9/tile_copy.fs.glsl
+
+const TILE_COPY_TRANSFORM: usize = 0;
+const TILE_COPY_TILE_SIZE: usize = 1;
+const TILE_COPY_FRAMEBUFFER_SIZE: usize = 2;
+const TILE_COPY_SRC: usize = 0;
+
+pub(crate) static TILE_COPY: ProgramPort = ProgramPort {
+    vertex_shader: "d3d9/tile_copy",
+    fragment_shader: "d3d9/tile_copy",
+    attributes: &["TilePosition"],
+    uniforms: &["Transform", "TileSize", "FramebufferSize"],
+    textures: &["Src"],
+    varying_count: 0,
+    vertex: tile_copy_vertex,
+    fragment: tile_copy_fragment,
+};
+
+fn tile_copy_vertex(env: &ShaderEnv, inputs: &[VertexInput], output: &mut VertexOutput) {
+    let position = inputs[0].ivec2().to_f32x2() * env.vec2(TILE_COPY_TILE_SIZE);
+    output.position = mat4_mul(&env.mat4(TILE_COPY_TRANSFORM),
+                               position.concat_xy_xy(F32x2::new(0.0, 1.0)));
+}
+
+fn tile_copy_fragment(env: &ShaderEnv, input: &FragmentInput) -> F32x4 {
+    let tex_coord = input.frag_coord / env.vec2(TILE_COPY_FRAMEBUFFER_SIZE);
+    env.texture(TILE_COPY_SRC, tex_coord)
+}
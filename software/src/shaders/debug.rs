@@ -0,0 +1,82 @@
+// pathfinder/software/src/shaders/debug.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Ports of the debug UI programs.
+
+use pathfinder_simd::default::{F32x2, F32x4};
+use super::{FragmentInput, ProgramPort, ShaderEnv, VertexInput, VertexOutput, with_w};
+
+// debug/solid.vs.glsl, debug/solid.fs.glsl
+
+const SOLID_FRAMEBUFFER_SIZE: usize = 0;
+const SOLID_COLOR: usize = 1;
+
+pub(crate) static SOLID: ProgramPort = ProgramPort {
+    vertex_shader: "debug/solid",
+    fragment_shader: "debug/solid",
+    attributes: &["Position"],
+    uniforms: &["FramebufferSize", "Color"],
+    textures: &[],
+    varying_count: 0,
+    vertex: solid_vertex,
+    fragment: solid_fragment,
+};
+
+fn solid_vertex(env: &ShaderEnv, inputs: &[VertexInput], output: &mut VertexOutput) {
+    output.position = screen_position(inputs[0].ivec2().to_f32x2(),
+                                      env.vec2(SOLID_FRAMEBUFFER_SIZE));
+}
+
+fn solid_fragment(env: &ShaderEnv, _: &FragmentInput) -> F32x4 {
+    let color = env.vec4(SOLID_COLOR);
+    with_w(color, 1.0) * F32x4::splat(color.w())
+}
+
+// debug/texture.vs.glsl, debug/texture.fs.glsl
+
+const TEXTURE_FRAMEBUFFER_SIZE: usize = 0;
+const TEXTURE_TEXTURE_SIZE: usize = 1;
+const TEXTURE_COLOR: usize = 2;
+const TEXTURE_TEXTURE: usize = 0;
+
+const TEXTURE_POSITION_ATTR: usize = 0;
+const TEXTURE_TEX_COORD_ATTR: usize = 1;
+
+const V_TEX_COORD: usize = 0;
+
+pub(crate) static TEXTURE: ProgramPort = ProgramPort {
+    vertex_shader: "debug/texture",
+    fragment_shader: "debug/texture",
+    attributes: &["Position", "TexCoord"],
+    uniforms: &["FramebufferSize", "TextureSize", "Color"],
+    textures: &["Texture"],
+    varying_count: 2,
+    vertex: texture_vertex,
+    fragment: texture_fragment,
+};
+
+fn texture_vertex(env: &ShaderEnv, inputs: &[VertexInput], output: &mut VertexOutput) {
+    let tex_coord = inputs[TEXTURE_TEX_COORD_ATTR].ivec2().to_f32x2() /
+        env.vec2(TEXTURE_TEXTURE_SIZE);
+    output.set_vec2(V_TEX_COORD, tex_coord);
+    output.position = screen_position(inputs[TEXTURE_POSITION_ATTR].ivec2().to_f32x2(),
+                                      env.vec2(TEXTURE_FRAMEBUFFER_SIZE));
+}
+
+fn texture_fragment(env: &ShaderEnv, input: &FragmentInput) -> F32x4 {
+    let color = env.vec4(TEXTURE_COLOR);
+    let alpha = env.texture(TEXTURE_TEXTURE, input.vec2(V_TEX_COORD)).x() * color.w();
+    with_w(color, 1.0) * F32x4::splat(alpha)
+}
+
+fn screen_position(position: F32x2, framebuffer_size: F32x2) -> F32x4 {
+    let position = position / framebuffer_size * F32x2::splat(2.0) - F32x2::splat(1.0);
+    F32x4::new(position.x(), -position.y(), 0.0, 1.0)
+}
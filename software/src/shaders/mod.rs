@@ -0,0 +1,246 @@
+// pathfinder/software/src/shaders/mod.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Rust ports of Pathfinder's shader programs.
+//!
+//! Each port mirrors the GLSL in `shaders/` as closely as possible, so that changes there are
+//! easy to carry over. A program is identified by the names of its vertex and fragment shaders,
+//! just as with the other backends. Attribute, uniform, and texture names are the GLSL names
+//! without their `a` or `u` prefixes; the index of a name in the port's lists is its location.
+
+use pathfinder_gpu::{ShaderKind, UniformData};
+use pathfinder_simd::default::{F32x2, F32x4, I32x2, I32x4};
+use crate::texture::Sampler;
+
+mod common;
+mod d3d9;
+mod debug;
+mod tile_fragment;
+
+/// The maximum number of floats a vertex shader can pass to a fragment shader.
+pub(crate) const MAX_VARYINGS: usize = 32;
+
+pub(crate) type Varyings = [f32; MAX_VARYINGS];
+
+/// A vertex and fragment shader pair ported to Rust.
+pub(crate) struct ProgramPort {
+    pub(crate) vertex_shader: &'static str,
+    pub(crate) fragment_shader: &'static str,
+    pub(crate) attributes: &'static [&'static str],
+    pub(crate) uniforms: &'static [&'static str],
+    pub(crate) textures: &'static [&'static str],
+    /// The number of floats of `Varyings` that the vertex shader writes.
+    pub(crate) varying_count: usize,
+    pub(crate) vertex: fn(&ShaderEnv, &[VertexInput], &mut VertexOutput),
+    pub(crate) fragment: fn(&ShaderEnv, &FragmentInput) -> F32x4,
+}
+
+static PROGRAMS: &[&ProgramPort] = &[
+    &common::BLIT,
+    &common::BLIT_LINEAR,
+    &common::CLEAR,
+    &common::REPROJECT,
+    &common::STENCIL,
+    &d3d9::FILL,
+    &d3d9::TILE,
+    &d3d9::TILE_CLIP_COMBINE,
+    &d3d9::TILE_CLIP_COPY,
+    &d3d9::TILE_COPY,
+    &debug::SOLID,
+    &debug::TEXTURE,
+];
+
+/// Returns true if there's a port of the shader with the given name and kind.
+pub(crate) fn has_shader(name: &str, kind: ShaderKind) -> bool {
+    PROGRAMS.iter().any(|program| {
+        match kind {
+            ShaderKind::Vertex => program.vertex_shader == name,
+            ShaderKind::Fragment => program.fragment_shader == name,
+            ShaderKind::Compute => false,
+        }
+    })
+}
+
+/// Finds the port of the program made of the given shaders.
+pub(crate) fn find_program(vertex_shader: &str, fragment_shader: &str)
+                           -> Option<&'static ProgramPort> {
+    PROGRAMS.iter().cloned().find(|program| {
+        program.vertex_shader == vertex_shader && program.fragment_shader == fragment_shader
+    })
+}
+
+/// The uniforms and textures bound for a draw call.
+pub(crate) struct ShaderEnv<'a> {
+    pub(crate) uniforms: &'a [Option<UniformData>],
+    pub(crate) textures: &'a [Option<Sampler<'a>>],
+}
+
+impl<'a> ShaderEnv<'a> {
+    fn uniform(&self, location: usize) -> Option<UniformData> {
+        self.uniforms.get(location).cloned().unwrap_or(None)
+    }
+
+    pub(crate) fn int(&self, location: usize) -> i32 {
+        match self.uniform(location) {
+            Some(UniformData::Int(value)) => value,
+            Some(UniformData::Float(value)) => value as i32,
+            _ => 0,
+        }
+    }
+
+    pub(crate) fn vec2(&self, location: usize) -> F32x2 {
+        match self.uniform(location) {
+            Some(UniformData::Vec2(value)) => value,
+            Some(UniformData::IVec2(value)) => value.to_f32x2(),
+            _ => F32x2::default(),
+        }
+    }
+
+    pub(crate) fn ivec2(&self, location: usize) -> I32x2 {
+        match self.uniform(location) {
+            Some(UniformData::IVec2(value)) => value,
+            Some(UniformData::Vec2(value)) => value.to_i32x2(),
+            _ => I32x2::default(),
+        }
+    }
+
+    pub(crate) fn vec4(&self, location: usize) -> F32x4 {
+        match self.uniform(location) {
+            Some(UniformData::Vec4(value)) => value,
+            _ => F32x4::default(),
+        }
+    }
+
+    pub(crate) fn mat4(&self, location: usize) -> [F32x4; 4] {
+        match self.uniform(location) {
+            Some(UniformData::Mat4(value)) => value,
+            _ => [F32x4::default(); 4],
+        }
+    }
+
+    /// Samples the texture bound to the given unit, like GLSL `texture()`. Unbound textures read
+    /// as transparent black.
+    pub(crate) fn texture(&self, unit: usize, tex_coord: F32x2) -> F32x4 {
+        match self.textures.get(unit) {
+            Some(Some(sampler)) => sampler.sample(tex_coord),
+            _ => F32x4::default(),
+        }
+    }
+}
+
+/// The value of a vertex attribute, as fetched from its buffer.
+///
+/// Integer attributes keep their exact values; the float view is provided for convenience.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct VertexInput {
+    pub(crate) float: F32x4,
+    pub(crate) int: I32x4,
+}
+
+impl Default for VertexInput {
+    #[inline]
+    fn default() -> VertexInput {
+        VertexInput { float: F32x4::new(0.0, 0.0, 0.0, 1.0), int: I32x4::new(0, 0, 0, 1) }
+    }
+}
+
+impl VertexInput {
+    #[inline]
+    pub(crate) fn ivec2(&self) -> I32x2 {
+        self.int.xy()
+    }
+
+    #[inline]
+    pub(crate) fn int(&self) -> i32 {
+        self.int[0]
+    }
+}
+
+/// The outputs of a vertex shader.
+#[derive(Clone, Copy)]
+pub(crate) struct VertexOutput {
+    /// `gl_Position`.
+    pub(crate) position: F32x4,
+    pub(crate) varyings: Varyings,
+}
+
+impl Default for VertexOutput {
+    #[inline]
+    fn default() -> VertexOutput {
+        VertexOutput { position: F32x4::default(), varyings: [0.0; MAX_VARYINGS] }
+    }
+}
+
+impl VertexOutput {
+    #[inline]
+    pub(crate) fn set_vec2(&mut self, offset: usize, value: F32x2) {
+        self.varyings[offset] = value.x();
+        self.varyings[offset + 1] = value.y();
+    }
+
+    #[inline]
+    pub(crate) fn set_vec4(&mut self, offset: usize, value: F32x4) {
+        self.varyings[offset..(offset + 4)].copy_from_slice(&[
+            value.x(), value.y(), value.z(), value.w()
+        ]);
+    }
+}
+
+/// The inputs of a fragment shader.
+pub(crate) struct FragmentInput<'a> {
+    /// `gl_FragCoord.xy`.
+    pub(crate) frag_coord: F32x2,
+    pub(crate) varyings: &'a [f32],
+}
+
+impl<'a> FragmentInput<'a> {
+    #[inline]
+    pub(crate) fn float(&self, offset: usize) -> f32 {
+        self.varyings[offset]
+    }
+
+    #[inline]
+    pub(crate) fn vec2(&self, offset: usize) -> F32x2 {
+        F32x2::new(self.varyings[offset], self.varyings[offset + 1])
+    }
+
+    #[inline]
+    pub(crate) fn vec4(&self, offset: usize) -> F32x4 {
+        F32x4::from_slice(&self.varyings[offset..(offset + 4)])
+    }
+}
+
+// GLSL built-ins
+
+#[inline]
+pub(crate) fn mix2(a: F32x2, b: F32x2, t: F32x2) -> F32x2 {
+    a + (b - a) * t
+}
+
+#[inline]
+pub(crate) fn mix4(a: F32x4, b: F32x4, t: F32x4) -> F32x4 {
+    a + (b - a) * t
+}
+
+/// Multiplies a column-major 4x4 matrix by a vector.
+#[inline]
+pub(crate) fn mat4_mul(matrix: &[F32x4; 4], vector: F32x4) -> F32x4 {
+    matrix[0] * F32x4::splat(vector.x()) +
+        matrix[1] * F32x4::splat(vector.y()) +
+        matrix[2] * F32x4::splat(vector.z()) +
+        matrix[3] * F32x4::splat(vector.w())
+}
+
+#[inline]
+pub(crate) fn with_w(vector: F32x4, w: f32) -> F32x4 {
+    let mut vector = vector;
+    vector.set_w(w);
+    vector
+}
@@ -0,0 +1,473 @@
+// pathfinder/software/src/shaders/tile_fragment.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A port of `tile_fragment.inc.glsl`, which computes the color of a fragment of a tile.
+
+use pathfinder_simd::default::{F32x2, F32x4};
+use std::f32::consts::{FRAC_PI_3, PI};
+use super::{ShaderEnv, mix4, with_w};
+
+const FRAC_6_PI: f32 = 6.0 / PI;
+
+const TILE_CTRL_MASK_MASK: i32 = 0x3;
+const TILE_CTRL_MASK_WINDING: i32 = 0x1;
+
+const TILE_CTRL_MASK_0_SHIFT: i32 = 0;
+
+const COMBINER_CTRL_COLOR_COMBINE_MASK: i32 = 0x3;
+const COMBINER_CTRL_COLOR_COMBINE_SRC_IN: i32 = 0x1;
+const COMBINER_CTRL_COLOR_COMBINE_DEST_IN: i32 = 0x2;
+
+const COMBINER_CTRL_FILTER_MASK: i32 = 0xf;
+const COMBINER_CTRL_FILTER_RADIAL_GRADIENT: i32 = 0x1;
+const COMBINER_CTRL_FILTER_TEXT: i32 = 0x2;
+const COMBINER_CTRL_FILTER_BLUR: i32 = 0x3;
+const COMBINER_CTRL_FILTER_COLOR_MATRIX: i32 = 0x4;
+const COMBINER_CTRL_FILTER_CONIC_GRADIENT: i32 = 0x5;
+
+const COMBINER_CTRL_COMPOSITE_MASK: i32 = 0xf;
+const COMBINER_CTRL_COMPOSITE_NORMAL: i32 = 0x0;
+const COMBINER_CTRL_COMPOSITE_MULTIPLY: i32 = 0x1;
+const COMBINER_CTRL_COMPOSITE_SCREEN: i32 = 0x2;
+const COMBINER_CTRL_COMPOSITE_OVERLAY: i32 = 0x3;
+const COMBINER_CTRL_COMPOSITE_DARKEN: i32 = 0x4;
+const COMBINER_CTRL_COMPOSITE_LIGHTEN: i32 = 0x5;
+const COMBINER_CTRL_COMPOSITE_COLOR_DODGE: i32 = 0x6;
+const COMBINER_CTRL_COMPOSITE_COLOR_BURN: i32 = 0x7;
+const COMBINER_CTRL_COMPOSITE_HARD_LIGHT: i32 = 0x8;
+const COMBINER_CTRL_COMPOSITE_SOFT_LIGHT: i32 = 0x9;
+const COMBINER_CTRL_COMPOSITE_DIFFERENCE: i32 = 0xa;
+const COMBINER_CTRL_COMPOSITE_EXCLUSION: i32 = 0xb;
+const COMBINER_CTRL_COMPOSITE_HUE: i32 = 0xc;
+const COMBINER_CTRL_COMPOSITE_SATURATION: i32 = 0xd;
+const COMBINER_CTRL_COMPOSITE_COLOR: i32 = 0xe;
+const COMBINER_CTRL_COMPOSITE_LUMINOSITY: i32 = 0xf;
+
+const COMBINER_CTRL_COLOR_FILTER_SHIFT: i32 = 4;
+const COMBINER_CTRL_COLOR_COMBINE_SHIFT: i32 = 8;
+const COMBINER_CTRL_COMPOSITE_SHIFT: i32 = 10;
+
+/// The arguments of `calculateColor()`. Textures are given by unit.
+pub(crate) struct TileFragmentParams {
+    pub(crate) frag_coord: F32x2,
+    pub(crate) color_texture_0: usize,
+    pub(crate) mask_texture_0: usize,
+    pub(crate) dest_texture: usize,
+    pub(crate) gamma_lut: usize,
+    pub(crate) color_texture_size_0: F32x2,
+    pub(crate) mask_texture_size_0: F32x2,
+    pub(crate) filter_params_0: F32x4,
+    pub(crate) filter_params_1: F32x4,
+    pub(crate) filter_params_2: F32x4,
+    pub(crate) filter_params_3: F32x4,
+    pub(crate) filter_params_4: F32x4,
+    pub(crate) framebuffer_size: F32x2,
+    pub(crate) ctrl: i32,
+    pub(crate) mask_tex_coord_0: F32x4,
+    pub(crate) color_tex_coord_0: F32x2,
+    pub(crate) base_color: F32x4,
+    pub(crate) tile_ctrl: i32,
+    pub(crate) linear_blending: bool,
+}
+
+pub(crate) fn calculate_color(env: &ShaderEnv, params: &TileFragmentParams) -> F32x4 {
+    // Sample mask.
+    let mask_ctrl_0 = (params.tile_ctrl >> TILE_CTRL_MASK_0_SHIFT) & TILE_CTRL_MASK_MASK;
+    let mask_alpha = sample_mask(env,
+                                 1.0,
+                                 params.mask_texture_0,
+                                 params.mask_texture_size_0,
+                                 params.mask_tex_coord_0,
+                                 mask_ctrl_0);
+
+    // Sample color.
+    let mut color = params.base_color;
+    let color_0_combine = (params.ctrl >> COMBINER_CTRL_COLOR_COMBINE_SHIFT) &
+        COMBINER_CTRL_COLOR_COMBINE_MASK;
+    if color_0_combine != 0 {
+        let color_0_filter = (params.ctrl >> COMBINER_CTRL_COLOR_FILTER_SHIFT) &
+            COMBINER_CTRL_FILTER_MASK;
+        let color_0 = filter_color(env, params, color_0_filter);
+        color = combine_color_0(color, color_0, color_0_combine);
+    }
+
+    // Apply mask.
+    color.set_w(color.w() * mask_alpha);
+
+    // Convert to linear light if we're blending there.
+    if params.linear_blending {
+        color = srgb_to_linear(color);
+    }
+
+    // Apply composite.
+    let composite_op = (params.ctrl >> COMBINER_CTRL_COMPOSITE_SHIFT) &
+        COMBINER_CTRL_COMPOSITE_MASK;
+    color = composite(env, color, params.dest_texture, params, composite_op);
+
+    // Premultiply alpha.
+    with_w(color * F32x4::splat(color.w()), color.w())
+}
+
+fn combine_color_0(dest_color: F32x4, src_color: F32x4, op: i32) -> F32x4 {
+    match op {
+        COMBINER_CTRL_COLOR_COMBINE_SRC_IN => with_w(src_color, src_color.w() * dest_color.w()),
+        COMBINER_CTRL_COLOR_COMBINE_DEST_IN => with_w(dest_color, src_color.w() * dest_color.w()),
+        _ => dest_color,
+    }
+}
+
+// Color filters
+
+fn filter_color(env: &ShaderEnv, params: &TileFragmentParams, color_filter: i32) -> F32x4 {
+    let (tex_coord, texture) = (params.color_tex_coord_0, params.color_texture_0);
+    match color_filter {
+        COMBINER_CTRL_FILTER_RADIAL_GRADIENT => filter_radial_gradient(env, params),
+        COMBINER_CTRL_FILTER_CONIC_GRADIENT => filter_conic_gradient(env, params),
+        COMBINER_CTRL_FILTER_BLUR => filter_blur(env, params),
+        COMBINER_CTRL_FILTER_TEXT => filter_text(env, params),
+        COMBINER_CTRL_FILTER_COLOR_MATRIX => {
+            let src_color = env.texture(texture, tex_coord);
+            let color_matrix = [
+                params.filter_params_0,
+                params.filter_params_1,
+                params.filter_params_2,
+                params.filter_params_3,
+            ];
+            super::mat4_mul(&color_matrix, src_color) + params.filter_params_4
+        }
+        _ => env.texture(texture, tex_coord),
+    }
+}
+
+fn filter_text_sample_1_tap(env: &ShaderEnv, params: &TileFragmentParams, offset: f32) -> f32 {
+    env.texture(params.color_texture_0, params.color_tex_coord_0 + F32x2::new(offset, 0.0)).x()
+}
+
+fn filter_text_convolve_7_tap(alpha_0: [f32; 4], alpha_1: [f32; 3], kernel: F32x4) -> f32 {
+    alpha_0[0] * kernel.x() + alpha_0[1] * kernel.y() + alpha_0[2] * kernel.z() +
+        alpha_0[3] * kernel.w() +
+        alpha_1[0] * kernel.z() + alpha_1[1] * kernel.y() + alpha_1[2] * kernel.x()
+}
+
+fn filter_text(env: &ShaderEnv, params: &TileFragmentParams) -> F32x4 {
+    // Unpack.
+    let kernel = params.filter_params_0;
+    let bg_color = params.filter_params_1;
+    let fg_color = params.filter_params_2;
+    let gamma_correction_enabled = params.filter_params_2.w() != 0.0;
+
+    // Apply defringing if necessary.
+    let mut alpha = if kernel.w() == 0.0 {
+        let alpha = env.texture(params.color_texture_0, params.color_tex_coord_0).x();
+        [alpha; 3]
+    } else {
+        let one_pixel = 1.0 / params.color_texture_size_0.x();
+        let wide = kernel.x() > 0.0;
+        let tap = |offset: f32| filter_text_sample_1_tap(env, params, offset * one_pixel);
+        let alpha_left = [if wide { tap(-4.0) } else { 0.0 }, tap(-3.0), tap(-2.0), tap(-1.0)];
+        let alpha_center = tap(0.0);
+        let alpha_right = [tap(1.0), tap(2.0), tap(3.0), if wide { tap(4.0) } else { 0.0 }];
+
+        let r = filter_text_convolve_7_tap(alpha_left,
+                                           [alpha_center, alpha_right[0], alpha_right[1]],
+                                           kernel);
+        let g = filter_text_convolve_7_tap([alpha_left[1], alpha_left[2], alpha_left[3],
+                                            alpha_center],
+                                           [alpha_right[0], alpha_right[1], alpha_right[2]],
+                                           kernel);
+        let b = filter_text_convolve_7_tap([alpha_left[2], alpha_left[3], alpha_center,
+                                            alpha_right[0]],
+                                           [alpha_right[1], alpha_right[2], alpha_right[3]],
+                                           kernel);
+        [r, g, b]
+    };
+
+    // Apply gamma correction if necessary.
+    if gamma_correction_enabled {
+        for channel in 0..3 {
+            let tex_coord = F32x2::new(alpha[channel], 1.0 - bg_color[channel]);
+            alpha[channel] = env.texture(params.gamma_lut, tex_coord).x();
+        }
+    }
+
+    // Finish.
+    let alpha = F32x4::new(alpha[0], alpha[1], alpha[2], 0.0);
+    with_w(mix4(bg_color, fg_color, alpha), 1.0)
+}
+
+fn filter_radial_gradient(env: &ShaderEnv, params: &TileFragmentParams) -> F32x4 {
+    let (line_from, line_vector) = (params.filter_params_0.xy(), params.filter_params_0.zw());
+    let (radii, uv_origin) = (params.filter_params_1.xy(), params.filter_params_1.zw());
+
+    let (d_p, d_c) = (params.color_tex_coord_0 - line_from, line_vector);
+    let d_r = radii.y() - radii.x();
+
+    let a = dot2(d_c, d_c) - d_r * d_r;
+    let b = dot2(d_p, d_c) + radii.x() * d_r;
+    let c = dot2(d_p, d_p) - radii.x() * radii.x();
+    let discrim = b * b - a * c;
+
+    let mut color = F32x4::default();
+    if discrim != 0.0 {
+        let mut ts = (F32x2::splat(discrim.sqrt()) * F32x2::new(1.0, -1.0) + F32x2::splat(b)) /
+            F32x2::splat(a);
+        if ts.x() > ts.y() {
+            ts = ts.yx();
+        }
+        let t = if ts.x() >= 0.0 { ts.x() } else { ts.y() };
+        color = env.texture(params.color_texture_0, uv_origin + F32x2::new(t, 0.0));
+    }
+    color
+}
+
+fn filter_conic_gradient(env: &ShaderEnv, params: &TileFragmentParams) -> F32x4 {
+    let (center, uv_origin) = (params.filter_params_0.xy(), params.filter_params_1.zw());
+    let angle = params.filter_params_0.z();
+
+    let d_p = params.color_tex_coord_0 - center;
+    // Divide by 2π to map the angle onto the gradient.
+    let t = fract((d_p.y().atan2(d_p.x()) - angle) * (0.5 / PI));
+    env.texture(params.color_texture_0, uv_origin + F32x2::new(t, 0.0))
+}
+
+fn filter_blur(env: &ShaderEnv, params: &TileFragmentParams) -> F32x4 {
+    let (tex_coord, texture) = (params.color_tex_coord_0, params.color_texture_0);
+
+    // Unpack.
+    let src_offset_scale = params.filter_params_0.xy() / params.color_texture_size_0;
+    let support = params.filter_params_0.z() as i32;
+    let mut gauss_coeff = params.filter_params_1;
+
+    // Set up our incremental calculation.
+    let mut gauss_sum = gauss_coeff.x();
+    let mut color = env.texture(texture, tex_coord) * F32x4::splat(gauss_coeff.x());
+    gauss_coeff = advance_gauss_coeff(gauss_coeff);
+
+    // This uses the same trick as the GLSL: linear filtering evaluates two texels at a time.
+    let mut i = 1;
+    while i <= support {
+        let mut gauss_partial_sum = gauss_coeff.x();
+        gauss_coeff = advance_gauss_coeff(gauss_coeff);
+        gauss_partial_sum += gauss_coeff.x();
+
+        let src_offset = src_offset_scale *
+            F32x2::splat(i as f32 + gauss_coeff.x() / gauss_partial_sum);
+        color += (env.texture(texture, tex_coord - src_offset) +
+                  env.texture(texture, tex_coord + src_offset)) *
+            F32x4::splat(gauss_partial_sum);
+
+        gauss_sum += 2.0 * gauss_partial_sum;
+        gauss_coeff = advance_gauss_coeff(gauss_coeff);
+        i += 2;
+    }
+
+    // Finish.
+    color * F32x4::splat(1.0 / gauss_sum)
+}
+
+// `gaussCoeff.xy *= gaussCoeff.yz`
+fn advance_gauss_coeff(gauss_coeff: F32x4) -> F32x4 {
+    F32x4::new(gauss_coeff.x() * gauss_coeff.y(),
+               gauss_coeff.y() * gauss_coeff.z(),
+               gauss_coeff.z(),
+               gauss_coeff.w())
+}
+
+// Composite operations
+
+fn composite(env: &ShaderEnv,
+             src_color: F32x4,
+             dest_texture: usize,
+             params: &TileFragmentParams,
+             op: i32)
+             -> F32x4 {
+    if op == COMBINER_CTRL_COMPOSITE_NORMAL {
+        return src_color;
+    }
+
+    // FIXME: What should the output alpha be here?
+    let dest_tex_coord = params.frag_coord / params.framebuffer_size;
+    let dest_color = env.texture(dest_texture, dest_tex_coord);
+    let blended_rgb = composite_rgb(dest_color, src_color, op);
+    let (src_alpha, dest_alpha) = (src_color.w(), dest_color.w());
+    with_w(src_color * F32x4::splat(src_alpha * (1.0 - dest_alpha)) +
+           blended_rgb * F32x4::splat(src_alpha * dest_alpha) +
+           dest_color * F32x4::splat(1.0 - src_alpha),
+           1.0)
+}
+
+fn composite_rgb(dest_color: F32x4, src_color: F32x4, op: i32) -> F32x4 {
+    let one = F32x4::splat(1.0);
+    match op {
+        COMBINER_CTRL_COMPOSITE_MULTIPLY => dest_color * src_color,
+        COMBINER_CTRL_COMPOSITE_SCREEN => composite_screen(dest_color, src_color),
+        COMBINER_CTRL_COMPOSITE_OVERLAY => composite_hard_light(src_color, dest_color),
+        COMBINER_CTRL_COMPOSITE_DARKEN => dest_color.min(src_color),
+        COMBINER_CTRL_COMPOSITE_LIGHTEN => dest_color.max(src_color),
+        COMBINER_CTRL_COMPOSITE_COLOR_DODGE => composite_color_dodge(dest_color, src_color),
+        COMBINER_CTRL_COMPOSITE_COLOR_BURN => {
+            one - composite_color_dodge(one - dest_color, one - src_color)
+        }
+        COMBINER_CTRL_COMPOSITE_HARD_LIGHT => composite_hard_light(dest_color, src_color),
+        COMBINER_CTRL_COMPOSITE_SOFT_LIGHT => composite_soft_light(dest_color, src_color),
+        COMBINER_CTRL_COMPOSITE_DIFFERENCE => (dest_color - src_color).abs(),
+        COMBINER_CTRL_COMPOSITE_EXCLUSION => {
+            dest_color + src_color - F32x4::splat(2.0) * dest_color * src_color
+        }
+        COMBINER_CTRL_COMPOSITE_HUE |
+        COMBINER_CTRL_COMPOSITE_SATURATION |
+        COMBINER_CTRL_COMPOSITE_COLOR |
+        COMBINER_CTRL_COMPOSITE_LUMINOSITY => {
+            composite_hsl_to_rgb(composite_hsl(composite_rgb_to_hsl(dest_color),
+                                               composite_rgb_to_hsl(src_color),
+                                               op))
+        }
+        _ => src_color,
+    }
+}
+
+fn composite_select(cond: [bool; 3], if_true: F32x4, if_false: F32x4) -> F32x4 {
+    F32x4::new(if cond[0] { if_true.x() } else { if_false.x() },
+               if cond[1] { if_true.y() } else { if_false.y() },
+               if cond[2] { if_true.z() } else { if_false.z() },
+               0.0)
+}
+
+fn composite_divide(num: f32, denom: f32) -> f32 {
+    if denom != 0.0 { num / denom } else { 0.0 }
+}
+
+fn compare(color: F32x4, op: impl Fn(f32) -> bool) -> [bool; 3] {
+    [op(color.x()), op(color.y()), op(color.z())]
+}
+
+fn composite_color_dodge(dest_color: F32x4, src_color: F32x4) -> F32x4 {
+    let dest_zero = compare(dest_color, |value| value == 0.0);
+    let src_one = compare(src_color, |value| value == 1.0);
+    composite_select(dest_zero,
+                     F32x4::default(),
+                     composite_select(src_one,
+                                      F32x4::splat(1.0),
+                                      dest_color / (F32x4::splat(1.0) - src_color)))
+}
+
+fn composite_hsl_to_rgb(hsl: F32x4) -> F32x4 {
+    let a = hsl.y() * hsl.z().min(1.0 - hsl.z());
+    let ks = F32x4::new(0.0, 8.0, 4.0, 0.0) + F32x4::splat(hsl.x() * FRAC_6_PI);
+    let ks = F32x4::new(ks.x().rem_euclid(12.0), ks.y().rem_euclid(12.0), ks.z().rem_euclid(12.0),
+                        0.0);
+    let ks = (ks - F32x4::splat(3.0)).min(F32x4::splat(9.0) - ks);
+    F32x4::splat(hsl.z()) - ks.clamp(F32x4::splat(-1.0), F32x4::splat(1.0)) * F32x4::splat(a)
+}
+
+fn composite_rgb_to_hsl(rgb: F32x4) -> F32x4 {
+    let (r, g, b) = (rgb.x(), rgb.y(), rgb.z());
+    let v = r.max(g).max(b);
+    let x_min = r.min(g).min(b);
+    let c = v - x_min;
+    let l = x_min + (v - x_min) * 0.5;
+    let terms = if r == v {
+        (0.0, g, b)
+    } else if g == v {
+        (2.0, b, r)
+    } else {
+        (4.0, r, g)
+    };
+    let h = FRAC_PI_3 * composite_divide(terms.0 * c + terms.1 - terms.2, c);
+    let s = composite_divide(c, v);
+    F32x4::new(h, s, l, 0.0)
+}
+
+fn composite_screen(dest_color: F32x4, src_color: F32x4) -> F32x4 {
+    dest_color + src_color - dest_color * src_color
+}
+
+fn composite_hard_light(dest_color: F32x4, src_color: F32x4) -> F32x4 {
+    composite_select(compare(src_color, |value| value <= 0.5),
+                     dest_color * F32x4::splat(2.0) * src_color,
+                     composite_screen(dest_color,
+                                      F32x4::splat(2.0) * src_color - F32x4::splat(1.0)))
+}
+
+fn composite_soft_light(dest_color: F32x4, src_color: F32x4) -> F32x4 {
+    let darkened_dest_color =
+        composite_select(compare(dest_color, |value| value <= 0.25),
+                         ((F32x4::splat(16.0) * dest_color - F32x4::splat(12.0)) * dest_color +
+                          F32x4::splat(4.0)) * dest_color,
+                         dest_color.sqrt());
+    let factor = composite_select(compare(src_color, |value| value <= 0.5),
+                                  dest_color * (F32x4::splat(1.0) - dest_color),
+                                  darkened_dest_color - dest_color);
+    dest_color + (src_color * F32x4::splat(2.0) - F32x4::splat(1.0)) * factor
+}
+
+fn composite_hsl(dest_color: F32x4, src_color: F32x4, op: i32) -> F32x4 {
+    match op {
+        COMBINER_CTRL_COMPOSITE_HUE => {
+            F32x4::new(src_color.x(), dest_color.y(), dest_color.z(), 0.0)
+        }
+        COMBINER_CTRL_COMPOSITE_SATURATION => {
+            F32x4::new(dest_color.x(), src_color.y(), dest_color.z(), 0.0)
+        }
+        COMBINER_CTRL_COMPOSITE_COLOR => {
+            F32x4::new(src_color.x(), src_color.y(), dest_color.z(), 0.0)
+        }
+        _ => F32x4::new(dest_color.x(), dest_color.y(), src_color.z(), 0.0),
+    }
+}
+
+// Masks
+
+fn sample_mask(env: &ShaderEnv,
+               mask_alpha: f32,
+               mask_texture: usize,
+               mask_texture_size: F32x2,
+               mask_tex_coord: F32x4,
+               mask_ctrl: i32)
+               -> f32 {
+    if mask_ctrl == 0 {
+        return mask_alpha;
+    }
+
+    let mask_tex_coord_i = mask_tex_coord.xy().floor().to_i32x2();
+    let texel_coord = F32x2::new(mask_tex_coord_i.x() as f32, (mask_tex_coord_i.y() / 4) as f32);
+    let texel = env.texture(mask_texture,
+                            (texel_coord + F32x2::splat(0.5)) / mask_texture_size);
+    let mut coverage = texel[mask_tex_coord_i.y().rem_euclid(4) as usize] + mask_tex_coord.z();
+
+    if (mask_ctrl & TILE_CTRL_MASK_WINDING) != 0 {
+        coverage = coverage.abs();
+    } else {
+        coverage = 1.0 - (1.0 - coverage.rem_euclid(2.0)).abs();
+    }
+    mask_alpha.min(coverage)
+}
+
+// Color spaces
+
+fn srgb_to_linear(color: F32x4) -> F32x4 {
+    let decode = |value: f32| {
+        let value = value.max(0.0);
+        if value < 0.04045 { value / 12.92 } else { ((value + 0.055) / 1.055).powf(2.4) }
+    };
+    F32x4::new(decode(color.x()), decode(color.y()), decode(color.z()), color.w())
+}
+
+// Utilities
+
+#[inline]
+fn dot2(a: F32x2, b: F32x2) -> f32 {
+    a.x() * b.x() + a.y() * b.y()
+}
+
+#[inline]
+fn fract(value: f32) -> f32 {
+    value - value.floor()
+}
@@ -0,0 +1,174 @@
+// pathfinder/software/src/texture.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Texel storage, format conversion, and filtering.
+//!
+//! Texels are stored as four floats regardless of format, but every write is rounded to the
+//! precision of the format, so rendering matches what a GPU would store. Single-channel formats
+//! read back as `(r, 0, 0, 1)`, as in OpenGL.
+
+use half::f16;
+use pathfinder_geometry::rect::RectI;
+use pathfinder_geometry::vector::Vector2I;
+use pathfinder_gpu::{TextureData, TextureDataRef, TextureFormat, TextureSamplingFlags};
+use pathfinder_simd::default::{F32x2, F32x4};
+
+const MAX_TEXEL_COORD: f32 = 1.0e6;
+
+/// Rounds a color to the precision of the given format.
+pub(crate) fn quantize(color: F32x4, format: TextureFormat) -> F32x4 {
+    match format {
+        TextureFormat::R8 => {
+            F32x4::new(unorm8(color.x()), 0.0, 0.0, 1.0)
+        }
+        TextureFormat::RGBA8 => {
+            F32x4::new(unorm8(color.x()), unorm8(color.y()), unorm8(color.z()), unorm8(color.w()))
+        }
+        TextureFormat::R16F => F32x4::new(half(color.x()), 0.0, 0.0, 1.0),
+        TextureFormat::RGBA16F => {
+            F32x4::new(half(color.x()), half(color.y()), half(color.z()), half(color.w()))
+        }
+        TextureFormat::RGBA32F => color,
+    }
+}
+
+fn unorm8(value: f32) -> f32 {
+    // Like GPUs, convert NaN to zero.
+    let value = if value.is_nan() { 0.0 } else { value.clamp(0.0, 1.0) };
+    (value * 255.0).round() / 255.0
+}
+
+fn half(value: f32) -> f32 {
+    f16::from_f32(value).to_f32()
+}
+
+/// Converts texel data in the layout of `TextureDataRef` to four-channel texels.
+pub(crate) fn texels_from_data(format: TextureFormat, data: TextureDataRef, texel_count: usize)
+                               -> Vec<F32x4> {
+    let channels = format.channels();
+    let components: Vec<f32> = match data {
+        TextureDataRef::U8(data) => {
+            data[0..(texel_count * channels)].iter().map(|&value| value as f32 / 255.0).collect()
+        }
+        TextureDataRef::F16(data) => {
+            data[0..(texel_count * channels)].iter().map(|value| value.to_f32()).collect()
+        }
+        TextureDataRef::F32(data) => data[0..(texel_count * channels)].to_vec(),
+    };
+    components.chunks(channels).map(|texel| {
+        let texel = if channels == 1 {
+            F32x4::new(texel[0], 0.0, 0.0, 1.0)
+        } else {
+            F32x4::from_slice(texel)
+        };
+        quantize(texel, format)
+    }).collect()
+}
+
+/// Converts a rectangle of texels to `TextureData` in the given format, with the rows in the order
+/// they're stored.
+pub(crate) fn texels_to_data(texels: &[F32x4], stride: i32, rect: RectI, format: TextureFormat)
+                             -> TextureData {
+    let channels = format.channels();
+    let mut components = Vec::with_capacity(rect.width() as usize * rect.height() as usize *
+                                            channels);
+    for y in rect.min_y()..rect.max_y() {
+        for x in rect.min_x()..rect.max_x() {
+            let texel = texels[(y * stride + x) as usize];
+            for channel in 0..channels {
+                components.push(texel[channel]);
+            }
+        }
+    }
+    match format {
+        TextureFormat::R8 | TextureFormat::RGBA8 => {
+            TextureData::U8(components.into_iter().map(|value| {
+                (value * 255.0).round() as u8
+            }).collect())
+        }
+        TextureFormat::R16F | TextureFormat::RGBA16F => {
+            TextureData::F16(components.into_iter().map(f16::from_f32).collect())
+        }
+        TextureFormat::RGBA32F => TextureData::F32(components),
+    }
+}
+
+/// A texture bound to a shader, ready for sampling.
+#[derive(Clone, Copy)]
+pub(crate) struct Sampler<'a> {
+    texels: &'a [F32x4],
+    size: Vector2I,
+    flags: TextureSamplingFlags,
+}
+
+impl<'a> Sampler<'a> {
+    #[inline]
+    pub(crate) fn new(texels: &'a [F32x4], size: Vector2I, flags: TextureSamplingFlags)
+                      -> Sampler<'a> {
+        Sampler { texels, size, flags }
+    }
+
+    /// Samples the texture at the given normalized coordinates, like GLSL `texture()`.
+    ///
+    /// There are no mipmaps and no screen-space derivatives, so the magnification filter is
+    /// always used.
+    pub(crate) fn sample(&self, tex_coord: F32x2) -> F32x4 {
+        if self.size.x() <= 0 || self.size.y() <= 0 {
+            return F32x4::default();
+        }
+
+        // Keep NaNs and huge coordinates from overflowing the integer conversions below.
+        let texel_coord = tex_coord * self.size.to_f32().0;
+        let texel_coord = F32x2::new(sanitize(texel_coord.x()), sanitize(texel_coord.y()));
+        if self.flags.contains(TextureSamplingFlags::NEAREST_MAG) {
+            let texel_coord = texel_coord.floor().to_i32x2();
+            return self.fetch(texel_coord.x(), texel_coord.y());
+        }
+
+        // Like GPUs, keep 8 bits of subtexel precision, so that sampling at a texel center returns
+        // exactly that texel rather than picking up a sliver of its neighbor.
+        let texel_coord = texel_coord - F32x2::splat(0.5);
+        let origin = texel_coord.floor();
+        let fract = ((texel_coord - origin) * F32x2::splat(256.0) + F32x2::splat(0.5)).floor() *
+            F32x2::splat(1.0 / 256.0);
+        let origin = origin.to_i32x2();
+        let (x0, y0) = (origin.x(), origin.y());
+        let top = mix(self.fetch(x0, y0), self.fetch(x0 + 1, y0), fract.x());
+        let bottom = mix(self.fetch(x0, y0 + 1), self.fetch(x0 + 1, y0 + 1), fract.x());
+        mix(top, bottom, fract.y())
+    }
+
+    fn fetch(&self, x: i32, y: i32) -> F32x4 {
+        let x = wrap(x, self.size.x(), self.flags.contains(TextureSamplingFlags::REPEAT_U));
+        let y = wrap(y, self.size.y(), self.flags.contains(TextureSamplingFlags::REPEAT_V));
+        self.texels[(y * self.size.x() + x) as usize]
+    }
+}
+
+fn wrap(coord: i32, length: i32, repeat: bool) -> i32 {
+    if repeat {
+        coord.rem_euclid(length)
+    } else {
+        coord.max(0).min(length - 1)
+    }
+}
+
+fn sanitize(coord: f32) -> f32 {
+    if coord.is_nan() {
+        0.0
+    } else {
+        coord.clamp(-MAX_TEXEL_COORD, MAX_TEXEL_COORD)
+    }
+}
+
+#[inline]
+fn mix(a: F32x4, b: F32x4, t: f32) -> F32x4 {
+    a * F32x4::splat(1.0 - t) + b * F32x4::splat(t)
+}
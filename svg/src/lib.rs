@@ -358,9 +358,9 @@ impl PaintExt for Paint {
             }
         }
 
-        let mut base_color = paint.base_color().to_f32();
+        let mut base_color = paint.base_color_f();
         base_color.set_a(base_color.a() * opacity.value() as f32);
-        paint.set_base_color(base_color.to_u8());
+        paint.set_base_color_f(base_color);
 
         paint
     }
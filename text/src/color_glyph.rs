@@ -0,0 +1,1129 @@
+// pathfinder/text/src/color_glyph.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Color glyphs from the `COLR`/`CPAL`, `sbix`, and `CBDT`/`CBLC` tables.
+//!
+//! `COLR` glyphs become a list of layers, each of which is an ordinary glyph outline filled with a
+//! solid color or a gradient. Bitmap glyphs become images. Everything is in font units with the y
+//! axis pointing up, like glyph outlines.
+//!
+//! SVG glyphs (the `SVG ` table) aren't supported; fonts with only SVG glyphs render with their
+//! fallback outlines.
+
+use pathfinder_color::ColorU;
+use pathfinder_content::gradient::{Gradient, GradientWrap};
+use pathfinder_content::pattern::Image;
+use pathfinder_geometry::line_segment::LineSegment2F;
+use pathfinder_geometry::transform2d::Transform2F;
+use pathfinder_geometry::vector::{Vector2F, vec2f};
+use pathfinder_simd::default::F32x2;
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+// The palette index that refers to the text color.
+const FOREGROUND_PALETTE_INDEX: u16 = 0xffff;
+
+// Limits the depth of the `COLR` version 1 paint graph, which may contain cycles in broken fonts.
+const MAX_PAINT_DEPTH: u32 = 64;
+// Limits the number of paints drawn for one glyph. Without this, a broken font could make paints
+// refer to the same paints many times over and take exponential time to draw.
+const MAX_PAINT_COUNT: u32 = 4096;
+
+/// The color tables of a font. Only fonts that have at least one of them get one of these.
+#[derive(Clone)]
+pub(crate) struct ColorGlyphTables {
+    colr: Option<Arc<[u8]>>,
+    palette: Vec<ColorU>,
+    sbix: Option<Arc<[u8]>>,
+    cbdt: Option<(Arc<[u8]>, Arc<[u8]>)>,
+    glyph_count: u16,
+    units_per_em: f32,
+}
+
+/// A layer of a `COLR` glyph: a glyph outline filled with a paint.
+pub(crate) struct ColorLayer {
+    pub(crate) glyph_id: u32,
+    /// Transforms the outline of the layer glyph into the space of the color glyph.
+    pub(crate) glyph_transform: Transform2F,
+    pub(crate) paint: LayerPaint,
+}
+
+pub(crate) enum LayerPaint {
+    /// The paint the text is drawn with.
+    Foreground,
+    Color(ColorU),
+    /// A gradient in the space of the color glyph.
+    Gradient(Gradient),
+}
+
+/// A bitmap glyph from the `sbix` or `CBDT` table.
+#[derive(Clone)]
+pub(crate) struct BitmapGlyph {
+    pub(crate) image: Image,
+    /// The position of the top left corner of the image, in font units.
+    pub(crate) origin: Vector2F,
+    /// The size of the image in font units.
+    pub(crate) size: Vector2F,
+}
+
+impl ColorGlyphTables {
+    /// Loads the color tables with the given function, which returns the table with the given tag.
+    /// Returns `None` if the font has no color tables.
+    pub(crate) fn new<L>(load_font_table: L, units_per_em: u32) -> Option<ColorGlyphTables>
+                         where L: Fn(u32) -> Option<Box<[u8]>> {
+        let load = |tag: &[u8; 4]| -> Option<Arc<[u8]>> {
+            load_font_table(u32::from_be_bytes(*tag)).map(Arc::from)
+        };
+
+        let (colr, palette) = match (load(b"COLR"), load(b"CPAL")) {
+            (Some(colr), Some(cpal)) => (Some(colr), read_palette(&cpal).unwrap_or(vec![])),
+            _ => (None, vec![]),
+        };
+        let sbix = load(b"sbix");
+        let cbdt = match (load(b"CBLC"), load(b"CBDT")) {
+            (Some(cblc), Some(cbdt)) => Some((cblc, cbdt)),
+            _ => None,
+        };
+        if colr.is_none() && sbix.is_none() && cbdt.is_none() {
+            return None;
+        }
+
+        let glyph_count = load(b"maxp").and_then(|maxp| read_u16(&maxp, 4)).unwrap_or(0);
+        Some(ColorGlyphTables {
+            colr,
+            palette,
+            sbix,
+            cbdt,
+            glyph_count,
+            units_per_em: units_per_em as f32,
+        })
+    }
+
+    /// Returns the layers of a `COLR` glyph, or `None` if the glyph isn't one or its table is
+    /// malformed, in which case the glyph is drawn with its ordinary outline.
+    ///
+    /// The foreground color is used for layers that refer to the text color with some
+    /// transparency, or in gradients. Opaque foreground layers use `LayerPaint::Foreground`
+    /// instead, so that text drawn with a gradient or pattern keeps it.
+    pub(crate) fn layers(&self, glyph_id: u32, foreground: ColorU) -> Option<Vec<ColorLayer>> {
+        let colr = self.colr.as_ref()?;
+        let mut builder = LayerBuilder {
+            colr,
+            palette: &self.palette,
+            foreground,
+            layers: vec![],
+            active_paints: vec![],
+            paint_count: 0,
+        };
+        if !builder.push_glyph_v1(glyph_id, Transform2F::default(), 0)? {
+            builder.push_glyph_v0(glyph_id)?;
+        }
+        Some(builder.layers)
+    }
+
+    /// Decodes the bitmap for a glyph, or returns `None` if the glyph has no bitmap.
+    ///
+    /// Only PNG bitmaps are supported, and decoding them requires the `pf-image` feature.
+    pub(crate) fn bitmap(&self, glyph_id: u32) -> Option<BitmapGlyph> {
+        if let Some(ref sbix) = self.sbix {
+            if let Some(bitmap) = self.sbix_bitmap(sbix, glyph_id) {
+                return Some(bitmap);
+            }
+        }
+        if let Some((ref cblc, ref cbdt)) = self.cbdt {
+            if let Some(bitmap) = self.cbdt_bitmap(cblc, cbdt, glyph_id) {
+                return Some(bitmap);
+            }
+        }
+        None
+    }
+
+    // The `sbix` table stores bitmaps in strikes of different sizes. The largest one is used,
+    // since the glyph will be scaled anyway.
+    fn sbix_bitmap(&self, sbix: &[u8], glyph_id: u32) -> Option<BitmapGlyph> {
+        if glyph_id >= self.glyph_count as u32 {
+            return None;
+        }
+        let strike_count = read_u32(sbix, 4)?;
+        let mut best_strike: Option<(u16, usize)> = None;
+        for strike_index in 0..(strike_count as usize) {
+            let strike_offset = read_u32(sbix, 8 + strike_index * 4)? as usize;
+            let ppem = read_u16(sbix, strike_offset)?;
+            if best_strike.map_or(true, |(best_ppem, _)| ppem > best_ppem) {
+                best_strike = Some((ppem, strike_offset));
+            }
+        }
+        let (ppem, strike_offset) = best_strike?;
+        if ppem == 0 {
+            return None;
+        }
+
+        // A glyph can be a duplicate of another one, but only one level deep.
+        let mut glyph_id = glyph_id as usize;
+        for _ in 0..2 {
+            let start = read_u32(sbix, strike_offset + 4 + glyph_id * 4)? as usize;
+            let end = read_u32(sbix, strike_offset + 8 + glyph_id * 4)? as usize;
+            if end < start + 8 {
+                return None;
+            }
+            let glyph_offset = strike_offset + start;
+            let data = sbix.get((glyph_offset + 8)..(strike_offset + end))?;
+            match sbix.get((glyph_offset + 4)..(glyph_offset + 8))? {
+                b"png " => {
+                    // The origin offset is the position of the bottom left corner of the image in
+                    // pixels.
+                    let origin_x = read_i16(sbix, glyph_offset)? as f32;
+                    let origin_y = read_i16(sbix, glyph_offset + 2)? as f32;
+                    let image = decode_png(data)?;
+                    let scale = self.units_per_em / ppem as f32;
+                    let size = image.size().to_f32();
+                    return Some(BitmapGlyph {
+                        image,
+                        origin: vec2f(origin_x, origin_y + size.y()) * scale,
+                        size: size * scale,
+                    });
+                }
+                b"dupe" => glyph_id = read_u16(data, 0)? as usize,
+                _ => return None,
+            }
+        }
+        None
+    }
+
+    // `CBLC` indexes the bitmaps in `CBDT` by size. The largest size that contains the glyph is
+    // used.
+    fn cbdt_bitmap(&self, cblc: &[u8], cbdt: &[u8], glyph_id: u32) -> Option<BitmapGlyph> {
+        let size_count = read_u32(cblc, 4)?;
+        let mut best_size: Option<(u8, usize)> = None;
+        for size_index in 0..(size_count as usize) {
+            let record = 8 + size_index * 48;
+            let start_glyph = read_u16(cblc, record + 40)? as u32;
+            let end_glyph = read_u16(cblc, record + 42)? as u32;
+            let ppem = *cblc.get(record + 45)?;
+            if glyph_id >= start_glyph && glyph_id <= end_glyph &&
+                    best_size.map_or(true, |(best_ppem, _)| ppem > best_ppem) {
+                best_size = Some((ppem, record));
+            }
+        }
+        let (ppem, record) = best_size?;
+        if ppem == 0 {
+            return None;
+        }
+
+        let (image_format, image_offset, index_metrics) = find_cbdt_glyph(cblc, record, glyph_id)?;
+        let (metrics, data) = match image_format {
+            17 => {
+                // Small glyph metrics: height, width, bearing X, bearing Y, advance.
+                let metrics = cbdt.get(image_offset..(image_offset + 5))?;
+                (metrics[0..4].to_vec(), read_cbdt_data(cbdt, image_offset + 5)?)
+            }
+            18 => {
+                // Big glyph metrics, with the horizontal metrics first.
+                let metrics = cbdt.get(image_offset..(image_offset + 8))?;
+                (metrics[0..4].to_vec(), read_cbdt_data(cbdt, image_offset + 8)?)
+            }
+            19 => (index_metrics?.to_vec(), read_cbdt_data(cbdt, image_offset)?),
+            _ => return None,
+        };
+
+        // The bearing is the position of the top left corner of the image in pixels.
+        let bearing = vec2f(metrics[2] as i8 as f32, metrics[3] as i8 as f32);
+        let image = decode_png(data)?;
+        let scale = self.units_per_em / ppem as f32;
+        Some(BitmapGlyph {
+            size: image.size().to_f32() * scale,
+            image,
+            origin: bearing * scale,
+        })
+    }
+}
+
+// Finds a glyph in the index subtables of a `CBLC` bitmap size record. Returns the image format,
+// the offset of the image in `CBDT`, and the metrics for images whose metrics are stored in the
+// index.
+fn find_cbdt_glyph(cblc: &[u8], record: usize, glyph_id: u32)
+                   -> Option<(u16, usize, Option<&[u8]>)> {
+    let array_offset = read_u32(cblc, record)? as usize;
+    let subtable_count = read_u32(cblc, record + 8)?;
+    for subtable_index in 0..(subtable_count as usize) {
+        let entry = array_offset + subtable_index * 8;
+        let first_glyph = read_u16(cblc, entry)? as u32;
+        let last_glyph = read_u16(cblc, entry + 2)? as u32;
+        if glyph_id < first_glyph || glyph_id > last_glyph {
+            continue;
+        }
+
+        let subtable = array_offset + read_u32(cblc, entry + 4)? as usize;
+        let index_format = read_u16(cblc, subtable)?;
+        let image_format = read_u16(cblc, subtable + 2)?;
+        let image_data_offset = read_u32(cblc, subtable + 4)? as usize;
+        let index = (glyph_id - first_glyph) as usize;
+        let (offset, metrics) = match index_format {
+            1 => (read_u32(cblc, subtable + 8 + index * 4)? as usize, None),
+            2 => {
+                let image_size = read_u32(cblc, subtable + 8)? as usize;
+                (index * image_size, Some(cblc.get((subtable + 12)..(subtable + 20))?))
+            }
+            3 => (read_u16(cblc, subtable + 8 + index * 2)? as usize, None),
+            4 => {
+                let glyph_count = read_u32(cblc, subtable + 8)? as usize;
+                let offset = (0..glyph_count).find_map(|pair_index| {
+                    let pair = subtable + 12 + pair_index * 4;
+                    if read_u16(cblc, pair)? as u32 == glyph_id {
+                        read_u16(cblc, pair + 2)
+                    } else {
+                        None
+                    }
+                })?;
+                (offset as usize, None)
+            }
+            5 => {
+                let image_size = read_u32(cblc, subtable + 8)? as usize;
+                let glyph_count = read_u32(cblc, subtable + 20)? as usize;
+                let position = (0..glyph_count).position(|id_index| {
+                    read_u16(cblc, subtable + 24 + id_index * 2) == Some(glyph_id as u16)
+                })?;
+                (position * image_size, Some(cblc.get((subtable + 12)..(subtable + 20))?))
+            }
+            _ => return None,
+        };
+        return Some((image_format, image_data_offset + offset, metrics));
+    }
+    None
+}
+
+fn read_cbdt_data(cbdt: &[u8], offset: usize) -> Option<&[u8]> {
+    let length = read_u32(cbdt, offset)? as usize;
+    cbdt.get((offset + 4)..(offset + 4 + length))
+}
+
+#[cfg(feature = "pf-image")]
+fn decode_png(data: &[u8]) -> Option<Image> {
+    let image = image::load_from_memory_with_format(data, image::ImageFormat::Png).ok()?;
+    Some(Image::from_image_buffer(image.to_rgba()))
+}
+
+#[cfg(not(feature = "pf-image"))]
+fn decode_png(_: &[u8]) -> Option<Image> {
+    None
+}
+
+// Reads the first palette of a `CPAL` table. Colors are stored as BGRA.
+fn read_palette(cpal: &[u8]) -> Option<Vec<ColorU>> {
+    let entry_count = read_u16(cpal, 2)? as usize;
+    let records_offset = read_u32(cpal, 8)? as usize;
+    let first_record = read_u16(cpal, 12)? as usize;
+    (0..entry_count).map(|entry_index| {
+        let record = records_offset + (first_record + entry_index) * 4;
+        let bgra = cpal.get(record..(record + 4))?;
+        Some(ColorU::new(bgra[2], bgra[1], bgra[0], bgra[3]))
+    }).collect()
+}
+
+struct LayerBuilder<'a> {
+    colr: &'a [u8],
+    palette: &'a [ColorU],
+    foreground: ColorU,
+    layers: Vec<ColorLayer>,
+    // The offsets of the paints being drawn, from the outermost to the innermost.
+    active_paints: Vec<usize>,
+    paint_count: u32,
+}
+
+// A paint resolved to something that can fill a glyph outline, along with the transform from the
+// space of the paint into the space of the color glyph.
+struct Fill {
+    paint: LayerPaint,
+    transform: Transform2F,
+}
+
+impl<'a> LayerBuilder<'a> {
+    // Version 0 glyphs are a flat list of glyphs, each filled with a solid color.
+    fn push_glyph_v0(&mut self, glyph_id: u32) -> Option<()> {
+        let (first_layer, layer_count) = self.find_base_glyph_v0(glyph_id)?;
+        let layers_offset = read_u32(self.colr, 8)? as usize;
+        for layer_index in first_layer..(first_layer + layer_count) {
+            let record = layers_offset + layer_index * 4;
+            let layer_glyph_id = read_u16(self.colr, record)?;
+            let palette_index = read_u16(self.colr, record + 2)?;
+            self.layers.push(ColorLayer {
+                glyph_id: layer_glyph_id as u32,
+                glyph_transform: Transform2F::default(),
+                paint: self.solid_paint(palette_index, 1.0),
+            });
+        }
+        Some(())
+    }
+
+    fn find_base_glyph_v0(&self, glyph_id: u32) -> Option<(usize, usize)> {
+        let record_count = read_u16(self.colr, 2)? as usize;
+        let records_offset = read_u32(self.colr, 4)? as usize;
+        let record = binary_search(record_count, |index| {
+            read_u16(self.colr, records_offset + index * 6).map(|id| id as u32)
+        }, glyph_id)?;
+        let record = records_offset + record * 6;
+        Some((read_u16(self.colr, record + 2)? as usize, read_u16(self.colr, record + 4)? as usize))
+    }
+
+    // Version 1 glyphs are a graph of paints. Returns whether the glyph is a version 1 glyph,
+    // or `None` if its paints are malformed.
+    fn push_glyph_v1(&mut self, glyph_id: u32, transform: Transform2F, depth: u32)
+                     -> Option<bool> {
+        if read_u16(self.colr, 0) != Some(1) {
+            return Some(false);
+        }
+        let list_offset = match read_u32(self.colr, 14)? {
+            0 => return Some(false),
+            list_offset => list_offset as usize,
+        };
+        let record_count = read_u32(self.colr, list_offset)? as usize;
+        let record = match binary_search(record_count, |index| {
+            read_u16(self.colr, list_offset + 4 + index * 6).map(|id| id as u32)
+        }, glyph_id) {
+            Some(record) => record,
+            None => return Some(false),
+        };
+        let paint_offset = read_u32(self.colr, list_offset + 4 + record * 6 + 2)?;
+        self.push_paint(list_offset + paint_offset as usize, transform, depth)?;
+        Some(true)
+    }
+
+    // Pushes the layers of a paint that is drawn on its own, rather than filling a glyph. Returns
+    // `None` if the paint is malformed, contains itself, or has too many paints.
+    fn push_paint(&mut self, offset: usize, transform: Transform2F, depth: u32) -> Option<()> {
+        if depth > MAX_PAINT_DEPTH || self.paint_count == MAX_PAINT_COUNT ||
+                self.active_paints.contains(&offset) {
+            return None;
+        }
+        self.paint_count += 1;
+        self.active_paints.push(offset);
+        self.push_paint_children(offset, transform, depth)?;
+        self.active_paints.pop();
+        Some(())
+    }
+
+    fn push_paint_children(&mut self, offset: usize, transform: Transform2F, depth: u32)
+                           -> Option<()> {
+        let colr = self.colr;
+        match *colr.get(offset)? {
+            1 => {
+                // PaintColrLayers
+                let layer_count = *colr.get(offset + 1)? as usize;
+                let first_layer = read_u32(colr, offset + 2)? as usize;
+                let list_offset = read_u32(colr, 18)? as usize;
+                for layer_index in first_layer..(first_layer + layer_count) {
+                    let paint_offset = read_u32(colr, list_offset + 4 + layer_index * 4)?;
+                    self.push_paint(list_offset + paint_offset as usize, transform, depth + 1)?;
+                }
+            }
+            10 => {
+                // PaintGlyph
+                let child = read_offset24(colr, offset, 1)?;
+                let glyph_id = read_u16(colr, offset + 4)? as u32;
+                let fill = self.fill(child, depth + 1)?;
+                let paint = match fill.paint {
+                    LayerPaint::Gradient(mut gradient) => {
+                        gradient.apply_transform(transform * fill.transform);
+                        LayerPaint::Gradient(gradient)
+                    }
+                    paint => paint,
+                };
+                self.layers.push(ColorLayer { glyph_id, glyph_transform: transform, paint });
+            }
+            11 => {
+                // PaintColrGlyph. The glyph must be a version 1 glyph.
+                let glyph_id = read_u16(colr, offset + 1)? as u32;
+                if !self.push_glyph_v1(glyph_id, transform, depth + 1)? {
+                    return None;
+                }
+            }
+            32 => {
+                // PaintComposite. Only simple source-over compositing is possible without
+                // offscreen targets, so the backdrop is drawn first and the source on top of it.
+                let source = read_offset24(colr, offset, 1)?;
+                let backdrop = read_offset24(colr, offset, 5)?;
+                self.push_paint(backdrop, transform, depth + 1)?;
+                self.push_paint(source, transform, depth + 1)?;
+            }
+            format => {
+                let (child, child_transform) = read_transform_paint(colr, offset, format)?;
+                self.push_paint(child, transform * child_transform, depth + 1)?;
+            }
+        }
+        Some(())
+    }
+
+    // Resolves the paint that fills a glyph. A nested `PaintGlyph` would clip to the intersection
+    // of both glyphs; the inner glyph is ignored and only its fill is used.
+    fn fill(&self, offset: usize, depth: u32) -> Option<Fill> {
+        if depth > MAX_PAINT_DEPTH {
+            return None;
+        }
+        let colr = self.colr;
+        let format = *colr.get(offset)?;
+        match format {
+            2 | 3 => {
+                // PaintSolid
+                let palette_index = read_u16(colr, offset + 1)?;
+                let alpha = read_f2dot14(colr, offset + 3)?;
+                Some(Fill {
+                    paint: self.solid_paint(palette_index, alpha),
+                    transform: Transform2F::default(),
+                })
+            }
+            4..=9 => {
+                let color_line = read_offset24(colr, offset, 1)?;
+                let var = format % 2 == 1;
+                let gradient = match format {
+                    4 | 5 => {
+                        // PaintLinearGradient
+                        let p0 = read_point(colr, offset + 4)?;
+                        let p1 = read_point(colr, offset + 8)?;
+                        let p2 = read_point(colr, offset + 12)?;
+                        self.linear_gradient(color_line, var, p0, p1, p2)?
+                    }
+                    6 | 7 => {
+                        // PaintRadialGradient
+                        let center_0 = read_point(colr, offset + 4)?;
+                        let radius_0 = read_u16(colr, offset + 8)? as f32;
+                        let center_1 = read_point(colr, offset + 10)?;
+                        let radius_1 = read_u16(colr, offset + 14)? as f32;
+                        self.radial_gradient(color_line, var, (center_0, radius_0),
+                                             (center_1, radius_1))?
+                    }
+                    _ => {
+                        // PaintSweepGradient
+                        let center = read_point(colr, offset + 4)?;
+                        let start_angle = read_f2dot14(colr, offset + 8)? * PI;
+                        let end_angle = read_f2dot14(colr, offset + 10)? * PI;
+                        self.sweep_gradient(color_line, var, center, start_angle, end_angle)?
+                    }
+                };
+                Some(Fill {
+                    paint: LayerPaint::Gradient(gradient),
+                    transform: Transform2F::default(),
+                })
+            }
+            10 => self.fill(read_offset24(colr, offset, 1)?, depth + 1),
+            _ => {
+                let (child, child_transform) = read_transform_paint(colr, offset, format)?;
+                let mut fill = self.fill(child, depth + 1)?;
+                fill.transform = child_transform * fill.transform;
+                Some(fill)
+            }
+        }
+    }
+
+    fn solid_paint(&self, palette_index: u16, alpha: f32) -> LayerPaint {
+        if palette_index == FOREGROUND_PALETTE_INDEX && alpha >= 1.0 {
+            return LayerPaint::Foreground;
+        }
+        LayerPaint::Color(self.color(palette_index, alpha))
+    }
+
+    fn color(&self, palette_index: u16, alpha: f32) -> ColorU {
+        let mut color = if palette_index == FOREGROUND_PALETTE_INDEX {
+            self.foreground
+        } else {
+            self.palette.get(palette_index as usize).cloned().unwrap_or(ColorU::black())
+        };
+        color.a = (color.a as f32 * alpha.max(0.0).min(1.0)).round() as u8;
+        color
+    }
+
+    // The gradient line runs from p0 to p1, rotated so that the lines of equal color are parallel
+    // to p0p2.
+    fn linear_gradient(&self,
+                       color_line: usize,
+                       var: bool,
+                       p0: Vector2F,
+                       p1: Vector2F,
+                       p2: Vector2F)
+                       -> Option<Gradient> {
+        let normal = (p2 - p0).yx() * vec2f(1.0, -1.0);
+        let normal_length_squared = normal.square_length();
+        let p1 = if normal_length_squared == 0.0 {
+            p1
+        } else {
+            p0 + normal * ((p1 - p0).dot(normal) / normal_length_squared)
+        };
+
+        let color_line = self.color_line(color_line, var)?;
+        let (start, end) = color_line.range;
+        let mut gradient = Gradient::linear_from_points(p0.lerp(p1, start), p0.lerp(p1, end));
+        color_line.apply(&mut gradient);
+        Some(gradient)
+    }
+
+    fn radial_gradient(&self,
+                       color_line: usize,
+                       var: bool,
+                       circle_0: (Vector2F, f32),
+                       circle_1: (Vector2F, f32))
+                       -> Option<Gradient> {
+        let color_line = self.color_line(color_line, var)?;
+        let (start, end) = color_line.range;
+        let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+        let line = LineSegment2F::new(circle_0.0.lerp(circle_1.0, start),
+                                      circle_0.0.lerp(circle_1.0, end));
+        let radii = F32x2::new(lerp(circle_0.1, circle_1.1, start).max(0.0),
+                               lerp(circle_0.1, circle_1.1, end).max(0.0));
+        let mut gradient = Gradient::radial(line, radii);
+        color_line.apply(&mut gradient);
+        Some(gradient)
+    }
+
+    // Sweep gradients run counterclockwise from the start angle to the end angle. Conic gradients
+    // always cover the full circle, so the stops are compressed into the swept part of it.
+    fn sweep_gradient(&self,
+                      color_line: usize,
+                      var: bool,
+                      center: Vector2F,
+                      start_angle: f32,
+                      end_angle: f32)
+                      -> Option<Gradient> {
+        let mut color_line = self.color_line(color_line, var)?;
+        let (start, end) = color_line.range;
+        let mut start_angle = start_angle + (end_angle - start_angle) * start;
+        let mut sweep = (end_angle - start_angle) * (end - start);
+        if sweep < 0.0 {
+            start_angle += sweep;
+            sweep = -sweep;
+            color_line.stops.reverse();
+            for stop in &mut color_line.stops {
+                stop.1 = 1.0 - stop.1;
+            }
+        }
+        let fraction = (sweep / (PI * 2.0)).min(1.0);
+        for stop in &mut color_line.stops {
+            stop.1 *= fraction;
+        }
+        // The sweep doesn't repeat around the circle.
+        color_line.wrap = GradientWrap::Clamp;
+
+        // Conic gradient angles increase in the same direction as the angle of the y axis, which
+        // points up in font space.
+        let mut gradient = Gradient::conic(center, start_angle);
+        color_line.apply(&mut gradient);
+        Some(gradient)
+    }
+
+    // Reads a color line. Stop offsets outside the range from 0 to 1 are allowed, so the offsets
+    // are normalized to that range, which is returned so that the gradient geometry can be
+    // adjusted to match.
+    fn color_line(&self, offset: usize, var: bool) -> Option<ColorLine> {
+        let extend = *self.colr.get(offset)?;
+        let stop_count = read_u16(self.colr, offset + 1)? as usize;
+        let stride = if var { 10 } else { 6 };
+        let mut stops = vec![];
+        for stop_index in 0..stop_count {
+            let stop = offset + 3 + stop_index * stride;
+            let stop_offset = read_f2dot14(self.colr, stop)?;
+            let palette_index = read_u16(self.colr, stop + 2)?;
+            let alpha = read_f2dot14(self.colr, stop + 4)?;
+            stops.push((self.color(palette_index, alpha), stop_offset));
+        }
+        if stops.is_empty() {
+            return None;
+        }
+
+        let start = stops.iter().map(|stop| stop.1).fold(f32::INFINITY, f32::min);
+        let end = stops.iter().map(|stop| stop.1).fold(f32::NEG_INFINITY, f32::max);
+        let (start, end) = if end - start < 1.0e-4 { (0.0, 1.0) } else { (start, end) };
+        for stop in &mut stops {
+            stop.1 = ((stop.1 - start) / (end - start)).max(0.0).min(1.0);
+        }
+
+        // Reflection isn't supported, so it repeats instead.
+        let wrap = match extend {
+            1 | 2 => GradientWrap::Repeat,
+            _ => GradientWrap::Clamp,
+        };
+        Some(ColorLine { stops, range: (start, end), wrap })
+    }
+}
+
+struct ColorLine {
+    stops: Vec<(ColorU, f32)>,
+    range: (f32, f32),
+    wrap: GradientWrap,
+}
+
+impl ColorLine {
+    fn apply(self, gradient: &mut Gradient) {
+        for (color, offset) in self.stops {
+            gradient.add_color_stop(color, offset);
+        }
+        gradient.wrap = self.wrap;
+    }
+}
+
+// Reads one of the paints that transform a child paint. Returns the child and the transform.
+fn read_transform_paint(colr: &[u8], offset: usize, format: u8) -> Option<(usize, Transform2F)> {
+    let child = read_offset24(colr, offset, 1)?;
+    let angle = |field: usize| read_f2dot14(colr, offset + field).map(|angle| angle * PI);
+    let around_center = |transform: Transform2F, field: usize| {
+        let center = read_point(colr, offset + field)?;
+        Some(Transform2F::from_translation(center) * transform *
+             Transform2F::from_translation(-center))
+    };
+
+    let transform = match format {
+        12 | 13 => {
+            // PaintTransform, with an affine matrix of 16.16 fixed-point numbers.
+            let matrix = read_offset24(colr, offset, 4)?;
+            let field = |index: usize| read_fixed(colr, matrix + index * 4);
+            Transform2F::row_major(field(0)?, field(2)?, field(4)?, field(1)?, field(3)?, field(5)?)
+        }
+        14 | 15 => Transform2F::from_translation(read_point(colr, offset + 4)?),
+        16 | 17 => Transform2F::from_scale(vec2f(read_f2dot14(colr, offset + 4)?,
+                                                 read_f2dot14(colr, offset + 6)?)),
+        18 | 19 => {
+            let scale = vec2f(read_f2dot14(colr, offset + 4)?, read_f2dot14(colr, offset + 6)?);
+            around_center(Transform2F::from_scale(scale), 8)?
+        }
+        20 | 21 => Transform2F::from_scale(read_f2dot14(colr, offset + 4)?),
+        22 | 23 => around_center(Transform2F::from_scale(read_f2dot14(colr, offset + 4)?), 6)?,
+        24 | 25 => Transform2F::from_rotation(angle(4)?),
+        26 | 27 => around_center(Transform2F::from_rotation(angle(4)?), 6)?,
+        28 | 29 => skew(angle(4)?, angle(6)?),
+        30 | 31 => around_center(skew(angle(4)?, angle(6)?), 8)?,
+        _ => return None,
+    };
+    Some((child, transform))
+}
+
+fn skew(x_angle: f32, y_angle: f32) -> Transform2F {
+    Transform2F::row_major(1.0, -x_angle.tan(), 0.0, y_angle.tan(), 1.0, 0.0)
+}
+
+// Finds the index of the record with the given glyph ID in a sorted array of records.
+fn binary_search<F>(count: usize, glyph_id_at: F, glyph_id: u32) -> Option<usize>
+                    where F: Fn(usize) -> Option<u32> {
+    let (mut low, mut high) = (0, count);
+    while low < high {
+        let middle = (low + high) / 2;
+        let middle_glyph_id = glyph_id_at(middle)?;
+        if middle_glyph_id == glyph_id {
+            return Some(middle);
+        }
+        if middle_glyph_id < glyph_id {
+            low = middle + 1;
+        } else {
+            high = middle;
+        }
+    }
+    None
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    let bytes = data.get(offset..(offset + 2))?;
+    Some(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_i16(data: &[u8], offset: usize) -> Option<i16> {
+    read_u16(data, offset).map(|value| value as i16)
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..(offset + 4))?;
+    Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+// Reads a 24-bit offset at the given field of a paint, relative to the start of the paint.
+fn read_offset24(data: &[u8], paint: usize, field: usize) -> Option<usize> {
+    let bytes = data.get((paint + field)..(paint + field + 3))?;
+    let offset = (bytes[0] as usize) << 16 | (bytes[1] as usize) << 8 | bytes[2] as usize;
+    if offset == 0 {
+        None
+    } else {
+        Some(paint + offset)
+    }
+}
+
+fn read_f2dot14(data: &[u8], offset: usize) -> Option<f32> {
+    read_i16(data, offset).map(|value| value as f32 / 16384.0)
+}
+
+fn read_fixed(data: &[u8], offset: usize) -> Option<f32> {
+    read_u32(data, offset).map(|value| value as i32 as f32 / 65536.0)
+}
+
+fn read_point(data: &[u8], offset: usize) -> Option<Vector2F> {
+    Some(vec2f(read_i16(data, offset)? as f32, read_i16(data, offset + 2)? as f32))
+}
+
+#[cfg(test)]
+mod test {
+    use pathfinder_color::ColorU;
+    use pathfinder_content::gradient::{Gradient, GradientWrap};
+    use pathfinder_geometry::line_segment::LineSegment2F;
+    use pathfinder_geometry::vector::vec2f;
+    use pathfinder_simd::default::F32x2;
+
+    use super::{ColorGlyphTables, ColorLayer, LayerPaint};
+
+    const RED: ColorU = ColorU { r: 255, g: 0, b: 0, a: 255 };
+    const BLUE: ColorU = ColorU { r: 0, g: 0, b: 255, a: 255 };
+    const GREEN: ColorU = ColorU { r: 0, g: 255, b: 0, a: 255 };
+    const FOREGROUND: u16 = 0xffff;
+
+    // Loads the given `COLR` table along with a palette of red and blue.
+    fn color_tables(colr: Vec<u8>) -> ColorGlyphTables {
+        let cpal = cpal(&[RED, BLUE]);
+        ColorGlyphTables::new(|tag| {
+            match &tag.to_be_bytes() {
+                b"COLR" => Some(colr.clone().into_boxed_slice()),
+                b"CPAL" => Some(cpal.clone().into_boxed_slice()),
+                _ => None,
+            }
+        }, 1000).unwrap()
+    }
+
+    fn layers(colr: Vec<u8>, glyph_id: u32) -> Option<Vec<ColorLayer>> {
+        color_tables(colr).layers(glyph_id, GREEN)
+    }
+
+    // The glyph ID and color of each layer, with `None` for the foreground paint.
+    fn solid_layers(layers: &[ColorLayer]) -> Vec<(u32, Option<ColorU>)> {
+        layers.iter().map(|layer| {
+            match layer.paint {
+                LayerPaint::Foreground => (layer.glyph_id, None),
+                LayerPaint::Color(color) => (layer.glyph_id, Some(color)),
+                LayerPaint::Gradient(_) => panic!("Expected a solid color!"),
+            }
+        }).collect()
+    }
+
+    fn gradient_of_only_layer(layers: Option<Vec<ColorLayer>>) -> Gradient {
+        let mut layers = layers.unwrap();
+        assert_eq!(layers.len(), 1);
+        match layers.pop().unwrap().paint {
+            LayerPaint::Gradient(gradient) => gradient,
+            _ => panic!("Expected a gradient!"),
+        }
+    }
+
+    fn cpal(colors: &[ColorU]) -> Vec<u8> {
+        let mut cpal = vec![];
+        cpal.extend_from_slice(&0u16.to_be_bytes());
+        cpal.extend_from_slice(&(colors.len() as u16).to_be_bytes());
+        cpal.extend_from_slice(&1u16.to_be_bytes());
+        cpal.extend_from_slice(&(colors.len() as u16).to_be_bytes());
+        cpal.extend_from_slice(&14u32.to_be_bytes());
+        cpal.extend_from_slice(&0u16.to_be_bytes());
+        for color in colors {
+            cpal.extend_from_slice(&[color.b, color.g, color.r, color.a]);
+        }
+        cpal
+    }
+
+    // Builds a version 0 `COLR` table from the first layer and layer count of each base glyph, in
+    // glyph ID order, and the glyph ID and palette index of each layer.
+    fn colr_v0(base_glyphs: &[(u16, u16, u16)], layers: &[(u16, u16)]) -> Vec<u8> {
+        let layers_offset = 14 + base_glyphs.len() * 6;
+        let mut colr = vec![];
+        colr.extend_from_slice(&0u16.to_be_bytes());
+        colr.extend_from_slice(&(base_glyphs.len() as u16).to_be_bytes());
+        colr.extend_from_slice(&14u32.to_be_bytes());
+        colr.extend_from_slice(&(layers_offset as u32).to_be_bytes());
+        colr.extend_from_slice(&(layers.len() as u16).to_be_bytes());
+        for &(glyph_id, first_layer, layer_count) in base_glyphs {
+            for value in &[glyph_id, first_layer, layer_count] {
+                colr.extend_from_slice(&value.to_be_bytes());
+            }
+        }
+        for &(glyph_id, palette_index) in layers {
+            colr.extend_from_slice(&glyph_id.to_be_bytes());
+            colr.extend_from_slice(&palette_index.to_be_bytes());
+        }
+        colr
+    }
+
+    // Builds a version 1 `COLR` table with no version 0 glyphs. Base glyphs, in glyph ID order,
+    // and layers refer to paints by their offset in `paints`.
+    fn colr_v1(base_glyphs: &[(u16, usize)], layers: &[usize], paints: &[u8]) -> Vec<u8> {
+        let base_glyph_list = 34;
+        let layer_list = base_glyph_list + 4 + base_glyphs.len() * 6;
+        let paints_offset = layer_list + 4 + layers.len() * 4;
+        let mut colr = vec![];
+        colr.extend_from_slice(&1u16.to_be_bytes());
+        colr.extend_from_slice(&[0; 12]);
+        colr.extend_from_slice(&(base_glyph_list as u32).to_be_bytes());
+        colr.extend_from_slice(&(layer_list as u32).to_be_bytes());
+        colr.extend_from_slice(&[0; 12]);
+        colr.extend_from_slice(&(base_glyphs.len() as u32).to_be_bytes());
+        for &(glyph_id, paint) in base_glyphs {
+            colr.extend_from_slice(&glyph_id.to_be_bytes());
+            let paint_offset = paints_offset + paint - base_glyph_list;
+            colr.extend_from_slice(&(paint_offset as u32).to_be_bytes());
+        }
+        colr.extend_from_slice(&(layers.len() as u32).to_be_bytes());
+        for &paint in layers {
+            colr.extend_from_slice(&((paints_offset + paint - layer_list) as u32).to_be_bytes());
+        }
+        colr.extend_from_slice(paints);
+        colr
+    }
+
+    fn f2dot14(value: f32) -> [u8; 2] {
+        ((value * 16384.0) as i16).to_be_bytes()
+    }
+
+    fn offset24(offset: usize) -> [u8; 3] {
+        [(offset >> 16) as u8, (offset >> 8) as u8, offset as u8]
+    }
+
+    fn paint_colr_layers(layer_count: u8, first_layer: u32) -> Vec<u8> {
+        let mut paint = vec![1, layer_count];
+        paint.extend_from_slice(&first_layer.to_be_bytes());
+        paint
+    }
+
+    fn paint_solid(palette_index: u16, alpha: f32) -> Vec<u8> {
+        let mut paint = vec![2];
+        paint.extend_from_slice(&palette_index.to_be_bytes());
+        paint.extend_from_slice(&f2dot14(alpha));
+        paint
+    }
+
+    // Gradients are followed directly by their color line.
+    fn paint_linear_gradient(points: [(i16, i16); 3], color_line: Vec<u8>) -> Vec<u8> {
+        let mut paint = vec![4];
+        paint.extend_from_slice(&offset24(16));
+        for &(x, y) in &points {
+            paint.extend_from_slice(&x.to_be_bytes());
+            paint.extend_from_slice(&y.to_be_bytes());
+        }
+        paint.extend(color_line);
+        paint
+    }
+
+    fn paint_radial_gradient(circles: [((i16, i16), u16); 2], color_line: Vec<u8>) -> Vec<u8> {
+        let mut paint = vec![6];
+        paint.extend_from_slice(&offset24(16));
+        for &((x, y), radius) in &circles {
+            paint.extend_from_slice(&x.to_be_bytes());
+            paint.extend_from_slice(&y.to_be_bytes());
+            paint.extend_from_slice(&radius.to_be_bytes());
+        }
+        paint.extend(color_line);
+        paint
+    }
+
+    // Angles are in half turns, as in the table.
+    fn paint_sweep_gradient(start_angle: f32, end_angle: f32, color_line: Vec<u8>) -> Vec<u8> {
+        let mut paint = vec![8];
+        paint.extend_from_slice(&offset24(12));
+        paint.extend_from_slice(&[0; 4]);
+        paint.extend_from_slice(&f2dot14(start_angle));
+        paint.extend_from_slice(&f2dot14(end_angle));
+        paint.extend(color_line);
+        paint
+    }
+
+    fn color_line(extend: u8, stops: &[(f32, u16)]) -> Vec<u8> {
+        let mut color_line = vec![extend];
+        color_line.extend_from_slice(&(stops.len() as u16).to_be_bytes());
+        for &(offset, palette_index) in stops {
+            color_line.extend_from_slice(&f2dot14(offset));
+            color_line.extend_from_slice(&palette_index.to_be_bytes());
+            color_line.extend_from_slice(&f2dot14(1.0));
+        }
+        color_line
+    }
+
+    // The glyph is filled with the child paint, which follows it directly.
+    fn paint_glyph(glyph_id: u16, child: Vec<u8>) -> Vec<u8> {
+        let mut paint = vec![10];
+        paint.extend_from_slice(&offset24(6));
+        paint.extend_from_slice(&glyph_id.to_be_bytes());
+        paint.extend(child);
+        paint
+    }
+
+    fn paint_colr_glyph(glyph_id: u16) -> Vec<u8> {
+        let mut paint = vec![11];
+        paint.extend_from_slice(&glyph_id.to_be_bytes());
+        paint
+    }
+
+    // Lays out paints one after the other, returning the offset of each.
+    fn concat(paints: &[Vec<u8>]) -> (Vec<u8>, Vec<usize>) {
+        let (mut data, mut offsets) = (vec![], vec![]);
+        for paint in paints {
+            offsets.push(data.len());
+            data.extend_from_slice(paint);
+        }
+        (data, offsets)
+    }
+
+    #[test]
+    fn version_0_glyphs_are_lists_of_solid_layers() {
+        let colr = colr_v0(&[(3, 0, 2), (7, 2, 1)], &[(10, 1), (11, FOREGROUND), (12, 0)]);
+        assert_eq!(solid_layers(&layers(colr.clone(), 3).unwrap()),
+                   vec![(10, Some(BLUE)), (11, None)]);
+        assert_eq!(solid_layers(&layers(colr.clone(), 7).unwrap()), vec![(12, Some(RED))]);
+        assert!(layers(colr, 4).is_none());
+    }
+
+    #[test]
+    fn version_1_layers_are_filled_with_solid_paints() {
+        let (paints, offsets) = concat(&[
+            paint_colr_layers(3, 0),
+            paint_glyph(5, paint_solid(0, 1.0)),
+            paint_glyph(6, paint_solid(FOREGROUND, 1.0)),
+            // The text color with some transparency is a solid color rather than the foreground
+            // paint.
+            paint_glyph(7, paint_solid(FOREGROUND, 0.5)),
+            paint_colr_glyph(1),
+        ]);
+        let colr = colr_v1(&[(1, offsets[0]), (2, offsets[4])],
+                           &[offsets[1], offsets[2], offsets[3]],
+                           &paints);
+        let expected = vec![(5, Some(RED)), (6, None), (7, Some(ColorU::new(0, 255, 0, 128)))];
+        assert_eq!(solid_layers(&layers(colr.clone(), 1).unwrap()), expected);
+        // `PaintColrGlyph` reuses the layers of another glyph.
+        assert_eq!(solid_layers(&layers(colr.clone(), 2).unwrap()), expected);
+        assert!(layers(colr, 3).is_none());
+    }
+
+    #[test]
+    fn linear_gradients_are_normalized_to_their_stops() {
+        // The stops cover the middle half of the gradient line, and the gradient repeats.
+        let color_line = color_line(1, &[(0.25, 0), (0.75, 1)]);
+        let paint = paint_glyph(5, paint_linear_gradient([(0, 0), (100, 0), (0, 100)],
+                                                         color_line));
+        let gradient = gradient_of_only_layer(layers(colr_v1(&[(1, 0)], &[], &paint), 1));
+
+        let mut expected = Gradient::linear_from_points(vec2f(25.0, 0.0), vec2f(75.0, 0.0));
+        expected.add_color_stop(RED, 0.0);
+        expected.add_color_stop(BLUE, 1.0);
+        expected.wrap = GradientWrap::Repeat;
+        assert_eq!(gradient, expected);
+    }
+
+    #[test]
+    fn linear_gradients_are_rotated_to_their_third_point() {
+        let color_line = color_line(0, &[(0.0, 0), (1.0, 1)]);
+        let paint = paint_glyph(5, paint_linear_gradient([(0, 0), (100, 100), (0, 100)],
+                                                         color_line));
+        let gradient = gradient_of_only_layer(layers(colr_v1(&[(1, 0)], &[], &paint), 1));
+        // Lines of equal color are parallel to the vertical line from p0 to p2.
+        let mut expected = Gradient::linear_from_points(vec2f(0.0, 0.0), vec2f(100.0, 0.0));
+        expected.add_color_stop(RED, 0.0);
+        expected.add_color_stop(BLUE, 1.0);
+        assert_eq!(gradient, expected);
+    }
+
+    #[test]
+    fn radial_gradients_keep_both_circles() {
+        let color_line = color_line(0, &[(0.0, 0), (1.0, 1)]);
+        let paint = paint_glyph(5, paint_radial_gradient([((10, 20), 0), ((10, 20), 50)],
+                                                         color_line));
+        let gradient = gradient_of_only_layer(layers(colr_v1(&[(1, 0)], &[], &paint), 1));
+
+        let center = vec2f(10.0, 20.0);
+        let mut expected = Gradient::radial(LineSegment2F::new(center, center),
+                                            F32x2::new(0.0, 50.0));
+        expected.add_color_stop(RED, 0.0);
+        expected.add_color_stop(BLUE, 1.0);
+        assert_eq!(gradient, expected);
+    }
+
+    #[test]
+    fn sweep_gradients_compress_their_stops_into_the_swept_angle() {
+        // A quarter turn counterclockwise from the x axis.
+        let color_line = color_line(0, &[(0.0, 0), (1.0, 1)]);
+        let paint = paint_glyph(5, paint_sweep_gradient(0.0, 0.5, color_line.clone()));
+        let gradient = gradient_of_only_layer(layers(colr_v1(&[(1, 0)], &[], &paint), 1));
+
+        let mut expected = Gradient::conic(vec2f(0.0, 0.0), 0.0);
+        expected.add_color_stop(RED, 0.0);
+        expected.add_color_stop(BLUE, 0.25);
+        assert_eq!(gradient, expected);
+
+        // A clockwise sweep becomes a counterclockwise one with the stops reversed.
+        let paint = paint_glyph(5, paint_sweep_gradient(0.5, 0.0, color_line));
+        let gradient = gradient_of_only_layer(layers(colr_v1(&[(1, 0)], &[], &paint), 1));
+
+        let mut expected = Gradient::conic(vec2f(0.0, 0.0), 0.0);
+        expected.add_color_stop(BLUE, 0.0);
+        expected.add_color_stop(RED, 0.25);
+        assert_eq!(gradient, expected);
+    }
+
+    #[test]
+    fn cyclic_paint_graphs_are_rejected() {
+        // A glyph that contains itself.
+        let colr = colr_v1(&[(1, 0)], &[], &paint_colr_glyph(1));
+        assert!(layers(colr, 1).is_none());
+
+        // A layer that contains itself.
+        let colr = colr_v1(&[(1, 0)], &[0], &paint_colr_layers(1, 0));
+        assert!(layers(colr, 1).is_none());
+
+        // Many layers that contain the paint they're in.
+        let colr = colr_v1(&[(1, 0)], &[0; 255], &paint_colr_layers(255, 0));
+        assert!(layers(colr, 1).is_none());
+    }
+
+    #[test]
+    fn paint_graphs_that_fan_out_too_much_are_rejected() {
+        // Each paint has 255 layers that all refer to the next paint, so the glyph would have
+        // 255^8 layers.
+        let mut paints: Vec<_> = (0..8).map(|index| paint_colr_layers(255, index * 255)).collect();
+        paints.push(paint_glyph(5, paint_solid(0, 1.0)));
+        let (paints, offsets) = concat(&paints);
+        let layer_list: Vec<_> = (1..9).flat_map(|index| vec![offsets[index]; 255]).collect();
+        let colr = colr_v1(&[(1, 0)], &layer_list, &paints);
+        assert!(layers(colr, 1).is_none());
+
+        // Reusing a paint is fine as long as there aren't too many paints in all.
+        let (paints, offsets) = concat(&[
+            paint_colr_layers(255, 0),
+            paint_glyph(5, paint_solid(0, 1.0)),
+        ]);
+        let colr = colr_v1(&[(1, 0)], &[offsets[1]; 255], &paints);
+        assert_eq!(solid_layers(&layers(colr, 1).unwrap()), vec![(5, Some(RED)); 255]);
+    }
+
+    #[test]
+    fn malformed_tables_are_rejected() {
+        // Truncated version 0 headers and layer records.
+        let colr = colr_v0(&[(3, 0, 2)], &[(10, 1), (11, 0)]);
+        assert!(layers(colr[0..10].to_vec(), 3).is_none());
+        assert!(layers(colr[0..(colr.len() - 2)].to_vec(), 3).is_none());
+
+        // A truncated version 1 header.
+        let paint = paint_glyph(5, paint_solid(0, 1.0));
+        let colr = colr_v1(&[(1, 0)], &[], &paint);
+        assert!(layers(colr[0..16].to_vec(), 1).is_none());
+        // A truncated paint.
+        assert!(layers(colr[0..(colr.len() - 2)].to_vec(), 1).is_none());
+
+        // A null offset to the paint that fills a glyph.
+        let mut paint = paint_glyph(5, paint_solid(0, 1.0));
+        paint[1..4].copy_from_slice(&offset24(0));
+        assert!(layers(colr_v1(&[(1, 0)], &[], &paint), 1).is_none());
+
+        // An unknown paint format.
+        assert!(layers(colr_v1(&[(1, 0)], &[], &[200, 0, 0, 0]), 1).is_none());
+
+        // A color line with no stops.
+        let paint = paint_glyph(5, paint_sweep_gradient(0.0, 1.0, color_line(0, &[])));
+        assert!(layers(colr_v1(&[(1, 0)], &[], &paint), 1).is_none());
+
+        // A layer outside the layer list.
+        let paints = [paint_colr_layers(2, 0), paint_glyph(5, paint_solid(0, 1.0))];
+        let colr = colr_v1(&[(1, 0)], &[6], &concat(&paints).0);
+        assert!(layers(colr, 1).is_none());
+    }
+}
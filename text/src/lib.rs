@@ -8,25 +8,52 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
+use crate::color_glyph::{BitmapGlyph, ColorGlyphTables, ColorLayer, LayerPaint};
+use crate::variations::VariedFace;
 use font_kit::error::GlyphLoadingError;
 use font_kit::hinting::HintingOptions;
 use font_kit::loader::Loader;
 use font_kit::loaders::default::Font as DefaultLoader;
 use font_kit::metrics::Metrics;
 use font_kit::outline::OutlineSink;
+use pathfinder_color::ColorU;
 use pathfinder_content::effects::BlendMode;
 use pathfinder_content::outline::{Contour, Outline};
+use pathfinder_content::pattern::Pattern;
 use pathfinder_content::stroke::{OutlineStrokeToFill, StrokeStyle};
 use pathfinder_geometry::line_segment::LineSegment2F;
+use pathfinder_geometry::rect::RectF;
 use pathfinder_geometry::transform2d::Transform2F;
 use pathfinder_geometry::vector::{Vector2F, vec2f};
-use pathfinder_renderer::paint::PaintId;
+use pathfinder_renderer::paint::{Paint, PaintId};
 use pathfinder_renderer::scene::{ClipPathId, DrawPath, Scene};
 use skribo::{FontCollection, Layout, TextStyle};
 use std::collections::HashMap;
+use std::f32::consts::PI;
 use std::mem;
 use std::sync::Arc;
 
+pub use crate::paragraph::{LineHeight, LineMetrics, Paragraph, ParagraphAlign, ParagraphLayout};
+pub use crate::paragraph::{ParagraphStyle, SpanStyle};
+pub use crate::variations::{FontVariation, glyph_advance};
+
+mod color_glyph;
+mod paragraph;
+#[cfg(feature = "shaping")]
+pub mod shaping;
+mod variations;
+
+// The amount each side of a glyph is dilated by for synthetic bold, as a fraction of the em size.
+// This matches FreeType's emboldening, which widens glyphs by 1/24 em.
+const SYNTHETIC_BOLD_DILATION: f32 = 1.0 / 48.0;
+
+// The slant of synthetic oblique text, which is the default angle of CSS `oblique`.
+const SYNTHETIC_OBLIQUE_ANGLE: f32 = 14.0 * PI / 180.0;
+
+// The index of the font that font data is parsed as. font-kit doesn't expose the index of fonts in
+// collections, so shaping and variations only work for the first font in a collection file.
+const COLLECTION_FONT_INDEX: u32 = 0;
+
 #[derive(Clone)]
 pub struct FontContext<F> where F: Loader {
     font_info: HashMap<String, FontInfo<F>>, 
@@ -37,21 +64,42 @@ struct FontInfo<F> where F: Loader {
     font: F,
     metrics: Metrics,
     outline_cache: HashMap<GlyphId, Outline>,
+    color_tables: Option<ColorGlyphTables>,
+    bitmap_cache: HashMap<GlyphId, Option<BitmapGlyph>>,
+    font_data: Option<Arc<Vec<u8>>>,
 }
 
 #[derive(Clone, Copy)]
-pub struct FontRenderOptions {
+pub struct FontRenderOptions<'a> {
     pub transform: Transform2F,
     pub render_mode: TextRenderMode,
     pub hinting_options: HintingOptions,
     pub clip_path: Option<ClipPathId>,
     pub blend_mode: BlendMode,
     pub paint_id: PaintId,
+    /// Variation axis values for variable fonts. The layout should have been made with the same
+    /// values, using `layout_with_variations()`.
+    ///
+    /// Hinting is ignored for glyphs drawn with variations.
+    pub variations: &'a [FontVariation],
+    /// Styles to synthesize for fonts that lack a bold or italic face.
+    pub synthetic_style: SyntheticStyle,
 }
 
-impl Default for FontRenderOptions {
+/// Bold and oblique styles synthesized from a regular font face, for when the font family has no
+/// such face.
+///
+/// Synthetic bold dilates the outlines without changing the advances, and synthetic oblique
+/// slants them to the right.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct SyntheticStyle {
+    pub bold: bool,
+    pub oblique: bool,
+}
+
+impl<'a> Default for FontRenderOptions<'a> {
     #[inline]
-    fn default() -> FontRenderOptions {
+    fn default() -> FontRenderOptions<'a> {
         FontRenderOptions {
             transform: Transform2F::default(),
             render_mode: TextRenderMode::Fill,
@@ -59,6 +107,8 @@ impl Default for FontRenderOptions {
             clip_path: None,
             blend_mode: BlendMode::SrcOver,
             paint_id: PaintId(0),
+            variations: &[],
+            synthetic_style: SyntheticStyle::default(),
         }
     }
 }
@@ -102,45 +152,59 @@ impl<F> FontContext<F> where F: Loader {
         };
         let font_info = font_info.get_mut();
 
-        // See if we have a cached outline.
-        //
-        // TODO(pcwalton): Cache hinted outlines too.
-        let mut cached_outline = None;
-        let can_cache_outline = render_options.hinting_options == HintingOptions::None;
-        if can_cache_outline {
-            if let Some(ref outline) = font_info.outline_cache.get(&glyph_id) {
-                cached_outline = Some((*outline).clone());
-            }
-        }
-
         let metrics = &font_info.metrics;
         let font_scale = font_size / metrics.units_per_em as f32;
         let render_transform = render_options.transform *
-            Transform2F::from_scale(vec2f(font_scale, -font_scale)).translate(glyph_offset);
+            Transform2F::from_scale(vec2f(font_scale, -font_scale)).translate(glyph_offset) *
+            synthetic_oblique_transform(render_options.synthetic_style);
+        let synthetic_bold = render_options.synthetic_style.bold;
 
-        let mut outline = match cached_outline {
-            Some(mut cached_outline) => {
-                let scale = 1.0 / metrics.units_per_em as f32;
-                cached_outline.transform(&(render_transform * Transform2F::from_scale(scale)));
-                cached_outline
-            }
-            None => {
-                let transform = if can_cache_outline {
-                    Transform2F::from_scale(metrics.units_per_em as f32)
-                } else {
-                    render_transform
-                };
-                let mut outline_builder = OutlinePathBuilder::new(&transform);
-                font.outline(glyph_id.0, render_options.hinting_options, &mut outline_builder)?;
-                let mut outline = outline_builder.build();
-                if can_cache_outline {
-                    font_info.outline_cache.insert(glyph_id, outline.clone());
-                    let scale = 1.0 / metrics.units_per_em as f32;
-                    outline.transform(&(render_transform * Transform2F::from_scale(scale)));
+        // Color glyphs are only filled. Stroking them strokes the plain outline.
+        if render_options.render_mode == TextRenderMode::Fill {
+            let foreground = scene.get_paint(render_options.paint_id).base_color();
+            if let Some(layers) = font_info.color_layers(glyph_id, foreground) {
+                for layer in layers {
+                    let mut outline = font_info.outline(layer.glyph_id, render_options)?;
+                    outline.transform(&layer.glyph_transform);
+                    if synthetic_bold {
+                        font_info.embolden(&mut outline);
+                    }
+                    outline.transform(&render_transform);
+                    let paint_id = match layer.paint {
+                        LayerPaint::Foreground => render_options.paint_id,
+                        LayerPaint::Color(color) => scene.push_paint(&Paint::from_color(color)),
+                        LayerPaint::Gradient(mut gradient) => {
+                            gradient.apply_transform(render_transform);
+                            scene.push_paint(&Paint::from_gradient(gradient))
+                        }
+                    };
+                    push_draw_path(scene, outline, paint_id, render_options);
                 }
-                outline
+                return Ok(());
             }
-        };
+
+            if let Some(bitmap) = font_info.bitmap(glyph_id) {
+                // Images have the y axis pointing down, unlike font units.
+                let image_size = bitmap.image.size().to_f32();
+                let image_transform = render_transform *
+                    Transform2F::from_translation(bitmap.origin) *
+                    Transform2F::from_scale(bitmap.size * vec2f(1.0, -1.0) / image_size);
+                let mut pattern = Pattern::from_image(bitmap.image);
+                pattern.apply_transform(image_transform);
+                let paint_id = scene.push_paint(&Paint::from_pattern(pattern));
+
+                let mut outline = Outline::from_rect(RectF::new(Vector2F::zero(), image_size));
+                outline.transform(&image_transform);
+                push_draw_path(scene, outline, paint_id, render_options);
+                return Ok(());
+            }
+        }
+
+        let mut outline = font_info.outline(glyph_id.0, render_options)?;
+        if synthetic_bold {
+            font_info.embolden(&mut outline);
+        }
+        outline.transform(&render_transform);
 
         if let TextRenderMode::Stroke(stroke_style) = render_options.render_mode {
             let mut stroke_to_fill = OutlineStrokeToFill::new(&outline, stroke_style);
@@ -148,11 +212,7 @@ impl<F> FontContext<F> where F: Loader {
             outline = stroke_to_fill.into_outline();
         }
 
-        let mut path = DrawPath::new(outline, render_options.paint_id);
-        path.set_clip_path(render_options.clip_path);
-        path.set_blend_mode(render_options.blend_mode);
-
-        scene.push_draw_path(path);
+        push_draw_path(scene, outline, render_options.paint_id, render_options);
         Ok(())
     }
 
@@ -194,6 +254,38 @@ impl FontContext<DefaultLoader> {
         Ok(())
     }
 
+    /// Pushes the glyphs of a laid-out paragraph. Each glyph is drawn with the paint of its span,
+    /// so the paint ID in the render options is ignored.
+    pub fn push_paragraph(&mut self,
+                          scene: &mut Scene,
+                          layout: &ParagraphLayout,
+                          render_options: &FontRenderOptions)
+                          -> Result<(), GlyphLoadingError> {
+        let mut glyph_render_options = *render_options;
+        let mut cached_font_key: Option<CachedFontKey<DefaultLoader>> = None;
+        for glyph in layout.glyphs() {
+            match cached_font_key {
+                Some(ref cached_font_key) if Arc::ptr_eq(&cached_font_key.font, &glyph.font) => {}
+                _ => {
+                    cached_font_key = Some(CachedFontKey {
+                        font: glyph.font.clone(),
+                        key: glyph.font.postscript_name(),
+                    });
+                }
+            }
+            let cached_font_key = cached_font_key.as_ref().unwrap();
+            glyph_render_options.paint_id = glyph.paint_id;
+            self.push_glyph(scene,
+                            &*cached_font_key.font,
+                            cached_font_key.key.as_ref().map(|key| &**key),
+                            GlyphId(glyph.glyph_id),
+                            glyph.offset,
+                            glyph.size,
+                            &glyph_render_options)?;
+        }
+        Ok(())
+    }
+
     #[inline]
     pub fn push_text(&mut self,
                      scene: &mut Scene,
@@ -202,11 +294,40 @@ impl FontContext<DefaultLoader> {
                      collection: &FontCollection,
                      render_options: &FontRenderOptions)
                      -> Result<(), GlyphLoadingError> {
-        let layout = skribo::layout(style, collection, text);
+        let layout = layout_with_variations(style, collection, text, render_options.variations);
         self.push_layout(scene, &layout, style, render_options)
     }
 }
 
+/// Lays out a single line of text.
+///
+/// If the `shaping` feature is enabled, this performs complex shaping and bidirectional
+/// reordering with `shaping::shape()`. Otherwise, it uses skribo's simple layout, which places
+/// one glyph per character from left to right.
+#[inline]
+pub fn layout(style: &TextStyle, collection: &FontCollection, text: &str) -> Layout {
+    layout_with_variations(style, collection, text, &[])
+}
+
+/// Lays out a single line of text with the given variation axis values applied to variable fonts.
+///
+/// With the `shaping` feature, the shaper uses the varied font. Otherwise, the glyphs are moved
+/// to account for the varied advances.
+pub fn layout_with_variations(style: &TextStyle,
+                              collection: &FontCollection,
+                              text: &str,
+                              variations: &[FontVariation])
+                              -> Layout {
+    #[cfg(feature = "shaping")]
+    return shaping::shape_with_variations(style, collection, text, variations);
+    #[cfg(not(feature = "shaping"))]
+    {
+        let mut layout = skribo::layout(style, collection, text);
+        variations::adjust_advances(&mut layout, variations);
+        layout
+    }
+}
+
 struct CachedFontKey<F> where F: Loader {
     font: Arc<F>,
     key: Option<String>,
@@ -215,8 +336,109 @@ struct CachedFontKey<F> where F: Loader {
 impl<F> FontInfo<F> where F: Loader {
     fn new(font: F) -> FontInfo<F> {
         let metrics = font.metrics();
-        FontInfo { font, metrics, outline_cache: HashMap::new() }
+        let color_tables = ColorGlyphTables::new(|tag| font.load_font_table(tag),
+                                                 metrics.units_per_em);
+        FontInfo {
+            font,
+            metrics,
+            outline_cache: HashMap::new(),
+            color_tables,
+            bitmap_cache: HashMap::new(),
+            font_data: None,
+        }
+    }
+
+    /// Loads the outline of a glyph in font units.
+    fn outline(&mut self, glyph_id: u32, render_options: &FontRenderOptions)
+               -> Result<Outline, GlyphLoadingError> {
+        if !render_options.variations.is_empty() {
+            return self.varied_outline(glyph_id, render_options.variations);
+        }
+
+        // See if we have a cached outline.
+        //
+        // TODO(pcwalton): Cache hinted outlines too.
+        let hinting_options = render_options.hinting_options;
+        let can_cache_outline = hinting_options == HintingOptions::None;
+        let units_per_em = self.metrics.units_per_em as f32;
+        if can_cache_outline {
+            if let Some(outline) = self.outline_cache.get(&GlyphId(glyph_id)) {
+                let scale = 1.0 / units_per_em;
+                return Ok(outline.clone().transformed(&Transform2F::from_scale(scale)));
+            }
+        }
+
+        let transform = if can_cache_outline {
+            Transform2F::from_scale(units_per_em)
+        } else {
+            Transform2F::default()
+        };
+        let mut outline_builder = OutlinePathBuilder::new(&transform);
+        self.font.outline(glyph_id, hinting_options, &mut outline_builder)?;
+        let mut outline = outline_builder.build();
+        if can_cache_outline {
+            self.outline_cache.insert(GlyphId(glyph_id), outline.clone());
+            outline.transform(&Transform2F::from_scale(1.0 / units_per_em));
+        }
+        Ok(outline)
+    }
+
+    // Varied outlines aren't cached, since they depend on the variation values.
+    fn varied_outline(&mut self, glyph_id: u32, variations: &[FontVariation])
+                      -> Result<Outline, GlyphLoadingError> {
+        if self.font_data.is_none() {
+            self.font_data = self.font.copy_font_data();
+        }
+        let face = self.font_data.as_ref().and_then(|data| VariedFace::new(data, variations));
+        let face = match face {
+            Some(face) => face,
+            None => {
+                // The font couldn't be parsed, so fall back to the default instance.
+                let mut outline_builder = OutlinePathBuilder::new(&Transform2F::default());
+                self.font.outline(glyph_id, HintingOptions::None, &mut outline_builder)?;
+                return Ok(outline_builder.build());
+            }
+        };
+        let mut outline_builder = OutlinePathBuilder::new(&Transform2F::default());
+        face.outline(glyph_id, &mut outline_builder);
+        Ok(outline_builder.build())
+    }
+
+    /// Dilates an outline in font units for synthetic bold.
+    fn embolden(&self, outline: &mut Outline) {
+        let dilation = self.metrics.units_per_em as f32 * SYNTHETIC_BOLD_DILATION;
+        outline.dilate(vec2f(dilation, dilation));
     }
+
+    fn color_layers(&self, glyph_id: GlyphId, foreground: ColorU) -> Option<Vec<ColorLayer>> {
+        self.color_tables.as_ref()?.layers(glyph_id.0, foreground)
+    }
+
+    fn bitmap(&mut self, glyph_id: GlyphId) -> Option<BitmapGlyph> {
+        let color_tables = self.color_tables.as_ref()?;
+        self.bitmap_cache
+            .entry(glyph_id)
+            .or_insert_with(|| color_tables.bitmap(glyph_id.0))
+            .clone()
+    }
+}
+
+// Slants font units to the right for synthetic oblique. The y axis points up in font units.
+fn synthetic_oblique_transform(synthetic_style: SyntheticStyle) -> Transform2F {
+    if !synthetic_style.oblique {
+        return Transform2F::default();
+    }
+    Transform2F::row_major(1.0, SYNTHETIC_OBLIQUE_ANGLE.tan(), 0.0, 0.0, 1.0, 0.0)
+}
+
+fn push_draw_path(scene: &mut Scene,
+                  outline: Outline,
+                  paint_id: PaintId,
+                  render_options: &FontRenderOptions) {
+    let mut path = DrawPath::new(outline, paint_id);
+    path.set_clip_path(render_options.clip_path);
+    path.set_blend_mode(render_options.blend_mode);
+    scene.push_draw_path(path);
 }
 
 impl<'a, F> FontInfoRefMut<'a, F> where F: Loader {
@@ -285,3 +507,42 @@ impl OutlineSink for OutlinePathBuilder {
         self.current_contour.close();
     }
 }
+
+#[cfg(test)]
+mod test {
+    use font_kit::loaders::default::Font as DefaultLoader;
+    use pathfinder_content::outline::Outline;
+    use pathfinder_geometry::rect::RectF;
+    use pathfinder_geometry::vector::{Vector2F, vec2f};
+    use std::sync::Arc;
+
+    use super::{FontInfo, SYNTHETIC_OBLIQUE_ANGLE, SyntheticStyle, synthetic_oblique_transform};
+
+    static ROBOTO: &[u8] = include_bytes!("../../resources/fonts/Roboto-Regular.ttf");
+
+    #[test]
+    fn synthetic_oblique_slants_to_the_right_about_the_baseline() {
+        let transform = synthetic_oblique_transform(SyntheticStyle::default());
+        assert!(transform.is_identity());
+
+        let transform = synthetic_oblique_transform(SyntheticStyle { bold: true, oblique: true });
+        assert_eq!(transform * vec2f(500.0, 0.0), vec2f(500.0, 0.0));
+        let top = transform * vec2f(0.0, 1000.0);
+        assert!((top.x() - 1000.0 * SYNTHETIC_OBLIQUE_ANGLE.tan()).abs() < 0.01);
+        assert_eq!(top.y(), 1000.0);
+        // The default angle of CSS `oblique` is 14 degrees.
+        assert!((top.x() - 249.33).abs() < 0.01);
+    }
+
+    #[test]
+    fn synthetic_bold_dilates_each_side_by_a_forty_eighth_of_an_em() {
+        let font = DefaultLoader::from_bytes(Arc::new(ROBOTO.to_vec()), 0).unwrap();
+        let font_info = FontInfo::new(font);
+        let dilation = font_info.metrics.units_per_em as f32 / 48.0;
+
+        let rect = RectF::new(Vector2F::zero(), vec2f(1000.0, 1000.0));
+        let mut outline = Outline::from_rect(rect);
+        font_info.embolden(&mut outline);
+        assert_eq!(outline.bounds(), rect.dilate(dilation));
+    }
+}
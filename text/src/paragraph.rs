@@ -0,0 +1,729 @@
+// pathfinder/text/src/paragraph.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Multi-line layout of text with mixed styles.
+
+use font_kit::loaders::default::Font as DefaultLoader;
+use pathfinder_geometry::line_segment::LineSegment2F;
+use pathfinder_geometry::vector::{Vector2F, vec2f};
+use pathfinder_renderer::paint::PaintId;
+use skribo::{FontCollection, TextStyle};
+use std::ops::Range;
+use std::sync::Arc;
+use xi_unicode::LineBreakIterator;
+
+/// Text made up of spans with different styles, to be laid out in one or more lines.
+#[derive(Clone, Default)]
+pub struct Paragraph {
+    text: String,
+    spans: Vec<TextSpan>,
+}
+
+/// The style of a span of text in a paragraph.
+#[derive(Clone)]
+pub struct SpanStyle {
+    pub font_collection: Arc<FontCollection>,
+    /// The font size in pixels.
+    pub size: f32,
+    pub paint_id: PaintId,
+}
+
+#[derive(Clone)]
+struct TextSpan {
+    range: Range<usize>,
+    style: SpanStyle,
+}
+
+/// How lines are positioned horizontally within the paragraph.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ParagraphAlign {
+    Left,
+    Right,
+    Center,
+    /// Stretches the spaces in each line to fill the width. The last line, and lines ended by a
+    /// line break, are left-aligned.
+    Justify,
+}
+
+/// The height of each line of a paragraph.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum LineHeight {
+    /// The ascent, descent, and line gap of the tallest font on the line.
+    Normal,
+    /// A multiple of the largest font size on the line.
+    Relative(f32),
+    /// A fixed height in pixels.
+    Absolute(f32),
+}
+
+/// Options that control how a paragraph is broken into lines.
+#[derive(Clone, PartialEq, Debug)]
+pub struct ParagraphStyle {
+    /// The width to wrap lines to. If `None`, lines are only broken at line breaks in the text.
+    pub max_width: Option<f32>,
+    pub align: ParagraphAlign,
+    pub line_height: LineHeight,
+    /// The maximum number of lines. Any further text is dropped.
+    pub max_lines: Option<usize>,
+    /// Text, such as `"…"`, that is drawn at the end of the last line if text was dropped. Glyphs
+    /// are removed from the end of the line as necessary to make room for it.
+    pub ellipsis: Option<String>,
+}
+
+/// A laid-out paragraph, which can be drawn with `FontContext::push_paragraph()`.
+///
+/// Positions are relative to the top left corner of the paragraph, with y pointing down.
+#[derive(Clone)]
+pub struct ParagraphLayout {
+    lines: Vec<LineMetrics>,
+    glyphs: Vec<PositionedGlyph>,
+    size: Vector2F,
+}
+
+/// The metrics of one line of a laid-out paragraph.
+#[derive(Clone, Debug)]
+pub struct LineMetrics {
+    /// The byte range of the text on this line, including any trailing whitespace and line break.
+    pub text_range: Range<usize>,
+    /// The y position of the top of the line box.
+    pub top: f32,
+    /// The height of the line box.
+    pub height: f32,
+    /// The y position of the baseline.
+    pub baseline: f32,
+    /// The distance from the baseline to the top of the tallest font on the line.
+    pub ascent: f32,
+    /// The distance from the baseline to the bottom of the lowest font on the line. This is
+    /// negative.
+    pub descent: f32,
+    /// The x position of the start of the line, after alignment.
+    pub x: f32,
+    /// The width of the line, not including trailing whitespace.
+    pub width: f32,
+    // The text offset and x position of each caret position on the line, in order.
+    carets: Vec<(usize, f32)>,
+}
+
+#[derive(Clone)]
+pub(crate) struct PositionedGlyph {
+    pub(crate) font: Arc<DefaultLoader>,
+    pub(crate) glyph_id: u32,
+    pub(crate) offset: Vector2F,
+    pub(crate) size: f32,
+    pub(crate) paint_id: PaintId,
+}
+
+// A character and the glyphs it was shaped into.
+struct Cluster {
+    range: Range<usize>,
+    style: SpanStyle,
+    // The font, glyph ID, and horizontal offset of each glyph.
+    glyphs: Vec<(Arc<DefaultLoader>, u32, f32)>,
+    advance: f32,
+    ascent: f32,
+    descent: f32,
+    line_gap: f32,
+    whitespace: bool,
+    line_break: bool,
+}
+
+// A range of clusters that make up a line.
+struct LineClusters {
+    range: Range<usize>,
+    // Whether the line was ended by a line break in the text, rather than by wrapping.
+    hard_break: bool,
+}
+
+impl Paragraph {
+    #[inline]
+    pub fn new() -> Paragraph {
+        Paragraph::default()
+    }
+
+    /// Appends text in the given style.
+    pub fn push_span(&mut self, text: &str, style: SpanStyle) {
+        let start = self.text.len();
+        self.text.push_str(text);
+        self.spans.push(TextSpan { range: start..self.text.len(), style });
+    }
+
+    /// Returns all the text in the paragraph. Text offsets in layouts are byte offsets into this.
+    #[inline]
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Breaks the paragraph into lines and positions its glyphs.
+    ///
+    /// Lines are broken at the opportunities given by the Unicode line breaking algorithm. A word
+    /// too long to fit on a line by itself is broken between characters.
+    pub fn layout(&self, style: &ParagraphStyle) -> ParagraphLayout {
+        let mut clusters = vec![];
+        for span in &self.spans {
+            for (offset, character) in self.text[span.range.clone()].char_indices() {
+                let start = span.range.start + offset;
+                clusters.push(Cluster::new(&self.text, start..(start + character.len_utf8()),
+                                           &span.style));
+            }
+        }
+
+        let mut lines = break_lines(&self.text, &clusters, style.max_width);
+
+        // Drop lines past the maximum, and make room for the ellipsis on the new last line.
+        let mut ellipsis = vec![];
+        if let Some(max_lines) = style.max_lines {
+            if lines.len() > max_lines {
+                lines.truncate(max_lines);
+                if let (Some(ellipsis_text), Some(last_line)) = (&style.ellipsis,
+                                                                  lines.last_mut()) {
+                    if last_line.range.end > 0 {
+                        let span_style = &clusters[last_line.range.end - 1].style;
+                        ellipsis = Cluster::shape(ellipsis_text, span_style);
+                    }
+                    let ellipsis_width: f32 = ellipsis.iter().map(|cluster| cluster.advance).sum();
+                    make_room_for_ellipsis(last_line,
+                                           &clusters,
+                                           ellipsis_width,
+                                           style.max_width.unwrap_or(f32::INFINITY));
+                }
+            }
+        }
+
+        ParagraphLayout::new(&self.text, &clusters, &lines, &ellipsis, style)
+    }
+}
+
+impl Default for ParagraphStyle {
+    #[inline]
+    fn default() -> ParagraphStyle {
+        ParagraphStyle {
+            max_width: None,
+            align: ParagraphAlign::Left,
+            line_height: LineHeight::Normal,
+            max_lines: None,
+            ellipsis: None,
+        }
+    }
+}
+
+impl ParagraphLayout {
+    fn new(text: &str,
+           clusters: &[Cluster],
+           lines: &[LineClusters],
+           ellipsis: &[Cluster],
+           style: &ParagraphStyle)
+           -> ParagraphLayout {
+        let line_clusters = |line_index: usize| {
+            let line: &LineClusters = &lines[line_index];
+            let ellipsis = if line_index + 1 == lines.len() { ellipsis } else { &ellipsis[0..0] };
+            clusters[line.range.clone()].iter().chain(ellipsis.iter())
+        };
+        let line_width = |line_index: usize| {
+            let line_clusters: Vec<&Cluster> = line_clusters(line_index).collect();
+            let visible_count = line_clusters.iter()
+                                             .rposition(|cluster| !cluster.whitespace)
+                                             .map_or(0, |index| index + 1);
+            line_clusters[0..visible_count].iter().map(|cluster| cluster.advance).sum::<f32>()
+        };
+        // Where a line with no clusters starts and ends in the text.
+        let empty_line_offset = |line: &LineClusters| {
+            clusters.get(line.range.start).map_or(text.len(), |cluster| cluster.range.start)
+        };
+
+        let paragraph_width = match style.max_width {
+            Some(max_width) => max_width,
+            None => (0..lines.len()).map(&line_width).fold(0.0, f32::max),
+        };
+
+        let mut layout = ParagraphLayout {
+            lines: vec![],
+            glyphs: vec![],
+            size: Vector2F::zero(),
+        };
+        let mut top = 0.0;
+        for (line_index, line) in lines.iter().enumerate() {
+            // An empty line after a trailing line break takes its metrics from the line break.
+            let metrics_clusters: Vec<&Cluster> = if line.range.is_empty() {
+                clusters[0..line.range.start].last().into_iter().collect()
+            } else {
+                line_clusters(line_index).collect()
+            };
+            let (mut ascent, mut descent, mut line_gap, mut font_size) = (0.0, 0.0, 0.0, 0.0);
+            for cluster in &metrics_clusters {
+                ascent = f32::max(ascent, cluster.ascent);
+                descent = f32::min(descent, cluster.descent);
+                line_gap = f32::max(line_gap, cluster.line_gap);
+                font_size = f32::max(font_size, cluster.style.size);
+            }
+            let height = match style.line_height {
+                LineHeight::Normal => ascent - descent + line_gap,
+                LineHeight::Relative(scale) => font_size * scale,
+                LineHeight::Absolute(height) => height,
+            };
+            // Leading is split evenly above and below the text.
+            let baseline = top + (height - (ascent - descent)) * 0.5 + ascent;
+
+            let width = line_width(line_index);
+            let is_last_line = line_index + 1 == lines.len();
+            let (x, justification_space) = match style.align {
+                ParagraphAlign::Left => (0.0, 0.0),
+                ParagraphAlign::Right => (paragraph_width - width, 0.0),
+                ParagraphAlign::Center => ((paragraph_width - width) * 0.5, 0.0),
+                ParagraphAlign::Justify if line.hard_break || is_last_line => (0.0, 0.0),
+                ParagraphAlign::Justify => {
+                    let line_clusters = &clusters[line.range.clone()];
+                    let space_count = line_clusters[0..visible_len(line_clusters)]
+                        .iter()
+                        .filter(|cluster| cluster.whitespace)
+                        .count();
+                    if space_count == 0 {
+                        (0.0, 0.0)
+                    } else {
+                        (0.0, f32::max(paragraph_width - width, 0.0) / space_count as f32)
+                    }
+                }
+            };
+
+            let mut carets = vec![];
+            let mut pen = x;
+            for cluster in &clusters[line.range.clone()] {
+                if cluster.line_break {
+                    break;
+                }
+                carets.push((cluster.range.start, pen));
+                layout.push_glyphs(cluster, pen, baseline);
+                pen += cluster.advance;
+                if cluster.whitespace {
+                    pen += justification_space;
+                }
+            }
+
+            // The caret after the last character, before any line break.
+            let text_end = match clusters[line.range.clone()].iter().find(|cluster| {
+                cluster.line_break
+            }) {
+                Some(line_break) => line_break.range.start,
+                None if line.range.is_empty() => empty_line_offset(line),
+                None => clusters[line.range.end - 1].range.end,
+            };
+            carets.push((text_end, pen));
+
+            if is_last_line {
+                for cluster in ellipsis {
+                    layout.push_glyphs(cluster, pen, baseline);
+                    pen += cluster.advance;
+                }
+            }
+
+            let text_range = if line.range.is_empty() {
+                text_end..text_end
+            } else {
+                clusters[line.range.start].range.start..clusters[line.range.end - 1].range.end
+            };
+            layout.lines.push(LineMetrics {
+                text_range,
+                top,
+                height,
+                baseline,
+                ascent,
+                descent,
+                x,
+                width,
+                carets,
+            });
+            top += height;
+        }
+
+        layout.size = vec2f(paragraph_width, top);
+        layout
+    }
+
+    fn push_glyphs(&mut self, cluster: &Cluster, x: f32, baseline: f32) {
+        for &(ref font, glyph_id, glyph_x) in &cluster.glyphs {
+            self.glyphs.push(PositionedGlyph {
+                font: font.clone(),
+                glyph_id,
+                offset: vec2f(x + glyph_x, baseline),
+                size: cluster.style.size,
+                paint_id: cluster.style.paint_id,
+            });
+        }
+    }
+
+    /// Returns the lines of the paragraph, from top to bottom.
+    #[inline]
+    pub fn lines(&self) -> &[LineMetrics] {
+        &self.lines
+    }
+
+    /// Returns the width and height of the paragraph. The width is the maximum width if one was
+    /// given, and otherwise the width of the longest line.
+    #[inline]
+    pub fn size(&self) -> Vector2F {
+        self.size
+    }
+
+    #[inline]
+    pub(crate) fn glyphs(&self) -> &[PositionedGlyph] {
+        &self.glyphs
+    }
+
+    /// Returns the text offset of the caret position closest to the given point.
+    pub fn hit_test(&self, point: Vector2F) -> usize {
+        let line = match self.lines.iter().find(|line| point.y() < line.top + line.height) {
+            Some(line) => line,
+            None => match self.lines.last() {
+                Some(line) => line,
+                None => return 0,
+            },
+        };
+        for carets in line.carets.windows(2) {
+            if point.x() < (carets[0].1 + carets[1].1) * 0.5 {
+                return carets[0].0;
+            }
+        }
+        line.carets.last().unwrap().0
+    }
+
+    /// Returns a vertical line spanning the line box at the caret position for the given text
+    /// offset, or `None` if the paragraph is empty.
+    ///
+    /// An offset at which a line was wrapped is placed at the start of the following line.
+    pub fn caret_position(&self, text_offset: usize) -> Option<LineSegment2F> {
+        let line = self.lines.iter().find(|line| text_offset < line.text_range.end)
+                                    .or_else(|| self.lines.last())?;
+        let x = line.carets
+                    .iter()
+                    .take_while(|&&(caret_offset, _)| caret_offset <= text_offset)
+                    .last()
+                    .unwrap_or(&line.carets[0])
+                    .1;
+        Some(LineSegment2F::new(vec2f(x, line.top), vec2f(x, line.top + line.height)))
+    }
+}
+
+impl LineMetrics {
+    /// Returns the text offset and x position of each caret position on the line, from left to
+    /// right. The last one is at the end of the line, before any line break.
+    #[inline]
+    pub fn caret_positions(&self) -> &[(usize, f32)] {
+        &self.carets
+    }
+}
+
+impl Cluster {
+    fn new(text: &str, range: Range<usize>, style: &SpanStyle) -> Cluster {
+        let string = &text[range.clone()];
+        let character = string.chars().next().unwrap();
+        let layout = skribo::layout(&TextStyle { size: style.size },
+                                    &style.font_collection,
+                                    string);
+
+        let mut cluster = Cluster {
+            range,
+            style: (*style).clone(),
+            glyphs: vec![],
+            advance: 0.0,
+            ascent: 0.0,
+            descent: 0.0,
+            line_gap: 0.0,
+            whitespace: character.is_whitespace(),
+            line_break: is_line_break(character),
+        };
+
+        for glyph in &layout.glyphs {
+            let font = &glyph.font.font;
+            let metrics = font.metrics();
+            let scale_factor = style.size / metrics.units_per_em as f32;
+            cluster.ascent = f32::max(cluster.ascent, metrics.ascent * scale_factor);
+            cluster.descent = f32::min(cluster.descent, metrics.descent * scale_factor);
+            cluster.line_gap = f32::max(cluster.line_gap, metrics.line_gap * scale_factor);
+            if cluster.line_break {
+                continue;
+            }
+
+            cluster.glyphs.push((font.clone(), glyph.glyph_id, glyph.offset.x()));
+            let advance = font.advance(glyph.glyph_id).map(|advance| advance.x()).unwrap_or(0.0);
+            cluster.advance = f32::max(cluster.advance, glyph.offset.x() + advance * scale_factor);
+        }
+        cluster
+    }
+
+    // Shapes text that isn't part of the paragraph, such as an ellipsis.
+    fn shape(text: &str, style: &SpanStyle) -> Vec<Cluster> {
+        text.char_indices().map(|(offset, character)| {
+            Cluster::new(text, offset..(offset + character.len_utf8()), style)
+        }).collect()
+    }
+}
+
+// Breaks clusters into lines at Unicode line break opportunities, wrapping to the maximum width
+// if there is one.
+fn break_lines(text: &str, clusters: &[Cluster], max_width: Option<f32>) -> Vec<LineClusters> {
+    let mut lines = vec![];
+    if clusters.is_empty() {
+        return lines;
+    }
+
+    let (mut line_start, mut line_end) = (0, 0);
+    for (break_offset, hard_break) in LineBreakIterator::new(text) {
+        let mut segment_end = line_end;
+        while segment_end < clusters.len() && clusters[segment_end].range.start < break_offset {
+            segment_end += 1;
+        }
+        if segment_end == line_end {
+            continue;
+        }
+
+        if let Some(max_width) = max_width {
+            let line_width = visible_width(&clusters[line_start..segment_end]);
+            if line_width > max_width && line_end > line_start {
+                // The segment doesn't fit, so move it to the next line.
+                lines.push(LineClusters { range: line_start..line_end, hard_break: false });
+                line_start = line_end;
+            }
+
+            if visible_width(&clusters[line_start..segment_end]) > max_width {
+                // The segment doesn't fit on a line by itself either, so break it between
+                // characters.
+                let mut x = 0.0;
+                for index in line_start..segment_end {
+                    let cluster = &clusters[index];
+                    let overflows = x + cluster.advance > max_width;
+                    if index > line_start && !cluster.whitespace && overflows {
+                        lines.push(LineClusters { range: line_start..index, hard_break: false });
+                        line_start = index;
+                        x = 0.0;
+                    }
+                    x += cluster.advance;
+                }
+            }
+        }
+
+        line_end = segment_end;
+        if hard_break && clusters[line_end - 1].line_break {
+            lines.push(LineClusters { range: line_start..line_end, hard_break: true });
+            line_start = line_end;
+        }
+    }
+
+    // Text that ends with a line break gets an empty last line, where a caret after the line
+    // break goes.
+    if line_start < clusters.len() || clusters[clusters.len() - 1].line_break {
+        lines.push(LineClusters { range: line_start..clusters.len(), hard_break: false });
+    }
+    lines
+}
+
+// Removes clusters from the end of the line until the ellipsis fits after it.
+fn make_room_for_ellipsis(line: &mut LineClusters,
+                          clusters: &[Cluster],
+                          ellipsis_width: f32,
+                          max_width: f32) {
+    while line.range.end > line.range.start &&
+            visible_width(&clusters[line.range.clone()]) + ellipsis_width > max_width {
+        line.range.end -= 1;
+    }
+    // The ellipsis goes directly after the last visible character.
+    while line.range.end > line.range.start && clusters[line.range.end - 1].whitespace {
+        line.range.end -= 1;
+    }
+    line.hard_break = false;
+}
+
+// The number of clusters, not counting trailing whitespace.
+fn visible_len(clusters: &[Cluster]) -> usize {
+    clusters.iter().rposition(|cluster| !cluster.whitespace).map_or(0, |index| index + 1)
+}
+
+// The width of the clusters, not counting trailing whitespace.
+fn visible_width(clusters: &[Cluster]) -> f32 {
+    clusters[0..visible_len(clusters)].iter().map(|cluster| cluster.advance).sum()
+}
+
+fn is_line_break(character: char) -> bool {
+    match character {
+        '\n' | '\r' | '\u{b}' | '\u{c}' | '\u{85}' | '\u{2028}' | '\u{2029}' => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use pathfinder_geometry::vector::vec2f;
+    use pathfinder_renderer::paint::PaintId;
+    use skribo::FontCollection;
+    use std::sync::Arc;
+
+    use super::{Cluster, LineClusters, ParagraphAlign, ParagraphLayout, ParagraphStyle};
+    use super::{SpanStyle, break_lines, is_line_break, make_room_for_ellipsis};
+
+    // Every character is 10 pixels wide, and every line is 10 pixels tall with its baseline 8
+    // pixels from the top, so that layouts don't depend on the fonts installed.
+    fn clusters(text: &str) -> Vec<Cluster> {
+        let style = SpanStyle {
+            font_collection: Arc::new(FontCollection::new()),
+            size: 10.0,
+            paint_id: PaintId(0),
+        };
+        text.char_indices().map(|(offset, character)| {
+            let line_break = is_line_break(character);
+            Cluster {
+                range: offset..(offset + character.len_utf8()),
+                style: style.clone(),
+                glyphs: vec![],
+                advance: if line_break { 0.0 } else { 10.0 },
+                ascent: 8.0,
+                descent: -2.0,
+                line_gap: 0.0,
+                whitespace: character.is_whitespace(),
+                line_break,
+            }
+        }).collect()
+    }
+
+    fn layout(text: &str, style: &ParagraphStyle) -> ParagraphLayout {
+        let clusters = clusters(text);
+        let lines = break_lines(text, &clusters, style.max_width);
+        ParagraphLayout::new(text, &clusters, &lines, &[], style)
+    }
+
+    fn layout_with_trailing_break() -> ParagraphLayout {
+        layout("a\n", &ParagraphStyle::default())
+    }
+
+    fn wrapped_style(max_width: f32, align: ParagraphAlign) -> ParagraphStyle {
+        ParagraphStyle { max_width: Some(max_width), align, ..ParagraphStyle::default() }
+    }
+
+    fn text_ranges(layout: &ParagraphLayout) -> Vec<(usize, usize)> {
+        layout.lines().iter().map(|line| (line.text_range.start, line.text_range.end)).collect()
+    }
+
+    #[test]
+    fn lines_wrap_at_word_boundaries() {
+        let layout = layout("aaa bbb ccc", &wrapped_style(75.0, ParagraphAlign::Left));
+        assert_eq!(text_ranges(&layout), vec![(0, 8), (8, 11)]);
+        // Trailing whitespace doesn't count toward the width of a line.
+        let widths: Vec<f32> = layout.lines().iter().map(|line| line.width).collect();
+        assert_eq!(widths, vec![70.0, 30.0]);
+        assert_eq!(layout.lines()[1].top, 10.0);
+        assert_eq!(layout.lines()[1].baseline, 18.0);
+        assert_eq!(layout.size(), vec2f(75.0, 20.0));
+    }
+
+    #[test]
+    fn long_words_are_broken_between_characters() {
+        let layout = layout("aaaaaaaaaa", &wrapped_style(35.0, ParagraphAlign::Left));
+        assert_eq!(text_ranges(&layout), vec![(0, 3), (3, 6), (6, 9), (9, 10)]);
+    }
+
+    #[test]
+    fn line_breaks_end_lines() {
+        let layout = layout("a\nb", &ParagraphStyle::default());
+        assert_eq!(text_ranges(&layout), vec![(0, 2), (2, 3)]);
+
+        // A trailing line break gets an empty line after it, with the height of the line break.
+        let layout = layout_with_trailing_break();
+        assert_eq!(text_ranges(&layout), vec![(0, 2), (2, 2)]);
+        assert_eq!(layout.lines()[1].height, 10.0);
+    }
+
+    #[test]
+    fn lines_are_aligned_within_the_maximum_width() {
+        let line_xs = |align| -> Vec<f32> {
+            let layout = layout("aaa bbb ccc", &wrapped_style(100.0, align));
+            layout.lines().iter().map(|line| line.x).collect()
+        };
+        assert_eq!(line_xs(ParagraphAlign::Left), vec![0.0, 0.0]);
+        assert_eq!(line_xs(ParagraphAlign::Right), vec![30.0, 70.0]);
+        assert_eq!(line_xs(ParagraphAlign::Center), vec![15.0, 35.0]);
+        assert_eq!(line_xs(ParagraphAlign::Justify), vec![0.0, 0.0]);
+    }
+
+    #[test]
+    fn justified_lines_stretch_their_spaces() {
+        let layout = layout("aaa bbb ccc", &wrapped_style(100.0, ParagraphAlign::Justify));
+        // The one space on the first line takes up all of the remaining 30 pixels.
+        let carets = layout.lines()[0].caret_positions();
+        assert_eq!(carets[3], (3, 30.0));
+        assert_eq!(carets[4], (4, 70.0));
+        // The last line isn't justified.
+        assert_eq!(layout.lines()[1].caret_positions()[1], (9, 10.0));
+    }
+
+    #[test]
+    fn ellipsis_replaces_characters_that_no_longer_fit() {
+        let text = "aaa bbb ccc";
+        let style = ParagraphStyle {
+            max_lines: Some(1),
+            ellipsis: Some("…".to_owned()),
+            ..wrapped_style(75.0, ParagraphAlign::Left)
+        };
+        let clusters = clusters(text);
+        let mut lines = break_lines(text, &clusters, style.max_width);
+        lines.truncate(1);
+        make_room_for_ellipsis(&mut lines[0], &clusters, 10.0, 75.0);
+        assert_eq!(lines[0].range, 0..6);
+        assert!(!lines[0].hard_break);
+
+        let ellipsis = self::clusters("…");
+        let layout = ParagraphLayout::new(text, &clusters, &lines, &ellipsis, &style);
+        assert_eq!(text_ranges(&layout), vec![(0, 6)]);
+        assert_eq!(layout.lines()[0].width, 70.0);
+    }
+
+    #[test]
+    fn ellipsis_follows_the_last_visible_character() {
+        let clusters = clusters("aaa bbb");
+        let mut line = LineClusters { range: 0..4, hard_break: true };
+        make_room_for_ellipsis(&mut line, &clusters, 10.0, 45.0);
+        assert_eq!(line.range, 0..3);
+        assert!(!line.hard_break);
+    }
+
+    #[test]
+    fn hit_test_and_caret_position_in_an_empty_paragraph() {
+        let layout = layout("", &ParagraphStyle::default());
+        assert!(layout.lines().is_empty());
+        assert_eq!(layout.hit_test(vec2f(10.0, 10.0)), 0);
+        assert!(layout.caret_position(0).is_none());
+    }
+
+    #[test]
+    fn hit_test_finds_the_closest_caret_on_wrapped_lines() {
+        let layout = layout("aaa bbb ccc", &wrapped_style(75.0, ParagraphAlign::Left));
+        assert_eq!(layout.hit_test(vec2f(12.0, 5.0)), 1);
+        assert_eq!(layout.hit_test(vec2f(16.0, 5.0)), 2);
+        // Points to the right of a line go to the end of it.
+        assert_eq!(layout.hit_test(vec2f(100.0, 15.0)), 11);
+        // Points below the paragraph go to the last line.
+        assert_eq!(layout.hit_test(vec2f(-5.0, 100.0)), 8);
+    }
+
+    #[test]
+    fn caret_position_at_a_wrap_is_on_the_following_line() {
+        let layout = layout("aaa bbb ccc", &wrapped_style(75.0, ParagraphAlign::Left));
+        let caret = layout.caret_position(8).unwrap();
+        assert_eq!((caret.from(), caret.to()), (vec2f(0.0, 10.0), vec2f(0.0, 20.0)));
+        let caret = layout.caret_position(7).unwrap();
+        assert_eq!(caret.from(), vec2f(70.0, 0.0));
+        let caret = layout.caret_position(11).unwrap();
+        assert_eq!(caret.from(), vec2f(30.0, 10.0));
+    }
+
+    #[test]
+    fn carets_after_a_trailing_line_break_are_on_the_empty_line() {
+        let layout = layout_with_trailing_break();
+        assert_eq!(layout.caret_position(2).unwrap().from(), vec2f(0.0, 10.0));
+        assert_eq!(layout.hit_test(vec2f(50.0, 15.0)), 2);
+        assert_eq!(layout.hit_test(vec2f(50.0, 5.0)), 1);
+    }
+}
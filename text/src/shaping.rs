@@ -0,0 +1,310 @@
+// pathfinder/text/src/shaping.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Complex text shaping with `rustybuzz` and bidirectional reordering with `unicode-bidi`.
+
+use crate::COLLECTION_FONT_INDEX;
+use crate::variations::FontVariation;
+use font_kit::loaders::default::Font as DefaultLoader;
+use pathfinder_geometry::vector::vec2f;
+use rustybuzz::{Direction, Face, Tag, UnicodeBuffer, Variation};
+use skribo::{FontCollection, FontRef, Glyph, Layout, TextStyle};
+use std::ops::Range;
+use std::sync::Arc;
+use unicode_bidi::BidiInfo;
+use unicode_script::{Script, UnicodeScript};
+
+/// Lays out a single line of text with complex shaping.
+///
+/// The text is split into paragraphs and reordered with the Unicode bidirectional algorithm. Each
+/// directional run is then split into runs of a single script and font, and each of those is
+/// shaped separately. Fonts are chosen from the collection the same way `skribo::layout()` does,
+/// except that combining marks and punctuation stay in the font of the preceding text if it
+/// supports them.
+///
+/// The result can be passed to `FontContext::push_layout()` like any other layout.
+#[inline]
+pub fn shape(style: &TextStyle, collection: &FontCollection, text: &str) -> Layout {
+    shape_with_variations(style, collection, text, &[])
+}
+
+/// Lays out a single line of text with complex shaping, with the given variation axis values
+/// applied to variable fonts.
+pub fn shape_with_variations(style: &TextStyle,
+                             collection: &FontCollection,
+                             text: &str,
+                             variations: &[FontVariation])
+                             -> Layout {
+    let mut layout = skribo::layout(style, collection, "");
+    let mut font_data = FontDataCache::new();
+    let mut x = 0.0;
+
+    let bidi_info = BidiInfo::new(text, None);
+    for paragraph in &bidi_info.paragraphs {
+        let (levels, visual_runs) = bidi_info.visual_runs(paragraph, paragraph.range.clone());
+        for visual_run in visual_runs {
+            let right_to_left = levels[visual_run.start].is_rtl();
+            let mut items = itemize(style, collection, text, visual_run);
+            // Items are in logical order, so right-to-left runs are laid out from their end.
+            if right_to_left {
+                items.reverse();
+            }
+            for item in items {
+                x = shape_item(&mut layout,
+                               &mut font_data,
+                               style,
+                               text,
+                               &item,
+                               right_to_left,
+                               variations,
+                               x);
+            }
+        }
+    }
+
+    layout
+}
+
+// A run of text in a single script and font.
+struct Item {
+    range: Range<usize>,
+    script: Script,
+    font: FontRef,
+}
+
+// Keeps the font data for shaping around for the duration of a layout, since font-kit copies it
+// each time it's requested.
+struct FontDataCache {
+    entries: Vec<(Arc<DefaultLoader>, Option<Arc<Vec<u8>>>)>,
+}
+
+impl FontDataCache {
+    fn new() -> FontDataCache {
+        FontDataCache { entries: vec![] }
+    }
+
+    fn get(&mut self, font: &Arc<DefaultLoader>) -> Option<Arc<Vec<u8>>> {
+        if let Some(&(_, ref data)) = self.entries.iter().find(|&&(ref entry_font, _)| {
+            Arc::ptr_eq(entry_font, font)
+        }) {
+            return data.clone();
+        }
+        let data = font.copy_font_data();
+        self.entries.push((font.clone(), data.clone()));
+        data
+    }
+}
+
+// Splits a directional run into items with a single script and font each.
+fn itemize(style: &TextStyle, collection: &FontCollection, text: &str, run: Range<usize>)
+           -> Vec<Item> {
+    let scripts = resolve_scripts(&text[run.clone()]);
+
+    let mut items: Vec<Item> = vec![];
+    for ((offset, character), script) in text[run.clone()].char_indices().zip(scripts) {
+        let start = run.start + offset;
+        let end = start + character.len_utf8();
+
+        // Characters used by any script stay in the current font if it has them.
+        if let Some(item) = items.last_mut() {
+            let neutral = match character.script() {
+                Script::Common | Script::Inherited => true,
+                _ => false,
+            };
+            if item.script == script && neutral &&
+                    item.font.font.glyph_for_char(character).is_some() {
+                item.range.end = end;
+                continue;
+            }
+        }
+
+        let font = match font_for_char(style, collection, character) {
+            Some(font) => font,
+            None => {
+                // Without any font, the character goes with the text before it.
+                if let Some(item) = items.last_mut() {
+                    item.range.end = end;
+                }
+                continue;
+            }
+        };
+        let extends_item = items.last().map_or(false, |item| {
+            item.script == script && Arc::ptr_eq(&item.font.font, &font.font)
+        });
+        if extends_item {
+            items.last_mut().unwrap().range.end = end;
+        } else {
+            items.push(Item { range: start..end, script, font });
+        }
+    }
+    items
+}
+
+// Returns the script of each character of the text. Characters used by many scripts, like spaces
+// and combining marks, take the script of the text around them.
+fn resolve_scripts(text: &str) -> Vec<Script> {
+    let mut scripts: Vec<Script> = text.chars().map(|character| character.script()).collect();
+    let is_neutral = |script: Script| {
+        script == Script::Common || script == Script::Inherited || script == Script::Unknown
+    };
+
+    let mut last_script = None;
+    for script in &mut scripts {
+        if !is_neutral(*script) {
+            last_script = Some(*script);
+        } else if let Some(last_script) = last_script {
+            *script = last_script;
+        }
+    }
+
+    // Neutral characters at the start take the script of the first real one.
+    if let Some(first_script) = scripts.iter().cloned().find(|&script| !is_neutral(script)) {
+        for script in scripts.iter_mut().take_while(|script| is_neutral(**script)) {
+            *script = first_script;
+        }
+    }
+    scripts
+}
+
+// Picks the font for a character using the fallback order of the collection.
+fn font_for_char(style: &TextStyle, collection: &FontCollection, character: char)
+                 -> Option<FontRef> {
+    let mut buffer = [0; 4];
+    let layout = skribo::layout(style, collection, character.encode_utf8(&mut buffer));
+    layout.glyphs.into_iter().next().map(|glyph| glyph.font)
+}
+
+// Shapes an item and appends its glyphs to the layout starting at the given x position, returning
+// the x position after the item.
+#[allow(clippy::too_many_arguments)]
+fn shape_item(layout: &mut Layout,
+              font_data: &mut FontDataCache,
+              style: &TextStyle,
+              text: &str,
+              item: &Item,
+              right_to_left: bool,
+              variations: &[FontVariation],
+              mut x: f32)
+              -> f32 {
+    let data = match font_data.get(&item.font.font) {
+        Some(data) => data,
+        None => return x,
+    };
+    let mut face = match Face::from_slice(&data, COLLECTION_FONT_INDEX) {
+        Some(face) => face,
+        None => return x,
+    };
+    if !variations.is_empty() {
+        let variations: Vec<Variation> = variations.iter().map(|variation| {
+            Variation { tag: Tag::from_bytes(&variation.axis), value: variation.value }
+        }).collect();
+        face.set_variations(&variations);
+    }
+
+    let mut buffer = UnicodeBuffer::new();
+    buffer.push_str(&text[item.range.clone()]);
+    buffer.guess_segment_properties();
+    buffer.set_direction(if right_to_left {
+        Direction::RightToLeft
+    } else {
+        Direction::LeftToRight
+    });
+    let glyph_buffer = rustybuzz::shape(&face, &[], buffer);
+
+    let units_per_em = item.font.font.metrics().units_per_em as f32;
+    let scale_factor = style.size / units_per_em;
+    for (info, position) in glyph_buffer.glyph_infos().iter().zip(glyph_buffer.glyph_positions()) {
+        // Shaping positions have y pointing up.
+        let offset = vec2f(x + position.x_offset as f32 * scale_factor,
+                           -position.y_offset as f32 * scale_factor);
+        layout.glyphs.push(Glyph { font: item.font.clone(), glyph_id: info.glyph_id, offset });
+        x += position.x_advance as f32 * scale_factor;
+    }
+    x
+}
+
+#[cfg(test)]
+mod test {
+    use font_kit::loaders::default::Font as DefaultLoader;
+    use skribo::{FontCollection, FontFamily, Layout, TextStyle};
+    use std::sync::Arc;
+    use unicode_script::Script;
+
+    use super::{itemize, resolve_scripts, shape};
+
+    static ROBOTO: &[u8] = include_bytes!("../../resources/fonts/Roboto-Regular.ttf");
+    static NOTO_EMOJI: &[u8] = include_bytes!("../../resources/fonts/NotoEmoji-Regular.ttf");
+
+    const STYLE: TextStyle = TextStyle { size: 16.0 };
+
+    // Roboto, falling back to Noto Emoji, which has no Latin letters.
+    fn font_collection() -> FontCollection {
+        let mut font_collection = FontCollection::new();
+        for data in &[ROBOTO, NOTO_EMOJI] {
+            let font = DefaultLoader::from_bytes(Arc::new(data.to_vec()), 0).unwrap();
+            font_collection.add_family(FontFamily::new_from_font(font));
+        }
+        font_collection
+    }
+
+    // Returns which of the given characters each glyph of the layout is, in visual order, skipping
+    // glyphs for any other characters.
+    fn glyph_characters(layout: &Layout, characters: &[char]) -> Vec<char> {
+        layout.glyphs.iter().filter_map(|glyph| {
+            characters.iter().cloned().find(|&character| {
+                glyph.font.font.glyph_for_char(character) == Some(glyph.glyph_id)
+            })
+        }).collect()
+    }
+
+    #[test]
+    fn neutral_characters_take_the_script_around_them() {
+        assert_eq!(resolve_scripts("a, β"),
+                   vec![Script::Latin, Script::Latin, Script::Latin, Script::Greek]);
+        // Neutral characters at the start take the script of the first real one.
+        assert_eq!(resolve_scripts(" (β"), vec![Script::Greek, Script::Greek, Script::Greek]);
+        assert_eq!(resolve_scripts("12"), vec![Script::Common, Script::Common]);
+    }
+
+    #[test]
+    fn characters_missing_from_a_font_fall_back_to_the_next_one() {
+        let font_collection = font_collection();
+        let text = "ab, 😀c";
+        let items = itemize(&STYLE, &font_collection, text, 0..text.len());
+        // The punctuation and space stay with the letters before them.
+        let ranges: Vec<_> = items.iter().map(|item| item.range.clone()).collect();
+        assert_eq!(ranges, vec![0..4, 4..8, 8..9]);
+        assert!(items[0].font.font.glyph_for_char('a').is_some());
+        assert!(items[1].font.font.glyph_for_char('a').is_none());
+        assert!(items[1].font.font.glyph_for_char('😀').is_some());
+        // The letter after the emoji goes back to the first font.
+        assert!(Arc::ptr_eq(&items[0].font.font, &items[2].font.font));
+        assert!(items.iter().all(|item| item.script == Script::Latin));
+    }
+
+    #[test]
+    fn right_to_left_runs_are_reordered() {
+        let font_collection = font_collection();
+        // U+202E RIGHT-TO-LEFT OVERRIDE makes the text up to U+202C POP DIRECTIONAL FORMATTING
+        // right-to-left.
+        let layout = shape(&STYLE, &font_collection, "a\u{202e}bc\u{202c}d");
+        assert_eq!(glyph_characters(&layout, &['a', 'b', 'c', 'd']), vec!['a', 'c', 'b', 'd']);
+        let x_positions: Vec<f32> = layout.glyphs.iter().map(|glyph| glyph.offset.x()).collect();
+        assert!(x_positions.windows(2).all(|pair| pair[0] <= pair[1]));
+    }
+
+    #[test]
+    fn fallback_items_are_reordered_within_right_to_left_runs() {
+        let font_collection = font_collection();
+        let layout = shape(&STYLE, &font_collection, "\u{202e}ab😀c\u{202c}");
+        assert_eq!(glyph_characters(&layout, &['a', 'b', 'c', '😀']),
+                   vec!['c', '😀', 'b', 'a']);
+    }
+}
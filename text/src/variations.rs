@@ -0,0 +1,241 @@
+// pathfinder/text/src/variations.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Variable font support.
+//!
+//! `font-kit` always loads the default instance of a variable font, so outlines and advances for
+//! other axis values are read with `ttf-parser` instead, which applies `gvar`, `CFF2`, and `HVAR`
+//! variations.
+
+use crate::COLLECTION_FONT_INDEX;
+use font_kit::loader::Loader;
+use font_kit::loaders::default::Font as DefaultLoader;
+use font_kit::outline::OutlineSink;
+use pathfinder_geometry::line_segment::LineSegment2F;
+use pathfinder_geometry::vector::{Vector2F, vec2f};
+use ttf_parser::{Face, GlyphId, OutlineBuilder, Tag};
+
+/// The value of a variation axis of a variable font, like CSS `font-variation-settings`.
+///
+/// Common registered axes are `wght` (weight), `wdth` (width), `opsz` (optical size), `slnt`
+/// (slant), and `ital` (italic). Axes that the font doesn't have are ignored.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct FontVariation {
+    pub axis: [u8; 4],
+    pub value: f32,
+}
+
+impl FontVariation {
+    #[inline]
+    pub fn new(axis: &[u8; 4], value: f32) -> FontVariation {
+        FontVariation { axis: *axis, value }
+    }
+}
+
+/// A face of a variable font with variations applied.
+pub(crate) struct VariedFace<'a> {
+    face: Face<'a>,
+}
+
+impl<'a> VariedFace<'a> {
+    pub(crate) fn new(data: &'a [u8], variations: &[FontVariation]) -> Option<VariedFace<'a>> {
+        let mut face = Face::from_slice(data, COLLECTION_FONT_INDEX).ok()?;
+        for variation in variations {
+            // Axes that the font doesn't have are ignored.
+            face.set_variation(Tag::from_bytes(&variation.axis), variation.value);
+        }
+        Some(VariedFace { face })
+    }
+
+    /// Sends the outline of a glyph, in font units, to the sink. Returns false if the glyph has no
+    /// outline.
+    pub(crate) fn outline<S>(&self, glyph_id: u32, sink: &mut S) -> bool where S: OutlineSink {
+        let mut builder = OutlineSinkBuilder { sink };
+        self.face.outline_glyph(GlyphId(glyph_id as u16), &mut builder).is_some()
+    }
+
+    /// Returns the horizontal advance of a glyph in font units.
+    pub(crate) fn advance(&self, glyph_id: u32) -> Option<f32> {
+        self.face.glyph_hor_advance(GlyphId(glyph_id as u16)).map(|advance| advance as f32)
+    }
+}
+
+/// Returns the advance of a glyph in font units with the given variations applied.
+///
+/// Without variations, this is the same as `Loader::advance()`.
+pub fn glyph_advance(font: &DefaultLoader, glyph_id: u32, variations: &[FontVariation])
+                     -> Vector2F {
+    let default_advance = font.advance(glyph_id).unwrap_or(Vector2F::zero());
+    if variations.is_empty() {
+        return default_advance;
+    }
+    let data = match font.copy_font_data() {
+        Some(data) => data,
+        None => return default_advance,
+    };
+    match VariedFace::new(&data, variations).and_then(|face| face.advance(glyph_id)) {
+        Some(advance) => vec2f(advance, default_advance.y()),
+        None => default_advance,
+    }
+}
+
+/// Moves the glyphs of a layout made for the default instance of its fonts to account for the
+/// advances of the varied instances. Each glyph is shifted by the difference in the advances of
+/// the glyphs before it, so kerning and other adjustments are preserved.
+///
+/// The shaper applies variations itself, so this is only needed for skribo's simple layout.
+#[cfg(not(feature = "shaping"))]
+pub(crate) fn adjust_advances(layout: &mut skribo::Layout, variations: &[FontVariation]) {
+    use std::sync::Arc;
+
+    if variations.is_empty() {
+        return;
+    }
+
+    let mut font_data: Vec<(Arc<DefaultLoader>, Option<Arc<Vec<u8>>>)> = vec![];
+    let size = layout.size;
+    shift_glyphs(&mut layout.glyphs, |glyph| {
+        let font = &glyph.font.font;
+        let position = font_data.iter().position(|(data_font, _)| Arc::ptr_eq(data_font, font));
+        let index = match position {
+            Some(index) => index,
+            None => {
+                font_data.push((font.clone(), font.copy_font_data()));
+                font_data.len() - 1
+            }
+        };
+        let data = font_data[index].1.as_ref()?;
+        let varied_advance = VariedFace::new(data, variations).and_then(|face| {
+            face.advance(glyph.glyph_id)
+        })?;
+        let default_advance = font.advance(glyph.glyph_id).map(|advance| advance.x());
+        let scale_factor = size / font.metrics().units_per_em as f32;
+        Some((varied_advance - default_advance.unwrap_or(varied_advance)) * scale_factor)
+    });
+}
+
+// Shifts each glyph right by the sum of the advance differences, in pixels, of the glyphs before
+// it. Glyphs whose difference is unknown don't move the ones after them.
+#[cfg(not(feature = "shaping"))]
+fn shift_glyphs<F>(glyphs: &mut [skribo::Glyph], mut advance_difference: F)
+                   where F: FnMut(&skribo::Glyph) -> Option<f32> {
+    let mut shift = 0.0;
+    for glyph in glyphs {
+        glyph.offset = glyph.offset + vec2f(shift, 0.0);
+        shift += advance_difference(glyph).unwrap_or(0.0);
+    }
+}
+
+struct OutlineSinkBuilder<'a, S> where S: OutlineSink {
+    sink: &'a mut S,
+}
+
+impl<'a, S> OutlineBuilder for OutlineSinkBuilder<'a, S> where S: OutlineSink {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.sink.move_to(vec2f(x, y));
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        self.sink.line_to(vec2f(x, y));
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        self.sink.quadratic_curve_to(vec2f(x1, y1), vec2f(x, y));
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        self.sink.cubic_curve_to(LineSegment2F::new(vec2f(x1, y1), vec2f(x2, y2)), vec2f(x, y));
+    }
+
+    fn close(&mut self) {
+        self.sink.close();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use font_kit::loader::Loader;
+    use font_kit::loaders::default::Font as DefaultLoader;
+    use font_kit::outline::OutlineSink;
+    use pathfinder_geometry::line_segment::LineSegment2F;
+    use pathfinder_geometry::vector::Vector2F;
+    use std::sync::Arc;
+
+    use super::{FontVariation, VariedFace, glyph_advance};
+
+    static ROBOTO: &[u8] = include_bytes!("../../resources/fonts/Roboto-Regular.ttf");
+
+    fn roboto() -> DefaultLoader {
+        DefaultLoader::from_bytes(Arc::new(ROBOTO.to_vec()), 0).unwrap()
+    }
+
+    #[test]
+    fn advances_without_variations_come_from_the_loader() {
+        let font = roboto();
+        let glyph_id = font.glyph_for_char('W').unwrap();
+        assert_eq!(glyph_advance(&font, glyph_id, &[]), font.advance(glyph_id).unwrap());
+    }
+
+    #[test]
+    fn axes_missing_from_the_font_are_ignored() {
+        let font = roboto();
+        let glyph_id = font.glyph_for_char('W').unwrap();
+        let variations = [FontVariation::new(b"wght", 900.0), FontVariation::new(b"wdth", 50.0)];
+        let advance = glyph_advance(&font, glyph_id, &variations);
+        assert!((advance - font.advance(glyph_id).unwrap()).length() < 1.0);
+
+        let face = VariedFace::new(ROBOTO, &variations).unwrap();
+        assert_eq!(face.advance(glyph_id), Some(advance.x()));
+        let mut outline = vec![];
+        assert!(face.outline(glyph_id, &mut RecordingSink(&mut outline)));
+        assert!(!outline.is_empty());
+    }
+
+    #[test]
+    fn unparseable_fonts_have_no_varied_face() {
+        assert!(VariedFace::new(&ROBOTO[0..64], &[]).is_none());
+    }
+
+    #[cfg(not(feature = "shaping"))]
+    #[test]
+    fn glyphs_are_shifted_by_the_advance_differences_before_them() {
+        use pathfinder_geometry::vector::vec2f;
+        use skribo::{FontRef, Glyph};
+
+        let font = FontRef::new(roboto());
+        let mut glyphs: Vec<Glyph> = (0..4).map(|index| {
+            Glyph { font: font.clone(), glyph_id: 1, offset: vec2f(index as f32 * 10.0, 3.0) }
+        }).collect();
+        let mut differences = vec![Some(2.0), None, Some(-5.0), Some(100.0)].into_iter();
+        super::shift_glyphs(&mut glyphs, |_| differences.next().unwrap());
+        let offsets: Vec<_> = glyphs.iter().map(|glyph| glyph.offset).collect();
+        assert_eq!(offsets,
+                   vec![vec2f(0.0, 3.0), vec2f(12.0, 3.0), vec2f(22.0, 3.0), vec2f(27.0, 3.0)]);
+    }
+
+    // Records the points of an outline.
+    struct RecordingSink<'a>(&'a mut Vec<Vector2F>);
+
+    impl<'a> OutlineSink for RecordingSink<'a> {
+        fn move_to(&mut self, to: Vector2F) {
+            self.0.push(to);
+        }
+        fn line_to(&mut self, to: Vector2F) {
+            self.0.push(to);
+        }
+        fn quadratic_curve_to(&mut self, _: Vector2F, to: Vector2F) {
+            self.0.push(to);
+        }
+        fn cubic_curve_to(&mut self, _: LineSegment2F, to: Vector2F) {
+            self.0.push(to);
+        }
+        fn close(&mut self) {}
+    }
+}
@@ -0,0 +1,104 @@
+// pathfinder/utils/flattening-bench/src/main.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Counts the line segments that the curves in SVG files are flattened into, comparing the
+//! tiler's quadratic-based flattening with recursive subdivision of cubics.
+//!
+//! Usage: `flattening-bench [SVG files...]`. With no arguments, every file in `resources/svg` is
+//! measured.
+
+use pathfinder_content::outline::ContourIterFlags;
+use pathfinder_content::segment::Segment;
+use pathfinder_renderer::scene::{DrawPathId, Scene};
+use pathfinder_svg::SVGScene;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use usvg::{Options, Tree};
+
+const TOLERANCES: [f32; 3] = [1.0, 0.25, 0.1];
+
+fn main() {
+    let mut paths: Vec<PathBuf> = env::args_os().skip(1).map(PathBuf::from).collect();
+    if paths.is_empty() {
+        let svg_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../../resources/svg");
+        for entry in fs::read_dir(svg_dir).expect("Failed to read `resources/svg`!") {
+            let path = entry.unwrap().path();
+            if path.extension().and_then(|extension| extension.to_str()) == Some("svg") {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+    }
+
+    println!("{:<36} {:>9} {:>10} {:>10} {:>7} {:>10} {:>10}",
+             "file", "tolerance", "subdivide", "parabola", "ratio", "sub. ms", "para. ms");
+    for path in &paths {
+        let data = fs::read(path).expect("Failed to read SVG file!");
+        let tree = Tree::from_data(&data, &Options::default()).expect("Failed to parse SVG!");
+        let scene = SVGScene::from_tree(&tree).scene;
+        let segments = curve_segments(&scene);
+
+        let name = path.file_name().unwrap().to_string_lossy();
+        for &tolerance in &TOLERANCES {
+            let (subdivided_count, subdivided_time) =
+                measure(&segments, |segment| subdivide(segment, tolerance));
+            let (flattened_count, flattened_time) =
+                measure(&segments, |segment| flatten(segment, tolerance));
+            println!("{:<36} {:>9} {:>10} {:>10} {:>7.3} {:>10.3} {:>10.3}",
+                     name,
+                     tolerance,
+                     subdivided_count,
+                     flattened_count,
+                     flattened_count as f64 / subdivided_count as f64,
+                     subdivided_time.as_secs_f64() * 1000.0,
+                     flattened_time.as_secs_f64() * 1000.0);
+        }
+    }
+}
+
+// Returns the quadratic and cubic curves in the scene's draw paths.
+fn curve_segments(scene: &Scene) -> Vec<Segment> {
+    let mut segments = vec![];
+    for draw_path_index in 0..scene.draw_path_count() {
+        let outline = scene.get_draw_path(DrawPathId(draw_path_index)).outline();
+        for contour in outline.contours() {
+            segments.extend(contour.iter(ContourIterFlags::empty()).filter(|segment| {
+                segment.is_quadratic() || segment.is_cubic()
+            }));
+        }
+    }
+    segments
+}
+
+fn measure<F>(segments: &[Segment], count_lines: F) -> (usize, Duration)
+              where F: FnMut(&Segment) -> usize {
+    let start_time = Instant::now();
+    let line_count = segments.iter().map(count_lines).sum();
+    (line_count, Instant::now() - start_time)
+}
+
+// Degree elevates quadratics and splits curves in half until they're flat.
+fn subdivide(segment: &Segment, tolerance: f32) -> usize {
+    let cubic = segment.to_cubic();
+    if cubic.as_cubic_segment().is_flat(tolerance) {
+        return 1;
+    }
+    let (prev, next) = cubic.split(0.5);
+    subdivide(&prev, tolerance) + subdivide(&next, tolerance)
+}
+
+// Flattens curves the way the tiler does.
+fn flatten(segment: &Segment, tolerance: f32) -> usize {
+    let mut line_count = 0;
+    segment.flatten(tolerance, |_| line_count += 1);
+    line_count
+}
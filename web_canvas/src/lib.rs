@@ -11,8 +11,8 @@
 use css_color_parser::Color;
 use pathfinder_canvas::{Canvas, CanvasFontContext, CanvasRenderingContext2D, FillRule, FillStyle};
 use pathfinder_canvas::{LineCap, Path2D};
-use pathfinder_color::ColorU;
-use pathfinder_geometry::rect::RectF;
+use pathfinder_color::{ColorU, color_slice_to_u8_slice};
+use pathfinder_geometry::rect::{RectF, RectI};
 use pathfinder_geometry::transform2d::Transform2F;
 use pathfinder_geometry::vector::{vec2f, vec2i};
 use pathfinder_renderer::concurrent::executor::SequentialExecutor;
@@ -23,9 +23,9 @@ use pathfinder_resources::embedded::EmbeddedResourceLoader;
 use pathfinder_webgl::WebGlDevice;
 use std::str::FromStr;
 use std::sync::Arc;
-use wasm_bindgen::JsCast;
+use wasm_bindgen::{Clamped, JsCast};
 use wasm_bindgen::prelude::*;
-use web_sys::{self, HtmlCanvasElement, WebGl2RenderingContext};
+use web_sys::{self, HtmlCanvasElement, ImageData, WebGl2RenderingContext};
 
 #[wasm_bindgen]
 pub struct PFCanvasRenderingContext2D {
@@ -131,6 +131,22 @@ impl PFCanvasRenderingContext2D {
 
     // TODO(pcwalton): Drawing text
 
+    // Pixel manipulation
+
+    #[wasm_bindgen(js_name = "getImageData")]
+    pub fn get_image_data(&mut self, sx: i32, sy: i32, sw: i32, sh: i32)
+                          -> Result<ImageData, JsValue> {
+        let rect = RectI::from_points(vec2i(sx, sy),
+                                      vec2i(sx.saturating_add(sw), sy.saturating_add(sh)));
+        let image_data = self.context
+                             .get_image_data(&mut self.renderer, rect)
+                             .map_err(|error| JsValue::from_str(&error.to_string()))?;
+        let mut pixels = color_slice_to_u8_slice(&image_data.data).to_vec();
+        ImageData::new_with_u8_clamped_array_and_sh(Clamped(&mut pixels),
+                                                    image_data.size.x() as u32,
+                                                    image_data.size.y() as u32)
+    }
+
     // Line styles
 
     #[wasm_bindgen(js_name = "lineWidth")]